@@ -0,0 +1,38 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use full_moon::ast::Ast;
+
+const DATE_SOURCE: &str = include_str!("./date.lua");
+
+fn parse(criterion: &mut Criterion) {
+	criterion.bench_function("parse date", |b| {
+		b.iter(|| full_moon::parse(black_box(DATE_SOURCE)).unwrap())
+	});
+}
+
+fn round_trip(criterion: &mut Criterion) {
+	let ast = full_moon::parse(DATE_SOURCE).unwrap();
+
+	criterion.bench_function("encode and decode ast of date", move |b| {
+		b.iter(|| {
+			let bytes = black_box(&ast).to_bytes();
+			let count = Ast::from_bytes(&bytes).unwrap().nodes().iter_stmts().count();
+			count
+		})
+	});
+}
+
+fn decode(criterion: &mut Criterion) {
+	let bytes = full_moon::parse(DATE_SOURCE).unwrap().to_bytes();
+
+	criterion.bench_function("decode ast of date", move |b| {
+		b.iter(|| Ast::from_bytes(black_box(&bytes)).unwrap())
+	});
+}
+
+criterion_group! {
+	name = benches;
+	config = Criterion::default().sample_size(20);
+	targets = parse, round_trip, decode
+}
+
+criterion_main!(benches);
@@ -0,0 +1,48 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// Small files like the ones a linter goes through, which are different so none of them are cached
+fn files() -> Vec<String> {
+    (0..1000)
+        .map(|index| {
+            format!(
+                "local value{0} = {{ name = \"file {0}\", count = {0} }}\n\
+                 local function get{0}(key)\n    return value{0}[key] or {0} * 2\nend\n\n\
+                 return get{0}\n",
+                index
+            )
+        })
+        .collect()
+}
+
+fn parse(criterion: &mut Criterion) {
+    let files = files();
+
+    criterion.bench_function("parse many small files", move |b| {
+        b.iter(|| {
+            for file in &files {
+                black_box(full_moon::parse(black_box(file)).unwrap());
+            }
+        })
+    });
+}
+
+fn parser(criterion: &mut Criterion) {
+    let files = files();
+    let mut parser = full_moon::Parser::new();
+
+    criterion.bench_function("parse many small files with one parser", move |b| {
+        b.iter(|| {
+            for file in &files {
+                black_box(parser.parse(black_box(file)).unwrap());
+            }
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = parse, parser
+}
+
+criterion_main!(benches);
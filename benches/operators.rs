@@ -0,0 +1,32 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// One expression with 10,000 binary operators, like generated code with long concatenations and arithmetic
+fn expression() -> String {
+    let mut code = String::from("local x = a");
+    for index in 0..10_000 {
+        code.push_str(match index % 4 {
+            0 => " .. b",
+            1 => " + c * 2",
+            2 => " ^ -d",
+            _ => " .. (e)",
+        });
+    }
+
+    code
+}
+
+fn parse(criterion: &mut Criterion) {
+    let code = expression();
+
+    criterion.bench_function("parse 10,000 operators", move |b| {
+        b.iter(|| full_moon::parse(black_box(&code)).is_ok())
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = parse
+}
+
+criterion_main!(benches);
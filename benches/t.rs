@@ -1,5 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use full_moon::node::Node;
+use full_moon::{ast::reparse::SourceEdit, node::Node};
 
 const T_SOURCE: &str = include_str!("./t.lua");
 
@@ -17,6 +17,20 @@ fn parse(criterion: &mut Criterion) {
     });
 }
 
+// Inserts a space in a statement in the middle of t, then takes it out again, each of which reparses the statement
+fn reparse(criterion: &mut Criterion) {
+    let mut ast = full_moon::parse(T_SOURCE).unwrap();
+    let offset = T_SOURCE[T_SOURCE.len() / 2..].find(" = ").unwrap() + T_SOURCE.len() / 2;
+
+    criterion.bench_function("reparse an edit of t", move |b| {
+        b.iter(|| {
+            ast.reparse(SourceEdit::new(offset..offset, " ")).unwrap();
+            ast.reparse(SourceEdit::new(offset..offset + 1, ""))
+                .unwrap()
+        })
+    });
+}
+
 fn range(criterion: &mut Criterion) {
     let ast = full_moon::parse(T_SOURCE).unwrap();
 
@@ -28,7 +42,7 @@ fn range(criterion: &mut Criterion) {
 criterion_group! {
     name = benches;
     config = Criterion::default().sample_size(20);
-    targets = tokenize, parse, range
+    targets = tokenize, parse, reparse, range
 }
 
 criterion_main!(benches);
@@ -0,0 +1,25 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const T_SOURCE: &str = include_str!("./t.lua");
+
+// Run with and without `--features tracing` to compare. Without the feature, nothing is traced at all,
+// and with it but no subscriber, every span and event is skipped after checking that nothing is listening.
+fn parse(criterion: &mut Criterion) {
+    let name = if cfg!(feature = "tracing") {
+        "parse t.lua with tracing compiled in"
+    } else {
+        "parse t.lua without tracing"
+    };
+
+    criterion.bench_function(name, move |b| {
+        b.iter(|| full_moon::parse(black_box(T_SOURCE)).is_ok())
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = parse
+}
+
+criterion_main!(benches);
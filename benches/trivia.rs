@@ -0,0 +1,80 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use full_moon::ParseConfig;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+const T_SOURCE: &str = include_str!("./t.lua");
+const DATE_SOURCE: &str = include_str!("./date.lua");
+
+// Counts the bytes that are allocated, for how much memory an Ast takes
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(pointer, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn config(preserve_trivia: bool) -> ParseConfig {
+    ParseConfig {
+        preserve_trivia,
+        ..ParseConfig::default()
+    }
+}
+
+// Prints how many bytes the Ast of the code takes, with and without trivia, since criterion only measures time
+fn print_memory(name: &str, code: &str) {
+    for preserve_trivia in &[true, false] {
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let ast = full_moon::parse_with(code, &config(*preserve_trivia)).unwrap();
+        let bytes = ALLOCATED.load(Ordering::Relaxed) - before;
+        println!(
+            "{} with preserve_trivia {}: {} tokens, {} bytes",
+            name,
+            preserve_trivia,
+            ast.iter_tokens().count(),
+            bytes
+        );
+    }
+}
+
+fn bench(criterion: &mut Criterion, name: &str, code: &'static str) {
+    print_memory(name, code);
+
+    criterion.bench_function(&format!("parse {}", name), move |b| {
+        b.iter(|| full_moon::parse_with(black_box(code), &config(true)))
+    });
+
+    criterion.bench_function(&format!("parse {} without trivia", name), move |b| {
+        b.iter(|| full_moon::parse_with(black_box(code), &config(false)))
+    });
+}
+
+fn t(criterion: &mut Criterion) {
+    bench(criterion, "t", T_SOURCE);
+}
+
+fn date(criterion: &mut Criterion) {
+    bench(criterion, "date", DATE_SOURCE);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = t, date
+}
+
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+// Prints the errors in a Lua file as colored diagnostics, run with:
+// cargo run --example diagnostics --features diagnostics -- file.lua
+use full_moon::diagnostics::{
+    self,
+    codespan_reporting::{
+        files::SimpleFile,
+        term::{
+            self,
+            termcolor::{ColorChoice, StandardStream},
+        },
+    },
+};
+use std::{env, fs, process};
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: diagnostics <file.lua>");
+            process::exit(2);
+        }
+    };
+
+    let code = fs::read_to_string(&path).expect("couldn't read the file");
+    let files = SimpleFile::new(&path, &code);
+    let writer = StandardStream::stderr(ColorChoice::Auto);
+    let config = term::Config::default();
+
+    let (_, errors) = full_moon::parse_all_errors(&code, 10);
+    for error in &errors {
+        term::emit(
+            &mut writer.lock(),
+            &config,
+            &files,
+            &diagnostics::diagnostic(error, ()),
+        )
+        .expect("couldn't print the diagnostic");
+    }
+
+    if !errors.is_empty() {
+        process::exit(1);
+    }
+}
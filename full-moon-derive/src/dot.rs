@@ -0,0 +1,104 @@
+use crate::{derive::*, node::is_id};
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+pub struct DotGenerator;
+
+impl DeriveGenerator for DotGenerator {
+    fn complete(input: &syn::DeriveInput, tokens: TokenStream) -> TokenStream {
+        let lifetime = input
+            .generics
+            .lifetimes()
+            .next()
+            .expect("must derive something with a lifetime");
+
+        let input_ident = &input.ident;
+        let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+        quote! {
+            impl #impl_generics crate::debug::ToDot<#lifetime> for #input_ident #ty_generics #where_clause {
+                fn add_to(
+                    &self,
+                    graph: &crate::debug::DotBuilder<#lifetime, '_>,
+                    label: &str,
+                    edges: &mut Vec<(String, crate::debug::DotNode)>,
+                ) {
+                    #tokens
+                }
+            }
+        }
+    }
+}
+
+impl StructGenerator for DotGenerator {
+    fn generate(input: &syn::Ident, strukt: &syn::DataStruct) -> TokenStream {
+        let kind = input.to_string();
+        let fields: Vec<_> = strukt
+            .fields
+            .iter()
+            .filter(|field| !is_id(field))
+            .map(|field| field.ident.as_ref().unwrap())
+            .collect();
+        let names = fields.iter().map(|field| field.to_string());
+
+        quote! {
+            let mut children = Vec::new();
+            #(crate::debug::ToDot::add_to(&self.#fields, graph, #names, &mut children);)*
+            edges.push((label.to_owned(), crate::debug::DotNode::node(#kind.to_owned(), children)));
+        }
+    }
+}
+
+impl MatchEnumGenerator for DotGenerator {
+    fn case_named(
+        input: &syn::Ident,
+        variant: &syn::Ident,
+        named: &syn::FieldsNamed,
+    ) -> TokenStream {
+        let kind = format!("{}::{}", input, variant);
+        let fields: Vec<_> = named
+            .named
+            .iter()
+            .filter(|field| !is_id(field))
+            .map(|field| field.ident.as_ref().unwrap())
+            .collect();
+        let names = fields.iter().map(|field| field.to_string());
+
+        quote! {
+            #input::#variant {
+                #(#fields,)* ..
+            } => {
+                let mut children = Vec::new();
+                #(crate::debug::ToDot::add_to(#fields, graph, #names, &mut children);)*
+                edges.push((label.to_owned(), crate::debug::DotNode::node(#kind.to_owned(), children)));
+            }
+        }
+    }
+
+    fn case_unnamed(
+        input: &syn::Ident,
+        variant: &syn::Ident,
+        fields: &syn::FieldsUnnamed,
+    ) -> TokenStream {
+        let kind = format!("{}::{}", input, variant);
+        let fields: Vec<_> = fields
+            .unnamed
+            .iter()
+            .enumerate()
+            .map(|(index, _)| format_ident!("__self_{}", index))
+            .collect();
+        let names = (0..fields.len()).map(|index| index.to_string());
+        let fields = &fields;
+
+        quote! {
+            #input::#variant(
+                #(#fields,)*
+            ) => {
+                let mut children = Vec::new();
+                #(crate::debug::ToDot::add_to(#fields, graph, #names, &mut children);)*
+                edges.push((label.to_owned(), crate::debug::DotNode::node(#kind.to_owned(), children)));
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::derive::*;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+pub struct HeapSizeGenerator;
+
+impl DeriveGenerator for HeapSizeGenerator {
+    fn complete(input: &syn::DeriveInput, tokens: TokenStream) -> TokenStream {
+        let input_ident = &input.ident;
+        let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+        let shrink = match &input.data {
+            syn::Data::Enum(data) => {
+                <ShrinkGenerator as EnumGenerator>::generate(input_ident, data)
+            }
+            syn::Data::Struct(data) => {
+                <ShrinkGenerator as StructGenerator>::generate(input_ident, data)
+            }
+            _ => unimplemented!(),
+        };
+
+        quote! {
+            impl #impl_generics crate::ast::memory::HeapSize for #input_ident #ty_generics #where_clause {
+                fn add_heap_size(&self, counter: &mut crate::ast::memory::HeapCounter) {
+                    counter.add_node();
+                    #tokens
+                }
+
+                fn shrink_to_fit(&mut self) {
+                    #shrink
+                }
+            }
+        }
+    }
+}
+
+// The names of the fields, or what they're bound to in a match when they have none
+fn field_idents<'a>(fields: impl IntoIterator<Item = &'a syn::Field>) -> Vec<syn::Ident> {
+    fields
+        .into_iter()
+        .enumerate()
+        .map(|(index, field)| match &field.ident {
+            Some(ident) => ident.clone(),
+            None => format_ident!("__self_{}", index),
+        })
+        .collect()
+}
+
+impl StructGenerator for HeapSizeGenerator {
+    fn generate(_input: &syn::Ident, strukt: &syn::DataStruct) -> TokenStream {
+        let fields: Vec<_> = strukt
+            .fields
+            .iter()
+            .map(|field| field.ident.as_ref().unwrap())
+            .collect();
+
+        quote! {
+            #(crate::ast::memory::HeapSize::add_heap_size(&self.#fields, counter);)*
+        }
+    }
+}
+
+impl MatchEnumGenerator for HeapSizeGenerator {
+    fn case_named(
+        input: &syn::Ident,
+        variant: &syn::Ident,
+        named: &syn::FieldsNamed,
+    ) -> TokenStream {
+        let fields = field_idents(&named.named);
+
+        quote! {
+            #input::#variant {
+                #(#fields,)*
+            } => {
+                #(crate::ast::memory::HeapSize::add_heap_size(#fields, counter);)*
+            }
+        }
+    }
+
+    fn case_unnamed(
+        input: &syn::Ident,
+        variant: &syn::Ident,
+        unnamed: &syn::FieldsUnnamed,
+    ) -> TokenStream {
+        let fields = field_idents(&unnamed.unnamed);
+
+        quote! {
+            #input::#variant(
+                #(#fields,)*
+            ) => {
+                #(crate::ast::memory::HeapSize::add_heap_size(#fields, counter);)*
+            }
+        }
+    }
+
+    fn case_unit(input: &syn::Ident, variant: &syn::Ident) -> TokenStream {
+        quote! {
+            #input::#variant => {}
+        }
+    }
+}
+
+// Generates the body of `shrink_to_fit`, which goes through the same fields mutably
+struct ShrinkGenerator;
+
+impl StructGenerator for ShrinkGenerator {
+    fn generate(_input: &syn::Ident, strukt: &syn::DataStruct) -> TokenStream {
+        let fields: Vec<_> = strukt
+            .fields
+            .iter()
+            .map(|field| field.ident.as_ref().unwrap())
+            .collect();
+
+        quote! {
+            #(crate::ast::memory::HeapSize::shrink_to_fit(&mut self.#fields);)*
+        }
+    }
+}
+
+impl MatchEnumGenerator for ShrinkGenerator {
+    fn case_named(
+        input: &syn::Ident,
+        variant: &syn::Ident,
+        named: &syn::FieldsNamed,
+    ) -> TokenStream {
+        let fields = field_idents(&named.named);
+
+        quote! {
+            #input::#variant {
+                #(#fields,)*
+            } => {
+                #(crate::ast::memory::HeapSize::shrink_to_fit(#fields);)*
+            }
+        }
+    }
+
+    fn case_unnamed(
+        input: &syn::Ident,
+        variant: &syn::Ident,
+        unnamed: &syn::FieldsUnnamed,
+    ) -> TokenStream {
+        let fields = field_idents(&unnamed.unnamed);
+
+        quote! {
+            #input::#variant(
+                #(#fields,)*
+            ) => {
+                #(crate::ast::memory::HeapSize::shrink_to_fit(#fields);)*
+            }
+        }
+    }
+
+    fn case_unit(input: &syn::Ident, variant: &syn::Ident) -> TokenStream {
+        quote! {
+            #input::#variant => {}
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{derive::*, node::is_id};
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+pub struct JsonGenerator;
+
+impl DeriveGenerator for JsonGenerator {
+    fn complete(input: &syn::DeriveInput, tokens: TokenStream) -> TokenStream {
+        let lifetime = input
+            .generics
+            .lifetimes()
+            .next()
+            .expect("must derive something with a lifetime");
+
+        let input_ident = &input.ident;
+        let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+        quote! {
+            #[cfg(feature = "json")]
+            impl #impl_generics crate::json::ToJson<#lifetime> for #input_ident #ty_generics #where_clause {
+                fn to_json(&self, json: &crate::json::JsonWriter<#lifetime, '_>) -> serde_json::Value {
+                    #tokens
+                }
+            }
+        }
+    }
+}
+
+impl StructGenerator for JsonGenerator {
+    fn generate(input: &syn::Ident, strukt: &syn::DataStruct) -> TokenStream {
+        let kind = input.to_string();
+        let fields: Vec<_> = strukt
+            .fields
+            .iter()
+            .filter(|field| !is_id(field))
+            .map(|field| field.ident.as_ref().unwrap())
+            .collect();
+        let names = fields.iter().map(|field| field.to_string());
+
+        quote! {
+            json.node(
+                #kind,
+                None,
+                crate::node::Node::range(self),
+                crate::json::Children::Named(vec![
+                    #((#names, crate::json::ToJson::to_json(&self.#fields, json)),)*
+                ]),
+            )
+        }
+    }
+}
+
+impl MatchEnumGenerator for JsonGenerator {
+    fn case_named(
+        input: &syn::Ident,
+        variant: &syn::Ident,
+        named: &syn::FieldsNamed,
+    ) -> TokenStream {
+        let kind = input.to_string();
+        let variant_name = variant.to_string();
+        let fields: Vec<_> = named
+            .named
+            .iter()
+            .filter(|field| !is_id(field))
+            .map(|field| field.ident.as_ref().unwrap())
+            .collect();
+        let names = fields.iter().map(|field| field.to_string());
+
+        quote! {
+            #input::#variant {
+                #(#fields,)* ..
+            } => json.node(
+                #kind,
+                Some(#variant_name),
+                crate::node::Node::range(self),
+                crate::json::Children::Named(vec![
+                    #((#names, crate::json::ToJson::to_json(#fields, json)),)*
+                ]),
+            ),
+        }
+    }
+
+    fn case_unnamed(
+        input: &syn::Ident,
+        variant: &syn::Ident,
+        fields: &syn::FieldsUnnamed,
+    ) -> TokenStream {
+        let kind = input.to_string();
+        let variant_name = variant.to_string();
+        let fields: Vec<_> = fields
+            .unnamed
+            .iter()
+            .enumerate()
+            .map(|(index, _)| format_ident!("__self_{}", index))
+            .collect();
+        let fields = &fields;
+
+        quote! {
+            #input::#variant(
+                #(#fields,)*
+            ) => json.node(
+                #kind,
+                Some(#variant_name),
+                crate::node::Node::range(self),
+                crate::json::Children::Unnamed(vec![
+                    #(crate::json::ToJson::to_json(#fields, json),)*
+                ]),
+            ),
+        }
+    }
+}
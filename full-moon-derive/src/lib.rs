@@ -3,6 +3,10 @@
 extern crate proc_macro;
 
 mod derive;
+mod dot;
+mod heap_size;
+mod json;
+mod lua_quote;
 mod node;
 mod owned;
 mod visit;
@@ -16,6 +20,21 @@ pub fn derive_visit(input: TokenStream) -> TokenStream {
     visit::VisitGenerator::derive(input)
 }
 
+#[proc_macro_derive(Dot, attributes(node))]
+pub fn derive_dot(input: TokenStream) -> TokenStream {
+    dot::DotGenerator::derive(input)
+}
+
+#[proc_macro_derive(HeapSize)]
+pub fn derive_heap_size(input: TokenStream) -> TokenStream {
+    heap_size::HeapSizeGenerator::derive(input)
+}
+
+#[proc_macro_derive(Json, attributes(node))]
+pub fn derive_json(input: TokenStream) -> TokenStream {
+    json::JsonGenerator::derive(input)
+}
+
 #[proc_macro_derive(Node, attributes(node))]
 pub fn derive_node(input: TokenStream) -> TokenStream {
     node::NodeGenerator::derive(input)
@@ -25,3 +44,9 @@ pub fn derive_node(input: TokenStream) -> TokenStream {
 pub fn derive_owned(input: TokenStream) -> TokenStream {
     owned::OwnedGenerator::derive(input)
 }
+
+/// Builds Lua nodes from Lua code, see `full_moon::lua_quote!`
+#[proc_macro]
+pub fn lua_quote(input: TokenStream) -> TokenStream {
+    lua_quote::lua_quote(input)
+}
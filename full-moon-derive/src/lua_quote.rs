@@ -0,0 +1,241 @@
+use proc_macro2::{Delimiter, Ident, Spacing, Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned};
+
+// Lua's operators, longest first, and `##`, which is written as a `#` since `#name` interpolates
+const OPERATORS: &[&str] = &[
+    "...", "..", "==", "~=", "<=", ">=", "##", "+", "-", "*", "/", "%", "^", "=", "<", ">", ";",
+    ":", ",", ".", "~",
+];
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local",
+    "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+// What was written last, for deciding whether there's a space before the next token
+#[derive(Clone, Copy, PartialEq)]
+enum Previous {
+    Start,
+    Open(Delimiter),
+    // A closing bracket, or anything else that can be called or indexed, such as a name that isn't a keyword
+    Value,
+    Keyword(&'static str),
+    // An operator that is written right before what it applies to, such as a unary minus or a `.`
+    Prefix,
+    Operator,
+}
+
+// The Lua code in the macro, split into the pieces between the interpolations
+struct Writer {
+    pieces: Vec<String>,
+    code: String,
+    interpolations: Vec<Ident>,
+    previous: Previous,
+}
+
+impl Writer {
+    fn space(&self) -> bool {
+        !matches!(
+            self.previous,
+            Previous::Start
+                | Previous::Open(Delimiter::Parenthesis)
+                | Previous::Open(Delimiter::Bracket)
+                | Previous::Prefix
+        )
+    }
+
+    fn push(&mut self, text: &str, space: bool, previous: Previous) {
+        if space {
+            self.code.push(' ');
+        }
+        self.code.push_str(text);
+        self.previous = previous;
+    }
+
+    fn word(&mut self, text: &str) {
+        let previous = match KEYWORDS.iter().find(|keyword| **keyword == text) {
+            Some(keyword) => Previous::Keyword(keyword),
+            None => Previous::Value,
+        };
+
+        self.push(text, self.space(), previous);
+    }
+
+    fn interpolation(&mut self, ident: Ident) {
+        if self.space() {
+            self.code.push(' ');
+        }
+
+        self.pieces.push(std::mem::take(&mut self.code));
+        self.interpolations.push(ident);
+        self.previous = Previous::Value;
+    }
+
+    fn operator(&mut self, operator: &str) {
+        let (text, space, previous) = match operator {
+            "##" => ("#", self.space(), Previous::Prefix),
+            "," | ";" => (operator, false, Previous::Operator),
+            "." | ":" => (operator, false, Previous::Prefix),
+            // A minus is unary when there's nothing before it to subtract from
+            "-" if self.previous != Previous::Value => ("-", self.space(), Previous::Prefix),
+            _ => (operator, self.space(), Previous::Operator),
+        };
+
+        self.push(text, space, previous);
+    }
+
+    fn open(&mut self, delimiter: Delimiter) {
+        let text = match delimiter {
+            Delimiter::Parenthesis => "(",
+            Delimiter::Bracket => "[",
+            Delimiter::Brace => "{",
+            Delimiter::None => return,
+        };
+
+        // Calls and indexes are written right after what they call or index, as are parameters
+        let space = match self.previous {
+            Previous::Value | Previous::Keyword("function") => delimiter == Delimiter::Brace,
+            _ => self.space(),
+        };
+
+        self.push(text, space, Previous::Open(delimiter));
+    }
+
+    fn close(&mut self, delimiter: Delimiter) {
+        let (text, space) = match delimiter {
+            Delimiter::Parenthesis => (")", false),
+            Delimiter::Bracket => ("]", false),
+            Delimiter::Brace => ("}", self.previous != Previous::Open(Delimiter::Brace)),
+            Delimiter::None => return,
+        };
+
+        self.push(text, space, Previous::Value);
+    }
+
+    fn write(&mut self, stream: TokenStream) -> syn::Result<()> {
+        let mut tokens = stream.into_iter().peekable();
+
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Group(group) => {
+                    self.open(group.delimiter());
+                    self.write(group.stream())?;
+                    self.close(group.delimiter());
+                }
+
+                TokenTree::Ident(ident) => {
+                    let name = ident.to_string();
+                    self.word(name.strip_prefix("r#").unwrap_or(&name));
+                }
+
+                TokenTree::Literal(literal) => {
+                    let text = literal.to_string();
+                    if text.starts_with('b') || text.starts_with('r') {
+                        return Err(syn::Error::new(
+                            literal.span(),
+                            "byte strings and raw strings aren't Lua",
+                        ));
+                    }
+
+                    self.word(&text);
+                }
+
+                TokenTree::Punct(punct) => {
+                    // Puncts written next to each other, such as `~=`, are split into Lua's operators
+                    let mut characters = vec![(punct.as_char(), punct.span())];
+                    let mut spacing = punct.spacing();
+                    while spacing == Spacing::Joint {
+                        match tokens.peek() {
+                            Some(TokenTree::Punct(next)) => {
+                                characters.push((next.as_char(), next.span()));
+                                spacing = next.spacing();
+                                tokens.next();
+                            }
+                            _ => break,
+                        }
+                    }
+
+                    let text: String = characters.iter().map(|(character, _)| character).collect();
+                    let mut offset = 0;
+                    while offset < characters.len() {
+                        let rest = &text[offset..];
+                        let span = characters[offset].1;
+
+                        if rest == "#" {
+                            match tokens.next() {
+                                Some(TokenTree::Ident(ident)) => {
+                                    self.interpolation(ident);
+                                    break;
+                                }
+                                _ => return Err(not_interpolated(span)),
+                            }
+                        }
+
+                        let operator = OPERATORS
+                            .iter()
+                            .find(|operator| rest.starts_with(*operator))
+                            .ok_or_else(|| match rest.chars().next() {
+                                Some('#') => not_interpolated(span),
+                                Some('\'') => syn::Error::new(span, "lifetimes aren't Lua"),
+                                Some(character) => {
+                                    syn::Error::new(span, format!("`{}` isn't Lua", character))
+                                }
+                                None => unreachable!("the rest is never empty"),
+                            })?;
+
+                        if rest.starts_with("--") {
+                            return Err(syn::Error::new(
+                                span,
+                                "Lua comments can't be written in lua_quote!, use Rust comments instead, \
+                                 or `- -` for two minuses",
+                            ));
+                        }
+
+                        self.operator(operator);
+                        offset += operator.len();
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expand(mut self) -> TokenStream {
+        self.pieces.push(self.code);
+        let pieces = &self.pieces;
+        let interpolations = self.interpolations.iter().map(|ident| {
+            quote_spanned! {ident.span()=>
+                ::full_moon::quote::Interpolate::interpolate(&#ident)
+            }
+        });
+
+        quote! {
+            ::full_moon::quote::quote(
+                &[#(#pieces),*],
+                ::core::convert::From::from([#(#interpolations),*]),
+            )
+        }
+    }
+}
+
+fn not_interpolated(span: Span) -> syn::Error {
+    syn::Error::new(
+        span,
+        "expected a name to interpolate after `#`, or `##` for the length operator",
+    )
+}
+
+pub fn lua_quote(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let mut writer = Writer {
+        pieces: Vec::new(),
+        code: String::new(),
+        interpolations: Vec::new(),
+        previous: Previous::Start,
+    };
+
+    match writer.write(input.into()) {
+        Ok(()) => writer.expand().into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
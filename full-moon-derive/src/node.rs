@@ -3,19 +3,25 @@ use crate::derive::*;
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 enum NodeHint {
     FullRange,
+    Id,
 }
 
 impl Hint for NodeHint {
-	fn unit(name: String) -> Option<Self> {
-		if name == "full_range" {
-			Some(NodeHint::FullRange)
-		} else {
-			None
-		}
-	}
+    fn unit(name: String) -> Option<Self> {
+        match name.as_str() {
+            "full_range" => Some(NodeHint::FullRange),
+            "id" => Some(NodeHint::Id),
+            _ => None,
+        }
+    }
+}
+
+// Whether the field is the id of a struct, which isn't part of what the node is made of
+pub fn is_id(field: &syn::Field) -> bool {
+    search_hint("node", &field.attrs) == Some(NodeHint::Id)
 }
 
 pub struct NodeGenerator;
@@ -30,6 +36,7 @@ impl DeriveGenerator for NodeGenerator {
         let pattern = quote! {{
             range => $range:expr,
             similar => $similar:expr,
+            id => $id:expr,
         }};
 
         quote! {
@@ -41,6 +48,10 @@ impl DeriveGenerator for NodeGenerator {
                 ("similar", #pattern) => {
                     $similar
                 };
+
+                ("id", #pattern) => {
+                    $id
+                };
             }
 
             impl #impl_generics crate::node::Node for #input_ident #ty_generics #where_clause {
@@ -55,6 +66,10 @@ impl DeriveGenerator for NodeGenerator {
                 fn similar(&self, other: &Self) -> bool {
                     #macro_name!("similar", { #tokens })
                 }
+
+                fn id(&self) -> Option<crate::node::NodeId> {
+                    #macro_name!("id", { #tokens })
+                }
             }
 
             impl #impl_generics crate::private::Sealed for #input_ident #ty_generics #where_clause {}
@@ -66,10 +81,12 @@ impl StructGenerator for NodeGenerator {
     fn generate(ident: &syn::Ident, strukt: &syn::DataStruct) -> TokenStream {
         let range = StructRangeGenerator::generate(ident, strukt);
         let similar = StructSimilarGenerator::generate(ident, strukt);
+        let id = StructIdGenerator::generate(ident, strukt);
 
         quote! {
             range => { #range },
             similar => { #similar },
+            id => { #id },
         }
     }
 }
@@ -81,6 +98,7 @@ impl StructGenerator for StructRangeGenerator {
         let fields = strukt
             .fields
             .iter()
+            .filter(|field| !is_id(field))
             .map(|field| field.ident.as_ref().unwrap())
             .collect::<Vec<_>>();
 
@@ -130,6 +148,7 @@ impl StructGenerator for StructSimilarGenerator {
         let fields = strukt
             .fields
             .iter()
+            .filter(|field| !is_id(field))
             .map(|field| field.ident.as_ref().unwrap())
             .collect::<Vec<_>>();
 
@@ -141,10 +160,30 @@ impl StructGenerator for StructSimilarGenerator {
     }
 }
 
+pub struct StructIdGenerator;
+
+impl StructGenerator for StructIdGenerator {
+    fn generate(_: &syn::Ident, strukt: &syn::DataStruct) -> TokenStream {
+        let id = strukt
+            .fields
+            .iter()
+            .find(|field| is_id(field))
+            .expect("nodes that are structs must have an id field")
+            .ident
+            .as_ref()
+            .unwrap();
+
+        quote! {
+            Some(self.#id.0)
+        }
+    }
+}
+
 impl EnumGenerator for NodeGenerator {
     fn generate(ident: &syn::Ident, enumm: &syn::DataEnum) -> TokenStream {
         let range = EnumRangeGenerator::generate(ident, enumm);
         let similar = EnumSimilarGenerator::generate(ident, enumm);
+        let id = EnumIdGenerator::generate(ident, enumm);
 
         quote! {
             range => {
@@ -153,6 +192,11 @@ impl EnumGenerator for NodeGenerator {
             },
 
             similar => { #similar },
+
+            id => {
+                #[allow(unused)]
+                #id
+            },
         }
     }
 }
@@ -168,6 +212,7 @@ impl MatchEnumGenerator for EnumRangeGenerator {
         let fields = named
             .named
             .iter()
+            .filter(|field| !is_id(field))
             .map(|field| field.ident.as_ref().unwrap())
             .collect::<Vec<_>>();
 
@@ -210,7 +255,7 @@ impl MatchEnumGenerator for EnumRangeGenerator {
 
         quote! {
             #input::#variant {
-                #(#fields,)*
+                #(#fields,)* ..
             } => {
                 #body
             }
@@ -269,19 +314,23 @@ impl MatchEnumGenerator for EnumSimilarGenerator {
         let fields = named
             .named
             .iter()
+            .filter(|field| !is_id(field))
             .map(|field| field.ident.as_ref().unwrap())
             .collect::<Vec<_>>();
 
-        let other_fields: Vec<_> = fields.iter().map(|ident| format_ident!("other_{}", ident)).collect();
+        let other_fields: Vec<_> = fields
+            .iter()
+            .map(|ident| format_ident!("other_{}", ident))
+            .collect();
 
         quote! {
             #input::#variant {
-                #(#fields,)*
+                #(#fields,)* ..
             } => {
                 if let #input::#variant {
                     #(
                         #fields: #other_fields,
-                    )*
+                    )* ..
                 } = &other {
                     #(
                         #fields.similar(#other_fields) &&
@@ -305,7 +354,10 @@ impl MatchEnumGenerator for EnumSimilarGenerator {
             .map(|(index, _)| format_ident!("__self_{}", index))
             .collect();
 
-        let other_fields: Vec<_> = fields.iter().map(|ident| format_ident!("other_{}", ident)).collect();
+        let other_fields: Vec<_> = fields
+            .iter()
+            .map(|ident| format_ident!("other_{}", ident))
+            .collect();
 
         quote! {
             #input::#variant(
@@ -328,3 +380,51 @@ impl MatchEnumGenerator for EnumSimilarGenerator {
         }
     }
 }
+
+pub struct EnumIdGenerator;
+
+impl MatchEnumGenerator for EnumIdGenerator {
+    // Variants made of several things have an id field of their own
+    fn case_named(
+        input: &syn::Ident,
+        variant: &syn::Ident,
+        named: &syn::FieldsNamed,
+    ) -> TokenStream {
+        let id = named
+            .named
+            .iter()
+            .find(|field| is_id(field))
+            .expect("variants of nodes with named fields must have an id field")
+            .ident
+            .as_ref()
+            .unwrap();
+
+        quote! {
+            #input::#variant { #id, .. } => {
+                Some(#id.0)
+            }
+        }
+    }
+
+    // Variants that are only another node or token are replaced along with it,
+    // so their id is made from its id and the kind of node they are
+    fn case_unnamed(
+        input: &syn::Ident,
+        variant: &syn::Ident,
+        fields: &syn::FieldsUnnamed,
+    ) -> TokenStream {
+        let rest = (1..fields.unnamed.len()).map(|_| quote! { _ });
+
+        quote! {
+            #input::#variant(first, #(#rest,)*) => {
+                Some(first.id()?.wrapped(crate::visitors::NodeKind::#input))
+            }
+        }
+    }
+
+    fn case_unit(input: &syn::Ident, variant: &syn::Ident) -> TokenStream {
+        quote! {
+            #input::#variant => None
+        }
+    }
+}
@@ -23,26 +23,26 @@ fn snake_case(pascal_case: &str) -> String {
 #[derive(PartialEq)]
 enum VisitHint {
     Skip,
-	SkipVisitSelf,
+    SkipVisitSelf,
     VisitAs(String),
 }
 
 impl Hint for VisitHint {
-	fn key_value(key: String, value: String) -> Option<Self> {
-		if key == "visit_as" {
-			Some(VisitHint::VisitAs(value))
-		} else {
-			None
-		}
-	}
-
-	fn unit(name: String) -> Option<Self> {
-		match name.as_str() {
-			"skip" => Some(VisitHint::Skip),
-			"skip_visit_self" => Some(VisitHint::SkipVisitSelf),
-			_ => None,
-		}
-	}
+    fn key_value(key: String, value: String) -> Option<Self> {
+        if key == "visit_as" {
+            Some(VisitHint::VisitAs(value))
+        } else {
+            None
+        }
+    }
+
+    fn unit(name: String) -> Option<Self> {
+        match name.as_str() {
+            "skip" => Some(VisitHint::Skip),
+            "skip_visit_self" => Some(VisitHint::SkipVisitSelf),
+            _ => None,
+        }
+    }
 }
 
 pub struct VisitGenerator;
@@ -58,45 +58,42 @@ impl DeriveGenerator for VisitGenerator {
         let input_ident = &input.ident;
         let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-        let (visit_self, visit_self_end) = match search_hint("visit", &input.attrs) {
-            Some(VisitHint::SkipVisitSelf) => (quote! {}, quote! {}),
-            Some(VisitHint::VisitAs(visit_as)) => {
-                let visit_as_end =
-                    syn::Ident::new(&format!("visit_{}_end", visit_as), input_ident.span());
-                let visit_as = syn::Ident::new(&format!("visit_{}", visit_as), input_ident.span());
-
-                (
-                    quote! {
-                        visitor.#visit_as(self);
-                    },
-                    quote! {
-                        visitor.#visit_as_end(self);
-                    },
-                )
-            }
+        let visit_methods = match search_hint("visit", &input.attrs) {
+            Some(VisitHint::SkipVisitSelf) => None,
+            Some(VisitHint::VisitAs(visit_as)) => Some((
+                syn::Ident::new(&format!("visit_{}", visit_as), input_ident.span()),
+                syn::Ident::new(&format!("visit_{}_end", visit_as), input_ident.span()),
+            )),
             _ => {
                 // name of self in snake_case
-                let ssself = syn::Ident::new(
-                    &format!("visit_{}", snake_case(&input_ident.to_string())),
-                    input_ident.span(),
-                );
-
-                let ssself_end = syn::Ident::new(
-                    &format!("visit_{}_end", snake_case(&input_ident.to_string())),
-                    input_ident.span(),
-                );
-
-                (
-                    quote! {
-                        visitor.#ssself(self);
-                    },
-                    quote! {
-                        visitor.#ssself_end(self);
-                    },
-                )
+                let ssself = snake_case(&input_ident.to_string());
+
+                Some((
+                    syn::Ident::new(&format!("visit_{}", ssself), input_ident.span()),
+                    syn::Ident::new(&format!("visit_{}_end", ssself), input_ident.span()),
+                ))
             }
         };
 
+        let (visit_self, visit_self_end, try_visit_self, try_visit_self_end) = match visit_methods {
+            Some((visit, visit_end)) => (
+                quote! {
+                    visitor.#visit(self);
+                },
+                quote! {
+                    visitor.#visit_end(self);
+                },
+                quote! {
+                    visitor.#visit(self)?;
+                },
+                quote! {
+                    visitor.#visit_end(self)?;
+                },
+            ),
+
+            None => (quote! {}, quote! {}, quote! {}, quote! {}),
+        };
+
         quote! {
             impl #impl_generics crate::visitors::Visit<#lifetime> for #input_ident #ty_generics #where_clause {
                 fn visit<V: crate::visitors::Visitor<#lifetime>>(&self, visitor: &mut V) {
@@ -106,9 +103,11 @@ impl DeriveGenerator for VisitGenerator {
                         }
                     }
 
+                    visitor.visit_node(crate::visitors::AnyNode::#input_ident(self));
                     #visit_self
                     #tokens
                     #visit_self_end
+                    visitor.visit_node_end(crate::visitors::AnyNode::#input_ident(self));
                 }
             }
 
@@ -125,6 +124,56 @@ impl DeriveGenerator for VisitGenerator {
                     #visit_self_end
                 }
             }
+
+            impl #impl_generics crate::visitors::TryVisit<#lifetime> for #input_ident #ty_generics #where_clause {
+                fn try_visit<V: crate::visitors::TryVisitor<#lifetime>>(&self, visitor: &mut V) -> Result<(), V::Error> {
+                    macro_rules! visit {
+                        ($visit_what: expr, $visitor: expr) => {
+                            $visit_what.try_visit($visitor)?;
+                        }
+                    }
+
+                    visitor.visit_node(crate::visitors::AnyNode::#input_ident(self))?;
+                    #try_visit_self
+                    #tokens
+                    #try_visit_self_end
+                    visitor.visit_node_end(crate::visitors::AnyNode::#input_ident(self))
+                }
+            }
+
+            impl #impl_generics crate::visitors::VisitNodesMut<#lifetime> for #input_ident #ty_generics #where_clause {
+                fn visit_nodes_mut<'__b>(&'__b mut self, nodes: &mut Vec<crate::visitors::AnyNodeMut<#lifetime, '__b>>) {
+                    nodes.push(crate::visitors::AnyNodeMut::#input_ident(self));
+                }
+
+                #[allow(unused_variables)]
+                fn visit_children_mut<'__b>(&'__b mut self, visitor: &mut Vec<crate::visitors::AnyNodeMut<#lifetime, '__b>>) {
+                    macro_rules! visit {
+                        ($visit_what: expr, $visitor: expr) => {
+                            $visit_what.visit_nodes_mut($visitor);
+                        }
+                    }
+
+                    #tokens
+                }
+            }
+
+            impl #impl_generics crate::visitors::VisitNodes<#lifetime> for #input_ident #ty_generics #where_clause {
+                fn visit_nodes<'__b>(&'__b self, nodes: &mut Vec<crate::visitors::AnyNode<#lifetime, '__b>>) {
+                    nodes.push(crate::visitors::AnyNode::#input_ident(self));
+                }
+
+                #[allow(unused_variables)]
+                fn visit_children<'__b>(&'__b self, visitor: &mut Vec<crate::visitors::AnyNode<#lifetime, '__b>>) {
+                    macro_rules! visit {
+                        ($visit_what: expr, $visitor: expr) => {
+                            $visit_what.visit_nodes($visitor);
+                        }
+                    }
+
+                    #tokens
+                }
+            }
         }
     }
 }
@@ -135,7 +184,7 @@ impl StructGenerator for VisitGenerator {
             .fields
             .iter()
             .filter(|field| search_hint("visit", &field.attrs) != Some(VisitHint::Skip))
-			.map(|field| field.ident.as_ref());
+            .map(|field| field.ident.as_ref());
 
         quote! {
             #(visit!(self.#fields, visitor);)*
@@ -152,12 +201,13 @@ impl MatchEnumGenerator for VisitGenerator {
         let fields: Vec<_> = named
             .named
             .iter()
+            .filter(|field| search_hint("visit", &field.attrs) != Some(VisitHint::Skip))
             .map(|field| field.ident.as_ref().unwrap())
             .collect();
 
         quote! {
             #input::#variant {
-                #(#fields,)*
+                #(#fields,)* ..
             } => {
                 #(
                     visit!(#fields, visitor);
@@ -0,0 +1,75 @@
+#ifndef FULL_MOON_H
+#define FULL_MOON_H
+
+/* This file is made by cbindgen from src/ffi.rs, don't change it by hand. */
+
+#include <stddef.h>
+
+// An Ast made by [`fullmoon_parse`](fn.fullmoon_parse.html), which owns its code.
+// It is opaque to C, which only has pointers to it.
+typedef struct FullMoonAst FullMoonAst;
+
+// Text made by full_moon, which is UTF-8 and owned by full_moon until it's given to
+// [`fullmoon_free_buffer`](fn.fullmoon_free_buffer.html).
+// The data is null when there's no text, such as when there's no error.
+typedef struct FullMoonBuffer {
+  // The bytes of the text, followed by a nul byte that isn't part of the length, so they can be used as a C string
+  // when the text has no nul bytes in it
+  char *data;
+  // How many bytes of text there are, not counting the nul byte at the end
+  size_t len;
+} FullMoonBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Parses the code, which is `len` bytes of UTF-8 that don't have to end with a nul byte.
+// Returns the Ast, or null if the code couldn't be parsed, with the reason in
+// [`fullmoon_last_error`](fn.fullmoon_last_error.html). The Ast has to be freed with
+// [`fullmoon_free_ast`](fn.fullmoon_free_ast.html).
+//
+// # Safety
+// `src` has to point to at least `len` bytes that can be read, and can only be null if `len` is 0.
+FullMoonAst *fullmoon_parse(const char *src, size_t len);
+
+// The error of the last function that failed on this thread, with where it is in the code for parse errors, such as
+// `unexpected token at line 1, character 11`. The data is null if the last function called on this thread, other
+// than this one, didn't fail. The message has to be freed with [`fullmoon_free_buffer`](fn.fullmoon_free_buffer.html).
+FullMoonBuffer fullmoon_last_error(void);
+
+// The code of the Ast, which is the same as the code it was parsed from.
+// The data is null if the Ast is null, with the reason in [`fullmoon_last_error`](fn.fullmoon_last_error.html).
+// The code has to be freed with [`fullmoon_free_buffer`](fn.fullmoon_free_buffer.html).
+//
+// # Safety
+// `handle` has to be null or an Ast from [`fullmoon_parse`](fn.fullmoon_parse.html) that hasn't been freed.
+FullMoonBuffer fullmoon_print(const FullMoonAst *handle);
+
+// The Ast as JSON, the same as [`Ast::to_json`](../ast/struct.Ast.html#method.to_json) with the default
+// [`JsonConfig`](../struct.JsonConfig.html). The data is null if the Ast is null, with the reason in
+// [`fullmoon_last_error`](fn.fullmoon_last_error.html).
+// The JSON has to be freed with [`fullmoon_free_buffer`](fn.fullmoon_free_buffer.html).
+//
+// # Safety
+// `handle` has to be null or an Ast from [`fullmoon_parse`](fn.fullmoon_parse.html) that hasn't been freed.
+FullMoonBuffer fullmoon_to_json(const FullMoonAst *handle);
+
+// Frees an Ast from [`fullmoon_parse`](fn.fullmoon_parse.html). Does nothing if it is null.
+//
+// # Safety
+// `handle` has to be null or an Ast from `fullmoon_parse` that hasn't been freed, and it can't be used after this.
+void fullmoon_free_ast(FullMoonAst *handle);
+
+// Frees text from a full_moon function, such as [`fullmoon_print`](fn.fullmoon_print.html).
+// Does nothing if its data is null.
+//
+// # Safety
+// `buffer` has to be from a full_moon function, unchanged, and not freed already. Its data can't be used after this.
+void fullmoon_free_buffer(FullMoonBuffer buffer);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* FULL_MOON_H */
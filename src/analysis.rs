@@ -0,0 +1,35 @@
+//! What the code of an [`Ast`](../ast/struct.Ast.html) does, found without running it,
+//! such as which local every name refers to with [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html).
+pub mod scopes;
+
+mod call_graph;
+mod captures;
+mod directives;
+mod function_metrics;
+mod global_writes;
+mod requires;
+mod semantic_tokens;
+mod shadowing;
+mod string_literals;
+mod unreachable;
+mod unused_locals;
+
+pub use self::call_graph::{
+    call_graph, CallGraph, CallGraphNode, CallSite, Callee, DefinedFunction,
+};
+pub use self::captures::{captures, Capture};
+pub use self::directives::{directives, Attachment, Directive};
+pub use self::function_metrics::{function_metrics, FunctionMetrics};
+pub use self::global_writes::{
+    global_writes, global_writes_with, GlobalWrite, GlobalWriteKind, GlobalWritesConfig,
+};
+pub use self::requires::{requires, requires_with, RequireInfo, RequiresConfig};
+pub use self::semantic_tokens::{
+    semantic_tokens, semantic_tokens_with, SemanticToken, SemanticTokenKind, SemanticTokensConfig,
+};
+pub use self::shadowing::{shadowing, ShadowInfo, ShadowKind};
+pub use self::string_literals::{string_literals, StringContext, StringLiteralInfo, TableKey};
+pub use self::unreachable::unreachable;
+pub use self::unused_locals::{
+    unused_locals, unused_locals_with, UnusedKind, UnusedLocal, UnusedLocalsConfig,
+};
@@ -0,0 +1,532 @@
+use super::scopes::{FunctionId, Resolution, ScopeAnalysis, VariableId, VariableKind};
+use crate::{
+    ast::{self, Ast, Call, Expression, Index, Prefix, Suffix, Value, Var},
+    node::{Node, TriviaBehavior},
+    tokenizer::{Position, Token, TokenReference},
+    visitors::Visitor,
+};
+use std::collections::{HashMap, HashSet};
+
+/// A function of a [`CallGraph`](struct.CallGraph.html) that calls are made from,
+/// or the code of the file outside of every function
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallGraphNode {
+    /// The code outside of every function, which runs when the file does
+    File,
+    /// A function that was given a name, with the same id a
+    /// [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html) of the Ast gives it
+    Function(FunctionId),
+}
+
+/// A function that was given a name, from [`CallGraph::functions`](struct.CallGraph.html#method.functions)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DefinedFunction {
+    id: FunctionId,
+    name: String,
+    range: (Position, Position),
+}
+
+impl DefinedFunction {
+    /// The id of the function, the same as the one a [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html) of the Ast gives it
+    pub fn id(&self) -> FunctionId {
+        self.id
+    }
+
+    /// The name the function was given, such as `f`, `M.f`, or `M:f`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The start and end of the function, from its `function` keyword to its `end`
+    pub fn range(&self) -> (Position, Position) {
+        self.range
+    }
+}
+
+/// What a [`CallSite`](struct.CallSite.html) calls, as it's written in the code
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Callee {
+    /// A local called by its name, such as `f()`
+    Local {
+        /// The local that is called
+        variable: VariableId,
+        /// The name of the local
+        name: String,
+    },
+    /// A global called by its name, such as `print()`
+    Global {
+        /// The name of the global
+        name: String,
+    },
+    /// A function in a table, such as `M.f()` or `string.format()`
+    Path {
+        /// The names with dots between them, such as `M.f`
+        path: String,
+    },
+    /// A method, such as `object:method()`
+    Method {
+        /// The code of what the method is called on, such as `object`
+        receiver: String,
+        /// The name of the method
+        method: String,
+    },
+    /// A function that can't be known without running the code, such as `functions[1]()` or `f()()`
+    Unknown,
+}
+
+/// A call from one function to another, from [`CallGraph::calls`](struct.CallGraph.html#method.calls)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallSite {
+    caller: CallGraphNode,
+    callee: Callee,
+    targets: Vec<FunctionId>,
+    range: (Position, Position),
+}
+
+impl CallSite {
+    /// The function the call is made from
+    pub fn caller(&self) -> CallGraphNode {
+        self.caller
+    }
+
+    /// What is called, as it's written in the code
+    pub fn callee(&self) -> &Callee {
+        &self.callee
+    }
+
+    /// The functions of the Ast that the callee could be, by the name they were given, in the order they're declared.
+    /// Usually there is one, but there can be more when the same name is given to more than one function,
+    /// and none for calls to functions that aren't in the Ast, or to [`Unknown`](enum.Callee.html#variant.Unknown) callees.
+    pub fn targets(&self) -> &[FunctionId] {
+        &self.targets
+    }
+
+    /// The start and end of the call, from the start of the callee to the end of the arguments
+    pub fn range(&self) -> (Position, Position) {
+        self.range
+    }
+}
+
+/// The functions of an Ast and the calls between them, from [`call_graph`](fn.call_graph.html)
+#[derive(Clone, Debug, Default)]
+pub struct CallGraph {
+    functions: Vec<DefinedFunction>,
+    calls: Vec<CallSite>,
+}
+
+impl CallGraph {
+    /// Every function that was given a name, in the order they appear in the code
+    pub fn functions(&self) -> &[DefinedFunction] {
+        &self.functions
+    }
+
+    /// The function with the id, if it was given a name
+    pub fn function(&self, id: FunctionId) -> Option<&DefinedFunction> {
+        self.functions.iter().find(|function| function.id == id)
+    }
+
+    /// Every call, in the order they appear in the code
+    pub fn calls(&self) -> &[CallSite] {
+        &self.calls
+    }
+
+    /// The calls made directly from the node, in the order they appear in the code
+    pub fn calls_from(&self, node: CallGraphNode) -> impl Iterator<Item = &CallSite> {
+        self.calls.iter().filter(move |call| call.caller == node)
+    }
+
+    /// The functions that can be called from the roots, including the ones that are roots,
+    /// by following the targets of their calls, and the targets of the calls in those, and so on.
+    /// The functions that aren't reachable from [`File`](enum.CallGraphNode.html#variant.File)
+    /// and the functions the file exports are never called.
+    pub fn reachable(&self, roots: impl IntoIterator<Item = CallGraphNode>) -> HashSet<FunctionId> {
+        let mut reachable = HashSet::new();
+        let mut stack: Vec<_> = roots.into_iter().collect();
+
+        while let Some(node) = stack.pop() {
+            if let CallGraphNode::Function(id) = node {
+                if !reachable.insert(id) {
+                    continue;
+                }
+            }
+
+            for call in self.calls_from(node) {
+                stack.extend(
+                    call.targets
+                        .iter()
+                        .filter(|target| !reachable.contains(*target))
+                        .map(|target| CallGraphNode::Function(*target)),
+                );
+            }
+        }
+
+        reachable
+    }
+}
+
+/// The functions of the Ast that are given names, and the calls made from each of them to the functions they call.
+///
+/// Functions are given names by declaring them, such as `function M.f()`, `function M:f()`, and `local function f()`,
+/// or by assigning them to locals, globals, and the fields of tables, such as `local f = function()` or `M.f = function()`.
+/// A function that isn't given a name, such as one passed as an argument, is part of the function it's in,
+/// so the calls in it are calls from that function.
+///
+/// Calls are matched with the functions they call by name, with locals and globals told apart by
+/// a [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html), so `M.f()` calls the `function M.f()` of the same `M`.
+/// Methods are matched the same way, including `self:f()` in another method of the table.
+///
+/// ```rust
+/// # use full_moon::analysis::{call_graph, CallGraphNode};
+/// let code = "local function helper() end
+/// local function unused() helper() end
+/// local M = {}
+/// function M.run() helper() end
+/// M.run()";
+/// let ast = full_moon::parse(code).unwrap();
+/// let graph = call_graph(&ast);
+///
+/// let reachable = graph.reachable(vec![CallGraphNode::File]);
+/// let never_called: Vec<_> = graph
+///     .functions()
+///     .iter()
+///     .filter(|function| !reachable.contains(&function.id()))
+///     .map(|function| function.name())
+///     .collect();
+/// assert_eq!(never_called, vec!["unused"]);
+/// ```
+pub fn call_graph(ast: &Ast) -> CallGraph {
+    let analysis = ScopeAnalysis::new(ast);
+    let mut builder = Builder {
+        analysis: &analysis,
+        functions: Vec::new(),
+        defined: HashSet::new(),
+        names: HashMap::new(),
+        tables: HashMap::new(),
+        callers: Vec::new(),
+        calls: Vec::new(),
+    };
+    builder.visit_ast(ast);
+
+    let Builder {
+        mut functions,
+        names,
+        calls,
+        ..
+    } = builder;
+
+    functions.sort_by_key(|function| function.range.0);
+    let mut calls: Vec<_> = calls
+        .into_iter()
+        .map(|(mut call, name)| {
+            if let Some(targets) = name.and_then(|name| names.get(&name)) {
+                call.targets = targets.clone();
+            }
+
+            call
+        })
+        .collect();
+    calls.sort_by_key(|call| call.range.0);
+
+    CallGraph { functions, calls }
+}
+
+// What the first name of a function's name refers to
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Root {
+    Local(VariableId),
+    Global(String),
+}
+
+// A function's name, as what its first name refers to and the names of the fields after it
+type Name = (Root, Vec<String>);
+
+struct Builder<'a, 'ast> {
+    analysis: &'a ScopeAnalysis<'ast>,
+    functions: Vec<DefinedFunction>,
+    defined: HashSet<FunctionId>,
+    // The functions given each name, in the order they're declared
+    names: HashMap<Name, Vec<FunctionId>>,
+    // The tables methods are declared in, for the `self` of the method
+    tables: HashMap<FunctionId, Name>,
+    // The functions with names that are being visited, or None for the ones without names
+    callers: Vec<Option<FunctionId>>,
+    calls: Vec<(CallSite, Option<Name>)>,
+}
+
+impl<'ast> Builder<'_, 'ast> {
+    fn root(&self, token: &TokenReference<'ast>) -> Option<Root> {
+        match self.analysis.resolve(token) {
+            Resolution::Local(id) => Some(Root::Local(id)),
+            Resolution::Global => Some(Root::Global(token.to_string())),
+            Resolution::NotAVariable => None,
+        }
+    }
+
+    fn define(
+        &mut self,
+        function_token: &TokenReference<'ast>,
+        body: &ast::FunctionBody<'ast>,
+        display: String,
+        name: Option<Name>,
+    ) {
+        let id = match self.analysis.function_id(body) {
+            Some(id) => id,
+            None => return,
+        };
+
+        let start = Token::start_position(function_token);
+        self.defined.insert(id);
+        self.functions.push(DefinedFunction {
+            id,
+            name: display,
+            range: (start, body.end_position().unwrap_or(start)),
+        });
+
+        if let Some(name) = name {
+            self.names.entry(name).or_default().push(id);
+        }
+    }
+
+    // The name of a prefix followed by only `.name` indexes, such as `a.b.c`
+    fn path(&self, prefix: &Prefix<'ast>, suffixes: &[&Suffix<'ast>]) -> Option<Name> {
+        let root = match prefix {
+            Prefix::Name(token) => self.root(token)?,
+            Prefix::Expression(_) => return None,
+        };
+
+        let fields = suffixes
+            .iter()
+            .map(|suffix| match suffix {
+                Suffix::Index(Index::Dot { name, .. }) => Some(name.to_string()),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+
+        Some((root, fields))
+    }
+
+    // The table that `self` is, when the prefix is the implicit `self` of a method
+    fn self_table(&self, prefix: &Prefix<'ast>) -> Option<Name> {
+        let token = match prefix {
+            Prefix::Name(token) => token,
+            Prefix::Expression(_) => return None,
+        };
+
+        let variable = match self.analysis.resolve(token) {
+            Resolution::Local(id) => self.analysis.variable(id),
+            _ => return None,
+        };
+
+        if variable.kind() != VariableKind::SelfParameter {
+            return None;
+        }
+
+        let function = self.analysis.scope(variable.scope()).function()?;
+        self.tables.get(&function).cloned()
+    }
+
+    // Adds every call in a prefix followed by suffixes
+    fn calls(&mut self, prefix: &Prefix<'ast>, suffixes: &[&Suffix<'ast>]) {
+        let start = prefix.start_position().unwrap_or_default();
+        let caller = match self.callers.iter().rev().find_map(|caller| *caller) {
+            Some(id) => CallGraphNode::Function(id),
+            None => CallGraphNode::File,
+        };
+
+        for (index, suffix) in suffixes.iter().enumerate() {
+            let call = match suffix {
+                Suffix::Call(call) => call,
+                Suffix::Index(_) => continue,
+            };
+
+            let before = &suffixes[..index];
+            let (callee, name) = match call {
+                Call::AnonymousCall(_) => match (prefix, self.path(prefix, before)) {
+                    (Prefix::Name(token), Some((root, fields))) if fields.is_empty() => {
+                        let callee = match &root {
+                            Root::Local(variable) => Callee::Local {
+                                variable: *variable,
+                                name: token.to_string(),
+                            },
+                            Root::Global(name) => Callee::Global { name: name.clone() },
+                        };
+
+                        (callee, Some((root, fields)))
+                    }
+                    (_, Some(name)) => (
+                        Callee::Path {
+                            path: text(prefix, before),
+                        },
+                        Some(name),
+                    ),
+                    (_, None) => (Callee::Unknown, None),
+                },
+                Call::MethodCall(method_call) => {
+                    let method = method_call.name().to_string();
+                    let table = match before {
+                        [] => self
+                            .self_table(prefix)
+                            .or_else(|| self.path(prefix, before)),
+                        _ => self.path(prefix, before),
+                    };
+
+                    (
+                        Callee::Method {
+                            receiver: text(prefix, before),
+                            method: method.clone(),
+                        },
+                        table.map(|(root, mut fields)| {
+                            fields.push(method);
+                            (root, fields)
+                        }),
+                    )
+                }
+            };
+
+            self.calls.push((
+                CallSite {
+                    caller,
+                    callee,
+                    targets: Vec::new(),
+                    range: (start, suffix.end_position().unwrap_or(start)),
+                },
+                name,
+            ));
+        }
+    }
+}
+
+// The code of a prefix followed by suffixes, without the whitespace around it
+fn text(prefix: &Prefix, suffixes: &[&Suffix]) -> String {
+    let mut text = prefix.print(TriviaBehavior::Trim);
+    for suffix in suffixes {
+        text.push_str(&suffix.print(TriviaBehavior::Trim));
+    }
+
+    text
+}
+
+// The function an expression is, if it is only a function, such as `function() end`
+pub(super) fn function_value<'a, 'ast>(
+    expression: &'a Expression<'ast>,
+) -> Option<&'a (TokenReference<'ast>, ast::FunctionBody<'ast>)> {
+    match expression {
+        Expression::Value {
+            value, binop: None, ..
+        } => match &**value {
+            Value::Function(function) => Some(function),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl<'ast> Visitor<'ast> for Builder<'_, 'ast> {
+    fn visit_function_declaration(&mut self, declaration: &ast::FunctionDeclaration<'ast>) {
+        let function_name = declaration.name();
+        let mut names = function_name.names().iter();
+        let first = match names.next() {
+            Some(first) => first,
+            None => return,
+        };
+
+        let fields: Vec<_> = names.map(ToString::to_string).collect();
+        let root = self.root(first);
+        let table = root.clone().map(|root| (root, fields.clone()));
+        let name = root.map(|root| {
+            let mut fields = fields.clone();
+            fields.extend(function_name.method_name().map(ToString::to_string));
+            (root, fields)
+        });
+
+        self.define(
+            declaration.function_token(),
+            declaration.body(),
+            function_name.print(TriviaBehavior::Trim),
+            name,
+        );
+
+        if let (Some(_), Some(table), Some(id)) = (
+            function_name.method_name(),
+            table,
+            self.analysis.function_id(declaration.body()),
+        ) {
+            self.tables.insert(id, table);
+        }
+    }
+
+    fn visit_local_function(&mut self, local_function: &ast::LocalFunction<'ast>) {
+        let name = self
+            .root(local_function.name())
+            .map(|root| (root, Vec::new()));
+        self.define(
+            local_function.function_token(),
+            local_function.func_body(),
+            local_function.name().to_string(),
+            name,
+        );
+    }
+
+    fn visit_local_assignment(&mut self, local_assignment: &ast::LocalAssignment<'ast>) {
+        for (token, expression) in local_assignment
+            .name_list()
+            .iter()
+            .zip(local_assignment.expr_list().iter())
+        {
+            if let Some((function_token, body)) = function_value(expression) {
+                let name = self.root(token).map(|root| (root, Vec::new()));
+                self.define(function_token, body, token.to_string(), name);
+            }
+        }
+    }
+
+    fn visit_assignment(&mut self, assignment: &ast::Assignment<'ast>) {
+        for (var, expression) in assignment
+            .var_list()
+            .iter()
+            .zip(assignment.expr_list().iter())
+        {
+            let (function_token, body) = match function_value(expression) {
+                Some(function) => function,
+                None => continue,
+            };
+
+            let name = match var {
+                Var::Name(token) => self.root(token).map(|root| (root, Vec::new())),
+                Var::Expression(var_expression) => self.path(
+                    var_expression.prefix(),
+                    &var_expression.iter_suffixes().collect::<Vec<_>>(),
+                ),
+            };
+
+            // Functions assigned to anything other than names and fields, such as `t[1] = function() end`,
+            // aren't given names
+            if name.is_some() {
+                self.define(function_token, body, var.print(TriviaBehavior::Trim), name);
+            }
+        }
+    }
+
+    fn visit_function_body(&mut self, body: &ast::FunctionBody<'ast>) {
+        let id = self
+            .analysis
+            .function_id(body)
+            .filter(|id| self.defined.contains(id));
+        self.callers.push(id);
+    }
+
+    fn visit_function_body_end(&mut self, _: &ast::FunctionBody<'ast>) {
+        self.callers.pop();
+    }
+
+    fn visit_function_call(&mut self, call: &ast::FunctionCall<'ast>) {
+        self.calls(call.prefix(), &call.iter_suffixes().collect::<Vec<_>>());
+    }
+
+    fn visit_var_expression(&mut self, var_expression: &ast::VarExpression<'ast>) {
+        self.calls(
+            var_expression.prefix(),
+            &var_expression.iter_suffixes().collect::<Vec<_>>(),
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use super::scopes::{FunctionId, ReferenceKind, ScopeAnalysis, ScopeId, VariableId};
+use crate::{
+    ast::Ast,
+    tokenizer::{Position, Token},
+};
+use std::collections::HashMap;
+
+/// A local declared outside of a function that the function uses, which is an upvalue of it, from [`captures`](fn.captures.html)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capture {
+    variable: VariableId,
+    name: String,
+    scope: ScopeId,
+    read_only: bool,
+    positions: Vec<Position>,
+}
+
+impl Capture {
+    /// The local that is captured
+    pub fn variable(&self) -> VariableId {
+        self.variable
+    }
+
+    /// The name of the local
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The scope the local was declared in, which is outside of the function
+    pub fn scope(&self) -> ScopeId {
+        self.scope
+    }
+
+    /// Whether the function only reads the local, rather than setting it too
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Where the names in the function that refer to the local are, including in the functions in it,
+    /// in the order they appear in the code
+    pub fn positions(&self) -> &[Position] {
+        &self.positions
+    }
+}
+
+/// The locals every function of the Ast captures from outside of it, by the function, with every function in it
+/// even if it captures nothing. The ids of the functions and locals are the same as the ones a
+/// [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html) of the same Ast gives, such as from
+/// [`ScopeAnalysis::function_id`](scopes/struct.ScopeAnalysis.html#method.function_id).
+///
+/// A function captures every local declared outside of it that it uses, including locals that only the functions
+/// in it use, since Lua has to keep them for those functions too. The captures of each function are in the order
+/// the locals were declared.
+///
+/// ```rust
+/// # use full_moon::analysis::captures;
+/// let ast = full_moon::parse("local count = 0\nlocal function counter()\n  return function() count = count + 1 end\nend").unwrap();
+/// let captures = captures(&ast);
+///
+/// // Both `counter` and the function it returns capture `count`
+/// assert_eq!(captures.len(), 2);
+/// for function_captures in captures.values() {
+///     assert_eq!(function_captures.len(), 1);
+///     assert_eq!(function_captures[0].name(), "count");
+///     assert!(!function_captures[0].is_read_only());
+///     assert_eq!(function_captures[0].positions().len(), 2);
+/// }
+/// ```
+pub fn captures(ast: &Ast) -> HashMap<FunctionId, Vec<Capture>> {
+    let analysis = ScopeAnalysis::new(ast);
+    let mut captures: HashMap<FunctionId, Vec<Capture>> = analysis
+        .scopes()
+        .filter_map(|scope| Some((scope.function()?, Vec::new())))
+        .collect();
+
+    for variable in analysis.variables() {
+        // Where the capture of the local is in the captures of each function that captures it
+        let mut indexes = HashMap::new();
+
+        for reference in variable.references() {
+            if reference.is_declaration() {
+                continue;
+            }
+
+            // The functions between the name and where the local was declared capture it
+            let mut scope = analysis.scope(reference.scope());
+            while scope.id() != variable.scope() {
+                if let Some(function) = scope.function() {
+                    let function_captures = captures.get_mut(&function).unwrap();
+                    let index = *indexes.entry(function).or_insert_with(|| {
+                        function_captures.push(Capture {
+                            variable: variable.id(),
+                            name: variable.name().to_owned(),
+                            scope: variable.scope(),
+                            read_only: true,
+                            positions: Vec::new(),
+                        });
+
+                        function_captures.len() - 1
+                    });
+
+                    let capture = &mut function_captures[index];
+                    capture
+                        .positions
+                        .push(Token::start_position(reference.token()));
+                    if reference.kind() == ReferenceKind::Write {
+                        capture.read_only = false;
+                    }
+                }
+
+                scope = match scope.parent() {
+                    Some(parent) => analysis.scope(parent),
+                    None => break,
+                };
+            }
+        }
+    }
+
+    captures
+}
@@ -0,0 +1,166 @@
+use crate::{
+    ast::{Ast, Block, Stmt},
+    tokenizer::{Position, Token, TokenKind, TokenType},
+    visitors::StmtComments,
+};
+use std::collections::HashMap;
+
+/// What a [`Directive`](struct.Directive.html) applies to
+#[derive(Clone, Copy, Debug)]
+pub enum Attachment<'ast, 'b> {
+    /// The statement the directive is right before, as one of its
+    /// [leading comments](../visitors/struct.StmtComments.html#method.leading)
+    Leading(&'b Stmt<'ast>),
+    /// The statement the directive is after on the same line, as one of its
+    /// [trailing comments](../visitors/struct.StmtComments.html#method.trailing)
+    Trailing(&'b Stmt<'ast>),
+    /// The whole chunk, for directives before any code that aren't leading comments of the first statement,
+    /// such as ones separated from it by a blank line
+    Chunk(&'b Block<'ast>),
+    /// Nothing, for directives that aren't before or after a statement, such as ones right before `end`
+    Detached,
+}
+
+impl<'ast, 'b> Attachment<'ast, 'b> {
+    /// The statement the directive applies to, if it is leading or trailing one
+    pub fn stmt(&self) -> Option<&'b Stmt<'ast>> {
+        match self {
+            Attachment::Leading(stmt) | Attachment::Trailing(stmt) => Some(stmt),
+            Attachment::Chunk(_) | Attachment::Detached => None,
+        }
+    }
+}
+
+/// A comment that starts with a prefix, such as `-- full-moon: ignore unused_local, shadowing`,
+/// from [`directives`](fn.directives.html)
+#[derive(Clone, Debug)]
+pub struct Directive<'ast, 'b> {
+    key: String,
+    values: Vec<String>,
+    range: (Position, Position),
+    attachment: Attachment<'ast, 'b>,
+}
+
+impl<'ast, 'b> Directive<'ast, 'b> {
+    /// The first word after the prefix, such as `ignore`
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The values after the key, separated by commas, with the whitespace around them removed,
+    /// such as `unused_local` and `shadowing`. Empty when there is nothing after the key.
+    pub fn values(&self) -> &[String] {
+        &self.values
+    }
+
+    /// The start and end of the comment
+    pub fn range(&self) -> (Position, Position) {
+        self.range
+    }
+
+    /// What the directive applies to
+    pub fn attachment(&self) -> Attachment<'ast, 'b> {
+        self.attachment
+    }
+}
+
+/// Every comment of the Ast whose text starts with the prefix, after the `--` and the whitespace following it,
+/// read as a directive, in the order they appear in the code. Directives are read as a key, which is the text up to
+/// the first whitespace after the prefix, followed by values separated by commas, so with the prefix `full-moon:`,
+/// `-- full-moon: ignore unused_local, shadowing` has the key `ignore` and the values `unused_local` and `shadowing`,
+/// and with the prefix `!`, `--!strict` has the key `strict` and no values. Comments with nothing after the prefix
+/// aren't directives. The text of multiline comments is read the same way, without their brackets.
+///
+/// Directives apply to what their comments belong to, the same way
+/// [`DocumentedVisitor`](../visitors/trait.DocumentedVisitor.html) associates comments with statements, so a lint
+/// and a documentation generator never disagree: a comment after a statement on its line applies to it, comments on
+/// the lines right before a statement apply to it, and other comments before any code apply to the whole chunk.
+/// Asts parsed without [trivia](../ast/struct.ParseConfig.html#structfield.preserve_trivia) keep their comments apart
+/// from their statements, so their directives only apply to the chunk, when they're before any code.
+///
+/// ```rust
+/// # use full_moon::{analysis::{directives, Attachment}, node::{Node, TriviaBehavior}};
+/// let ast = full_moon::parse("--!strict\n\nlocal x = 1 --!nolint unused, shadowing\n--!ignore\nprint(x)").unwrap();
+/// let directives = directives(&ast, "!");
+///
+/// let keys: Vec<&str> = directives.iter().map(|directive| directive.key()).collect();
+/// assert_eq!(keys, ["strict", "nolint", "ignore"]);
+/// assert_eq!(directives[1].values(), ["unused", "shadowing"]);
+///
+/// assert!(matches!(directives[0].attachment(), Attachment::Chunk(_)));
+/// assert!(matches!(directives[1].attachment(), Attachment::Trailing(_)));
+/// let stmt = directives[2].attachment().stmt().unwrap();
+/// assert_eq!(stmt.print(TriviaBehavior::Trim), "print(x)");
+/// ```
+pub fn directives<'ast, 'b>(ast: &'b Ast<'ast>, prefix: &str) -> Vec<Directive<'ast, 'b>> {
+    // The comments are found by where they start, which is unique to each of them
+    let mut attachments = HashMap::new();
+    for stmt in ast.nodes_of_type::<Stmt>() {
+        let comments = StmtComments::new(stmt);
+        for comment in comments.leading() {
+            attachments.insert(comment.start_position().bytes(), Attachment::Leading(stmt));
+        }
+        for comment in comments.trailing() {
+            attachments.insert(comment.start_position().bytes(), Attachment::Trailing(stmt));
+        }
+    }
+
+    let code_start = ast
+        .iter_tokens()
+        .find(|token| !token.token_type().ignore() && token.token_kind() != TokenKind::Eof)
+        .map_or(usize::MAX, |token| token.start_position().bytes());
+
+    let mut directives = Vec::new();
+    for token in ast.comments() {
+        let (key, values) = match parse(token, prefix) {
+            Some(directive) => directive,
+            None => continue,
+        };
+
+        let start = token.start_position().bytes();
+        let attachment = match attachments.get(&start) {
+            Some(attachment) => *attachment,
+            None if start < code_start => Attachment::Chunk(ast.nodes()),
+            None => Attachment::Detached,
+        };
+
+        directives.push(Directive {
+            key,
+            values,
+            range: (token.start_position(), token.end_position()),
+            attachment,
+        });
+    }
+
+    directives
+}
+
+// The key and values of a comment that starts with the prefix
+fn parse(token: &Token, prefix: &str) -> Option<(String, Vec<String>)> {
+    let token_type = token.token_type();
+    let text = match &*token_type {
+        TokenType::SingleLineComment { comment } | TokenType::MultiLineComment { comment, .. } => {
+            comment
+        }
+        _ => return None,
+    };
+
+    let rest = text.trim_start().strip_prefix(prefix)?.trim();
+    let (key, values) = match rest.find(char::is_whitespace) {
+        Some(end) => (&rest[..end], &rest[end..]),
+        None => (rest, ""),
+    };
+
+    if key.is_empty() {
+        return None;
+    }
+
+    let values = values
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+        .collect();
+
+    Some((key.to_owned(), values))
+}
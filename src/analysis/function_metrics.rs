@@ -0,0 +1,328 @@
+use super::{
+    call_graph::function_value,
+    scopes::{FunctionId, ScopeAnalysis},
+};
+use crate::{
+    ast::{self, Ast, BinOp, Expression, Field, Value},
+    node::{Node, TriviaBehavior},
+    tokenizer::{Position, Token, TokenReference},
+    visitors::{Visit, Visitor},
+};
+use std::collections::HashMap;
+
+/// Measures of how big and complicated a function is, from [`function_metrics`](fn.function_metrics.html).
+/// Each measure only counts the code of the function itself, not the code of the functions in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionMetrics {
+    id: FunctionId,
+    name: Option<String>,
+    range: (Position, Position),
+    cyclomatic_complexity: usize,
+    statement_count: usize,
+    parameter_count: usize,
+    max_nesting_depth: usize,
+    line_span: usize,
+}
+
+impl FunctionMetrics {
+    /// The id of the function, the same as the one a [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html) of the Ast gives it
+    pub fn id(&self) -> FunctionId {
+        self.id
+    }
+
+    /// The name the function was given, such as `f`, `M.f`, or `M:f`, or the key of the table field it's the value of,
+    /// such as `f` in `{ f = function() end }`. Functions that aren't given names, such as ones passed as arguments,
+    /// have no name.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The start and end of the function, from its `function` keyword to its `end`
+    pub fn range(&self) -> (Position, Position) {
+        self.range
+    }
+
+    /// 1, plus 1 for every `if` and `elseif`, 1 for every `while`, `repeat`, numeric `for`, and generic `for` loop,
+    /// and 1 for every `and` and `or` in the conditions of `if`, `elseif`, `while`, and `until`,
+    /// including the ones in parentheses and in the arguments of calls in the conditions.
+    /// `else`, `break`, `return`, and `and` and `or` outside of conditions, such as `local x = a or b`, add nothing.
+    pub fn cyclomatic_complexity(&self) -> usize {
+        self.cyclomatic_complexity
+    }
+
+    /// How many statements are in the function, including `return` and `break` and the statements of
+    /// nested blocks, such as the ones in `if` statements, the same as
+    /// [`Statistics::function_statements`](../visitors/struct.Statistics.html#method.function_statements).
+    /// A statement that declares a function counts as 1.
+    pub fn statement_count(&self) -> usize {
+        self.statement_count
+    }
+
+    /// How many parameters are between the parentheses of the function, including `...`,
+    /// but not the implicit `self` of methods
+    pub fn parameter_count(&self) -> usize {
+        self.parameter_count
+    }
+
+    /// The most blocks inside of each other in the function, not counting the body of the function:
+    /// `if x then end` is 1, `if x then while y do end end` is 2, and a function with no blocks in it is 0.
+    /// The blocks of `elseif` and `else` are as deep as the block of their `if`.
+    pub fn max_nesting_depth(&self) -> usize {
+        self.max_nesting_depth
+    }
+
+    /// How many lines the function is on, from the line of its `function` keyword to the line of its `end`,
+    /// so a function on one line is 1
+    pub fn line_span(&self) -> usize {
+        self.line_span
+    }
+}
+
+/// The metrics of every function of the Ast, including local and anonymous functions, in the order they appear
+/// in the code, for checking that functions aren't too big or complicated. The way every measure is counted is
+/// documented on [`FunctionMetrics`](struct.FunctionMetrics.html), and doesn't change between versions.
+///
+/// ```rust
+/// # use full_moon::analysis::function_metrics;
+/// let ast = full_moon::parse("function clamp(x, low, high)
+///     if x < low or x ~= x then
+///         return low
+///     elseif x > high then
+///         return high
+///     end
+///     return x
+/// end").unwrap();
+/// let metrics = &function_metrics(&ast)[0];
+///
+/// assert_eq!(metrics.name(), Some("clamp"));
+/// assert_eq!(metrics.cyclomatic_complexity(), 4);
+/// assert_eq!(metrics.statement_count(), 4);
+/// assert_eq!(metrics.parameter_count(), 3);
+/// assert_eq!(metrics.max_nesting_depth(), 1);
+/// assert_eq!(metrics.line_span(), 8);
+/// ```
+pub fn function_metrics(ast: &Ast) -> Vec<FunctionMetrics> {
+    let analysis = ScopeAnalysis::new(ast);
+    let mut visitor = Metrics {
+        analysis: &analysis,
+        names: HashMap::new(),
+        function_tokens: HashMap::new(),
+        metrics: Vec::new(),
+        open: Vec::new(),
+    };
+    visitor.visit_ast(ast);
+    visitor.metrics
+}
+
+struct Metrics<'a, 'ast> {
+    analysis: &'a ScopeAnalysis<'ast>,
+    names: HashMap<FunctionId, String>,
+    function_tokens: HashMap<FunctionId, TokenReference<'ast>>,
+    metrics: Vec<FunctionMetrics>,
+    // The functions being visited, as where they are in `metrics` and how many blocks are open in them,
+    // including their body
+    open: Vec<(usize, usize)>,
+}
+
+impl<'ast> Metrics<'_, 'ast> {
+    fn function(
+        &mut self,
+        function_token: &TokenReference<'ast>,
+        body: &ast::FunctionBody<'ast>,
+        name: Option<String>,
+    ) {
+        if let Some(id) = self.analysis.function_id(body) {
+            self.function_tokens.insert(id, function_token.clone());
+            if let Some(name) = name {
+                self.names.entry(id).or_insert(name);
+            }
+        }
+    }
+
+    fn current(&mut self) -> Option<&mut FunctionMetrics> {
+        let (index, _) = *self.open.last()?;
+        self.metrics.get_mut(index)
+    }
+
+    fn branch(&mut self, conditions: &[&Expression<'ast>]) {
+        let mut operators = LogicalOperators::default();
+        for condition in conditions {
+            condition.visit(&mut operators);
+        }
+
+        if let Some(metrics) = self.current() {
+            metrics.cyclomatic_complexity += conditions.len().max(1) + operators.count;
+        }
+    }
+
+    fn count_statement(&mut self) {
+        if let Some(metrics) = self.current() {
+            metrics.statement_count += 1;
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Metrics<'_, 'ast> {
+    fn visit_function_declaration(&mut self, declaration: &ast::FunctionDeclaration<'ast>) {
+        self.function(
+            declaration.function_token(),
+            declaration.body(),
+            Some(declaration.name().print(TriviaBehavior::Trim)),
+        );
+    }
+
+    fn visit_local_function(&mut self, local_function: &ast::LocalFunction<'ast>) {
+        self.function(
+            local_function.function_token(),
+            local_function.func_body(),
+            Some(local_function.name().to_string()),
+        );
+    }
+
+    fn visit_local_assignment(&mut self, local_assignment: &ast::LocalAssignment<'ast>) {
+        for (name, expression) in local_assignment
+            .name_list()
+            .iter()
+            .zip(local_assignment.expr_list().iter())
+        {
+            if let Some((function_token, body)) = function_value(expression) {
+                self.function(function_token, body, Some(name.to_string()));
+            }
+        }
+    }
+
+    fn visit_assignment(&mut self, assignment: &ast::Assignment<'ast>) {
+        for (var, expression) in assignment
+            .var_list()
+            .iter()
+            .zip(assignment.expr_list().iter())
+        {
+            if let Some((function_token, body)) = function_value(expression) {
+                self.function(function_token, body, Some(var.print(TriviaBehavior::Trim)));
+            }
+        }
+    }
+
+    fn visit_field(&mut self, field: &Field<'ast>) {
+        if let Field::NameKey { key, value, .. } = field {
+            if let Some((function_token, body)) = function_value(value) {
+                self.function(function_token, body, Some(key.to_string()));
+            }
+        }
+    }
+
+    fn visit_value(&mut self, value: &Value<'ast>) {
+        if let Value::Function((function_token, body)) = value {
+            self.function(function_token, body, None);
+        }
+    }
+
+    fn visit_function_body(&mut self, body: &ast::FunctionBody<'ast>) {
+        let id = match self.analysis.function_id(body) {
+            Some(id) => id,
+            None => return,
+        };
+
+        let end = Token::end_position(body.end_token());
+        let (start, start_line) = match self.function_tokens.get(&id) {
+            // The line of the end of the keyword, since a token at the start of a line has the line before it
+            Some(token) => (
+                Token::start_position(token),
+                Token::end_position(token).line(),
+            ),
+            None => (end, end.line()),
+        };
+
+        self.open.push((self.metrics.len(), 0));
+        self.metrics.push(FunctionMetrics {
+            id,
+            name: self.names.get(&id).cloned(),
+            range: (start, end),
+            cyclomatic_complexity: 1,
+            statement_count: 0,
+            parameter_count: body.iter_parameters().count(),
+            max_nesting_depth: 0,
+            line_span: end.line().saturating_sub(start_line) + 1,
+        });
+    }
+
+    fn visit_function_body_end(&mut self, body: &ast::FunctionBody<'ast>) {
+        if self.analysis.function_id(body).is_some() {
+            self.open.pop();
+        }
+    }
+
+    fn visit_block(&mut self, _: &ast::Block<'ast>) {
+        if let Some((index, depth)) = self.open.last_mut() {
+            *depth += 1;
+            let metrics = &mut self.metrics[*index];
+            metrics.max_nesting_depth = metrics.max_nesting_depth.max(*depth - 1);
+        }
+    }
+
+    fn visit_block_end(&mut self, _: &ast::Block<'ast>) {
+        if let Some((_, depth)) = self.open.last_mut() {
+            *depth -= 1;
+        }
+    }
+
+    fn visit_if(&mut self, if_stmt: &ast::If<'ast>) {
+        let mut conditions = vec![if_stmt.condition()];
+        conditions.extend(
+            if_stmt
+                .else_if()
+                .into_iter()
+                .flatten()
+                .map(ast::ElseIf::condition),
+        );
+
+        self.branch(&conditions);
+    }
+
+    fn visit_while(&mut self, while_loop: &ast::While<'ast>) {
+        self.branch(&[while_loop.condition()]);
+    }
+
+    fn visit_repeat(&mut self, repeat: &ast::Repeat<'ast>) {
+        self.branch(&[repeat.until()]);
+    }
+
+    fn visit_numeric_for(&mut self, _: &ast::NumericFor<'ast>) {
+        self.branch(&[]);
+    }
+
+    fn visit_generic_for(&mut self, _: &ast::GenericFor<'ast>) {
+        self.branch(&[]);
+    }
+
+    fn visit_stmt(&mut self, _: &ast::Stmt<'ast>) {
+        self.count_statement();
+    }
+
+    fn visit_last_stmt(&mut self, _: &ast::LastStmt<'ast>) {
+        self.count_statement();
+    }
+}
+
+// Counts the `and` and `or` operators in a condition, outside of the functions in it
+#[derive(Default)]
+struct LogicalOperators {
+    count: usize,
+    functions: usize,
+}
+
+impl<'ast> Visitor<'ast> for LogicalOperators {
+    fn visit_bin_op(&mut self, bin_op: &ast::BinOpRhs<'ast>) {
+        if self.functions == 0 && matches!(bin_op.bin_op(), BinOp::And(_) | BinOp::Or(_)) {
+            self.count += 1;
+        }
+    }
+
+    fn visit_function_body(&mut self, _: &ast::FunctionBody<'ast>) {
+        self.functions += 1;
+    }
+
+    fn visit_function_body_end(&mut self, _: &ast::FunctionBody<'ast>) {
+        self.functions -= 1;
+    }
+}
@@ -0,0 +1,204 @@
+use super::scopes::{FunctionId, Resolution, ScopeAnalysis};
+use crate::{
+    ast::{self, Ast, Call, Prefix, Suffix, Var},
+    node::Node,
+    tokenizer::{Position, TokenReference},
+    visitors::Visitor,
+};
+
+/// The options for [`global_writes_with`](fn.global_writes_with.html).
+/// Use [`GlobalWritesConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlobalWritesConfig {
+    /// The names of globals that are meant to be set, which aren't reported, only `_G` by default.
+    /// Add others such as the name of the table a module is in for code that makes its modules globals.
+    pub allowed: Vec<String>,
+    /// Whether to report the writes that are likely meant to be globals since they're after
+    /// a call to `module` or `setfenv`, false by default
+    pub include_intentional: bool,
+}
+
+impl Default for GlobalWritesConfig {
+    fn default() -> Self {
+        Self {
+            allowed: vec!["_G".to_owned()],
+            include_intentional: false,
+        }
+    }
+}
+
+/// How a [`GlobalWrite`](struct.GlobalWrite.html) sets the global
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GlobalWriteKind {
+    /// An assignment, such as `x = 1`
+    Assignment,
+    /// A function declaration, such as `function x() end`
+    FunctionDeclaration,
+}
+
+/// A name that is set without being a local, from [`global_writes`](fn.global_writes.html)
+#[derive(Clone, Debug)]
+pub struct GlobalWrite<'ast> {
+    token: TokenReference<'ast>,
+    kind: GlobalWriteKind,
+    range: (Position, Position),
+    function: Option<FunctionId>,
+    intentional: bool,
+}
+
+impl<'ast> GlobalWrite<'ast> {
+    /// The name that is set
+    pub fn token(&self) -> &TokenReference<'ast> {
+        &self.token
+    }
+
+    /// Whether the global is set by an assignment or a function declaration
+    pub fn kind(&self) -> GlobalWriteKind {
+        self.kind
+    }
+
+    /// The start and end of the statement that sets the global
+    pub fn range(&self) -> (Position, Position) {
+        self.range
+    }
+
+    /// The function the global is set in, the same as the ids a [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html)
+    /// of the same Ast gives, or None if it is set outside of every function
+    pub fn function(&self) -> Option<FunctionId> {
+        self.function
+    }
+
+    /// Whether the global is set after a call to `module` or `setfenv` in the function it is set in, or a function
+    /// it is in, which change the table globals are set in, so setting globals is likely what the code means to do.
+    /// Writes with this set are only reported when
+    /// [`GlobalWritesConfig::include_intentional`](struct.GlobalWritesConfig.html#structfield.include_intentional) is set.
+    pub fn is_intentional(&self) -> bool {
+        self.intentional
+    }
+}
+
+/// Every name the Ast sets that doesn't refer to a local, in the order they appear in the code, for finding names that
+/// were meant to be declared with `local`. This includes function declarations such as `function x() end`, but not
+/// `function x.y() end`, which sets a field. Writes inside of functions are included too, such as to a name that
+/// is only declared as a local after the function.
+/// Writes to `_G` and writes after a call to `module` or `setfenv` aren't reported.
+///
+/// ```rust
+/// # use full_moon::analysis::{global_writes, GlobalWriteKind};
+/// let ast = full_moon::parse("local count\nfunction increment()\n  count = count + 1\n  total = count\nend\nM.x = 1").unwrap();
+/// let writes: Vec<_> = global_writes(&ast)
+///     .iter()
+///     .map(|write| (write.token().to_string(), write.kind(), write.function().is_some()))
+///     .collect();
+///
+/// assert_eq!(
+///     writes,
+///     vec![
+///         ("increment".to_owned(), GlobalWriteKind::FunctionDeclaration, false),
+///         ("total".to_owned(), GlobalWriteKind::Assignment, true),
+///     ]
+/// );
+/// ```
+pub fn global_writes<'ast>(ast: &Ast<'ast>) -> Vec<GlobalWrite<'ast>> {
+    global_writes_with(ast, &GlobalWritesConfig::default())
+}
+
+/// The same as [`global_writes`](fn.global_writes.html), but with options, such as globals that are meant to be set
+pub fn global_writes_with<'ast>(
+    ast: &Ast<'ast>,
+    config: &GlobalWritesConfig,
+) -> Vec<GlobalWrite<'ast>> {
+    let analysis = ScopeAnalysis::new(ast);
+    let mut visitor = GlobalWrites {
+        analysis: &analysis,
+        config,
+        functions: vec![(None, false)],
+        writes: Vec::new(),
+    };
+    visitor.visit_ast(ast);
+    visitor.writes
+}
+
+struct GlobalWrites<'a, 'ast> {
+    analysis: &'a ScopeAnalysis<'ast>,
+    config: &'a GlobalWritesConfig,
+    // The functions the visitor is in, from the outermost, with whether the table globals are set in was changed in them.
+    // A function starts with the table of the function it was made in, the same as in Lua.
+    functions: Vec<(Option<FunctionId>, bool)>,
+    writes: Vec<GlobalWrite<'ast>>,
+}
+
+impl<'ast> GlobalWrites<'_, 'ast> {
+    fn write(&mut self, token: &TokenReference<'ast>, kind: GlobalWriteKind, node: &impl Node) {
+        let name = token.to_string();
+        if self.analysis.resolve(token) != Resolution::Global || self.config.allowed.contains(&name)
+        {
+            return;
+        }
+
+        let (function, intentional) = *self
+            .functions
+            .last()
+            .expect("the visitor should be in the file at least");
+        if intentional && !self.config.include_intentional {
+            return;
+        }
+
+        if let Some(range) = node.range() {
+            self.writes.push(GlobalWrite {
+                token: token.clone(),
+                kind,
+                range,
+                function,
+                intentional,
+            });
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for GlobalWrites<'_, 'ast> {
+    fn visit_assignment(&mut self, assignment: &ast::Assignment<'ast>) {
+        for var in assignment.var_list().iter() {
+            if let Var::Name(name) = var {
+                self.write(name, GlobalWriteKind::Assignment, assignment);
+            }
+        }
+    }
+
+    fn visit_function_declaration(&mut self, declaration: &ast::FunctionDeclaration<'ast>) {
+        let name = declaration.name();
+        let mut names = name.names().iter();
+        if let (Some(first), None, None) = (names.next(), names.next(), name.method_name()) {
+            self.write(first, GlobalWriteKind::FunctionDeclaration, declaration);
+        }
+    }
+
+    fn visit_function_body(&mut self, body: &ast::FunctionBody<'ast>) {
+        let intentional = self
+            .functions
+            .last()
+            .is_some_and(|(_, intentional)| *intentional);
+        self.functions
+            .push((self.analysis.function_id(body), intentional));
+    }
+
+    fn visit_function_body_end(&mut self, _: &ast::FunctionBody<'ast>) {
+        self.functions.pop();
+    }
+
+    fn visit_function_call(&mut self, call: &ast::FunctionCall<'ast>) {
+        let function = match (call.prefix(), call.iter_suffixes().next()) {
+            (Prefix::Name(function), Some(Suffix::Call(Call::AnonymousCall(_)))) => function,
+            _ => return,
+        };
+
+        let name = function.to_string();
+        if (name == "module" || name == "setfenv")
+            && self.analysis.resolve(function) == Resolution::Global
+        {
+            if let Some((_, intentional)) = self.functions.last_mut() {
+                *intentional = true;
+            }
+        }
+    }
+}
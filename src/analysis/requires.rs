@@ -0,0 +1,190 @@
+use super::scopes::{self, Resolution, ScopeAnalysis};
+use crate::{
+    ast::{self, simplified, Ast, Call, Expression, FunctionArgs, Prefix, Suffix, Value},
+    node::Node,
+    tokenizer::{Position, Token, TokenReference},
+    visitors::Visitor,
+};
+use std::collections::HashMap;
+
+/// The options for [`requires_with`](fn.requires_with.html).
+/// Use [`RequiresConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequiresConfig {
+    /// The names of the global functions that load modules, only `require` by default.
+    /// Add others such as `import` or `dofile` for code that loads modules with them.
+    pub functions: Vec<String>,
+}
+
+impl Default for RequiresConfig {
+    fn default() -> Self {
+        Self {
+            functions: vec!["require".to_owned()],
+        }
+    }
+}
+
+/// A call that loads a module, such as `require("module")`, from [`requires`](fn.requires.html)
+#[derive(Clone, Debug)]
+pub struct RequireInfo<'ast> {
+    function: TokenReference<'ast>,
+    module: Option<String>,
+    range: (Position, Position),
+    local: Option<TokenReference<'ast>>,
+}
+
+impl<'ast> RequireInfo<'ast> {
+    /// The name of the function that was called, such as `require`
+    pub fn function(&self) -> &TokenReference<'ast> {
+        &self.function
+    }
+
+    /// The module that is loaded, with the escapes of the string decoded,
+    /// or None if the argument isn't a string, such as `require(name)`
+    pub fn module(&self) -> Option<&str> {
+        self.module.as_deref()
+    }
+
+    /// Whether the module is only known when the code runs, since the argument isn't a string
+    pub fn is_dynamic(&self) -> bool {
+        self.module.is_none()
+    }
+
+    /// The start and end of the call, from the name of the function to the end of its arguments
+    pub fn range(&self) -> (Position, Position) {
+        self.range
+    }
+
+    /// The name of the local the module is assigned to when it is declared, such as `m` in
+    /// `local m = require("module")`
+    pub fn local(&self) -> Option<&TokenReference<'ast>> {
+        self.local.as_ref()
+    }
+}
+
+/// Every call to `require` in the Ast, in the order they appear in the code, for finding what modules code depends on.
+/// Calls with a string, `require("module")` or `require "module"`, give the module, and calls with anything else
+/// are dynamic. A local or parameter named `require` isn't the global, so calls to it aren't included.
+///
+/// ```rust
+/// # use full_moon::analysis::requires;
+/// let ast = full_moon::parse("local json = require(\"json\")\nrequire \"lib.init\"\nrequire(name)").unwrap();
+/// let requires = requires(&ast);
+///
+/// let modules: Vec<_> = requires.iter().map(|require| require.module()).collect();
+/// assert_eq!(modules, vec![Some("json"), Some("lib.init"), None]);
+/// assert_eq!(requires[0].local().unwrap().to_string(), "json");
+/// assert!(requires[2].is_dynamic());
+/// ```
+pub fn requires<'ast>(ast: &Ast<'ast>) -> Vec<RequireInfo<'ast>> {
+    requires_with(ast, &RequiresConfig::default())
+}
+
+/// The same as [`requires`](fn.requires.html), but with options, such as other functions that load modules
+pub fn requires_with<'ast>(ast: &Ast<'ast>, config: &RequiresConfig) -> Vec<RequireInfo<'ast>> {
+    let analysis = ScopeAnalysis::new(ast);
+    let mut visitor = Requires {
+        analysis: &analysis,
+        config,
+        requires: Vec::new(),
+        locals: HashMap::new(),
+    };
+    visitor.visit_ast(ast);
+
+    let Requires {
+        mut requires,
+        locals,
+        ..
+    } = visitor;
+    for require in &mut requires {
+        require.local = locals.get(&scopes::key(&require.function)).cloned();
+    }
+
+    requires.sort_by_key(|require| require.range.0);
+    requires
+}
+
+struct Requires<'a, 'ast> {
+    analysis: &'a ScopeAnalysis<'ast>,
+    config: &'a RequiresConfig,
+    requires: Vec<RequireInfo<'ast>>,
+    // The locals that calls are assigned to, by the name of the function that is called
+    locals: HashMap<usize, TokenReference<'ast>>,
+}
+
+impl<'ast> Requires<'_, 'ast> {
+    // Finds a require at the prefix of a call or an index, such as `require("module").field`
+    fn find(&mut self, prefix: &Prefix<'ast>, suffix: Option<&Suffix<'ast>>) {
+        let (function, arguments) = match (prefix, suffix) {
+            (Prefix::Name(function), Some(Suffix::Call(Call::AnonymousCall(arguments)))) => {
+                (function, arguments)
+            }
+            _ => return,
+        };
+
+        let name = function.to_string();
+        if !self.config.functions.contains(&name)
+            || self.analysis.resolve(function) != Resolution::Global
+        {
+            return;
+        }
+
+        let module = match arguments {
+            FunctionArgs::String(token) => Some(token),
+            FunctionArgs::Parentheses { arguments, .. } => match arguments.iter().next() {
+                Some(Expression::Value {
+                    value, binop: None, ..
+                }) => match &**value {
+                    Value::String(token) => Some(token),
+                    _ => None,
+                },
+                _ => None,
+            },
+            FunctionArgs::TableConstructor(_) => None,
+        };
+
+        let start = Token::start_position(function);
+        self.requires.push(RequireInfo {
+            function: function.clone(),
+            module: module
+                .and_then(simplified::string)
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned()),
+            range: (start, arguments.end_position().unwrap_or(start)),
+            local: None,
+        });
+    }
+}
+
+impl<'ast> Visitor<'ast> for Requires<'_, 'ast> {
+    fn visit_function_call(&mut self, call: &ast::FunctionCall<'ast>) {
+        self.find(call.prefix(), call.iter_suffixes().next());
+    }
+
+    fn visit_var_expression(&mut self, var_expression: &ast::VarExpression<'ast>) {
+        self.find(
+            var_expression.prefix(),
+            var_expression.iter_suffixes().next(),
+        );
+    }
+
+    fn visit_local_assignment(&mut self, local_assignment: &ast::LocalAssignment<'ast>) {
+        for (name, expression) in local_assignment
+            .name_list()
+            .iter()
+            .zip(local_assignment.expr_list().iter())
+        {
+            if let Expression::Value {
+                value, binop: None, ..
+            } = expression
+            {
+                if let Value::FunctionCall(call) = &**value {
+                    if let (Prefix::Name(function), 1) =
+                        (call.prefix(), call.iter_suffixes().count())
+                    {
+                        self.locals.insert(scopes::key(function), name.clone());
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,519 @@
+//! The scopes of code, with every local declared in them and which local or global every name refers to,
+//! through [`ScopeAnalysis`](struct.ScopeAnalysis.html).
+use crate::{
+    ast::{self, Ast},
+    tokenizer::{Position, Token, TokenReference},
+    visitors::{ScopeKind, ScopedVisitor, Scopes, Visitor},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Which [`Variable`](struct.Variable.html) a [`ScopeAnalysis`](struct.ScopeAnalysis.html) is about
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VariableId(usize);
+
+/// Which [`Scope`](struct.Scope.html) a [`ScopeAnalysis`](struct.ScopeAnalysis.html) is about
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScopeId(usize);
+
+/// Which function a [`ScopeAnalysis`](struct.ScopeAnalysis.html) is about, with the functions of an Ast
+/// in the order they start in. Find the function of a [`FunctionBody`](../../ast/struct.FunctionBody.html)
+/// with [`ScopeAnalysis::function_id`](struct.ScopeAnalysis.html#method.function_id).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FunctionId(usize);
+
+/// What a token refers to, from [`ScopeAnalysis::resolve`](struct.ScopeAnalysis.html#method.resolve)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Resolution {
+    /// The name of a local, either where it is declared or where it is used
+    Local(VariableId),
+    /// A name that isn't a local where it is used, such as `print`
+    Global,
+    /// A token that isn't the name of a variable, such as a keyword, a field in `x.y`, or a token from another Ast
+    NotAVariable,
+}
+
+/// How a [`Variable`](struct.Variable.html) was declared
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VariableKind {
+    /// A name of a `local` statement, such as `x` in `local x = 1`
+    Local,
+    /// The name of a `local function`, which the function can call itself with
+    LocalFunction,
+    /// A parameter of a function, such as `x` in `function(x) end`
+    Parameter,
+    /// The implicit `self` parameter of a method, such as `function x:y() end`
+    SelfParameter,
+    /// The control variable of a numeric `for` loop, such as `i` in `for i = 1, 10 do end`
+    NumericFor,
+    /// A name declared by a generic `for` loop, such as `key` in `for key, value in pairs(t) do end`
+    GenericFor,
+}
+
+/// Whether a [`ReferenceInfo`](struct.ReferenceInfo.html) gets the value of its variable or sets it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReferenceKind {
+    /// The value of the variable is used, including when it is indexed to set a field, such as `t` in `t.x = 1`
+    Read,
+    /// The variable is set, such as `x` in `x = 1` or `function x() end`, or where it is declared,
+    /// such as a `local` or the variable of a `for` loop, which are set when they're declared
+    Write,
+}
+
+/// A name that refers to a [`Variable`](struct.Variable.html)
+#[derive(Clone, Debug)]
+pub struct ReferenceInfo<'ast> {
+    token: TokenReference<'ast>,
+    kind: ReferenceKind,
+    scope: ScopeId,
+    function_depth: usize,
+    declaration: bool,
+}
+
+impl<'ast> ReferenceInfo<'ast> {
+    /// The name that refers to the variable
+    pub fn token(&self) -> &TokenReference<'ast> {
+        &self.token
+    }
+
+    /// Whether the name gets the value of the variable or sets it
+    pub fn kind(&self) -> ReferenceKind {
+        self.kind
+    }
+
+    /// The innermost scope the name is in
+    pub fn scope(&self) -> ScopeId {
+        self.scope
+    }
+
+    /// How many functions the name is in, 0 for names outside of every function.
+    /// The parameters of a function are in it.
+    pub fn function_depth(&self) -> usize {
+        self.function_depth
+    }
+
+    /// Whether the name is the one the variable is declared with, which is always a write
+    pub fn is_declaration(&self) -> bool {
+        self.declaration
+    }
+}
+
+/// A local variable, with where it was declared and every name that refers to it
+#[derive(Clone, Debug)]
+pub struct Variable<'ast> {
+    id: VariableId,
+    name: String,
+    kind: VariableKind,
+    scope: ScopeId,
+    declaration: Option<TokenReference<'ast>>,
+    references: Vec<ReferenceInfo<'ast>>,
+}
+
+impl<'ast> Variable<'ast> {
+    /// The id of the variable
+    pub fn id(&self) -> VariableId {
+        self.id
+    }
+
+    /// The name of the variable
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// How the variable was declared
+    pub fn kind(&self) -> VariableKind {
+        self.kind
+    }
+
+    /// The scope the variable was declared in
+    pub fn scope(&self) -> ScopeId {
+        self.scope
+    }
+
+    /// The token the variable was declared with.
+    /// Returns `None` for the implicit `self` parameter of methods, such as `function x:y() end`.
+    pub fn declaration(&self) -> Option<&TokenReference<'ast>> {
+        self.declaration.as_ref()
+    }
+
+    /// Where the variable was declared, `None` for the implicit `self` parameter of methods
+    pub fn declaration_position(&self) -> Option<Position> {
+        Some(self.declaration.as_ref()?.start_position())
+    }
+
+    /// Every name that refers to the variable, in the order they appear in the code,
+    /// starting with the name it was declared with if it has one
+    pub fn references(&self) -> &[ReferenceInfo<'ast>] {
+        &self.references
+    }
+
+    /// Where the names that refer to the variable are, in the order they appear in the code,
+    /// starting with where it was declared if it has a name there
+    pub fn reference_positions(&self) -> Vec<Position> {
+        self.references
+            .iter()
+            .map(|reference| reference.token.start_position())
+            .collect()
+    }
+}
+
+/// A scope of the code, such as the body of a function or of a loop
+#[derive(Clone, Debug)]
+pub struct Scope {
+    id: ScopeId,
+    kind: ScopeKind,
+    parent: Option<ScopeId>,
+    function: Option<FunctionId>,
+    variables: Vec<VariableId>,
+}
+
+impl Scope {
+    /// The id of the scope
+    pub fn id(&self) -> ScopeId {
+        self.id
+    }
+
+    /// The kind of construct that opened the scope
+    pub fn kind(&self) -> ScopeKind {
+        self.kind
+    }
+
+    /// The scope this one is in, `None` for the scope of the whole file
+    pub fn parent(&self) -> Option<ScopeId> {
+        self.parent
+    }
+
+    /// The function the scope is the body of, for scopes of the kind [`ScopeKind::Function`](../../visitors/enum.ScopeKind.html#variant.Function)
+    pub fn function(&self) -> Option<FunctionId> {
+        self.function
+    }
+
+    /// The variables declared in the scope, in the order they were declared
+    pub fn variables(&self) -> &[VariableId] {
+        &self.variables
+    }
+}
+
+/// The scopes of an [`Ast`](../../ast/struct.Ast.html), with every local declared in them and what every name refers to.
+///
+/// Locals are declared the same way [`ScopedVisitor`](../../visitors/trait.ScopedVisitor.html) declares them,
+/// which is the way Lua does, so a local declared again with the same name shadows the one before it from then on,
+/// `local x = x` refers to the `x` from before, a `local function` can call itself,
+/// and the condition of a `repeat` loop can see the locals of its body.
+///
+/// ```rust
+/// # use full_moon::analysis::scopes::{Resolution, ScopeAnalysis};
+/// let ast = full_moon::parse("local x = 1\nlocal x = x + 1\nprint(x)").unwrap();
+/// let analysis = ScopeAnalysis::new(&ast);
+///
+/// let tokens: Vec<_> = ast.iter_tokens().filter(|token| token.to_string() == "x").collect();
+/// let first = analysis.resolve(tokens[0]);
+/// let second = analysis.resolve(tokens[1]);
+/// assert_ne!(first, second);
+/// // The `x` in `x + 1` is the first one, and the one passed to `print` is the second
+/// assert_eq!(analysis.resolve(tokens[2]), first);
+/// assert_eq!(analysis.resolve(tokens[3]), second);
+///
+/// let print = ast.iter_tokens().find(|token| token.to_string() == "print").unwrap();
+/// assert_eq!(analysis.resolve(print), Resolution::Global);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ScopeAnalysis<'ast> {
+    scopes: Vec<Scope>,
+    variables: Vec<Variable<'ast>>,
+    globals: Vec<TokenReference<'ast>>,
+    // The functions by the opening parenthesis of their parameters
+    functions: HashMap<usize, FunctionId>,
+    // What the tokens that are names refer to, by the token types they point to, which every clone of a token shares
+    resolutions: HashMap<usize, Resolution>,
+}
+
+impl<'ast> ScopeAnalysis<'ast> {
+    /// Finds the scopes of the Ast and what every name in it refers to
+    pub fn new(ast: &Ast<'ast>) -> Self {
+        let mut builder = Builder::default();
+        builder.visit_ast_scoped(ast);
+        builder.analysis
+    }
+
+    /// What the token refers to, if it is a name in the Ast, including the names locals are declared with.
+    /// Takes a [`TokenReference`](../../tokenizer/enum.TokenReference.html) as well, since it dereferences to its token.
+    pub fn resolve(&self, token: &Token<'ast>) -> Resolution {
+        self.resolutions
+            .get(&key(token))
+            .copied()
+            .unwrap_or(Resolution::NotAVariable)
+    }
+
+    /// The variable with the id
+    pub fn variable(&self, id: VariableId) -> &Variable<'ast> {
+        &self.variables[id.0]
+    }
+
+    /// Every name that refers to the variable with the id, the same as [`Variable::references`](struct.Variable.html#method.references),
+    /// such as to rename it or to highlight where it is used.
+    ///
+    /// ```rust
+    /// # use full_moon::analysis::scopes::{ReferenceKind, Resolution, ScopeAnalysis};
+    /// let ast = full_moon::parse("local t = {}\nt.x = 1\nt = nil").unwrap();
+    /// let analysis = ScopeAnalysis::new(&ast);
+    /// let t = ast.iter_tokens().find(|token| token.to_string() == "t").unwrap();
+    ///
+    /// let id = match analysis.resolve(t) {
+    ///     Resolution::Local(id) => id,
+    ///     _ => unreachable!(),
+    /// };
+    /// let kinds: Vec<_> = analysis.references(id).iter().map(|reference| reference.kind()).collect();
+    /// assert_eq!(kinds, vec![ReferenceKind::Write, ReferenceKind::Read, ReferenceKind::Write]);
+    /// ```
+    pub fn references(&self, id: VariableId) -> &[ReferenceInfo<'ast>] {
+        &self.variables[id.0].references
+    }
+
+    /// Every variable in the Ast, in the order they were declared
+    pub fn variables(&self) -> impl Iterator<Item = &Variable<'ast>> {
+        self.variables.iter()
+    }
+
+    /// The scope with the id
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+
+    /// Every scope in the Ast, in the order they start in, the first being the scope of the whole file
+    pub fn scopes(&self) -> impl Iterator<Item = &Scope> {
+        self.scopes.iter()
+    }
+
+    /// The id of the function with the body, the same for every clone of it,
+    /// or `None` if the body isn't in the Ast
+    pub fn function_id(&self, body: &ast::FunctionBody<'ast>) -> Option<FunctionId> {
+        let (open, _) = body.parameters_parantheses().tokens();
+        self.functions.get(&key(open)).copied()
+    }
+
+    /// The names that don't refer to a local, in the order they appear in the code
+    pub fn globals(&self) -> &[TokenReference<'ast>] {
+        &self.globals
+    }
+}
+
+// Identifies a token by what its clones share, so the same token in the Ast and in the analysis has the same key
+pub(crate) fn key(token: &Token) -> usize {
+    Arc::as_ptr(&token.token_type) as *const () as usize
+}
+
+#[derive(Default)]
+struct Builder<'ast> {
+    scopes: Scopes<'ast>,
+    // The scopes that are open, from the outermost to the innermost, the same as `scopes`
+    open: Vec<ScopeId>,
+    // The implicit `self` parameters, by the scope of their method, since they have no token to find them by
+    self_parameters: HashMap<ScopeId, VariableId>,
+    // How many of the open scopes are functions
+    function_depth: usize,
+    // The names that are about to be visited as what an assignment sets
+    writes: HashSet<usize>,
+    // The opening parenthesis of the parameters of the function whose scope is about to be entered
+    function: Option<usize>,
+    analysis: ScopeAnalysis<'ast>,
+}
+
+impl<'ast> Builder<'ast> {
+    fn declare(&mut self, token: Option<&TokenReference<'ast>>, name: &str, kind: VariableKind) {
+        let scope = *self
+            .open
+            .last()
+            .expect("locals should be declared in a scope");
+        let id = VariableId(self.analysis.variables.len());
+
+        self.analysis.variables.push(Variable {
+            id,
+            name: name.to_owned(),
+            kind,
+            scope,
+            declaration: token.cloned(),
+            references: token
+                .map(|token| ReferenceInfo {
+                    token: token.clone(),
+                    kind: ReferenceKind::Write,
+                    scope,
+                    function_depth: self.function_depth,
+                    declaration: true,
+                })
+                .into_iter()
+                .collect(),
+        });
+        self.analysis.scopes[scope.0].variables.push(id);
+
+        match token {
+            Some(token) => {
+                self.analysis
+                    .resolutions
+                    .insert(key(token), Resolution::Local(id));
+            }
+
+            None => {
+                self.self_parameters.insert(scope, id);
+            }
+        }
+    }
+
+    fn reference(&mut self, token: &TokenReference<'ast>) {
+        let kind = if self.writes.remove(&key(token)) {
+            ReferenceKind::Write
+        } else {
+            ReferenceKind::Read
+        };
+
+        let name = token.to_string();
+        let found = self
+            .scopes
+            .iter()
+            .zip(self.open.iter().rev())
+            .find_map(|(scope, id)| Some((scope.lookup(&name)?, *id)));
+
+        let id = match found {
+            Some((local, scope)) => match local.token() {
+                Some(declaration) => match self.analysis.resolutions.get(&key(declaration)) {
+                    Some(Resolution::Local(id)) => Some(*id),
+                    _ => None,
+                },
+
+                None => self.self_parameters.get(&scope).copied(),
+            },
+
+            None => None,
+        };
+
+        let resolution = match id {
+            Some(id) => {
+                self.analysis.variables[id.0]
+                    .references
+                    .push(ReferenceInfo {
+                        token: token.clone(),
+                        kind,
+                        scope: *self.open.last().expect("names should be in a scope"),
+                        function_depth: self.function_depth,
+                        declaration: false,
+                    });
+                Resolution::Local(id)
+            }
+
+            None => {
+                self.analysis.globals.push(token.clone());
+                Resolution::Global
+            }
+        };
+
+        self.analysis.resolutions.insert(key(token), resolution);
+    }
+}
+
+impl<'ast> Visitor<'ast> for Builder<'ast> {
+    // The names are declared once the statement is visited, after its expressions were
+    fn visit_local_assignment(&mut self, local_assignment: &ast::LocalAssignment<'ast>) {
+        for name in local_assignment.name_list().iter() {
+            self.declare(Some(name), &name.to_string(), VariableKind::Local);
+        }
+    }
+
+    fn visit_local_function(&mut self, local_function: &ast::LocalFunction<'ast>) {
+        let name = local_function.name();
+        self.declare(Some(name), &name.to_string(), VariableKind::LocalFunction);
+    }
+
+    fn visit_function_body(&mut self, body: &ast::FunctionBody<'ast>) {
+        let (open, _) = body.parameters_parantheses().tokens();
+        self.function = Some(key(open));
+    }
+
+    // The names an assignment sets are visited after it
+    fn visit_assignment(&mut self, assignment: &ast::Assignment<'ast>) {
+        for var in assignment.var_list().iter() {
+            if let ast::Var::Name(name) = var {
+                self.writes.insert(key(name));
+            }
+        }
+    }
+
+    // Only the first name is a variable, such as `x` in `function x.y:z() end`, which is only set by `function x() end`
+    fn visit_function_name(&mut self, function_name: &ast::FunctionName<'ast>) {
+        let mut names = function_name.names().iter();
+        if let Some(name) = names.next() {
+            if names.next().is_none() && function_name.method_name().is_none() {
+                self.writes.insert(key(name));
+            }
+
+            self.reference(name);
+        }
+    }
+
+    fn visit_var(&mut self, var: &ast::Var<'ast>) {
+        if let ast::Var::Name(name) = var {
+            self.reference(name);
+        }
+    }
+
+    fn visit_prefix(&mut self, prefix: &ast::Prefix<'ast>) {
+        if let ast::Prefix::Name(name) = prefix {
+            self.reference(name);
+        }
+    }
+}
+
+impl<'ast> ScopedVisitor<'ast> for Builder<'ast> {
+    fn scopes_mut(&mut self) -> &mut Scopes<'ast> {
+        &mut self.scopes
+    }
+
+    // The locals a scope starts with are the parameters and loop variables of the construct that opened it
+    fn enter_scope(&mut self, kind: ScopeKind) {
+        let id = ScopeId(self.analysis.scopes.len());
+        let mut function = None;
+        if kind == ScopeKind::Function {
+            self.function_depth += 1;
+            if let Some(open) = self.function.take() {
+                let id = FunctionId(self.analysis.functions.len());
+                self.analysis.functions.insert(open, id);
+                function = Some(id);
+            }
+        }
+
+        self.analysis.scopes.push(Scope {
+            id,
+            kind,
+            parent: self.open.last().copied(),
+            function,
+            variables: Vec::new(),
+        });
+        self.open.push(id);
+
+        let locals = self
+            .scopes
+            .current_scope()
+            .map(|scope| scope.locals().to_vec())
+            .unwrap_or_default();
+
+        for local in &locals {
+            let variable_kind = match (kind, local.token()) {
+                (ScopeKind::Function, None) => VariableKind::SelfParameter,
+                (ScopeKind::NumericFor, _) => VariableKind::NumericFor,
+                (ScopeKind::GenericFor, _) => VariableKind::GenericFor,
+                _ => VariableKind::Parameter,
+            };
+
+            self.declare(local.token(), local.name(), variable_kind);
+        }
+    }
+
+    fn exit_scope(&mut self) {
+        if let Some(id) = self.open.pop() {
+            if self.analysis.scopes[id.0].kind == ScopeKind::Function {
+                self.function_depth -= 1;
+            }
+        }
+    }
+}
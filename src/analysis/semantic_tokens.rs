@@ -0,0 +1,329 @@
+use super::scopes::{
+    self, FunctionId, ReferenceInfo, ReferenceKind, Resolution, ScopeAnalysis, ScopeId,
+    VariableKind,
+};
+use crate::{
+    ast::{Assignment, Ast, Field, FunctionName, Index, MethodCall, Suffix, Var},
+    tokenizer::{Position, Token, TokenKind, TokenReference, TokenType},
+    visitors::Visitor,
+};
+use std::collections::{HashMap, HashSet};
+
+/// The options for [`semantic_tokens_with`](fn.semantic_tokens_with.html).
+/// Use [`SemanticTokensConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SemanticTokensConfig {
+    /// Whether to include keywords, strings, numbers, and comments too, which are classified by their tokens alone,
+    /// so that they don't need another pass over the tokens. False by default, so only names are included.
+    pub lexical: bool,
+}
+
+/// What a [`SemanticToken`](struct.SemanticToken.html) is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SemanticTokenKind {
+    /// A local of the function it's used in, such as `x` in `local x = 1`, or the variable of a `for` loop
+    Local,
+    /// A parameter of the function it's used in, including `self`
+    Parameter,
+    /// A local or parameter of a function outside of the one it's used in, such as `x` in
+    /// `local x; function f() return x end`
+    Upvalue,
+    /// A name that isn't a local, such as `print`
+    Global,
+    /// The name of a field, such as `y` in `x.y`, `{ y = 1 }`, or `function x.y.z() end`
+    Field,
+    /// The name of a method, such as `y` in `x:y()` or `function x:y() end`
+    Method,
+    /// The name a function is declared with, such as `f` in `local function f() end` or `function M.f() end`,
+    /// along with the rest of the uses of a local function
+    Function,
+    /// A keyword, such as `local` or `nil`, when [`lexical`](struct.SemanticTokensConfig.html#structfield.lexical) is on
+    Keyword,
+    /// A string, when [`lexical`](struct.SemanticTokensConfig.html#structfield.lexical) is on
+    String,
+    /// A number, when [`lexical`](struct.SemanticTokensConfig.html#structfield.lexical) is on
+    Number,
+    /// A comment, when [`lexical`](struct.SemanticTokensConfig.html#structfield.lexical) is on
+    Comment,
+}
+
+/// A token classified for highlighting, from [`semantic_tokens`](fn.semantic_tokens.html)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SemanticToken {
+    range: (Position, Position),
+    kind: SemanticTokenKind,
+    declaration: bool,
+    readonly: bool,
+    modification: bool,
+}
+
+impl SemanticToken {
+    /// The start and end of the token
+    pub fn range(&self) -> (Position, Position) {
+        self.range
+    }
+
+    /// What the token is
+    pub fn kind(&self) -> SemanticTokenKind {
+        self.kind
+    }
+
+    /// Whether the token declares what it names, such as a local, a parameter, the name of a function, or a field
+    /// of a table constructor
+    pub fn is_declaration(&self) -> bool {
+        self.declaration
+    }
+
+    /// Whether what the token names shouldn't be set, which is true for the variables of `for` loops
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Whether the token is set by an assignment, such as `x` and `y` in `x, t.y = 1, 2`
+    pub fn is_modification(&self) -> bool {
+        self.modification
+    }
+}
+
+/// Every name of the Ast classified by what it refers to, in the order they appear in the code, using
+/// [`SemanticTokensConfig::default()`](struct.SemanticTokensConfig.html), for semantic highlighting in editors.
+/// See [`semantic_tokens_with`](fn.semantic_tokens_with.html) for the options.
+///
+/// Names are classified with a [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html), so a local is an upvalue where
+/// it's used in a function inside of the one it's declared in, and names that aren't variables are classified by
+/// where they are, such as the names of fields and methods. Use
+/// [`lsp::semantic_tokens_data`](../lsp/fn.semantic_tokens_data.html) to encode them for the Language Server Protocol.
+///
+/// ```rust
+/// # use full_moon::analysis::{semantic_tokens, SemanticTokenKind};
+/// let ast = full_moon::parse("local t = {}\nfunction t:f(x) print(self, x, t.y) end").unwrap();
+/// let kinds: Vec<SemanticTokenKind> = semantic_tokens(&ast).iter().map(|token| token.kind()).collect();
+///
+/// assert_eq!(kinds, [
+///     SemanticTokenKind::Local,
+///     SemanticTokenKind::Local,
+///     SemanticTokenKind::Method,
+///     SemanticTokenKind::Parameter,
+///     SemanticTokenKind::Global,
+///     SemanticTokenKind::Parameter,
+///     SemanticTokenKind::Parameter,
+///     SemanticTokenKind::Upvalue,
+///     SemanticTokenKind::Field,
+/// ]);
+/// ```
+pub fn semantic_tokens(ast: &Ast) -> Vec<SemanticToken> {
+    semantic_tokens_with(ast, &SemanticTokensConfig::default())
+}
+
+/// Every name of the Ast classified like [`semantic_tokens`](fn.semantic_tokens.html), along with the keywords,
+/// strings, numbers, and comments if [`lexical`](struct.SemanticTokensConfig.html#structfield.lexical) is on.
+///
+/// ```rust
+/// # use full_moon::analysis::{semantic_tokens_with, SemanticTokenKind, SemanticTokensConfig};
+/// let config = SemanticTokensConfig { lexical: true };
+/// let ast = full_moon::parse("for i = 1, 2 do end -- loop").unwrap();
+/// let tokens = semantic_tokens_with(&ast, &config);
+/// let kinds: Vec<SemanticTokenKind> = tokens.iter().map(|token| token.kind()).collect();
+///
+/// assert_eq!(kinds, [
+///     SemanticTokenKind::Keyword,
+///     SemanticTokenKind::Local,
+///     SemanticTokenKind::Number,
+///     SemanticTokenKind::Number,
+///     SemanticTokenKind::Keyword,
+///     SemanticTokenKind::Keyword,
+///     SemanticTokenKind::Comment,
+/// ]);
+/// assert!(tokens[1].is_declaration() && tokens[1].is_readonly());
+/// ```
+pub fn semantic_tokens_with(ast: &Ast, config: &SemanticTokensConfig) -> Vec<SemanticToken> {
+    let analysis = ScopeAnalysis::new(ast);
+    let references: HashMap<usize, &ReferenceInfo> = analysis
+        .variables()
+        .flat_map(|variable| variable.references())
+        .map(|reference| (scopes::key(reference.token()), reference))
+        .collect();
+
+    let mut names = Names::default();
+    names.visit_ast(ast);
+
+    let mut tokens = Vec::new();
+    for token in ast.iter_tokens() {
+        let key = scopes::key(token);
+        let reference = references.get(&key);
+        let mut semantic = SemanticToken {
+            range: (token.start_position(), token.end_position()),
+            kind: SemanticTokenKind::Global,
+            declaration: reference.is_some_and(|reference| reference.is_declaration()),
+            readonly: false,
+            modification: reference.is_some_and(|reference| {
+                reference.kind() == ReferenceKind::Write && !reference.is_declaration()
+            }),
+        };
+
+        semantic.kind = match token.token_kind() {
+            TokenKind::Identifier => match (names.names.get(&key), analysis.resolve(token)) {
+                (Some(name), _) => {
+                    semantic.declaration |= name.declaration;
+                    semantic.modification |= name.modification;
+                    name.kind
+                }
+
+                (None, Resolution::Local(id)) => {
+                    let variable = analysis.variable(id);
+                    semantic.readonly = matches!(
+                        variable.kind(),
+                        VariableKind::NumericFor | VariableKind::GenericFor
+                    );
+
+                    // Used in a function inside of the one it's declared in
+                    let upvalue = reference.is_some_and(|reference| {
+                        function(&analysis, reference.scope())
+                            != function(&analysis, variable.scope())
+                    });
+
+                    match variable.kind() {
+                        VariableKind::LocalFunction => SemanticTokenKind::Function,
+                        _ if upvalue => SemanticTokenKind::Upvalue,
+                        VariableKind::Parameter | VariableKind::SelfParameter => {
+                            SemanticTokenKind::Parameter
+                        }
+                        _ => SemanticTokenKind::Local,
+                    }
+                }
+
+                (None, Resolution::Global) => {
+                    semantic.modification |= names.assigned.contains(&key);
+                    SemanticTokenKind::Global
+                }
+
+                (None, Resolution::NotAVariable) => continue,
+            },
+
+            _ if !config.lexical => continue,
+            TokenKind::Symbol if is_keyword(token) => SemanticTokenKind::Keyword,
+            TokenKind::StringLiteral => SemanticTokenKind::String,
+            TokenKind::Number => SemanticTokenKind::Number,
+            TokenKind::SingleLineComment | TokenKind::MultiLineComment => {
+                SemanticTokenKind::Comment
+            }
+            _ => continue,
+        };
+
+        tokens.push(semantic);
+    }
+
+    tokens
+}
+
+// The function a scope is in, `None` for the scopes outside of every function
+fn function(analysis: &ScopeAnalysis, scope: ScopeId) -> Option<FunctionId> {
+    let mut scope = analysis.scope(scope);
+    loop {
+        match (scope.function(), scope.parent()) {
+            (Some(function), _) => return Some(function),
+            (None, Some(parent)) => scope = analysis.scope(parent),
+            (None, None) => return None,
+        }
+    }
+}
+
+fn is_keyword(token: &Token) -> bool {
+    match &*token.token_type() {
+        TokenType::Symbol { symbol } => symbol
+            .to_string()
+            .starts_with(|character: char| character.is_ascii_alphabetic()),
+        _ => false,
+    }
+}
+
+struct Name {
+    kind: SemanticTokenKind,
+    declaration: bool,
+    modification: bool,
+}
+
+// The names that are classified by where they are, rather than by what they refer to,
+// and the names that are assigned to
+#[derive(Default)]
+struct Names {
+    names: HashMap<usize, Name>,
+    assigned: HashSet<usize>,
+}
+
+impl Names {
+    fn insert(&mut self, token: &TokenReference, kind: SemanticTokenKind, declaration: bool) {
+        self.names.insert(
+            scopes::key(token),
+            Name {
+                kind,
+                declaration,
+                modification: false,
+            },
+        );
+    }
+}
+
+impl<'ast> Visitor<'ast> for Names {
+    fn visit_assignment(&mut self, assignment: &Assignment<'ast>) {
+        for var in assignment.var_list() {
+            match var {
+                Var::Name(name) => {
+                    self.assigned.insert(scopes::key(name));
+                }
+
+                Var::Expression(var_expression) => {
+                    if let Some(Suffix::Index(Index::Dot { name, .. })) =
+                        var_expression.iter_suffixes().last()
+                    {
+                        self.names.insert(
+                            scopes::key(name),
+                            Name {
+                                kind: SemanticTokenKind::Field,
+                                declaration: false,
+                                modification: true,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn visit_field(&mut self, field: &Field<'ast>) {
+        if let Field::NameKey { key, .. } = field {
+            self.insert(key, SemanticTokenKind::Field, true);
+        }
+    }
+
+    // Field names of assignments are visited after the assignment, so they aren't replaced
+    fn visit_index(&mut self, index: &Index<'ast>) {
+        if let Index::Dot { name, .. } = index {
+            self.names.entry(scopes::key(name)).or_insert(Name {
+                kind: SemanticTokenKind::Field,
+                declaration: false,
+                modification: false,
+            });
+        }
+    }
+
+    fn visit_method_call(&mut self, method_call: &MethodCall<'ast>) {
+        self.insert(method_call.name(), SemanticTokenKind::Method, false);
+    }
+
+    fn visit_function_name(&mut self, function_name: &FunctionName<'ast>) {
+        let names: Vec<_> = function_name.names().iter().collect();
+        for name in names.iter().skip(1) {
+            self.insert(name, SemanticTokenKind::Field, false);
+        }
+
+        match function_name.method_name() {
+            Some(method) => self.insert(method, SemanticTokenKind::Method, true),
+            None => {
+                if let Some(name) = names.last() {
+                    self.insert(name, SemanticTokenKind::Function, true);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use super::scopes::{ScopeAnalysis, Variable, VariableId, VariableKind};
+use crate::{ast::Ast, tokenizer::Position, visitors::ScopeKind};
+
+/// How a [`ShadowInfo`](struct.ShadowInfo.html) shadows the local before it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShadowKind {
+    /// The local is declared again in the same scope, such as `local x = 1; local x = 2`,
+    /// or as a parameter with the same name as another one, or as a local in a function with a parameter's name
+    Redeclaration,
+    /// The variable of a `for` loop has the name of a local outside of the loop, such as `i` in
+    /// `local i = 0; for i = 1, 10 do end`
+    LoopVariable,
+    /// The local is in a scope inside of the scope of the other one, both in the same function,
+    /// such as `local x; do local x end`
+    SameFunction,
+    /// The local is in a function inside of the scope of the other one, which the function could use instead,
+    /// such as `local x; function f(x) end`
+    Closure,
+}
+
+/// A local with the name of a local that was visible where it was declared, from [`shadowing`](fn.shadowing.html)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShadowInfo {
+    variable: VariableId,
+    name: String,
+    position: Position,
+    shadowed: VariableId,
+    shadowed_kind: VariableKind,
+    shadowed_position: Option<Position>,
+    kind: ShadowKind,
+}
+
+impl ShadowInfo {
+    /// The local that shadows the other one
+    pub fn variable(&self) -> VariableId {
+        self.variable
+    }
+
+    /// The name of both locals
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Where the local was declared
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// The local that is shadowed, which can't be used by its name where the other one is visible
+    pub fn shadowed(&self) -> VariableId {
+        self.shadowed
+    }
+
+    /// How the shadowed local was declared, such as a parameter
+    pub fn shadowed_kind(&self) -> VariableKind {
+        self.shadowed_kind
+    }
+
+    /// Where the shadowed local was declared, `None` for the implicit `self` parameter of methods
+    pub fn shadowed_position(&self) -> Option<Position> {
+        self.shadowed_position
+    }
+
+    /// Whether the locals are in the same scope, the same function, or different functions
+    pub fn kind(&self) -> ShadowKind {
+        self.kind
+    }
+}
+
+/// Every local of the Ast declared with the name of a local that is visible where it is declared,
+/// including parameters and the variables of loops, in the order they're declared. The ids of the locals are the
+/// same as the ones a [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html) of the same Ast gives.
+///
+/// Locals are visible the way [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html) finds them, which is the way
+/// Lua does, so a local declared after a function doesn't shadow a local in it, the parameters of a function are
+/// in the same scope as its body, and so are the variables of a `for` loop. The implicit `self` parameter
+/// of methods can be shadowed, but isn't reported as shadowing anything, since it has no name in the code.
+///
+/// ```rust
+/// # use full_moon::analysis::{shadowing, ShadowKind};
+/// let ast = full_moon::parse("local x = 1\nlocal x = 2\nfor x = 1, 10 do\n  local f = function(x) end\nend").unwrap();
+/// let kinds: Vec<_> = shadowing(&ast)
+///     .iter()
+///     .map(|shadow| (shadow.name().to_owned(), shadow.position().line(), shadow.kind()))
+///     .collect();
+///
+/// assert_eq!(
+///     kinds,
+///     vec![
+///         ("x".to_owned(), 2, ShadowKind::Redeclaration),
+///         ("x".to_owned(), 3, ShadowKind::LoopVariable),
+///         ("x".to_owned(), 4, ShadowKind::Closure),
+///     ]
+/// );
+/// ```
+pub fn shadowing(ast: &Ast) -> Vec<ShadowInfo> {
+    let analysis = ScopeAnalysis::new(ast);
+
+    analysis
+        .variables()
+        .filter_map(|variable| {
+            let position = variable.declaration_position()?;
+            let (shadowed, kind) = shadowed(&analysis, variable)?;
+
+            Some(ShadowInfo {
+                variable: variable.id(),
+                name: variable.name().to_owned(),
+                position,
+                shadowed: shadowed.id(),
+                shadowed_kind: shadowed.kind(),
+                shadowed_position: shadowed.declaration_position(),
+                kind,
+            })
+        })
+        .collect()
+}
+
+// The local the variable shadows, which is the latest one with its name declared before it in the scopes it is in
+fn shadowed<'a, 'ast>(
+    analysis: &'a ScopeAnalysis<'ast>,
+    variable: &Variable<'ast>,
+) -> Option<(&'a Variable<'ast>, ShadowKind)> {
+    let loop_variable = matches!(
+        variable.kind(),
+        VariableKind::NumericFor | VariableKind::GenericFor
+    );
+    let mut closure = false;
+    let mut scope = analysis.scope(variable.scope());
+
+    loop {
+        // Ids are in the order locals were declared, so only the locals before the variable were visible to it
+        let found = scope
+            .variables()
+            .iter()
+            .rev()
+            .filter(|id| **id < variable.id())
+            .map(|id| analysis.variable(*id))
+            .find(|other| other.name() == variable.name());
+
+        if let Some(other) = found {
+            let kind = if scope.id() == variable.scope() {
+                ShadowKind::Redeclaration
+            } else if loop_variable {
+                ShadowKind::LoopVariable
+            } else if closure {
+                ShadowKind::Closure
+            } else {
+                ShadowKind::SameFunction
+            };
+
+            return Some((other, kind));
+        }
+
+        closure |= scope.kind() == ScopeKind::Function;
+        scope = analysis.scope(scope.parent()?);
+    }
+}
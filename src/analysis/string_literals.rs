@@ -0,0 +1,338 @@
+use super::scopes;
+use crate::{
+    ast::{
+        self,
+        simplified::{self, SBinOp},
+        Ast, BinOpRhs, Call, Expression, Field, FunctionArgs, Prefix, Suffix, Value,
+    },
+    node::{Node, TriviaBehavior},
+    tokenizer::{Position, Token, TokenReference},
+    visitors::{Visit, Visitor},
+};
+use std::collections::HashMap;
+
+/// Where a [`StringLiteralInfo`](struct.StringLiteralInfo.html) is used
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StringContext {
+    /// An argument of a call, such as `"hello"` in `translate("hello")`, `log "hello"`, or `ui:label(x, "hello")`
+    CallArgument {
+        /// The code of the function that is called, without the whitespace around it,
+        /// such as `translate`, `M.log`, or `ui:label` for a method
+        function: String,
+        /// Which argument the string is, starting from 0
+        index: usize,
+    },
+    /// The value of a field of a table constructor, such as `"hello"` in `{ title = "hello" }`
+    TableValue {
+        /// The key of the field
+        key: TableKey,
+    },
+    /// What is assigned to a variable, such as `"hello"` in `local title = "hello"` or `t.title = "hello"`
+    Assignment {
+        /// The code of the variable, without the whitespace around it, such as `title` or `t.title`
+        target: String,
+    },
+    /// An operand of `..`, with where the whole concatenation is used, such as
+    /// `Concatenation(CallArgument { function: "print", index: 0 })` for `"hello"` in `print("hello " .. name)`
+    Concatenation(Box<StringContext>),
+    /// Anywhere else, such as a comparison or the key of an index
+    Other,
+}
+
+/// The key of a field a string is the value of, from [`StringContext::TableValue`](enum.StringContext.html#variant.TableValue)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TableKey {
+    /// A field with no key, with which of those fields it is starting from 1, the way Lua numbers them,
+    /// such as 2 for `"b"` in `{ "a", x = 1, "b" }`
+    Index(usize),
+    /// A name, such as `title` in `{ title = "hello" }`
+    Name(String),
+    /// The code of the expression in brackets, without the whitespace around it, such as `"title"` in `{ ["title"] = "hello" }`
+    Expression(String),
+}
+
+/// A string literal, from [`string_literals`](fn.string_literals.html)
+#[derive(Clone, Debug)]
+pub struct StringLiteralInfo<'ast> {
+    token: TokenReference<'ast>,
+    value: Option<String>,
+    range: (Position, Position),
+    context: StringContext,
+}
+
+impl<'ast> StringLiteralInfo<'ast> {
+    /// The token of the string
+    pub fn token(&self) -> &TokenReference<'ast> {
+        &self.token
+    }
+
+    /// The value of the string, with its escapes decoded and the bytes that aren't UTF-8 replaced with `�`,
+    /// or None if it has an escape that is too large, which only [`parse_fallible`](../fn.parse_fallible.html) allows
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// The code of the string, with its quotes or brackets, such as `"hello\n"` or `[[hello]]`
+    pub fn raw(&self) -> String {
+        self.token.to_string()
+    }
+
+    /// The start and end of the string, from its opening quote or bracket to its closing one
+    pub fn range(&self) -> (Position, Position) {
+        self.range
+    }
+
+    /// Where the string is used, such as an argument of a call or the value of a field
+    pub fn context(&self) -> &StringContext {
+        &self.context
+    }
+}
+
+/// Every string literal in the Ast, in the order they appear in the code, including long strings such as `[[hello]]`
+/// and strings called with such as `f "hello"`, with where they are used, such as for finding the strings to translate.
+///
+/// The context of a string in parentheses is the context of the parentheses, and the context of a string used with `..`
+/// is a [`StringContext::Concatenation`](enum.StringContext.html#variant.Concatenation) with the context of the concatenation.
+///
+/// ```rust
+/// # use full_moon::analysis::{string_literals, StringContext, TableKey};
+/// let ast = full_moon::parse("local title = [[Welcome]]\nprint(translate(\"Hi \" .. name), { label = 'OK' })").unwrap();
+/// let strings = string_literals(&ast);
+///
+/// let values: Vec<_> = strings.iter().map(|string| string.value().unwrap()).collect();
+/// assert_eq!(values, vec!["Welcome", "Hi ", "OK"]);
+/// assert_eq!(strings[0].raw(), "[[Welcome]]");
+///
+/// assert_eq!(strings[0].context(), &StringContext::Assignment { target: "title".to_owned() });
+/// assert_eq!(
+///     strings[1].context(),
+///     &StringContext::Concatenation(Box::new(StringContext::CallArgument {
+///         function: "translate".to_owned(),
+///         index: 0,
+///     }))
+/// );
+/// assert_eq!(strings[2].context(), &StringContext::TableValue { key: TableKey::Name("label".to_owned()) });
+/// ```
+pub fn string_literals<'ast>(ast: &Ast<'ast>) -> Vec<StringLiteralInfo<'ast>> {
+    // Only the nodes are visited, since visiting the Ast would visit each string once more for its tokens
+    let mut visitor = StringLiterals::default();
+    ast.nodes().visit(&mut visitor);
+
+    let StringLiterals {
+        mut strings,
+        mut contexts,
+    } = visitor;
+    for string in &mut strings {
+        if let Some(context) = contexts.remove(&scopes::key(&string.token)) {
+            string.context = context;
+        }
+    }
+
+    strings.sort_by_key(|string| string.range.0);
+    strings
+}
+
+#[derive(Default)]
+struct StringLiterals<'ast> {
+    strings: Vec<StringLiteralInfo<'ast>>,
+    // The contexts of the strings that aren't used anywhere else, by their tokens, found before the strings are visited
+    contexts: HashMap<usize, StringContext>,
+}
+
+// An operand of a chain of binary operators, such as `a` in `-a + b`
+enum Operand<'a, 'ast> {
+    String(&'a TokenReference<'ast>),
+    Parentheses(&'a Expression<'ast>),
+    // Anything else, or a string or parentheses with a unary operator, which aren't used as they are
+    Other,
+}
+
+impl<'ast> StringLiterals<'ast> {
+    // Finds the contexts of the strings that are the expression or its operands
+    fn classify(&mut self, expression: &Expression<'ast>, context: &StringContext) {
+        let mut operands = Vec::new();
+        let mut operators = Vec::new();
+        flatten(expression, false, &mut operands, &mut operators);
+
+        // The chain is one concatenation if `..` holds its operands more tightly than every other operator in it
+        let (concatenation_priority, _) = SBinOp::TwoDots.priority();
+        let whole = operators
+            .iter()
+            .all(|operator| operator.priority().0 >= concatenation_priority);
+        let concatenation = match (context, whole) {
+            (StringContext::Concatenation(_), true) => context.clone(),
+            (_, true) => StringContext::Concatenation(Box::new(context.clone())),
+            (_, false) => StringContext::Concatenation(Box::new(StringContext::Other)),
+        };
+
+        for (index, operand) in operands.into_iter().enumerate() {
+            let left = index.checked_sub(1).and_then(|index| operators.get(index));
+            let right = operators.get(index);
+
+            // The operator the operand belongs to, which is the one on the left if they hold it as tightly,
+            // since those are left associative
+            let operator = match (left, right) {
+                (Some(left), Some(right)) if left.priority().1 >= right.priority().0 => Some(left),
+                (_, Some(right)) => Some(right),
+                (left, None) => left,
+            };
+
+            let operand_context = match operator {
+                None => context.clone(),
+                Some(SBinOp::TwoDots) => concatenation.clone(),
+                Some(_) => StringContext::Other,
+            };
+
+            match operand {
+                Operand::String(token) => {
+                    self.contexts.insert(scopes::key(token), operand_context);
+                }
+                Operand::Parentheses(expression) => self.classify(expression, &operand_context),
+                Operand::Other => {}
+            }
+        }
+    }
+
+    // Finds the contexts of the arguments of the calls in a prefix followed by suffixes
+    fn calls<'a>(&mut self, prefix: &Prefix<'ast>, suffixes: impl Iterator<Item = &'a Suffix<'ast>>)
+    where
+        'ast: 'a,
+    {
+        let mut function = prefix.print(TriviaBehavior::Trim);
+        for suffix in suffixes {
+            let arguments = match suffix {
+                Suffix::Call(Call::AnonymousCall(arguments)) => Some((function.clone(), arguments)),
+                Suffix::Call(Call::MethodCall(method_call)) => Some((
+                    format!("{}:{}", function, method_call.name()),
+                    method_call.args(),
+                )),
+                Suffix::Index(_) => None,
+            };
+
+            if let Some((function, arguments)) = arguments {
+                match arguments {
+                    FunctionArgs::Parentheses { arguments, .. } => {
+                        for (index, argument) in arguments.iter().enumerate() {
+                            let context = StringContext::CallArgument {
+                                function: function.clone(),
+                                index,
+                            };
+                            self.classify(argument, &context);
+                        }
+                    }
+
+                    FunctionArgs::String(token) => {
+                        self.contexts.insert(
+                            scopes::key(token),
+                            StringContext::CallArgument { function, index: 0 },
+                        );
+                    }
+
+                    FunctionArgs::TableConstructor(_) => {}
+                }
+            }
+
+            function.push_str(&suffix.print(TriviaBehavior::Trim));
+        }
+    }
+}
+
+// Splits an expression into its operands and the binary operators between them
+fn flatten<'a, 'ast>(
+    expression: &'a Expression<'ast>,
+    unary: bool,
+    operands: &mut Vec<Operand<'a, 'ast>>,
+    operators: &mut Vec<SBinOp>,
+) {
+    match expression {
+        Expression::Value { value, binop, .. } => {
+            operands.push(match &**value {
+                _ if unary => Operand::Other,
+                Value::String(token) => Operand::String(token),
+                Value::ParseExpression(Expression::Parentheses { expression, .. }) => {
+                    Operand::Parentheses(expression)
+                }
+                _ => Operand::Other,
+            });
+
+            if let Some(BinOpRhs { bin_op, rhs, .. }) = binop {
+                operators.push(SBinOp::from(bin_op));
+                flatten(rhs, false, operands, operators);
+            }
+        }
+
+        // The unary operator is only on the operand after it, since it holds it more tightly than the operators after it
+        Expression::UnaryOperator { expression, .. } => {
+            flatten(expression, true, operands, operators)
+        }
+
+        Expression::Parentheses { expression, .. } => operands.push(if unary {
+            Operand::Other
+        } else {
+            Operand::Parentheses(expression)
+        }),
+
+        Expression::Error(_) => operands.push(Operand::Other),
+    }
+}
+
+impl<'ast> Visitor<'ast> for StringLiterals<'ast> {
+    fn visit_string_literal(&mut self, token: &TokenReference<'ast>) {
+        let start = Token::start_position(token);
+        self.strings.push(StringLiteralInfo {
+            token: token.clone(),
+            value: simplified::string(token)
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned()),
+            range: (start, Token::end_position(token)),
+            context: StringContext::Other,
+        });
+    }
+
+    fn visit_function_call(&mut self, call: &ast::FunctionCall<'ast>) {
+        self.calls(call.prefix(), call.iter_suffixes());
+    }
+
+    fn visit_var_expression(&mut self, var_expression: &ast::VarExpression<'ast>) {
+        self.calls(var_expression.prefix(), var_expression.iter_suffixes());
+    }
+
+    fn visit_table_constructor(&mut self, table: &ast::TableConstructor<'ast>) {
+        let mut index = 0;
+        for (field, _) in table.iter_fields() {
+            let (key, value) = match field {
+                Field::NoKey(value) => {
+                    index += 1;
+                    (TableKey::Index(index), value)
+                }
+                Field::NameKey { key, value, .. } => (TableKey::Name(key.to_string()), value),
+                Field::ExpressionKey { key, value, .. } => {
+                    (TableKey::Expression(key.print(TriviaBehavior::Trim)), value)
+                }
+            };
+
+            self.classify(value, &StringContext::TableValue { key });
+        }
+    }
+
+    fn visit_assignment(&mut self, assignment: &ast::Assignment<'ast>) {
+        for (var, expression) in assignment
+            .var_list()
+            .iter()
+            .zip(assignment.expr_list().iter())
+        {
+            let target = var.print(TriviaBehavior::Trim);
+            self.classify(expression, &StringContext::Assignment { target });
+        }
+    }
+
+    fn visit_local_assignment(&mut self, local_assignment: &ast::LocalAssignment<'ast>) {
+        for (name, expression) in local_assignment
+            .name_list()
+            .iter()
+            .zip(local_assignment.expr_list().iter())
+        {
+            let target = name.to_string();
+            self.classify(expression, &StringContext::Assignment { target });
+        }
+    }
+}
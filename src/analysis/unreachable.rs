@@ -0,0 +1,229 @@
+use super::scopes::{Resolution, ScopeAnalysis};
+use crate::{
+    ast::{self, Ast, Block, Call, FunctionCall, LastStmt, Prefix, Stmt, Suffix},
+    node::Node,
+    tokenizer::Position,
+    transform::truthiness,
+    visitors::{Visit, Visitor},
+};
+
+/// The statements of the Ast that can never run, as the start and end of each run of them, in the order they appear
+/// in the code, for finding code left after an early `return` or behind an infinite loop.
+///
+/// A run starts at the first statement after one that never finishes and goes to the end of its block,
+/// since nothing in a block can come after `return` or `break`. A statement never finishes when it is:
+///
+/// - an `if` where every branch ends in `return`, `break`, or another statement that never finishes,
+///   with an `else` or a condition that is always true, such as `if x then return 1 else return 2 end`
+/// - a `while` loop with a condition that is always true, or a `repeat` loop with one that is always false,
+///   with no `break` in it that stops that loop, rather than a loop inside of it, such as `while true do end`
+/// - a call to the global `error`, such as `error("unreachable")`
+/// - a `do` block that never finishes
+///
+/// Conditions are always true or false if they only involve literals, the same ones
+/// [`fold_constants`](../transform/fn.fold_constants.html) evaluates. Statements that can't run since they're in
+/// a branch that is never taken, such as in `if false then end`, aren't included, since
+/// [`eliminate_dead_branches`](../transform/fn.eliminate_dead_branches.html) finds those.
+///
+/// ```rust
+/// # use full_moon::analysis::unreachable;
+/// let ast = full_moon::parse("local function check(x)
+///     if x then
+///         return 1
+///     else
+///         error(\"no x\")
+///     end
+///     print(x)
+///     return 2
+/// end").unwrap();
+/// let ranges = unreachable(&ast);
+///
+/// assert_eq!(ranges.len(), 1);
+/// let (start, end) = ranges[0];
+/// assert_eq!((start.line(), end.line()), (7, 8));
+/// ```
+pub fn unreachable(ast: &Ast) -> Vec<(Position, Position)> {
+    let analysis = ScopeAnalysis::new(ast);
+    let mut visitor = Unreachable {
+        analysis: &analysis,
+        ranges: Vec::new(),
+    };
+
+    visitor.block(ast.nodes());
+    ast.nodes().visit(&mut visitor);
+
+    // The functions in code that can't run are visited too, but all of their code is in the range of that code already
+    let mut ranges = visitor.ranges;
+    ranges.sort();
+    ranges.dedup();
+    let outer = ranges.clone();
+    ranges.retain(|range| {
+        !outer
+            .iter()
+            .any(|other| other != range && other.0 <= range.0 && range.1 <= other.1)
+    });
+    ranges
+}
+
+// How code stops running, which is by finishing, by breaking out of the loop it is in, or both
+#[derive(Clone, Copy, Debug)]
+struct Exits {
+    finishes: bool,
+    breaks: bool,
+}
+
+impl Exits {
+    const NEVER: Exits = Exits {
+        finishes: false,
+        breaks: false,
+    };
+
+    const FINISHES: Exits = Exits {
+        finishes: true,
+        breaks: false,
+    };
+
+    fn or(self, other: Exits) -> Exits {
+        Exits {
+            finishes: self.finishes || other.finishes,
+            breaks: self.breaks || other.breaks,
+        }
+    }
+}
+
+struct Unreachable<'a, 'ast> {
+    analysis: &'a ScopeAnalysis<'ast>,
+    ranges: Vec<(Position, Position)>,
+}
+
+impl<'ast> Unreachable<'_, 'ast> {
+    // How a block stops running, finding the statements in it that can't run, but not the ones in the functions in it
+    fn block(&mut self, block: &Block<'ast>) -> Exits {
+        let stmts: Vec<_> = block.iter_stmts().collect();
+        let mut breaks = false;
+
+        for (index, stmt) in stmts.iter().enumerate() {
+            let exits = self.stmt(stmt);
+            breaks |= exits.breaks;
+
+            if !exits.finishes {
+                let start = stmts[index + 1..]
+                    .first()
+                    .and_then(|stmt| stmt.range())
+                    .or_else(|| block.last_stmts().and_then(Node::range));
+                let end = block
+                    .last_stmts()
+                    .and_then(Node::range)
+                    .or_else(|| stmts.last().and_then(|stmt| stmt.range()));
+
+                if let (Some((start, _)), Some((_, end))) = (start, end) {
+                    self.ranges.push((start, end));
+                }
+
+                return Exits {
+                    finishes: false,
+                    breaks,
+                };
+            }
+        }
+
+        match block.last_stmts() {
+            Some(LastStmt::Break(_)) => Exits {
+                finishes: false,
+                breaks: true,
+            },
+            Some(LastStmt::Return(_)) => Exits {
+                finishes: false,
+                breaks,
+            },
+            None => Exits {
+                finishes: true,
+                breaks,
+            },
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt<'ast>) -> Exits {
+        match stmt {
+            Stmt::Do(do_block) => self.block(do_block.block()),
+
+            Stmt::FunctionCall(call) if self.is_error(call) => Exits::NEVER,
+
+            Stmt::If(if_statement) => {
+                let arms = std::iter::once((if_statement.condition(), if_statement.block())).chain(
+                    if_statement
+                        .else_if()
+                        .into_iter()
+                        .flatten()
+                        .map(|else_if| (else_if.condition(), else_if.block())),
+                );
+
+                let mut exits = Exits::NEVER;
+                for (condition, block) in arms {
+                    // Branches with conditions that are always false are still checked for code in them that can't run
+                    let arm = self.block(block);
+                    match truthiness(condition) {
+                        Some(false) => {}
+                        Some(true) => return exits.or(arm),
+                        None => exits = exits.or(arm),
+                    }
+                }
+
+                match if_statement.else_block() {
+                    Some(block) => exits.or(self.block(block)),
+                    None => exits.or(Exits::FINISHES),
+                }
+            }
+
+            // The breaks in a loop stop that loop, so they're how the loop finishes
+            Stmt::While(while_loop) => {
+                let body = self.block(while_loop.block());
+                Exits {
+                    finishes: body.breaks || truthiness(while_loop.condition()) != Some(true),
+                    breaks: false,
+                }
+            }
+
+            // The condition of `until` is only checked after the body finishes
+            Stmt::Repeat(repeat) => {
+                let body = self.block(repeat.block());
+                Exits {
+                    finishes: body.breaks
+                        || (body.finishes && truthiness(repeat.until()) != Some(false)),
+                    breaks: false,
+                }
+            }
+
+            // Loops over numbers and iterators can always run no times
+            Stmt::NumericFor(numeric_for) => {
+                self.block(numeric_for.block());
+                Exits::FINISHES
+            }
+
+            Stmt::GenericFor(generic_for) => {
+                self.block(generic_for.block());
+                Exits::FINISHES
+            }
+
+            _ => Exits::FINISHES,
+        }
+    }
+
+    // Whether the call is to the global `error`, which never returns
+    fn is_error(&self, call: &FunctionCall<'ast>) -> bool {
+        let mut suffixes = call.iter_suffixes();
+        match (call.prefix(), suffixes.next(), suffixes.next()) {
+            (Prefix::Name(name), Some(Suffix::Call(Call::AnonymousCall(_))), None) => {
+                name.to_string() == "error" && self.analysis.resolve(name) == Resolution::Global
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Unreachable<'_, 'ast> {
+    // A `break` can't leave a function, so its body is checked on its own
+    fn visit_function_body(&mut self, body: &ast::FunctionBody<'ast>) {
+        self.block(body.block());
+    }
+}
@@ -0,0 +1,158 @@
+use super::scopes::{ReferenceKind, Resolution, ScopeAnalysis, VariableId, VariableKind};
+use crate::{
+    ast::{self, Ast},
+    node::Node,
+    tokenizer::{Token, TokenReference},
+    visitors::Visitor,
+};
+use std::collections::HashMap;
+
+/// The options for [`unused_locals_with`](fn.unused_locals_with.html).
+/// Use [`UnusedLocalsConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnusedLocalsConfig<'a> {
+    /// Locals with names that start with it aren't reported, `_` by default,
+    /// since names that start with `_` are the way to say a local isn't meant to be used, such as `for _, value in pairs(t)`.
+    /// Nothing is ignored when it's empty
+    pub ignore_prefix: &'a str,
+}
+
+impl Default for UnusedLocalsConfig<'_> {
+    fn default() -> Self {
+        Self { ignore_prefix: "_" }
+    }
+}
+
+/// Whether an [`UnusedLocal`](struct.UnusedLocal.html) is set after it is declared
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnusedKind {
+    /// Nothing refers to the local after it is declared
+    NeverUsed,
+    /// The local is set after it is declared, such as `x = 1`, but its value is never used
+    AssignedButNeverRead,
+}
+
+/// A local that is never read, from [`unused_locals`](fn.unused_locals.html)
+#[derive(Clone, Debug)]
+pub struct UnusedLocal<'ast> {
+    id: VariableId,
+    token: TokenReference<'ast>,
+    kind: VariableKind,
+    unused: UnusedKind,
+}
+
+impl<'ast> UnusedLocal<'ast> {
+    /// The id of the local in the [`ScopeAnalysis`](scopes/struct.ScopeAnalysis.html) of the Ast
+    pub fn id(&self) -> VariableId {
+        self.id
+    }
+
+    /// The token the local was declared with
+    pub fn token(&self) -> &TokenReference<'ast> {
+        &self.token
+    }
+
+    /// How the local was declared, such as a parameter or the variable of a `for` loop
+    pub fn kind(&self) -> VariableKind {
+        self.kind
+    }
+
+    /// Whether the local is set after it is declared
+    pub fn unused(&self) -> UnusedKind {
+        self.unused
+    }
+}
+
+/// The locals of the Ast that are never read, including function parameters and the variables of loops,
+/// in the order they're declared. Locals with names that start with `_` aren't reported.
+/// A `local function` that is only called from inside of itself is never read, and neither are the implicit
+/// `self` parameters of methods, since they have no name to report.
+///
+/// ```rust
+/// # use full_moon::analysis::{unused_locals, UnusedKind};
+/// let ast = full_moon::parse("local a, b = 1, 2\nlocal c = 3\nc = 4\nfor _, value in pairs(t) do print(a) end").unwrap();
+/// let unused: Vec<_> = unused_locals(&ast)
+///     .iter()
+///     .map(|local| (local.token().to_string(), local.unused()))
+///     .collect();
+///
+/// assert_eq!(
+///     unused,
+///     vec![
+///         ("b".to_owned(), UnusedKind::NeverUsed),
+///         ("c".to_owned(), UnusedKind::AssignedButNeverRead),
+///         ("value".to_owned(), UnusedKind::NeverUsed),
+///     ]
+/// );
+/// ```
+pub fn unused_locals<'ast>(ast: &Ast<'ast>) -> Vec<UnusedLocal<'ast>> {
+    unused_locals_with(ast, &UnusedLocalsConfig::default())
+}
+
+/// The same as [`unused_locals`](fn.unused_locals.html), but with options, such as what names to ignore
+pub fn unused_locals_with<'ast>(
+    ast: &Ast<'ast>,
+    config: &UnusedLocalsConfig,
+) -> Vec<UnusedLocal<'ast>> {
+    let analysis = ScopeAnalysis::new(ast);
+    let mut bodies = LocalFunctionBodies {
+        analysis: &analysis,
+        bodies: HashMap::new(),
+    };
+    bodies.visit_ast(ast);
+
+    analysis
+        .variables()
+        .filter_map(|variable| {
+            let token = variable.declaration()?;
+            if !config.ignore_prefix.is_empty() && variable.name().starts_with(config.ignore_prefix)
+            {
+                return None;
+            }
+
+            // Calling a local function from inside of itself doesn't use it
+            let body = bodies.bodies.get(&variable.id());
+            let references = variable.references().iter().filter(|reference| {
+                let bytes = Token::start_position(reference.token()).bytes();
+                !reference.is_declaration()
+                    && !matches!(body, Some((start, end)) if (*start..*end).contains(&bytes))
+            });
+
+            let mut written = false;
+            for reference in references {
+                match reference.kind() {
+                    ReferenceKind::Read => return None,
+                    ReferenceKind::Write => written = true,
+                }
+            }
+
+            Some(UnusedLocal {
+                id: variable.id(),
+                token: token.clone(),
+                kind: variable.kind(),
+                unused: if written {
+                    UnusedKind::AssignedButNeverRead
+                } else {
+                    UnusedKind::NeverUsed
+                },
+            })
+        })
+        .collect()
+}
+
+// Finds the bytes the body of every local function is between
+struct LocalFunctionBodies<'a, 'ast> {
+    analysis: &'a ScopeAnalysis<'ast>,
+    bodies: HashMap<VariableId, (usize, usize)>,
+}
+
+impl<'ast> Visitor<'ast> for LocalFunctionBodies<'_, 'ast> {
+    fn visit_local_function(&mut self, local_function: &ast::LocalFunction<'ast>) {
+        if let (Resolution::Local(id), Some((start, end))) = (
+            self.analysis.resolve(local_function.name()),
+            local_function.func_body().range(),
+        ) {
+            self.bodies.insert(id, (start.bytes(), end.bytes()));
+        }
+    }
+}
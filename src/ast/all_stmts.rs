@@ -0,0 +1,255 @@
+//! Iterating over every statement in a [`Block`](../struct.Block.html), however deep it is,
+//! with [`Block::all_stmts`](../struct.Block.html#method.all_stmts).
+use super::{
+    punctuated::Punctuated, Ast, Block, Call, Expression, Field, FunctionArgs, FunctionCall, Index,
+    LastStmt, Prefix, Stmt, Suffix, TableConstructor, Value, Var,
+};
+use crate::{prelude::*, tokenizer::TokenReference};
+use core::slice;
+
+impl<'a> Block<'a> {
+    /// An iterator over every [statement](enum.Stmt.html) in the block, including the ones in the blocks of
+    /// if statements, loops, do blocks, and functions, whether declared or in an expression, such as
+    /// `local f = function() end`. Each comes with how deep it is, starting at 0 for the statements of the block itself,
+    /// and they are in the order they're in the code, with the statements of a block right after the statement it's in.
+    ///
+    /// The statements are found as they're iterated over rather than all at once, so stopping early is cheap.
+    ///
+    /// ```rust
+    /// # use full_moon::ast::Stmt;
+    /// let ast = full_moon::parse("if x then\n    while y do call() end\nend\nprint(x)").unwrap();
+    /// let stmts: Vec<_> = ast
+    ///     .nodes()
+    ///     .all_stmts()
+    ///     .map(|(stmt, depth)| (matches!(stmt, Stmt::FunctionCall(_)), depth))
+    ///     .collect();
+    ///
+    /// assert_eq!(stmts, [(false, 0), (false, 1), (true, 2), (true, 0)]);
+    /// ```
+    pub fn all_stmts(&self) -> impl Iterator<Item = (&Stmt<'a>, usize)> {
+        AllStmts {
+            frames: vec![Frame::new(self, 0)],
+        }
+    }
+}
+
+impl<'a> Ast<'a> {
+    /// An iterator over every statement in the Ast with how deep it is, the same as
+    /// [`Block::all_stmts`](struct.Block.html#method.all_stmts) on its [nodes](#method.nodes)
+    pub fn all_stmts(&self) -> impl Iterator<Item = (&Stmt<'a>, usize)> {
+        self.nodes().all_stmts()
+    }
+}
+
+// The blocks being iterated over, with the innermost last
+struct AllStmts<'a, 'b> {
+    frames: Vec<Frame<'a, 'b>>,
+}
+
+struct Frame<'a, 'b> {
+    stmts: slice::Iter<'b, (Stmt<'a>, Option<TokenReference<'a>>)>,
+    // Only for the functions in it, like `return function() end`, which are gone through after the statements
+    last_stmt: Option<&'b LastStmt<'a>>,
+    depth: usize,
+}
+
+impl<'a, 'b> Frame<'a, 'b> {
+    fn new(block: &'b Block<'a>, depth: usize) -> Self {
+        Self {
+            stmts: block.stmts.iter(),
+            last_stmt: block.last_stmts(),
+            depth,
+        }
+    }
+}
+
+impl<'a, 'b> AllStmts<'a, 'b> {
+    // Puts the blocks inside of something on top, so they're gone through before what's after it
+    fn push(&mut self, blocks: Vec<&'b Block<'a>>, depth: usize) {
+        self.frames.extend(
+            blocks
+                .into_iter()
+                .rev()
+                .map(|block| Frame::new(block, depth)),
+        );
+    }
+}
+
+impl<'a, 'b> Iterator for AllStmts<'a, 'b> {
+    type Item = (&'b Stmt<'a>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.frames.last_mut()?;
+            let depth = frame.depth;
+
+            if let Some((stmt, _)) = frame.stmts.next() {
+                let mut blocks = Vec::new();
+                stmt_blocks(stmt, &mut blocks);
+                self.push(blocks, depth + 1);
+                return Some((stmt, depth));
+            }
+
+            match frame.last_stmt.take() {
+                Some(LastStmt::Return(r#return)) => {
+                    let mut blocks = Vec::new();
+                    punctuated_blocks(r#return.returns(), &mut blocks);
+                    self.push(blocks, depth + 1);
+                }
+
+                Some(LastStmt::Break(_)) => {}
+
+                None => {
+                    self.frames.pop();
+                }
+            }
+        }
+    }
+}
+
+// The blocks directly inside of a statement, in the order they're in the code, without the ones inside of those
+fn stmt_blocks<'a, 'b>(stmt: &'b Stmt<'a>, blocks: &mut Vec<&'b Block<'a>>) {
+    match stmt {
+        Stmt::Assignment(assignment) => {
+            for var in assignment.var_list() {
+                var_blocks(var, blocks);
+            }
+            punctuated_blocks(assignment.expr_list(), blocks);
+        }
+
+        Stmt::Do(r#do) => blocks.push(r#do.block()),
+        Stmt::Error(_) => {}
+        Stmt::FunctionCall(call) => function_call_blocks(call, blocks),
+        Stmt::FunctionDeclaration(declaration) => blocks.push(declaration.body().block()),
+
+        Stmt::GenericFor(generic_for) => {
+            punctuated_blocks(generic_for.expr_list(), blocks);
+            blocks.push(generic_for.block());
+        }
+
+        Stmt::If(r#if) => {
+            expression_blocks(r#if.condition(), blocks);
+            blocks.push(r#if.block());
+
+            for else_if in r#if.else_if().into_iter().flatten() {
+                expression_blocks(else_if.condition(), blocks);
+                blocks.push(else_if.block());
+            }
+
+            blocks.extend(r#if.else_block());
+        }
+
+        Stmt::LocalAssignment(assignment) => punctuated_blocks(assignment.expr_list(), blocks),
+        Stmt::LocalFunction(function) => blocks.push(function.func_body().block()),
+
+        Stmt::NumericFor(numeric_for) => {
+            expression_blocks(numeric_for.start(), blocks);
+            expression_blocks(numeric_for.end(), blocks);
+            if let Some(step) = numeric_for.step() {
+                expression_blocks(step, blocks);
+            }
+            blocks.push(numeric_for.block());
+        }
+
+        Stmt::Repeat(repeat) => {
+            blocks.push(repeat.block());
+            expression_blocks(repeat.until(), blocks);
+        }
+
+        Stmt::While(r#while) => {
+            expression_blocks(r#while.condition(), blocks);
+            blocks.push(r#while.block());
+        }
+    }
+}
+
+// The blocks of the functions in expressions, such as `function() end`
+fn expression_blocks<'a, 'b>(expression: &'b Expression<'a>, blocks: &mut Vec<&'b Block<'a>>) {
+    match expression {
+        Expression::Parentheses { expression, .. }
+        | Expression::UnaryOperator { expression, .. } => expression_blocks(expression, blocks),
+
+        Expression::Value { value, binop, .. } => {
+            value_blocks(value, blocks);
+            if let Some(binop) = binop {
+                expression_blocks(binop.rhs(), blocks);
+            }
+        }
+
+        Expression::Error(_) => {}
+    }
+}
+
+fn value_blocks<'a, 'b>(value: &'b Value<'a>, blocks: &mut Vec<&'b Block<'a>>) {
+    match value {
+        Value::Function((_, body)) => blocks.push(body.block()),
+        Value::FunctionCall(call) => function_call_blocks(call, blocks),
+        Value::TableConstructor(table) => table_blocks(table, blocks),
+        Value::ParseExpression(expression) => expression_blocks(expression, blocks),
+        Value::Var(var) => var_blocks(var, blocks),
+        Value::Number(_) | Value::String(_) | Value::Symbol(_) => {}
+    }
+}
+
+fn var_blocks<'a, 'b>(var: &'b Var<'a>, blocks: &mut Vec<&'b Block<'a>>) {
+    if let Var::Expression(var_expression) = var {
+        prefix_blocks(var_expression.prefix(), blocks);
+        for suffix in var_expression.iter_suffixes() {
+            suffix_blocks(suffix, blocks);
+        }
+    }
+}
+
+fn function_call_blocks<'a, 'b>(call: &'b FunctionCall<'a>, blocks: &mut Vec<&'b Block<'a>>) {
+    prefix_blocks(call.prefix(), blocks);
+    for suffix in call.iter_suffixes() {
+        suffix_blocks(suffix, blocks);
+    }
+}
+
+fn prefix_blocks<'a, 'b>(prefix: &'b Prefix<'a>, blocks: &mut Vec<&'b Block<'a>>) {
+    if let Prefix::Expression(expression) = prefix {
+        expression_blocks(expression, blocks);
+    }
+}
+
+fn suffix_blocks<'a, 'b>(suffix: &'b Suffix<'a>, blocks: &mut Vec<&'b Block<'a>>) {
+    let args = match suffix {
+        Suffix::Call(Call::AnonymousCall(args)) => args,
+        Suffix::Call(Call::MethodCall(method_call)) => method_call.args(),
+
+        Suffix::Index(Index::Brackets { expression, .. }) => {
+            return expression_blocks(expression, blocks)
+        }
+
+        Suffix::Index(Index::Dot { .. }) => return,
+    };
+
+    match args {
+        FunctionArgs::Parentheses { arguments, .. } => punctuated_blocks(arguments, blocks),
+        FunctionArgs::String(_) => {}
+        FunctionArgs::TableConstructor(table) => table_blocks(table, blocks),
+    }
+}
+
+fn table_blocks<'a, 'b>(table: &'b TableConstructor<'a>, blocks: &mut Vec<&'b Block<'a>>) {
+    for (field, _) in table.iter_fields() {
+        match field {
+            Field::ExpressionKey { key, value, .. } => {
+                expression_blocks(key, blocks);
+                expression_blocks(value, blocks);
+            }
+
+            Field::NameKey { value, .. } | Field::NoKey(value) => expression_blocks(value, blocks),
+        }
+    }
+}
+
+fn punctuated_blocks<'a, 'b>(
+    expressions: &'b Punctuated<'a, Expression<'a>>,
+    blocks: &mut Vec<&'b Block<'a>>,
+) {
+    for expression in expressions {
+        expression_blocks(expression, blocks);
+    }
+}
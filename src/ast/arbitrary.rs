@@ -0,0 +1,597 @@
+//! Generation of valid Asts from fuzzing input, through the [`Arbitrary`](https://docs.rs/arbitrary) implementation
+//! of [`Ast`](../struct.Ast.html) and [`arbitrary_with`](fn.arbitrary_with.html).
+//! Only available with the `arbitrary` feature.
+//!
+//! Random strings are almost never Lua, so fuzzing with them mostly tests the errors of the parser.
+//! The code generated here is always valid Lua 5.1, made from the tokens of statements and expressions chosen
+//! from the input, with whitespace and comments between every token, so fuzzers get through to what uses the Ast.
+//! Printing a generated Ast and parsing the code again always gives the same Ast, other than the positions of the tokens.
+use super::{Ast, ParseConfig};
+use crate::tokenizer::{StringLiteralQuoteType, Symbol, Token, TokenType};
+use arbitrary::{Arbitrary, Result, Unstructured};
+use std::borrow::Cow;
+
+/// The options for [`arbitrary_with`](fn.arbitrary_with.html).
+/// Use [`ArbitraryConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArbitraryConfig {
+    /// How many blocks and expressions can be in each other, 4 by default. Blocks this deep are empty,
+    /// and expressions this deep are only names and literals. Code nested more deeply than the
+    /// [recursion limit](../struct.ParseConfig.html#structfield.recursion_limit) of parsing can't be parsed again
+    /// without raising it, which the default is far from.
+    pub max_depth: usize,
+    /// The most statements a block can have, not counting a `return` or `break` at its end, 4 by default
+    pub max_stmts: usize,
+}
+
+impl Default for ArbitraryConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_stmts: 4,
+        }
+    }
+}
+
+/// Generates an Ast from the fuzzing input like its [`Arbitrary`](https://docs.rs/arbitrary) implementation,
+/// using the options of the [config](struct.ArbitraryConfig.html).
+///
+/// ```rust
+/// use arbitrary::Unstructured;
+/// use full_moon::{ast::arbitrary::{arbitrary_with, ArbitraryConfig}, node::Node};
+///
+/// let config = ArbitraryConfig {
+///     max_depth: 2,
+///     ..ArbitraryConfig::default()
+/// };
+///
+/// let ast = arbitrary_with(&mut Unstructured::new(b"some fuzzing input"), &config).unwrap();
+/// let code = full_moon::print(&ast);
+/// assert!(full_moon::parse(&code).unwrap().nodes().similar(ast.nodes()));
+/// ```
+pub fn arbitrary_with<'a>(u: &mut Unstructured, config: &ArbitraryConfig) -> Result<Ast<'a>> {
+    let mut generator = Generator {
+        u,
+        config,
+        tokens: Vec::new(),
+        depth: 0,
+        vararg: true,
+        in_loop: false,
+    };
+
+    generator.block()?;
+    let tokens = generator.trivia()?;
+
+    // The code is only as deep as the config allows, so parsing it has no limit,
+    // other than the level past the limit not overflowing
+    let config = ParseConfig {
+        recursion_limit: usize::MAX - 1,
+        expression_depth_limit: usize::MAX,
+        ..ParseConfig::default()
+    };
+    let mut ast = Ast::from_tokens_with(tokens, &config).expect("generated code is always valid");
+    ast.update_positions();
+    Ok(ast)
+}
+
+/// Generates Asts of valid code with [`ArbitraryConfig::default()`](struct.ArbitraryConfig.html),
+/// only available with the `arbitrary` feature. See [`arbitrary_with`](arbitrary/fn.arbitrary_with.html)
+/// for the options.
+impl<'a, 'b> Arbitrary<'a> for Ast<'b> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_with(u, &ArbitraryConfig::default())
+    }
+}
+
+// None of these are keywords, with or without the `roblox` feature
+const NAMES: &[&str] = &["a", "b", "x1", "_", "foo", "value", "print", "self"];
+
+const NUMBERS: &[&str] = &["0", "1", "42", "3.14", ".5", "1e3", "2E-2", "0xff"];
+
+const STRINGS: &[(&str, StringLiteralQuoteType, Option<usize>)] = &[
+    ("", StringLiteralQuoteType::Double, None),
+    ("hello", StringLiteralQuoteType::Double, None),
+    ("it's \\\"quoted\\\"", StringLiteralQuoteType::Double, None),
+    ("tab\\t", StringLiteralQuoteType::Single, None),
+    ("say \"hi\"", StringLiteralQuoteType::Single, None),
+    ("\\'", StringLiteralQuoteType::Single, None),
+    ("long\nstring", StringLiteralQuoteType::Brackets, Some(0)),
+    ("has ]] in it", StringLiteralQuoteType::Brackets, Some(1)),
+];
+
+const BINARY_OPERATORS: &[Symbol] = &[
+    Symbol::And,
+    Symbol::Or,
+    Symbol::Caret,
+    Symbol::TwoDots,
+    Symbol::TwoEqual,
+    Symbol::TildeEqual,
+    Symbol::GreaterThan,
+    Symbol::GreaterThanEqual,
+    Symbol::LessThan,
+    Symbol::LessThanEqual,
+    Symbol::Minus,
+    Symbol::Percent,
+    Symbol::Plus,
+    Symbol::Slash,
+    Symbol::Star,
+];
+
+const UNARY_OPERATORS: &[Symbol] = &[Symbol::Minus, Symbol::Not, Symbol::Hash];
+
+// Generates the tokens of the code other than trivia, which is put between them at the end.
+// Where there's a choice, the first one is the simplest, since input that runs out gives the first choice
+// from then on, so the code always ends.
+struct Generator<'u, 'a, 'b> {
+    u: &'u mut Unstructured<'a>,
+    config: &'u ArbitraryConfig,
+    tokens: Vec<TokenType<'b>>,
+    depth: usize,
+    // Whether `...` can be used, which is in the chunk and in functions that have it as a parameter
+    vararg: bool,
+    // Whether `break` can be used, which is in loops, but not in the functions in them
+    in_loop: bool,
+}
+
+impl<'b> Generator<'_, '_, 'b> {
+    fn symbol(&mut self, symbol: Symbol) {
+        self.tokens.push(TokenType::Symbol { symbol });
+    }
+
+    fn name(&mut self) -> Result<()> {
+        let identifier = *self.u.choose(NAMES)?;
+        self.tokens.push(TokenType::Identifier {
+            identifier: Cow::Borrowed(identifier),
+        });
+        Ok(())
+    }
+
+    // Whether there can be anything more deeply nested
+    fn nested(&self) -> bool {
+        self.depth < self.config.max_depth
+    }
+
+    fn deeper(&mut self, generate: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        self.depth += 1;
+        let result = generate(self);
+        self.depth -= 1;
+        result
+    }
+
+    // The items with commas between them, at least one of them
+    fn list(&mut self, max: usize, mut item: impl FnMut(&mut Self) -> Result<()>) -> Result<()> {
+        for index in 0..=self.u.int_in_range(0..=max - 1)? {
+            if index > 0 {
+                self.symbol(Symbol::Comma);
+            }
+            item(self)?;
+        }
+        Ok(())
+    }
+
+    fn block(&mut self) -> Result<()> {
+        if !self.nested() {
+            return Ok(());
+        }
+
+        let mut semicolon = true;
+        for _ in 0..self.u.int_in_range(0..=self.config.max_stmts)? {
+            let start = self.tokens.len();
+            self.stmt()?;
+
+            // A statement in parentheses would be a call of the end of the statement before it
+            let parentheses = TokenType::Symbol {
+                symbol: Symbol::LeftParen,
+            };
+            if !semicolon && self.tokens[start] == parentheses {
+                self.tokens.insert(
+                    start,
+                    TokenType::Symbol {
+                        symbol: Symbol::Semicolon,
+                    },
+                );
+            }
+
+            semicolon = self.u.ratio(1, 8)?;
+            if semicolon {
+                self.symbol(Symbol::Semicolon);
+            }
+        }
+
+        match self.u.int_in_range(0..=3)? {
+            1 => {
+                self.symbol(Symbol::Return);
+                if self.u.arbitrary()? {
+                    self.list(3, Self::expression)?;
+                }
+            }
+            2 if self.in_loop => self.symbol(Symbol::Break),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn nested_block(&mut self) -> Result<()> {
+        self.deeper(Self::block)
+    }
+
+    fn loop_block(&mut self) -> Result<()> {
+        let in_loop = core::mem::replace(&mut self.in_loop, true);
+        let result = self.nested_block();
+        self.in_loop = in_loop;
+        result
+    }
+
+    fn stmt(&mut self) -> Result<()> {
+        match self.u.int_in_range(0..=10)? {
+            0 => {
+                self.symbol(Symbol::Local);
+                self.list(3, Self::name)?;
+                if self.u.ratio(3, 4)? {
+                    self.symbol(Symbol::Equal);
+                    self.list(3, Self::expression)?;
+                }
+            }
+
+            1 => {
+                self.list(2, Self::var)?;
+                self.symbol(Symbol::Equal);
+                self.list(3, Self::expression)?;
+            }
+
+            2 => self.function_call()?,
+
+            3 => {
+                self.symbol(Symbol::Do);
+                self.nested_block()?;
+                self.symbol(Symbol::End);
+            }
+
+            4 => {
+                self.symbol(Symbol::While);
+                self.expression()?;
+                self.symbol(Symbol::Do);
+                self.loop_block()?;
+                self.symbol(Symbol::End);
+            }
+
+            5 => {
+                self.symbol(Symbol::Repeat);
+                self.loop_block()?;
+                self.symbol(Symbol::Until);
+                self.expression()?;
+            }
+
+            6 => {
+                self.symbol(Symbol::If);
+                self.expression()?;
+                self.symbol(Symbol::Then);
+                self.nested_block()?;
+                for _ in 0..self.u.int_in_range(0..=2)? {
+                    self.symbol(Symbol::ElseIf);
+                    self.expression()?;
+                    self.symbol(Symbol::Then);
+                    self.nested_block()?;
+                }
+                if self.u.arbitrary()? {
+                    self.symbol(Symbol::Else);
+                    self.nested_block()?;
+                }
+                self.symbol(Symbol::End);
+            }
+
+            7 => {
+                self.symbol(Symbol::For);
+                self.name()?;
+                self.symbol(Symbol::Equal);
+                self.expression()?;
+                self.symbol(Symbol::Comma);
+                self.expression()?;
+                if self.u.arbitrary()? {
+                    self.symbol(Symbol::Comma);
+                    self.expression()?;
+                }
+                self.symbol(Symbol::Do);
+                self.loop_block()?;
+                self.symbol(Symbol::End);
+            }
+
+            8 => {
+                self.symbol(Symbol::For);
+                self.list(3, Self::name)?;
+                self.symbol(Symbol::In);
+                self.list(3, Self::expression)?;
+                self.symbol(Symbol::Do);
+                self.loop_block()?;
+                self.symbol(Symbol::End);
+            }
+
+            9 => {
+                self.symbol(Symbol::Function);
+                self.name()?;
+                for _ in 0..self.u.int_in_range(0..=2)? {
+                    self.symbol(Symbol::Dot);
+                    self.name()?;
+                }
+                if self.u.arbitrary()? {
+                    self.symbol(Symbol::Colon);
+                    self.name()?;
+                }
+                self.function_body()?;
+            }
+
+            _ => {
+                self.symbol(Symbol::Local);
+                self.symbol(Symbol::Function);
+                self.name()?;
+                self.function_body()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn function_body(&mut self) -> Result<()> {
+        self.symbol(Symbol::LeftParen);
+        let parameters = self.u.int_in_range(0..=3)?;
+        for index in 0..parameters {
+            if index > 0 {
+                self.symbol(Symbol::Comma);
+            }
+            self.name()?;
+        }
+
+        let vararg = self.u.arbitrary()?;
+        if vararg {
+            if parameters > 0 {
+                self.symbol(Symbol::Comma);
+            }
+            self.symbol(Symbol::Ellipse);
+        }
+        self.symbol(Symbol::RightParen);
+
+        let vararg = core::mem::replace(&mut self.vararg, vararg);
+        let in_loop = core::mem::replace(&mut self.in_loop, false);
+        let result = self.nested_block();
+        self.vararg = vararg;
+        self.in_loop = in_loop;
+        result?;
+
+        self.symbol(Symbol::End);
+        Ok(())
+    }
+
+    // A name or an expression in parentheses, with a few suffixes after it
+    fn prefix_expression(&mut self) -> Result<()> {
+        if self.nested() && self.u.ratio(1, 5)? {
+            self.symbol(Symbol::LeftParen);
+            self.expression()?;
+            self.symbol(Symbol::RightParen);
+        } else {
+            self.name()?;
+        }
+
+        let suffixes = if self.nested() {
+            self.u.int_in_range(0..=2)?
+        } else {
+            0
+        };
+        for _ in 0..suffixes {
+            if self.u.arbitrary()? {
+                self.call()?;
+            } else {
+                self.index()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn var(&mut self) -> Result<()> {
+        if self.nested() && self.u.arbitrary()? {
+            self.prefix_expression()?;
+            self.index()
+        } else {
+            self.name()
+        }
+    }
+
+    fn function_call(&mut self) -> Result<()> {
+        self.prefix_expression()?;
+        self.call()
+    }
+
+    fn index(&mut self) -> Result<()> {
+        if self.u.arbitrary()? {
+            self.symbol(Symbol::LeftBracket);
+            self.expression()?;
+            self.symbol(Symbol::RightBracket);
+        } else {
+            self.symbol(Symbol::Dot);
+            self.name()?;
+        }
+        Ok(())
+    }
+
+    fn call(&mut self) -> Result<()> {
+        if self.u.arbitrary()? {
+            self.symbol(Symbol::Colon);
+            self.name()?;
+        }
+
+        match self.u.int_in_range(0..=2)? {
+            0 => {
+                self.symbol(Symbol::LeftParen);
+                if self.u.arbitrary()? {
+                    self.list(3, Self::expression)?;
+                }
+                self.symbol(Symbol::RightParen);
+            }
+            1 => self.string()?,
+            _ => self.table()?,
+        }
+        Ok(())
+    }
+
+    fn table(&mut self) -> Result<()> {
+        self.symbol(Symbol::LeftBrace);
+        let fields = if self.nested() {
+            self.u.int_in_range(0..=3)?
+        } else {
+            0
+        };
+
+        for index in 0..fields {
+            if index > 0 {
+                let separator = *self.u.choose(&[Symbol::Comma, Symbol::Semicolon])?;
+                self.symbol(separator);
+            }
+
+            match self.u.int_in_range(0..=2)? {
+                0 => self.expression()?,
+                1 => {
+                    self.name()?;
+                    self.symbol(Symbol::Equal);
+                    self.expression()?;
+                }
+                _ => {
+                    self.symbol(Symbol::LeftBracket);
+                    self.expression()?;
+                    self.symbol(Symbol::RightBracket);
+                    self.symbol(Symbol::Equal);
+                    self.expression()?;
+                }
+            }
+        }
+
+        if fields > 0 && self.u.ratio(1, 4)? {
+            self.symbol(Symbol::Comma);
+        }
+        self.symbol(Symbol::RightBrace);
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<()> {
+        let (literal, quote_type, multi_line) = *self.u.choose(STRINGS)?;
+        self.tokens.push(TokenType::StringLiteral {
+            literal: Cow::Borrowed(literal),
+            multi_line,
+            quote_type,
+        });
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<()> {
+        self.deeper(|this| {
+            this.simple_expression()?;
+            let operators = if this.nested() {
+                this.u.int_in_range(0..=2)?
+            } else {
+                0
+            };
+            for _ in 0..operators {
+                let operator = *this.u.choose(BINARY_OPERATORS)?;
+                this.symbol(operator);
+                this.simple_expression()?;
+            }
+            Ok(())
+        })
+    }
+
+    // An expression without binary operators, other than in parentheses
+    fn simple_expression(&mut self) -> Result<()> {
+        let choice = if self.nested() {
+            self.u.int_in_range(0..=10)?
+        } else {
+            self.u.int_in_range(0..=5)?
+        };
+
+        match choice {
+            0 => self.name()?,
+            1 => {
+                let number = *self.u.choose(NUMBERS)?;
+                self.tokens.push(TokenType::Number {
+                    text: Cow::Borrowed(number),
+                });
+            }
+            2 => self.string()?,
+            3 => {
+                let symbol = *self.u.choose(&[Symbol::Nil, Symbol::True, Symbol::False])?;
+                self.symbol(symbol);
+            }
+            4 if self.vararg => self.symbol(Symbol::Ellipse),
+            4 | 5 => self.name()?,
+            6 => {
+                let operator = *self.u.choose(UNARY_OPERATORS)?;
+                self.symbol(operator);
+                self.deeper(Self::simple_expression)?;
+            }
+            7 => {
+                self.symbol(Symbol::Function);
+                self.deeper(Self::function_body)?;
+            }
+            8 => self.deeper(Self::table)?,
+            _ => self.deeper(Self::prefix_expression)?,
+        }
+
+        Ok(())
+    }
+
+    // The tokens with whitespace and comments between them, and an Eof at the end
+    fn trivia(&mut self) -> Result<Vec<Token<'b>>> {
+        let mut tokens = Vec::new();
+        if !self.tokens.is_empty() && self.u.ratio(1, 4)? {
+            tokens.push(Token::from(TokenType::SingleLineComment {
+                comment: Cow::Borrowed(" generated"),
+            }));
+            tokens.push(whitespace("\n"));
+        }
+
+        for (index, token_type) in core::mem::take(&mut self.tokens).into_iter().enumerate() {
+            // Lua 5.1 can't tell a call on a new line from a new statement in parentheses,
+            // so parentheses stay on the line before them
+            let new_lines = token_type
+                != TokenType::Symbol {
+                    symbol: Symbol::LeftParen,
+                };
+
+            if index > 0 {
+                match self.u.int_in_range(0..=15)? {
+                    12 if new_lines => tokens.push(whitespace("\n")),
+                    13 if new_lines => tokens.push(whitespace("\n\t")),
+                    14 if new_lines => {
+                        tokens.push(whitespace(" "));
+                        tokens.push(Token::from(TokenType::SingleLineComment {
+                            comment: Cow::Borrowed(" comment"),
+                        }));
+                        tokens.push(whitespace("\n"));
+                    }
+                    15 => {
+                        tokens.push(whitespace(" "));
+                        tokens.push(Token::from(TokenType::MultiLineComment {
+                            blocks: 0,
+                            comment: Cow::Borrowed(" note "),
+                        }));
+                        tokens.push(whitespace(" "));
+                    }
+                    _ => tokens.push(whitespace(" ")),
+                }
+            }
+
+            tokens.push(Token::from(token_type));
+        }
+
+        if !tokens.is_empty() && self.u.arbitrary()? {
+            tokens.push(whitespace("\n"));
+        }
+        tokens.push(Token::from(TokenType::Eof));
+        Ok(tokens)
+    }
+}
+
+fn whitespace<'b>(characters: &'static str) -> Token<'b> {
+    Token::from(TokenType::Whitespace {
+        characters: Cow::Borrowed(characters),
+    })
+}
@@ -0,0 +1,199 @@
+//! Dropping the [`Block`](../struct.Block.html) of an [`Ast`](../struct.Ast.html) without recursing into the
+//! expressions in it. The parser can make expressions nested in each other as deeply as memory allows, such as
+//! tables, operators, and function calls nested hundreds of thousands of levels deep, which would overflow the stack
+//! if each expression dropped the ones in it by recursing, so they're taken out and dropped one at a time instead.
+//! Only the block of an Ast, and what the parser parses and then doesn't use, are dropped this way,
+//! so what's in expressions can still be moved out of them by matching.
+use super::{
+    Block, Call, ErrorExpression, Expression, Field, FunctionArgs, Index, Prefix, Suffix,
+    TableConstructor, Value, Var,
+};
+use crate::{
+    node::StoredId,
+    prelude::*,
+    visitors::{VisitMut, VisitorMut},
+};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    ops::{Deref, DerefMut},
+};
+
+// The block of an Ast, which drops the expressions in it one at a time.
+// A type with a lifetime that implements Drop needs what it borrows to outlive it, and nodes can only be compared
+// with nodes of the same lifetime, so an Ast compared with one parsed from code made after it couldn't be dropped
+// before that code. The block is kept without its lifetime instead, for a type without one to drop it, which is
+// what `Vec` does for what's in it with `#[may_dangle]`.
+pub(crate) struct AstBlock<'a> {
+    block: ErasedBlock,
+    marker: PhantomData<Block<'a>>,
+}
+
+struct ErasedBlock(ManuallyDrop<Block<'static>>);
+
+impl<'a> AstBlock<'a> {
+    pub(crate) fn new(block: Block<'a>) -> Self {
+        // SAFETY: only the lifetime changes, and the block is only used with its own lifetime, through `Deref`,
+        // apart from being dropped, which moves what's in it without reading anything it borrows
+        let block = unsafe { mem::transmute::<Block<'a>, Block<'static>>(block) };
+
+        Self {
+            block: ErasedBlock(ManuallyDrop::new(block)),
+            marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> Block<'a> {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never dropped, so the block is only taken out of it once
+        let block = unsafe { ManuallyDrop::take(&mut this.block.0) };
+        // SAFETY: the block is given back the lifetime it was made with
+        unsafe { mem::transmute::<Block<'static>, Block<'a>>(block) }
+    }
+}
+
+impl<'a> Deref for AstBlock<'a> {
+    type Target = Block<'a>;
+
+    fn deref(&self) -> &Block<'a> {
+        // SAFETY: the block was made with this lifetime
+        unsafe { &*(&*self.block.0 as *const Block<'static>).cast::<Block<'a>>() }
+    }
+}
+
+impl<'a> DerefMut for AstBlock<'a> {
+    fn deref_mut(&mut self) -> &mut Block<'a> {
+        // SAFETY: the block was made with this lifetime, and nodes with only it can be put in it
+        unsafe { &mut *(&mut *self.block.0 as *mut Block<'static>).cast::<Block<'a>>() }
+    }
+}
+
+impl Clone for AstBlock<'_> {
+    fn clone(&self) -> Self {
+        Self::new((**self).clone())
+    }
+}
+
+impl fmt::Debug for AstBlock<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, formatter)
+    }
+}
+
+impl Drop for ErasedBlock {
+    fn drop(&mut self) {
+        // SAFETY: the block is never used again after it's taken
+        drop_nodes(unsafe { ManuallyDrop::take(&mut self.0) });
+    }
+}
+
+// Drops the nodes one expression at a time, for the block of an Ast and for what the parser parses and then doesn't use
+pub(crate) fn drop_nodes<'a, N: VisitMut<'a>>(mut nodes: N) {
+    // Visiting the nodes only recurses into the blocks in them, since the expressions in each expression
+    // are taken out when it's visited, and how deeply blocks are in each other is limited by the recursion limit
+    let mut taker = TakeNested { nested: Vec::new() };
+    nodes.visit_mut(&mut taker);
+
+    // Each expression is dropped at the end of the loop with nothing left in it, so dropping it doesn't recurse
+    while let Some(mut expression) = taker.nested.pop() {
+        expression.visit_mut(&mut taker);
+    }
+}
+
+struct TakeNested<'a> {
+    nested: Vec<Expression<'a>>,
+}
+
+impl<'a> VisitorMut<'a> for TakeNested<'a> {
+    fn visit_expression(&mut self, expression: &mut Expression<'a>) {
+        take_nested(expression, &mut self.nested);
+    }
+}
+
+// The expression, leaving one with nothing in it in its place
+fn take<'a>(expression: &mut Expression<'a>) -> Expression<'a> {
+    mem::replace(
+        expression,
+        Expression::Error(ErrorExpression {
+            tokens: Vec::new(),
+            id: StoredId::DROPPED,
+        }),
+    )
+}
+
+// Moves the expressions directly in the expression into `nested`, without the ones in those
+fn take_nested<'a>(expression: &mut Expression<'a>, nested: &mut Vec<Expression<'a>>) {
+    match expression {
+        Expression::Parentheses { expression, .. }
+        | Expression::UnaryOperator { expression, .. } => nested.push(take(expression)),
+
+        Expression::Value { value, binop, .. } => {
+            if let Some(binop) = binop {
+                nested.push(take(&mut binop.rhs));
+            }
+
+            match &mut **value {
+                Value::FunctionCall(call) => {
+                    take_prefix(&mut call.prefix, nested);
+                    take_suffixes(&mut call.suffixes, nested);
+                }
+
+                Value::ParseExpression(expression) => nested.push(take(expression)),
+                Value::TableConstructor(table) => take_fields(table, nested),
+
+                Value::Var(Var::Expression(var)) => {
+                    take_prefix(&mut var.prefix, nested);
+                    take_suffixes(&mut var.suffixes, nested);
+                }
+
+                // How deeply functions are in each other is limited by the recursion limit, as they have blocks
+                Value::Function(_)
+                | Value::Number(_)
+                | Value::String(_)
+                | Value::Symbol(_)
+                | Value::Var(Var::Name(_)) => {}
+            }
+        }
+
+        Expression::Error(_) => {}
+    }
+}
+
+fn take_prefix<'a>(prefix: &mut Prefix<'a>, nested: &mut Vec<Expression<'a>>) {
+    if let Prefix::Expression(expression) = prefix {
+        nested.push(take(expression));
+    }
+}
+
+fn take_suffixes<'a>(suffixes: &mut Vec<Suffix<'a>>, nested: &mut Vec<Expression<'a>>) {
+    for suffix in mem::take(suffixes) {
+        match suffix {
+            Suffix::Call(Call::AnonymousCall(args)) => take_args(args, nested),
+            Suffix::Call(Call::MethodCall(method_call)) => take_args(method_call.args, nested),
+            Suffix::Index(Index::Brackets { expression, .. }) => nested.push(expression),
+            Suffix::Index(Index::Dot { .. }) => {}
+        }
+    }
+}
+
+fn take_args<'a>(args: FunctionArgs<'a>, nested: &mut Vec<Expression<'a>>) {
+    match args {
+        FunctionArgs::Parentheses { arguments, .. } => nested.extend(arguments),
+        FunctionArgs::TableConstructor(mut table) => take_fields(&mut table, nested),
+        FunctionArgs::String(_) => {}
+    }
+}
+
+fn take_fields<'a>(table: &mut TableConstructor<'a>, nested: &mut Vec<Expression<'a>>) {
+    for (field, _) in mem::take(&mut table.fields) {
+        match field {
+            Field::ExpressionKey { key, value, .. } => {
+                nested.push(key);
+                nested.push(value);
+            }
+
+            Field::NameKey { value, .. } | Field::NoKey(value) => nested.push(value),
+        }
+    }
+}
@@ -0,0 +1,508 @@
+//! How much memory an [`Ast`](../struct.Ast.html) takes, with [`Ast::memory_usage`](../struct.Ast.html#method.memory_usage),
+//! and trimming it with [`Ast::shrink_to_fit`](../struct.Ast.html#method.shrink_to_fit).
+use super::{
+    punctuated::{Pair, Punctuated},
+    span::ContainedSpan,
+    Ast,
+};
+use crate::{
+    node::StoredId,
+    prelude::*,
+    tokenizer::{Token, TokenKind, TokenReference, TokenType},
+    visitors::{Visit, VisitMut, Visitor, VisitorMut},
+};
+use alloc::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+use core::mem;
+use generational_arena::Arena;
+
+/// How many bytes an Ast takes on the heap, from [`Ast::memory_usage`](../struct.Ast.html#method.memory_usage).
+/// The text of tokens borrowed from the code isn't counted, since it's in the code rather than the Ast,
+/// so only the text of owned Asts and tokens made with a `String` is in [`string_bytes`](#method.string_bytes).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    token_arena_bytes: usize,
+    node_count: usize,
+    node_bytes: usize,
+    string_bytes: usize,
+}
+
+impl MemoryReport {
+    /// The bytes of the arena of tokens, along with the positions and types each token points to,
+    /// including trivia, and the comments of an Ast parsed without trivia
+    pub fn token_arena_bytes(&self) -> usize {
+        self.token_arena_bytes
+    }
+
+    /// How many nodes there are, such as statements and expressions, counting the block of the Ast
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    /// The bytes the nodes take in the boxes and vectors they're in
+    pub fn node_bytes(&self) -> usize {
+        self.node_bytes
+    }
+
+    /// The bytes of the text of tokens that's owned rather than borrowed from the code,
+    /// and of the [source](../struct.Ast.html#method.source) of an owned Ast
+    pub fn string_bytes(&self) -> usize {
+        self.string_bytes
+    }
+
+    /// All of the bytes the Ast takes on the heap
+    pub fn total_bytes(&self) -> usize {
+        self.token_arena_bytes + self.node_bytes + self.string_bytes
+    }
+}
+
+// Adds up the bytes of everything in an Ast, going through all of it
+#[derive(Default)]
+pub(crate) struct HeapCounter {
+    report: MemoryReport,
+    // The allocations already counted, since clones of tokens point to the same positions and types
+    seen: BTreeSet<usize>,
+}
+
+impl HeapCounter {
+    pub(crate) fn add_node(&mut self) {
+        self.report.node_count += 1;
+    }
+
+    // Adds the allocation of an Arc, with its counts, returning false if it was already counted
+    fn add_arc<T>(&mut self, arc: &Arc<T>) -> bool {
+        if self.seen.insert(Arc::as_ptr(arc) as *const () as usize) {
+            self.report.token_arena_bytes += mem::size_of::<(usize, usize, T)>();
+            true
+        } else {
+            false
+        }
+    }
+
+    // Adds an arena and the tokens in it, unless it was already counted, such as when nodes borrow from it
+    fn add_arena<'a>(&mut self, arena: &Arc<Arena<Token<'a>>>) {
+        if self.add_arc(arena) {
+            self.report.token_arena_bytes += arena.capacity() * mem::size_of::<Entry<Token<'a>>>();
+            for (_, token) in arena.iter() {
+                token.add_heap_size(self);
+            }
+        }
+    }
+}
+
+// The same as an entry of generational-arena, which is private, for how much space each token takes in the arena
+#[allow(dead_code)]
+enum Entry<T> {
+    Free { next_free: Option<usize> },
+    Occupied { generation: u64, value: T },
+}
+
+/// Nodes, and what they're made of, which can say how much memory they take on the heap,
+/// not counting their own size, and free what they don't use.
+/// Derived for nodes with `HeapSize` from full-moon-derive.
+pub(crate) trait HeapSize {
+    fn add_heap_size(&self, counter: &mut HeapCounter);
+
+    fn shrink_to_fit(&mut self);
+}
+
+impl<'a> Ast<'a> {
+    /// How many bytes the Ast takes on the heap, found by going through all of its nodes and tokens, including trivia.
+    /// Tokens and nodes shared with clones of the Ast are counted too, so the Ast may take less than this on its own,
+    /// and so are the arenas of other Asts that nodes put into this one borrow their tokens from.
+    ///
+    /// ```rust
+    /// let code = "local x = 1 -- one";
+    /// let ast = full_moon::parse(code).unwrap();
+    /// let report = ast.memory_usage();
+    /// assert!(report.node_count() > 0);
+    /// assert_eq!(report.string_bytes(), 0);
+    ///
+    /// let owned = full_moon::parse_owned(code.to_owned()).unwrap();
+    /// assert!(owned.memory_usage().string_bytes() > 0);
+    /// ```
+    pub fn memory_usage(&self) -> MemoryReport {
+        let mut counter = HeapCounter::default();
+
+        counter.add_arena(&self.tokens);
+
+        if let Some(comments) = &self.comments {
+            counter.report.token_arena_bytes += comments.capacity() * mem::size_of::<Token<'a>>();
+            for comment in comments {
+                comment.add_heap_size(&mut counter);
+            }
+        }
+
+        if let Some(Cow::Owned(source)) = &self.source {
+            counter.report.string_bytes += source.capacity();
+        }
+
+        self.nodes.add_heap_size(&mut counter);
+        counter.report
+    }
+
+    /// Frees the space the Ast's vectors and strings have but don't use, such as from growing while parsing,
+    /// for keeping the Ast around for a long time.
+    /// The arena of tokens is always made with the space it needs already.
+    ///
+    /// ```rust
+    /// let mut ast = full_moon::parse("local x, y, z = 1, 2, 3").unwrap();
+    /// let before = ast.memory_usage();
+    /// ast.shrink_to_fit();
+    /// assert!(ast.memory_usage().total_bytes() <= before.total_bytes());
+    /// assert_eq!(full_moon::print(&ast), "local x, y, z = 1, 2, 3");
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        for (_, token) in self.tokens.iter() {
+            shrink_text(token);
+        }
+
+        if let Some(comments) = &mut self.comments {
+            comments.shrink_to_fit();
+        }
+
+        if let Some(Cow::Owned(source)) = &mut self.source {
+            source.shrink_to_fit();
+        }
+
+        self.nodes.shrink_to_fit();
+    }
+
+    /// Drops the tokens in the arena of the Ast that none of its nodes have anymore, such as those of nodes that were
+    /// replaced with a [`VisitorMut`](../visitors/trait.VisitorMut.html), along with the comments and whitespace
+    /// around them, so that they don't take memory for as long as the Ast is kept.
+    /// The trivia around a token is decided the same way as for [`TriviaBehavior`](../node/enum.TriviaBehavior.html).
+    ///
+    /// The tokens that are kept stay in the same order, with the same positions, so the positions after tokens
+    /// that were dropped don't match the code [`print`](../fn.print.html) gives anymore. If you need them to,
+    /// call [`update_positions`](#method.update_positions) afterwards.
+    /// Tokens that nodes borrow from the arenas of other Asts aren't moved into the Ast's arena.
+    ///
+    /// ```rust
+    /// # use full_moon::{ast::Stmt, visitors::VisitorMut};
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// struct ReplaceCall<'ast>(Stmt<'ast>);
+    ///
+    /// impl<'ast> VisitorMut<'ast> for ReplaceCall<'ast> {
+    ///     fn visit_stmt(&mut self, stmt: &mut Stmt<'ast>) {
+    ///         if let Stmt::FunctionCall(_) = stmt {
+    ///             *stmt = self.0.clone();
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut ast = full_moon::parse("local x = 1\ncall(x) -- call\nreturn x")?;
+    /// let replacement = full_moon::parse("x = 2")?;
+    /// ReplaceCall(replacement.nodes().iter_stmts().next().unwrap().clone()).visit_ast(&mut ast);
+    /// assert_eq!(full_moon::print(&ast), "local x = 1\ncall(x) -- call\nreturn x");
+    ///
+    /// ast.compact();
+    /// assert_eq!(full_moon::print(&ast), "local x = 1\nreturn x");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compact(&mut self) {
+        let mut live = LiveTokens {
+            arena: &self.tokens,
+            offsets: BTreeSet::new(),
+        };
+        self.nodes.visit(&mut live);
+
+        // The trivia before a token leads it, and the trivia after it on the same line trails it,
+        // so both are kept only when the token is
+        let mut kept = Vec::with_capacity(live.offsets.len());
+        let mut leading = Vec::new();
+        let mut trailing = None;
+
+        for (index, token) in self.tokens.iter() {
+            let offset = index.into_raw_parts().0;
+            if !token.token_type().ignore() {
+                // The end of the code isn't in the nodes
+                let is_live =
+                    live.offsets.contains(&offset) || token.token_kind() == TokenKind::Eof;
+                if is_live {
+                    kept.append(&mut leading);
+                    kept.push(offset);
+                } else {
+                    leading.clear();
+                }
+
+                trailing = Some(is_live);
+                continue;
+            }
+
+            match trailing {
+                Some(is_live) => {
+                    if is_live {
+                        kept.push(offset);
+                    }
+
+                    if token.token_kind() == TokenKind::Whitespace
+                        && token.to_string().contains('\n')
+                    {
+                        trailing = None;
+                    }
+                }
+
+                None => leading.push(offset),
+            }
+        }
+
+        if kept.len() == self.tokens.len() {
+            return;
+        }
+
+        let old = Arc::clone(&self.tokens);
+        let offsets: BTreeMap<usize, usize> = kept
+            .iter()
+            .enumerate()
+            .map(|(new, old)| (*old, new))
+            .collect();
+        let new = Arc::new(
+            kept.iter()
+                .filter_map(|offset| old.get_unknown_gen(*offset))
+                .map(|(token, _)| token.clone())
+                .collect::<Arena<_>>(),
+        );
+
+        self.nodes.visit_mut(&mut Reborrow {
+            old: &old,
+            new: &new,
+            offsets: &offsets,
+        });
+        self.tokens = new;
+    }
+}
+
+// Points token references from one arena to the same tokens in another
+pub(crate) struct Reborrow<'a, 'b> {
+    pub(crate) old: &'b Arc<Arena<Token<'a>>>,
+    pub(crate) new: &'b Arc<Arena<Token<'a>>>,
+    pub(crate) offsets: &'b BTreeMap<usize, usize>,
+}
+
+impl<'a, 'b> Reborrow<'a, 'b> {
+    fn reborrow(&self, token: &mut TokenReference<'a>) {
+        let offset = match token.arena_offset() {
+            Some((arena, offset)) if Arc::ptr_eq(arena, self.old) => offset,
+            _ => return,
+        };
+
+        if let Some((_, index)) = self
+            .offsets
+            .get(&offset)
+            .and_then(|offset| self.new.get_unknown_gen(*offset))
+        {
+            *token = TokenReference::Borrowed {
+                arena: Arc::clone(self.new),
+                index,
+            };
+        }
+    }
+}
+
+impl<'a, 'b> VisitorMut<'a> for Reborrow<'a, 'b> {
+    fn visit_token(&mut self, token: &mut TokenReference<'a>) {
+        self.reborrow(token);
+    }
+
+    // The tokens of contained spans aren't visited on their own
+    fn visit_contained_span(&mut self, span: &mut ContainedSpan<'a>) {
+        let (open, close) = span.tokens_mut();
+        self.reborrow(open);
+        self.reborrow(close);
+    }
+}
+
+// The offsets of the tokens in the arena that nodes have
+struct LiveTokens<'a, 'b> {
+    arena: &'b Arc<Arena<Token<'a>>>,
+    offsets: BTreeSet<usize>,
+}
+
+impl<'a, 'b> LiveTokens<'a, 'b> {
+    fn token(&mut self, token: &TokenReference<'a>) {
+        if let Some((arena, offset)) = token.arena_offset() {
+            if Arc::ptr_eq(arena, self.arena) {
+                self.offsets.insert(offset);
+            }
+        }
+    }
+}
+
+impl<'a, 'b> Visitor<'a> for LiveTokens<'a, 'b> {
+    fn visit_token(&mut self, token: &TokenReference<'a>) {
+        self.token(token);
+    }
+
+    // The tokens of contained spans aren't visited on their own
+    fn visit_contained_span(&mut self, span: &ContainedSpan<'a>) {
+        let (open, close) = span.tokens();
+        self.token(open);
+        self.token(close);
+    }
+}
+
+// The text of the token, if it has any
+fn text<'a, 'b>(token_type: &'b TokenType<'a>) -> Option<&'b Cow<'a, str>> {
+    match token_type {
+        TokenType::Eof | TokenType::Symbol { .. } => None,
+        TokenType::Identifier { identifier } => Some(identifier),
+        TokenType::MultiLineComment { comment, .. } => Some(comment),
+        TokenType::Number { text } => Some(text),
+        TokenType::SingleLineComment { comment } => Some(comment),
+        TokenType::StringLiteral { literal, .. } => Some(literal),
+        TokenType::Whitespace { characters } => Some(characters),
+    }
+}
+
+// Tokens in the arena can be shared with clones of the Ast, so their text is shrunk through their cell,
+// unless it's being borrowed
+fn shrink_text(token: &Token) {
+    if let Ok(mut token_type) = token.token_type.try_borrow_mut() {
+        let text = match &mut *token_type {
+            TokenType::Eof | TokenType::Symbol { .. } => None,
+            TokenType::Identifier { identifier } => Some(identifier),
+            TokenType::MultiLineComment { comment, .. } => Some(comment),
+            TokenType::Number { text } => Some(text),
+            TokenType::SingleLineComment { comment } => Some(comment),
+            TokenType::StringLiteral { literal, .. } => Some(literal),
+            TokenType::Whitespace { characters } => Some(characters),
+        };
+
+        if let Some(Cow::Owned(text)) = text {
+            text.shrink_to_fit();
+        }
+    }
+}
+
+impl HeapSize for Token<'_> {
+    fn add_heap_size(&self, counter: &mut HeapCounter) {
+        counter.add_arc(&self.start_position);
+        counter.add_arc(&self.end_position);
+        if let Some(lines) = &self.lines {
+            if counter.add_arc(lines) {
+                counter.report.token_arena_bytes += lines.heap_size();
+            }
+        }
+        if counter.add_arc(&self.token_type) {
+            if let Some(Cow::Owned(text)) = text(&self.token_type.borrow()) {
+                counter.report.string_bytes += text.capacity();
+            }
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        shrink_text(self);
+    }
+}
+
+impl HeapSize for TokenReference<'_> {
+    // Tokens borrowed from an arena are counted with it
+    fn add_heap_size(&self, counter: &mut HeapCounter) {
+        match self {
+            TokenReference::Borrowed { arena, .. } => counter.add_arena(arena),
+            TokenReference::Owned(token) => token.add_heap_size(counter),
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        if let TokenReference::Owned(token) = self {
+            token.shrink_to_fit();
+        }
+    }
+}
+
+impl HeapSize for StoredId {
+    fn add_heap_size(&self, _: &mut HeapCounter) {}
+
+    fn shrink_to_fit(&mut self) {}
+}
+
+impl<T: HeapSize> HeapSize for Pair<'_, T> {
+    fn add_heap_size(&self, counter: &mut HeapCounter) {
+        match self {
+            Pair::End(value) => value.add_heap_size(counter),
+            Pair::Punctuated(value, punctuation) => {
+                value.add_heap_size(counter);
+                punctuation.add_heap_size(counter);
+            }
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        match self {
+            Pair::End(value) => value.shrink_to_fit(),
+            Pair::Punctuated(value, punctuation) => {
+                value.shrink_to_fit();
+                punctuation.shrink_to_fit();
+            }
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for Punctuated<'_, T> {
+    fn add_heap_size(&self, counter: &mut HeapCounter) {
+        self.pairs.add_heap_size(counter);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.pairs.shrink_to_fit();
+    }
+}
+
+impl<T: HeapSize> HeapSize for Box<T> {
+    fn add_heap_size(&self, counter: &mut HeapCounter) {
+        counter.report.node_bytes += mem::size_of::<T>();
+        (**self).add_heap_size(counter);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        (**self).shrink_to_fit();
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn add_heap_size(&self, counter: &mut HeapCounter) {
+        if let Some(value) = self {
+            value.add_heap_size(counter);
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        if let Some(value) = self {
+            value.shrink_to_fit();
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn add_heap_size(&self, counter: &mut HeapCounter) {
+        counter.report.node_bytes += self.capacity() * mem::size_of::<T>();
+        for value in self {
+            value.add_heap_size(counter);
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        Vec::shrink_to_fit(self);
+        for value in self {
+            value.shrink_to_fit();
+        }
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn add_heap_size(&self, counter: &mut HeapCounter) {
+        self.0.add_heap_size(counter);
+        self.1.add_heap_size(counter);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit();
+        self.1.shrink_to_fit();
+    }
+}
@@ -1,33 +1,62 @@
+mod all_stmts;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+mod drop;
+pub mod memory;
 pub mod owned;
+mod replace_token;
 #[macro_use]
 mod parser_util;
 mod parsers;
+pub(crate) mod printer;
 pub mod punctuated;
+pub mod reparse;
+pub mod simplified;
+pub mod snapshot;
 pub mod span;
-
-use crate::tokenizer::{Symbol, Token, TokenKind, TokenReference, TokenType};
-use full_moon_derive::{Node, Owned, Visit};
+pub mod stream;
+
+use crate::{
+    node::{Node, NodeId, NodePath, StoredId},
+    prelude::*,
+    tokenizer::{Position, Symbol, Token, TokenKind, TokenReference, TokenType},
+    visitors::{
+        self, AnyNode, AnyNodeMut, FromAnyNode, Nodes, NodesOfType, NodesWithPaths, PostOrder,
+        PostOrderMut, TraversalOrder, Visitor, VisitorMut,
+    },
+    ErrorCode,
+};
+use alloc::{borrow::Cow, sync::Arc};
+use core::{
+    fmt,
+    iter::FromIterator,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use full_moon_derive::{Dot, HeapSize, Json, Node, Owned, Visit};
 use generational_arena::Arena;
-use itertools::Itertools;
 #[cfg(feature = "serde")]
-use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, fmt, iter::FromIterator, sync::Arc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "std")]
+use std::io;
 
-use parser_util::{
-    InternalAstError, OneOrMore, Parser, ParserState, ZeroOrMore, ZeroOrMoreDelimited,
-};
+pub(crate) use drop::AstBlock;
+use parser_util::{InternalAstError, OneOrMore, Parser, ParserState, ZeroOrMoreDelimited};
 
 use punctuated::{Pair, Punctuated};
 use span::ContainedSpan;
 
 /// A block of statements, such as in if/do/etc block
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Block<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
-    stmts: Vec<(Stmt<'a>, Option<TokenReference<'a>>)>,
+    pub(crate) stmts: Vec<(Stmt<'a>, Option<TokenReference<'a>>)>,
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
-    last_stmt: Option<(LastStmt<'a>, Option<TokenReference<'a>>)>,
+    pub(crate) last_stmt: Option<(LastStmt<'a>, Option<TokenReference<'a>>)>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> Block<'a> {
@@ -43,7 +72,7 @@ impl<'a> Block<'a> {
 }
 
 /// The last statement of a [`Block`](struct.Block.html)
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum LastStmt<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -54,12 +83,16 @@ pub enum LastStmt<'a> {
 }
 
 /// A `return` statement
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Return<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
     token: TokenReference<'a>,
     returns: Punctuated<'a, Expression<'a>>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> Return<'a> {
@@ -75,7 +108,7 @@ impl<'a> Return<'a> {
 }
 
 /// Fields of a [`TableConstructor`](struct.TableConstructor.html)
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum Field<'a> {
     /// A key in the format of `[expression] = value`
@@ -89,6 +122,11 @@ pub enum Field<'a> {
         equal: TokenReference<'a>,
         /// The `value` part of `[expression] = value`
         value: Expression<'a>,
+        /// The [id](../node/struct.NodeId.html) of the field, `StoredId::default()` for a new one
+        #[node(id)]
+        #[visit(skip)]
+        #[cfg_attr(feature = "serde", serde(skip))]
+        id: StoredId,
     },
 
     /// A key in the format of `name = value`
@@ -100,6 +138,11 @@ pub enum Field<'a> {
         equal: TokenReference<'a>,
         /// The `value` part of `name = value`
         value: Expression<'a>,
+        /// The [id](../node/struct.NodeId.html) of the field, `StoredId::default()` for a new one
+        #[node(id)]
+        #[visit(skip)]
+        #[cfg_attr(feature = "serde", serde(skip))]
+        id: StoredId,
     },
 
     /// A field with no key, just a value (such as `"a"` in `{ "a" }`)
@@ -112,13 +155,17 @@ pub enum Field<'a> {
 pub type TableConstructorField<'a> = (Field<'a>, Option<TokenReference<'a>>);
 
 /// A table being constructed, such as `{ 1, 2, 3 }` or `{ a = 1 }`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct TableConstructor<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
     #[node(full_range)]
     braces: ContainedSpan<'a>,
     fields: Vec<TableConstructorField<'a>>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> TableConstructor<'a> {
@@ -134,13 +181,17 @@ impl<'a> TableConstructor<'a> {
 }
 
 /// A binary operation, such as (`+ 3`)
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[visit(visit_as = "bin_op")]
 pub struct BinOpRhs<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
-    bin_op: BinOp<'a>,
-    rhs: Box<Expression<'a>>,
+    pub(crate) bin_op: BinOp<'a>,
+    pub(crate) rhs: Box<Expression<'a>>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> BinOpRhs<'a> {
@@ -156,9 +207,9 @@ impl<'a> BinOpRhs<'a> {
 }
 
 /// An expression, mostly useful for getting values
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-#[cfg_attr(feature = "serde", serde(untagged))]
+#[cfg_attr(feature = "serde", serde(untagged, remote = "Self"))]
 pub enum Expression<'a> {
     /// A statement in parentheses, such as `(#list)`
     Parentheses {
@@ -168,6 +219,11 @@ pub enum Expression<'a> {
         contained: ContainedSpan<'a>,
         /// The expression inside the parentheses
         expression: Box<Expression<'a>>,
+        /// The [id](../node/struct.NodeId.html) of the expression, `StoredId::default()` for a new one
+        #[node(id)]
+        #[visit(skip)]
+        #[cfg_attr(feature = "serde", serde(skip))]
+        id: StoredId,
     },
 
     /// A unary operation, such as `#list`
@@ -177,6 +233,11 @@ pub enum Expression<'a> {
         unop: UnOp<'a>,
         /// The expression the operation is being done on, the `list` part of `#list`
         expression: Box<Expression<'a>>,
+        /// The [id](../node/struct.NodeId.html) of the expression, `StoredId::default()` for a new one
+        #[node(id)]
+        #[visit(skip)]
+        #[cfg_attr(feature = "serde", serde(skip))]
+        id: StoredId,
     },
 
     /// A value, such as "strings"
@@ -186,11 +247,19 @@ pub enum Expression<'a> {
         value: Box<Value<'a>>,
         /// The binary operation being done, if one exists (the `+ 3` part of `2 + 3`)
         binop: Option<BinOpRhs<'a>>,
+        /// The [id](../node/struct.NodeId.html) of the expression, `StoredId::default()` for a new one
+        #[node(id)]
+        #[visit(skip)]
+        #[cfg_attr(feature = "serde", serde(skip))]
+        id: StoredId,
     },
+
+    /// Code that couldn't be parsed, only made by [`parse_fallible`](../fn.parse_fallible.html) and [`parse_all_errors`](../fn.parse_all_errors.html)
+    Error(ErrorExpression<'a>),
 }
 
 /// Values that cannot be used standalone, but as part of things such as [statements](enum.Stmt.html)
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum Value<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -213,7 +282,7 @@ pub enum Value<'a> {
 }
 
 /// A statement that stands alone
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum Stmt<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -221,6 +290,8 @@ pub enum Stmt<'a> {
     Assignment(Assignment<'a>),
     /// A do block, `do end`
     Do(Do<'a>),
+    /// Code that couldn't be parsed, only made by [`parse_fallible`](../fn.parse_fallible.html) and [`parse_all_errors`](../fn.parse_all_errors.html)
+    Error(ErrorStmt<'a>),
     /// A function call on its own, such as `call()`
     FunctionCall(FunctionCall<'a>),
     /// A function declaration, such as `function x() end`
@@ -243,7 +314,7 @@ pub enum Stmt<'a> {
 
 /// A node used before another in cases such as function calling
 /// The `("foo")` part of `("foo"):upper()`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum Prefix<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -255,7 +326,7 @@ pub enum Prefix<'a> {
 
 /// The indexing of something, such as `x.y` or `x["y"]`
 /// Values of variants are the keys, such as `"y"`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum Index<'a> {
     /// Indexing in the form of `x["y"]`
@@ -265,6 +336,11 @@ pub enum Index<'a> {
         brackets: ContainedSpan<'a>,
         /// The `"y"` part of `["y"]`
         expression: Expression<'a>,
+        /// The [id](../node/struct.NodeId.html) of the index, `StoredId::default()` for a new one
+        #[node(id)]
+        #[visit(skip)]
+        #[cfg_attr(feature = "serde", serde(skip))]
+        id: StoredId,
     },
 
     /// Indexing in the form of `x.y`
@@ -274,11 +350,16 @@ pub enum Index<'a> {
         dot: TokenReference<'a>,
         /// The `y` part of `.y`
         name: TokenReference<'a>,
+        /// The [id](../node/struct.NodeId.html) of the index, `StoredId::default()` for a new one
+        #[node(id)]
+        #[visit(skip)]
+        #[cfg_attr(feature = "serde", serde(skip))]
+        id: StoredId,
     },
 }
 
 /// Arguments used for a function
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum FunctionArgs<'a> {
     /// Used when a function is called in the form of `call(1, 2, 3)`
@@ -289,6 +370,11 @@ pub enum FunctionArgs<'a> {
         /// The `(...) part of (1, 2, 3)`
         #[node(full_range)]
         parentheses: ContainedSpan<'a>,
+        /// The [id](../node/struct.NodeId.html) of the arguments, `StoredId::default()` for a new one
+        #[node(id)]
+        #[visit(skip)]
+        #[cfg_attr(feature = "serde", serde(skip))]
+        id: StoredId,
     },
     /// Used when a function is called in the form of `call "foobar"`
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -298,7 +384,7 @@ pub enum FunctionArgs<'a> {
 }
 
 /// A numeric for loop, such as `for index = 1, 10 do end`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct NumericFor<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -313,6 +399,10 @@ pub struct NumericFor<'a> {
     do_token: TokenReference<'a>,
     block: Block<'a>,
     end_token: TokenReference<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> NumericFor<'a> {
@@ -377,7 +467,7 @@ impl<'a> NumericFor<'a> {
 }
 
 /// A generic for loop, such as `for index, value in pairs(list) do end`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct GenericFor<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -388,6 +478,10 @@ pub struct GenericFor<'a> {
     do_token: TokenReference<'a>,
     block: Block<'a>,
     end_token: TokenReference<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> GenericFor<'a> {
@@ -430,19 +524,23 @@ impl<'a> GenericFor<'a> {
 }
 
 /// An if statement
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct If<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
-    if_token: TokenReference<'a>,
-    condition: Expression<'a>,
-    then_token: TokenReference<'a>,
-    block: Block<'a>,
-    else_if: Option<Vec<ElseIf<'a>>>,
-    else_token: Option<TokenReference<'a>>,
+    pub(crate) if_token: TokenReference<'a>,
+    pub(crate) condition: Expression<'a>,
+    pub(crate) then_token: TokenReference<'a>,
+    pub(crate) block: Block<'a>,
+    pub(crate) else_if: Option<Vec<ElseIf<'a>>>,
+    pub(crate) else_token: Option<TokenReference<'a>>,
     #[cfg_attr(feature = "serde", serde(rename = "else"))]
-    r#else: Option<Block<'a>>,
-    end_token: TokenReference<'a>,
+    pub(crate) r#else: Option<Block<'a>>,
+    pub(crate) end_token: TokenReference<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> If<'a> {
@@ -490,14 +588,18 @@ impl<'a> If<'a> {
 }
 
 /// An elseif block in a bigger [`If`](struct.If.html) statement
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct ElseIf<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
-    else_if_token: TokenReference<'a>,
-    condition: Expression<'a>,
-    then_token: TokenReference<'a>,
-    block: Block<'a>,
+    pub(crate) else_if_token: TokenReference<'a>,
+    pub(crate) condition: Expression<'a>,
+    pub(crate) then_token: TokenReference<'a>,
+    pub(crate) block: Block<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> ElseIf<'a> {
@@ -523,7 +625,7 @@ impl<'a> ElseIf<'a> {
 }
 
 /// A while loop
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct While<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -532,6 +634,10 @@ pub struct While<'a> {
     do_token: TokenReference<'a>,
     block: Block<'a>,
     end_token: TokenReference<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> While<'a> {
@@ -562,14 +668,18 @@ impl<'a> While<'a> {
 }
 
 /// A repeat loop
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Repeat<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
-    repeat_token: TokenReference<'a>,
-    block: Block<'a>,
-    until_token: TokenReference<'a>,
-    until: Expression<'a>,
+    pub(crate) repeat_token: TokenReference<'a>,
+    pub(crate) block: Block<'a>,
+    pub(crate) until_token: TokenReference<'a>,
+    pub(crate) until: Expression<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> Repeat<'a> {
@@ -595,13 +705,17 @@ impl<'a> Repeat<'a> {
 }
 
 /// A method call, such as `x:y()`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct MethodCall<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
     colon_token: TokenReference<'a>,
     name: TokenReference<'a>,
     args: FunctionArgs<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> MethodCall<'a> {
@@ -622,7 +736,7 @@ impl<'a> MethodCall<'a> {
 }
 
 /// Something being called
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum Call<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -633,7 +747,7 @@ pub enum Call<'a> {
 }
 
 /// A function body, everything except `function x` in `function x(a, b, c) call() end`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct FunctionBody<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -641,6 +755,10 @@ pub struct FunctionBody<'a> {
     parameters: Punctuated<'a, Parameter<'a>>,
     block: Block<'a>,
     end_token: TokenReference<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> FunctionBody<'a> {
@@ -666,7 +784,7 @@ impl<'a> FunctionBody<'a> {
 }
 
 /// A parameter in a function declaration
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum Parameter<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -678,7 +796,7 @@ pub enum Parameter<'a> {
 
 /// A suffix in certain cases, such as `:y()` in `x:y()`
 /// Can be stacked on top of each other, such as in `x()()()`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum Suffix<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -689,12 +807,16 @@ pub enum Suffix<'a> {
 }
 
 /// A complex expression used by [`Var`](enum.Var.html), consisting of both a prefix and suffixes
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct VarExpression<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
-    prefix: Prefix<'a>,
-    suffixes: Vec<Suffix<'a>>,
+    pub(crate) prefix: Prefix<'a>,
+    pub(crate) suffixes: Vec<Suffix<'a>>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> VarExpression<'a> {
@@ -710,7 +832,7 @@ impl<'a> VarExpression<'a> {
 }
 
 /// Used in [`Assignment`s](struct.Assignment.html) and [`Value`s](enum.Value.html)
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum Var<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -721,13 +843,17 @@ pub enum Var<'a> {
 }
 
 /// An assignment, such as `x = y`. Not used for [`LocalAssignment`s](struct.LocalAssignment.html)
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Assignment<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
     var_list: Punctuated<'a, Var<'a>>,
     equal_token: TokenReference<'a>,
     expr_list: Punctuated<'a, Expression<'a>>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> Assignment<'a> {
@@ -750,7 +876,7 @@ impl<'a> Assignment<'a> {
 }
 
 /// A declaration of a local function, such as `local function x() end`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct LocalFunction<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -758,6 +884,10 @@ pub struct LocalFunction<'a> {
     function_token: TokenReference<'a>,
     name: TokenReference<'a>,
     func_body: FunctionBody<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> LocalFunction<'a> {
@@ -783,7 +913,7 @@ impl<'a> LocalFunction<'a> {
 }
 
 /// An assignment to a local variable, such as `local x = 1`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct LocalAssignment<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
@@ -791,6 +921,10 @@ pub struct LocalAssignment<'a> {
     name_list: Punctuated<'a, TokenReference<'a>>,
     equal_token: Option<TokenReference<'a>>,
     expr_list: Punctuated<'a, Expression<'a>>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> LocalAssignment<'a> {
@@ -825,13 +959,17 @@ impl<'a> LocalAssignment<'a> {
 
 /// A `do` block, such as `do ... end`
 /// This is not used for things like `while true do end`, only those on their own
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Do<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
-    do_token: TokenReference<'a>,
-    block: Block<'a>,
-    end_token: TokenReference<'a>,
+    pub(crate) do_token: TokenReference<'a>,
+    pub(crate) block: Block<'a>,
+    pub(crate) end_token: TokenReference<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> Do<'a> {
@@ -851,13 +989,56 @@ impl<'a> Do<'a> {
     }
 }
 
+/// Code in place of a statement that couldn't be parsed, only made by [`parse_fallible`](../fn.parse_fallible.html) and [`parse_all_errors`](../fn.parse_all_errors.html)
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ErrorStmt<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    tokens: Vec<TokenReference<'a>>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
+}
+
+impl<'a> ErrorStmt<'a> {
+    /// The tokens that were skipped, in order
+    pub fn tokens(&self) -> impl Iterator<Item = &TokenReference<'a>> {
+        self.tokens.iter()
+    }
+}
+
+/// Code in place of an expression that couldn't be parsed, only made by [`parse_fallible`](../fn.parse_fallible.html) and [`parse_all_errors`](../fn.parse_all_errors.html).
+/// There are no tokens when the expression is missing, such as in `local x =` at the end of the code.
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ErrorExpression<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    tokens: Vec<TokenReference<'a>>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
+}
+
+impl<'a> ErrorExpression<'a> {
+    /// The tokens that were skipped, in order
+    pub fn tokens(&self) -> impl Iterator<Item = &TokenReference<'a>> {
+        self.tokens.iter()
+    }
+}
+
 /// A function being called, such as `call()`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct FunctionCall<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
-    prefix: Prefix<'a>,
-    suffixes: Vec<Suffix<'a>>,
+    pub(crate) prefix: Prefix<'a>,
+    pub(crate) suffixes: Vec<Suffix<'a>>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> FunctionCall<'a> {
@@ -873,12 +1054,16 @@ impl<'a> FunctionCall<'a> {
 }
 
 /// A function name when being [declared](struct.FunctionDeclaration.html)
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct FunctionName<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
     names: Punctuated<'a, TokenReference<'a>>,
     colon_name: Option<(TokenReference<'a>, TokenReference<'a>)>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> FunctionName<'a> {
@@ -896,13 +1081,17 @@ impl<'a> FunctionName<'a> {
 
 /// A normal function declaration, supports simple declarations like `function x() end`
 /// as well as complicated declarations such as `function x.y.z:a() end`
-#[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct FunctionDeclaration<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
     function_token: TokenReference<'a>,
     name: FunctionName<'a>,
     body: FunctionBody<'a>,
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) id: StoredId,
 }
 
 impl<'a> FunctionDeclaration<'a> {
@@ -924,7 +1113,7 @@ impl<'a> FunctionDeclaration<'a> {
 
 macro_rules! make_op {
     ($enum:ident, $(#[$outer:meta])* { $($operator:ident,)+ }) => {
-        #[derive(Clone, Debug, PartialEq, Owned, Node, Visit)]
+        #[derive(Clone, Debug, PartialEq, Owned, Node, Visit, Json, Dot, HeapSize)]
         #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
         #[visit(skip_visit_self)]
         $(#[$outer])*
@@ -935,6 +1124,16 @@ macro_rules! make_op {
                 $operator(TokenReference<'a>),
             )+
         }
+
+        impl<'a> $enum<'a> {
+            pub(crate) fn token(&self) -> &TokenReference<'a> {
+                match self {
+                    $(
+                        $enum::$operator(token) => token,
+                    )+
+                }
+            }
+        }
     };
 }
 
@@ -968,6 +1167,57 @@ make_op!(UnOp,
     }
 );
 
+/// Something that would have been valid in place of the token that caused an [`AstError`](enum.AstError.html)
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum ExpectedItem {
+    /// A specific symbol, such as `)` or `end`
+    Symbol(Symbol),
+    /// A name, such as `x`
+    Identifier,
+    /// A number, such as `1`
+    Number,
+    /// A string, such as `"foo"`
+    String,
+    /// Any expression, such as `x + 1`
+    Expression,
+    /// Any statement, such as `local x = 1`
+    Statement,
+    /// The end of the code
+    Eof,
+}
+
+impl fmt::Display for ExpectedItem {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpectedItem::Symbol(symbol) => write!(formatter, "`{}`", symbol),
+            ExpectedItem::Identifier => write!(formatter, "an identifier"),
+            ExpectedItem::Number => write!(formatter, "a number"),
+            ExpectedItem::String => write!(formatter, "a string"),
+            ExpectedItem::Expression => write!(formatter, "an expression"),
+            ExpectedItem::Statement => write!(formatter, "a statement"),
+            ExpectedItem::Eof => write!(formatter, "the end of the code"),
+        }
+    }
+}
+
+// Such as "expected one of `,`, `)` or an expression", None if nothing was expected
+pub(crate) fn expected_message(expected: &[ExpectedItem]) -> Option<String> {
+    match expected {
+        [] => None,
+        [item] => Some(format!("expected {}", item)),
+        [items @ .., last] => Some(format!(
+            "expected one of {} or {}",
+            items
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            last
+        )),
+    }
+}
+
 /// An error that occurs when creating the ast *after* tokenizing
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
@@ -983,38 +1233,471 @@ pub enum AstError<'a> {
         token: Token<'a>,
         /// Any additional information that could be provided for debugging
         additional: Option<Cow<'a, str>>,
+        /// For tokens that were expected to close a block or brackets, such as the `end` of `do` or a `)`,
+        /// the token that opened them, such as the `do` or the `(`
+        #[cfg_attr(
+            feature = "serde",
+            serde(borrow, default, skip_serializing_if = "Option::is_none")
+        )]
+        opening: Option<Token<'a>>,
+        /// Everything that would have been valid in place of the token, sorted and without duplicates.
+        /// Empty if the parser didn't try anything at the token, such as when an error is found after going past it
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Vec::is_empty")
+        )]
+        expected: Vec<ExpectedItem>,
+    },
+    /// The code has more blocks in each other than the
+    /// [`recursion_limit`](struct.ParseConfig.html#structfield.recursion_limit), such as thousands of `do`s,
+    /// or more expressions in each other than the
+    /// [`expression_depth_limit`](struct.ParseConfig.html#structfield.expression_depth_limit),
+    /// such as thousands of `(`s
+    RecursionLimitExceeded {
+        /// The position of the token that went past the limit
+        position: Position,
+        /// The recursion limit that was used
+        limit: usize,
     },
+    /// Parsing was stopped by the [`cancellation`](struct.ParseConfig.html#structfield.cancellation) token
+    /// before it was done
+    Cancelled,
+    /// A reserved keyword where there has to be a name, such as `local end = 1` or `function f(nil)`.
+    /// Keywords can't be names anywhere, but this is only given where nothing other than a name could be,
+    /// since anywhere else the keyword could be the start of other code
+    ReservedKeywordAsName {
+        /// The keyword that was used as a name
+        keyword: Symbol,
+        /// The position the keyword starts at
+        position: Position,
+    },
+    /// An end of file token before the last of the tokens passed to [`parse_tokens`](../fn.parse_tokens.html)
+    EofBeforeEnd {
+        /// The position of the end of file token
+        position: Position,
+    },
+    /// A token passed to [`parse_tokens`](../fn.parse_tokens.html) that starts before the token before it ends,
+    /// or ends before it starts
+    TokenOutOfOrder {
+        /// The position the token starts at
+        position: Position,
+    },
+    /// The id passed to [`Ast::replace_token_text`](struct.Ast.html#method.replace_token_text)
+    /// is of a node of the Ast that isn't a token, such as a [`Stmt`](enum.Stmt.html)
+    NotAToken,
+    /// The id passed to [`Ast::replace_token_text`](struct.Ast.html#method.replace_token_text)
+    /// isn't of anything in the Ast, such as a token of another Ast
+    TokenNotInAst,
+}
+
+impl<'a> AstError<'a> {
+    // Parsers only return `NoMatch` to their callers, which turn it into an unexpected token
+    fn from_internal(error: InternalAstError<'a>) -> Self {
+        match error {
+            InternalAstError::UnexpectedToken {
+                token,
+                additional,
+                opening,
+                expected,
+            } => AstError::UnexpectedToken {
+                token: (*token).to_owned(),
+                additional: additional.map(Cow::Borrowed),
+                opening: opening.map(|opening| (*opening).to_owned()),
+                expected,
+            },
+
+            InternalAstError::RecursionLimitExceeded { token, limit } => {
+                AstError::RecursionLimitExceeded {
+                    position: (*token).start_position(),
+                    limit,
+                }
+            }
+
+            InternalAstError::Cancelled => AstError::Cancelled,
+
+            InternalAstError::ReservedKeywordAsName { token, keyword } => {
+                AstError::ReservedKeywordAsName {
+                    keyword,
+                    position: (*token).start_position(),
+                }
+            }
+
+            InternalAstError::NoMatch => unreachable!("no match errors are never returned"),
+        }
+    }
+
+    /// The start and end positions of the token that caused the error, if there is one.
+    /// For [`RecursionLimitExceeded`](#variant.RecursionLimitExceeded), both are the position the limit was exceeded at
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// let error = match full_moon::parse("local x = ]") {
+    ///     Err(full_moon::Error::AstError(error)) => error,
+    ///     _ => unreachable!(),
+    /// };
+    ///
+    /// let (start, end) = error.range().unwrap();
+    /// assert_eq!((start.bytes(), end.bytes()), (10, 11));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn range(&self) -> Option<(Position, Position)> {
+        match self {
+            AstError::UnexpectedToken { token, .. } => {
+                Some((token.start_position(), token.end_position()))
+            }
+            AstError::RecursionLimitExceeded { position, .. }
+            | AstError::EofBeforeEnd { position }
+            | AstError::TokenOutOfOrder { position } => Some((*position, *position)),
+            AstError::ReservedKeywordAsName { keyword, position } => {
+                // Keywords are never on more than one line
+                let length = keyword.to_string().len();
+                Some((
+                    *position,
+                    Position {
+                        bytes: position.bytes + length,
+                        character: position.character + length,
+                        line: position.line,
+                    },
+                ))
+            }
+            AstError::Empty
+            | AstError::NoEof
+            | AstError::Cancelled
+            | AstError::NotAToken
+            | AstError::TokenNotInAst => None,
+        }
+    }
+
+    /// When the code ended before a block or brackets were closed, the symbol that opened the innermost one,
+    /// such as `function`, `if`, `repeat`, or `(`, and where it starts.
+    /// Errors anywhere in an unclosed block have it if they're at the end of the code, even if they aren't about the block.
+    ///
+    /// ```rust
+    /// # use full_moon::tokenizer::Symbol;
+    /// let error = match full_moon::parse("function f()\n  local x =") {
+    ///     Err(full_moon::Error::AstError(error)) => error,
+    ///     _ => unreachable!(),
+    /// };
+    ///
+    /// let (symbol, position) = error.unclosed().unwrap();
+    /// assert_eq!(symbol, Symbol::Function);
+    /// assert_eq!(position.bytes(), 0);
+    /// ```
+    pub fn unclosed(&self) -> Option<(Symbol, Position)> {
+        match self {
+            AstError::UnexpectedToken {
+                token,
+                opening: Some(opening),
+                ..
+            } if *token.token_type() == TokenType::Eof => match &*opening.token_type() {
+                TokenType::Symbol { symbol } => Some((*symbol, opening.start_position())),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The code of the kind of error, see [`ErrorCode`](../enum.ErrorCode.html)
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AstError::Empty => ErrorCode::EmptyTokens,
+            AstError::NoEof => ErrorCode::NoEof,
+            AstError::UnexpectedToken { additional, .. } => {
+                ErrorCode::from_additional(additional.as_deref())
+            }
+            AstError::RecursionLimitExceeded { .. } => ErrorCode::RecursionLimitExceeded,
+            AstError::Cancelled => ErrorCode::Cancelled,
+            AstError::ReservedKeywordAsName { .. } => ErrorCode::ReservedKeywordAsName,
+            AstError::EofBeforeEnd { .. } => ErrorCode::EofBeforeEnd,
+            AstError::TokenOutOfOrder { .. } => ErrorCode::TokenOutOfOrder,
+            AstError::NotAToken => ErrorCode::NotAToken,
+            AstError::TokenNotInAst => ErrorCode::TokenNotInAst,
+        }
+    }
+
+    /// What went wrong, without the token or where it is, such as `unexpected token`
+    /// or `unexpected end of code`. This is the start of the first line of the `Display` output.
+    pub fn message(&self) -> &str {
+        match self {
+            AstError::Empty => "tokens passed was empty, which shouldn't happen normally",
+            AstError::NoEof => "tokens passed had no eof token, which shouldn't happen normally",
+            AstError::UnexpectedToken { token, .. } => match &*token.token_type() {
+                TokenType::Eof => "unexpected end of code",
+                _ => "unexpected token",
+            },
+            AstError::RecursionLimitExceeded { .. } => "code is nested too deeply",
+            AstError::Cancelled => "parsing was cancelled",
+            AstError::ReservedKeywordAsName { .. } => "reserved keyword used as a name",
+            AstError::EofBeforeEnd { .. } => "eof token before the end of the tokens",
+            AstError::TokenOutOfOrder { .. } => "token out of order",
+            AstError::NotAToken => "node to replace the text of isn't a token",
+            AstError::TokenNotInAst => "token to replace the text of isn't in the ast",
+        }
+    }
+
+    /// The start and end positions of the token that opened the block or brackets that weren't closed,
+    /// such as the `do` for `do` without an `end`
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// let error = match full_moon::parse("call()\nwhile x do\n  call()") {
+    ///     Err(full_moon::Error::AstError(error)) => error,
+    ///     _ => unreachable!(),
+    /// };
+    ///
+    /// let (start, end) = error.opening_range().unwrap();
+    /// assert_eq!((start.bytes(), end.bytes()), (7, 12));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn opening_range(&self) -> Option<(Position, Position)> {
+        match self {
+            AstError::UnexpectedToken {
+                opening: Some(opening),
+                ..
+            } => Some((opening.start_position(), opening.end_position())),
+            _ => None,
+        }
+    }
 }
 
 impl<'a> fmt::Display for AstError<'a> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            AstError::Empty => write!(formatter, "tokens passed was empty, which shouldn't happen normally"),
-            AstError::NoEof => write!(formatter, "tokens passed had no eof token, which shouldn't happen normally"),
-            AstError::UnexpectedToken { token, additional } => write!(
+        formatter.write_str(self.message())?;
+
+        if let Some((position, _)) = self.range() {
+            write!(
                 formatter,
-                "unexpected token `{}`. (starting from line {}, character {} and ending on line {}, character {}){}",
+                " at line {}, character {}",
+                position.line(),
+                position.character()
+            )?;
+        }
+
+        match self {
+            AstError::UnexpectedToken {
                 token,
-                token.start_position().line(),
-                token.start_position().character(),
-                token.end_position().line(),
-                token.end_position().character(),
-                match additional {
-                    Some(additional) => format!("\nadditional information: {}", additional),
-                    None => String::new(),
+                additional,
+                opening,
+                expected,
+            } => {
+                if *token.token_type() != TokenType::Eof {
+                    write!(formatter, "\nfound `{}`", token)?;
+                }
+
+                if let Some(additional) = additional {
+                    write!(formatter, "\nadditional information: {}", additional)?;
+                }
+
+                if let Some(opening) = opening {
+                    write!(
+                        formatter,
+                        "\nto close `{}` on line {}, character {}",
+                        opening,
+                        opening.start_position().line(),
+                        opening.start_position().character(),
+                    )?;
+                }
+
+                if let Some(message) = expected_message(expected) {
+                    write!(formatter, "\n{}", message)?;
                 }
-            )
+            }
+
+            AstError::RecursionLimitExceeded { limit, .. } => {
+                write!(formatter, "\nthe recursion limit is {}", limit)?;
+            }
+
+            AstError::ReservedKeywordAsName { keyword, .. } => {
+                write!(formatter, "\n{}", reserved_keyword_message(*keyword))?;
+            }
+
+            AstError::Empty
+            | AstError::NoEof
+            | AstError::Cancelled
+            | AstError::EofBeforeEnd { .. }
+            | AstError::TokenOutOfOrder { .. }
+            | AstError::NotAToken
+            | AstError::TokenNotInAst => {}
         }
+
+        Ok(())
     }
 }
 
+#[cfg(feature = "std")]
 impl<'a> std::error::Error for AstError<'a> {}
 
+// What is wrong with the keyword being used as a name, shown under the error
+pub(crate) fn reserved_keyword_message(keyword: Symbol) -> String {
+    format!(
+        "`{}` is a reserved keyword and cannot be used as a name here",
+        keyword
+    )
+}
+
+/// The options for [`parse_with`](../fn.parse_with.html), [`parse_fallible`](../fn.parse_fallible.html),
+/// and [`Ast::from_tokens_with`](struct.Ast.html#method.from_tokens_with).
+/// Use [`ParseConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseConfig {
+    /// How many blocks and expressions can be in each other, counting the block of the whole code, 200 by default.
+    /// Every block in a block counts, such as of a `do` or a function, along with the expression of a statement.
+    /// Expressions in expressions are limited by [`expression_depth_limit`](#structfield.expression_depth_limit)
+    /// instead. Code nested more deeply than this gives an
+    /// [`AstError::RecursionLimitExceeded`](enum.AstError.html#variant.RecursionLimitExceeded)
+    /// instead of overflowing the stack, which matters when parsing code that isn't trusted.
+    /// Parsing and using nodes takes more stack the more deeply they're nested: the defaults fit in a thread with
+    /// a 2 MiB stack, but raising them may need a thread with a bigger stack.
+    pub recursion_limit: usize,
+    /// How many expressions can be in each other, such as in parentheses, square brackets, function calls,
+    /// tables, or after operators, 200 by default. Expressions are parsed without recursing, but visiting,
+    /// cloning, comparing, and debug formatting them recurses, so this keeps an Ast that parsed fine from overflowing
+    /// the stack later. Expressions nested more deeply than this also give an
+    /// [`AstError::RecursionLimitExceeded`](enum.AstError.html#variant.RecursionLimitExceeded),
+    /// with this as its limit. Raise it for deeply nested data, such as big serialized tables,
+    /// along with the stack size of the threads using the Ast.
+    pub expression_depth_limit: usize,
+    /// A token to stop parsing with, such as when the code has changed and is going to be parsed again,
+    /// None by default. Once the token is cancelled, parsing stops partway through
+    /// with an [`AstError::Cancelled`](enum.AstError.html#variant.Cancelled),
+    /// and everything parsed up to then is dropped. With `parse_fallible`, the code that wasn't parsed yet
+    /// is put in a [`Stmt::Error`](enum.Stmt.html#variant.Error) instead, after the statements that were.
+    pub cancellation: Option<CancellationToken>,
+    /// How many errors [`parse_fallible`](../fn.parse_fallible.html) finds at most, 100 by default.
+    /// Parsing goes on to the end of the code after this many, but the errors after them aren't kept.
+    /// Everything else stops at the first error, so this doesn't change them.
+    pub max_errors: usize,
+    /// Whether the Ast keeps the whitespace and comments of the code in its tokens, true by default.
+    /// Turning it off is for only analyzing the code, such as linting it: parsing makes no tokens for whitespace,
+    /// which is most of the tokens of most code, and the comments are kept apart from the other tokens,
+    /// in [`Ast::comments`](struct.Ast.html#method.comments). Visitors and positions are the same as with it on,
+    /// but the code can't be printed back exactly: [`print`](../fn.print.html) puts spaces and new lines
+    /// between the tokens instead of the whitespace that was there, and printing nodes on their own puts nothing
+    /// between their tokens, since there's nothing in the Ast to print there.
+    pub preserve_trivia: bool,
+    /// Whether the Ast keeps the code it was parsed from, for [`Ast::source`](struct.Ast.html#method.source)
+    /// and [`Ast::source_of`](struct.Ast.html#method.source_of), true by default. Asts borrow from their code
+    /// already, so keeping it costs nothing, other than a copy of it when the Ast is made owned.
+    pub retain_source: bool,
+}
+
+impl Default for ParseConfig {
+    fn default() -> Self {
+        Self {
+            recursion_limit: 200,
+            expression_depth_limit: 200,
+            cancellation: None,
+            max_errors: 100,
+            preserve_trivia: true,
+            retain_source: true,
+        }
+    }
+}
+
+// Takes the whitespace and comments out of the tokens when the config doesn't keep them, giving back the comments
+fn take_trivia<'a>(tokens: &mut Vec<Token<'a>>, config: &ParseConfig) -> Option<Vec<Token<'a>>> {
+    if config.preserve_trivia {
+        return None;
+    }
+
+    let mut comments = Vec::new();
+    tokens.retain(|token| match token.token_kind() {
+        TokenKind::Whitespace => false,
+        TokenKind::SingleLineComment | TokenKind::MultiLineComment => {
+            comments.push(token.clone());
+            false
+        }
+        _ => true,
+    });
+
+    Some(comments)
+}
+
+/// A flag that can be set from any thread to stop parsing that uses it,
+/// through the [`cancellation`](struct.ParseConfig.html#structfield.cancellation) of a ParseConfig.
+/// Clones of a token are the same token, so cancelling one cancels all of them.
+///
+/// The parser checks the token before every statement and expression it parses,
+/// so parsing stops soon after the token is cancelled even for code that takes a long time to parse.
+///
+/// ```rust
+/// # use full_moon::{ast::AstError, CancellationToken, Error, ParseConfig};
+/// let cancellation = CancellationToken::new();
+/// let config = ParseConfig {
+///     cancellation: Some(cancellation.clone()),
+///     ..ParseConfig::default()
+/// };
+///
+/// assert!(full_moon::parse_with("local x = 1", &config).is_ok());
+///
+/// cancellation.cancel();
+/// assert!(matches!(
+///     full_moon::parse_with("local x = 1", &config),
+///     Err(Error::AstError(AstError::Cancelled))
+/// ));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that isn't cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token, so that parsing using it or any of its clones stops.
+    /// A token can't be uncancelled: make a new one for parsing again.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancellationToken {
+    /// Uses the flag as the token, so that setting it to true cancels the token
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self(flag)
+    }
+}
+
+impl PartialEq for CancellationToken {
+    /// Whether the tokens are clones of each other, since tokens that are cancelled together are the same token
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CancellationToken {}
+
 /// An abstract syntax tree, contains all the nodes used in the code
+///
+/// With the `serde` feature, an Ast can be serialized along with all of its tokens, such as to cache it,
+/// and deserialized into an Ast that prints, visits, and changes the same as the one that was serialized.
+///
+/// ```rust
+/// # fn main() -> Result<(), serde_json::Error> {
+/// let ast = full_moon::parse("local x = 1 -- one").unwrap();
+/// let json = serde_json::to_string(&ast)?;
+/// let deserialized: full_moon::ast::Ast = serde_json::from_str(&json)?;
+/// assert_eq!(full_moon::print(&deserialized), "local x = 1 -- one");
+/// assert_eq!(deserialized.nodes(), ast.nodes());
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Clone, Debug)]
 pub struct Ast<'a> {
-    nodes: Block<'a>,
+    nodes: AstBlock<'a>,
     pub(crate) tokens: Arc<Arena<Token<'a>>>,
+    // The comments when parsed without trivia, which aren't in the tokens then, or None when they are
+    pub(crate) comments: Option<Vec<Token<'a>>>,
+    // The code the Ast was parsed from, when it's kept
+    pub(crate) source: Option<Cow<'a, str>>,
 }
 
 impl<'a> Ast<'a> {
@@ -1028,65 +1711,219 @@ impl<'a> Ast<'a> {
     ///
     /// More likely, if the tokens pass are invalid Lua 5.1 code, an
     /// UnexpectedToken error will be returned.
+    ///
+    /// The tokens are used as they are, without tokenizing their code again, so they can come from
+    /// [`into_tokens`](#method.into_tokens) or [`tokenizer::tokens`](../tokenizer/fn.tokens.html) and be changed first.
+    /// Their positions are kept too, and the tokens are printed in the order of their positions, so if any of them
+    /// were changed, added, or made from a [`TokenType`](../tokenizer/enum.TokenType.html), call
+    /// [`update_positions`](#method.update_positions) on the Ast.
+    ///
+    /// ```rust
+    /// use full_moon::{ast::Ast, tokenizer::{self, Token, TokenType}};
+    /// use std::borrow::Cow;
+    ///
+    /// let tokens = tokenizer::tokens("local x = 1").unwrap();
+    /// let tokens = tokens.into_iter().map(|token| match &*token.token_type() {
+    ///     TokenType::Identifier { .. } => Token::from(TokenType::Identifier {
+    ///         identifier: Cow::Borrowed("renamed"),
+    ///     }),
+    ///     _ => token.clone(),
+    /// });
+    ///
+    /// let mut ast = Ast::from_tokens(tokens.collect()).unwrap();
+    /// ast.update_positions();
+    /// assert_eq!(full_moon::print(&ast), "local renamed = 1");
+    /// ```
+    ///
+    /// Uses the default [`ParseConfig`](struct.ParseConfig.html), so code that is nested
+    /// more deeply than 200 blocks and expressions gives a RecursionLimitExceeded error.
     pub fn from_tokens(tokens: Vec<Token<'a>>) -> Result<Ast<'a>, AstError<'a>> {
-        if *tokens.last().ok_or(AstError::Empty)?.token_type() != TokenType::Eof {
-            Err(AstError::NoEof)
-        } else {
-            let tokens = Arc::new(Arena::from_iter(tokens));
+        Ast::from_tokens_with(tokens, &ParseConfig::default())
+    }
 
-            let mut state = ParserState::new(Arc::clone(&tokens));
+    /// Create an Ast from the passed tokens like [`from_tokens`](#method.from_tokens),
+    /// using the options of the [config](struct.ParseConfig.html)
+    ///
+    /// # Errors
+    ///
+    /// The same as `from_tokens`, along with RecursionLimitExceeded if the code is nested more deeply than the recursion limit,
+    /// and Cancelled if the cancellation token is cancelled before parsing is done.
+    pub fn from_tokens_with(
+        mut tokens: Vec<Token<'a>>,
+        config: &ParseConfig,
+    ) -> Result<Ast<'a>, AstError<'a>> {
+        Ast::from_tokens_reusing(&mut tokens, config, &mut Vec::new()).map_err(|(error, _)| error)
+    }
+
+    // Like `from_tokens_with`, but takes the tokens out of the vector and uses the buffer for what was expected while
+    // parsing, so a `Parser` can reuse their capacity. Both are left empty, unless the tokens are empty or have no Eof.
+    // Errors at a token come with its index in the arena, which is its index in the tokens when trivia is kept.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn from_tokens_reusing(
+        tokens: &mut Vec<Token<'a>>,
+        config: &ParseConfig,
+        expected: &mut Vec<ExpectedItem>,
+    ) -> Result<Ast<'a>, (AstError<'a>, Option<usize>)> {
+        let last = tokens.last().ok_or((AstError::Empty, None))?;
+        if *last.token_type() != TokenType::Eof {
+            Err((AstError::NoEof, None))
+        } else {
+            let comments = take_trivia(tokens, config);
+            let tokens = Arc::new(Arena::from_iter(tokens.drain(..)));
+
+            let state = ParserState::new(Arc::clone(&tokens))
+                .with_recursion_limit(config.recursion_limit)
+                .with_expression_depth_limit(config.expression_depth_limit)
+                .with_cancellation(config.cancellation.clone())
+                .with_expected_buffer(core::mem::take(expected));
+            let result = Ast::parse_state(tokens, state.clone());
+            *expected = state.take_expected_buffer();
+            result.map(|ast| Ast { comments, ..ast })
+        }
+    }
 
-            if tokens
-                .iter()
-                .filter(|token| !token.1.token_type().ignore())
-                .count()
-                == 1
-            {
-                // Entirely comments/whitespace
-                return Ok(Ast {
-                    nodes: Block {
-                        stmts: Vec::new(),
-                        last_stmt: None,
-                    },
-                    tokens,
-                });
-            }
+    // Parses the tokens from the state at their start
+    #[allow(clippy::result_large_err)]
+    fn parse_state(
+        tokens: Arc<Arena<Token<'a>>>,
+        mut state: ParserState<'a>,
+    ) -> Result<Ast<'a>, (AstError<'a>, Option<usize>)> {
+        if tokens
+            .iter()
+            .filter(|token| !token.1.token_type().ignore())
+            .count()
+            == 1
+        {
+            // Entirely comments/whitespace
+            return Ok(Ast {
+                nodes: AstBlock::new(Block {
+                    stmts: Vec::new(),
+                    last_stmt: None,
+                    id: StoredId::new(),
+                }),
+                tokens,
+                comments: None,
+                source: None,
+            });
+        }
 
-            // ParserState has to have at least 2 tokens, the last being an EOF, thus unwrap() can't fail
-            if state.peek().token_type().ignore() {
-                state = state.advance().unwrap();
-            }
+        // ParserState has to have at least 2 tokens, the last being an EOF, thus unwrap() can't fail
+        if state.peek().token_type().ignore() {
+            state = state.advance().unwrap();
+        }
 
-            match parsers::ParseBlock.parse(state.clone()) {
-                Ok((state, block)) => {
-                    if state.index == tokens.len() - 1 {
-                        Ok(Ast {
-                            tokens,
-                            nodes: block,
-                        })
-                    } else {
-                        Err(AstError::UnexpectedToken {
-                            token: (*state.peek()).to_owned(),
-                            additional: Some(Cow::Borrowed("leftover token")),
-                        })
-                    }
+        match parsers::ParseBlock.parse(state.clone()) {
+            Ok((state, block)) => {
+                if state.index == tokens.len() - 1 {
+                    Ok(Ast {
+                        tokens,
+                        nodes: AstBlock::new(block),
+                        comments: None,
+                        source: None,
+                    })
+                } else {
+                    drop(AstBlock::new(block));
+                    state.expect(ExpectedItem::Eof);
+                    let error = AstError::UnexpectedToken {
+                        token: (*state.peek()).to_owned(),
+                        additional: ErrorCode::LeftoverToken.additional().map(Cow::Borrowed),
+                        opening: None,
+                        expected: state.expected(),
+                    };
+                    Err((error, Some(state.index)))
                 }
+            }
 
-                Err(InternalAstError::NoMatch) => Err(AstError::UnexpectedToken {
+            Err(InternalAstError::NoMatch) => {
+                let error = AstError::UnexpectedToken {
                     token: (*state.peek()).to_owned(),
                     additional: None,
-                }),
+                    opening: None,
+                    expected: state.expected(),
+                };
+                Err((error, Some(state.index)))
+            }
 
-                Err(InternalAstError::UnexpectedToken { token, additional }) => {
-                    Err(AstError::UnexpectedToken {
-                        token: (*token).to_owned(),
-                        additional: additional.map(Cow::Borrowed),
-                    })
-                }
+            Err(InternalAstError::Cancelled) => Err((AstError::Cancelled, None)),
+
+            Err(error) => {
+                let index = parsers::error_index(&state, &error);
+                Err((AstError::from_internal(error), Some(index)))
             }
         }
     }
 
+    // An Ast with no code, for when the code couldn't be tokenized
+    pub(crate) fn empty() -> Ast<'a> {
+        let start = Position {
+            bytes: 0,
+            character: 1,
+            line: 1,
+        };
+
+        Ast {
+            nodes: AstBlock::new(Block {
+                stmts: Vec::new(),
+                last_stmt: None,
+                id: StoredId::new(),
+            }),
+            tokens: Arc::new(Arena::from_iter(vec![Token::new(TokenType::Eof, start)])),
+            comments: None,
+            source: None,
+        }
+    }
+
+    // Like `from_tokens_with`, but with up to `max_errors` errors found by parsing the top level statements one at a time,
+    // and an Ast with the code that couldn't be parsed in error nodes
+    pub(crate) fn from_tokens_with_errors(
+        mut tokens: Vec<Token<'a>>,
+        config: &ParseConfig,
+    ) -> (Option<Ast<'a>>, Vec<AstError<'a>>) {
+        match tokens.last() {
+            None => return (None, vec![AstError::Empty]),
+            Some(last) if *last.token_type() != TokenType::Eof => {
+                return (None, vec![AstError::NoEof])
+            }
+            Some(_) => {}
+        }
+
+        let comments = take_trivia(&mut tokens, config);
+        let tokens = Arc::new(Arena::from_iter(tokens));
+        let new_state = || {
+            ParserState::new(Arc::clone(&tokens))
+                .with_recursion_limit(config.recursion_limit)
+                .with_expression_depth_limit(config.expression_depth_limit)
+                .with_cancellation(config.cancellation.clone())
+        };
+
+        // Valid code is parsed once, the same as without finding every error
+        let error = match Ast::parse_state(Arc::clone(&tokens), new_state()) {
+            Ok(ast) => return (Some(Ast { comments, ..ast }), Vec::new()),
+            Err((error, _)) => error,
+        };
+
+        let mut state = new_state();
+        if state.peek().token_type().ignore() {
+            state = state.advance().unwrap();
+        }
+
+        let (block, errors) = parsers::recover_errors(state, config.max_errors.max(1));
+        let mut errors: Vec<_> = errors.into_iter().map(AstError::from_internal).collect();
+        if errors.is_empty() {
+            errors.push(error);
+        }
+
+        (
+            Some(Ast {
+                tokens,
+                nodes: AstBlock::new(block),
+                comments,
+                source: None,
+            }),
+            errors,
+        )
+    }
+
     /// The entire code of the function
     ///
     /// ```rust
@@ -1104,26 +1941,699 @@ impl<'a> Ast<'a> {
         &mut self.nodes
     }
 
+    /// A lazy iterator over every node of type `T` in the Ast, in the order they appear
+    ///
+    /// ```rust
+    /// # use full_moon::ast::FunctionCall;
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// let ast = full_moon::parse("foo(bar())")?;
+    /// assert_eq!(ast.nodes_of_type::<FunctionCall>().count(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn nodes_of_type<'b, T: FromAnyNode<'a>>(&'b self) -> NodesOfType<'a, 'b, T> {
+        NodesOfType::new(Nodes::new(self.nodes()))
+    }
+
+    /// The first node of type `T` in the Ast, if there is one.
+    /// Stops walking the tree once it is found.
+    pub fn first_node_of_type<'b, T: FromAnyNode<'a>>(&'b self) -> Option<&'b T> {
+        self.nodes_of_type().next()
+    }
+
+    /// The node or token in the Ast with the [id](../node/struct.NodeId.html), if there is one.
+    /// This goes through the nodes one at a time until it's found.
+    ///
+    /// ```rust
+    /// # use full_moon::{ast::LocalAssignment, node::Node, visitors::NodeKind};
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// let ast = full_moon::parse("local x = 1")?;
+    /// let id = ast.first_node_of_type::<LocalAssignment>().unwrap().id().unwrap();
+    /// assert_eq!(ast.node_by_id(id).unwrap().kind(), NodeKind::LocalAssignment);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn node_by_id(&self, id: NodeId) -> Option<AnyNode<'a, '_>> {
+        Nodes::new(self.nodes()).find_map(|node| match node {
+            // The tokens of contained spans aren't visited on their own
+            AnyNode::ContainedSpan(span) if span.id() != Some(id) => {
+                let (open, close) = span.tokens();
+                [open, close]
+                    .iter()
+                    .find(|token| token.token_type.id == id)
+                    .map(|token| AnyNode::TokenReference(token))
+            }
+
+            node => Some(node).filter(|node| node.id() == id),
+        })
+    }
+
+    /// A lazy, pre-order iterator over every node and token in the Ast along with the
+    /// [path](../node/struct.NodePath.html) to it
+    pub fn nodes_with_paths(&self) -> NodesWithPaths<'a, '_> {
+        NodesWithPaths::new(AnyNode::Block(&self.nodes))
+    }
+
+    /// The node at the path, if there is one
+    ///
+    /// ```rust
+    /// # use full_moon::visitors::NodeKind;
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// let mut ast = full_moon::parse("local x = 1 call()")?;
+    /// let (path, _) = ast
+    ///     .nodes_with_paths()
+    ///     .find(|(_, node)| node.kind() == NodeKind::FunctionCall)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(ast.get_path(&path).unwrap().kind(), NodeKind::FunctionCall);
+    /// assert_eq!(ast.modify_at_path(&path, |node| node.kind()), Some(NodeKind::FunctionCall));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_path(&self, path: &NodePath) -> Option<AnyNode<'a, '_>> {
+        let mut node = AnyNode::Block(&self.nodes);
+
+        for &index in path.indices() {
+            let mut children = Vec::new();
+            node.visit_children(&mut children);
+            node = *children.get(index)?;
+        }
+
+        Some(node)
+    }
+
+    /// Calls `modify` with the node at the path and returns its result, or returns None if there is no node there
+    pub fn modify_at_path<R, F: FnOnce(AnyNodeMut<'a, '_>) -> R>(
+        &mut self,
+        path: &NodePath,
+        modify: F,
+    ) -> Option<R> {
+        let mut node = AnyNodeMut::Block(&mut self.nodes);
+
+        for &index in path.indices() {
+            let mut children = Vec::new();
+            node.visit_children_mut(&mut children);
+            node = children.into_iter().nth(index)?;
+        }
+
+        Some(modify(node))
+    }
+
+    /// Visits the top level statements of the Ast in parallel, splitting them into one chunk per thread.
+    /// Every chunk is visited by its own visitor created by `make_visitor`, and the visitors are returned
+    /// in the order of their chunks so that they can be merged.
+    ///
+    /// Unlike [`Visitor::visit_ast`](../visitors/trait.Visitor.html#method.visit_ast), the top level block
+    /// itself is not visited, and tokens are only visited as part of the statements that hold them.
+    /// This means comments and whitespace are not visited.
+    ///
+    /// Only available with the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    pub fn par_visit<V, F>(&self, make_visitor: F) -> Vec<V>
+    where
+        V: Visitor<'a> + Send,
+        F: Fn() -> V + Sync,
+    {
+        use crate::visitors::Visit;
+        use rayon::prelude::*;
+
+        let stmts = &self.nodes.stmts;
+        let threads = rayon::current_num_threads();
+        let chunk_size = stmts.len().div_ceil(threads).max(1);
+
+        let mut chunks: Vec<_> = stmts.chunks(chunk_size).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+
+        let last_chunk = chunks.len() - 1;
+
+        chunks
+            .into_par_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut visitor = make_visitor();
+
+                for stmt in chunk {
+                    stmt.visit(&mut visitor);
+                }
+
+                if index == last_chunk {
+                    self.nodes.last_stmt.visit(&mut visitor);
+                }
+
+                visitor
+            })
+            .collect()
+    }
+
+    /// Visits the Ast with the visitor, in the [order](../visitors/enum.TraversalOrder.html) given
+    ///
+    /// ```rust
+    /// # use full_moon::{ast, visitors::*};
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// #[derive(Default)]
+    /// struct CallLogger(Vec<String>);
+    ///
+    /// impl Visitor<'_> for CallLogger {
+    ///     fn visit_function_call(&mut self, call: &ast::FunctionCall) {
+    ///         if let ast::Prefix::Name(name) = call.prefix() {
+    ///             self.0.push(name.to_string());
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let ast = full_moon::parse("foo(bar())")?;
+    /// let mut logger = CallLogger::default();
+    /// ast.visit_with_order(&mut logger, TraversalOrder::PostOrder);
+    /// assert_eq!(logger.0, vec!["bar", "foo"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn visit_with_order<V: Visitor<'a>>(&self, visitor: &mut V, order: TraversalOrder) {
+        match order {
+            TraversalOrder::PreOrder => visitor.visit_ast(self),
+            TraversalOrder::PostOrder => PostOrder(visitor).visit_ast(self),
+        }
+    }
+
+    /// Visits the Ast with the mutable visitor, in the [order](../visitors/enum.TraversalOrder.html) given
+    pub fn visit_mut_with_order<V: VisitorMut<'a>>(
+        &mut self,
+        visitor: &mut V,
+        order: TraversalOrder,
+    ) {
+        match order {
+            TraversalOrder::PreOrder => visitor.visit_ast(self),
+            TraversalOrder::PostOrder => PostOrderMut(visitor).visit_ast(self),
+        }
+    }
+
+    /// The Ast as JSON, for tools that aren't written in Rust. Every node is an object with its kind, range,
+    /// and children by name, and tokens have their text and the comments and whitespace around them.
+    /// The [config](../struct.JsonConfig.html) can leave out the trivia and ranges to make it smaller.
+    ///
+    /// # Schema
+    /// The output is an object with `"schema"`, the [version of the schema](../constant.JSON_SCHEMA_VERSION.html),
+    /// `"block"`, the node of the [`Block`](struct.Block.html) of the code, and `"eof"`, the token at the end of the code,
+    /// which has the comments and whitespace at the end as its leading trivia. The version only changes when the
+    /// output changes in a way that could break a reader, such as a field being renamed, but new node kinds can be
+    /// added without changing it.
+    ///
+    /// - A node is `{ "kind", "variant", "range", "children" }`. `"kind"` is the type of the node, such as
+    ///   `"LocalAssignment"`. Nodes that are one of many variants, such as `"Stmt"` and `"Expression"`, have a
+    ///   `"variant"`, such as `"LocalAssignment"`, and other nodes have no `"variant"`. `"children"` is an object with
+    ///   the fields of the node by name, such as `"name_list"`, or an array of them in order for variants such as
+    ///   `Stmt::LocalAssignment` that have no field names.
+    /// - A token is `{ "kind": "Token", "token_kind", "text", "range", "leading_trivia", "trailing_trivia" }`.
+    ///   `"token_kind"` is the name of its [`TokenKind`](../tokenizer/enum.TokenKind.html), such as `"Identifier"`,
+    ///   and `"text"` is its code. The comments and whitespace after a token on its line, up to and including the
+    ///   new line, are its trailing trivia, and the rest before the next token are that token's leading trivia.
+    /// - Trivia is `{ "kind": "Trivia", "token_kind", "text", "range" }`.
+    /// - A range is `{ "start", "end" }`, with positions as `{ "bytes", "line", "character" }`. The range of a node
+    ///   with no tokens, such as an empty block, is `null`.
+    /// - A field that might not be there, such as the `else` of an `if`, is `null` if it isn't. A list is an array,
+    ///   and a pair of values, such as a statement and its semicolon, is an array of the two.
+    ///   A punctuated list, such as the names of a `local`, is an array of `{ "value", "punctuation" }`.
+    ///
+    /// The keys of objects are sorted. Only available with the `json` feature.
+    ///
+    /// ```rust
+    /// # use full_moon::JsonConfig;
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// let ast = full_moon::parse("x = 1")?;
+    /// let config = JsonConfig {
+    ///     trivia: false,
+    ///     positions: false,
+    ///     ..JsonConfig::default()
+    /// };
+    ///
+    /// let json: serde_json::Value = serde_json::from_str(&ast.to_json(&config))?;
+    /// assert_eq!(json["schema"], 1);
+    ///
+    /// let stmt = &json["block"]["children"]["stmts"][0][0];
+    /// assert_eq!(stmt["kind"], "Stmt");
+    /// assert_eq!(stmt["variant"], "Assignment");
+    ///
+    /// let equal = &stmt["children"][0]["children"]["equal_token"];
+    /// assert_eq!(equal["text"], "=");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_json(&self, config: &crate::JsonConfig) -> String {
+        crate::json::ast_to_json(self, config)
+    }
+
+    /// Writes the nodes of the Ast as s-expressions, such as `(local_assignment x (value (number 1)))`,
+    /// for comparing Asts in snapshot tests. The same as [`to_sexpr_with`](#method.to_sexpr_with)
+    /// with the default [`SexprConfig`](../debug/struct.SexprConfig.html), which leaves out positions.
+    ///
+    /// Every node is a list of its kind in snake case, such as `local_assignment` or `name_key` for a
+    /// `Field::NameKey`, then its fields in order. Variants that only hold another node, such as `Stmt::If`,
+    /// are written as that node. Tokens are written as their code, other than symbols such as keywords and
+    /// punctuation, since the kind of their node says what they are. Symbols are only written when they're
+    /// the only token of a node that doesn't say what they are, such as `(symbol true)` or `(plus +)`,
+    /// and for every `:`, which tells methods apart from functions, such as `(function_name t : x)`.
+    /// Comments, whitespace, and the brackets of contained spans aren't written, and line breaks in tokens,
+    /// such as in long strings, are written as `\n`.
+    ///
+    /// Lists that fit in 80 columns are on one line, and the rest have each of their children on a line of its own,
+    /// indented by two spaces. Later versions of full_moon write nodes the same way, other than new kinds of nodes.
+    ///
+    /// ```rust
+    /// let ast = full_moon::parse("local x = 1 -- one\nprint(x + 1)").unwrap();
+    /// assert_eq!(ast.to_sexpr(), "\
+    /// (block
+    ///   (local_assignment x (value (number 1)))
+    ///   (function_call
+    ///     (name print)
+    ///     (parentheses (value (name x) (bin_op_rhs (plus +) (value (number 1)))))))
+    /// ");
+    /// ```
+    pub fn to_sexpr(&self) -> String {
+        self.to_sexpr_with(&crate::debug::SexprConfig::default())
+    }
+
+    /// Writes the nodes of the Ast as s-expressions, the same as [`to_sexpr`](#method.to_sexpr),
+    /// with the bytes of the range of every node and token if the [`SexprConfig`](../debug/struct.SexprConfig.html)
+    /// has positions.
+    ///
+    /// ```rust
+    /// # use full_moon::debug::SexprConfig;
+    /// let ast = full_moon::parse("x = y").unwrap();
+    /// let sexpr = ast.to_sexpr_with(&SexprConfig { positions: true });
+    /// assert_eq!(sexpr, "\
+    /// (block@0..5
+    ///   (assignment@0..5 (name@0..1 x@0..1) (value@4..5 (name@4..5 y@4..5))))
+    /// ");
+    /// ```
+    pub fn to_sexpr_with(&self, config: &crate::debug::SexprConfig) -> String {
+        crate::debug::ast_to_sexpr(self, config)
+    }
+
+    /// Encodes the Ast as compact bytes, to cache it and read it back with [`from_bytes`](#method.from_bytes)
+    /// instead of parsing the code again. The bytes start with the
+    /// [version of the format](../constant.BINARY_FORMAT_VERSION.html) and of full_moon that wrote them,
+    /// so bytes from another version are rejected rather than read wrong.
+    /// Only available with the `binary` feature.
+    ///
+    /// ```rust
+    /// let ast = full_moon::parse("local x = 1").unwrap();
+    /// let bytes = ast.to_bytes();
+    /// let cached = full_moon::ast::Ast::from_bytes(&bytes).unwrap();
+    /// assert_eq!(full_moon::print(&cached), "local x = 1");
+    /// assert_eq!(cached.nodes(), ast.nodes());
+    /// ```
+    #[cfg(feature = "binary")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        crate::binary::ast_to_bytes(self)
+    }
+
+    /// Reads an Ast from bytes written by [`to_bytes`](#method.to_bytes), which it borrows from the same way
+    /// a parsed Ast borrows from its code. Bytes written by another version of full_moon give
+    /// [`BinaryError::Version`](../enum.BinaryError.html#variant.Version), which means a cache is stale,
+    /// and bytes that are truncated or corrupted give an error instead of panicking.
+    /// So that reading them can't overflow the stack, Asts with deeply nested nodes, such as a table
+    /// in a table more than about fifty times, can't be read. Only available with the `binary` feature.
+    ///
+    /// ```rust
+    /// # use full_moon::{ast::Ast, BinaryError};
+    /// assert_eq!(Ast::from_bytes(b"local x = 1").unwrap_err(), BinaryError::NotAnAst);
+    ///
+    /// let bytes = full_moon::parse("local x = 1").unwrap().to_bytes();
+    /// match Ast::from_bytes(&bytes[..bytes.len() - 1]) {
+    ///     Err(BinaryError::Invalid { .. }) => {}
+    ///     other => panic!("expected an invalid error, got {:?}", other),
+    /// }
+    /// ```
+    #[cfg(feature = "binary")]
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Ast<'a>, crate::BinaryError> {
+        crate::binary::ast_from_bytes(bytes)
+    }
+
+    /// The [`LineIndex`](../struct.LineIndex.html) of the code of the Ast, for converting the bytes of positions
+    /// to lines and columns in an encoding, such as the UTF-16 code units of the Language Server Protocol
+    pub fn line_index(&self) -> crate::LineIndex {
+        crate::LineIndex::new(&crate::print(self))
+    }
+
     /// An iterator over the tokens used to create the Ast
     pub fn iter_tokens(&self) -> impl Iterator<Item = &Token<'a>> {
-        self.tokens.iter().map(|(_, token)| token).sorted()
+        // Tokens put in at the same bytes, such as ones that were inserted, stay in the order they're in the arena
+        let mut tokens: Vec<_> = self.tokens.iter().map(|(_, token)| token).collect();
+        tokens.sort_by_key(|token| token.start_bytes());
+        tokens.into_iter()
+    }
+
+    /// The comments of the code, in order. They're tokens of the Ast along with the rest, unless it was parsed with
+    /// [`preserve_trivia`](struct.ParseConfig.html#structfield.preserve_trivia) off, which keeps them apart from them.
+    ///
+    /// ```rust
+    /// # use full_moon::ParseConfig;
+    /// let config = ParseConfig {
+    ///     preserve_trivia: false,
+    ///     ..ParseConfig::default()
+    /// };
+    ///
+    /// let ast = full_moon::parse_with("local x = 1 -- one\nlocal y = 2", &config).unwrap();
+    /// let comments: Vec<_> = ast.comments().map(|comment| comment.to_string()).collect();
+    /// assert_eq!(comments, ["-- one"]);
+    /// assert_eq!(ast.iter_tokens().count(), 9);
+    /// ```
+    pub fn comments(&self) -> impl Iterator<Item = &Token<'a>> {
+        self.iter_tokens()
+            .filter(|token| {
+                matches!(
+                    token.token_kind(),
+                    TokenKind::SingleLineComment | TokenKind::MultiLineComment
+                )
+            })
+            .chain(self.comments.iter().flatten())
+    }
+
+    /// Whether the whitespace and comments of the code are tokens of the Ast, which they are unless it was parsed
+    /// with [`preserve_trivia`](struct.ParseConfig.html#structfield.preserve_trivia) off
+    pub fn has_trivia(&self) -> bool {
+        self.comments.is_none()
+    }
+
+    // Keeps the code the Ast was parsed from, if the config says to
+    pub(crate) fn with_source(self, code: &'a str, config: &ParseConfig) -> Self {
+        Self {
+            source: config.retain_source.then_some(Cow::Borrowed(code)),
+            ..self
+        }
+    }
+
+    /// The code the Ast was parsed from, exactly as it was, unless it was parsed with
+    /// [`retain_source`](struct.ParseConfig.html#structfield.retain_source) off. None for Asts that weren't parsed
+    /// from code, such as ones made [from tokens](#method.from_tokens) or deserialized. This doesn't change when
+    /// the Ast is changed, so use [`print`](../fn.print.html) for its code as it is now.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// The code of a node of the Ast exactly as it was written, from its first token to its last, such as for
+    /// hashing it or showing it with its own formatting, without printing it.
+    ///
+    /// None when the Ast has no [source](#method.source), or when the node, or the comments and whitespace inside of it,
+    /// are no longer what was parsed, such as after the Ast was changed, so that the code is never different from
+    /// what the node is now. Only the tokens the node borrows from the Ast are looked at, so nodes from other Asts,
+    /// new nodes, and nodes with new tokens in them have no code, and neither do the nodes of owned Asts,
+    /// which don't borrow their tokens. Use [`stale_source_of`](#method.stale_source_of) for the code
+    /// at the positions of the node even when it changed.
+    ///
+    /// ```rust
+    /// # use full_moon::{ast::LocalAssignment, tokenizer::{TokenReference, TokenType}, visitors::VisitorMut};
+    /// struct Rename;
+    ///
+    /// impl<'a> VisitorMut<'a> for Rename {
+    ///     fn visit_identifier(&mut self, token: &mut TokenReference<'a>) {
+    ///         token.set_token_type(TokenType::Identifier { identifier: "y".into() });
+    ///     }
+    /// }
+    ///
+    /// let mut ast = full_moon::parse("local x  =  { 1,2 } -- x\nprint(x)").unwrap();
+    /// let local = ast.first_node_of_type::<LocalAssignment>().unwrap();
+    /// assert_eq!(ast.source_of(local), Some("local x  =  { 1,2 }"));
+    ///
+    /// Rename.visit_ast(&mut ast);
+    /// let local = ast.first_node_of_type::<LocalAssignment>().unwrap();
+    /// assert_eq!(ast.source_of(local), None);
+    /// assert_eq!(ast.stale_source_of(local), Some("local x  =  { 1,2 }"));
+    /// ```
+    pub fn source_of<'b, N: Node + visitors::Visit<'b>>(&self, node: &N) -> Option<&str>
+    where
+        'a: 'b,
+    {
+        let source = self.source()?;
+        let mut tokens = SourceTokens::new(&self.tokens);
+        node.visit(&mut tokens);
+        let (first, last, count) = tokens.first_and_last()?;
+
+        let get = |offset: usize| self.tokens.get_unknown_gen(offset).map(|(token, _)| token);
+        let start = get(first)?.start_position().bytes();
+        let mut end = start;
+        let mut significant = 0;
+        for offset in first..=last {
+            let token = match get(offset) {
+                Some(token) => token,
+                None => continue,
+            };
+
+            let token_start = token.start_position().bytes();
+            if token_start < end || !self.is_trivia(source.get(end..token_start)?, end) {
+                return None;
+            }
+
+            end = token.end_position().bytes();
+            if source.get(token_start..end)? != token.to_string() {
+                return None;
+            }
+
+            if !token.token_type().ignore() {
+                significant += 1;
+            }
+        }
+
+        // Tokens that were taken out of the node are still in the Ast
+        if significant != count {
+            return None;
+        }
+
+        source.get(start..end)
+    }
+
+    /// The code at the positions of a node in the code the Ast was parsed from, like
+    /// [`source_of`](#method.source_of), but without checking that the node is still what was parsed,
+    /// so the code can be of an older version of the node, or be something else entirely.
+    /// None when the Ast has no [source](#method.source), or the node's positions aren't in it.
+    pub fn stale_source_of<'b, N: Node + visitors::Visit<'b>>(&self, node: &N) -> Option<&str>
+    where
+        'a: 'b,
+    {
+        let mut tokens = SourceTokens::new(&self.tokens);
+        node.visit(&mut tokens);
+        let (start, end) = tokens.range?;
+        self.source()?.get(start..end)
+    }
+
+    // Whether the code between two tokens of an Ast where it starts at the byte is only whitespace and comments.
+    // For Asts with trivia, the code between tokens that are next to each other is always empty.
+    fn is_trivia(&self, code: &str, start: usize) -> bool {
+        let comments = match &self.comments {
+            Some(comments) => comments,
+            None => return code.is_empty(),
+        };
+
+        let end = start + code.len();
+        let is_whitespace = |from: usize, to: usize| {
+            from <= to
+                && code
+                    .get(from - start..to - start)
+                    .is_some_and(|text| text.chars().all(char::is_whitespace))
+        };
+
+        let first = comments.partition_point(|comment| comment.start_position().bytes() < start);
+        let mut bytes = start;
+        for comment in comments[first..]
+            .iter()
+            .take_while(|comment| Token::end_position(comment).bytes() <= end)
+        {
+            let (comment_start, comment_end) = (
+                comment.start_position().bytes(),
+                comment.end_position().bytes(),
+            );
+            if !is_whitespace(bytes, comment_start)
+                || code.get(comment_start - start..comment_end - start)
+                    != Some(&*comment.to_string())
+            {
+                return false;
+            }
+
+            bytes = comment_end;
+        }
+
+        is_whitespace(bytes, end)
+    }
+
+    /// The tokens of the Ast in the order they're printed, including comments, whitespace, and the Eof at the end.
+    /// They own their types and don't share anything with the Ast, so they can be kept, changed, and sent to other
+    /// threads on their own, then parsed again with [`from_tokens`](#method.from_tokens) without tokenizing the code.
+    ///
+    /// ```rust
+    /// # use full_moon::ast::Ast;
+    /// let tokens = full_moon::parse("local x = 1 -- comment").unwrap().into_tokens();
+    /// assert_eq!(tokens.len(), 10);
+    ///
+    /// let ast = Ast::from_tokens(tokens).unwrap();
+    /// assert_eq!(full_moon::print(&ast), "local x = 1 -- comment");
+    /// ```
+    pub fn into_tokens(self) -> Vec<Token<'static>> {
+        self.iter_tokens().map(Token::detached).collect()
+    }
+
+    // The comments and whitespace before and after each token that isn't trivia, by its offset in the arena.
+    // Trivia after a token on the same line, up to and including the new line, trails it,
+    // and the rest of the trivia before the next token leads that one.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn trivia_by_token(
+        &self,
+    ) -> alloc::collections::BTreeMap<usize, (Vec<&Token<'a>>, Vec<&Token<'a>>)> {
+        let mut tokens: Vec<_> = self
+            .tokens
+            .iter()
+            .map(|(index, token)| (index.into_raw_parts().0, token))
+            .collect();
+        tokens.sort_by_key(|&(_, token)| token.start_bytes());
+
+        let mut trivia = alloc::collections::BTreeMap::new();
+        let mut leading = Vec::new();
+        let mut trailing = None;
+
+        for (offset, token) in tokens {
+            if !token.token_type().ignore() {
+                trivia.insert(offset, (core::mem::take(&mut leading), Vec::new()));
+                trailing = Some(offset);
+                continue;
+            }
+
+            match trailing.and_then(|offset| trivia.get_mut(&offset)) {
+                Some((_, trailing_trivia)) => {
+                    trailing_trivia.push(token);
+                    if token.token_kind() == TokenKind::Whitespace
+                        && token.to_string().contains('\n')
+                    {
+                        trailing = None;
+                    }
+                }
+
+                None => leading.push(token),
+            }
+        }
+
+        trivia
+    }
+
+    // The token at the end of the code, which isn't in the nodes
+    pub(crate) fn eof(&self) -> Option<TokenReference<'a>> {
+        self.tokens
+            .iter()
+            .find(|(_, token)| token.token_kind() == TokenKind::Eof)
+            .map(|(index, _)| TokenReference::Borrowed {
+                arena: Arc::clone(&self.tokens),
+                index,
+            })
+    }
+
+    /// Writes the code of the Ast to a writer, without building a `String` first.
+    /// Writes the same code as [`print`](../fn.print.html). Only available with the `std` feature.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// let ast = full_moon::parse("local x = 1")?;
+    /// let mut code = Vec::new();
+    /// ast.write_to(&mut code)?;
+    /// assert_eq!(code, b"local x = 1");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_to<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        for token in self.iter_tokens() {
+            write!(writer, "{}", token)?;
+        }
+
+        Ok(())
+    }
+
+    /// The start positions of the statements that would continue the statement before them if printed,
+    /// such as `(g)()` after `f()`, which Lua reads as the single call `f()(g)()` no matter the whitespace
+    /// between them. These need a semicolon before them to be separate statements.
+    /// Statements after ones ending in a keyword or a value that can't be called, such as `x = 1`, are never ambiguous.
+    ///
+    /// Code that was parsed doesn't have any, since it was already read as one statement, but replacing statements,
+    /// such as with a [`VisitorMut`](../visitors/trait.VisitorMut.html), can make them.
+    /// [`print_pretty`](../fn.print_pretty.html) and [`print_minified`](../fn.print_minified.html)
+    /// put semicolons before them when printing.
+    ///
+    /// ```rust
+    /// # use full_moon::{ast::Stmt, visitors::VisitorMut};
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// struct ReplaceDo<'ast>(Stmt<'ast>);
+    ///
+    /// impl<'ast> VisitorMut<'ast> for ReplaceDo<'ast> {
+    ///     fn visit_stmt(&mut self, stmt: &mut Stmt<'ast>) {
+    ///         if let Stmt::Do(_) = stmt {
+    ///             *stmt = self.0.clone();
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut ast = full_moon::parse("do end\n(g)()")?;
+    /// assert!(ast.ambiguous_statements().is_empty());
+    ///
+    /// let call = full_moon::parse("f()")?.nodes().iter_stmts().next().unwrap().clone();
+    /// ReplaceDo(call).visit_ast(&mut ast);
+    /// assert_eq!(ast.ambiguous_statements()[0].bytes(), 7);
+    ///
+    /// let config = full_moon::PrettyConfig::default();
+    /// assert_eq!(full_moon::print_pretty(&ast, &config), "f();\n(g)()\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn ambiguous_statements(&self) -> Vec<Position> {
+        #[derive(Default)]
+        struct AmbiguousStatements(Vec<Position>);
+
+        impl<'ast> Visitor<'ast> for AmbiguousStatements {
+            fn visit_block(&mut self, block: &Block<'ast>) {
+                for pair in block.stmts.windows(2) {
+                    if let [(previous, None), (stmt, _)] = pair {
+                        if printer::ambiguous(previous, stmt) {
+                            self.0.extend(stmt.start_position());
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut visitor = AmbiguousStatements::default();
+        visitor.visit_ast(self);
+        visitor.0
     }
 
     /// Will update the positions of all the tokens in the tree
     /// Necessary if you are both mutating the tree and need the positions of the tokens
     pub fn update_positions(&mut self) {
-        use crate::tokenizer::Position;
-
-        let mut start_position = Position {
-            bytes: 0,
-            character: 1,
-            line: 1,
+        self.update_positions_from(0);
+    }
+
+    // Updates the positions of the tokens from the one at the offset in the arena on, after the ones before it
+    pub(crate) fn update_positions_from(&mut self, offset: usize) {
+        let previous = offset
+            .checked_sub(1)
+            .and_then(|offset| self.tokens.iter().nth(offset));
+
+        let (mut start_position, mut next_is_new_line) = match previous {
+            Some((_, token)) => (token.end_position(), token.to_string().ends_with('\n')),
+            None => (
+                Position {
+                    bytes: 0,
+                    character: 1,
+                    line: 1,
+                },
+                false,
+            ),
         };
 
-        let mut next_is_new_line = false;
-
-        for (_, token) in self.tokens.iter() {
-            let display = token.to_string();
+        // One string for the text of every token, rather than a new one for each of them
+        let mut display = String::new();
+        for (_, token) in self.tokens.iter().skip(offset) {
+            display.clear();
+            fmt::Write::write_fmt(&mut display, format_args!("{}", token))
+                .expect("writing to a string never fails");
 
             let mut lines = bytecount::count(&display.as_bytes(), b'\n');
             if token.token_kind() == TokenKind::Whitespace {
@@ -1164,3 +2674,265 @@ impl<'a> Ast<'a> {
         }
     }
 }
+
+// Expressions are untagged in formats meant to be read by people, such as JSON, but deserializing an untagged
+// enum buffers all of it to try each variant, so other formats, such as `Ast::to_bytes`, say which variant it is
+#[cfg(feature = "serde")]
+#[derive(Deserialize, Serialize)]
+#[serde(remote = "Expression")]
+enum TaggedExpression<'a> {
+    Parentheses {
+        #[serde(borrow)]
+        contained: ContainedSpan<'a>,
+        expression: Box<Expression<'a>>,
+        #[serde(skip)]
+        id: StoredId,
+    },
+    UnaryOperator {
+        #[serde(borrow)]
+        unop: UnOp<'a>,
+        expression: Box<Expression<'a>>,
+        #[serde(skip)]
+        id: StoredId,
+    },
+    Value {
+        #[serde(borrow)]
+        value: Box<Value<'a>>,
+        binop: Option<BinOpRhs<'a>>,
+        #[serde(skip)]
+        id: StoredId,
+    },
+    Error(ErrorExpression<'a>),
+}
+
+#[cfg(feature = "serde")]
+impl<'a> Serialize for Expression<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            Expression::serialize(self, serializer)
+        } else {
+            TaggedExpression::serialize(self, serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de: 'a, 'a> Deserialize<'de> for Expression<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            Expression::deserialize(deserializer)
+        } else {
+            TaggedExpression::deserialize(deserializer)
+        }
+    }
+}
+
+// How an Ast is serialized: its nodes, every token in the order they are in its arena,
+// and its comments if it was parsed without trivia
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct SerializedAst<'b, 'a> {
+    nodes: &'b Block<'a>,
+    tokens: Vec<&'b Token<'a>>,
+    comments: Option<&'b Vec<Token<'a>>>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct DeserializedAst<'a> {
+    #[serde(borrow)]
+    nodes: Block<'a>,
+    #[serde(borrow)]
+    tokens: Vec<Token<'a>>,
+    #[serde(borrow)]
+    comments: Option<Vec<Token<'a>>>,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> Serialize for Ast<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedAst {
+            nodes: &self.nodes,
+            tokens: self.tokens.iter().map(|(_, token)| token).collect(),
+            comments: self.comments.as_ref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de: 'a, 'a> Deserialize<'de> for Ast<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let DeserializedAst {
+            mut nodes,
+            tokens,
+            comments,
+        } = DeserializedAst::deserialize(deserializer)?;
+        let tokens = Arc::new(Arena::from_iter(tokens));
+
+        let mut linker = TokenLinker::new(&tokens);
+        crate::visitors::VisitMut::visit_mut(&mut nodes, &mut linker);
+        if linker.unlinked {
+            return Err(serde::de::Error::custom(
+                "the nodes of the ast have a token that isn't in its tokens",
+            ));
+        }
+
+        Ok(Ast {
+            nodes: AstBlock::new(nodes),
+            tokens,
+            comments,
+            source: None,
+        })
+    }
+}
+
+// The tokens of a node that it borrows from an arena, for finding its code in the source of the Ast
+struct SourceTokens<'a, 'b> {
+    arena: &'b Arc<Arena<Token<'a>>>,
+    // The offsets of the tokens in the arena, in the order they're visited
+    offsets: Vec<usize>,
+    // The offset of the last token visited in order
+    previous: Option<usize>,
+    // Whether any token isn't from the arena, or is before the token visited before it
+    changed: bool,
+    // The bytes from the start of the first token to the end of the last one, from their positions
+    range: Option<(usize, usize)>,
+}
+
+impl<'a, 'b> SourceTokens<'a, 'b> {
+    fn new(arena: &'b Arc<Arena<Token<'a>>>) -> Self {
+        Self {
+            arena,
+            offsets: Vec::new(),
+            previous: None,
+            changed: false,
+            range: None,
+        }
+    }
+
+    // The offsets of the first and last tokens, if every token is from the arena and no token is there twice
+    fn first_and_last(mut self) -> Option<(usize, usize, usize)> {
+        if self.changed {
+            return None;
+        }
+
+        self.offsets.sort_unstable();
+        if self.offsets.windows(2).any(|pair| pair[0] == pair[1]) {
+            return None;
+        }
+
+        Some((
+            *self.offsets.first()?,
+            *self.offsets.last()?,
+            self.offsets.len(),
+        ))
+    }
+}
+
+impl SourceTokens<'_, '_> {
+    // Contained spans aren't visited in order, as some nodes visit them before what's inside of them and some after
+    fn token(&mut self, token: &TokenReference, in_order: bool) {
+        let (start, end) = (
+            Token::start_position(token).bytes(),
+            Token::end_position(token).bytes(),
+        );
+        self.range = Some(match self.range {
+            Some((first, last)) => (first.min(start), last.max(end)),
+            None => (start, end),
+        });
+
+        let offset = match token.arena_offset() {
+            // The tokens of nodes can have a shorter lifetime than the arena's, so only the pointers are compared
+            Some((arena, offset))
+                if Arc::as_ptr(arena).cast::<()>() == Arc::as_ptr(self.arena).cast() =>
+            {
+                offset
+            }
+            _ => {
+                self.changed = true;
+                return;
+            }
+        };
+
+        if in_order {
+            self.previous = match self.previous {
+                Some(previous) if offset < previous => {
+                    self.changed = true;
+                    Some(previous)
+                }
+                _ => Some(offset),
+            };
+        }
+
+        self.offsets.push(offset);
+    }
+}
+
+impl<'ast> Visitor<'ast> for SourceTokens<'_, '_> {
+    fn visit_token(&mut self, token: &TokenReference<'ast>) {
+        self.token(token, true);
+    }
+
+    // The tokens of contained spans aren't visited on their own
+    fn visit_contained_span(&mut self, span: &ContainedSpan<'ast>) {
+        let (open, close) = span.tokens();
+        self.token(open, false);
+        self.token(close, false);
+    }
+}
+
+// Replaces the deserialized tokens of nodes with the same tokens from the arena, so that changing a token
+// through the nodes changes the code of the Ast, the same as for an Ast that was parsed
+#[cfg(feature = "serde")]
+struct TokenLinker<'a> {
+    arena: Arc<Arena<Token<'a>>>,
+    // The indexes of the tokens in the arena by the bytes they start and end at
+    indexes: alloc::collections::BTreeMap<(usize, usize), Vec<generational_arena::Index>>,
+    unlinked: bool,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> TokenLinker<'a> {
+    fn new(arena: &Arc<Arena<Token<'a>>>) -> Self {
+        let mut indexes = alloc::collections::BTreeMap::<_, Vec<_>>::new();
+        for (index, token) in arena.iter() {
+            indexes
+                .entry((token.start_position().bytes(), token.end_position().bytes()))
+                .or_default()
+                .push(index);
+        }
+
+        Self {
+            arena: Arc::clone(arena),
+            indexes,
+            unlinked: false,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> VisitorMut<'a> for TokenLinker<'a> {
+    fn visit_token(&mut self, token: &mut TokenReference<'a>) {
+        let key = (
+            Token::start_position(token).bytes(),
+            Token::end_position(token).bytes(),
+        );
+        let index = self.indexes.get(&key).and_then(|indexes| {
+            indexes
+                .iter()
+                .find(|index| self.arena[**index] == **token)
+                .copied()
+        });
+
+        match index {
+            Some(index) => {
+                *token = TokenReference::Borrowed {
+                    arena: Arc::clone(&self.arena),
+                    index,
+                }
+            }
+            None => self.unlinked = true,
+        }
+    }
+}
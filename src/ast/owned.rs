@@ -3,9 +3,10 @@
 //! an [`Ast<'a>`](../struct.Ast.html), calling `ast.owned()` on it will produce an owned `Ast<'static>`.
 use super::*;
 use crate::tokenizer::*;
+use crate::{node::StoredId, prelude::*};
 
-use atomic_refcell::AtomicRefCell;
-use std::borrow::Cow;
+use alloc::borrow::Cow;
+use core::ops::{Deref, DerefMut};
 
 /// A trait for getting an owned version of a node.
 /// Refer to the [module documentation](index.html) for more details.
@@ -20,13 +21,78 @@ where
     fn owned(&self) -> Self::Owned;
 }
 
+/// An [`Ast`](../struct.Ast.html) that owns everything in it, along with the code it was parsed from,
+/// made by [`parse_owned`](../../fn.parse_owned.html). It can be kept for as long as it's needed and sent
+/// between threads, such as in a language server or a WASM module, without borrowing the code from anywhere.
+///
+/// It dereferences to the `Ast`, so it can be printed, visited, and changed the same as one.
+///
+/// ```rust
+/// use full_moon::{ast::owned::OwnedAst, visitors::Visitor};
+///
+/// fn parse_file(code: String) -> OwnedAst {
+///     full_moon::parse_owned(code).unwrap()
+/// }
+///
+/// let mut ast = parse_file(String::from("local x = 1"));
+/// assert_eq!(ast.source(), "local x = 1");
+/// assert_eq!(full_moon::print(&ast), "local x = 1");
+///
+/// full_moon::transform::normalize_spacing(&mut ast);
+/// assert_eq!(ast.nodes().iter_stmts().count(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct OwnedAst {
+    source: String,
+    ast: Ast<'static>,
+}
+
+impl OwnedAst {
+    pub(crate) fn new(source: String, ast: Ast<'static>) -> Self {
+        Self { source, ast }
+    }
+
+    /// The code the Ast was parsed from. This doesn't change when the Ast is changed,
+    /// so use [`print`](../../fn.print.html) for the code of the Ast as it is now.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The code the Ast was parsed from and the Ast, to keep either of them without the other
+    pub fn into_parts(self) -> (String, Ast<'static>) {
+        (self.source, self.ast)
+    }
+}
+
+impl Deref for OwnedAst {
+    type Target = Ast<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.ast
+    }
+}
+
+impl DerefMut for OwnedAst {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.ast
+    }
+}
+
 impl Owned for Ast<'_> {
     type Owned = Ast<'static>;
 
     fn owned(&self) -> Self::Owned {
         Ast {
-            nodes: self.nodes.owned(),
+            nodes: AstBlock::new(self.nodes.owned()),
             tokens: Arc::new(self.tokens.iter().map(|(_, token)| token.owned()).collect()),
+            comments: self
+                .comments
+                .as_ref()
+                .map(|comments| comments.iter().map(Token::owned).collect()),
+            source: self
+                .source
+                .as_ref()
+                .map(|source| Cow::Owned(source.clone().into_owned())),
         }
     }
 }
@@ -36,13 +102,58 @@ impl Owned for AstError<'_> {
 
     fn owned(&self) -> Self::Owned {
         match self {
-            AstError::UnexpectedToken { token, additional } => AstError::UnexpectedToken {
+            AstError::UnexpectedToken {
+                token,
+                additional,
+                opening,
+                expected,
+            } => AstError::UnexpectedToken {
                 additional: additional.clone().map(Cow::into_owned).map(Cow::Owned),
                 token: token.owned(),
+                opening: opening.as_ref().map(Owned::owned),
+                expected: expected.clone(),
             },
 
+            AstError::RecursionLimitExceeded { position, limit } => {
+                AstError::RecursionLimitExceeded {
+                    position: *position,
+                    limit: *limit,
+                }
+            }
+
             AstError::Empty => AstError::Empty,
             AstError::NoEof => AstError::NoEof,
+            AstError::Cancelled => AstError::Cancelled,
+            AstError::NotAToken => AstError::NotAToken,
+            AstError::TokenNotInAst => AstError::TokenNotInAst,
+            AstError::EofBeforeEnd { position } => AstError::EofBeforeEnd {
+                position: *position,
+            },
+            AstError::TokenOutOfOrder { position } => AstError::TokenOutOfOrder {
+                position: *position,
+            },
+
+            AstError::ReservedKeywordAsName { keyword, position } => {
+                AstError::ReservedKeywordAsName {
+                    keyword: *keyword,
+                    position: *position,
+                }
+            }
+        }
+    }
+}
+
+impl Owned for crate::Error<'_> {
+    type Owned = crate::Error<'static>;
+
+    fn owned(&self) -> Self::Owned {
+        match self {
+            crate::Error::AstError(error) => crate::Error::AstError(error.owned()),
+            crate::Error::TokenizerError(error) => crate::Error::TokenizerError(error.owned()),
+            crate::Error::TokensError { index, error } => crate::Error::TokensError {
+                index: *index,
+                error: error.owned(),
+            },
         }
     }
 }
@@ -81,11 +192,23 @@ impl Owned for Token<'_> {
         Token {
             start_position: self.start_position.clone(),
             end_position: self.end_position.clone(),
-            token_type: Arc::new(AtomicRefCell::new(self.token_type().owned())),
+            token_type: Arc::new(TokenTypeCell::new(
+                self.token_type().owned(),
+                self.token_type.id,
+            )),
+            lines: self.lines.clone(),
         }
     }
 }
 
+impl Owned for StoredId {
+    type Owned = StoredId;
+
+    fn owned(&self) -> Self::Owned {
+        *self
+    }
+}
+
 impl Owned for TokenizerError {
     type Owned = TokenizerError;
 
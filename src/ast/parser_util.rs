@@ -1,15 +1,24 @@
 // Exported macros are documented since no amount of allow(missing_docs) silenced the lint
 
-use super::punctuated::{Pair, Punctuated};
+use super::{
+    punctuated::{Pair, Punctuated},
+    CancellationToken, ExpectedItem, ParseConfig,
+};
 use crate::{
     node::Node,
-    tokenizer::{Token, TokenReference},
+    prelude::*,
+    tokenizer::{Symbol, Token, TokenReference, TokenType},
     visitors::{Visit, VisitMut},
+    ErrorCode,
+};
+use alloc::{rc::Rc, sync::Arc};
+use core::{
+    cell::{Cell, RefCell},
+    fmt,
 };
 use generational_arena::Arena;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::{fmt, sync::Arc};
 
 // This is cloned everywhere, so make sure cloning is as inexpensive as possible
 #[derive(Clone)]
@@ -17,14 +26,123 @@ pub struct ParserState<'a> {
     pub index: usize,
     pub len: usize,
     pub tokens: Arc<Arena<Token<'a>>>,
+    // Shared by every state made from the same one
+    expected: Rc<RefCell<Expected>>,
+    // The tokens that opened the blocks being parsed, such as a `function`, innermost first
+    open_blocks: Option<Rc<OpenBlock<'a>>>,
+    // How many blocks and expressions are being parsed in each other, shared like `expected`
+    depth: Rc<Depth>,
+    recursion_limit: usize,
+    expression_depth_limit: usize,
+    cancellation: Option<Rc<CancellationToken>>,
+    // The furthest index peeked at, only kept track of when asked for with `with_furthest`
+    furthest: Option<Rc<Cell<usize>>>,
+}
+
+#[derive(Default)]
+struct Depth {
+    // Levels of nesting counted with `nest`, for both blocks and expressions
+    levels: Cell<usize>,
+    // Expressions being parsed in each other, set by the parser of expressions as it goes into them
+    expressions: Cell<usize>,
+}
+
+pub struct Nesting<'a> {
+    depth: Rc<Depth>,
+    exceeded: Option<InternalAstError<'a>>,
+}
+
+impl<'a> Nesting<'a> {
+    // The result of what was parsed in this level of nesting, or the error if it was past the recursion limit
+    pub fn check<T>(
+        self,
+        result: Result<T, InternalAstError<'a>>,
+    ) -> Result<T, InternalAstError<'a>> {
+        match (result, &self.exceeded) {
+            (Ok(_), Some(exceeded)) => Err(exceeded.clone()),
+            (result, _) => result,
+        }
+    }
+}
+
+impl Drop for Nesting<'_> {
+    fn drop(&mut self) {
+        self.depth.levels.set(self.depth.levels.get() - 1);
+    }
+}
+
+struct OpenBlock<'a> {
+    token: TokenReference<'a>,
+    parent: Option<Rc<OpenBlock<'a>>>,
 }
 
+// What was tried at the last token anything was tried at, so errors there can say what would have been valid
+#[derive(Clone, Default)]
+struct Expected {
+    index: usize,
+    items: Vec<ExpectedItem>,
+}
+
+// What was expected at some point, to be put back later with `restore_expected`
+pub struct SavedExpected(Expected);
+
 impl<'a> ParserState<'a> {
     pub fn new(tokens: Arc<Arena<Token<'a>>>) -> ParserState<'a> {
         ParserState {
             index: 0,
             len: tokens.len(),
             tokens,
+            expected: Rc::new(RefCell::new(Expected::default())),
+            open_blocks: None,
+            depth: Rc::new(Depth::default()),
+            recursion_limit: ParseConfig::default().recursion_limit,
+            expression_depth_limit: ParseConfig::default().expression_depth_limit,
+            cancellation: None,
+            furthest: None,
+        }
+    }
+
+    pub fn with_recursion_limit(self, recursion_limit: usize) -> ParserState<'a> {
+        ParserState {
+            recursion_limit,
+            ..self
+        }
+    }
+
+    pub fn with_expression_depth_limit(self, expression_depth_limit: usize) -> ParserState<'a> {
+        ParserState {
+            expression_depth_limit,
+            ..self
+        }
+    }
+
+    // Uses the vector for what was expected, so its capacity is reused. Get it back with `take_expected_buffer`.
+    pub fn with_expected_buffer(self, mut items: Vec<ExpectedItem>) -> ParserState<'a> {
+        items.clear();
+        *self.expected.borrow_mut() = Expected { index: 0, items };
+        self
+    }
+
+    // The vector for what was expected, cleared, leaving an empty one in its place
+    pub fn take_expected_buffer(&self) -> Vec<ExpectedItem> {
+        let mut items = core::mem::take(&mut self.expected.borrow_mut().items);
+        items.clear();
+        items
+    }
+
+    pub fn with_cancellation(self, cancellation: Option<CancellationToken>) -> ParserState<'a> {
+        ParserState {
+            cancellation: cancellation.map(Rc::new),
+            ..self
+        }
+    }
+
+    // Keeps track of the furthest index peeked at in the cell, such as for knowing whether parsing looked at the end
+    // of tokens that stop before the end of the code
+    pub fn with_furthest(self, furthest: Rc<Cell<usize>>) -> ParserState<'a> {
+        ParserState {
+            furthest: Some(furthest),
+            ..self
         }
     }
 
@@ -36,6 +154,13 @@ impl<'a> ParserState<'a> {
                 index: state.index + 1,
                 len: self.len,
                 tokens: Arc::clone(&self.tokens),
+                expected: Rc::clone(&self.expected),
+                open_blocks: self.open_blocks.clone(),
+                depth: Rc::clone(&self.depth),
+                recursion_limit: self.recursion_limit,
+                expression_depth_limit: self.expression_depth_limit,
+                cancellation: self.cancellation.clone(),
+                furthest: self.furthest.clone(),
             };
 
             if !state.peek().token_type().ignore() {
@@ -49,20 +174,197 @@ impl<'a> ParserState<'a> {
             panic!("peek failed, when there should always be an eof");
         }
 
-        // sorted_by is commented out because it had a extremely high performance cost
-        // Uncommenting the line changes one large file from being parsed in ~0.1s to **14 seconds**!!!
-        // Iteration of self.tokens is explicitly undefined, but it happens to work out
-        // TODO: How can we guarantee order without the performance cost? Create our own arena?
+        if let Some(furthest) = &self.furthest {
+            furthest.set(furthest.get().max(self.index));
+        }
+
+        // The arena is only ever made from the tokens in order and never has anything removed,
+        // so the token at an index is in the slot with that index, which is found without iterating up to it
         TokenReference::Borrowed {
             arena: Arc::clone(&self.tokens),
             index: self
                 .tokens
-                .iter()
-                // .sorted_by(|left, right| left.1.cmp(&right.1))
-                .nth(self.index)
+                .get_unknown_gen(self.index)
                 .expect("couldn't peek, no eof?")
-                .0,
+                .1,
+        }
+    }
+
+    // The bytes the token being looked at starts at, for tracing, which unlike `peek` isn't counted as looking at it
+    #[cfg(feature = "tracing")]
+    pub fn bytes(&self) -> usize {
+        self.tokens
+            .get_unknown_gen(self.index)
+            .map_or(0, |(token, _)| token.start_position().bytes())
+    }
+
+    // Counts one more level of nesting until the guard is dropped, failing instead of going past the recursion limit,
+    // since the parser would otherwise overflow the stack on code such as thousands of `(`s.
+    // The level right past the limit is only an error once something is parsed in it with `Nesting::check`,
+    // so that a call with no arguments at the limit doesn't fail for trying to parse an expression in it.
+    pub fn nest(&self) -> Result<Nesting<'a>, InternalAstError<'a>> {
+        self.check_cancelled()?;
+
+        let depth = self.depth.levels.get() + 1;
+        let exceeded = InternalAstError::RecursionLimitExceeded {
+            token: self.peek(),
+            limit: self.recursion_limit,
+        };
+
+        if depth > self.recursion_limit + 1 {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                index = self.index,
+                limit = self.recursion_limit,
+                "recursion limit exceeded"
+            );
+            return Err(exceeded);
+        }
+
+        self.depth.levels.set(depth);
+        Ok(Nesting {
+            depth: Rc::clone(&self.depth),
+            exceeded: if depth > self.recursion_limit {
+                Some(exceeded)
+            } else {
+                None
+            },
+        })
+    }
+
+    // How many expressions the one being parsed is in, including the ones a function it's in is in
+    pub fn expression_depth(&self) -> usize {
+        self.depth.expressions.get()
+    }
+
+    pub fn set_expression_depth(&self, depth: usize) {
+        self.depth.expressions.set(depth);
+    }
+
+    // Fails if the expression starting at this token is past the expression depth limit. Expressions are parsed
+    // without recursing, but cloning, visiting, and comparing them recurses, so code with thousands of `(`s
+    // would otherwise parse fine only to overflow the stack later.
+    // Like with `nest`, this is only checked once an expression is there, so `{}` at the limit is fine.
+    pub fn check_expression_depth(&self) -> Result<(), InternalAstError<'a>> {
+        if self.expression_depth() > self.expression_depth_limit {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                index = self.index,
+                limit = self.expression_depth_limit,
+                "expression depth limit exceeded"
+            );
+            return Err(InternalAstError::RecursionLimitExceeded {
+                token: self.peek(),
+                limit: self.expression_depth_limit,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Fails if the cancellation token was cancelled, checked often enough that parsing stops soon after it is.
+    // This is only a relaxed load, so checking it is cheap enough to do for every statement and expression.
+    pub fn check_cancelled(&self) -> Result<(), InternalAstError<'a>> {
+        match &self.cancellation {
+            Some(cancellation) if cancellation.is_cancelled() => Err(InternalAstError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    // The state inside of the block opened by the token, until `close_block` is used
+    pub fn open_block(&self, token: &TokenReference<'a>) -> ParserState<'a> {
+        ParserState {
+            open_blocks: Some(Rc::new(OpenBlock {
+                token: token.clone(),
+                parent: self.open_blocks.clone(),
+            })),
+            ..self.clone()
+        }
+    }
+
+    // The state outside of the innermost block, after the token that closes it
+    pub fn close_block(&self) -> ParserState<'a> {
+        ParserState {
+            open_blocks: self
+                .open_blocks
+                .as_ref()
+                .and_then(|block| block.parent.clone()),
+            ..self.clone()
+        }
+    }
+
+    // The token that opened the innermost block if the code ends here, since that block is never closed
+    pub fn unclosed_block(&self) -> Option<TokenReference<'a>> {
+        match (&*self.peek().token_type(), &self.open_blocks) {
+            (TokenType::Eof, Some(block)) => Some(block.token.clone()),
+            _ => None,
+        }
+    }
+
+    // Records that the item was tried at the current token and didn't match
+    pub fn expect(&self, item: ExpectedItem) {
+        let mut expected = self.expected.borrow_mut();
+        if expected.index != self.index {
+            expected.index = self.index;
+            expected.items.clear();
         }
+
+        expected.items.push(item);
+    }
+
+    // Everything that was tried at the current token, sorted and without duplicates
+    pub fn expected(&self) -> Vec<ExpectedItem> {
+        let expected = self.expected.borrow();
+        if expected.index != self.index {
+            return Vec::new();
+        }
+
+        let mut items = expected.items.clone();
+        items.sort();
+        items.dedup();
+
+        // Every error at a token is made with what was expected there, so this is where they're traced from
+        #[cfg(feature = "tracing")]
+        tracing::debug!(index = self.index, bytes = self.bytes(), expected = ?items, "unexpected token");
+        items
+    }
+
+    // What was expected so far, for parsing quietly when what's parsed isn't all in one call, like with `quiet`
+    pub fn save_expected(&self) -> SavedExpected {
+        SavedExpected(self.expected.borrow().clone())
+    }
+
+    pub fn restore_expected(&self, saved: SavedExpected) {
+        *self.expected.borrow_mut() = saved.0;
+    }
+
+    // Parses without recording anything that was tried, for what can always be left out,
+    // such as a semicolon after a statement, which would otherwise be in every error after one
+    pub fn quiet<T>(
+        &self,
+        parse: impl FnOnce(ParserState<'a>) -> Result<(ParserState<'a>, T), InternalAstError<'a>>,
+    ) -> Result<(ParserState<'a>, T), InternalAstError<'a>> {
+        let expected = self.save_expected();
+        let result = parse(self.clone());
+        self.restore_expected(expected);
+        result
+    }
+
+    // Parses, and if nothing matches, records only the one item instead of every way it could have started,
+    // such as "an expression" instead of every kind of value
+    pub fn expecting<T>(
+        &self,
+        item: ExpectedItem,
+        parse: impl FnOnce(ParserState<'a>) -> Result<(ParserState<'a>, T), InternalAstError<'a>>,
+    ) -> Result<(ParserState<'a>, T), InternalAstError<'a>> {
+        let expected = self.expected.borrow().clone();
+        let result = parse(self.clone());
+        if let Err(InternalAstError::NoMatch) = result {
+            *self.expected.borrow_mut() = expected;
+            self.expect(item);
+        }
+
+        result
     }
 }
 
@@ -97,28 +399,76 @@ macro_rules! define_parser {
                 &self,
                 state: ParserState<'a>,
             ) -> Result<(ParserState<'a>, $node), InternalAstError<'a>> {
-                $body(self, state)
+                // Made in a function of its own, so what making it takes isn't in the stack frame of every production
+                #[cfg(feature = "tracing")]
+                #[cold]
+                #[inline(never)]
+                fn span(state: &ParserState) -> tracing::span::EnteredSpan {
+                    tracing::trace_span!(
+                        stringify!($parser),
+                        index = state.index,
+                        bytes = state.bytes()
+                    )
+                    .entered()
+                }
+
+                #[cfg(feature = "tracing")]
+                let _span = span(&state);
+
+                let result = $body(self, state);
+                #[cfg(feature = "tracing")]
+                $crate::ast::parser_util::trace_result(&result);
+                result
             }
         }
     };
 }
 
+// Says how a production that was traced ended: with what it parsed, with nothing, which the parser goes back from
+// to try something else, or with an error
+#[cfg(feature = "tracing")]
+#[inline(never)]
+pub fn trace_result<T>(result: &Result<(ParserState, T), InternalAstError>) {
+    match result {
+        Ok((state, _)) => tracing::trace!(end = state.index, "matched"),
+        Err(InternalAstError::NoMatch) => tracing::trace!("no match, backtracking"),
+        Err(_) => tracing::trace!("error"),
+    }
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! parse_first_of {
     ($state:ident, {$($parser:expr => $constructor:expr,)+}) => ({
-        $(
-            match $parser.parse($state.clone()) {
-                Ok((state, node)) => return Ok((state, $constructor(node.into()))),
-                Err(InternalAstError::NoMatch) => {},
-                Err(other) => return Err(other),
-            };
-        )+
-
-        Err(InternalAstError::NoMatch)
+        $crate::ast::parser_util::first_of($state, &[$(
+            &|state| {
+                let (state, node) = $parser.parse(state)?;
+                Ok((state, $constructor(node.into())))
+            },
+        )+])
     });
 }
 
+// One of the parsers that `parse_first_of!` tries, making what it parsed into the node they all make
+type Alternative<'a, 'b, T> =
+    &'b dyn Fn(ParserState<'a>) -> Result<(ParserState<'a>, T), InternalAstError<'a>>;
+
+// Tries each alternative in a call of its own, so what they parse isn't all in one stack frame,
+// which debug builds would otherwise make room for every alternative in
+pub fn first_of<'a, T>(
+    state: ParserState<'a>,
+    alternatives: &[Alternative<'a, '_, T>],
+) -> Result<(ParserState<'a>, T), InternalAstError<'a>> {
+    for alternative in alternatives {
+        match alternative(state.clone()) {
+            Err(InternalAstError::NoMatch) => {}
+            result => return result,
+        }
+    }
+
+    Err(InternalAstError::NoMatch)
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! expect {
@@ -129,19 +479,39 @@ macro_rules! expect {
                 return Err(InternalAstError::UnexpectedToken {
                     token: $state.peek(),
                     additional: None,
+                    opening: $state.unclosed_block(),
+                    expected: $state.expected(),
+                });
+            }
+            Err(other) => return Err(other),
+        };
+    };
+
+    ($state:ident, $parsed:expr, $code:expr) => {
+        match $parsed {
+            Ok((state, node)) => (state, node),
+            Err(InternalAstError::NoMatch) => {
+                return Err(InternalAstError::UnexpectedToken {
+                    token: $state.peek(),
+                    additional: $code.additional(),
+                    opening: $state.unclosed_block(),
+                    expected: $state.expected(),
                 });
             }
             Err(other) => return Err(other),
         };
     };
 
-    ($state:ident, $parsed:expr, $error:tt) => {
+    // For tokens that close blocks and brackets, along with the token that opened them
+    ($state:ident, $parsed:expr, $code:expr, $opening:expr) => {
         match $parsed {
             Ok((state, node)) => (state, node),
             Err(InternalAstError::NoMatch) => {
                 return Err(InternalAstError::UnexpectedToken {
                     token: $state.peek(),
-                    additional: Some($error),
+                    additional: $code.additional(),
+                    opening: Some($opening.clone()),
+                    expected: $state.expected(),
                 });
             }
             Err(other) => return Err(other),
@@ -170,12 +540,27 @@ pub enum InternalAstError<'a> {
         #[cfg_attr(feature = "serde", serde(borrow))]
         token: TokenReference<'a>,
         additional: Option<&'a str>,
+        opening: Option<TokenReference<'a>>,
+        expected: Vec<ExpectedItem>,
+    },
+    RecursionLimitExceeded {
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        token: TokenReference<'a>,
+        limit: usize,
+    },
+    Cancelled,
+    ReservedKeywordAsName {
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        token: TokenReference<'a>,
+        keyword: Symbol,
     },
 }
 
+#[cfg(test)]
 #[derive(Clone, Debug, PartialEq)]
 pub struct ZeroOrMore<P>(pub P);
 
+#[cfg(test)]
 impl<'a, P, T> Parser<'a> for ZeroOrMore<P>
 where
     P: Parser<'a, Item = T>,
@@ -272,7 +657,9 @@ where
                     } else {
                         return Err(InternalAstError::UnexpectedToken {
                             token: state.peek(),
-                            additional: Some("trailing character"),
+                            additional: ErrorCode::TrailingCharacter.additional(),
+                            opening: state.unclosed_block(),
+                            expected: state.expected(),
                         });
                     }
                 }
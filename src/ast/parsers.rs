@@ -1,10 +1,15 @@
 use super::{
-    parser_util::{InternalAstError, Parser, ParserState},
+    drop::drop_nodes,
+    parser_util::{InternalAstError, Parser, ParserState, SavedExpected},
     span::ContainedSpan,
     *,
 };
 
-use crate::tokenizer::{TokenKind, TokenReference, TokenType};
+use crate::{
+    prelude::*,
+    tokenizer::{TokenKind, TokenReference, TokenType},
+    ErrorCode,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 struct ParseSymbol(Symbol);
@@ -19,6 +24,7 @@ define_parser!(
         if *token.token_type() == expecting {
             Ok((state.advance().ok_or(InternalAstError::NoMatch)?, token))
         } else {
+            state.expect(ExpectedItem::Symbol(this.0));
             Err(InternalAstError::NoMatch)
         }
     }
@@ -35,6 +41,7 @@ define_parser!(
         if token.token_kind() == TokenKind::Number {
             Ok((state.advance().ok_or(InternalAstError::NoMatch)?, token))
         } else {
+            state.expect(ExpectedItem::Number);
             Err(InternalAstError::NoMatch)
         }
     }
@@ -51,6 +58,7 @@ define_parser!(
         if token.token_kind() == TokenKind::StringLiteral {
             Ok((state.advance().ok_or(InternalAstError::NoMatch)?, token))
         } else {
+            state.expect(ExpectedItem::String);
             Err(InternalAstError::NoMatch)
         }
     }
@@ -59,12 +67,15 @@ define_parser!(
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct ParseBlock;
 define_parser!(ParseBlock, Block<'a>, |_, mut state: ParserState<'a>| {
+    let nesting = state.nest()?;
     let mut stmts = Vec::new();
     while let Ok((new_state, stmt)) = keep_going!(ParseStmt.parse(state.clone())) {
         state = new_state;
+        state.check_cancelled()?;
         let mut semicolon = None;
 
-        if let Ok((new_state, new_semicolon)) = ParseSymbol(Symbol::Semicolon).parse(state.clone())
+        if let Ok((new_state, new_semicolon)) =
+            state.quiet(|state| ParseSymbol(Symbol::Semicolon).parse(state))
         {
             state = new_state;
             semicolon = Some(new_semicolon);
@@ -73,32 +84,338 @@ define_parser!(ParseBlock, Block<'a>, |_, mut state: ParserState<'a>| {
         stmts.push((stmt, semicolon));
     }
 
-    if let Ok((mut state, last_stmt)) = keep_going!(ParseLastStmt.parse(state.clone())) {
-        let mut semicolon = None;
+    nesting.check(
+        if let Ok((mut state, last_stmt)) = keep_going!(ParseLastStmt.parse(state.clone())) {
+            let mut semicolon = None;
+
+            if let Ok((new_state, new_semicolon)) =
+                state.quiet(|state| ParseSymbol(Symbol::Semicolon).parse(state))
+            {
+                state = new_state;
+                semicolon = Some(new_semicolon)
+            }
+
+            Ok((
+                state,
+                Block {
+                    stmts,
+                    last_stmt: Some((last_stmt, semicolon)),
+                    id: StoredId::new(),
+                },
+            ))
+        } else {
+            Ok((
+                state,
+                Block {
+                    stmts,
+                    last_stmt: None,
+                    id: StoredId::new(),
+                },
+            ))
+        },
+    )
+});
+
+// Parses the top level statements one at a time, collecting up to `max_errors` errors.
+// After a statement with an error, parsing starts again at the next statement outside of the blocks it opened,
+// so that one mistake doesn't also make errors out of the code after it.
+// The code that was skipped is kept in the block as an `ErrorStmt`, or as an `ErrorExpression`
+// when only the values of an assignment or of a return at the end of the code couldn't be parsed.
+pub fn recover_errors<'a>(
+    mut state: ParserState<'a>,
+    max_errors: usize,
+) -> (Block<'a>, Vec<InternalAstError<'a>>) {
+    let mut errors = Vec::new();
+    let mut stmts = Vec::new();
+    let mut last_stmt = None;
+
+    while *state.peek().token_type() != TokenType::Eof {
+        let error = match parse_top_level_stmt(state.clone()) {
+            Ok((new_state, TopLevelStmt::Stmt(stmt, semicolon))) => {
+                stmts.push((stmt, semicolon));
+                state = new_state;
+                continue;
+            }
+
+            Ok((_, TopLevelStmt::LastStmt(stmt, semicolon))) => {
+                last_stmt = Some((stmt, semicolon));
+                break;
+            }
+
+            Err(error) => error,
+        };
+
+        let error_index = error_index(&state, &error);
+        let cancelled = error == InternalAstError::Cancelled;
+        if errors.len() < max_errors {
+            errors.push(error);
+        }
+
+        // Nothing else is parsed once parsing is cancelled, so the rest of the code is all one error statement
+        if cancelled {
+            let mut end = state.clone();
+            while *end.peek().token_type() != TokenType::Eof {
+                end = end.advance().expect("there should always be an eof");
+            }
+
+            stmts.push((
+                Stmt::Error(ErrorStmt {
+                    tokens: tokens_between(&state, &end),
+                    id: StoredId::new(),
+                }),
+                None,
+            ));
+            break;
+        }
+
+        let next = next_statement(&state, error_index);
+        if *next.peek().token_type() == TokenType::Eof {
+            if let Some(partial) = partial_return(&state, &next, error_index) {
+                last_stmt = Some((partial, None));
+                break;
+            }
+        }
+
+        let stmt = partial_assignment(&state, &next, error_index).unwrap_or_else(|| {
+            Stmt::Error(ErrorStmt {
+                tokens: tokens_between(&state, &next),
+                id: StoredId::new(),
+            })
+        });
+
+        stmts.push((stmt, None));
+        state = next;
+    }
+
+    (
+        Block {
+            stmts,
+            last_stmt,
+            id: StoredId::new(),
+        },
+        errors,
+    )
+}
+
+// A top level statement and the semicolon after it, which is either a statement or the `return` or `break` at the end
+pub enum TopLevelStmt<'a> {
+    Stmt(Stmt<'a>, Option<TokenReference<'a>>),
+    LastStmt(LastStmt<'a>, Option<TokenReference<'a>>),
+}
+
+// Parses the top level statement at the state, with the same errors as parsing it in the block of the whole code,
+// so a `return` or `break` that isn't at the end of the code is an error, as is anything that isn't a statement
+pub fn parse_top_level_stmt<'a>(
+    state: ParserState<'a>,
+) -> Result<(ParserState<'a>, TopLevelStmt<'a>), InternalAstError<'a>> {
+    match ParseStmt.parse(state.clone()) {
+        Ok((state, stmt)) => {
+            let (state, semicolon) = parse_semicolon(state);
+            Ok((state, TopLevelStmt::Stmt(stmt, semicolon)))
+        }
+
+        Err(InternalAstError::NoMatch) => match ParseLastStmt.parse(state.clone()) {
+            Ok((state, stmt)) => {
+                let (state, semicolon) = parse_semicolon(state);
+                if *state.peek().token_type() == TokenType::Eof {
+                    Ok((state, TopLevelStmt::LastStmt(stmt, semicolon)))
+                } else {
+                    Err(leftover_token(&state))
+                }
+            }
+
+            Err(InternalAstError::NoMatch) => Err(leftover_token(&state)),
+            Err(error) => Err(error),
+        },
+
+        Err(error) => Err(error),
+    }
+}
+
+// The index of the token an error is at, or of the state it happened at if it isn't at a token of it
+pub fn error_index<'a>(state: &ParserState<'a>, error: &InternalAstError<'a>) -> usize {
+    match error {
+        InternalAstError::UnexpectedToken { token, .. }
+        | InternalAstError::RecursionLimitExceeded { token, .. }
+        | InternalAstError::ReservedKeywordAsName { token, .. } => token
+            .arena_offset()
+            .map_or(state.index, |(_, offset)| offset),
+        InternalAstError::NoMatch | InternalAstError::Cancelled => state.index,
+    }
+}
+
+fn parse_semicolon<'a>(state: ParserState<'a>) -> (ParserState<'a>, Option<TokenReference<'a>>) {
+    match state.quiet(|state| ParseSymbol(Symbol::Semicolon).parse(state)) {
+        Ok((state, semicolon)) => (state, Some(semicolon)),
+        Err(_) => (state, None),
+    }
+}
+
+fn leftover_token<'a>(state: &ParserState<'a>) -> InternalAstError<'a> {
+    state.expect(ExpectedItem::Eof);
+    InternalAstError::UnexpectedToken {
+        token: state.peek(),
+        additional: ErrorCode::LeftoverToken.additional(),
+        opening: None,
+        expected: state.expected(),
+    }
+}
 
-        if let Ok((new_state, new_semicolon)) = ParseSymbol(Symbol::Semicolon).parse(state.clone())
+// The tokens from the start up to, but not including, the end
+fn tokens_between<'a>(start: &ParserState<'a>, end: &ParserState<'a>) -> Vec<TokenReference<'a>> {
+    let mut state = start.clone();
+    let mut tokens = Vec::new();
+
+    while state.index < end.index {
+        tokens.push(state.peek());
+        state = state.advance().expect("there should always be an eof");
+    }
+
+    tokens
+}
+
+// The values of an assignment as an `ErrorExpression`, if the error is after its `=`
+fn partial_values<'a>(
+    after_equal: &ParserState<'a>,
+    next: &ParserState<'a>,
+    error_index: usize,
+) -> Option<Punctuated<'a, Expression<'a>>> {
+    if after_equal.index > error_index || after_equal.index > next.index {
+        return None;
+    }
+
+    let mut values = Punctuated::new();
+    values.push(Pair::End(Expression::Error(ErrorExpression {
+        tokens: tokens_between(after_equal, next),
+        id: StoredId::new(),
+    })));
+
+    Some(values)
+}
+
+// The statement with the error as a local assignment or an assignment, if the error is in its values
+fn partial_assignment<'a>(
+    state: &ParserState<'a>,
+    next: &ParserState<'a>,
+    error_index: usize,
+) -> Option<Stmt<'a>> {
+    if let Ok((state, local_token)) = ParseSymbol(Symbol::Local).parse(state.clone()) {
+        let (state, name_list) = OneOrMore(ParseIdentifier, ParseSymbol(Symbol::Comma), false)
+            .parse(state.clone())
+            .ok()?;
+        let (state, equal_token) = ParseSymbol(Symbol::Equal).parse(state.clone()).ok()?;
+
+        return Some(Stmt::LocalAssignment(LocalAssignment {
+            local_token,
+            name_list,
+            equal_token: Some(equal_token),
+            expr_list: partial_values(&state, next, error_index)?,
+            id: StoredId::new(),
+        }));
+    }
+
+    let (state, var_list) = OneOrMore(ParseVar, ParseSymbol(Symbol::Comma), false)
+        .parse(state.clone())
+        .ok()?;
+    let (state, equal_token) = ParseSymbol(Symbol::Equal).parse(state.clone()).ok()?;
+
+    Some(Stmt::Assignment(Assignment {
+        var_list,
+        equal_token,
+        expr_list: partial_values(&state, next, error_index)?,
+        id: StoredId::new(),
+    }))
+}
+
+// The statement with the error as a return, if it is one and the error is in its values
+fn partial_return<'a>(
+    state: &ParserState<'a>,
+    next: &ParserState<'a>,
+    error_index: usize,
+) -> Option<LastStmt<'a>> {
+    let (state, token) = ParseSymbol(Symbol::Return).parse(state.clone()).ok()?;
+
+    Some(LastStmt::Return(Return {
+        token,
+        returns: partial_values(&state, next, error_index)?,
+        id: StoredId::new(),
+    }))
+}
+
+// The start of the first statement after both the error and the blocks opened since the start of the statement it's in.
+// Blocks that aren't closed, such as from a missing `end`, go on until the end of the code.
+pub fn next_statement<'a>(start: &ParserState<'a>, error_index: usize) -> ParserState<'a> {
+    let mut state = start.clone();
+    let mut depth = 0usize;
+
+    loop {
+        let token = state.peek();
+        let token_type = token.token_type();
+
+        if *token_type == TokenType::Eof
+            || (state.index > start.index
+                && state.index >= error_index
+                && depth == 0
+                && starts_statement(&state, &token_type))
         {
-            state = new_state;
-            semicolon = Some(new_semicolon)
+            return state;
         }
 
-        Ok((
-            state,
-            Block {
-                stmts,
-                last_stmt: Some((last_stmt, semicolon)),
-            },
-        ))
-    } else {
-        Ok((
-            state,
-            Block {
-                stmts,
-                last_stmt: None,
-            },
-        ))
+        match &*token_type {
+            TokenType::Symbol {
+                symbol: Symbol::Do | Symbol::Function | Symbol::If | Symbol::Repeat,
+            } => depth += 1,
+
+            TokenType::Symbol {
+                symbol: Symbol::End | Symbol::Until,
+            } => depth = depth.saturating_sub(1),
+
+            _ => {}
+        }
+
+        state = state.advance().expect("there should always be an eof");
     }
-});
+}
+
+// Keywords that start statements always do, while names and parentheses are only trusted to at the start of a line
+fn starts_statement(state: &ParserState, token_type: &TokenType) -> bool {
+    match token_type {
+        TokenType::Symbol { symbol } => match symbol {
+            Symbol::Local
+            | Symbol::Function
+            | Symbol::If
+            | Symbol::While
+            | Symbol::For
+            | Symbol::Repeat
+            | Symbol::Do
+            | Symbol::Return
+            | Symbol::Break => true,
+            Symbol::LeftParen => starts_line(state),
+            _ => false,
+        },
+
+        TokenType::Identifier { .. } => starts_line(state),
+        _ => false,
+    }
+}
+
+fn starts_line(state: &ParserState) -> bool {
+    for index in (0..state.index).rev() {
+        let token = match state.tokens.get_unknown_gen(index) {
+            Some((token, _)) => token,
+            None => continue,
+        };
+
+        match &*token.token_type() {
+            TokenType::Whitespace { characters } if characters.contains('\n') => return true,
+            TokenType::SingleLineComment { .. } => return true,
+            token_type if token_type.ignore() => {}
+            _ => return false,
+        }
+    }
+
+    true
+}
 
 #[derive(Clone, Debug, PartialEq)]
 struct ParseLastStmt;
@@ -112,10 +429,17 @@ define_parser!(
             state,
             ZeroOrMoreDelimited(ParseExpression, ParseSymbol(Symbol::Comma), false)
                 .parse(state.clone()),
-            "return values"
+            ErrorCode::ExpectedReturnValues
         );
 
-        Ok((state, LastStmt::Return(Return { token, returns })))
+        Ok((
+            state,
+            LastStmt::Return(Return {
+                token,
+                returns,
+                id: StoredId::new(),
+            }),
+        ))
     } else if let Ok((state, token)) = ParseSymbol(Symbol::Break).parse(state.clone()) {
         Ok((state, LastStmt::Break(token)))
     } else {
@@ -124,177 +448,721 @@ define_parser!(
 );
 
 #[derive(Clone, Debug, PartialEq)]
-struct ParseField;
-define_parser!(ParseField, Field<'a>, |_, state: ParserState<'a>| {
-    if let Ok((state, start_bracket)) = ParseSymbol(Symbol::LeftBracket).parse(state.clone()) {
-        let (state, key) = expect!(state, ParseExpression.parse(state.clone()), "expected key");
-        let (state, end_bracket) = expect!(
-            state,
-            ParseSymbol(Symbol::RightBracket).parse(state.clone()),
-            "expected ']'"
-        );
-        let (state, equal) = expect!(
-            state,
-            ParseSymbol(Symbol::Equal).parse(state.clone()),
-            "expected '='"
-        );
-        let (state, value) = expect!(
-            state,
-            ParseExpression.parse(state.clone()),
-            "expected value"
-        );
+struct ParseExpression;
+define_parser!(
+    ParseExpression,
+    Expression<'a>,
+    |_, state: ParserState<'a>| {
+        let nesting = state.nest()?;
 
-        return Ok((
-            state.clone(),
-            Field::ExpressionKey {
-                brackets: ContainedSpan::new(start_bracket, end_bracket),
-                key,
-                equal,
-                value,
-            },
-        ));
-    } else if let Ok((state, key)) = keep_going!(ParseIdentifier.parse(state.clone())) {
-        if let Ok((state, equal)) = ParseSymbol(Symbol::Equal).parse(state.clone()) {
-            let (state, value) = expect!(
-                state,
-                ParseExpression.parse(state.clone()),
-                "expected value"
-            );
+        match nesting.check(parse_nested(state, Parsing::Expression))? {
+            (state, Parsed::Expression(expression)) => Ok((state, expression)),
+            (_, Parsed::Prefix(_)) => unreachable!("only statements are parsed as prefixes"),
+        }
+    }
+);
+
+// A prefix and every suffix after it, for the function calls and variables that statements start with
+fn parse_suffixed<'a>(
+    state: ParserState<'a>,
+) -> Result<(ParserState<'a>, PartialValue<'a>), InternalAstError<'a>> {
+    match parse_nested(state, Parsing::Prefix)? {
+        (state, Parsed::Prefix(value)) => Ok((state, value)),
+        (_, Parsed::Expression(_)) => unreachable!("parsing a prefix stops after its suffixes"),
+    }
+}
+
+// Expressions are parsed with a stack of what they're in instead of by recursing,
+// since machine generated code, such as serialized data, can nest tables, operators, parentheses,
+// and calls and indexes in each other thousands of levels deep.
+// Only functions recurse, since they have blocks, so those are all that count towards the recursion limit.
+enum Nested<'a> {
+    // An operator waiting for the expression after it
+    UnOp(UnOp<'a>),
+    BinOp(Value<'a>, BinOp<'a>),
+    // A table waiting for the key in square brackets of its next field, after the `[`
+    Key(PartialTable<'a>, TokenReference<'a>),
+    // A table waiting for the value of its next field, after the key if there is one
+    Field(PartialTable<'a>, FieldKey<'a>),
+    // An expression in parentheses, after the `(`
+    Parentheses(TokenReference<'a>),
+    // A value waiting for the expression in the square brackets indexing it, after the `[`
+    Index(PartialValue<'a>, TokenReference<'a>),
+    // A value waiting for the next argument it's called with, after the `(` or the `,` after the last one
+    Arguments(PartialValue<'a>, PartialCall<'a>),
+    // A value called with a table, waiting for the table to be closed
+    TableArgument(PartialValue<'a>, Option<MethodName<'a>>),
+}
 
-            return Ok((state.clone(), Field::NameKey { key, equal, value }));
+impl<'a> Nested<'a> {
+    fn drop_nodes(self) {
+        match self {
+            Nested::UnOp(_) | Nested::Parentheses(_) => {}
+            Nested::BinOp(value, _) => drop_nodes(value),
+            Nested::Key(table, _)
+            | Nested::Field(table, FieldKey::Name { .. })
+            | Nested::Field(table, FieldKey::None) => drop_nodes(table.fields),
+            Nested::Field(table, FieldKey::Expression { key, .. }) => {
+                drop_nodes((table.fields, key))
+            }
+            Nested::Index(value, _) | Nested::TableArgument(value, _) => {
+                drop_nodes((value.prefix, value.suffixes))
+            }
+            Nested::Arguments(value, call) => {
+                drop_nodes(((value.prefix, value.suffixes), call.arguments))
+            }
         }
     }
+}
+
+struct PartialTable<'a> {
+    start_brace: TokenReference<'a>,
+    fields: Vec<TableConstructorField<'a>>,
+}
 
-    if let Ok((state, expr)) = keep_going!(ParseExpression.parse(state.clone())) {
-        return Ok((state.clone(), Field::NoKey(expr)));
+impl<'a> PartialTable<'a> {
+    fn new(start_brace: TokenReference<'a>) -> Self {
+        PartialTable {
+            start_brace,
+            fields: Vec::new(),
+        }
     }
+}
 
-    Err(InternalAstError::NoMatch)
-});
+enum FieldKey<'a> {
+    Expression {
+        start_bracket: TokenReference<'a>,
+        key: Expression<'a>,
+        end_bracket: TokenReference<'a>,
+        equal: TokenReference<'a>,
+    },
+    Name {
+        key: TokenReference<'a>,
+        equal: TokenReference<'a>,
+    },
+    None,
+}
 
-struct ParseTableConstructor;
-define_parser!(
-    ParseTableConstructor,
-    TableConstructor<'a>,
-    |_, state: ParserState<'a>| {
-        let (mut state, start_brace) = ParseSymbol(Symbol::LeftBrace).parse(state.clone())?;
-        let mut fields = Vec::new();
+// A prefix and the suffixes after it so far. The prefix and its suffixes are only parsed once, then which value it is
+// depends on what's last, since trying each one would parse it again for each, which is three times as long for every
+// parenthesis that an expression is in.
+struct PartialValue<'a> {
+    prefix: Prefix<'a>,
+    suffixes: Vec<Suffix<'a>>,
+}
 
-        while let Ok((new_state, field)) = keep_going!(ParseField.parse(state.clone())) {
-            let field_sep = if let Ok((new_state, separator)) =
-                ParseSymbol(Symbol::Comma).parse(new_state.clone())
-            {
-                state = new_state;
-                Some(separator)
-            } else if let Ok((new_state, separator)) =
-                ParseSymbol(Symbol::Semicolon).parse(new_state.clone())
-            {
-                state = new_state;
-                Some(separator)
-            } else {
+impl<'a> PartialValue<'a> {
+    fn new(prefix: Prefix<'a>) -> Self {
+        PartialValue {
+            prefix,
+            suffixes: Vec::new(),
+        }
+    }
+
+    fn into_value(self) -> Value<'a> {
+        let PartialValue { prefix, suffixes } = self;
+
+        match (suffixes.last(), prefix) {
+            (Some(Suffix::Call(_)), prefix) => Value::FunctionCall(FunctionCall {
+                prefix,
+                suffixes,
+                id: StoredId::new(),
+            }),
+            (Some(Suffix::Index(_)), prefix) => Value::Var(Var::Expression(VarExpression {
+                prefix,
+                suffixes,
+                id: StoredId::new(),
+            })),
+            (None, Prefix::Name(name)) => Value::Var(Var::Name(name)),
+            (None, Prefix::Expression(expression)) => Value::ParseExpression(expression),
+        }
+    }
+}
+
+// The `:` and name of a method being called
+struct MethodName<'a> {
+    colon_token: TokenReference<'a>,
+    name: TokenReference<'a>,
+}
+
+struct PartialCall<'a> {
+    method: Option<MethodName<'a>>,
+    left_paren: TokenReference<'a>,
+    arguments: Punctuated<'a, Expression<'a>>,
+}
+
+// What to parse for, either a whole expression or only a prefix and its suffixes
+#[derive(Clone, Copy, PartialEq)]
+enum Parsing {
+    Expression,
+    Prefix,
+}
+
+// What to parse next
+enum Step<'a> {
+    // The start of an expression
+    Expression,
+    // The start of a prefix, for parsing only a prefix and its suffixes
+    Prefix,
+    // What the start of an expression was, for what to parse after it
+    Start(ExpressionStart<'a>),
+    // The next field of a table, or the `}` closing it
+    Fields(PartialTable<'a>),
+    // A table that was closed, which is either a value or the argument of a call
+    Table(TableConstructor<'a>),
+    // The next suffix of a value, if there is one
+    Suffixes(PartialValue<'a>),
+    // The operator after a value, if there is one
+    Value(Value<'a>),
+    // Nothing more of the expression, which is then put in what it's nested in
+    Done(Expression<'a>),
+}
+
+enum ExpressionStart<'a> {
+    Table(TokenReference<'a>),
+    Value(Value<'a>),
+    UnOp(UnOp<'a>),
+    Parentheses(TokenReference<'a>),
+    Name(TokenReference<'a>),
+}
+
+// How the arguments of a call start
+enum ArgumentsStart<'a> {
+    Parentheses(TokenReference<'a>),
+    Table(TokenReference<'a>),
+    String(TokenReference<'a>),
+}
+
+enum Parsed<'a> {
+    Expression(Expression<'a>),
+    Prefix(PartialValue<'a>),
+}
+
+// Parses an expression, or a prefix and its suffixes
+fn parse_nested<'a>(
+    state: ParserState<'a>,
+    parsing: Parsing,
+) -> Result<(ParserState<'a>, Parsed<'a>), InternalAstError<'a>> {
+    // What was expected before each list of suffixes being parsed, outermost first
+    let mut quiet = Vec::new();
+    let mut stack = Vec::new();
+    let outer = state.expression_depth();
+    let result = parse_nested_with(state.clone(), parsing, outer, &mut stack, &mut quiet);
+    state.set_expression_depth(outer);
+
+    // What's left after an error is dropped a frame at a time, since it can be nested as deeply as the expressions
+    for nested in stack {
+        nested.drop_nodes();
+    }
+
+    // Suffixes are parsed quietly, so after an error in them, what was expected is put back as it was before them
+    if let (Err(_), Some(expected)) = (&result, quiet.into_iter().next()) {
+        state.restore_expected(expected);
+    }
+
+    result
+}
+
+fn parse_nested_with<'a>(
+    mut state: ParserState<'a>,
+    parsing: Parsing,
+    outer: usize,
+    stack: &mut Vec<Nested<'a>>,
+    quiet: &mut Vec<SavedExpected>,
+) -> Result<(ParserState<'a>, Parsed<'a>), InternalAstError<'a>> {
+    let mut step = match parsing {
+        Parsing::Expression => Step::Expression,
+        Parsing::Prefix => Step::Prefix,
+    };
+
+    loop {
+        step = match step {
+            Step::Expression => {
+                state.check_cancelled()?;
+                state.set_expression_depth(outer + stack.len());
+                let start = state.expecting(ExpectedItem::Expression, |state| {
+                    if let Ok((state, start_brace)) =
+                        ParseSymbol(Symbol::LeftBrace).parse(state.clone())
+                    {
+                        Ok((state, ExpressionStart::Table(start_brace)))
+                    } else if let Ok((state, value)) = keep_going!(ParseValue.parse(state.clone()))
+                    {
+                        Ok((state, ExpressionStart::Value(value)))
+                    } else if let Ok((state, start)) = parse_prefix(&state) {
+                        Ok((state, start))
+                    } else if let Ok((state, unop)) = keep_going!(ParseUnOp.parse(state.clone())) {
+                        Ok((state, ExpressionStart::UnOp(unop)))
+                    } else {
+                        Err(InternalAstError::NoMatch)
+                    }
+                });
+
+                match start {
+                    Ok((new_state, start)) => {
+                        state.check_expression_depth()?;
+                        state = new_state;
+                        Step::Start(start)
+                    }
+
+                    Err(InternalAstError::NoMatch) => match stack.pop() {
+                        None => return Err(InternalAstError::NoMatch),
+                        // There's no field without a key either, so the table has no more fields
+                        Some(Nested::Field(table, FieldKey::None)) => {
+                            let (new_state, table) = close_table(state, table)?;
+                            state = new_state;
+                            Step::Table(table)
+                        }
+
+                        // A call can have no arguments, but not a `,` without one after it
+                        Some(Nested::Arguments(value, call)) if call.arguments.is_empty() => {
+                            let (new_state, value) = close_call(state, value, call)?;
+                            state = new_state;
+                            Step::Suffixes(value)
+                        }
+
+                        Some(nested) => {
+                            let code = match &nested {
+                                Nested::UnOp(_)
+                                | Nested::BinOp(..)
+                                | Nested::Parentheses(_)
+                                | Nested::Index(..) => ErrorCode::ExpectedExpression,
+                                Nested::Key(..) => ErrorCode::ExpectedKey,
+                                Nested::Field(..) => ErrorCode::ExpectedValue,
+                                Nested::Arguments(..) => ErrorCode::TrailingCharacter,
+                                Nested::TableArgument(..) => {
+                                    unreachable!("tables are parsed as fields until they're closed")
+                                }
+                            };
+
+                            // It's put back to be dropped with the rest of the stack
+                            stack.push(nested);
+                            return Err(unexpected_token(&state, code));
+                        }
+                    },
+
+                    Err(other) => return Err(other),
+                }
+            }
+
+            Step::Prefix => {
+                let (new_state, start) = parse_prefix(&state)?;
                 state = new_state;
-                None
-            };
+                Step::Start(start)
+            }
 
-            let is_none = field_sep.is_none();
-            fields.push((field, field_sep));
-            if is_none {
-                break;
+            Step::Start(start) => match start {
+                ExpressionStart::Table(start_brace) => Step::Fields(PartialTable::new(start_brace)),
+                ExpressionStart::Value(value) => Step::Value(value),
+                ExpressionStart::UnOp(unop) => {
+                    stack.push(Nested::UnOp(unop));
+                    Step::Expression
+                }
+                ExpressionStart::Parentheses(left_paren) => {
+                    stack.push(Nested::Parentheses(left_paren));
+                    Step::Expression
+                }
+                ExpressionStart::Name(name) => {
+                    quiet.push(state.save_expected());
+                    Step::Suffixes(PartialValue::new(Prefix::Name(name)))
+                }
+            },
+
+            Step::Fields(table) => {
+                if let Ok((new_state, start_bracket)) =
+                    ParseSymbol(Symbol::LeftBracket).parse(state.clone())
+                {
+                    state = new_state;
+                    stack.push(Nested::Key(table, start_bracket));
+                } else {
+                    let name = match ParseIdentifier.parse(state.clone()) {
+                        Ok((new_state, key)) => ParseSymbol(Symbol::Equal)
+                            .parse(new_state)
+                            .ok()
+                            .map(|(new_state, equal)| (new_state, key, equal)),
+                        Err(_) => None,
+                    };
+
+                    if let Some((new_state, key, equal)) = name {
+                        state = new_state;
+                        stack.push(Nested::Field(table, FieldKey::Name { key, equal }));
+                    } else {
+                        stack.push(Nested::Field(table, FieldKey::None));
+                    }
+                }
+
+                Step::Expression
             }
-        }
 
-        let (state, end_brace) = expect!(
-            state,
-            ParseSymbol(Symbol::RightBrace).parse(state.clone()),
-            "expected '}'"
-        );
+            Step::Table(table) => match stack.pop() {
+                Some(Nested::TableArgument(mut value, method)) => {
+                    value
+                        .suffixes
+                        .push(call_suffix(method, FunctionArgs::TableConstructor(table)));
+                    Step::Suffixes(value)
+                }
 
-        Ok((
-            state,
-            TableConstructor {
-                braces: ContainedSpan::new(start_brace, end_brace),
-                fields,
+                nested => {
+                    stack.extend(nested);
+                    Step::Value(Value::TableConstructor(table))
+                }
             },
-        ))
-    }
-);
 
-#[derive(Clone, Debug, PartialEq)]
-struct ParseExpression;
-define_parser!(
-    ParseExpression,
-    Expression<'a>,
-    |_, state: ParserState<'a>| if let Ok((state, value)) =
-        keep_going!(ParseValue.parse(state.clone()))
-    {
-        let (state, binop) = if let Ok((state, bin_op)) = ParseBinOp.parse(state.clone()) {
-            let (state, rhs) = expect!(
-                state,
-                ParseExpression.parse(state.clone()),
-                "expected expression"
-            );
+            Step::Suffixes(mut value) => {
+                let call = if let Some((new_state, arguments)) = parse_arguments_start(&state) {
+                    Some((new_state, None, arguments))
+                } else if let Ok((new_state, colon_token)) =
+                    ParseSymbol(Symbol::Colon).parse(state.clone())
+                {
+                    let (new_state, name) = expect!(
+                        new_state,
+                        ParseName.parse(new_state.clone()),
+                        ErrorCode::ExpectedMethod
+                    );
+
+                    match parse_arguments_start(&new_state) {
+                        Some((new_state, arguments)) => {
+                            Some((new_state, Some(MethodName { colon_token, name }), arguments))
+                        }
+                        None => return Err(unexpected_token(&new_state, ErrorCode::ExpectedArgs)),
+                    }
+                } else {
+                    None
+                };
+
+                if let Some((new_state, method, arguments)) = call {
+                    state = new_state;
+                    match arguments {
+                        ArgumentsStart::Parentheses(left_paren) => {
+                            stack.push(Nested::Arguments(
+                                value,
+                                PartialCall {
+                                    method,
+                                    left_paren,
+                                    arguments: Punctuated::new(),
+                                },
+                            ));
+                            Step::Expression
+                        }
+                        ArgumentsStart::Table(start_brace) => {
+                            stack.push(Nested::TableArgument(value, method));
+                            Step::Fields(PartialTable::new(start_brace))
+                        }
+                        ArgumentsStart::String(string) => {
+                            value
+                                .suffixes
+                                .push(call_suffix(method, FunctionArgs::String(string)));
+                            Step::Suffixes(value)
+                        }
+                    }
+                } else if let Ok((new_state, start_bracket)) =
+                    ParseSymbol(Symbol::LeftBracket).parse(state.clone())
+                {
+                    state = new_state;
+                    stack.push(Nested::Index(value, start_bracket));
+                    Step::Expression
+                } else if let Ok((new_state, dot)) = ParseSymbol(Symbol::Dot).parse(state.clone()) {
+                    let (new_state, name) = expect!(
+                        new_state,
+                        ParseName.parse(new_state.clone()),
+                        ErrorCode::ExpectedName
+                    );
+
+                    state = new_state;
+                    value.suffixes.push(Suffix::Index(Index::Dot {
+                        dot,
+                        name,
+                        id: StoredId::new(),
+                    }));
+                    Step::Suffixes(value)
+                } else {
+                    // Suffixes can always be left out, so what was tried for them isn't in errors after them
+                    if let Some(expected) = quiet.pop() {
+                        state.restore_expected(expected);
+                    }
+
+                    if stack.is_empty() && parsing == Parsing::Prefix {
+                        return Ok((state, Parsed::Prefix(value)));
+                    }
+
+                    Step::Value(value.into_value())
+                }
+            }
 
-            (
-                state,
-                Some(BinOpRhs {
-                    bin_op,
-                    rhs: Box::new(rhs),
+            Step::Value(value) => {
+                if let Ok((new_state, bin_op)) = ParseBinOp.parse(state.clone()) {
+                    state = new_state;
+                    stack.push(Nested::BinOp(value, bin_op));
+                    Step::Expression
+                } else {
+                    Step::Done(Expression::Value {
+                        value: Box::new(value),
+                        binop: None,
+                        id: StoredId::new(),
+                    })
+                }
+            }
+
+            Step::Done(expression) => match stack.pop() {
+                None => return Ok((state, Parsed::Expression(expression))),
+
+                Some(Nested::UnOp(unop)) => Step::Done(Expression::UnaryOperator {
+                    unop,
+                    expression: Box::new(expression),
+                    id: StoredId::new(),
                 }),
-            )
-        } else {
-            (state, None)
-        };
 
-        let value = Box::new(value);
+                Some(Nested::BinOp(value, bin_op)) => Step::Done(Expression::Value {
+                    value: Box::new(value),
+                    binop: Some(BinOpRhs {
+                        bin_op,
+                        rhs: Box::new(expression),
+                        id: StoredId::new(),
+                    }),
+                    id: StoredId::new(),
+                }),
 
-        Ok((state, Expression::Value { value, binop }))
-    } else if let Ok((state, unop)) = keep_going!(ParseUnOp.parse(state.clone())) {
-        let (state, expression) = expect!(
-            state,
-            ParseExpression.parse(state.clone()),
-            "expected expression"
-        );
+                Some(Nested::Key(table, start_bracket)) => {
+                    let (new_state, end_bracket) = expect!(
+                        state,
+                        ParseSymbol(Symbol::RightBracket).parse(state.clone()),
+                        ErrorCode::ExpectedRightBracket,
+                        start_bracket
+                    );
+                    let (new_state, equal) = expect!(
+                        new_state,
+                        ParseSymbol(Symbol::Equal).parse(new_state.clone()),
+                        ErrorCode::ExpectedEquals
+                    );
+
+                    state = new_state;
+                    stack.push(Nested::Field(
+                        table,
+                        FieldKey::Expression {
+                            start_bracket,
+                            key: expression,
+                            end_bracket,
+                            equal,
+                        },
+                    ));
+
+                    Step::Expression
+                }
 
-        let expression = Box::new(expression);
+                Some(Nested::Field(mut table, key)) => {
+                    let field = match key {
+                        FieldKey::Expression {
+                            start_bracket,
+                            key,
+                            end_bracket,
+                            equal,
+                        } => Field::ExpressionKey {
+                            brackets: ContainedSpan::new(start_bracket, end_bracket),
+                            key,
+                            equal,
+                            value: expression,
+                            id: StoredId::new(),
+                        },
+                        FieldKey::Name { key, equal } => Field::NameKey {
+                            key,
+                            equal,
+                            value: expression,
+                            id: StoredId::new(),
+                        },
+                        FieldKey::None => Field::NoKey(expression),
+                    };
+
+                    let separator = if let Ok((new_state, separator)) =
+                        ParseSymbol(Symbol::Comma).parse(state.clone())
+                    {
+                        state = new_state;
+                        Some(separator)
+                    } else if let Ok((new_state, separator)) =
+                        ParseSymbol(Symbol::Semicolon).parse(state.clone())
+                    {
+                        state = new_state;
+                        Some(separator)
+                    } else {
+                        None
+                    };
+
+                    let is_none = separator.is_none();
+                    table.fields.push((field, separator));
+                    if !is_none {
+                        Step::Fields(table)
+                    } else {
+                        let (new_state, table) = close_table(state, table)?;
+                        state = new_state;
+                        Step::Table(table)
+                    }
+                }
 
-        Ok((state, Expression::UnaryOperator { unop, expression }))
-    } else {
-        Err(InternalAstError::NoMatch)
-    }
-);
+                Some(Nested::Parentheses(left_paren)) => {
+                    let (new_state, right_paren) = expect!(
+                        state,
+                        ParseSymbol(Symbol::RightParen).parse(state.clone()),
+                        ErrorCode::ExpectedRightParen,
+                        left_paren
+                    );
+
+                    state = new_state;
+                    quiet.push(state.save_expected());
+                    Step::Suffixes(PartialValue::new(Prefix::Expression(
+                        Expression::Parentheses {
+                            contained: ContainedSpan::new(left_paren, right_paren),
+                            expression: Box::new(expression),
+                            id: StoredId::new(),
+                        },
+                    )))
+                }
 
-#[derive(Clone, Debug, PartialEq)]
-struct ParseParenExpression;
-define_parser!(
-    ParseParenExpression,
-    Expression<'a>,
-    |_, state: ParserState<'a>| if let Ok((state, left_paren)) =
-        ParseSymbol(Symbol::LeftParen).parse(state.clone())
-    {
-        let (state, expression) = expect!(
-            state,
-            ParseExpression.parse(state.clone()),
-            "expected expression"
-        );
+                Some(Nested::Index(mut value, start_bracket)) => {
+                    let (new_state, end_bracket) = expect!(
+                        state,
+                        ParseSymbol(Symbol::RightBracket).parse(state.clone()),
+                        ErrorCode::ExpectedRightBracket,
+                        start_bracket
+                    );
+
+                    state = new_state;
+                    value.suffixes.push(Suffix::Index(Index::Brackets {
+                        brackets: ContainedSpan::new(start_bracket, end_bracket),
+                        expression,
+                        id: StoredId::new(),
+                    }));
+                    Step::Suffixes(value)
+                }
 
-        let (state, right_paren) = expect!(
-            state,
-            ParseSymbol(Symbol::RightParen).parse(state.clone()),
-            "expected ')'"
-        );
+                Some(Nested::Arguments(value, mut call)) => {
+                    if let Ok((new_state, comma)) = ParseSymbol(Symbol::Comma).parse(state.clone())
+                    {
+                        state = new_state;
+                        call.arguments.push(Pair::Punctuated(expression, comma));
+                        stack.push(Nested::Arguments(value, call));
+                        Step::Expression
+                    } else {
+                        call.arguments.push(Pair::End(expression));
+                        let (new_state, value) = close_call(state, value, call)?;
+                        state = new_state;
+                        Step::Suffixes(value)
+                    }
+                }
 
-        Ok((
-            state,
-            Expression::Parentheses {
-                contained: ContainedSpan::new(left_paren, right_paren),
-                expression: Box::new(expression),
+                Some(Nested::TableArgument(..)) => {
+                    unreachable!("tables are parsed as fields until they're closed")
+                }
             },
-        ))
+        };
+    }
+}
+
+// The start of a prefix: either the `(` of an expression in parentheses, or a name
+fn parse_prefix<'a>(
+    state: &ParserState<'a>,
+) -> Result<(ParserState<'a>, ExpressionStart<'a>), InternalAstError<'a>> {
+    if let Ok((state, left_paren)) = ParseSymbol(Symbol::LeftParen).parse(state.clone()) {
+        Ok((state, ExpressionStart::Parentheses(left_paren)))
     } else {
-        Err(InternalAstError::NoMatch)
+        let (state, name) = ParseIdentifier.parse(state.clone())?;
+        Ok((state, ExpressionStart::Name(name)))
     }
-);
+}
+
+fn parse_arguments_start<'a>(
+    state: &ParserState<'a>,
+) -> Option<(ParserState<'a>, ArgumentsStart<'a>)> {
+    if let Ok((state, left_paren)) = ParseSymbol(Symbol::LeftParen).parse(state.clone()) {
+        Some((state, ArgumentsStart::Parentheses(left_paren)))
+    } else if let Ok((state, start_brace)) = ParseSymbol(Symbol::LeftBrace).parse(state.clone()) {
+        Some((state, ArgumentsStart::Table(start_brace)))
+    } else {
+        ParseStringLiteral
+            .parse(state.clone())
+            .ok()
+            .map(|(state, string)| (state, ArgumentsStart::String(string)))
+    }
+}
+
+fn close_table<'a>(
+    state: ParserState<'a>,
+    mut table: PartialTable<'a>,
+) -> Result<(ParserState<'a>, TableConstructor<'a>), InternalAstError<'a>> {
+    let end_brace = ParseSymbol(Symbol::RightBrace).parse(state.clone());
+    if end_brace.is_err() {
+        drop_nodes(core::mem::take(&mut table.fields));
+    }
+
+    let (state, end_brace) = expect!(
+        state,
+        end_brace,
+        ErrorCode::ExpectedRightBrace,
+        table.start_brace
+    );
+
+    Ok((
+        state,
+        TableConstructor {
+            braces: ContainedSpan::new(table.start_brace, end_brace),
+            fields: table.fields,
+            id: StoredId::new(),
+        },
+    ))
+}
+
+// The value with the call in parentheses after it, after its last argument
+fn close_call<'a>(
+    state: ParserState<'a>,
+    mut value: PartialValue<'a>,
+    mut call: PartialCall<'a>,
+) -> Result<(ParserState<'a>, PartialValue<'a>), InternalAstError<'a>> {
+    let right_paren = ParseSymbol(Symbol::RightParen).parse(state.clone());
+    if right_paren.is_err() {
+        drop_nodes((
+            core::mem::take(&mut value.suffixes),
+            core::mem::replace(&mut call.arguments, Punctuated::new()),
+        ));
+    }
+
+    let (state, right_paren) = expect!(
+        state,
+        right_paren,
+        ErrorCode::ExpectedRightParen,
+        call.left_paren
+    );
+
+    value.suffixes.push(call_suffix(
+        call.method,
+        FunctionArgs::Parentheses {
+            arguments: call.arguments,
+            parentheses: ContainedSpan::new(call.left_paren, right_paren),
+            id: StoredId::new(),
+        },
+    ));
+    Ok((state, value))
+}
+
+fn call_suffix<'a>(method: Option<MethodName<'a>>, args: FunctionArgs<'a>) -> Suffix<'a> {
+    Suffix::Call(match method {
+        Some(MethodName { colon_token, name }) => Call::MethodCall(MethodCall {
+            colon_token,
+            name,
+            args,
+            id: StoredId::new(),
+        }),
+        None => Call::AnonymousCall(args),
+    })
+}
 
+// The error for the token where something with the code was expected
+fn unexpected_token<'a>(state: &ParserState<'a>, code: ErrorCode) -> InternalAstError<'a> {
+    InternalAstError::UnexpectedToken {
+        token: state.peek(),
+        additional: code.additional(),
+        opening: state.unclosed_block(),
+        expected: state.expected(),
+    }
+}
+
+// The values that aren't prefixes, which are parsed along with their suffixes by `parse_nested`
 #[derive(Clone, Debug, PartialEq)]
 struct ParseValue;
 define_parser!(
@@ -308,19 +1176,13 @@ define_parser!(
         ParseStringLiteral => Value::String,
         ParseSymbol(Symbol::Ellipse) => Value::Symbol,
         ParseFunction => Value::Function,
-        ParseTableConstructor => Value::TableConstructor,
-        ParseFunctionCall => Value::FunctionCall,
-        ParseVar => Value::Var,
-        ParseParenExpression => Value::ParseExpression,
     })
 );
 
 #[derive(Clone, Debug, Default, PartialEq)]
 struct ParseStmt;
-define_parser!(
-    ParseStmt,
-    Stmt<'a>,
-    |_, state: ParserState<'a>| parse_first_of!(state, {
+define_parser!(ParseStmt, Stmt<'a>, |_, state: ParserState<'a>| state
+    .expecting(ExpectedItem::Statement, |state| parse_first_of!(state, {
         ParseAssignment => Stmt::Assignment,
         ParseFunctionCall => Stmt::FunctionCall,
         ParseDo => Stmt::Do,
@@ -332,87 +1194,7 @@ define_parser!(
         ParseFunctionDeclaration => Stmt::FunctionDeclaration,
         ParseLocalFunction => Stmt::LocalFunction,
         ParseLocalAssignment => Stmt::LocalAssignment,
-    })
-);
-
-#[derive(Clone, Debug, PartialEq)]
-struct ParsePrefix;
-define_parser!(
-    ParsePrefix,
-    Prefix<'a>,
-    |_, state: ParserState<'a>| parse_first_of!(state, {
-        ParseParenExpression => Prefix::Expression,
-        ParseIdentifier => Prefix::Name,
-    })
-);
-
-struct ParseIndex;
-define_parser!(
-    ParseIndex,
-    Index<'a>,
-    |_, state: ParserState<'a>| if let Ok((state, start_bracket)) =
-        ParseSymbol(Symbol::LeftBracket).parse(state.clone())
-    {
-        let (state, expression) = expect!(
-            state,
-            ParseExpression.parse(state.clone()),
-            "expected expression"
-        );
-        let (state, end_bracket) = expect!(
-            state,
-            ParseSymbol(Symbol::RightBracket).parse(state.clone()),
-            "expected ']'"
-        );
-        Ok((
-            state,
-            Index::Brackets {
-                brackets: ContainedSpan::new(start_bracket, end_bracket),
-                expression,
-            },
-        ))
-    } else if let Ok((state, dot)) = ParseSymbol(Symbol::Dot).parse(state.clone()) {
-        let (state, name) = expect!(state, ParseIdentifier.parse(state.clone()), "expected name");
-        Ok((state, Index::Dot { dot, name }))
-    } else {
-        Err(InternalAstError::NoMatch)
-    }
-);
-
-#[derive(Clone, Debug, PartialEq)]
-struct ParseFunctionArgs;
-define_parser!(ParseFunctionArgs, FunctionArgs<'a>, |_,
-                                                     state: ParserState<
-    'a,
->| if let Ok((state, left_paren)) =
-    keep_going!(ParseSymbol(Symbol::LeftParen).parse(state.clone()))
-{
-    let (state, arguments) = expect!(
-        state,
-        ZeroOrMoreDelimited(ParseExpression, ParseSymbol(Symbol::Comma), false)
-            .parse(state.clone()),
-        "expected arguments"
-    );
-    let (state, right_paren) = expect!(
-        state,
-        ParseSymbol(Symbol::RightParen).parse(state.clone()),
-        "expected ')'"
-    );
-    Ok((
-        state,
-        FunctionArgs::Parentheses {
-            arguments,
-            parentheses: ContainedSpan::new(left_paren, right_paren),
-        },
-    ))
-} else if let Ok((state, table_constructor)) =
-    keep_going!(ParseTableConstructor.parse(state.clone()))
-{
-    Ok((state, FunctionArgs::TableConstructor(table_constructor)))
-} else if let Ok((state, string)) = keep_going!(ParseStringLiteral.parse(state.clone())) {
-    Ok((state, FunctionArgs::String(string)))
-} else {
-    Err(InternalAstError::NoMatch)
-});
+    })));
 
 #[derive(Clone, Debug, PartialEq)]
 struct ParseNumericFor;
@@ -421,33 +1203,34 @@ define_parser!(
     NumericFor<'a>,
     |_, state: ParserState<'a>| {
         let (state, for_token) = ParseSymbol(Symbol::For).parse(state.clone())?;
+        let state = state.open_block(&for_token);
         let (state, index_variable) = expect!(
             state,
-            ParseIdentifier.parse(state.clone()),
-            "expected names"
+            ParseName.parse(state.clone()),
+            ErrorCode::ExpectedNames
         );
         let (state, equal_token) = ParseSymbol(Symbol::Equal).parse(state.clone())?; // Numeric fors run before generic fors, so we can't guarantee this
         let (state, start) = expect!(
             state,
             ParseExpression.parse(state.clone()),
-            "expected start expression"
+            ErrorCode::ExpectedStartExpression
         );
         let (state, start_end_comma) = expect!(
             state,
             ParseSymbol(Symbol::Comma).parse(state.clone()),
-            "expected comma"
+            ErrorCode::ExpectedComma
         );
         let (state, end) = expect!(
             state,
             ParseExpression.parse(state.clone()),
-            "expected end expression"
+            ErrorCode::ExpectedEndExpression
         );
         let (state, step, end_step_comma) =
             if let Ok((state, comma)) = ParseSymbol(Symbol::Comma).parse(state.clone()) {
                 let (state, expression) = expect!(
                     state,
                     ParseExpression.parse(state.clone()),
-                    "expected limit expression"
+                    ErrorCode::ExpectedLimitExpression
                 );
                 (state, Some(expression), Some(comma))
             } else {
@@ -456,14 +1239,20 @@ define_parser!(
         let (state, do_token) = expect!(
             state,
             ParseSymbol(Symbol::Do).parse(state.clone()),
-            "expected 'do'"
+            ErrorCode::ExpectedDo
+        );
+        let (state, block) = expect!(
+            state,
+            ParseBlock.parse(state.clone()),
+            ErrorCode::ExpectedBlock
         );
-        let (state, block) = expect!(state, ParseBlock.parse(state.clone()), "expected block");
         let (state, end_token) = expect!(
             state,
             ParseSymbol(Symbol::End).parse(state.clone()),
-            "expected 'end'"
+            ErrorCode::ExpectedEnd,
+            for_token
         );
+        let state = state.close_block();
 
         Ok((
             state,
@@ -479,6 +1268,7 @@ define_parser!(
                 do_token,
                 block,
                 end_token,
+                id: StoredId::new(),
             },
         ))
     }
@@ -491,32 +1281,39 @@ define_parser!(
     GenericFor<'a>,
     |_, state: ParserState<'a>| {
         let (state, for_token) = ParseSymbol(Symbol::For).parse(state.clone())?;
+        let state = state.open_block(&for_token);
         let (state, names) = expect!(
             state,
-            OneOrMore(ParseIdentifier, ParseSymbol(Symbol::Comma), false).parse(state.clone()),
-            "expected names"
+            OneOrMore(ParseName, ParseSymbol(Symbol::Comma), false).parse(state.clone()),
+            ErrorCode::ExpectedNames
         );
         let (state, in_token) = expect!(
             state,
             ParseSymbol(Symbol::In).parse(state.clone()),
-            "expected 'in'"
+            ErrorCode::ExpectedIn
         ); // Numeric fors run before here, so there has to be an in
         let (state, expr_list) = expect!(
             state,
             OneOrMore(ParseExpression, ParseSymbol(Symbol::Comma), false).parse(state.clone()),
-            "expected expression"
+            ErrorCode::ExpectedExpression
         );
         let (state, do_token) = expect!(
             state,
             ParseSymbol(Symbol::Do).parse(state.clone()),
-            "expected 'do'"
+            ErrorCode::ExpectedDo
+        );
+        let (state, block) = expect!(
+            state,
+            ParseBlock.parse(state.clone()),
+            ErrorCode::ExpectedBlock
         );
-        let (state, block) = expect!(state, ParseBlock.parse(state.clone()), "expected block");
         let (state, end_token) = expect!(
             state,
             ParseSymbol(Symbol::End).parse(state.clone()),
-            "expected 'end'"
+            ErrorCode::ExpectedEnd,
+            for_token
         );
+        let state = state.close_block();
         Ok((
             state,
             GenericFor {
@@ -527,6 +1324,7 @@ define_parser!(
                 do_token,
                 block,
                 end_token,
+                id: StoredId::new(),
             },
         ))
     }
@@ -536,43 +1334,57 @@ define_parser!(
 struct ParseIf;
 define_parser!(ParseIf, If<'a>, |_, state: ParserState<'a>| {
     let (state, if_token) = ParseSymbol(Symbol::If).parse(state.clone())?;
+    let state = state.open_block(&if_token);
     let (state, condition) = expect!(
         state,
         ParseExpression.parse(state.clone()),
-        "expected condition"
+        ErrorCode::ExpectedCondition
     );
     let (state, then_token) = expect!(
         state,
         ParseSymbol(Symbol::Then).parse(state.clone()),
-        "expected 'then'"
+        ErrorCode::ExpectedThen
+    );
+    let (mut state, block) = expect!(
+        state,
+        ParseBlock.parse(state.clone()),
+        ErrorCode::ExpectedBlock
     );
-    let (mut state, block) = expect!(state, ParseBlock.parse(state.clone()), "expected block");
 
     let mut else_ifs = Vec::new();
     while let Ok((new_state, else_if_token)) = ParseSymbol(Symbol::ElseIf).parse(state.clone()) {
         let (new_state, condition) = expect!(
-            state,
-            ParseExpression.parse(new_state),
-            "expected condition"
+            new_state,
+            ParseExpression.parse(new_state.clone()),
+            ErrorCode::ExpectedCondition
         );
         let (new_state, then_token) = expect!(
-            state,
-            ParseSymbol(Symbol::Then).parse(new_state),
-            "expected 'then'"
+            new_state,
+            ParseSymbol(Symbol::Then).parse(new_state.clone()),
+            ErrorCode::ExpectedThen
+        );
+        let (new_state, block) = expect!(
+            new_state,
+            ParseBlock.parse(new_state.clone()),
+            ErrorCode::ExpectedBlock
         );
-        let (new_state, block) = expect!(state, ParseBlock.parse(new_state), "expected block");
         state = new_state;
         else_ifs.push(ElseIf {
             else_if_token,
             condition,
             then_token,
             block,
+            id: StoredId::new(),
         });
     }
 
     let (state, else_token, r#else) =
         if let Ok((state, else_token)) = ParseSymbol(Symbol::Else).parse(state.clone()) {
-            let (state, block) = expect!(state, ParseBlock.parse(state.clone()), "expected block");
+            let (state, block) = expect!(
+                state,
+                ParseBlock.parse(state.clone()),
+                ErrorCode::ExpectedBlock
+            );
             (state, Some(else_token), Some(block))
         } else {
             (state, None, None)
@@ -581,8 +1393,10 @@ define_parser!(ParseIf, If<'a>, |_, state: ParserState<'a>| {
     let (state, end_token) = expect!(
         state,
         ParseSymbol(Symbol::End).parse(state.clone()),
-        "expected 'end'"
+        ErrorCode::ExpectedEnd,
+        if_token
     );
+    let state = state.close_block();
 
     Ok((
         state,
@@ -599,6 +1413,7 @@ define_parser!(ParseIf, If<'a>, |_, state: ParserState<'a>| {
                 Some(else_ifs)
             },
             end_token,
+            id: StoredId::new(),
         },
     ))
 });
@@ -607,22 +1422,29 @@ define_parser!(ParseIf, If<'a>, |_, state: ParserState<'a>| {
 struct ParseWhile;
 define_parser!(ParseWhile, While<'a>, |_, state: ParserState<'a>| {
     let (state, while_token) = ParseSymbol(Symbol::While).parse(state.clone())?;
+    let state = state.open_block(&while_token);
     let (state, condition) = expect!(
         state,
         ParseExpression.parse(state.clone()),
-        "expected condition"
+        ErrorCode::ExpectedCondition
     );
     let (state, do_token) = expect!(
         state,
         ParseSymbol(Symbol::Do).parse(state.clone()),
-        "expected 'do'"
+        ErrorCode::ExpectedDo
+    );
+    let (state, block) = expect!(
+        state,
+        ParseBlock.parse(state.clone()),
+        ErrorCode::ExpectedBlock
     );
-    let (state, block) = expect!(state, ParseBlock.parse(state.clone()), "expected block");
     let (state, end_token) = expect!(
         state,
         ParseSymbol(Symbol::End).parse(state.clone()),
-        "expected 'end'"
+        ErrorCode::ExpectedEnd,
+        while_token
     );
+    let state = state.close_block();
     Ok((
         state,
         While {
@@ -631,6 +1453,7 @@ define_parser!(ParseWhile, While<'a>, |_, state: ParserState<'a>| {
             do_token,
             block,
             end_token,
+            id: StoredId::new(),
         },
     ))
 });
@@ -639,16 +1462,23 @@ define_parser!(ParseWhile, While<'a>, |_, state: ParserState<'a>| {
 struct ParseRepeat;
 define_parser!(ParseRepeat, Repeat<'a>, |_, state: ParserState<'a>| {
     let (state, repeat_token) = ParseSymbol(Symbol::Repeat).parse(state.clone())?;
-    let (state, block) = expect!(state, ParseBlock.parse(state.clone()), "expected block");
+    let state = state.open_block(&repeat_token);
+    let (state, block) = expect!(
+        state,
+        ParseBlock.parse(state.clone()),
+        ErrorCode::ExpectedBlock
+    );
     let (state, until_token) = expect!(
         state,
         ParseSymbol(Symbol::Until).parse(state.clone()),
-        "expected 'until'"
+        ErrorCode::ExpectedUntil,
+        repeat_token
     );
+    let state = state.close_block();
     let (state, until) = expect!(
         state,
         ParseExpression.parse(state.clone()),
-        "expected condition"
+        ErrorCode::ExpectedCondition
     );
     Ok((
         state,
@@ -657,105 +1487,111 @@ define_parser!(ParseRepeat, Repeat<'a>, |_, state: ParserState<'a>| {
             until_token,
             until,
             block,
+            id: StoredId::new(),
         },
     ))
 });
 
-struct ParseMethodCall;
-define_parser!(
-    ParseMethodCall,
-    MethodCall<'a>,
-    |_, state: ParserState<'a>| {
-        let (state, colon_token) = ParseSymbol(Symbol::Colon).parse(state.clone())?;
-        let (state, name) = expect!(
+// The token is the `function` keyword before the body, which its `end` closes
+#[derive(Clone, Debug, PartialEq)]
+struct ParseFunctionBody<'a, 'b>(&'b TokenReference<'a>);
+impl<'a, 'b> Parser<'a> for ParseFunctionBody<'a, 'b> {
+    type Item = FunctionBody<'a>;
+
+    fn parse(
+        &self,
+        state: ParserState<'a>,
+    ) -> Result<(ParserState<'a>, FunctionBody<'a>), InternalAstError<'a>> {
+        #[cfg(feature = "tracing")]
+        #[cold]
+        #[inline(never)]
+        fn span(state: &ParserState) -> tracing::span::EnteredSpan {
+            tracing::trace_span!(
+                "ParseFunctionBody",
+                index = state.index,
+                bytes = state.bytes()
+            )
+            .entered()
+        }
+
+        #[cfg(feature = "tracing")]
+        let _span = span(&state);
+
+        let result = self.parse_body(state);
+        #[cfg(feature = "tracing")]
+        super::parser_util::trace_result(&result);
+        result
+    }
+}
+
+impl<'a, 'b> ParseFunctionBody<'a, 'b> {
+    fn parse_body(
+        &self,
+        state: ParserState<'a>,
+    ) -> Result<(ParserState<'a>, FunctionBody<'a>), InternalAstError<'a>> {
+        let state = state.open_block(self.0);
+        let (mut state, start_paranthese) = expect!(
             state,
-            ParseIdentifier.parse(state.clone()),
-            "expected method"
+            ParseSymbol(Symbol::LeftParen).parse(state.clone()),
+            ErrorCode::ExpectedLeftParen
         );
-        let (state, args) = expect!(
+
+        let mut parameters = Punctuated::new();
+
+        if let Ok((new_state, names)) = keep_going!(OneOrMore(
+            ParseName,
+            ParseSymbol(Symbol::Comma),
+            false
+        )
+        .parse(state.clone()))
+        {
+            state = new_state;
+            parameters.extend(names.into_pairs().map(|pair| {
+                let tuple = pair.into_tuple();
+                Pair::new(Parameter::Name(tuple.0), tuple.1)
+            }));
+
+            if let Ok((new_state, comma)) = ParseSymbol(Symbol::Comma).parse(state.clone()) {
+                if let Ok((new_state, ellipse)) = ParseSymbol(Symbol::Ellipse).parse(new_state) {
+                    state = new_state;
+                    parameters.push(Pair::new(Parameter::Ellipse(ellipse), Some(comma)));
+                }
+            }
+        } else if let Ok((new_state, ellipse)) = ParseSymbol(Symbol::Ellipse).parse(state.clone()) {
+            state = new_state;
+            parameters.push(Pair::new(Parameter::Ellipse(ellipse), None));
+        }
+
+        let (state, end_parenthese) = expect!(
             state,
-            ParseFunctionArgs.parse(state.clone()),
-            "expected args"
+            ParseSymbol(Symbol::RightParen).parse(state.clone()),
+            ErrorCode::ExpectedRightParen,
+            start_paranthese
         );
+        let (state, block) = expect!(
+            state,
+            ParseBlock.parse(state.clone()),
+            ErrorCode::ExpectedBlock
+        );
+        let (state, end_token) = expect!(
+            state,
+            ParseSymbol(Symbol::End).parse(state.clone()),
+            ErrorCode::ExpectedEnd,
+            self.0
+        );
+        let state = state.close_block();
         Ok((
             state,
-            MethodCall {
-                colon_token,
-                name,
-                args,
+            FunctionBody {
+                parameters_parantheses: ContainedSpan::new(start_paranthese, end_parenthese),
+                parameters,
+                block,
+                end_token,
+                id: StoredId::new(),
             },
         ))
     }
-);
-
-#[derive(Clone, Debug, PartialEq)]
-struct ParseCall;
-define_parser!(
-    ParseCall,
-    Call<'a>,
-    |_, state: ParserState<'a>| parse_first_of!(state, {
-        ParseFunctionArgs => Call::AnonymousCall,
-        ParseMethodCall => Call::MethodCall,
-    })
-);
-
-#[derive(Clone, Debug, PartialEq)]
-struct ParseFunctionBody;
-define_parser!(ParseFunctionBody, FunctionBody<'a>, |_,
-                                                     state: ParserState<
-    'a,
->| {
-    let (mut state, start_paranthese) = expect!(
-        state,
-        ParseSymbol(Symbol::LeftParen).parse(state.clone()),
-        "expected '('"
-    );
-
-    let mut parameters = Punctuated::new();
-
-    if let Ok((new_state, names)) =
-        keep_going!(
-            OneOrMore(ParseIdentifier, ParseSymbol(Symbol::Comma), false).parse(state.clone())
-        )
-    {
-        state = new_state;
-        parameters.extend(names.into_pairs().map(|pair| {
-            let tuple = pair.into_tuple();
-            Pair::new(Parameter::Name(tuple.0), tuple.1)
-        }));
-
-        if let Ok((new_state, comma)) = ParseSymbol(Symbol::Comma).parse(state.clone()) {
-            if let Ok((new_state, ellipse)) = ParseSymbol(Symbol::Ellipse).parse(new_state) {
-                state = new_state;
-                parameters.push(Pair::new(Parameter::Ellipse(ellipse), Some(comma)));
-            }
-        }
-    } else if let Ok((new_state, ellipse)) = ParseSymbol(Symbol::Ellipse).parse(state.clone()) {
-        state = new_state;
-        parameters.push(Pair::new(Parameter::Ellipse(ellipse), None));
-    }
-
-    let (state, end_parenthese) = expect!(
-        state,
-        ParseSymbol(Symbol::RightParen).parse(state.clone()),
-        "expected ')'"
-    );
-    let (state, block) = expect!(state, ParseBlock.parse(state.clone()), "expected block");
-    let (state, end_token) = expect!(
-        state,
-        ParseSymbol(Symbol::End).parse(state.clone()),
-        "expected 'end'"
-    );
-    Ok((
-        state,
-        FunctionBody {
-            parameters_parantheses: ContainedSpan::new(start_paranthese, end_parenthese),
-            parameters,
-            block,
-            end_token,
-        },
-    ))
-});
+}
 
 #[derive(Clone, Debug, PartialEq)]
 struct ParseFunction;
@@ -766,36 +1602,33 @@ define_parser!(
         let (state, token) = ParseSymbol(Symbol::Function).parse(state.clone())?;
         let (state, body) = expect!(
             state,
-            ParseFunctionBody.parse(state.clone()),
-            "expected function body"
+            ParseFunctionBody(&token).parse(state.clone()),
+            ErrorCode::ExpectedFunctionBody
         );
         Ok((state, (token, body)))
     }
 );
 
-#[derive(Clone, Debug, PartialEq)]
-struct ParseSuffix;
-define_parser!(
-    ParseSuffix,
-    Suffix<'a>,
-    |_, state: ParserState<'a>| parse_first_of!(state, {
-        ParseCall => Suffix::Call,
-        ParseIndex => Suffix::Index,
-    })
-);
-
 #[derive(Clone, Debug, PartialEq)]
 struct ParseVarExpression;
 define_parser!(
     ParseVarExpression,
     VarExpression<'a>,
     |_, state: ParserState<'a>| {
-        let (state, prefix) = ParsePrefix.parse(state.clone())?;
-        let (state, suffixes) = ZeroOrMore(ParseSuffix).parse(state.clone())?;
+        let (state, PartialValue { prefix, suffixes }) = parse_suffixed(state)?;
 
         if let Some(Suffix::Index(_)) = suffixes.last() {
-            Ok((state, VarExpression { prefix, suffixes }))
+            Ok((
+                state,
+                VarExpression {
+                    prefix,
+                    suffixes,
+                    id: StoredId::new(),
+                },
+            ))
         } else {
+            // Statements that are calls are parsed as variables first
+            drop_nodes((prefix, suffixes));
             Err(InternalAstError::NoMatch)
         }
     }
@@ -824,7 +1657,7 @@ define_parser!(
         let (state, expr_list) = expect!(
             state,
             OneOrMore(ParseExpression, ParseSymbol(Symbol::Comma), false).parse(state.clone()),
-            "expected values"
+            ErrorCode::ExpectedValues
         );
 
         Ok((
@@ -833,6 +1666,7 @@ define_parser!(
                 var_list,
                 equal_token,
                 expr_list,
+                id: StoredId::new(),
             },
         ))
     }
@@ -846,8 +1680,12 @@ define_parser!(
     |_, state: ParserState<'a>| {
         let (state, local_token) = ParseSymbol(Symbol::Local).parse(state.clone())?;
         let (state, function_token) = ParseSymbol(Symbol::Function).parse(state.clone())?;
-        let (state, name) = expect!(state, ParseIdentifier.parse(state.clone()), "expected name");
-        let (state, func_body) = ParseFunctionBody.parse(state.clone())?;
+        let (state, name) = expect!(
+            state,
+            ParseName.parse(state.clone()),
+            ErrorCode::ExpectedName
+        );
+        let (state, func_body) = ParseFunctionBody(&function_token).parse(state.clone())?;
         Ok((
             state,
             LocalFunction {
@@ -855,6 +1693,7 @@ define_parser!(
                 function_token,
                 name,
                 func_body,
+                id: StoredId::new(),
             },
         ))
     }
@@ -869,21 +1708,19 @@ define_parser!(
         let (state, local_token) = ParseSymbol(Symbol::Local).parse(state.clone())?;
         let (state, name_list) = expect!(
             state,
-            OneOrMore(ParseIdentifier, ParseSymbol(Symbol::Comma), false).parse(state.clone()),
-            "expected name"
+            OneOrMore(ParseName, ParseSymbol(Symbol::Comma), false).parse(state.clone()),
+            ErrorCode::ExpectedName
         );
 
         let ((state, expr_list), equal_token) =
             match ParseSymbol(Symbol::Equal).parse(state.clone()) {
                 Ok((state, equal_token)) => (
-                    OneOrMore(ParseExpression, ParseSymbol(Symbol::Comma), false)
-                        .parse(state.clone())
-                        .or_else(|_| {
-                            Err(InternalAstError::UnexpectedToken {
-                                token: state.peek(),
-                                additional: Some("expected expression"),
-                            })
-                        })?,
+                    expect!(
+                        state,
+                        OneOrMore(ParseExpression, ParseSymbol(Symbol::Comma), false)
+                            .parse(state.clone()),
+                        ErrorCode::ExpectedExpression
+                    ),
                     Some(equal_token),
                 ),
                 Err(InternalAstError::NoMatch) => ((state, Punctuated::new()), None),
@@ -897,6 +1734,7 @@ define_parser!(
                 name_list,
                 equal_token,
                 expr_list,
+                id: StoredId::new(),
             },
         ))
     }
@@ -906,12 +1744,19 @@ define_parser!(
 struct ParseDo;
 define_parser!(ParseDo, Do<'a>, |_, state: ParserState<'a>| {
     let (state, do_token) = ParseSymbol(Symbol::Do).parse(state.clone())?;
-    let (state, block) = expect!(state, ParseBlock.parse(state.clone()), "expected block");
+    let state = state.open_block(&do_token);
+    let (state, block) = expect!(
+        state,
+        ParseBlock.parse(state.clone()),
+        ErrorCode::ExpectedBlock
+    );
     let (state, end_token) = expect!(
         state,
         ParseSymbol(Symbol::End).parse(state.clone()),
-        "expected 'end'"
+        ErrorCode::ExpectedEnd,
+        do_token
     );
+    let state = state.close_block();
 
     Ok((
         state,
@@ -919,6 +1764,7 @@ define_parser!(ParseDo, Do<'a>, |_, state: ParserState<'a>| {
             do_token,
             block,
             end_token,
+            id: StoredId::new(),
         },
     ))
 });
@@ -929,12 +1775,19 @@ define_parser!(ParseFunctionCall, FunctionCall<'a>, |_,
                                                      state: ParserState<
     'a,
 >| {
-    let (state, prefix) = ParsePrefix.parse(state.clone())?;
-    let (state, suffixes) = ZeroOrMore(ParseSuffix).parse(state.clone())?;
+    let (state, PartialValue { prefix, suffixes }) = parse_suffixed(state)?;
 
     if let Some(Suffix::Call(_)) = suffixes.last() {
-        Ok((state, FunctionCall { prefix, suffixes }))
+        Ok((
+            state,
+            FunctionCall {
+                prefix,
+                suffixes,
+                id: StoredId::new(),
+            },
+        ))
     } else {
+        drop_nodes((prefix, suffixes));
         Err(InternalAstError::NoMatch)
     }
 });
@@ -946,20 +1799,27 @@ define_parser!(ParseFunctionName, FunctionName<'a>, |_,
     'a,
 >| {
     let (state, names) =
-        OneOrMore(ParseIdentifier, ParseSymbol(Symbol::Dot), false).parse(state.clone())?;
+        OneOrMore(ParseName, ParseSymbol(Symbol::Dot), false).parse(state.clone())?;
     let (state, colon_name) =
         if let Ok((state, colon)) = ParseSymbol(Symbol::Colon).parse(state.clone()) {
             let (state, colon_name) = expect!(
                 state,
-                ParseIdentifier.parse(state.clone()),
-                "expected method name"
+                ParseName.parse(state.clone()),
+                ErrorCode::ExpectedMethodName
             );
             (state, Some((colon, colon_name)))
         } else {
             (state, None)
         };
 
-    Ok((state, FunctionName { names, colon_name }))
+    Ok((
+        state,
+        FunctionName {
+            names,
+            colon_name,
+            id: StoredId::new(),
+        },
+    ))
 });
 
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -972,12 +1832,12 @@ define_parser!(
         let (state, name) = expect!(
             state,
             ParseFunctionName.parse(state.clone()),
-            "expected function name"
+            ErrorCode::ExpectedFunctionName
         );
         let (state, body) = expect!(
             state,
-            ParseFunctionBody.parse(state.clone()),
-            "expected function body"
+            ParseFunctionBody(&function_token).parse(state.clone()),
+            ErrorCode::ExpectedFunctionBody
         );
         Ok((
             state,
@@ -985,6 +1845,7 @@ define_parser!(
                 function_token,
                 name,
                 body,
+                id: StoredId::new(),
             },
         ))
     }
@@ -1000,20 +1861,60 @@ define_parser!(ParseIdentifier, TokenReference<'a>, |_, state: ParserState<'a>|
             state.advance().ok_or(InternalAstError::NoMatch)?,
             next_token,
         )),
-        _ => Err(InternalAstError::NoMatch),
+        _ => {
+            state.expect(ExpectedItem::Identifier);
+            Err(InternalAstError::NoMatch)
+        }
     }
 });
 
+// A name where nothing else could be, such as after `local`, failing with a more helpful error for a keyword
+// than the unexpected token it would be otherwise
+#[derive(Clone, Debug, Default, PartialEq)]
+struct ParseName;
+define_parser!(
+    ParseName,
+    TokenReference<'a>,
+    |_, state: ParserState<'a>| {
+        let next_token = state.peek();
+        let keyword = match &*next_token.token_type() {
+            TokenType::Symbol { symbol } if symbol.is_keyword() => Some(*symbol),
+            _ => None,
+        };
+
+        match keyword {
+            Some(keyword) => Err(InternalAstError::ReservedKeywordAsName {
+                token: next_token,
+                keyword,
+            }),
+            None => ParseIdentifier.parse(state),
+        }
+    }
+);
+
 macro_rules! make_op_parser {
 	($enum:ident, $parser:ident, { $($operator:ident,)+ }) => {
 		#[derive(Clone, Debug, PartialEq)]
         struct $parser;
         define_parser!($parser, $enum<'a>, |_, state: ParserState<'a>| {
-            $(
-                if let Ok((state, operator)) = ParseSymbol(Symbol::$operator).parse(state.clone()) {
-                    return Ok((state.clone(), $enum::$operator(operator)));
-                }
-            )+
+            // The token is looked at once, instead of trying each operator, since this is tried after every value.
+            // Nothing is recorded as expected: binary operators can always be left out, and unary operators are only
+            // tried where if nothing matches, only that an expression was expected is recorded.
+            let token = state.peek();
+            let symbol = match &*token.token_type() {
+                TokenType::Symbol { symbol } => Some(*symbol),
+                _ => None,
+            };
+
+            match symbol {
+                $(
+                    Some(Symbol::$operator) => {
+                        let state = state.advance().ok_or(InternalAstError::NoMatch)?;
+                        return Ok((state, $enum::$operator(token)));
+                    }
+                )+
+                _ => {}
+            }
 
 			// This is to ensure the operators ALWAYS match those in the actual operator
 			// It won't compile if they don't match up
@@ -1061,6 +1962,7 @@ make_op_parser!(UnOp, ParseUnOp,
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::parser_util::ZeroOrMore;
     use crate::tokenizer::tokens;
     use pretty_assertions::assert_eq;
 
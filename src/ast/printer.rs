@@ -0,0 +1,1150 @@
+//! Printers that change the whitespace of the code they print.
+use super::{punctuated::Punctuated, span::ContainedSpan, *};
+use crate::prelude::*;
+use crate::tokenizer::{Token, TokenKind, TokenReference, TokenType};
+use alloc::sync::Arc;
+use core::{fmt::Write, mem};
+use generational_arena::Arena;
+
+/// The indentation to use for every level of nesting
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+    /// Indent with the amount of spaces
+    Spaces(usize),
+    /// Indent with one tab
+    Tabs,
+}
+
+impl Indent {
+    // The whitespace for one level of nesting
+    pub(crate) fn whitespace(self) -> String {
+        match self {
+            Indent::Spaces(width) => " ".repeat(width),
+            Indent::Tabs => "\t".to_owned(),
+        }
+    }
+}
+
+/// The characters used to end lines
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, used by Unix
+    Lf,
+    /// `\r\n`, used by Windows
+    CrLf,
+    /// The line endings already in the code. When new lines are added, such as in
+    /// [`print_pretty`](fn.print_pretty.html), the first line ending in the code is used, or `\n` if there are none.
+    Preserve,
+}
+
+impl LineEnding {
+    // `None` when preserving line endings
+    pub(crate) fn as_str(self) -> Option<&'static str> {
+        match self {
+            LineEnding::Lf => Some("\n"),
+            LineEnding::CrLf => Some("\r\n"),
+            LineEnding::Preserve => None,
+        }
+    }
+
+    fn detect(ast: &Ast) -> &'static str {
+        ast.iter_tokens()
+            .find_map(|token| match &*token.token_type() {
+                TokenType::Whitespace { characters } if characters.contains('\n') => {
+                    Some(if characters.contains("\r\n") {
+                        "\r\n"
+                    } else {
+                        "\n"
+                    })
+                }
+                _ => None,
+            })
+            .unwrap_or("\n")
+    }
+}
+
+/// The options for [`print_with`](fn.print_with.html).
+/// Use [`PrintConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrintConfig {
+    /// The characters to end lines with, [`LineEnding::Preserve`](enum.LineEnding.html#variant.Preserve) by default
+    pub line_ending: LineEnding,
+}
+
+impl Default for PrintConfig {
+    fn default() -> Self {
+        Self {
+            line_ending: LineEnding::Preserve,
+        }
+    }
+}
+
+/// Prints back Lua code from an [`Ast`](ast/struct.Ast.html) like [`print`](fn.print.html),
+/// using the options of the [config](struct.PrintConfig.html).
+///
+/// Line endings are only changed in whitespace and at the end of single line comments.
+/// The contents of strings, including multi-line strings, and of multi-line comments are left untouched.
+///
+/// ```rust
+/// # use full_moon::{LineEnding, PrintConfig};
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let ast = full_moon::parse("local x = 1 -- one\r\nlocal y = [[a\r\nb]]\r\n")?;
+/// let config = PrintConfig {
+///     line_ending: LineEnding::Lf,
+/// };
+///
+/// assert_eq!(
+///     full_moon::print_with(&ast, &config),
+///     "local x = 1 -- one\nlocal y = [[a\r\nb]]\n"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn print_with(ast: &Ast, config: &PrintConfig) -> String {
+    let line_ending = match config.line_ending.as_str() {
+        Some(line_ending) => line_ending,
+        None => return crate::print(ast),
+    };
+
+    let mut output = String::new();
+    for token in ast.iter_tokens() {
+        match &*token.token_type() {
+            TokenType::Whitespace { characters } => {
+                let mut lines = characters.split('\n').peekable();
+                while let Some(line) = lines.next() {
+                    if lines.peek().is_some() {
+                        output.push_str(line.strip_suffix('\r').unwrap_or(line));
+                        output.push_str(line_ending);
+                    } else {
+                        output.push_str(line);
+                    }
+                }
+            }
+
+            TokenType::SingleLineComment { comment } => {
+                output.push_str("--");
+                output.push_str(comment.trim_end_matches('\r'));
+            }
+
+            _ => write!(output, "{}", token).expect("writing to a string can't fail"),
+        }
+    }
+
+    output
+}
+
+/// The options for [`print_pretty`](../fn.print_pretty.html).
+/// Use [`PrettyConfig::default()`](#impl-Default) for the fields you don't want to change.
+///
+/// ```rust
+/// # use full_moon::{Indent, PrettyConfig};
+/// let config = PrettyConfig {
+///     indent: Indent::Tabs,
+///     ..PrettyConfig::default()
+/// };
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrettyConfig {
+    /// The indentation for every level of nesting, 4 spaces by default
+    pub indent: Indent,
+    /// The characters to end lines with, `\n` by default
+    pub line_ending: LineEnding,
+    /// Whether to put spaces around binary operators, such as `1 + 2` instead of `1+2`. True by default.
+    /// Spaces are always used when they are needed, such as in `x and y`.
+    pub spaces_around_operators: bool,
+    /// Whether to put a space after commas and separators in tables, such as `f(1, 2)` instead of `f(1,2)`. True by default.
+    pub space_after_commas: bool,
+    /// Whether to put blocks with a single statement in them on one line, such as `if x then return end`. False by default.
+    pub collapse_single_statement_blocks: bool,
+    /// The longest a line can be, in characters, before the arguments of a function call, the fields of a table,
+    /// or the parameters of a function on it are put on their own lines. 120 by default.
+    pub max_width: usize,
+    /// Whether to put a semicolon after statements that the statement after them would otherwise continue,
+    /// such as in `f();` followed by `(g)()`, which Lua reads as `f()(g)()` without it. True by default.
+    /// See [`Ast::ambiguous_statements`](ast/struct.Ast.html#method.ambiguous_statements).
+    /// Code that was parsed never needs them, but statements can be replaced with ones that do.
+    pub separate_ambiguous_statements: bool,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        Self {
+            indent: Indent::Spaces(4),
+            line_ending: LineEnding::Lf,
+            spaces_around_operators: true,
+            space_after_commas: true,
+            collapse_single_statement_blocks: false,
+            max_width: 120,
+            separate_ambiguous_statements: true,
+        }
+    }
+}
+
+/// Prints back Lua code from an [`Ast`](ast/struct.Ast.html), replacing its whitespace with new whitespace
+/// from the [config](struct.PrettyConfig.html). Every statement is put on its own line and indented.
+///
+/// Comments are kept. Comments that were on their own lines are put on their own lines before the next
+/// token, which is usually the start of the statement they document, and comments at the end of a line
+/// are kept at the end of the line.
+///
+/// Function arguments, table fields, and function parameters are put on one line if it fits in
+/// [`max_width`](struct.PrettyConfig.html#structfield.max_width). Otherwise, every one of them is put on its own line,
+/// breaking after the commas, and indented one level more than the line they started on:
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let ast = full_moon::parse("call(first, { 1, 2 }, -- comment\nlast)")?;
+/// let config = full_moon::PrettyConfig {
+///     max_width: 20,
+///     ..full_moon::PrettyConfig::default()
+/// };
+///
+/// assert_eq!(
+///     full_moon::print_pretty(&ast, &config),
+///     "call(\n    first,\n    { 1, 2 }, -- comment\n    last\n)\n",
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let ast = full_moon::parse("local function f(x,y) -- adds\nreturn x+y end")?;
+/// assert_eq!(
+///     full_moon::print_pretty(&ast, &full_moon::PrettyConfig::default()),
+///     "local function f(x, y) -- adds\n    return x + y\nend\n",
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn print_pretty(ast: &Ast, config: &PrettyConfig) -> String {
+    let mut printer = Printer::new(ast, config, false);
+    printer.block(&ast.nodes);
+    printer.finish()
+}
+
+/// Prints back Lua code from an [`Ast`](ast/struct.Ast.html) as small as possible, without comments
+/// and only with the whitespace needed to keep tokens apart, such as in `local x`.
+///
+/// A semicolon is added before any statement that starts with `(` and follows an expression without one,
+/// since it would otherwise continue the expression as a function call.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let ast = full_moon::parse("local x = 1 -- one\nlocal y = x .. 2\nprint(y)")?;
+/// assert_eq!(full_moon::print_minified(&ast), "local x=1 local y=x.. 2 print(y)");
+/// # Ok(())
+/// # }
+/// ```
+pub fn print_minified(ast: &Ast) -> String {
+    let config = PrettyConfig::default();
+    let mut printer = Printer::new(ast, &config, true);
+    printer.block(&ast.nodes);
+    printer.finish()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pending {
+    Nothing,
+    Space,
+    Newline,
+}
+
+// The state of a printer before printing something, to go back to if it has to be printed differently
+struct Snapshot {
+    length: usize,
+    pending: Pending,
+    cursor: Option<usize>,
+    saw_newline: bool,
+    line_empty: bool,
+    previous_number: bool,
+}
+
+struct Printer<'a, 'b> {
+    config: &'b PrettyConfig,
+    indent: String,
+    arena: &'b Arc<Arena<Token<'a>>>,
+    output: String,
+    depth: usize,
+    pending: Pending,
+    // The offset in the arena of the last token that was printed, or whose comments were printed
+    cursor: Option<usize>,
+    // Whether there was a newline in the code since the last printed token or comment
+    saw_newline: bool,
+    line_empty: bool,
+    previous_number: bool,
+    // Whether statements are being put on the same line, in a collapsed block
+    collapsed: bool,
+    // Whether to only print the whitespace that is needed, and no comments
+    minify: bool,
+    line_ending: &'static str,
+    // The depth of the list being put on one line to see if it fits, if there is one.
+    // The lists inside of it aren't wrapped.
+    measuring: Option<usize>,
+    // Whether a comment in the list being measured needs a line break after it or before it
+    measured_line_comment: bool,
+}
+
+impl<'a, 'b> Printer<'a, 'b> {
+    fn new(ast: &'b Ast<'a>, config: &'b PrettyConfig, minify: bool) -> Self {
+        Self {
+            config,
+            indent: config.indent.whitespace(),
+            arena: &ast.tokens,
+            output: String::new(),
+            depth: 0,
+            pending: Pending::Nothing,
+            cursor: None,
+            saw_newline: false,
+            line_empty: true,
+            previous_number: false,
+            collapsed: false,
+            minify,
+            line_ending: config
+                .line_ending
+                .as_str()
+                .unwrap_or_else(|| LineEnding::detect(ast)),
+            measuring: None,
+            measured_line_comment: false,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        let start = self.cursor.map_or(0, |cursor| cursor + 1);
+        let remaining: Vec<_> = self
+            .arena
+            .iter()
+            .filter(|(index, _)| index.into_raw_parts().0 >= start)
+            .map(|(_, token)| token)
+            .collect();
+
+        for token in remaining {
+            self.trivia(token);
+        }
+
+        if !self.output.is_empty() && !self.minify {
+            self.output.push_str(self.line_ending);
+        }
+
+        self.output
+    }
+
+    fn space(&mut self) {
+        if self.pending == Pending::Nothing && !self.minify {
+            self.pending = Pending::Space;
+        }
+    }
+
+    fn line(&mut self) {
+        if !self.minify {
+            self.pending = Pending::Newline;
+        }
+    }
+
+    fn flush(&mut self, next: &str) {
+        match self.pending {
+            Pending::Newline => {
+                if !self.output.is_empty() {
+                    self.output.push_str(self.line_ending);
+                }
+
+                for _ in 0..self.depth {
+                    self.output.push_str(&self.indent);
+                }
+
+                self.line_empty = true;
+            }
+
+            Pending::Space => self.output.push(' '),
+
+            Pending::Nothing => {
+                if needs_separator(&self.output, self.previous_number, next) {
+                    self.output.push(' ');
+                }
+            }
+        }
+
+        self.pending = Pending::Nothing;
+    }
+
+    fn token(&mut self, token: &TokenReference<'a>) {
+        self.comments_before(token);
+        self.text(
+            &token.to_string(),
+            matches!(&*token.token_type(), TokenType::Number { .. }),
+        );
+    }
+
+    fn text(&mut self, text: &str, number: bool) {
+        self.flush(text);
+        self.output.push_str(text);
+
+        self.line_empty = false;
+        self.saw_newline = false;
+        self.previous_number = number;
+    }
+
+    fn comments_before(&mut self, token: &TokenReference<'a>) {
+        let offset = match token.arena_offset() {
+            Some((arena, offset)) if Arc::ptr_eq(arena, self.arena) => offset,
+            _ => return,
+        };
+
+        let start = self.cursor.map_or(0, |cursor| cursor + 1);
+        for index in start..offset {
+            if let Some((token, _)) = self.arena.get_unknown_gen(index) {
+                self.trivia(token);
+            }
+        }
+
+        self.cursor = Some(self.cursor.map_or(offset, |cursor| cursor.max(offset)));
+    }
+
+    fn trivia(&mut self, token: &Token<'a>) {
+        if self.minify {
+            return;
+        }
+
+        match &*token.token_type() {
+            TokenType::Whitespace { characters } if characters.contains('\n') => {
+                self.saw_newline = true;
+            }
+
+            TokenType::SingleLineComment { .. } => {
+                self.comment(token);
+                self.line();
+            }
+
+            TokenType::MultiLineComment { .. } => {
+                self.comment(token);
+                if self.pending != Pending::Newline {
+                    self.space();
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    fn comment(&mut self, comment: &Token<'a>) {
+        let own_line = self.saw_newline || self.line_empty;
+        if self.measuring == Some(self.depth)
+            && (own_line || comment.token_kind() == TokenKind::SingleLineComment)
+        {
+            self.measured_line_comment = true;
+        }
+
+        if own_line {
+            // Comments on their own line stay on their own line
+            if !self.line_empty {
+                self.line();
+            }
+
+            if self.pending == Pending::Newline {
+                self.flush("");
+            }
+        } else {
+            // Comments at the end of a line stay there, even if the line is about to end
+            self.output.push(' ');
+        }
+
+        // Single line comments end before `\n`, so code with `\r\n` line endings leaves a `\r` at their end
+        self.output
+            .push_str(comment.to_string().trim_end_matches('\r'));
+        self.line_empty = false;
+        self.saw_newline = false;
+
+        if self.pending != Pending::Newline {
+            self.pending = Pending::Nothing;
+        }
+    }
+
+    fn operator(&mut self, token: &TokenReference<'a>) {
+        let keyword = matches!(
+            &*token.token_type(),
+            TokenType::Symbol {
+                symbol: Symbol::And | Symbol::Or | Symbol::Not,
+            }
+        );
+
+        if keyword || self.config.spaces_around_operators {
+            self.space();
+            self.token(token);
+            self.space();
+        } else {
+            self.token(token);
+        }
+    }
+
+    fn comma(&mut self, token: &TokenReference<'a>) {
+        self.token(token);
+        if self.config.space_after_commas {
+            self.space();
+        }
+    }
+
+    fn punctuated<T>(
+        &mut self,
+        punctuated: &Punctuated<'a, T>,
+        mut print: impl FnMut(&mut Self, &T),
+    ) {
+        for pair in punctuated.pairs() {
+            print(self, pair.value());
+            if let Some(punctuation) = pair.punctuation() {
+                self.comma(punctuation);
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            length: self.output.len(),
+            pending: self.pending,
+            cursor: self.cursor,
+            saw_newline: self.saw_newline,
+            line_empty: self.line_empty,
+            previous_number: self.previous_number,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.output.truncate(snapshot.length);
+        self.pending = snapshot.pending;
+        self.cursor = snapshot.cursor;
+        self.saw_newline = snapshot.saw_newline;
+        self.line_empty = snapshot.line_empty;
+        self.previous_number = snapshot.previous_number;
+    }
+
+    // Whether the first and last lines of what was printed since the snapshot fit in the max width,
+    // including the code before it on its first line
+    fn fits(&self, snapshot: &Snapshot) -> bool {
+        let line_start = self.output[..snapshot.length]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+
+        let printed = &self.output[line_start..];
+        let first_line = printed.split('\n').next().unwrap_or_default();
+        let last_line = printed.rsplit('\n').next().unwrap_or_default();
+
+        first_line.trim_end_matches('\r').chars().count() <= self.config.max_width
+            && last_line.chars().count() <= self.config.max_width
+    }
+
+    // Whether there is a single line comment between a token and the next token that isn't trivia
+    fn line_comment_after(&self, token: &TokenReference<'a>) -> bool {
+        let offset = match token.arena_offset() {
+            Some((arena, offset)) if Arc::ptr_eq(arena, self.arena) => offset,
+            _ => return false,
+        };
+
+        (offset + 1..)
+            .map_while(|index| self.arena.get_unknown_gen(index))
+            .map(|(token, _)| token.token_kind())
+            .take_while(|kind| {
+                matches!(
+                    kind,
+                    TokenKind::Whitespace
+                        | TokenKind::SingleLineComment
+                        | TokenKind::MultiLineComment
+                )
+            })
+            .any(|kind| kind == TokenKind::SingleLineComment)
+    }
+
+    // Items separated by commas between two brackets, all on one line if it fits and there are no comments
+    // that need their own lines in between, or otherwise with every item on its own line
+    fn list<T: Copy>(
+        &mut self,
+        span: &ContainedSpan<'a>,
+        padded: bool,
+        items: &[(T, Option<&TokenReference<'a>>)],
+        print: impl Fn(&mut Self, T),
+    ) {
+        let (start, end) = span.tokens();
+
+        if !self.minify && self.measuring.is_none() && !items.is_empty() {
+            let snapshot = self.snapshot();
+            self.measuring = Some(self.depth);
+            self.list_on_one_line(span, padded, items, &print);
+            self.measuring = None;
+
+            let fits = !mem::take(&mut self.measured_line_comment) && self.fits(&snapshot);
+            self.restore(snapshot);
+
+            if !fits {
+                self.token(start);
+                self.depth += 1;
+                for (item, separator) in items {
+                    self.line();
+                    print(self, *item);
+                    if let Some(separator) = separator {
+                        self.token(separator);
+                        // Comments after the separator that come before the next item on the same line
+                        // are moved to the next line with it
+                        self.saw_newline = !self.line_comment_after(separator);
+                    }
+                }
+
+                self.comments_before(end);
+                self.depth -= 1;
+                self.line();
+                self.token(end);
+                return;
+            }
+        }
+
+        self.list_on_one_line(span, padded, items, &print);
+    }
+
+    fn list_on_one_line<T: Copy>(
+        &mut self,
+        span: &ContainedSpan<'a>,
+        padded: bool,
+        items: &[(T, Option<&TokenReference<'a>>)],
+        print: &impl Fn(&mut Self, T),
+    ) {
+        let (start, end) = span.tokens();
+        self.token(start);
+        if padded && !items.is_empty() {
+            self.space();
+        }
+
+        for (item, separator) in items {
+            print(self, *item);
+            if let Some(separator) = separator {
+                self.comma(separator);
+            }
+        }
+
+        if padded && !items.is_empty() {
+            self.space();
+        }
+
+        self.token(end);
+    }
+
+    fn contained<T>(
+        &mut self,
+        span: &ContainedSpan<'a>,
+        inner: T,
+        print: impl FnOnce(&mut Self, T),
+    ) {
+        let (start, end) = span.tokens();
+        self.token(start);
+        print(self, inner);
+        self.token(end);
+    }
+
+    fn block(&mut self, block: &Block<'a>) {
+        let mut previous = None;
+        for (stmt, semicolon) in &block.stmts {
+            if (self.minify || self.config.separate_ambiguous_statements)
+                && previous.is_some_and(|previous| ambiguous(previous, stmt))
+            {
+                self.text(";", false);
+            }
+
+            self.statement_start();
+            self.stmt(stmt);
+            if let Some(semicolon) = semicolon {
+                self.token(semicolon);
+            }
+
+            previous = Some(stmt).filter(|_| semicolon.is_none());
+        }
+
+        if let Some((last_stmt, semicolon)) = &block.last_stmt {
+            self.statement_start();
+            self.last_stmt(last_stmt);
+            if let Some(semicolon) = semicolon {
+                self.token(semicolon);
+            }
+        }
+    }
+
+    fn statement_start(&mut self) {
+        if self.collapsed {
+            self.space();
+        } else {
+            self.line();
+        }
+    }
+
+    // A nested block, and the token that ends it, such as `end`
+    fn body(&mut self, block: &Block<'a>, end_token: &TokenReference<'a>) {
+        let statements = block.stmts.len() + block.last_stmt.iter().count();
+        let collapsed =
+            statements == 0 || (statements == 1 && self.config.collapse_single_statement_blocks);
+
+        self.nested(block, end_token, collapsed);
+        if collapsed && self.pending != Pending::Newline {
+            self.space();
+        } else {
+            self.line();
+        }
+
+        self.token(end_token);
+    }
+
+    // An indented block, including the comments before the token after it
+    fn nested(&mut self, block: &Block<'a>, next_token: &TokenReference<'a>, collapsed: bool) {
+        self.depth += 1;
+        let outer = mem::replace(&mut self.collapsed, collapsed);
+        self.block(block);
+        self.comments_before(next_token);
+        self.collapsed = outer;
+        self.depth -= 1;
+    }
+
+    fn stmt(&mut self, stmt: &Stmt<'a>) {
+        match stmt {
+            Stmt::Assignment(assignment) => {
+                self.punctuated(&assignment.var_list, Self::var);
+                self.space();
+                self.token(&assignment.equal_token);
+                self.space();
+                self.punctuated(&assignment.expr_list, Self::expression);
+            }
+
+            Stmt::Do(do_block) => {
+                self.token(&do_block.do_token);
+                self.body(&do_block.block, &do_block.end_token);
+            }
+
+            Stmt::Error(error) => self.error_tokens(&error.tokens),
+            Stmt::FunctionCall(call) => self.function_call(call),
+
+            Stmt::FunctionDeclaration(declaration) => {
+                self.token(&declaration.function_token);
+                self.space();
+                self.punctuated(&declaration.name.names, |printer, name| printer.token(name));
+                if let Some((colon, name)) = &declaration.name.colon_name {
+                    self.token(colon);
+                    self.token(name);
+                }
+                self.function_body(&declaration.body);
+            }
+
+            Stmt::GenericFor(generic_for) => {
+                self.token(&generic_for.for_token);
+                self.space();
+                self.punctuated(&generic_for.names, |printer, name| printer.token(name));
+                self.space();
+                self.token(&generic_for.in_token);
+                self.space();
+                self.punctuated(&generic_for.expr_list, Self::expression);
+                self.space();
+                self.token(&generic_for.do_token);
+                self.body(&generic_for.block, &generic_for.end_token);
+            }
+
+            Stmt::If(if_stmt) => self.if_stmt(if_stmt),
+
+            Stmt::LocalAssignment(local_assignment) => {
+                self.token(&local_assignment.local_token);
+                self.space();
+                self.punctuated(&local_assignment.name_list, |printer, name| {
+                    printer.token(name)
+                });
+                if let Some(equal_token) = &local_assignment.equal_token {
+                    self.space();
+                    self.token(equal_token);
+                    self.space();
+                    self.punctuated(&local_assignment.expr_list, Self::expression);
+                }
+            }
+
+            Stmt::LocalFunction(local_function) => {
+                self.token(&local_function.local_token);
+                self.space();
+                self.token(&local_function.function_token);
+                self.space();
+                self.token(&local_function.name);
+                self.function_body(&local_function.func_body);
+            }
+
+            Stmt::NumericFor(numeric_for) => {
+                self.token(&numeric_for.for_token);
+                self.space();
+                self.token(&numeric_for.index_variable);
+                self.space();
+                self.token(&numeric_for.equal_token);
+                self.space();
+                self.expression(&numeric_for.start);
+                self.comma(&numeric_for.start_end_comma);
+                self.expression(&numeric_for.end);
+                if let Some(comma) = &numeric_for.end_step_comma {
+                    self.comma(comma);
+                }
+                if let Some(step) = &numeric_for.step {
+                    self.expression(step);
+                }
+                self.space();
+                self.token(&numeric_for.do_token);
+                self.body(&numeric_for.block, &numeric_for.end_token);
+            }
+
+            Stmt::Repeat(repeat) => {
+                self.token(&repeat.repeat_token);
+                self.body(&repeat.block, &repeat.until_token);
+                self.space();
+                self.expression(&repeat.until);
+            }
+
+            Stmt::While(while_loop) => {
+                self.token(&while_loop.while_token);
+                self.space();
+                self.expression(&while_loop.condition);
+                self.space();
+                self.token(&while_loop.do_token);
+                self.body(&while_loop.block, &while_loop.end_token);
+            }
+        }
+    }
+
+    fn if_stmt(&mut self, if_stmt: &If<'a>) {
+        self.token(&if_stmt.if_token);
+        self.space();
+        self.expression(&if_stmt.condition);
+        self.space();
+        self.token(&if_stmt.then_token);
+
+        let branches = if_stmt.else_if.iter().flatten().count() + if_stmt.r#else.iter().count();
+        if branches == 0 {
+            return self.body(&if_stmt.block, &if_stmt.end_token);
+        }
+
+        // Blocks of if statements with branches are never collapsed
+        let else_ifs = if_stmt.else_if.iter().flatten();
+        let mut next_tokens = else_ifs
+            .clone()
+            .map(|else_if| &else_if.else_if_token)
+            .chain(if_stmt.else_token.iter())
+            .chain(Some(&if_stmt.end_token));
+
+        self.nested(&if_stmt.block, next_tokens.next().unwrap(), false);
+        for else_if in else_ifs {
+            self.line();
+            self.token(&else_if.else_if_token);
+            self.space();
+            self.expression(&else_if.condition);
+            self.space();
+            self.token(&else_if.then_token);
+            self.nested(&else_if.block, next_tokens.next().unwrap(), false);
+        }
+
+        if let (Some(else_token), Some(else_block)) = (&if_stmt.else_token, &if_stmt.r#else) {
+            self.line();
+            self.token(else_token);
+            self.nested(else_block, next_tokens.next().unwrap(), false);
+        }
+
+        self.line();
+        self.token(&if_stmt.end_token);
+    }
+
+    fn last_stmt(&mut self, last_stmt: &LastStmt<'a>) {
+        match last_stmt {
+            LastStmt::Break(token) => self.token(token),
+            LastStmt::Return(return_stmt) => {
+                self.token(&return_stmt.token);
+                if !return_stmt.returns.is_empty() {
+                    self.space();
+                    self.punctuated(&return_stmt.returns, Self::expression);
+                }
+            }
+        }
+    }
+
+    fn function_body(&mut self, body: &FunctionBody<'a>) {
+        // The comma before `...` is stored with it, after the rest of the parameters
+        let mut parameters: Vec<(&TokenReference<'a>, Option<&TokenReference<'a>>)> = Vec::new();
+        for pair in body.parameters.pairs() {
+            match (pair.value(), pair.punctuation()) {
+                (Parameter::Ellipse(ellipse), Some(comma)) => {
+                    if let Some((_, separator)) = parameters.last_mut() {
+                        *separator = Some(comma);
+                    }
+                    parameters.push((ellipse, None));
+                }
+
+                (Parameter::Ellipse(token), comma) | (Parameter::Name(token), comma) => {
+                    parameters.push((token, comma));
+                }
+            }
+        }
+
+        self.list(
+            &body.parameters_parantheses,
+            false,
+            &parameters,
+            |printer, parameter| printer.token(parameter),
+        );
+        self.body(&body.block, &body.end_token);
+    }
+
+    fn function_call(&mut self, call: &FunctionCall<'a>) {
+        self.prefix(&call.prefix);
+        for suffix in &call.suffixes {
+            self.suffix(suffix);
+        }
+    }
+
+    fn prefix(&mut self, prefix: &Prefix<'a>) {
+        match prefix {
+            Prefix::Expression(expression) => self.expression(expression),
+            Prefix::Name(name) => self.token(name),
+        }
+    }
+
+    fn suffix(&mut self, suffix: &Suffix<'a>) {
+        match suffix {
+            Suffix::Call(Call::AnonymousCall(args)) => self.function_args(args),
+            Suffix::Call(Call::MethodCall(method_call)) => {
+                self.token(&method_call.colon_token);
+                self.token(&method_call.name);
+                self.function_args(&method_call.args);
+            }
+
+            Suffix::Index(Index::Brackets {
+                brackets,
+                expression,
+                ..
+            }) => self.contained(brackets, expression, Self::expression),
+
+            Suffix::Index(Index::Dot { dot, name, .. }) => {
+                self.token(dot);
+                self.token(name);
+            }
+        }
+    }
+
+    fn function_args(&mut self, args: &FunctionArgs<'a>) {
+        match args {
+            FunctionArgs::Parentheses {
+                arguments,
+                parentheses,
+                ..
+            } => {
+                let arguments: Vec<_> = arguments
+                    .pairs()
+                    .map(|pair| (pair.value(), pair.punctuation()))
+                    .collect();
+                self.list(parentheses, false, &arguments, Self::expression);
+            }
+
+            FunctionArgs::String(string) => {
+                self.space();
+                self.token(string);
+            }
+
+            FunctionArgs::TableConstructor(table) => {
+                self.space();
+                self.table_constructor(table);
+            }
+        }
+    }
+
+    fn table_constructor(&mut self, table: &TableConstructor<'a>) {
+        let fields: Vec<_> = table
+            .fields
+            .iter()
+            .map(|(field, separator)| (field, separator.as_ref()))
+            .collect();
+        self.list(&table.braces, true, &fields, Self::field);
+    }
+
+    fn field(&mut self, field: &Field<'a>) {
+        match field {
+            Field::ExpressionKey {
+                brackets,
+                key,
+                equal,
+                value,
+                ..
+            } => {
+                self.contained(brackets, key, Self::expression);
+                self.space();
+                self.token(equal);
+                self.space();
+                self.expression(value);
+            }
+
+            Field::NameKey {
+                key, equal, value, ..
+            } => {
+                self.token(key);
+                self.space();
+                self.token(equal);
+                self.space();
+                self.expression(value);
+            }
+
+            Field::NoKey(value) => self.expression(value),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression<'a>) {
+        match expression {
+            Expression::Parentheses {
+                contained,
+                expression,
+                ..
+            } => self.contained(contained, &**expression, Self::expression),
+
+            Expression::UnaryOperator {
+                unop, expression, ..
+            } => {
+                let token = unop.token();
+                self.token(token);
+                if let UnOp::Not(_) = unop {
+                    self.space();
+                }
+                self.expression(expression);
+            }
+
+            Expression::Value { value, binop, .. } => {
+                self.value(value);
+                if let Some(binop) = binop {
+                    self.operator(binop.bin_op.token());
+                    self.expression(&binop.rhs);
+                }
+            }
+
+            Expression::Error(error) => self.error_tokens(&error.tokens),
+        }
+    }
+
+    // Code that couldn't be parsed is printed a token at a time, since there's no way to know how it should look
+    fn error_tokens(&mut self, tokens: &[TokenReference<'a>]) {
+        for (index, token) in tokens.iter().enumerate() {
+            if index > 0 {
+                self.space();
+            }
+
+            self.token(token);
+        }
+    }
+
+    fn value(&mut self, value: &Value<'a>) {
+        match value {
+            Value::Function((function_token, body)) => {
+                self.token(function_token);
+                self.function_body(body);
+            }
+
+            Value::FunctionCall(call) => self.function_call(call),
+            Value::TableConstructor(table) => self.table_constructor(table),
+            Value::ParseExpression(expression) => self.expression(expression),
+            Value::Number(token) | Value::String(token) | Value::Symbol(token) => self.token(token),
+            Value::Var(var) => self.var(var),
+        }
+    }
+
+    fn var(&mut self, var: &Var<'a>) {
+        match var {
+            Var::Expression(var_expression) => {
+                self.prefix(&var_expression.prefix);
+                for suffix in &var_expression.suffixes {
+                    self.suffix(suffix);
+                }
+            }
+
+            Var::Name(name) => self.token(name),
+        }
+    }
+}
+
+// Whether `stmt` would continue the expression at the end of `previous` as a call if there is no semicolon
+// between them, no matter the whitespace
+pub(crate) fn ambiguous(previous: &Stmt, stmt: &Stmt) -> bool {
+    ends_with_prefix_expression(previous) && starts_with_parentheses(stmt)
+}
+
+// Whether the statement ends with an expression that can be called, rather than a keyword such as `end`
+// or a value such as a number
+fn ends_with_prefix_expression(stmt: &Stmt) -> bool {
+    let expression = match stmt {
+        Stmt::FunctionCall(_) => return true,
+        Stmt::Assignment(assignment) => assignment.expr_list.iter().last(),
+        Stmt::LocalAssignment(local) => local.expr_list.iter().last(),
+        Stmt::Repeat(repeat) => Some(&repeat.until),
+        _ => None,
+    };
+
+    let mut expression = match expression {
+        Some(expression) => expression,
+        None => return false,
+    };
+
+    loop {
+        match expression {
+            Expression::Parentheses { .. } => return true,
+            Expression::UnaryOperator {
+                expression: inner, ..
+            } => expression = inner,
+            Expression::Value {
+                binop: Some(binop), ..
+            } => expression = binop.rhs(),
+            Expression::Value {
+                value, binop: None, ..
+            } => {
+                return matches!(
+                    **value,
+                    Value::FunctionCall(_) | Value::ParseExpression(_) | Value::Var(_)
+                )
+            }
+            Expression::Error(_) => return false,
+        }
+    }
+}
+
+// Whether the statement starts with `(`, such as `(f or g)()`
+fn starts_with_parentheses(stmt: &Stmt) -> bool {
+    let prefix = match stmt {
+        Stmt::FunctionCall(call) => &call.prefix,
+        Stmt::Assignment(assignment) => match assignment.var_list.iter().next() {
+            Some(Var::Expression(var_expression)) => &var_expression.prefix,
+            _ => return false,
+        },
+        _ => return false,
+    };
+
+    matches!(prefix, Prefix::Expression(_))
+}
+
+// Whether a space is needed between two tokens so that they don't become different tokens when printed together,
+// such as `local x` becoming `localx`, or `- -x` becoming a comment. A number takes every letter, digit, and `.`
+// after it, so `1.` followed by `local` needs one too, even though `.` doesn't end a word.
+pub(crate) fn needs_separator(previous: &str, previous_number: bool, next: &str) -> bool {
+    let (previous_char, next_char) = match (previous.chars().last(), next.chars().next()) {
+        (Some(previous_char), Some(next_char)) => (previous_char, next_char),
+        _ => return false,
+    };
+
+    let word = |character: char| character.is_alphanumeric() || character == '_';
+
+    (word(previous_char) && word(next_char))
+        || (previous_char == '-' && next_char == '-')
+        || (previous_char == '.' && (next_char == '.' || next_char.is_ascii_digit()))
+        || (previous_number && (word(next_char) || next_char == '.'))
+        || (previous_char == '[' && (next_char == '[' || next_char == '='))
+        || (next_char == '=' && "=<>~".contains(previous_char))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_minify_adds_semicolon_before_parentheses() {
+        let mut ast = parse("x = y; (f)(); (t).z = 1; do end (g)()").unwrap();
+        for (_, semicolon) in &mut ast.nodes_mut().stmts {
+            *semicolon = None;
+        }
+
+        assert_eq!(print_minified(&ast), "x=y;(f)();(t).z=1 do end(g)()");
+    }
+}
@@ -16,9 +16,13 @@
 //! ```
 use crate::{
     node::Node,
+    prelude::*,
     private::Sealed,
     tokenizer::{Position, TokenReference},
-    visitors::{Visit, VisitMut, Visitor, VisitorMut},
+    visitors::{
+        AnyNode, AnyNodeMut, TryVisit, TryVisitor, Visit, VisitMut, VisitNodes, VisitNodesMut,
+        Visitor, VisitorMut,
+    },
 };
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -29,7 +33,7 @@ use serde::{Deserialize, Serialize};
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Punctuated<'a, T> {
     #[cfg_attr(feature = "serde", serde(borrow))]
-    pairs: Vec<Pair<'a, T>>,
+    pub(crate) pairs: Vec<Pair<'a, T>>,
 }
 
 impl<'a, T> Punctuated<'a, T> {
@@ -186,7 +190,25 @@ impl<'a, T: VisitMut<'a>> VisitMut<'a> for Punctuated<'a, T> {
     }
 }
 
-impl<'a, T> std::iter::Extend<Pair<'a, T>> for Punctuated<'a, T> {
+impl<'a, T: TryVisit<'a>> TryVisit<'a> for Punctuated<'a, T> {
+    fn try_visit<V: TryVisitor<'a>>(&self, visitor: &mut V) -> Result<(), V::Error> {
+        self.pairs.try_visit(visitor)
+    }
+}
+
+impl<'a, T: VisitNodes<'a>> VisitNodes<'a> for Punctuated<'a, T> {
+    fn visit_nodes<'b>(&'b self, nodes: &mut Vec<AnyNode<'a, 'b>>) {
+        self.pairs.visit_nodes(nodes);
+    }
+}
+
+impl<'a, T: VisitNodesMut<'a>> VisitNodesMut<'a> for Punctuated<'a, T> {
+    fn visit_nodes_mut<'b>(&'b mut self, nodes: &mut Vec<AnyNodeMut<'a, 'b>>) {
+        self.pairs.visit_nodes_mut(nodes);
+    }
+}
+
+impl<'a, T> core::iter::Extend<Pair<'a, T>> for Punctuated<'a, T> {
     fn extend<I: IntoIterator<Item = Pair<'a, T>>>(&mut self, iter: I) {
         self.pairs.extend(iter);
     }
@@ -228,7 +250,7 @@ impl<'a: 'b, 'b, T> IntoIterator for &'b mut Punctuated<'a, T> {
 /// An iterator over owned values of type `T`.
 /// Refer to the [module documentation](index.html) for more details.
 pub struct IntoIter<'a, T> {
-    inner: std::vec::IntoIter<Pair<'a, T>>,
+    inner: alloc::vec::IntoIter<Pair<'a, T>>,
 }
 
 impl<'a, T> Iterator for IntoIter<'a, T> {
@@ -242,7 +264,7 @@ impl<'a, T> Iterator for IntoIter<'a, T> {
 /// An iterator over borrowed values of type `&T`.
 /// Refer to the [module documentation](index.html) for more details.
 pub struct Iter<'a, 'b, T> {
-    inner: std::slice::Iter<'b, Pair<'a, T>>,
+    inner: core::slice::Iter<'b, Pair<'a, T>>,
 }
 
 impl<'a, 'b, T> Iterator for Iter<'a, 'b, T> {
@@ -256,7 +278,7 @@ impl<'a, 'b, T> Iterator for Iter<'a, 'b, T> {
 /// An iterator over borrowed values of type `&mut T`.
 /// Refer to the [module documentation](index.html) for more details.
 pub struct IterMut<'a, 'b, T> {
-    inner: std::slice::IterMut<'b, Pair<'a, T>>,
+    inner: core::slice::IterMut<'b, Pair<'a, T>>,
 }
 
 impl<'a, 'b, T> Iterator for IterMut<'a, 'b, T> {
@@ -400,3 +422,39 @@ impl<'a, T: VisitMut<'a>> VisitMut<'a> for Pair<'a, T> {
         }
     }
 }
+
+impl<'a, T: TryVisit<'a>> TryVisit<'a> for Pair<'a, T> {
+    fn try_visit<V: TryVisitor<'a>>(&self, visitor: &mut V) -> Result<(), V::Error> {
+        match self {
+            Pair::End(value) => value.try_visit(visitor),
+            Pair::Punctuated(value, punctuation) => {
+                value.try_visit(visitor)?;
+                punctuation.try_visit(visitor)
+            }
+        }
+    }
+}
+
+impl<'a, T: VisitNodes<'a>> VisitNodes<'a> for Pair<'a, T> {
+    fn visit_nodes<'b>(&'b self, nodes: &mut Vec<AnyNode<'a, 'b>>) {
+        match self {
+            Pair::End(value) => value.visit_nodes(nodes),
+            Pair::Punctuated(value, punctuation) => {
+                value.visit_nodes(nodes);
+                punctuation.visit_nodes(nodes);
+            }
+        }
+    }
+}
+
+impl<'a, T: VisitNodesMut<'a>> VisitNodesMut<'a> for Pair<'a, T> {
+    fn visit_nodes_mut<'b>(&'b mut self, nodes: &mut Vec<AnyNodeMut<'a, 'b>>) {
+        match self {
+            Pair::End(value) => value.visit_nodes_mut(nodes),
+            Pair::Punctuated(value, punctuation) => {
+                value.visit_nodes_mut(nodes);
+                punctuation.visit_nodes_mut(nodes);
+            }
+        }
+    }
+}
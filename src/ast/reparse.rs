@@ -0,0 +1,620 @@
+//! Parsing the code of an [`Ast`](../struct.Ast.html) again after it's edited, with [`Ast::reparse`](../struct.Ast.html#method.reparse),
+//! which only tokenizes and parses the statements the edit is in.
+use super::{owned::Owned, span::ContainedSpan, Ast, Block, ParseConfig, Stmt};
+use crate::{
+    node::Node,
+    prelude::*,
+    tokenizer::{self, Position, Symbol, Token, TokenReference, TokenType, TokenTypeCell},
+    visitors::{VisitMut, VisitorMut},
+    Error,
+};
+use alloc::sync::Arc;
+use core::{fmt::Write, iter::FromIterator, ops::Range};
+use generational_arena::{Arena, Index};
+
+/// A change to the code of an Ast, replacing a range of it with new text, for [`Ast::reparse`](../struct.Ast.html#method.reparse)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceEdit {
+    range: Range<usize>,
+    text: String,
+}
+
+impl SourceEdit {
+    /// An edit that replaces the bytes of the code in the range with the text.
+    /// For insertions, the start and end of the range are the same, and for deletions, the text is empty.
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    /// The bytes of the code to replace
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// The text to replace the range with
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// What [`Ast::reparse`](../struct.Ast.html#method.reparse) parsed again, for knowing what to update,
+/// such as the results of linting the statements that changed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReparseInfo {
+    range: (Position, Position),
+    full: bool,
+}
+
+impl ReparseInfo {
+    /// The start and end of the code that was tokenized and parsed again, in the edited code.
+    /// Everything outside of it is the same as before, other than positions after it, which have moved with the edit.
+    /// The whole code when it was all parsed again.
+    pub fn range(&self) -> (Position, Position) {
+        self.range
+    }
+
+    /// Whether all of the code was parsed again, since the edit couldn't be parsed on its own,
+    /// such as when it adds an `end` that closes a block earlier
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+}
+
+impl<'a> Ast<'a> {
+    /// Edits the code of the Ast, then parses it again, which parses only the statements the edit is in when it can,
+    /// so editing a large file doesn't parse all of it again. The Ast is the same as parsing all of the edited code,
+    /// with [`parse`](../fn.parse.html) and the default [`ParseConfig`](struct.ParseConfig.html), would give.
+    ///
+    /// The statements are the ones in the innermost block the edit is in, such as of a function or an `if`,
+    /// along with the comments and whitespace around them. Edits that can't be parsed there on their own, such as
+    /// ones that change where a block ends, or tokens that would join with the ones around them, are parsed along with
+    /// all of the rest of the code instead. The code is what [`print`](../fn.print.html) gives, and tokens that were
+    /// changed without [`update_positions`](#method.update_positions) being called after also make all of it be parsed.
+    /// Asts parsed without [trivia](struct.ParseConfig.html#structfield.preserve_trivia) are always parsed again
+    /// all at once, still without trivia, from the code printing them gives.
+    ///
+    /// # Errors
+    /// The same as `parse` when the edited code can't be parsed, which leaves the Ast as it was.
+    ///
+    /// # Panics
+    /// Panics if the range of the edit isn't in the code, or doesn't start and end between characters.
+    ///
+    /// ```rust
+    /// use full_moon::ast::reparse::SourceEdit;
+    ///
+    /// let mut ast = full_moon::parse("local x = 1\nfunction f()\n\treturn x\nend\n").unwrap();
+    /// let info = ast.reparse(SourceEdit::new(33..34, "y + 1")).unwrap();
+    /// assert_eq!(full_moon::print(&ast), "local x = 1\nfunction f()\n\treturn y + 1\nend\n");
+    /// assert!(!info.is_full());
+    /// assert_eq!(info.range().0.line(), 2);
+    ///
+    /// assert!(ast.reparse(SourceEdit::new(10..11, "")).is_err());
+    /// assert_eq!(full_moon::print(&ast), "local x = 1\nfunction f()\n\treturn y + 1\nend\n");
+    /// ```
+    pub fn reparse(&mut self, edit: SourceEdit) -> Result<ReparseInfo, Error<'static>> {
+        // Asts without trivia have nothing between their tokens, so their code is only what printing them gives
+        let code = if self.has_trivia() {
+            self.contiguous_code()
+        } else {
+            None
+        };
+        let old_code = code.clone().unwrap_or_else(|| crate::print(self));
+        let mut new_code = old_code.clone();
+        new_code.replace_range(edit.range(), edit.text());
+
+        if let Some(old_code) = code {
+            if let Some(info) = self.reparse_part(&old_code, &new_code, &edit) {
+                return Ok(info);
+            }
+        }
+
+        self.reparse_all(&new_code)
+    }
+
+    // The code of the Ast if every token starts where the one before it in the arena ends, so the positions can be
+    // used to find the tokens an edit is in
+    fn contiguous_code(&self) -> Option<String> {
+        let mut code = String::new();
+        for (_, token) in self.tokens.iter() {
+            let start = code.len();
+            write!(code, "{}", token).expect("writing to a string never fails");
+            if token.start_position().bytes() != start || token.end_position().bytes() != code.len()
+            {
+                return None;
+            }
+        }
+
+        Some(code)
+    }
+
+    fn reparse_all(&mut self, code: &str) -> Result<ReparseInfo, Error<'static>> {
+        let tokens = tokenizer::tokens(code).map_err(Error::TokenizerError)?;
+        let config = ParseConfig {
+            preserve_trivia: self.has_trivia(),
+            ..ParseConfig::default()
+        };
+
+        let ast = Ast::from_tokens_with(tokens.iter().map(Token::detached).collect(), &config)
+            .map_err(|error| Error::AstError(error).owned())?;
+        *self = Ast {
+            source: self.source.take(),
+            ..ast
+        };
+
+        let start = Position {
+            bytes: 0,
+            character: 1,
+            line: 1,
+        };
+        let end = self
+            .tokens
+            .iter()
+            .next_back()
+            .map_or(start, |(_, token)| token.end_position());
+
+        Ok(ReparseInfo {
+            range: (start, end),
+            full: true,
+        })
+    }
+
+    // Parses only the statements the edit is in, which gives None without changing the Ast if they can't be parsed on
+    // their own, which is never because the code is invalid, since that's found by parsing all of it
+    fn reparse_part(
+        &mut self,
+        old_code: &str,
+        new_code: &str,
+        edit: &SourceEdit,
+    ) -> Option<ReparseInfo> {
+        let edit = edit.range();
+        let growth = new_code.len() as isize - old_code.len() as isize;
+        let target = Target::find(&self.nodes, old_code.len(), &edit)?;
+
+        let tokens: Vec<&Token<'a>> = self.tokens.iter().map(|(_, token)| token).collect();
+        let starts: Vec<usize> = tokens
+            .iter()
+            .map(|token| Token::start_position(token).bytes())
+            .collect();
+        let ends: Vec<usize> = tokens
+            .iter()
+            .map(|token| Token::end_position(token).bytes())
+            .collect();
+
+        // The region is the statements and the edit, widened to whole tokens and the comments and whitespace around
+        // them, which no nodes have, so it starts and ends next to tokens that aren't changed
+        let mut start = target.region.start;
+        let mut end = target.region.end;
+
+        let containing = starts
+            .partition_point(|bytes| *bytes <= start)
+            .checked_sub(1);
+        if let Some(slot) = containing.filter(|slot| ends[*slot] > start) {
+            start = starts[slot];
+        }
+
+        let mut first = starts.partition_point(|bytes| *bytes < start);
+        while first > 0 && tokens[first - 1].token_type().ignore() {
+            first -= 1;
+        }
+
+        let containing = starts.partition_point(|bytes| *bytes < end).checked_sub(1);
+        if let Some(slot) = containing.filter(|slot| ends[*slot] > end) {
+            end = ends[slot];
+        }
+
+        let mut after = starts.partition_point(|bytes| *bytes < end);
+        while after < tokens.len() && tokens[after].token_type().ignore() {
+            after += 1;
+        }
+
+        let start = starts[first];
+        let end = starts[after];
+        let new_end = (end as isize + growth) as usize;
+
+        // The tokens before and after the region are tokenized with it, to check that they don't join with it,
+        // such as `a` and `b` becoming `ab`, or a `-` before a `-` becoming a comment
+        let before = first.checked_sub(1).map(|slot| tokens[slot]);
+        let next = tokens[after];
+        let next = if *next.token_type() == TokenType::Eof {
+            None
+        } else {
+            Some(next)
+        };
+
+        let window_start = before.map_or(start, |token| token.start_position().bytes());
+        let window_end = next.map_or(new_code.len(), |token| {
+            (token.end_position().bytes() as isize + growth) as usize
+        });
+        let window = tokenizer::tokens(&new_code[window_start..window_end]).ok()?;
+
+        let mut lexed = Vec::new();
+        let mut surrounding = Vec::new();
+        for token in &window {
+            if *token.token_type() == TokenType::Eof {
+                continue;
+            }
+
+            let token_start = window_start + token.start_position().bytes();
+            let token_end = window_start + token.end_position().bytes();
+            if token_end <= start || token_start >= new_end {
+                surrounding.push(token);
+            } else if token_start >= start && token_end <= new_end {
+                lexed.push(token.detached());
+            } else {
+                return None;
+            }
+        }
+
+        let unchanged: Vec<&Token<'a>> = before.into_iter().chain(next).collect();
+        if surrounding.len() != unchanged.len()
+            || surrounding
+                .iter()
+                .zip(&unchanged)
+                .any(|(lexed, token)| *lexed.token_type() != *token.token_type())
+        {
+            return None;
+        }
+
+        // A statement starting with `(` could be a call of the one before it, as in `f\n(g)()`
+        let starts_with_paren = |token: Option<&Token>| match token {
+            Some(token) => {
+                *token.token_type()
+                    == TokenType::Symbol {
+                        symbol: Symbol::LeftParen,
+                    }
+            }
+            None => false,
+        };
+
+        if starts_with_paren(next)
+            || (target.items.start > 0
+                && starts_with_paren(lexed.iter().find(|token| !token.token_type().ignore())))
+        {
+            return None;
+        }
+
+        // Blocks count towards the recursion limit, so the ones the statements are in are taken off of it
+        let mut config = ParseConfig::default();
+        config.recursion_limit = config.recursion_limit.checked_sub(target.path.len())?;
+
+        let region_len = lexed.len();
+        let mut region_tokens = lexed;
+        region_tokens.push(Token::new(
+            TokenType::Eof,
+            Position {
+                bytes: new_end - start,
+                character: 1,
+                line: 1,
+            },
+        ));
+
+        let region = Ast::from_tokens_with(region_tokens, &config).ok()?;
+
+        let block = target.block(&self.nodes);
+        let items = block.stmts.len() + block.last_stmt.iter().len();
+        let old_last_stmt = block.last_stmt.is_some();
+
+        // A `return` or `break` has to be the last statement of its block
+        if region.nodes.last_stmt.is_some() && target.items.end < items {
+            return None;
+        }
+
+        if old_last_stmt
+            && target.items.start > block.stmts.len()
+            && (!region.nodes.stmts.is_empty() || region.nodes.last_stmt.is_some())
+        {
+            return None;
+        }
+
+        // Nothing is changed until here, since the statements can be parsed on their own.
+        // The tokens after the region are copied, since they move, and clones of the Ast still have them.
+        let arena = Arc::new(Arena::from_iter(
+            tokens[..first]
+                .iter()
+                .map(|token| (*token).clone())
+                .chain(
+                    region
+                        .tokens
+                        .iter()
+                        .map(|(_, token)| token.clone())
+                        .take(region_len),
+                )
+                .chain(tokens[after..].iter().map(|token| Token {
+                    token_type: Arc::new(TokenTypeCell::new(
+                        token.token_type().clone(),
+                        token.token_type.id,
+                    )),
+                    ..Token::new(TokenType::Eof, Token::start_position(token))
+                })),
+        ));
+
+        let old_arena = Arc::clone(&self.tokens);
+        let block = target.block_mut(&mut self.nodes);
+        let Block {
+            stmts, last_stmt, ..
+        } = region.nodes.into_inner();
+        let replaces_last_stmt = old_last_stmt
+            && target.items.start <= block.stmts.len()
+            && target.items.end > block.stmts.len();
+        let stmts_end = target.items.end.min(block.stmts.len());
+        let stmts_start = target.items.start.min(stmts_end);
+        block.stmts.splice(stmts_start..stmts_end, stmts);
+        if replaces_last_stmt || last_stmt.is_some() {
+            block.last_stmt = last_stmt;
+        }
+
+        let mut relinker = Relinker {
+            indexes: arena.iter().map(|(index, _)| index).collect(),
+            arena: Arc::clone(&arena),
+            old_arena,
+            region_arena: region.tokens,
+            first,
+            after,
+            region_len,
+            unlinked: false,
+        };
+
+        self.nodes.visit_mut(&mut relinker);
+        self.tokens = arena;
+        self.update_positions_from(first);
+
+        if relinker.unlinked {
+            // Only the tokens of the statements that were replaced are in the region, so this shouldn't happen,
+            // but if it does, parsing all of it is still correct
+            return self.reparse_all(new_code).ok();
+        }
+
+        let region_start = self.tokens[relinker.indexes[first]].start_position();
+        let region_end = match region_len {
+            0 => region_start,
+            _ => self.tokens[relinker.indexes[first + region_len - 1]].end_position(),
+        };
+
+        Some(ReparseInfo {
+            range: (region_start, region_end),
+            full: false,
+        })
+    }
+}
+
+// The block with the statements an edit is in, which is the innermost block the edit is in
+struct Target {
+    // The statement and which of its blocks to go into from the block of the whole code to get to the block
+    path: Vec<(usize, usize)>,
+    // The statements of the block the edit touches, counting the last statement after the others
+    items: Range<usize>,
+    // The bytes of those statements and the edit, in the code before the edit
+    region: Range<usize>,
+}
+
+impl Target {
+    fn find(nodes: &Block, code_len: usize, edit: &Range<usize>) -> Option<Self> {
+        let mut block = nodes;
+        let mut bounds = 0..code_len;
+        let mut path = Vec::new();
+
+        loop {
+            let spans = item_spans(block)?;
+            let touching: Vec<usize> = (0..spans.len())
+                .filter(|item| spans[*item].start <= edit.end && edit.start <= spans[*item].end)
+                .collect();
+
+            // An edit in one statement that's only in one of its blocks is in that block instead
+            if let [item] = touching[..] {
+                if let Some((stmt, _)) = block.stmts.get(item) {
+                    let child =
+                        child_blocks(stmt)
+                            .into_iter()
+                            .enumerate()
+                            .find(|(_, (bounds, _))| {
+                                bounds.start <= edit.start && edit.end <= bounds.end
+                            });
+
+                    if let Some((number, (child_bounds, child))) = child {
+                        path.push((item, number));
+                        block = child;
+                        bounds = child_bounds;
+                        continue;
+                    }
+                }
+            }
+
+            let (items, region) = match (touching.first(), touching.last()) {
+                (Some(first), Some(last)) => (
+                    *first..*last + 1,
+                    spans[*first].start.min(edit.start)..spans[*last].end.max(edit.end),
+                ),
+
+                _ => {
+                    let item = spans.iter().filter(|span| span.end <= edit.start).count();
+                    (item..item, edit.clone())
+                }
+            };
+
+            if region.start < bounds.start || region.end > bounds.end {
+                return None;
+            }
+
+            return Some(Target {
+                path,
+                items,
+                region,
+            });
+        }
+    }
+
+    fn block<'b, 'a>(&self, nodes: &'b Block<'a>) -> &'b Block<'a> {
+        self.path.iter().fold(nodes, |block, (item, number)| {
+            child_blocks(&block.stmts[*item].0)
+                .into_iter()
+                .nth(*number)
+                .expect("the path goes to a block")
+                .1
+        })
+    }
+
+    fn block_mut<'b, 'a>(&self, nodes: &'b mut Block<'a>) -> &'b mut Block<'a> {
+        self.path.iter().fold(nodes, |block, (item, number)| {
+            child_blocks_mut(&mut block.stmts[*item].0)
+                .into_iter()
+                .nth(*number)
+                .expect("the path goes to a block")
+        })
+    }
+}
+
+fn bytes(token: &TokenReference) -> Range<usize> {
+    Token::start_position(token).bytes()..Token::end_position(token).bytes()
+}
+
+// The bytes of the statement, along with its semicolon if it has one
+fn item_span(node: &impl Node, semicolon: &Option<TokenReference>) -> Option<Range<usize>> {
+    let (start, end) = node.range()?;
+    let end = semicolon
+        .as_ref()
+        .map_or(end.bytes(), |semicolon| bytes(semicolon).end);
+    Some(start.bytes()..end)
+}
+
+// The bytes of each statement of the block, then of its last statement if it has one,
+// or None if a statement has no tokens to find where it is by
+fn item_spans(block: &Block) -> Option<Vec<Range<usize>>> {
+    let stmts = block
+        .stmts
+        .iter()
+        .map(|(stmt, semicolon)| item_span(stmt, semicolon));
+    let last_stmt = block
+        .last_stmt
+        .iter()
+        .map(|(last_stmt, semicolon)| item_span(last_stmt, semicolon));
+    stmts.chain(last_stmt).collect()
+}
+
+// The blocks of statements in the statement, with the bytes between the tokens around each one,
+// such as from the end of a `do` to the start of its `end`
+fn child_blocks<'b, 'a>(stmt: &'b Stmt<'a>) -> Vec<(Range<usize>, &'b Block<'a>)> {
+    let between =
+        |open: &TokenReference, close: &TokenReference| bytes(open).end..bytes(close).start;
+
+    match stmt {
+        Stmt::Do(node) => vec![(between(&node.do_token, &node.end_token), &node.block)],
+        Stmt::While(node) => vec![(between(&node.do_token, &node.end_token), &node.block)],
+        Stmt::Repeat(node) => vec![(between(&node.repeat_token, &node.until_token), &node.block)],
+        Stmt::NumericFor(node) => vec![(between(&node.do_token, &node.end_token), &node.block)],
+        Stmt::GenericFor(node) => vec![(between(&node.do_token, &node.end_token), &node.block)],
+        Stmt::FunctionDeclaration(node) => vec![(
+            between(
+                node.body.parameters_parantheses.tokens().1,
+                &node.body.end_token,
+            ),
+            &node.body.block,
+        )],
+        Stmt::LocalFunction(node) => vec![(
+            between(
+                node.func_body.parameters_parantheses.tokens().1,
+                &node.func_body.end_token,
+            ),
+            &node.func_body.block,
+        )],
+
+        // Each block of an `if` goes up to the token that starts the next one
+        Stmt::If(node) => {
+            let mut blocks = Vec::new();
+            let (mut open, mut block) = (&node.then_token, &node.block);
+            for else_if in node.else_if.iter().flatten() {
+                blocks.push((between(open, &else_if.else_if_token), block));
+                open = &else_if.then_token;
+                block = &else_if.block;
+            }
+
+            if let (Some(else_token), Some(else_block)) = (&node.else_token, &node.r#else) {
+                blocks.push((between(open, else_token), block));
+                open = else_token;
+                block = else_block;
+            }
+
+            blocks.push((between(open, &node.end_token), block));
+            blocks
+        }
+
+        _ => Vec::new(),
+    }
+}
+
+// The same blocks as `child_blocks`, in the same order
+fn child_blocks_mut<'b, 'a>(stmt: &'b mut Stmt<'a>) -> Vec<&'b mut Block<'a>> {
+    match stmt {
+        Stmt::Do(node) => vec![&mut node.block],
+        Stmt::While(node) => vec![&mut node.block],
+        Stmt::Repeat(node) => vec![&mut node.block],
+        Stmt::NumericFor(node) => vec![&mut node.block],
+        Stmt::GenericFor(node) => vec![&mut node.block],
+        Stmt::FunctionDeclaration(node) => vec![&mut node.body.block],
+        Stmt::LocalFunction(node) => vec![&mut node.func_body.block],
+        Stmt::If(node) => core::iter::once(&mut node.block)
+            .chain(
+                node.else_if
+                    .iter_mut()
+                    .flatten()
+                    .map(|else_if| &mut else_if.block),
+            )
+            .chain(node.r#else.as_mut())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Points the tokens of the nodes at the same tokens in the new arena, both the ones that are still there from the old
+// arena and the ones that were parsed again
+struct Relinker<'a> {
+    arena: Arc<Arena<Token<'a>>>,
+    indexes: Vec<Index>,
+    old_arena: Arc<Arena<Token<'a>>>,
+    region_arena: Arc<Arena<Token<'a>>>,
+    // Where the region is in the old arena, from its first token up to the token after it
+    first: usize,
+    after: usize,
+    region_len: usize,
+    unlinked: bool,
+}
+
+impl<'a> Relinker<'a> {
+    fn relink(&mut self, token: &mut TokenReference<'a>) {
+        let slot = match token.arena_offset() {
+            Some((arena, offset)) if Arc::ptr_eq(arena, &self.old_arena) => {
+                if offset < self.first {
+                    offset
+                } else if offset >= self.after {
+                    offset - self.after + self.first + self.region_len
+                } else {
+                    self.unlinked = true;
+                    return;
+                }
+            }
+
+            Some((arena, offset)) if Arc::ptr_eq(arena, &self.region_arena) => self.first + offset,
+            _ => return,
+        };
+
+        *token = TokenReference::Borrowed {
+            arena: Arc::clone(&self.arena),
+            index: self.indexes[slot],
+        };
+    }
+}
+
+impl<'a> VisitorMut<'a> for Relinker<'a> {
+    fn visit_token(&mut self, token: &mut TokenReference<'a>) {
+        self.relink(token);
+    }
+
+    // The tokens of spans, such as parentheses, aren't visited on their own
+    fn visit_contained_span(&mut self, span: &mut ContainedSpan<'a>) {
+        let (start, end) = span.tokens_mut();
+        self.relink(start);
+        self.relink(end);
+    }
+}
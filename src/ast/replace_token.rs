@@ -0,0 +1,218 @@
+//! Changing the text of one token of an [`Ast`](../struct.Ast.html) in place,
+//! with [`Ast::replace_token_text`](../struct.Ast.html#method.replace_token_text).
+use super::{owned::Owned, Ast, AstError, ExpectedItem};
+use crate::{
+    node::NodeId,
+    prelude::*,
+    tokenizer::{self, Position, Token, TokenType},
+    Error,
+};
+use alloc::borrow::Cow;
+
+impl<'a> Ast<'a> {
+    /// Changes the text of the token with the id, such as the name of a variable at one place it's used or `0.5`
+    /// to `0.25`, without changing any nodes. The id can be given as a
+    /// [`TokenReference`](../tokenizer/enum.TokenReference.html) of the Ast, or an id from
+    /// [`Node::id`](../node/trait.Node.html#method.id) for one. The positions of the tokens after it are moved
+    /// to where they are with the new text, including their lines if it has more or fewer new lines than the old text,
+    /// so they stay right without calling [`update_positions`](#method.update_positions).
+    ///
+    /// # Errors
+    /// If the text can't be tokenized, its TokenizerError is returned.
+    /// If it isn't one token of the same kind as the one it replaces, such as a name for a name or a string for a string,
+    /// an AstError::UnexpectedToken is returned with the first token that isn't, at its position in the text.
+    /// Symbols can only be replaced by the same symbol, since the nodes they're in are made for the symbols they have.
+    /// Only the text is checked, so text that would be read differently next to the tokens around it, such as a
+    /// multi-line comment replaced by a single line comment with code after it on the same line, isn't caught.
+    /// If the id is of a node of the Ast that isn't a token, an AstError::NotAToken is returned,
+    /// and if it isn't of anything in the Ast, such as a token of another Ast, an AstError::TokenNotInAst is.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut ast = full_moon::parse("local x = [[one]] print(x)")?;
+    /// let string = ast.iter_tokens().find(|token| token.to_string() == "[[one]]").unwrap().clone();
+    /// let print = ast.iter_tokens().find(|token| token.to_string() == "print").unwrap().clone();
+    ///
+    /// ast.replace_token_text(&string, "[[one\ntwo]]")?;
+    /// assert_eq!(full_moon::print(&ast), "local x = [[one\ntwo]] print(x)");
+    /// assert_eq!(print.start_position().line(), 2);
+    /// assert_eq!(print.start_position().character(), 7);
+    ///
+    /// assert!(ast.replace_token_text(&string, "x").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replace_token_text(
+        &mut self,
+        token: impl Into<NodeId>,
+        new_text: &str,
+    ) -> Result<(), Error<'static>> {
+        let id = token.into();
+        let token = self
+            .tokens
+            .iter()
+            .map(|(_, token)| token)
+            .chain(self.comments.iter().flatten())
+            .find(|token| token.token_type.id == id)
+            .cloned()
+            .ok_or_else(|| {
+                Error::AstError(match self.node_by_id(id) {
+                    Some(_) => AstError::NotAToken,
+                    None => AstError::TokenNotInAst,
+                })
+            })?;
+
+        let new_type = single_token(&token, new_text)?;
+        let old_text = token.to_string();
+        let (start, end) = (token.start_position(), token.end_position());
+        let shift = PositionShift::new(start, end, &old_text, new_text);
+
+        *token.token_type.borrow_mut() = new_type;
+        token.end_position.store(shift.new_end);
+
+        for other in self
+            .tokens
+            .iter()
+            .map(|(_, token)| token)
+            .chain(self.comments.iter().flatten())
+        {
+            // Only the bytes are needed to know the token is before this one, rather than its line and character
+            if other.start_bytes() < end.bytes() || other.token_type.id == id {
+                continue;
+            }
+
+            other
+                .start_position
+                .store(shift.shift(other.start_position()));
+            other.end_position.store(shift.shift(other.end_position()));
+        }
+
+        Ok(())
+    }
+}
+
+// The type of the token the text is, if it's one token that can replace the token
+fn single_token<'a, 'b>(
+    token: &Token<'a>,
+    new_text: &'b str,
+) -> Result<TokenType<'a>, Error<'static>> {
+    let tokens = tokenizer::tokens(new_text).map_err(Error::TokenizerError)?;
+    let error = |found: &Token<'b>, additional: Option<&'static str>, expected| {
+        Error::AstError(AstError::UnexpectedToken {
+            token: found.detached(),
+            additional: additional.map(Cow::Borrowed),
+            opening: None,
+            expected,
+        })
+    };
+
+    let new_token = &tokens[0];
+    if new_token.token_kind() != token.token_kind()
+        || matches!(&*token.token_type(), TokenType::Symbol { .. })
+            && *new_token.token_type() != *token.token_type()
+    {
+        let (additional, expected) = match &*token.token_type() {
+            TokenType::Eof => (None, vec![ExpectedItem::Eof]),
+            TokenType::Identifier { .. } => (None, vec![ExpectedItem::Identifier]),
+            TokenType::MultiLineComment { .. } => (Some("expected a multi-line comment"), vec![]),
+            TokenType::Number { .. } => (None, vec![ExpectedItem::Number]),
+            TokenType::SingleLineComment { .. } => (Some("expected a comment"), vec![]),
+            TokenType::StringLiteral { .. } => (None, vec![ExpectedItem::String]),
+            TokenType::Symbol { symbol } => (None, vec![ExpectedItem::Symbol(*symbol)]),
+            TokenType::Whitespace { .. } => (Some("expected whitespace"), vec![]),
+        };
+
+        return Err(error(new_token, additional, expected));
+    }
+
+    match tokens.get(1) {
+        Some(leftover) if leftover.token_kind() != tokenizer::TokenKind::Eof => {
+            Err(error(leftover, Some("leftover token"), vec![]))
+        }
+        _ => Ok(new_token.token_type().owned()),
+    }
+}
+
+// How the positions after a token move when its text is replaced. Lines and characters are worked out as they
+// would be counted with the new text, where a position right after a new line is at the new line, not the line after
+struct PositionShift {
+    old_end: Position,
+    new_end: Position,
+    // The line and character the old and new text end at, counting new lines the moment they're passed
+    old_line: usize,
+    old_character: usize,
+    new_line: usize,
+    new_character: usize,
+}
+
+impl PositionShift {
+    fn new(start: Position, old_end: Position, old_text: &str, new_text: &str) -> Self {
+        let (old_line, old_character) = if old_text.ends_with('\n') {
+            (old_end.line + 1, 1)
+        } else {
+            (old_end.line, old_end.character)
+        };
+
+        // Where the token really starts, which is the line before if it's at the start of one
+        let old_lines = bytecount::count(old_text.as_bytes(), b'\n');
+        let start_line = old_line - old_lines;
+        let start_character = if old_lines == 0 {
+            old_character - old_text.chars().count()
+        } else if start.line < start_line {
+            1
+        } else {
+            start.character
+        };
+
+        let (new_line, new_character) = advance(start_line, start_character, new_text);
+        let (line, character) = match new_text.strip_suffix('\n') {
+            Some(before_new_line) => advance(start_line, start_character, before_new_line),
+            None => (new_line, new_character),
+        };
+
+        Self {
+            old_end,
+            new_end: Position {
+                bytes: start.bytes + new_text.len(),
+                character,
+                line,
+            },
+            old_line,
+            old_character,
+            new_line,
+            new_character,
+        }
+    }
+
+    // The position after the token at the old position
+    fn shift(&self, position: Position) -> Position {
+        if position.bytes == self.old_end.bytes {
+            return self.new_end;
+        }
+
+        Position {
+            bytes: position.bytes + self.new_end.bytes - self.old_end.bytes,
+            // Only the characters on the same line as the end of the token move
+            character: if position.line == self.old_line {
+                position.character + self.new_character - self.old_character
+            } else {
+                position.character
+            },
+            line: position.line + self.new_line - self.old_line,
+        }
+    }
+}
+
+// The line and character after the text, starting at the line and character
+fn advance(mut line: usize, mut character: usize, text: &str) -> (usize, usize) {
+    for rest in text.chars() {
+        if rest == '\n' {
+            line += 1;
+            character = 1;
+        } else {
+            character += 1;
+        }
+    }
+
+    (line, character)
+}
@@ -0,0 +1,783 @@
+//! A simplified version of the [`Ast`](../struct.Ast.html) for interpreters and analysis that don't care how the code
+//! is written, only what it does. There are no tokens, comments, or whitespace, names are `String`s, literals are
+//! their values, and binary operations are nested by the precedence of their operators, so `1 + 2 * 3` is an addition
+//! of `1` and a multiplication. Every node has the range of the code it came from, for pointing back at it.
+//!
+//! The simplified nodes own everything in them, so they can outlive the code, but they can't be turned back into it.
+//!
+//! ```rust
+//! use full_moon::ast::simplified::{SBinOp, SBlock, SExprKind, SStmtKind};
+//!
+//! let ast = full_moon::parse("local x = 1 + 2 * 3 -- comment").unwrap();
+//! let block = SBlock::from(&ast);
+//!
+//! match &block.stmts[0].kind {
+//!     SStmtKind::Local { names, values } => {
+//!         assert_eq!(names, &["x"]);
+//!         match &values[0].kind {
+//!             SExprKind::Binary { operator, left, .. } => {
+//!                 assert_eq!(*operator, SBinOp::Plus);
+//!                 assert_eq!(left.kind, SExprKind::Number(1.0));
+//!             }
+//!             other => panic!("expected an addition, got {:?}", other),
+//!         }
+//!     }
+//!     other => panic!("expected a local assignment, got {:?}", other),
+//! }
+//! ```
+use super::*;
+use crate::prelude::*;
+use crate::tokenizer::{unescape, Position};
+
+/// The range of the code a simplified node came from, the same as [`Node::range`](../../node/trait.Node.html#method.range)
+/// of the node it was made from. None if the node has no tokens, such as an empty block.
+pub type SRange = Option<(Position, Position)>;
+
+/// A block of statements, made from a [`Block`](../struct.Block.html) or the [`Ast`](../struct.Ast.html)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SBlock {
+    /// The statements of the block, in order, including its `return` or `break` at the end if it has one
+    pub stmts: Vec<SStmt>,
+    /// The range of the block
+    pub range: SRange,
+}
+
+/// A statement, made from a [`Stmt`](../enum.Stmt.html) or [`LastStmt`](../enum.LastStmt.html)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SStmt {
+    /// What the statement is
+    pub kind: SStmtKind,
+    /// The range of the statement
+    pub range: SRange,
+}
+
+/// The kinds of [`SStmt`](struct.SStmt.html)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum SStmtKind {
+    /// An assignment, such as `x, t.y = 1, 2`. The targets are [names](enum.SExprKind.html#variant.Name)
+    /// and [indexes](enum.SExprKind.html#variant.Index).
+    Assignment {
+        /// The `x, t.y` part of `x, t.y = 1, 2`
+        targets: Vec<SExpr>,
+        /// The `1, 2` part of `x, t.y = 1, 2`
+        values: Vec<SExpr>,
+    },
+    /// A `break` statement
+    Break,
+    /// A function call on its own, such as `call()`, which is always a [call](enum.SExprKind.html#variant.Call)
+    /// or [method call](enum.SExprKind.html#variant.MethodCall)
+    Call(SExpr),
+    /// A do block, `do end`
+    Do(SBlock),
+    /// Code that couldn't be parsed, only made by [`parse_fallible`](../../fn.parse_fallible.html)
+    Error,
+    /// A function declaration, such as `function t.x:y() end`
+    FunctionDeclaration {
+        /// The `t.x` part of `function t.x:y() end`
+        names: Vec<String>,
+        /// The `y` part of `function t.x:y() end`, if it is a method.
+        /// The `self` parameter of methods isn't in the parameters of the function.
+        method: Option<String>,
+        /// The function itself
+        function: SFunction,
+    },
+    /// A generic for loop, such as `for index, value in pairs(list) do end`
+    GenericFor {
+        /// The `index, value` part of `for index, value in pairs(list) do end`
+        names: Vec<String>,
+        /// The `pairs(list)` part of `for index, value in pairs(list) do end`
+        values: Vec<SExpr>,
+        /// The code inside the loop
+        body: SBlock,
+    },
+    /// An if statement, such as `if x then elseif y then else end`
+    If {
+        /// The condition and block of the `if` and every `elseif`, in order
+        branches: Vec<(SExpr, SBlock)>,
+        /// The block of the `else`, if there is one
+        else_block: Option<SBlock>,
+    },
+    /// A local assignment, such as `local x, y = 1, 2`
+    Local {
+        /// The `x, y` part of `local x, y = 1, 2`
+        names: Vec<String>,
+        /// The `1, 2` part of `local x, y = 1, 2`, which is empty for `local x`
+        values: Vec<SExpr>,
+    },
+    /// A local function declaration, such as `local function x() end`
+    LocalFunction {
+        /// The `x` part of `local function x() end`
+        name: String,
+        /// The function itself
+        function: SFunction,
+    },
+    /// A numeric for loop, such as `for index = 1, 10, 2 do end`
+    NumericFor {
+        /// The `index` part of `for index = 1, 10, 2 do end`
+        name: String,
+        /// The `1` part of `for index = 1, 10, 2 do end`
+        start: SExpr,
+        /// The `10` part of `for index = 1, 10, 2 do end`
+        end: SExpr,
+        /// The `2` part of `for index = 1, 10, 2 do end`, if there is one
+        step: Option<SExpr>,
+        /// The code inside the loop
+        body: SBlock,
+    },
+    /// A repeat loop, such as `repeat until x`
+    Repeat {
+        /// The code inside the loop
+        body: SBlock,
+        /// The `x` part of `repeat until x`
+        until: SExpr,
+    },
+    /// A `return` statement, with the values being returned
+    Return(Vec<SExpr>),
+    /// A while loop, such as `while x do end`
+    While {
+        /// The `x` part of `while x do end`
+        condition: SExpr,
+        /// The code inside the loop
+        body: SBlock,
+    },
+}
+
+/// An expression, made from an [`Expression`](../enum.Expression.html)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SExpr {
+    /// What the expression is
+    pub kind: SExprKind,
+    /// The range of the expression
+    pub range: SRange,
+}
+
+/// The kinds of [`SExpr`](struct.SExpr.html)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum SExprKind {
+    /// A binary operation, such as `x + 1`
+    Binary {
+        /// The `+` part of `x + 1`
+        operator: SBinOp,
+        /// The `x` part of `x + 1`
+        left: Box<SExpr>,
+        /// The `1` part of `x + 1`
+        right: Box<SExpr>,
+    },
+    /// `true` or `false`
+    Boolean(bool),
+    /// A call of a function, such as `call(1, 2)`, `call "x"`, or `call { 1 }`
+    Call {
+        /// The `call` part of `call(1, 2)`
+        function: Box<SExpr>,
+        /// The `1, 2` part of `call(1, 2)`, which is the string or table for calls without parentheses
+        arguments: Vec<SExpr>,
+    },
+    /// Code that couldn't be parsed, only made by [`parse_fallible`](../../fn.parse_fallible.html),
+    /// or a literal that doesn't have a value, such as a number with invalid digits
+    Error,
+    /// An anonymous function, such as `function(x) end`
+    Function(SFunction),
+    /// Indexing, such as `t.x` or `t["x"]`, which are both indexed by the string `x`
+    Index {
+        /// The `t` part of `t.x`
+        object: Box<SExpr>,
+        /// The `x` part of `t.x`, or the `"x"` part of `t["x"]`
+        key: Box<SExpr>,
+    },
+    /// A method call, such as `object:method(1, 2)`
+    MethodCall {
+        /// The `object` part of `object:method(1, 2)`
+        object: Box<SExpr>,
+        /// The `method` part of `object:method(1, 2)`
+        method: String,
+        /// The `1, 2` part of `object:method(1, 2)`
+        arguments: Vec<SExpr>,
+    },
+    /// A name, such as `x`
+    Name(String),
+    /// `nil`
+    Nil,
+    /// The value of a number, such as `1`, `0.5`, or `0xFF`
+    Number(f64),
+    /// An expression in parentheses, such as `(call())`, which only has the first value of calls and `...`
+    Parentheses(Box<SExpr>),
+    /// The bytes of a string after reading its escapes as Lua 5.1 does, such as `a\n` for `"a\n"` and `[[a\n]]`.
+    /// They are bytes since Lua strings don't have to be UTF-8, such as `"\255"`.
+    String(Vec<u8>),
+    /// A table constructor, such as `{ 1, x = 2, [3] = 4 }`
+    Table(Vec<SField>),
+    /// A unary operation, such as `-x`
+    Unary {
+        /// The `-` part of `-x`
+        operator: SUnOp,
+        /// The `x` part of `-x`
+        operand: Box<SExpr>,
+    },
+    /// The `...` of a function that takes any number of arguments
+    Vararg,
+}
+
+/// A function, made from a [`FunctionBody`](../struct.FunctionBody.html)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SFunction {
+    /// The names of the parameters, such as `a, b` in `function(a, b, ...) end`
+    pub parameters: Vec<String>,
+    /// Whether the function takes any number of arguments after the parameters with `...`
+    pub variadic: bool,
+    /// The code of the function
+    pub body: SBlock,
+    /// The range of the function body, from its parameters to its `end`
+    pub range: SRange,
+}
+
+/// A field of a [table](enum.SExprKind.html#variant.Table)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum SField {
+    /// A field with a key, such as `x = 1` or `["x"] = 1`, which both have the key of the string `x`
+    Keyed {
+        /// The `x` part of `x = 1`
+        key: SExpr,
+        /// The `1` part of `x = 1`
+        value: SExpr,
+    },
+    /// A field with no key, such as `1` in `{ 1 }`, which is at the next index of the table
+    Positional(SExpr),
+}
+
+macro_rules! simplified_op {
+    ($enum:ident, $concrete:ident, $(#[$outer:meta])* { $($operator:ident,)+ }) => {
+        $(#[$outer])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+        pub enum $enum {
+            $(
+                #[allow(missing_docs)]
+                $operator,
+            )+
+        }
+
+        impl From<&$concrete<'_>> for $enum {
+            fn from(operator: &$concrete) -> Self {
+                match operator {
+                    $(
+                        $concrete::$operator(_) => $enum::$operator,
+                    )+
+                }
+            }
+        }
+    };
+}
+
+simplified_op!(SBinOp, BinOp,
+    #[doc = "An operator of a [binary operation](enum.SExprKind.html#variant.Binary), the same as a [`BinOp`](../enum.BinOp.html)"]
+    {
+        And,
+        Caret,
+        GreaterThan,
+        GreaterThanEqual,
+        LessThan,
+        LessThanEqual,
+        Minus,
+        Or,
+        Percent,
+        Plus,
+        Slash,
+        Star,
+        TildeEqual,
+        TwoDots,
+        TwoEqual,
+    }
+);
+
+simplified_op!(SUnOp, UnOp,
+    #[doc = "An operator of a [unary operation](enum.SExprKind.html#variant.Unary), the same as a [`UnOp`](../enum.UnOp.html)"]
+    {
+        Minus,
+        Not,
+        Hash,
+    }
+);
+
+// How tightly unary operators hold their operand, which is tighter than every binary operator other than `^`
+pub(crate) const UNARY_PRIORITY: u8 = 8;
+
+impl SBinOp {
+    // How tightly the operator holds the operand on its left and on its right, the same as Lua 5.1.
+    // Operators that hold the right more loosely, `..` and `^`, are right associative.
+    pub(crate) fn priority(self) -> (u8, u8) {
+        match self {
+            SBinOp::Or => (1, 1),
+            SBinOp::And => (2, 2),
+            SBinOp::GreaterThan
+            | SBinOp::GreaterThanEqual
+            | SBinOp::LessThan
+            | SBinOp::LessThanEqual
+            | SBinOp::TildeEqual
+            | SBinOp::TwoEqual => (3, 3),
+            SBinOp::TwoDots => (5, 4),
+            SBinOp::Minus | SBinOp::Plus => (6, 6),
+            SBinOp::Percent | SBinOp::Slash | SBinOp::Star => (7, 7),
+            SBinOp::Caret => (10, 9),
+        }
+    }
+}
+
+impl SExpr {
+    fn new(kind: SExprKind, range: SRange) -> Self {
+        Self { kind, range }
+    }
+
+    fn boxed(kind: SExprKind, range: SRange) -> Box<Self> {
+        Box::new(Self::new(kind, range))
+    }
+}
+
+// The range from the start of the first range to the end of the second, if they both have one
+fn join(start: SRange, end: SRange) -> SRange {
+    Some((start?.0, end?.1))
+}
+
+impl From<&Ast<'_>> for SBlock {
+    fn from(ast: &Ast) -> Self {
+        ast.nodes().into()
+    }
+}
+
+impl From<&Block<'_>> for SBlock {
+    fn from(block: &Block) -> Self {
+        Self {
+            stmts: block
+                .iter_stmts()
+                .map(SStmt::from)
+                .chain(block.last_stmts().map(SStmt::from))
+                .collect(),
+            range: block.range(),
+        }
+    }
+}
+
+impl From<&LastStmt<'_>> for SStmt {
+    fn from(last_stmt: &LastStmt) -> Self {
+        let kind = match last_stmt {
+            LastStmt::Break(_) => SStmtKind::Break,
+            LastStmt::Return(r#return) => SStmtKind::Return(expressions(r#return.returns())),
+        };
+
+        Self {
+            kind,
+            range: last_stmt.range(),
+        }
+    }
+}
+
+impl From<&Stmt<'_>> for SStmt {
+    fn from(stmt: &Stmt) -> Self {
+        let kind = match stmt {
+            Stmt::Assignment(assignment) => SStmtKind::Assignment {
+                targets: assignment.var_list().iter().map(var).collect(),
+                values: expressions(assignment.expr_list()),
+            },
+
+            Stmt::Do(r#do) => SStmtKind::Do(r#do.block().into()),
+
+            Stmt::Error(_) => SStmtKind::Error,
+
+            Stmt::FunctionCall(call) => {
+                SStmtKind::Call(suffixed(call.prefix(), call.iter_suffixes(), call.range()))
+            }
+
+            Stmt::FunctionDeclaration(declaration) => SStmtKind::FunctionDeclaration {
+                names: declaration.name().names().iter().map(name).collect(),
+                method: declaration.name().method_name().map(name),
+                function: declaration.body().into(),
+            },
+
+            Stmt::GenericFor(generic_for) => SStmtKind::GenericFor {
+                names: generic_for.names().iter().map(name).collect(),
+                values: expressions(generic_for.expr_list()),
+                body: generic_for.block().into(),
+            },
+
+            Stmt::If(r#if) => SStmtKind::If {
+                branches: core::iter::once((r#if.condition().into(), r#if.block().into()))
+                    .chain(
+                        r#if.else_if()
+                            .into_iter()
+                            .flatten()
+                            .map(|else_if| (else_if.condition().into(), else_if.block().into())),
+                    )
+                    .collect(),
+                else_block: r#if.else_block().map(SBlock::from),
+            },
+
+            Stmt::LocalAssignment(local_assignment) => SStmtKind::Local {
+                names: local_assignment.name_list().iter().map(name).collect(),
+                values: expressions(local_assignment.expr_list()),
+            },
+
+            Stmt::LocalFunction(local_function) => SStmtKind::LocalFunction {
+                name: name(local_function.name()),
+                function: local_function.func_body().into(),
+            },
+
+            Stmt::NumericFor(numeric_for) => SStmtKind::NumericFor {
+                name: name(numeric_for.index_variable()),
+                start: numeric_for.start().into(),
+                end: numeric_for.end().into(),
+                step: numeric_for.step().map(SExpr::from),
+                body: numeric_for.block().into(),
+            },
+
+            Stmt::Repeat(repeat) => SStmtKind::Repeat {
+                body: repeat.block().into(),
+                until: repeat.until().into(),
+            },
+
+            Stmt::While(r#while) => SStmtKind::While {
+                condition: r#while.condition().into(),
+                body: r#while.block().into(),
+            },
+        };
+
+        Self {
+            kind,
+            range: stmt.range(),
+        }
+    }
+}
+
+impl From<&FunctionBody<'_>> for SFunction {
+    fn from(body: &FunctionBody) -> Self {
+        let mut parameters = Vec::new();
+        let mut variadic = false;
+        for parameter in body.iter_parameters() {
+            match parameter {
+                Parameter::Ellipse(_) => variadic = true,
+                Parameter::Name(token) => parameters.push(name(token)),
+            }
+        }
+
+        Self {
+            parameters,
+            variadic,
+            body: body.block().into(),
+            range: body.range(),
+        }
+    }
+}
+
+// An operator waiting for the operand after it
+enum Pending {
+    Unary(SUnOp, Option<Position>),
+    Binary(SBinOp),
+}
+
+impl Pending {
+    // How tightly the operator holds the operand on its right
+    fn right_priority(&self) -> u8 {
+        match self {
+            Pending::Unary(..) => UNARY_PRIORITY,
+            Pending::Binary(operator) => operator.priority().1,
+        }
+    }
+
+    // Replaces the operands at the end with the operation on them
+    fn apply(self, operands: &mut Vec<SExpr>) {
+        let right = operands
+            .pop()
+            .expect("every operator has an operand after it");
+
+        let operation = match self {
+            Pending::Unary(operator, start) => {
+                let range = start.and_then(|start| Some((start, right.range?.1)));
+                SExpr::new(
+                    SExprKind::Unary {
+                        operator,
+                        operand: Box::new(right),
+                    },
+                    range,
+                )
+            }
+
+            Pending::Binary(operator) => {
+                let left = operands
+                    .pop()
+                    .expect("every binary operator has an operand before it");
+                let range = join(left.range, right.range);
+                SExpr::new(
+                    SExprKind::Binary {
+                        operator,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    },
+                    range,
+                )
+            }
+        };
+
+        operands.push(operation);
+    }
+}
+
+impl From<&Expression<'_>> for SExpr {
+    // The concrete tree keeps operands and operators in the order they are written, without nesting them
+    // by precedence, so they are nested here the same way Lua 5.1 does, with a stack of the operators
+    // that are waiting for their operands.
+    fn from(expression: &Expression) -> Self {
+        let mut operands = Vec::new();
+        let mut operators = Vec::new();
+        let mut next = expression;
+
+        loop {
+            let binop = match next {
+                Expression::UnaryOperator {
+                    unop, expression, ..
+                } => {
+                    operators.push(Pending::Unary(unop.into(), unop.token().start_position()));
+                    next = expression;
+                    continue;
+                }
+
+                Expression::Value { value, binop, .. } => {
+                    operands.push(self::value(value, value.range()));
+                    match binop {
+                        Some(binop) => binop,
+                        None => break,
+                    }
+                }
+
+                Expression::Parentheses { expression, .. } => {
+                    let inner = SExpr::from(&**expression);
+                    operands.push(SExpr::new(
+                        SExprKind::Parentheses(Box::new(inner)),
+                        next.range(),
+                    ));
+                    break;
+                }
+
+                Expression::Error(error) => {
+                    operands.push(SExpr::new(SExprKind::Error, error.range()));
+                    break;
+                }
+            };
+
+            // The operators before this one that hold their right operand at least as tightly as this one
+            // holds its left operand have all of theirs now
+            let operator = SBinOp::from(binop.bin_op());
+            while let Some(pending) = operators.pop() {
+                if operator.priority().0 > pending.right_priority() {
+                    operators.push(pending);
+                    break;
+                }
+
+                pending.apply(&mut operands);
+            }
+
+            operators.push(Pending::Binary(operator));
+            next = binop.rhs();
+        }
+
+        while let Some(pending) = operators.pop() {
+            pending.apply(&mut operands);
+        }
+
+        operands.pop().expect("every expression has an operand")
+    }
+}
+
+fn value(value: &Value, range: SRange) -> SExpr {
+    let kind = match value {
+        Value::Function((_, body)) => SExprKind::Function(body.into()),
+        Value::FunctionCall(call) => return suffixed(call.prefix(), call.iter_suffixes(), range),
+        Value::TableConstructor(table) => table_constructor(table),
+        Value::Number(token) => {
+            number(&token.to_string()).map_or(SExprKind::Error, SExprKind::Number)
+        }
+        Value::ParseExpression(expression) => return expression.into(),
+        Value::String(token) => string(token).map_or(SExprKind::Error, SExprKind::String),
+        Value::Symbol(token) => match &*token.token_type() {
+            TokenType::Symbol {
+                symbol: Symbol::True,
+            } => SExprKind::Boolean(true),
+            TokenType::Symbol {
+                symbol: Symbol::False,
+            } => SExprKind::Boolean(false),
+            TokenType::Symbol {
+                symbol: Symbol::Ellipse,
+            } => SExprKind::Vararg,
+            _ => SExprKind::Nil,
+        },
+        Value::Var(var) => return self::var(var),
+    };
+
+    SExpr::new(kind, range)
+}
+
+fn var(var: &Var) -> SExpr {
+    match var {
+        Var::Expression(expression) => suffixed(
+            expression.prefix(),
+            expression.iter_suffixes(),
+            expression.range(),
+        ),
+        Var::Name(token) => SExpr::new(SExprKind::Name(name(token)), token.range()),
+    }
+}
+
+// The calls and indexes of the suffixes on the prefix, each one holding the ones before it,
+// so `a.b()` is a call of the index `a.b`
+fn suffixed<'a, 'b>(
+    prefix: &Prefix<'a>,
+    suffixes: impl Iterator<Item = &'b Suffix<'a>>,
+    range: SRange,
+) -> SExpr
+where
+    'a: 'b,
+{
+    let mut expression = match prefix {
+        Prefix::Expression(expression) => expression.into(),
+        Prefix::Name(token) => SExpr::new(SExprKind::Name(name(token)), token.range()),
+    };
+
+    for suffix in suffixes {
+        let object = Box::new(expression);
+        let range = join(object.range, suffix.range());
+        let kind = match suffix {
+            Suffix::Call(Call::AnonymousCall(args)) => SExprKind::Call {
+                function: object,
+                arguments: arguments(args),
+            },
+            Suffix::Call(Call::MethodCall(method_call)) => SExprKind::MethodCall {
+                object,
+                method: name(method_call.name()),
+                arguments: arguments(method_call.args()),
+            },
+            Suffix::Index(Index::Brackets { expression, .. }) => SExprKind::Index {
+                object,
+                key: Box::new(expression.into()),
+            },
+            Suffix::Index(Index::Dot { name: key, .. }) => SExprKind::Index {
+                object,
+                key: SExpr::boxed(SExprKind::String(name(key).into_bytes()), key.range()),
+            },
+        };
+
+        expression = SExpr::new(kind, range);
+    }
+
+    // The range of the whole node is more exact when the prefix has no range, such as an error
+    expression.range = range.or(expression.range);
+    expression
+}
+
+fn arguments(args: &FunctionArgs) -> Vec<SExpr> {
+    match args {
+        FunctionArgs::Parentheses { arguments, .. } => expressions(arguments),
+        FunctionArgs::String(token) => vec![SExpr::new(
+            string(token).map_or(SExprKind::Error, SExprKind::String),
+            token.range(),
+        )],
+        FunctionArgs::TableConstructor(table) => {
+            vec![SExpr::new(table_constructor(table), table.range())]
+        }
+    }
+}
+
+fn table_constructor(table: &TableConstructor) -> SExprKind {
+    SExprKind::Table(
+        table
+            .iter_fields()
+            .map(|(field, _)| match field {
+                Field::ExpressionKey { key, value, .. } => SField::Keyed {
+                    key: key.into(),
+                    value: value.into(),
+                },
+                Field::NameKey { key, value, .. } => SField::Keyed {
+                    key: SExpr::new(SExprKind::String(name(key).into_bytes()), key.range()),
+                    value: value.into(),
+                },
+                Field::NoKey(value) => SField::Positional(value.into()),
+            })
+            .collect(),
+    )
+}
+
+fn expressions(expressions: &Punctuated<Expression>) -> Vec<SExpr> {
+    expressions.iter().map(SExpr::from).collect()
+}
+
+fn name(token: &TokenReference) -> String {
+    token.to_string()
+}
+
+// The value of a number token, or None if it has invalid digits, which only parse_fallible allows
+pub(crate) fn number(text: &str) -> Option<f64> {
+    let digits = |digits: &str, radix: u32| {
+        if digits.is_empty() {
+            return None;
+        }
+
+        digits.chars().try_fold(0.0, |value, digit| {
+            Some(value * f64::from(radix) + f64::from(digit.to_digit(radix)?))
+        })
+    };
+
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        digits(hex, 16)
+    } else if let Some(binary) = text.strip_prefix("0b").filter(|_| cfg!(feature = "roblox")) {
+        digits(binary, 2)
+    } else {
+        text.parse().ok()
+    }
+}
+
+// The bytes of a string token, or None if it has an escape that is too large, which only parse_fallible allows
+pub(crate) fn string(token: &TokenReference) -> Option<Vec<u8>> {
+    match &*token.token_type() {
+        TokenType::StringLiteral {
+            literal,
+            multi_line: Some(_),
+            ..
+        } => Some(long_string_value(literal)),
+        TokenType::StringLiteral { literal, .. } => unescape(literal),
+        _ => None,
+    }
+}
+
+// Lua skips the new line right after the opening long bracket, and reads every other `\r\n`, `\n\r`, or `\r`
+// in a long string as a `\n`
+fn long_string_value(literal: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(literal.len());
+    let mut rest = literal.as_bytes();
+    let mut first = true;
+
+    while let Some((&byte, after)) = rest.split_first() {
+        rest = after;
+        if byte != b'\r' && byte != b'\n' {
+            bytes.push(byte);
+            first = false;
+            continue;
+        }
+
+        // A `\r\n` or `\n\r` is one line break, but `\n\n` is two
+        if let Some((&next, after)) = rest.split_first() {
+            if (next == b'\r' || next == b'\n') && next != byte {
+                rest = after;
+            }
+        }
+
+        if !first {
+            bytes.push(b'\n');
+        }
+        first = false;
+    }
+
+    bytes
+}
@@ -0,0 +1,117 @@
+//! Undoing changes to an [`Ast`](../struct.Ast.html), with [`Ast::snapshot`](../struct.Ast.html#method.snapshot)
+//! and [`Ast::restore`](../struct.Ast.html#method.restore).
+use super::{span::ContainedSpan, Ast, AstBlock};
+use crate::{
+    prelude::*,
+    tokenizer::{Token, TokenReference, TokenState},
+    visitors::{Visit, Visitor},
+};
+use alloc::sync::Arc;
+use generational_arena::Arena;
+
+/// An Ast as it was when [`Ast::snapshot`](../struct.Ast.html#method.snapshot) was called,
+/// for changing it back with [`Ast::restore`](../struct.Ast.html#method.restore).
+///
+/// The snapshot shares the arena of tokens with the Ast rather than copying it, and only keeps the types and
+/// positions of the tokens, which can be changed through the Ast, along with the nodes, whose tokens are shared too.
+/// Text borrowed from the code isn't copied either, so a snapshot takes much less than an
+/// [owned](../owned/trait.Owned.html) copy of the Ast.
+#[derive(Clone, Debug)]
+pub struct AstSnapshot<'a> {
+    nodes: AstBlock<'a>,
+    tokens: Arc<Arena<Token<'a>>>,
+    comments: Option<Vec<Token<'a>>>,
+    states: Vec<TokenState<'a>>,
+}
+
+impl<'a> Ast<'a> {
+    /// Takes a snapshot of the Ast as it is now, to undo the changes made to it after with
+    /// [`restore`](#method.restore). Snapshots are independent of each other, so any of them can be restored
+    /// in any order, such as an older one after others were, and cloned to restore one more than once.
+    ///
+    /// A clone of the Ast can't be used instead, since changing the types of tokens, such as with
+    /// [`set_token_type`](../tokenizer/enum.TokenReference.html#method.set_token_type), and updating their positions
+    /// changes them for its clones too, as they share their tokens.
+    ///
+    /// ```rust
+    /// # use full_moon::{tokenizer::{TokenReference, TokenType}, visitors::VisitorMut};
+    /// struct Rename;
+    ///
+    /// impl<'a> VisitorMut<'a> for Rename {
+    ///     fn visit_identifier(&mut self, token: &mut TokenReference<'a>) {
+    ///         token.set_token_type(TokenType::Identifier { identifier: "y".into() });
+    ///     }
+    /// }
+    ///
+    /// let mut ast = full_moon::parse("local x = 1").unwrap();
+    /// let snapshot = ast.snapshot();
+    ///
+    /// Rename.visit_ast(&mut ast);
+    /// ast.update_positions();
+    /// assert_eq!(full_moon::print(&ast), "local y = 1");
+    ///
+    /// ast.restore(snapshot);
+    /// assert_eq!(full_moon::print(&ast), "local x = 1");
+    /// ```
+    pub fn snapshot(&self) -> AstSnapshot<'a> {
+        let mut states: Vec<_> = self.tokens.iter().map(|(_, token)| token.state()).collect();
+        if let Some(comments) = &self.comments {
+            states.extend(comments.iter().map(Token::state));
+        }
+
+        // Tokens of the nodes that aren't in the arena, such as new ones, can be changed as well
+        let mut foreign = ForeignTokens {
+            arena: &self.tokens,
+            states: &mut states,
+        };
+        self.nodes.visit(&mut foreign);
+
+        AstSnapshot {
+            nodes: self.nodes.clone(),
+            tokens: Arc::clone(&self.tokens),
+            comments: self.comments.clone(),
+            states,
+        }
+    }
+
+    /// Changes the Ast back to how it was when the [snapshot](#method.snapshot) was taken, undoing every change
+    /// made to it since, including to the types and positions of its tokens. The [source](#method.source) of the Ast
+    /// is kept. Restoring a snapshot of another Ast makes this Ast the same as that one was.
+    pub fn restore(&mut self, snapshot: AstSnapshot<'a>) {
+        for state in &snapshot.states {
+            state.restore();
+        }
+
+        self.nodes = snapshot.nodes;
+        self.tokens = snapshot.tokens;
+        self.comments = snapshot.comments;
+    }
+}
+
+// Keeps the states of the tokens of nodes that aren't from the arena
+struct ForeignTokens<'a, 'b> {
+    arena: &'b Arc<Arena<Token<'a>>>,
+    states: &'b mut Vec<TokenState<'a>>,
+}
+
+impl<'a> ForeignTokens<'a, '_> {
+    fn token(&mut self, token: &TokenReference<'a>) {
+        match token.arena_offset() {
+            Some((arena, _)) if Arc::ptr_eq(arena, self.arena) => {}
+            _ => self.states.push(token.state()),
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for ForeignTokens<'a, '_> {
+    fn visit_token(&mut self, token: &TokenReference<'a>) {
+        self.token(token);
+    }
+
+    // The tokens of contained spans aren't visited on their own
+    fn visit_contained_span(&mut self, span: &ContainedSpan<'a>) {
+        let (open, close) = span.tokens();
+        self.token(open);
+        self.token(close);
+    }
+}
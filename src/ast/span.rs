@@ -8,22 +8,28 @@
 //! Contained spans don't contain the inner data, just the start and end bounds.
 //! ```
 use crate::{
-    node::Node,
+    node::{Node, NodeId, StoredId},
+    prelude::*,
     private::Sealed,
     tokenizer::{Position, TokenReference},
 };
 
-use full_moon_derive::{Owned, Visit};
+use full_moon_derive::{Dot, HeapSize, Json, Owned, Visit};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 /// A contained span with the beginning and ending bounds.
 /// Refer to the [module documentation](index.html) for more details.
-#[derive(Clone, Debug, PartialEq, Owned, Visit)]
+#[derive(Clone, Debug, PartialEq, Owned, Visit, Json, Dot, HeapSize)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct ContainedSpan<'a> {
-    #[serde(borrow)]
+    #[cfg_attr(feature = "serde", serde(borrow))]
     #[visit(skip)]
     tokens: (TokenReference<'a>, TokenReference<'a>),
+    #[node(id)]
+    #[visit(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    id: StoredId,
 }
 
 impl<'a> ContainedSpan<'a> {
@@ -31,6 +37,7 @@ impl<'a> ContainedSpan<'a> {
     pub fn new(start: TokenReference<'a>, end: TokenReference<'a>) -> Self {
         Self {
             tokens: (start, end),
+            id: StoredId::new(),
         }
     }
 
@@ -57,6 +64,10 @@ impl<'a> Node for ContainedSpan<'a> {
     fn similar(&self, other: &Self) -> bool {
         self.tokens.0.similar(&other.tokens.0) && self.tokens.1.similar(&other.tokens.1)
     }
+
+    fn id(&self) -> Option<NodeId> {
+        Some(self.id.0)
+    }
 }
 
 impl<'a> Sealed for ContainedSpan<'a> {}
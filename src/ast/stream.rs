@@ -0,0 +1,388 @@
+//! Parsing code one top level statement at a time, with [`parse_stmts`](../../fn.parse_stmts.html),
+//! for code too big to keep every token and node of at once.
+use super::{
+    memory::Reborrow,
+    parser_util::{InternalAstError, ParserState},
+    parsers::{self, TopLevelStmt},
+    AstError, ExpectedItem, LastStmt, ParseConfig, Stmt,
+};
+use crate::{
+    prelude::*,
+    tokenizer::{Lexer, Position, Token, TokenKind, TokenReference, TokenType, TokenizerError},
+    visitors::{VisitMut, VisitorMut},
+    Error,
+};
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    rc::Rc,
+    sync::Arc,
+};
+use core::{cell::Cell, iter::FusedIterator};
+use generational_arena::Arena;
+
+// How many tokens are lexed for a statement at first, which is doubled until the statement is all in them
+const LOOKAHEAD_TOKENS: usize = 64;
+
+/// The top level statements of code, parsed one at a time as they're asked for,
+/// from [`parse_stmts`](../../fn.parse_stmts.html) and the functions like it.
+///
+/// Only the tokens of the statement being parsed, and the few after it that were lexed to know where it ends,
+/// are kept while parsing, so the whole code is never tokenized or parsed at once.
+/// Each statement has its own tokens, with the comments and whitespace before it and the rest of its last line after it,
+/// so dropping a statement frees all of it. Statements in blocks, such as in a function, are parsed along with the
+/// statement they're in.
+///
+/// The `return` or `break` that can end the code isn't a [`Stmt`](../enum.Stmt.html), so it is kept for
+/// [`last_stmt`](#method.last_stmt) instead of being given, and the semicolons after statements aren't given either.
+#[derive(Debug)]
+pub struct StmtStream<'a> {
+    lexer: Lexer<'a>,
+    // The error that stopped tokenizing, given once a statement needs the tokens after it
+    lexer_error: Option<TokenizerError>,
+    // The tokens lexed but not in a statement yet, starting with the trivia before the next statement
+    pending: VecDeque<Token<'a>>,
+    config: ParseConfig,
+    recover: bool,
+    // How many errors were given, which is at most `max_errors` when recovering
+    errors: usize,
+    queued: VecDeque<Result<Stmt<'a>, Error<'a>>>,
+    // What was tried at the start of the next statement, since it was also the end of the one before it
+    expected: Vec<ExpectedItem>,
+    last_stmt: Option<(LastStmt<'a>, Option<TokenReference<'a>>)>,
+    finished: bool,
+}
+
+// What parsing the next statement from the tokens lexed so far gave
+enum Attempt<'a> {
+    // There are no statements left, only trivia
+    End,
+    // `expected` is what was tried at the start of the next statement, which its errors would also have
+    Parsed {
+        stmt: TopLevelStmt<'a>,
+        next: usize,
+        expected: Vec<ExpectedItem>,
+    },
+    // `next` is where the next statement starts after the error, when recovering
+    Failed {
+        error: InternalAstError<'a>,
+        next: Option<(usize, Vec<ExpectedItem>)>,
+    },
+}
+
+impl<'a> StmtStream<'a> {
+    // The statements of the code, which stop at the first error unless `recover` is true,
+    // in which case the statement with it is skipped like `parse_fallible` would
+    pub(crate) fn new(code: &'a str, config: &ParseConfig, recover: bool) -> Self {
+        Self {
+            lexer: Lexer::new(code, recover, config.preserve_trivia),
+            lexer_error: None,
+            pending: VecDeque::new(),
+            config: config.clone(),
+            recover,
+            errors: 0,
+            queued: VecDeque::new(),
+            expected: Vec::new(),
+            last_stmt: None,
+            finished: false,
+        }
+    }
+
+    /// The `return` or `break` at the end of the code, if there is one and every statement before it has been given.
+    ///
+    /// ```rust
+    /// # use full_moon::node::{Node, TriviaBehavior};
+    /// let mut stmts = full_moon::parse_stmts("local x = 1\nreturn x");
+    /// assert!(stmts.next().unwrap().is_ok());
+    /// assert!(stmts.last_stmt().is_none());
+    ///
+    /// assert!(stmts.next().is_none());
+    /// assert_eq!(stmts.last_stmt().unwrap().print(TriviaBehavior::Trim), "return x");
+    /// ```
+    pub fn last_stmt(&self) -> Option<&LastStmt<'a>> {
+        self.last_stmt.as_ref().map(|(last_stmt, _)| last_stmt)
+    }
+
+    // Whether there could be more tokens to lex
+    fn lexing(&self) -> bool {
+        !self.lexer.is_finished() && self.lexer_error.is_none()
+    }
+
+    fn lex(&mut self) {
+        match self.lexer.next_token() {
+            // Comments aren't kept without trivia, since there is no Ast to put them in
+            Ok(Some(token)) => match token.token_kind() {
+                TokenKind::SingleLineComment | TokenKind::MultiLineComment
+                    if !self.config.preserve_trivia => {}
+                _ => self.pending.push_back(token),
+            },
+
+            Ok(None) => {}
+            Err(error) => self.lexer_error = Some(error),
+        }
+    }
+
+    // Parses the next statement, lexing more of the code until parsing it doesn't need to look past what was lexed,
+    // and queues it along with any errors before it
+    fn parse_next(&mut self) {
+        let mut lookahead = LOOKAHEAD_TOKENS;
+
+        loop {
+            while self.pending.len() < lookahead && self.lexing() {
+                self.lex();
+            }
+
+            // Until the end of the code, the tokens end with an Eof that isn't in it,
+            // which is only ever peeked at if the statement could go on after the tokens
+            let mut tokens: Vec<Token<'a>> = self.pending.iter().cloned().collect();
+            let ends_code = self.lexer.is_finished();
+            if !ends_code {
+                let position = tokens.last().map_or(
+                    Position {
+                        bytes: 0,
+                        character: 1,
+                        line: 1,
+                    },
+                    Token::end_position,
+                );
+                tokens.push(Token::new(TokenType::Eof, position));
+            }
+
+            let eof = tokens.len() - 1;
+            let arena = Arc::new(tokens.into_iter().collect::<Arena<_>>());
+            let furthest = Rc::new(Cell::new(0));
+            let attempt = self.attempt(&arena, &furthest);
+
+            if furthest.get() >= eof && !ends_code {
+                if self.lexing() {
+                    lookahead *= 2;
+                    continue;
+                }
+
+                for error in self.take_lexer_errors(usize::MAX) {
+                    self.queue_error(error);
+                }
+                if let Some(error) = self.lexer_error.take() {
+                    self.queue_error(Error::TokenizerError(error));
+                }
+
+                self.finished = true;
+                return;
+            }
+
+            self.finish(&arena, attempt);
+            return;
+        }
+    }
+
+    fn attempt(&self, arena: &Arc<Arena<Token<'a>>>, furthest: &Rc<Cell<usize>>) -> Attempt<'a> {
+        let mut state = ParserState::new(Arc::clone(arena))
+            .with_recursion_limit(self.config.recursion_limit)
+            .with_expression_depth_limit(self.config.expression_depth_limit)
+            .with_cancellation(self.config.cancellation.clone())
+            .with_furthest(Rc::clone(furthest));
+        if state.peek().token_type().ignore() {
+            state = state.advance().expect("there should always be an eof");
+        }
+
+        if *state.peek().token_type() == TokenType::Eof {
+            return Attempt::End;
+        }
+
+        for item in &self.expected {
+            state.expect(*item);
+        }
+
+        // The statement is in the block of the whole code, which counts towards the recursion limit
+        let result = state
+            .nest()
+            .and_then(|nesting| nesting.check(parsers::parse_top_level_stmt(state.clone())));
+
+        match result {
+            Ok((next, stmt)) => Attempt::Parsed {
+                stmt,
+                next: next.index,
+                expected: next.expected(),
+            },
+
+            Err(error) => {
+                let next = if self.recover && error != InternalAstError::Cancelled {
+                    let error_index = parsers::error_index(&state, &error);
+                    let next = parsers::next_statement(&state, error_index);
+                    Some((next.index, next.expected()))
+                } else {
+                    None
+                };
+
+                Attempt::Failed { error, next }
+            }
+        }
+    }
+
+    fn finish(&mut self, arena: &Arc<Arena<Token<'a>>>, attempt: Attempt<'a>) {
+        match attempt {
+            Attempt::End => {
+                for error in self.take_lexer_errors(usize::MAX) {
+                    self.queue_error(error);
+                }
+                self.pending.clear();
+                self.finished = true;
+            }
+
+            Attempt::Parsed {
+                stmt,
+                next,
+                expected,
+            } => {
+                let (tokens, offsets) = self.take_stmt_tokens(arena, next);
+                self.expected = expected;
+                let end = tokens
+                    .iter()
+                    .next_back()
+                    .map(|(_, token)| token.end_position());
+                if let Some(end) = end {
+                    for error in self.take_lexer_errors(end.bytes()) {
+                        self.queue_error(error);
+                    }
+                }
+
+                let mut reborrow = Reborrow {
+                    old: arena,
+                    new: &tokens,
+                    offsets: &offsets,
+                };
+
+                match stmt {
+                    TopLevelStmt::Stmt(mut stmt, _) => {
+                        stmt.visit_mut(&mut reborrow);
+                        self.queued.push_back(Ok(stmt));
+                    }
+
+                    TopLevelStmt::LastStmt(mut last_stmt, mut semicolon) => {
+                        last_stmt.visit_mut(&mut reborrow);
+                        if let Some(semicolon) = &mut semicolon {
+                            reborrow.visit_token(semicolon);
+                        }
+
+                        self.last_stmt = Some((last_stmt, semicolon));
+                        self.finished = true;
+                    }
+                }
+            }
+
+            Attempt::Failed { error, next } => {
+                let mut errors = match next {
+                    Some((next, expected)) => {
+                        let (tokens, _) = self.take_stmt_tokens(arena, next);
+                        self.expected = expected;
+                        match tokens.iter().next_back() {
+                            Some((_, token)) => {
+                                self.take_lexer_errors(token.end_position().bytes())
+                            }
+                            None => Vec::new(),
+                        }
+                    }
+
+                    None => {
+                        self.finished = true;
+                        self.take_lexer_errors(usize::MAX)
+                    }
+                };
+
+                // In the order of where they are, like `parse_fallible` gives them
+                errors.push(Error::AstError(AstError::from_internal(error)));
+                errors.sort_by_key(|error| error.position().map_or(usize::MAX, Position::bytes));
+                for error in errors {
+                    self.queue_error(error);
+                }
+            }
+        }
+    }
+
+    // Takes the tokens of the statement that ends before the token at `next` out of the pending tokens,
+    // and gives them with the offsets to reborrow the statement's tokens with.
+    // They are the first tokens of the arena it was parsed from, so their offsets are the same.
+    fn take_stmt_tokens(
+        &mut self,
+        arena: &Arc<Arena<Token<'a>>>,
+        next: usize,
+    ) -> (Arc<Arena<Token<'a>>>, BTreeMap<usize, usize>) {
+        let end = stmt_end(arena, next);
+        let tokens: Arena<Token<'a>> = self.pending.drain(..end).collect();
+
+        (
+            Arc::new(tokens),
+            (0..end).map(|offset| (offset, offset)).collect(),
+        )
+    }
+
+    // Takes the errors tokenizing kept going past before the byte
+    fn take_lexer_errors(&mut self, before: usize) -> Vec<Error<'a>> {
+        let count = self
+            .lexer
+            .errors
+            .iter()
+            .take_while(|error| error.position().bytes() < before)
+            .count();
+
+        self.lexer
+            .errors
+            .drain(..count)
+            .map(Error::TokenizerError)
+            .collect()
+    }
+
+    fn queue_error(&mut self, error: Error<'a>) {
+        if !self.recover || self.errors < self.config.max_errors.max(1) {
+            self.errors += 1;
+            self.queued.push_back(Err(error));
+        }
+    }
+}
+
+// The length of the tokens up to the end of the statement before the token at `next`,
+// which is the trivia after the statement's last token up to the end of its line
+fn stmt_end(arena: &Arena<Token>, next: usize) -> usize {
+    let get = |offset: usize| {
+        arena
+            .get_unknown_gen(offset)
+            .map(|(token, _)| token)
+            .expect("the tokens before the next statement should be in the arena")
+    };
+
+    let mut end = next;
+    while end > 0 && get(end - 1).token_type().ignore() {
+        end -= 1;
+    }
+
+    while end < next {
+        let token = get(end);
+        end += 1;
+        if let TokenType::Whitespace { characters } = &*token.token_type() {
+            if characters.contains('\n') {
+                break;
+            }
+        }
+    }
+
+    end
+}
+
+impl<'a> Iterator for StmtStream<'a> {
+    type Item = Result<Stmt<'a>, Error<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.queued.pop_front() {
+                return Some(item);
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            self.parse_next();
+        }
+    }
+}
+
+impl FusedIterator for StmtStream<'_> {}
@@ -0,0 +1,827 @@
+use crate::ast::Ast;
+use serde::{
+    de::{self, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor},
+    ser::{self, Serialize},
+    Deserialize,
+};
+use std::{
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+    fmt,
+};
+
+/// The version of the encoding of [`Ast::to_bytes`](ast/struct.Ast.html#method.to_bytes),
+/// which is changed whenever it changes in a way that older versions couldn't read
+pub const BINARY_FORMAT_VERSION: u32 = 2;
+
+// The bytes every encoded Ast starts with, followed by the format version and the version of full_moon
+const MAGIC: &[u8] = b"fullmoon";
+const FULL_MOON_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// How deeply lists and maps can be nested before the bytes are treated as corrupted,
+// so that corrupted bytes can't make reading them overflow the stack
+const MAX_DEPTH: usize = 384;
+
+// Every value starts with one of these, which is what makes the encoding self-describing
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const UINT: u8 = 3;
+const NEGATIVE_INT: u8 = 4;
+const FLOAT: u8 = 5;
+// A string that hasn't been used yet, after which it can be referred to by its index
+const STRING: u8 = 6;
+const STRING_INDEX: u8 = 7;
+const BYTES: u8 = 8;
+const SEQ: u8 = 9;
+const MAP: u8 = 10;
+const END: u8 = 11;
+
+/// An error given by [`Ast::from_bytes`](ast/struct.Ast.html#method.from_bytes) when the bytes can't be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BinaryError {
+    /// The bytes don't start with the header written by [`Ast::to_bytes`](ast/struct.Ast.html#method.to_bytes),
+    /// so they were never an encoded Ast
+    NotAnAst,
+    /// The bytes were written by a different version of full_moon, and can't be read by this one.
+    /// A cache that gives this error is stale, and should be made again from the code.
+    Version {
+        /// The [`BINARY_FORMAT_VERSION`](constant.BINARY_FORMAT_VERSION.html) the bytes were written with
+        format_version: u32,
+        /// The version of full_moon the bytes were written with
+        full_moon_version: String,
+    },
+    /// The bytes have a valid header, but what comes after it isn't an Ast, such as when they are truncated or corrupted
+    Invalid {
+        /// How many bytes were read before finding what was wrong
+        offset: usize,
+        /// What was wrong, such as `unexpected end of bytes`
+        message: String,
+    },
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinaryError::NotAnAst => write!(formatter, "bytes aren't an encoded ast"),
+            BinaryError::Version {
+                format_version,
+                full_moon_version,
+            } => write!(
+                formatter,
+                "ast was encoded by full_moon {} with format version {}, but this is full_moon {} with format version {}",
+                full_moon_version, format_version, FULL_MOON_VERSION, BINARY_FORMAT_VERSION,
+            ),
+            BinaryError::Invalid { offset, message } => {
+                write!(formatter, "invalid encoded ast at byte {}: {}", offset, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+// The error of the serializer and deserializer, which is given the offset where it happened
+// when it becomes a `BinaryError`
+#[derive(Debug)]
+struct Message(String);
+
+impl fmt::Display for Message {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Message {}
+
+impl ser::Error for Message {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Message(message.to_string())
+    }
+}
+
+impl de::Error for Message {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Message(message.to_string())
+    }
+}
+
+pub(crate) fn ast_to_bytes(ast: &Ast) -> Vec<u8> {
+    let mut encoder = Encoder {
+        output: MAGIC.to_vec(),
+        strings: HashMap::new(),
+    };
+
+    encoder
+        .output
+        .extend_from_slice(&BINARY_FORMAT_VERSION.to_le_bytes());
+    encoder.output.push(FULL_MOON_VERSION.len() as u8);
+    encoder
+        .output
+        .extend_from_slice(FULL_MOON_VERSION.as_bytes());
+
+    ast.serialize(&mut encoder)
+        .expect("an ast can always be serialized");
+    encoder.output
+}
+
+pub(crate) fn ast_from_bytes(bytes: &[u8]) -> Result<Ast<'_>, BinaryError> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(BinaryError::NotAnAst);
+    }
+
+    let mut offset = MAGIC.len();
+    let format_version = bytes.get(offset..offset + 4).ok_or(BinaryError::NotAnAst)?;
+    let format_version = u32::from_le_bytes(format_version.try_into().unwrap());
+    offset += 4;
+
+    let version_length = *bytes.get(offset).ok_or(BinaryError::NotAnAst)? as usize;
+    offset += 1;
+    let full_moon_version = bytes
+        .get(offset..offset + version_length)
+        .ok_or(BinaryError::NotAnAst)?;
+    offset += version_length;
+
+    if format_version != BINARY_FORMAT_VERSION || full_moon_version != FULL_MOON_VERSION.as_bytes()
+    {
+        return Err(BinaryError::Version {
+            format_version,
+            full_moon_version: String::from_utf8_lossy(full_moon_version).into_owned(),
+        });
+    }
+
+    let mut decoder = Decoder {
+        input: bytes,
+        offset,
+        strings: Vec::new(),
+        depth: 0,
+    };
+
+    let ast = Ast::deserialize(&mut decoder).and_then(|ast| {
+        if decoder.offset == bytes.len() {
+            Ok(ast)
+        } else {
+            Err(Message(
+                "unexpected bytes after the end of the ast".to_owned(),
+            ))
+        }
+    });
+
+    ast.map_err(|Message(message)| BinaryError::Invalid {
+        offset: decoder.offset,
+        message,
+    })
+}
+
+struct Encoder {
+    output: Vec<u8>,
+    // The strings that have been written, by their index
+    strings: HashMap<String, u64>,
+}
+
+impl Encoder {
+    // Writes an integer in 7 bit groups, least significant first, with the top bit set on all but the last
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                self.output.push(byte);
+                break;
+            }
+
+            self.output.push(byte | 0x80);
+        }
+    }
+
+    fn string(&mut self, string: &str) {
+        match self.strings.get(string) {
+            Some(&index) => {
+                self.output.push(STRING_INDEX);
+                self.varint(index);
+            }
+
+            None => {
+                self.strings
+                    .insert(string.to_owned(), self.strings.len() as u64);
+                self.output.push(STRING);
+                self.varint(string.len() as u64);
+                self.output.extend_from_slice(string.as_bytes());
+            }
+        }
+    }
+}
+
+impl ser::Serializer for &mut Encoder {
+    type Ok = ();
+    type Error = Message;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, value: bool) -> Result<(), Message> {
+        self.output.push(if value { TRUE } else { FALSE });
+        Ok(())
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<(), Message> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<(), Message> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<(), Message> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<(), Message> {
+        if value >= 0 {
+            self.serialize_u64(value as u64)
+        } else {
+            // -1 is written as 0, -2 as 1, and so on
+            self.output.push(NEGATIVE_INT);
+            self.varint(!value as u64);
+            Ok(())
+        }
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<(), Message> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<(), Message> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<(), Message> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<(), Message> {
+        self.output.push(UINT);
+        self.varint(value);
+        Ok(())
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<(), Message> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<(), Message> {
+        self.output.push(FLOAT);
+        self.output.extend_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), Message> {
+        self.string(value.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), Message> {
+        self.string(value);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), Message> {
+        self.output.push(BYTES);
+        self.varint(value.len() as u64);
+        self.output.extend_from_slice(value);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Message> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Message> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Message> {
+        self.output.push(NULL);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Message> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), Message> {
+        self.string(variant);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Message> {
+        value.serialize(self)
+    }
+
+    // Variants with data are maps from the name of the variant to the data, the same as in JSON
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Message> {
+        self.output.push(MAP);
+        self.string(variant);
+        value.serialize(&mut *self)?;
+        self.output.push(END);
+        Ok(())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, Message> {
+        self.output.push(SEQ);
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, length: usize) -> Result<Self, Message> {
+        self.serialize_seq(Some(length))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, length: usize) -> Result<Self, Message> {
+        self.serialize_seq(Some(length))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self, Message> {
+        self.output.push(MAP);
+        self.string(variant);
+        self.output.push(SEQ);
+        Ok(self)
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self, Message> {
+        self.output.push(MAP);
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, Message> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self, Message> {
+        self.output.push(MAP);
+        self.string(variant);
+        self.output.push(MAP);
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for &mut Encoder {
+    type Ok = ();
+    type Error = Message;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Message> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Message> {
+        self.output.push(END);
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Encoder {
+    type Ok = ();
+    type Error = Message;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Message> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Message> {
+        self.output.push(END);
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut Encoder {
+    type Ok = ();
+    type Error = Message;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Message> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Message> {
+        self.output.push(END);
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Encoder {
+    type Ok = ();
+    type Error = Message;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Message> {
+        value.serialize(&mut **self)
+    }
+
+    // Ends both the fields and the map around them
+    fn end(self) -> Result<(), Message> {
+        self.output.extend_from_slice(&[END, END]);
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Encoder {
+    type Ok = ();
+    type Error = Message;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Message> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Message> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Message> {
+        self.output.push(END);
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Encoder {
+    type Ok = ();
+    type Error = Message;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Message> {
+        self.string(key);
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Message> {
+        self.output.push(END);
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Encoder {
+    type Ok = ();
+    type Error = Message;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Message> {
+        self.string(key);
+        value.serialize(&mut **self)
+    }
+
+    // Ends both the fields and the map around them
+    fn end(self) -> Result<(), Message> {
+        self.output.extend_from_slice(&[END, END]);
+        Ok(())
+    }
+}
+
+struct Decoder<'de> {
+    input: &'de [u8],
+    offset: usize,
+    // The strings that have been read, for the strings after them that refer to them by index
+    strings: Vec<&'de str>,
+    depth: usize,
+}
+
+impl<'de> Decoder<'de> {
+    fn peek(&self) -> Result<u8, Message> {
+        self.input
+            .get(self.offset)
+            .copied()
+            .ok_or_else(|| Message("unexpected end of bytes".to_owned()))
+    }
+
+    fn byte(&mut self) -> Result<u8, Message> {
+        let byte = self.peek()?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, length: u64) -> Result<&'de [u8], Message> {
+        let remaining = self.input.len() - self.offset;
+        if length > remaining as u64 {
+            return Err(Message(format!(
+                "length {} is longer than the {} bytes left",
+                length, remaining
+            )));
+        }
+
+        let bytes = &self.input[self.offset..self.offset + length as usize];
+        self.offset += length as usize;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u64, Message> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            if shift == 63 && byte > 1 {
+                break;
+            }
+
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(Message("integer is too large".to_owned()))
+    }
+
+    fn string(&mut self, tag: u8) -> Result<&'de str, Message> {
+        if tag == STRING_INDEX {
+            let index = self.varint()?;
+            let string = usize::try_from(index)
+                .ok()
+                .and_then(|index| self.strings.get(index));
+            return string.copied().ok_or_else(|| {
+                Message(format!(
+                    "string {} is out of range, since only {} strings come before it",
+                    index,
+                    self.strings.len()
+                ))
+            });
+        }
+
+        let length = self.varint()?;
+        let string = std::str::from_utf8(self.take(length)?)
+            .map_err(|_| Message("string isn't valid utf-8".to_owned()))?;
+        self.strings.push(string);
+        Ok(string)
+    }
+
+    fn enter(&mut self) -> Result<(), Message> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            Err(Message("lists and maps are nested too deeply".to_owned()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn end(&mut self) -> Result<(), Message> {
+        if self.byte()? != END {
+            return Err(Message("expected the end of a list or map".to_owned()));
+        }
+
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Decoder<'de> {
+    type Error = Message;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Message> {
+        match self.byte()? {
+            NULL => visitor.visit_unit(),
+            FALSE => visitor.visit_bool(false),
+            TRUE => visitor.visit_bool(true),
+            UINT => visitor.visit_u64(self.varint()?),
+
+            NEGATIVE_INT => {
+                let value = self.varint()?;
+                if value > i64::MAX as u64 {
+                    return Err(Message("integer is too large".to_owned()));
+                }
+
+                visitor.visit_i64(!(value as i64))
+            }
+
+            FLOAT => {
+                let bytes = self.take(8)?;
+                visitor.visit_f64(f64::from_le_bytes(bytes.try_into().unwrap()))
+            }
+
+            tag @ (STRING | STRING_INDEX) => visitor.visit_borrowed_str(self.string(tag)?),
+
+            BYTES => {
+                let length = self.varint()?;
+                visitor.visit_borrowed_bytes(self.take(length)?)
+            }
+
+            SEQ => {
+                self.enter()?;
+                let value = visitor.visit_seq(Elements(self))?;
+                self.end()?;
+                Ok(value)
+            }
+
+            MAP => {
+                self.enter()?;
+                let value = visitor.visit_map(Elements(self))?;
+                self.end()?;
+                Ok(value)
+            }
+
+            tag => Err(Message(format!("unknown tag {}", tag))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Message> {
+        if self.peek()? == NULL {
+            self.offset += 1;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Message> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Message> {
+        match self.peek()? {
+            STRING | STRING_INDEX => visitor.visit_enum(UnitVariant(self)),
+
+            MAP => {
+                self.offset += 1;
+                self.enter()?;
+                let value = visitor.visit_enum(Variant(&mut *self))?;
+                self.end()?;
+                Ok(value)
+            }
+
+            tag => Err(Message(format!("expected an enum, found tag {}", tag))),
+        }
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+// The elements of a list or the entries of a map, up to the end of it
+struct Elements<'a, 'de>(&'a mut Decoder<'de>);
+
+impl<'de> SeqAccess<'de> for Elements<'_, 'de> {
+    type Error = Message;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Message> {
+        if self.0.peek()? == END {
+            Ok(None)
+        } else {
+            seed.deserialize(&mut *self.0).map(Some)
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for Elements<'_, 'de> {
+    type Error = Message;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Message> {
+        if self.0.peek()? == END {
+            Ok(None)
+        } else {
+            seed.deserialize(&mut *self.0).map(Some)
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Message> {
+        seed.deserialize(&mut *self.0)
+    }
+}
+
+// A variant without data, which is just its name
+struct UnitVariant<'a, 'de>(&'a mut Decoder<'de>);
+
+impl<'de> EnumAccess<'de> for UnitVariant<'_, 'de> {
+    type Error = Message;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Message> {
+        let variant = seed.deserialize(&mut *self.0)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for UnitVariant<'_, 'de> {
+    type Error = Message;
+
+    fn unit_variant(self) -> Result<(), Message> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, _: T) -> Result<T::Value, Message> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"newtype variant",
+        ))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, _: V) -> Result<V::Value, Message> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        _: V,
+    ) -> Result<V::Value, Message> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+// A variant with data, which is a map from its name to the data
+struct Variant<'a, 'de>(&'a mut Decoder<'de>);
+
+impl<'de> EnumAccess<'de> for Variant<'_, 'de> {
+    type Error = Message;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Message> {
+        let variant = seed.deserialize(&mut *self.0)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Variant<'_, 'de> {
+    type Error = Message;
+
+    fn unit_variant(self) -> Result<(), Message> {
+        <()>::deserialize(self.0)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Message> {
+        seed.deserialize(self.0)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Message> {
+        de::Deserializer::deserialize_any(self.0, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Message> {
+        de::Deserializer::deserialize_any(self.0, visitor)
+    }
+}
@@ -0,0 +1,444 @@
+use crate::{
+    ast::{
+        punctuated::{Pair, Punctuated},
+        Ast,
+    },
+    prelude::*,
+    tokenizer::{Token, TokenReference},
+};
+use alloc::{borrow::Cow, collections::BTreeMap};
+use core::fmt::Write;
+
+/// The options for [`to_dot_with`](fn.to_dot_with.html).
+/// Use [`DotConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DotConfig {
+    /// Whether the comments and whitespace around tokens are in the graph, under the tokens they belong to,
+    /// false by default
+    pub trivia: bool,
+    /// How many levels of nodes under the Ast are in the graph, all of them if None, which is the default.
+    /// Nodes at the deepest level that have children left out of the graph have `...` under their kind.
+    pub max_depth: Option<usize>,
+    /// Whether nodes with only tokens under them, such as a `Value::Number`, are a single node with their kind
+    /// and the code of their tokens, false by default. Tokens with trivia in the graph aren't collapsed.
+    pub collapse_tokens: bool,
+}
+
+/// The options for [`Ast::to_sexpr_with`](../ast/struct.Ast.html#method.to_sexpr_with).
+/// Use [`SexprConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SexprConfig {
+    /// Whether every node and token has the bytes of its range after its kind or code, such as `x@6..7`,
+    /// false by default
+    pub positions: bool,
+}
+
+// A node of the graph, before it is written, which is also the tree the s-expressions are written from
+pub(crate) struct DotNode {
+    kind: String,
+    // The code of the node if it is a token
+    code: Option<String>,
+    // The bytes from the start of the first token of the node to the end of the last one, for s-expressions
+    range: Option<(usize, usize)>,
+    // The nodes under this one, with the field of this one they are from
+    children: Vec<(String, DotNode)>,
+}
+
+impl DotNode {
+    pub(crate) fn node(kind: String, children: Vec<(String, DotNode)>) -> Self {
+        // The fields of a node aren't always in the order of the code, such as the arguments of a call
+        // before its parentheses
+        let range = children.iter().filter_map(|(_, child)| child.range).reduce(
+            |(start, end), (child_start, child_end)| (start.min(child_start), end.max(child_end)),
+        );
+
+        Self {
+            kind,
+            code: None,
+            range,
+            children,
+        }
+    }
+
+    fn token(token: &Token, children: Vec<(String, DotNode)>) -> Self {
+        Self {
+            kind: format!("{:?}", token.token_kind()),
+            code: Some(token.to_string()),
+            range: Some((
+                Token::start_position(token).bytes(),
+                Token::end_position(token).bytes(),
+            )),
+            children,
+        }
+    }
+}
+
+// Implemented for every node with `#[derive(Dot)]`, along with the types in their fields.
+// Adds the nodes of the graph for the value to the edges of its parent, all with the label of the field it's in.
+pub(crate) trait ToDot<'a> {
+    fn add_to(&self, graph: &DotBuilder<'a, '_>, label: &str, edges: &mut Vec<(String, DotNode)>);
+}
+
+pub(crate) struct DotBuilder<'a, 'b> {
+    config: &'b DotConfig,
+    // The comments and whitespace before and after each token, by its offset in the arena of the Ast
+    trivia: BTreeMap<usize, (Vec<&'b Token<'a>>, Vec<&'b Token<'a>>)>,
+}
+
+impl<'a, 'b> DotBuilder<'a, 'b> {
+    fn token(&self, token: &TokenReference<'a>) -> DotNode {
+        let mut children = Vec::new();
+        if self.config.trivia {
+            if let Some((leading, trailing)) = token
+                .arena_offset()
+                .and_then(|(_, offset)| self.trivia.get(&offset))
+            {
+                for (name, trivia) in &[("leading_trivia", leading), ("trailing_trivia", trailing)]
+                {
+                    for (index, trivia) in trivia.iter().enumerate() {
+                        children.push((
+                            format!("{}[{}]", name, index),
+                            DotNode::token(trivia, Vec::new()),
+                        ));
+                    }
+                }
+            }
+        }
+
+        DotNode::token(token, children)
+    }
+}
+
+/// Writes the Ast as a [DOT](https://graphviz.org/doc/info/lang.html) graph, such as to draw it with Graphviz,
+/// using the default [`DotConfig`](struct.DotConfig.html). Use [`to_dot_with`](fn.to_dot_with.html) to change it.
+///
+/// Every node of the graph is a node of the Ast, with its kind, such as `LocalAssignment` or `Stmt::If`,
+/// or a token, with its [`TokenKind`](../tokenizer/enum.TokenKind.html) and code. The edges from a node are labeled
+/// with the field they are in, such as `condition` or `block`, along with the index for lists, such as `stmts[0]`,
+/// and the index of the field for variants without field names, such as `0`.
+/// The root of the graph is the Ast, with its `nodes` and the `eof` token at the end of the code.
+///
+/// ```rust
+/// let ast = full_moon::parse("local x = 1").unwrap();
+/// let dot = full_moon::debug::to_dot(&ast);
+/// assert!(dot.starts_with("digraph ast {"));
+/// assert!(dot.contains(r#"[label="LocalAssignment"]"#));
+/// assert!(dot.contains(r#"[label="Identifier\n\"x\"", shape=ellipse]"#));
+/// assert!(dot.contains(r#"[label="name_list[0]"]"#));
+/// ```
+pub fn to_dot(ast: &Ast) -> String {
+    to_dot_with(ast, &DotConfig::default())
+}
+
+/// Writes the Ast as a [DOT](https://graphviz.org/doc/info/lang.html) graph, the same as
+/// [`to_dot`](fn.to_dot.html), with a [`DotConfig`](struct.DotConfig.html) to keep the graphs of big files readable.
+///
+/// ```rust
+/// # use full_moon::debug::{to_dot_with, DotConfig};
+/// let ast = full_moon::parse("-- one\nlocal x = 1").unwrap();
+/// let config = DotConfig {
+///     trivia: true,
+///     collapse_tokens: true,
+///     ..DotConfig::default()
+/// };
+///
+/// let dot = to_dot_with(&ast, &config);
+/// assert!(dot.contains(r#"[label="SingleLineComment\n\"-- one\"", shape=ellipse, style=dashed]"#));
+/// assert!(dot.contains(r#"[label="Value::Number\n1"]"#));
+/// ```
+pub fn to_dot_with(ast: &Ast, config: &DotConfig) -> String {
+    let mut output = String::from("digraph ast {\n");
+    let mut next_id = 0;
+    write_node(
+        &mut output,
+        config,
+        &tree(ast, config),
+        0,
+        false,
+        &mut next_id,
+    );
+    output.push_str("}\n");
+    output
+}
+
+// The node of the Ast, with its nodes and the token at the end of the code under it
+fn tree(ast: &Ast, config: &DotConfig) -> DotNode {
+    let graph = DotBuilder {
+        config,
+        trivia: ast.trivia_by_token(),
+    };
+
+    let mut children = Vec::new();
+    ast.nodes().add_to(&graph, "nodes", &mut children);
+    ast.eof().add_to(&graph, "eof", &mut children);
+    DotNode::node("Ast".to_owned(), children)
+}
+
+// Writes the node and everything under it, returning its id
+fn write_node(
+    output: &mut String,
+    config: &DotConfig,
+    node: &DotNode,
+    depth: usize,
+    trivia: bool,
+    next_id: &mut usize,
+) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+
+    let collapse = config.collapse_tokens
+        && node.code.is_none()
+        && !node.children.is_empty()
+        && node
+            .children
+            .iter()
+            .all(|(_, child)| child.code.is_some() && child.children.is_empty());
+    let cut_off = config.max_depth == Some(depth) && !node.children.is_empty();
+
+    let label = match &node.code {
+        Some(code) => format!("{}\n{:?}", node.kind, code),
+        None if collapse => {
+            let code: Vec<_> = node
+                .children
+                .iter()
+                .filter_map(|(_, child)| child.code.as_deref())
+                .collect();
+            format!("{}\n{}", node.kind, code.join(" "))
+        }
+        None if cut_off => format!("{}\n...", node.kind),
+        None => node.kind.clone(),
+    };
+
+    write!(output, "    n{} [label=\"{}\"", id, escape(&label)).unwrap();
+    if node.code.is_some() {
+        output.push_str(", shape=ellipse");
+        if trivia {
+            output.push_str(", style=dashed");
+        }
+    }
+    output.push_str("];\n");
+
+    if !collapse && !cut_off {
+        for (label, child) in &node.children {
+            // The children of a token are its trivia
+            let child_id = write_node(
+                output,
+                config,
+                child,
+                depth + 1,
+                node.code.is_some(),
+                next_id,
+            );
+            writeln!(
+                output,
+                "    n{} -> n{} [label=\"{}\"];",
+                id,
+                child_id,
+                escape(label)
+            )
+            .unwrap();
+        }
+    }
+
+    id
+}
+
+// Escapes text for a quoted string in DOT, where lines are separated with `\n`
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// How wide a line of s-expressions can be before the children of a list go on lines of their own
+const SEXPR_WIDTH: usize = 80;
+
+// A list or atom of an s-expression, along with how wide it is when written on one line
+struct Sexpr {
+    text: String,
+    children: Option<Vec<Sexpr>>,
+    width: usize,
+}
+
+impl Sexpr {
+    fn new(
+        text: String,
+        range: Option<(usize, usize)>,
+        config: &SexprConfig,
+        children: Option<Vec<Sexpr>>,
+    ) -> Self {
+        let text = match range {
+            Some((start, end)) if config.positions => format!("{}@{}..{}", text, start, end),
+            _ => text,
+        };
+
+        let width = match &children {
+            Some(children) => children
+                .iter()
+                .fold(text.len() + 2, |width, child| width + 1 + child.width),
+            None => text.len(),
+        };
+
+        Self {
+            text,
+            children,
+            width,
+        }
+    }
+
+    fn write(&self, output: &mut String, indent: usize) {
+        let children = match &self.children {
+            Some(children) => children,
+            None => return output.push_str(&self.text),
+        };
+
+        output.push('(');
+        output.push_str(&self.text);
+        let multi_line = indent + self.width > SEXPR_WIDTH;
+        for child in children {
+            if multi_line {
+                output.push('\n');
+                output.push_str(&" ".repeat(indent + 2));
+            } else {
+                output.push(' ');
+            }
+
+            child.write(output, indent + 2);
+        }
+        output.push(')');
+    }
+}
+
+// The s-expression of the node, or None if it isn't written, such as the brackets of a contained span
+fn sexpr(node: &DotNode, config: &SexprConfig) -> Option<Sexpr> {
+    if let Some(code) = &node.code {
+        // The end of the code has no code of its own
+        if node.kind == "Eof" {
+            return None;
+        }
+
+        let code = code
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+            .replace('\t', "\\t");
+        return Some(Sexpr::new(code, node.range, config, None));
+    }
+
+    if node.kind == "ContainedSpan" {
+        return None;
+    }
+
+    // Variants that only hold another node, such as `Stmt::If`, are written as that node
+    if let [(label, child)] = &node.children[..] {
+        if label == "0" && node.kind.contains("::") && child.code.is_none() {
+            return sexpr(child, config);
+        }
+    }
+
+    let kind = snake_case(node.kind.rsplit("::").next().unwrap_or_default());
+
+    // Symbols are left out, since the kind says what they are, unless they say something the kind doesn't,
+    // such as the `true` of a `(symbol true)`, or a `:`, which tells methods apart from functions
+    let only_symbol = match &node.children[..] {
+        [(_, child)] => child.kind == "Symbol" && child.code.as_deref() != Some(kind.as_str()),
+        _ => false,
+    };
+    let written = |child: &DotNode| {
+        only_symbol || child.kind != "Symbol" || child.code.as_deref() == Some(":")
+    };
+
+    let children = node
+        .children
+        .iter()
+        .filter(|(_, child)| written(child))
+        .filter_map(|(_, child)| sexpr(child, config))
+        .collect();
+
+    Some(Sexpr::new(kind, node.range, config, Some(children)))
+}
+
+fn snake_case(kind: &str) -> String {
+    let mut snake = String::with_capacity(kind.len() + 4);
+    for (index, character) in kind.char_indices() {
+        if character.is_ascii_uppercase() && index > 0 {
+            snake.push('_');
+        }
+        snake.push(character.to_ascii_lowercase());
+    }
+    snake
+}
+
+pub(crate) fn ast_to_sexpr(ast: &Ast, config: &SexprConfig) -> String {
+    let tree = tree(ast, &DotConfig::default());
+    let mut output = String::new();
+    for (_, child) in &tree.children {
+        if let Some(sexpr) = sexpr(child, config) {
+            sexpr.write(&mut output, 0);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+impl<'a> ToDot<'a> for TokenReference<'a> {
+    fn add_to(&self, graph: &DotBuilder<'a, '_>, label: &str, edges: &mut Vec<(String, DotNode)>) {
+        edges.push((label.to_owned(), graph.token(self)));
+    }
+}
+
+impl<'a, T: ToDot<'a>> ToDot<'a> for &T {
+    fn add_to(&self, graph: &DotBuilder<'a, '_>, label: &str, edges: &mut Vec<(String, DotNode)>) {
+        (**self).add_to(graph, label, edges)
+    }
+}
+
+impl<'a, T: ToDot<'a>> ToDot<'a> for Box<T> {
+    fn add_to(&self, graph: &DotBuilder<'a, '_>, label: &str, edges: &mut Vec<(String, DotNode)>) {
+        (**self).add_to(graph, label, edges)
+    }
+}
+
+impl<'a, T: Clone + ToDot<'a>> ToDot<'a> for Cow<'a, T> {
+    fn add_to(&self, graph: &DotBuilder<'a, '_>, label: &str, edges: &mut Vec<(String, DotNode)>) {
+        (**self).add_to(graph, label, edges)
+    }
+}
+
+impl<'a, T: ToDot<'a>> ToDot<'a> for Option<T> {
+    fn add_to(&self, graph: &DotBuilder<'a, '_>, label: &str, edges: &mut Vec<(String, DotNode)>) {
+        if let Some(value) = self {
+            value.add_to(graph, label, edges);
+        }
+    }
+}
+
+impl<'a, T: ToDot<'a>> ToDot<'a> for Vec<T> {
+    fn add_to(&self, graph: &DotBuilder<'a, '_>, label: &str, edges: &mut Vec<(String, DotNode)>) {
+        for (index, value) in self.iter().enumerate() {
+            value.add_to(graph, &format!("{}[{}]", label, index), edges);
+        }
+    }
+}
+
+// Both values of a pair, such as a statement and its semicolon, have the label of the pair
+impl<'a, A: ToDot<'a>, B: ToDot<'a>> ToDot<'a> for (A, B) {
+    fn add_to(&self, graph: &DotBuilder<'a, '_>, label: &str, edges: &mut Vec<(String, DotNode)>) {
+        self.0.add_to(graph, label, edges);
+        self.1.add_to(graph, label, edges);
+    }
+}
+
+impl<'a, T: ToDot<'a>> ToDot<'a> for Punctuated<'a, T> {
+    fn add_to(&self, graph: &DotBuilder<'a, '_>, label: &str, edges: &mut Vec<(String, DotNode)>) {
+        for (index, pair) in self.pairs().enumerate() {
+            pair.add_to(graph, &format!("{}[{}]", label, index), edges);
+        }
+    }
+}
+
+impl<'a, T: ToDot<'a>> ToDot<'a> for Pair<'a, T> {
+    fn add_to(&self, graph: &DotBuilder<'a, '_>, label: &str, edges: &mut Vec<(String, DotNode)>) {
+        self.value().add_to(graph, label, edges);
+        self.punctuation().add_to(graph, label, edges);
+    }
+}
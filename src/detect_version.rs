@@ -0,0 +1,336 @@
+use crate::{
+    prelude::*,
+    tokenizer::{Lexer, Position, Symbol, Token, TokenType, TokenizerErrorType},
+};
+
+/// A version of Lua, or a dialect of it, that code can be written for
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LuaVersion {
+    /// Lua 5.1, which full_moon parses
+    Lua51,
+    /// Lua 5.2
+    Lua52,
+    /// Lua 5.3
+    Lua53,
+    /// Lua 5.4
+    Lua54,
+    /// LuaJIT, which is Lua 5.1 with a few additions, such as `goto`
+    LuaJit,
+    /// Luau, the dialect of Lua used by Roblox, which is Lua 5.1 with many additions
+    Luau,
+}
+
+impl LuaVersion {
+    // Every version, in the order the smallest one that has some features is chosen from
+    const ALL: [LuaVersion; 6] = [
+        LuaVersion::Lua51,
+        LuaVersion::Lua52,
+        LuaVersion::Lua53,
+        LuaVersion::Lua54,
+        LuaVersion::LuaJit,
+        LuaVersion::Luau,
+    ];
+
+    /// Whether code written for this version can use the feature
+    ///
+    /// ```rust
+    /// # use full_moon::{DialectFeature, LuaVersion};
+    /// assert!(LuaVersion::Lua54.has(DialectFeature::Goto));
+    /// assert!(!LuaVersion::Luau.has(DialectFeature::Goto));
+    /// ```
+    pub fn has(self, feature: DialectFeature) -> bool {
+        use LuaVersion::*;
+
+        match feature {
+            DialectFeature::Goto => matches!(self, Lua52 | Lua53 | Lua54 | LuaJit),
+            DialectFeature::FloorDivision => matches!(self, Lua53 | Lua54 | Luau),
+            DialectFeature::BitwiseOperators => matches!(self, Lua53 | Lua54),
+            DialectFeature::Attributes => self == Lua54,
+            DialectFeature::TypeCasts
+            | DialectFeature::InterpolatedStrings
+            | DialectFeature::BinaryNumbers => self == Luau,
+            DialectFeature::IntegerSuffixes => self == LuaJit,
+        }
+    }
+}
+
+/// Syntax that only some versions of Lua have, found by [`detect_version`](fn.detect_version.html)
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum DialectFeature {
+    /// A `goto` statement or a label, such as `goto continue` and `::continue::`, from Lua 5.2 and LuaJIT
+    Goto,
+    /// The `//` operator, from Lua 5.3 and Luau
+    FloorDivision,
+    /// The `&`, `|`, `~`, `<<`, and `>>` operators, from Lua 5.3
+    BitwiseOperators,
+    /// A `<const>` or `<close>` attribute of a local, from Lua 5.4
+    Attributes,
+    /// A `::` type cast, such as `x :: number`, from Luau
+    TypeCasts,
+    /// A string in backticks, such as `` `hello {name}` ``, from Luau
+    InterpolatedStrings,
+    /// A binary number, such as `0b101`, from Luau, which full_moon reads with the `roblox` feature
+    BinaryNumbers,
+    /// A number with an `LL` or `ULL` suffix, such as `1LL`, from LuaJIT
+    IntegerSuffixes,
+}
+
+/// The features of versions of Lua that code uses, from [`detect_version`](fn.detect_version.html)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DetectedVersion {
+    // The first evidence of each feature, in the order they're found in the code
+    evidence: Vec<(DialectFeature, Position)>,
+}
+
+impl DetectedVersion {
+    /// The features that were found, in the order of their first evidence in the code
+    pub fn features(&self) -> impl Iterator<Item = DialectFeature> + '_ {
+        self.evidence.iter().map(|(feature, _)| *feature)
+    }
+
+    /// Whether the feature was found
+    pub fn contains(&self, feature: DialectFeature) -> bool {
+        self.evidence(feature).is_some()
+    }
+
+    /// Where the first evidence of the feature is, such as the `goto` of a `goto` statement,
+    /// or None if it wasn't found
+    pub fn evidence(&self, feature: DialectFeature) -> Option<Position> {
+        self.evidence
+            .iter()
+            .find(|(found, _)| *found == feature)
+            .map(|(_, position)| *position)
+    }
+
+    /// The smallest version that has every feature that was found, which is
+    /// [`Lua51`](enum.LuaVersion.html#variant.Lua51) when none were, or None when no version has all of them,
+    /// such as for code with both bitwise operators and type casts. Versions of Lua are chosen before LuaJIT
+    /// and Luau, so code that only uses `goto` is Lua 5.2.
+    pub fn version(&self) -> Option<LuaVersion> {
+        LuaVersion::ALL
+            .iter()
+            .copied()
+            .find(|version| self.features().all(|feature| version.has(feature)))
+    }
+
+    // Keeps the evidence if it's the first for its feature
+    fn add(&mut self, feature: DialectFeature, position: Position) {
+        match self
+            .evidence
+            .iter_mut()
+            .find(|(found, _)| *found == feature)
+        {
+            Some((_, first)) if position.bytes() < first.bytes() => *first = position,
+            Some(_) => {}
+            None => self.evidence.push((feature, position)),
+        }
+    }
+}
+
+/// Guesses which version of Lua code is written for, by looking for syntax that only some versions have,
+/// such as `goto`, `//`, and Luau's type casts, before choosing how to parse it.
+///
+/// Only the tokens of the code are looked at, without parsing it, so it's fast and works on code that full_moon
+/// can't parse, and what is inside of strings and comments is never mistaken for code. Since it's a guess,
+/// some code can look like it uses a feature it doesn't, such as a variable named `goto` in Lua 5.1,
+/// and features that aren't found by their tokens alone, such as Luau's type annotations, aren't looked for.
+/// Code that can't be tokenized past a point, such as an unclosed string, is only looked at up to it.
+///
+/// ```rust
+/// # use full_moon::{detect_version, DialectFeature, LuaVersion};
+/// let detected = detect_version("local x <const> = 1 // 2 -- a & b");
+/// assert_eq!(
+///     detected.features().collect::<Vec<_>>(),
+///     vec![DialectFeature::Attributes, DialectFeature::FloorDivision],
+/// );
+/// assert_eq!(detected.evidence(DialectFeature::FloorDivision).unwrap().bytes(), 20);
+/// assert_eq!(detected.version(), Some(LuaVersion::Lua54));
+///
+/// assert_eq!(detect_version("print('::x:: //')").version(), Some(LuaVersion::Lua51));
+/// assert_eq!(detect_version("local x = y :: number").version(), Some(LuaVersion::Luau));
+/// assert_eq!(detect_version("x = a & b :: number").version(), None);
+/// ```
+pub fn detect_version(code: &str) -> DetectedVersion {
+    let mut detected = DetectedVersion::default();
+    let tokens = significant_tokens(code, &mut detected);
+
+    let symbol = |index: usize| match tokens.get(index).map(|token| token.token_type()) {
+        Some(token_type) => match *token_type {
+            TokenType::Symbol { symbol } => Some(symbol),
+            _ => None,
+        },
+        None => None,
+    };
+    let identifier = |index: usize| match tokens.get(index).map(|token| token.token_type()) {
+        Some(token_type) => match &*token_type {
+            TokenType::Identifier { identifier } => Some(identifier.to_string()),
+            _ => None,
+        },
+        None => None,
+    };
+    // Whether the token is right after the one before it, such as the second `/` of `//`
+    let touching = |index: usize| {
+        index > 0
+            && tokens.get(index).map(Token::start_position)
+                == Some(tokens[index - 1].end_position())
+    };
+
+    let mut index = 0;
+    while let Some(token) = tokens.get(index) {
+        let position = token.start_position();
+        let mut length = 1;
+
+        match (symbol(index), symbol(index + 1)) {
+            // A label is `::`, a name, and `::`, and every other `::` is a type cast
+            (Some(Symbol::Colon), Some(Symbol::Colon)) if touching(index + 1) => {
+                if identifier(index + 2).is_some()
+                    && symbol(index + 3) == Some(Symbol::Colon)
+                    && symbol(index + 4) == Some(Symbol::Colon)
+                    && touching(index + 4)
+                {
+                    detected.add(DialectFeature::Goto, position);
+                    length = 5;
+                } else {
+                    detected.add(DialectFeature::TypeCasts, position);
+                    length = 2;
+                }
+            }
+
+            (Some(Symbol::Slash), Some(Symbol::Slash)) if touching(index + 1) => {
+                detected.add(DialectFeature::FloorDivision, position);
+                length = 2;
+            }
+
+            (Some(Symbol::LessThan), Some(Symbol::LessThan)) if touching(index + 1) => {
+                detected.add(DialectFeature::BitwiseOperators, position);
+                length = 2;
+            }
+
+            // Luau types can end in `>>`, such as `{ Array<Array<number>> }`, but they aren't followed by a value
+            (Some(Symbol::GreaterThan), Some(Symbol::GreaterThan))
+                if touching(index + 1) && starts_value(tokens.get(index + 2)) =>
+            {
+                detected.add(DialectFeature::BitwiseOperators, position);
+                length = 2;
+            }
+
+            // An attribute is `<const>` or `<close>` after the name of a local
+            (Some(Symbol::LessThan), _)
+                if matches!(
+                    identifier(index + 1).as_deref(),
+                    Some("const") | Some("close")
+                ) && symbol(index + 2) == Some(Symbol::GreaterThan)
+                    && index > 0
+                    && identifier(index - 1).is_some()
+                    && matches!(
+                        symbol(index.saturating_sub(2)),
+                        Some(Symbol::Local) | Some(Symbol::Comma)
+                    ) =>
+            {
+                detected.add(DialectFeature::Attributes, position);
+                length = 3;
+            }
+
+            (None, _)
+                if identifier(index).as_deref() == Some("goto")
+                    && identifier(index + 1).is_some() =>
+            {
+                detected.add(DialectFeature::Goto, position);
+                length = 2;
+            }
+
+            (None, _) => {
+                if let TokenType::Number { text } = &*token.token_type() {
+                    let text = text.to_ascii_lowercase();
+                    if text.starts_with("0b") {
+                        detected.add(DialectFeature::BinaryNumbers, position);
+                    } else if text.ends_with("ll") {
+                        detected.add(DialectFeature::IntegerSuffixes, position);
+                    }
+                }
+            }
+
+            _ => {}
+        }
+
+        index += length;
+    }
+
+    detected
+        .evidence
+        .sort_by_key(|(_, position)| position.bytes());
+    detected
+}
+
+// The tokens of the code that aren't comments or whitespace, going past the characters that can't be tokenized
+// and keeping the features they're evidence of, which are the ones that are only single characters
+fn significant_tokens<'a>(code: &'a str, detected: &mut DetectedVersion) -> Vec<Token<'a>> {
+    let mut lexer = Lexer::new(code, true, false);
+    let mut tokens = Vec::new();
+
+    loop {
+        match lexer.next_token() {
+            Ok(Some(token)) => {
+                if !token.token_type().ignore() {
+                    tokens.push(token);
+                }
+            }
+
+            Ok(None) => break,
+
+            Err(error) => {
+                let character = match error.error() {
+                    TokenizerErrorType::UnexpectedToken(character) => *character,
+                    _ => break,
+                };
+
+                let start = error.position().bytes();
+                let mut end = start + character.len_utf8();
+                match character {
+                    '&' | '|' | '~' => {
+                        detected.add(DialectFeature::BitwiseOperators, error.position())
+                    }
+
+                    // The string is gone past as well, so its text isn't read as code
+                    '`' => {
+                        detected.add(DialectFeature::InterpolatedStrings, error.position());
+                        let mut characters = code[end..].char_indices();
+                        end = code.len();
+                        while let Some((offset, character)) = characters.next() {
+                            match character {
+                                '\\' => {
+                                    characters.next();
+                                }
+                                '`' => {
+                                    end = start + 1 + offset + 1;
+                                    break;
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+
+                    _ => {}
+                }
+
+                lexer.skip_to(end);
+            }
+        }
+    }
+
+    tokens
+}
+
+// Whether the token can start a value, such as the name or number after an operator
+fn starts_value(token: Option<&Token>) -> bool {
+    match token.map(|token| token.token_type()).as_deref() {
+        Some(TokenType::Identifier { .. })
+        | Some(TokenType::Number { .. })
+        | Some(TokenType::StringLiteral { .. }) => true,
+        Some(TokenType::Symbol { symbol }) => matches!(
+            symbol,
+            Symbol::LeftParen | Symbol::LeftBrace | Symbol::Minus | Symbol::Hash | Symbol::Not
+        ),
+        _ => false,
+    }
+}
@@ -0,0 +1,66 @@
+//! Conversions from errors to [`codespan-reporting`](https://docs.rs/codespan-reporting/0.11) diagnostics,
+//! for printing them to terminals with colors and the code they're in.
+//!
+//! ```rust
+//! use full_moon::diagnostics::{
+//!     self,
+//!     codespan_reporting::{files::SimpleFile, term::{self, termcolor::NoColor}},
+//! };
+//!
+//! let code = "call(1 2)";
+//! let error = full_moon::parse(code).unwrap_err();
+//!
+//! let mut output = NoColor::new(Vec::new());
+//! let diagnostic = diagnostics::diagnostic(&error, ());
+//! term::emit(&mut output, &term::Config::default(), &SimpleFile::new("main.lua", code), &diagnostic).unwrap();
+//! assert!(String::from_utf8(output.into_inner()).unwrap().contains("expected one of `,` or `)`"));
+//! ```
+
+use crate::{ast::AstError, tokenizer::TokenizerError, Error};
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+
+/// The version of `codespan-reporting` the diagnostics are made with, so that they can be printed
+/// without depending on the same version of it
+pub use codespan_reporting;
+
+/// Converts an error into an error diagnostic for the file the code was in.
+/// The primary label is under the code that caused the error, with what was expected there, such as
+/// `expected one of `,` or `)``. For errors in code that didn't close a block or brackets,
+/// a secondary label is under the token that opened them, such as `(`.
+/// Errors that aren't caused by any code, such as [`AstError::Empty`](../ast/enum.AstError.html#variant.Empty), have no labels.
+pub fn diagnostic<FileId: Copy>(error: &Error, file_id: FileId) -> Diagnostic<FileId> {
+    let description = error.describe();
+    let mut labels = Vec::new();
+
+    if let Some((start, end)) = description.range {
+        let label = Label::primary(file_id, start..end);
+        labels.push(match description.label {
+            Some(message) => label.with_message(message),
+            None => label,
+        });
+    }
+
+    if let Some((opening, (start, end))) = description.opening {
+        labels.push(
+            Label::secondary(file_id, start..end)
+                .with_message(format!("`{}` opened here", opening)),
+        );
+    }
+
+    Diagnostic::error()
+        .with_message(description.message)
+        .with_labels(labels)
+}
+
+/// Converts an [`AstError`](../ast/enum.AstError.html) into a diagnostic, the same as [`diagnostic`](fn.diagnostic.html)
+pub fn ast_error_diagnostic<FileId: Copy>(error: &AstError, file_id: FileId) -> Diagnostic<FileId> {
+    diagnostic(&Error::AstError(error.clone()), file_id)
+}
+
+/// Converts a [`TokenizerError`](../tokenizer/struct.TokenizerError.html) into a diagnostic, the same as [`diagnostic`](fn.diagnostic.html)
+pub fn tokenizer_error_diagnostic<FileId: Copy>(
+    error: &TokenizerError,
+    file_id: FileId,
+) -> Diagnostic<FileId> {
+    diagnostic(&Error::TokenizerError(error.clone()), file_id)
+}
@@ -0,0 +1,356 @@
+use crate::{
+    ast::Ast,
+    prelude::*,
+    tokenizer::{Position, Token},
+    visitors::{AnyNode, NodeKind},
+};
+
+// Past this many statement pairs in a block, statements are paired up in order instead of aligned
+const MAX_ALIGNMENT_SIZE: usize = 1_000_000;
+
+/// A node that differs between two [`Ast`](ast/struct.Ast.html)s, created by [`diff`](fn.diff.html)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffEntry {
+    kind: NodeKind,
+    old_range: (Position, Position),
+    replacement: String,
+}
+
+impl DiffEntry {
+    /// The kind of node that differs. For inserted statements, this is the kind of the new node.
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    /// The range in the old code to replace. For inserted statements, the start and end are the same.
+    pub fn old_range(&self) -> (Position, Position) {
+        self.old_range
+    }
+
+    /// The code from the new [`Ast`](ast/struct.Ast.html) to replace the old range with, including any comments
+    /// and whitespace inside of it. Empty for deleted statements.
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+}
+
+/// Finds the nodes that differ between two [`Ast`](ast/struct.Ast.html)s, such as before and after a transform,
+/// so that only the changed code has to be rewritten.
+///
+/// Both trees are walked in lockstep, only descending into nodes that aren't [similar](node/trait.Node.html#tymethod.similar),
+/// so changes to comments and whitespace alone are ignored. Each entry marks the smallest node that changed.
+/// Statements inserted into or deleted from a block get their own entries, which include the whitespace before them.
+///
+/// Replacement code is printed from the tokens of `new`, so its positions must be up to date,
+/// such as by using [`Ast::update_positions`](ast/struct.Ast.html#method.update_positions) after mutating it.
+/// Applying every entry to the old code, last to first, gives code that parses to a tree similar to `new`.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let old = full_moon::parse("local x = 1\nprint(x)")?;
+/// let new = full_moon::parse("local x = 2\nprint(x)")?;
+///
+/// let entries = full_moon::diff(&old, &new);
+/// assert_eq!(entries.len(), 1);
+/// assert_eq!(entries[0].kind(), full_moon::visitors::NodeKind::TokenReference);
+/// assert_eq!(entries[0].old_range().0.bytes(), 10);
+/// assert_eq!(entries[0].replacement(), "2");
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff<'a>(old: &Ast<'a>, new: &Ast<'a>) -> Vec<DiffEntry> {
+    let start = Position {
+        bytes: 0,
+        character: 1,
+        line: 1,
+    };
+
+    let mut differ = Differ {
+        new_tokens: new.iter_tokens().collect(),
+        entries: Vec::new(),
+    };
+
+    differ.diff(
+        AnyNode::Block(old.nodes()),
+        AnyNode::Block(new.nodes()),
+        start,
+        start,
+    );
+
+    differ.entries
+}
+
+struct Differ<'a, 'b> {
+    new_tokens: Vec<&'b Token<'a>>,
+    entries: Vec<DiffEntry>,
+}
+
+impl<'a, 'b> Differ<'a, 'b> {
+    // The code of `new` between two positions
+    fn text(&self, start: Position, end: Position) -> String {
+        let first = self
+            .new_tokens
+            .partition_point(|token| token.start_position().bytes() < start.bytes());
+
+        self.new_tokens[first..]
+            .iter()
+            .take_while(|token| token.end_position().bytes() <= end.bytes())
+            .map(|token| token.to_string())
+            .collect()
+    }
+
+    // The hints are where the nodes would be if they don't have a range, such as empty blocks
+    fn diff(
+        &mut self,
+        old: AnyNode<'a, '_>,
+        new: AnyNode<'a, '_>,
+        old_hint: Position,
+        new_hint: Position,
+    ) {
+        if old.similar(new) {
+            return;
+        }
+
+        if let (AnyNode::Block(_), AnyNode::Block(_)) = (old, new) {
+            let old_stmts = block_stmts(old);
+            let new_stmts = block_stmts(new);
+            return self.diff_stmts(&old_stmts, &new_stmts, old_hint, new_hint);
+        }
+
+        let (mut old_children, mut new_children) = (Vec::new(), Vec::new());
+        old.visit_children(&mut old_children);
+        new.visit_children(&mut new_children);
+
+        let same_shape = !old_children.is_empty()
+            && old_children.len() == new_children.len()
+            && old_children
+                .iter()
+                .zip(&new_children)
+                .all(|(old, new)| old.kind() == new.kind());
+
+        if !same_shape {
+            return self.replace(old, new, old_hint, new_hint);
+        }
+
+        let (mut old_hint, mut new_hint) = (old_hint, new_hint);
+        for (old_child, new_child) in old_children.into_iter().zip(new_children) {
+            self.diff(old_child, new_child, old_hint, new_hint);
+            old_hint = old_child.range().map_or(old_hint, |(_, end)| end);
+            new_hint = new_child.range().map_or(new_hint, |(_, end)| end);
+        }
+    }
+
+    fn replace(
+        &mut self,
+        old: AnyNode<'a, '_>,
+        new: AnyNode<'a, '_>,
+        old_hint: Position,
+        new_hint: Position,
+    ) {
+        let (new_start, new_end) = new.range().unwrap_or((new_hint, new_hint));
+        self.entries.push(DiffEntry {
+            kind: old.kind(),
+            old_range: old.range().unwrap_or((old_hint, old_hint)),
+            replacement: self.text(new_start, new_end),
+        });
+    }
+
+    fn diff_stmts(
+        &mut self,
+        old: &[Statement<'a, '_>],
+        new: &[Statement<'a, '_>],
+        mut old_hint: Position,
+        mut new_hint: Position,
+    ) {
+        let old_nodes: Vec<_> = old.iter().map(|stmt| stmt.node).collect();
+        let new_nodes: Vec<_> = new.iter().map(|stmt| stmt.node).collect();
+
+        for step in align(&old_nodes, &new_nodes) {
+            match step {
+                Step::Same(old_index, new_index) => {
+                    let (old, new) = (&old[old_index], &new[new_index]);
+                    if old.semicolon == new.semicolon {
+                        self.diff(old.node, new.node, old_hint, new_hint);
+                    } else if let (Some((old_start, _)), Some((new_start, _))) =
+                        (old.node.range(), new.node.range())
+                    {
+                        self.entries.push(DiffEntry {
+                            kind: old.node.kind(),
+                            old_range: (old_start, old.end.unwrap_or(old_start)),
+                            replacement: self.text(new_start, new.end.unwrap_or(new_start)),
+                        });
+                    }
+                    old_hint = old.end.unwrap_or(old_hint);
+                    new_hint = new.end.unwrap_or(new_hint);
+                }
+
+                Step::Delete(old_index) => {
+                    let old = &old[old_index];
+                    let end = old.end.unwrap_or(old_hint);
+                    self.entries.push(DiffEntry {
+                        kind: old.node.kind(),
+                        old_range: (old_hint, end),
+                        replacement: String::new(),
+                    });
+                    old_hint = end;
+                }
+
+                Step::Insert(new_index) => {
+                    let new = &new[new_index];
+                    let end = new.end.unwrap_or(new_hint);
+                    self.entries.push(DiffEntry {
+                        kind: new.node.kind(),
+                        old_range: (old_hint, old_hint),
+                        replacement: self.text(new_hint, end),
+                    });
+                    new_hint = end;
+                }
+            }
+        }
+    }
+}
+
+// A statement in a block, ending after its semicolon if it has one
+struct Statement<'a, 'b> {
+    node: AnyNode<'a, 'b>,
+    end: Option<Position>,
+    semicolon: bool,
+}
+
+fn block_stmts<'a, 'b>(block: AnyNode<'a, 'b>) -> Vec<Statement<'a, 'b>> {
+    let mut children = Vec::new();
+    block.visit_children(&mut children);
+
+    let mut stmts: Vec<Statement> = Vec::new();
+    for child in children {
+        match (child, stmts.last_mut()) {
+            (AnyNode::TokenReference(semicolon), Some(stmt)) => {
+                stmt.end = Some(semicolon.end_position());
+                stmt.semicolon = true;
+            }
+
+            _ => stmts.push(Statement {
+                node: child,
+                end: child.range().map(|(_, end)| end),
+                semicolon: false,
+            }),
+        }
+    }
+
+    stmts
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+    // Either similar, or a statement that changed in place
+    Same(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+// Aligns statements by their longest common subsequence of similar statements.
+// Between two similar statements, the rest are paired up in order if they are the same kind of node,
+// and are otherwise deleted or inserted.
+fn align<'a>(old: &[AnyNode<'a, '_>], new: &[AnyNode<'a, '_>]) -> Vec<Step> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old.similar(**new))
+        .count();
+
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old.similar(**new))
+        .count();
+
+    let (old_middle, new_middle) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut steps: Vec<_> = (0..prefix).map(|index| Step::Same(index, index)).collect();
+
+    let mut anchors = if old_middle.len() * new_middle.len() <= MAX_ALIGNMENT_SIZE {
+        longest_common_subsequence(old_middle, new_middle)
+    } else {
+        Vec::new()
+    };
+    anchors.push((old_middle.len(), new_middle.len()));
+
+    let (mut old_index, mut new_index) = (0, 0);
+    for (old_anchor, new_anchor) in anchors {
+        let old_run = old_anchor - old_index;
+        let new_run = new_anchor - new_index;
+
+        for offset in 0..old_run.max(new_run) {
+            let old_offset = Some(prefix + old_index + offset).filter(|_| offset < old_run);
+            let new_offset = Some(prefix + new_index + offset).filter(|_| offset < new_run);
+
+            match (old_offset, new_offset) {
+                (Some(old_offset), Some(new_offset))
+                    if old[old_offset].kind() == new[new_offset].kind() =>
+                {
+                    steps.push(Step::Same(old_offset, new_offset))
+                }
+
+                (old_offset, new_offset) => {
+                    steps.extend(old_offset.map(Step::Delete));
+                    steps.extend(new_offset.map(Step::Insert));
+                }
+            }
+        }
+
+        if old_anchor < old_middle.len() {
+            steps.push(Step::Same(prefix + old_anchor, prefix + new_anchor));
+        }
+
+        old_index = old_anchor + 1;
+        new_index = new_anchor + 1;
+    }
+
+    steps.extend(
+        (0..suffix).map(|index| Step::Same(old.len() - suffix + index, new.len() - suffix + index)),
+    );
+
+    steps
+}
+
+// The indices of every pair of similar statements in the longest common subsequence, in order
+fn longest_common_subsequence<'a>(
+    old: &[AnyNode<'a, '_>],
+    new: &[AnyNode<'a, '_>],
+) -> Vec<(usize, usize)> {
+    let width = new.len() + 1;
+    let mut lengths = vec![0; (old.len() + 1) * width];
+
+    for old_index in (0..old.len()).rev() {
+        for new_index in (0..new.len()).rev() {
+            lengths[old_index * width + new_index] = if old[old_index].similar(new[new_index]) {
+                lengths[(old_index + 1) * width + new_index + 1] + 1
+            } else {
+                lengths[(old_index + 1) * width + new_index]
+                    .max(lengths[old_index * width + new_index + 1])
+            };
+        }
+    }
+
+    let (mut old_index, mut new_index) = (0, 0);
+    let mut pairs = Vec::new();
+    while old_index < old.len() && new_index < new.len() {
+        if old[old_index].similar(new[new_index]) {
+            pairs.push((old_index, new_index));
+            old_index += 1;
+            new_index += 1;
+        } else if lengths[(old_index + 1) * width + new_index]
+            >= lengths[old_index * width + new_index + 1]
+        {
+            old_index += 1;
+        } else {
+            new_index += 1;
+        }
+    }
+
+    pairs
+}
@@ -0,0 +1,285 @@
+use crate::{
+    ast::{expected_message, reserved_keyword_message, AstError},
+    prelude::*,
+    tokenizer::{TokenType, TokenizerErrorType},
+    Error,
+};
+
+// Lines wider than this are cut down to the part around the error
+const MAX_LINE_WIDTH: usize = 100;
+const TAB_WIDTH: usize = 4;
+
+impl<'a> Error<'a> {
+    /// Renders the error with the code it's in, the way rustc does, for showing to people writing the code.
+    /// The `source` is the code that was parsed, and `filename` is put before the line and column if there is one.
+    ///
+    /// The line the error is on is printed with `^`s under the range of the error, along with what was expected there.
+    /// Errors over more than one line show the first and last lines with `...` between them.
+    /// Lines and columns start at 1, with columns counting characters like the tokenizer does,
+    /// and tabs are printed as 4 spaces so that the `^`s line up under them.
+    /// Lines wider than 100 columns are cut down to the part around the error, with `...` where they were cut.
+    ///
+    /// ```rust
+    /// let code = "local x = ]";
+    /// let error = full_moon::parse(code).unwrap_err();
+    /// assert_eq!(
+    ///     error.display_rich(code, Some("main.lua")),
+    ///     concat!(
+    ///         "error: unexpected token `]`\n",
+    ///         " --> main.lua:1:11\n",
+    ///         "  |\n",
+    ///         "1 | local x = ]\n",
+    ///         "  |           ^ expected an expression\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn display_rich(&self, source: &str, filename: Option<&str>) -> String {
+        let description = self.describe();
+        let range = match description.range {
+            Some(range) => range,
+            None => return format!("error: {}\n", description.message),
+        };
+
+        let note = description.opening.map(|(opening, (start, _))| {
+            let (line, column) = line_column(source, start);
+            format!("to close `{}` on line {}, column {}", opening, line, column)
+        });
+
+        render(
+            source,
+            filename,
+            &description.message,
+            range,
+            description.label,
+            note,
+        )
+    }
+
+    // The parts of the error that are shown in `display_rich` and diagnostics
+    pub(crate) fn describe(&self) -> Description {
+        match self {
+            Error::AstError(AstError::UnexpectedToken {
+                token,
+                additional,
+                opening,
+                expected,
+            }) => Description {
+                message: match &*token.token_type() {
+                    TokenType::Eof => "unexpected end of code".to_owned(),
+                    _ if token.to_string().contains('\n') => "unexpected token".to_owned(),
+                    _ => format!("unexpected token `{}`", token),
+                },
+
+                range: Some((token.start_position().bytes(), token.end_position().bytes())),
+
+                label: expected_message(expected)
+                    .or_else(|| additional.as_ref().map(|additional| additional.to_string())),
+
+                opening: opening.as_ref().map(|opening| {
+                    (
+                        opening.to_string(),
+                        (
+                            opening.start_position().bytes(),
+                            opening.end_position().bytes(),
+                        ),
+                    )
+                }),
+            },
+
+            Error::AstError(AstError::RecursionLimitExceeded { position, limit }) => Description {
+                message: self.message().to_owned(),
+                range: Some((position.bytes(), position.bytes())),
+                label: Some(format!("the recursion limit is {}", limit)),
+                opening: None,
+            },
+
+            Error::AstError(AstError::ReservedKeywordAsName { keyword, position }) => Description {
+                message: self.message().to_owned(),
+                range: Some((
+                    position.bytes(),
+                    position.bytes() + keyword.to_string().len(),
+                )),
+                label: Some(reserved_keyword_message(*keyword)),
+                opening: None,
+            },
+
+            Error::AstError(error) => Description {
+                message: error.message().to_owned(),
+                range: None,
+                label: None,
+                opening: None,
+            },
+
+            Error::TokensError { error, .. } => Error::AstError(error.clone()).describe(),
+
+            Error::TokenizerError(error) => {
+                let start = error.position().bytes();
+
+                // Unclosed strings and comments are at their first character, which is always a quote, `[`, or `-`
+                let width = match error.error() {
+                    TokenizerErrorType::UnexpectedToken(character) => character.len_utf8(),
+                    TokenizerErrorType::UnclosedComment | TokenizerErrorType::UnclosedString => 1,
+                    TokenizerErrorType::InvalidEscape { escape, .. } => escape.len(),
+                    TokenizerErrorType::MalformedNumber { text, .. } => text.len(),
+                };
+
+                Description {
+                    message: error.error().to_string(),
+                    range: Some((start, start + width)),
+                    label: match error.error() {
+                        TokenizerErrorType::MalformedNumber { reason, .. } => {
+                            Some(reason.to_string())
+                        }
+                        _ => None,
+                    },
+                    opening: None,
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct Description {
+    pub(crate) message: String,
+    // The byte range the error is at, None for errors that aren't caused by any code
+    pub(crate) range: Option<(usize, usize)>,
+    // What was expected instead, put next to the range
+    pub(crate) label: Option<String>,
+    // The code of the token that opened what wasn't closed, and its byte range
+    pub(crate) opening: Option<(String, (usize, usize))>,
+}
+
+fn render(
+    source: &str,
+    filename: Option<&str>,
+    message: &str,
+    (start, end): (usize, usize),
+    label: Option<String>,
+    note: Option<String>,
+) -> String {
+    let start = start.min(source.len());
+    let end = end.max(start).min(source.len());
+
+    // A range ending at the start of a line ends at the end of the line before it instead
+    let end = if end > start && source[..end].ends_with('\n') {
+        end - 1
+    } else {
+        end
+    };
+
+    let (start_line, start_column) = line_column(source, start);
+    let (end_line, end_column) = line_column(source, end);
+    let gutter = " ".repeat(end_line.to_string().len());
+    let label = label.map(|label| format!(" {}", label)).unwrap_or_default();
+
+    let mut output = String::new();
+    let mut push = |line: String| {
+        output.push_str(line.trim_end());
+        output.push('\n');
+    };
+
+    push(format!("error: {}", message));
+    push(match filename {
+        Some(filename) => format!("{}--> {}:{}:{}", gutter, filename, start_line, start_column),
+        None => format!("{}--> {}:{}", gutter, start_line, start_column),
+    });
+    push(format!("{} |", gutter));
+
+    let line_text = |line: usize| source.split('\n').nth(line - 1).unwrap_or_default();
+    let numbered = |line: usize| format!("{:>width$}", line, width = gutter.len());
+
+    if start_line == end_line {
+        let (text, underline) = snippet(line_text(start_line), start_column, end_column);
+        push(format!("{} | {}", numbered(start_line), text));
+        push(format!("{} | {}{}", gutter, underline, label));
+    } else {
+        let first = line_text(start_line);
+        let first_end = first.trim_end_matches('\r').chars().count() + 1;
+        let (text, underline) = snippet(first, start_column, first_end);
+        push(format!("{} | {}", numbered(start_line), text));
+        push(format!("{} | {}", gutter, underline));
+
+        if end_line > start_line + 1 {
+            push(format!("{}...", gutter));
+        }
+
+        let (text, underline) = snippet(line_text(end_line), 1, end_column);
+        push(format!("{} | {}", numbered(end_line), text));
+        push(format!("{} | {}{}", gutter, underline, label));
+    }
+
+    if let Some(note) = note {
+        push(format!("{} = note: {}", gutter, note));
+    }
+
+    output
+}
+
+// The line and column of the byte, both starting at 1, with columns counting every character as one
+fn line_column(source: &str, bytes: usize) -> (usize, usize) {
+    let before = &source[..bytes.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    (
+        bytecount::count(before.as_bytes(), b'\n') + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+// The line with tabs replaced by spaces, cut down if it's too wide, and the `^`s to put under it
+// from the start column up to, but not including, the end column. There's always at least one `^`.
+fn snippet(line: &str, start_column: usize, end_column: usize) -> (String, String) {
+    let line = line.trim_end_matches('\r');
+    let mut characters = Vec::new();
+    let (mut start, mut end) = (None, None);
+
+    for (column, character) in line.chars().chain(Some(' ')).enumerate() {
+        if column + 1 == start_column {
+            start = Some(characters.len());
+        }
+
+        if column + 1 == end_column {
+            end = Some(characters.len());
+        }
+
+        if character == '\t' {
+            characters.resize(characters.len() + TAB_WIDTH, ' ');
+        } else {
+            characters.push(character);
+        }
+    }
+
+    // The extra space is only there so the `^` can go after the end of the line
+    characters.pop();
+
+    let start = start.unwrap_or(characters.len());
+    let end = end.unwrap_or(characters.len()).max(start + 1);
+
+    let offset = if characters.len() > MAX_LINE_WIDTH {
+        start
+            .saturating_sub(MAX_LINE_WIDTH / 2)
+            .min(characters.len() - MAX_LINE_WIDTH)
+    } else {
+        0
+    };
+
+    let shown_end = (offset + MAX_LINE_WIDTH).min(characters.len());
+    let mut text = String::new();
+    let mut underline = String::new();
+
+    if offset > 0 {
+        text.push_str("...");
+        underline.push_str("   ");
+    }
+
+    text.extend(&characters[offset..shown_end]);
+    if shown_end < characters.len() {
+        text.push_str("...");
+    }
+
+    let underline_end = end.min(shown_end.max(start + 1));
+    underline.push_str(&" ".repeat(start - offset));
+    underline.push_str(&"^".repeat(underline_end - start));
+
+    (text, underline)
+}
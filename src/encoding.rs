@@ -0,0 +1,395 @@
+use crate::{
+    ast::owned::OwnedAst, line_index::LineIndex, parse_owned_with, prelude::*, print,
+    tokenizer::Position, Error, ParseConfig,
+};
+use core::{
+    convert::TryFrom,
+    fmt,
+    ops::{Deref, DerefMut, Range},
+    str,
+};
+
+// The characters of the bytes from 0x80 to 0x9F in Windows-1252. The five bytes it doesn't use are read
+// as the control characters with the same values, the same as Latin-1 and web browsers, so every byte can be read.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+/// An encoding of text that code can be read from and written back to, for [`parse_bytes_lossy`](fn.parse_bytes_lossy.html)
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TextEncoding {
+    /// UTF-8, which full_moon parses
+    Utf8,
+    /// Latin-1, also called ISO-8859-1, where every byte is the character with its value
+    Latin1,
+    /// Windows-1252, which is Latin-1 with printable characters such as `€` and `“` instead of
+    /// most of the control characters from 0x80 to 0x9F
+    Windows1252,
+}
+
+impl TextEncoding {
+    /// Reads the bytes as text in the encoding, or None if they aren't valid in it, which is only possible for UTF-8
+    ///
+    /// ```rust
+    /// # use full_moon::TextEncoding;
+    /// assert_eq!(TextEncoding::Latin1.decode(b"caf\xE9").unwrap(), "café");
+    /// assert_eq!(TextEncoding::Windows1252.decode(b"\x80 5").unwrap(), "€ 5");
+    /// assert_eq!(TextEncoding::Utf8.decode(b"caf\xE9"), None);
+    /// ```
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            TextEncoding::Utf8 => str::from_utf8(bytes).ok().map(ToOwned::to_owned),
+            TextEncoding::Latin1 => Some(bytes.iter().map(|byte| char::from(*byte)).collect()),
+            TextEncoding::Windows1252 => Some(
+                bytes
+                    .iter()
+                    .map(|byte| match byte {
+                        0x80..=0x9F => WINDOWS_1252[usize::from(byte - 0x80)],
+                        _ => char::from(*byte),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Writes the text in the encoding, the opposite of [`decode`](#method.decode)
+    ///
+    /// # Errors
+    /// An [`EncodeError`](struct.EncodeError.html) with the first character that the encoding doesn't have,
+    /// such as `€` in Latin-1.
+    ///
+    /// ```rust
+    /// # use full_moon::TextEncoding;
+    /// assert_eq!(TextEncoding::Windows1252.encode("€ 5").unwrap(), b"\x80 5");
+    ///
+    /// let error = TextEncoding::Latin1.encode("x = '€'").unwrap_err();
+    /// assert_eq!(error.character(), '€');
+    /// assert_eq!(error.position().bytes(), 5);
+    /// ```
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, EncodeError> {
+        let byte = |character: char| match self {
+            TextEncoding::Utf8 => unreachable!(),
+            TextEncoding::Latin1 => u8::try_from(u32::from(character)).ok(),
+            TextEncoding::Windows1252 => match u32::from(character) {
+                value @ (0..=0x7F | 0xA0..=0xFF) => Some(value as u8),
+                _ => WINDOWS_1252
+                    .iter()
+                    .position(|found| *found == character)
+                    .map(|index| 0x80 + index as u8),
+            },
+        };
+
+        if self == TextEncoding::Utf8 {
+            return Ok(text.as_bytes().to_vec());
+        }
+
+        text.char_indices()
+            .map(|(index, character)| {
+                byte(character).ok_or_else(|| EncodeError {
+                    character,
+                    position: LineIndex::new(text).character_position(index),
+                    encoding: self,
+                })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Latin1 => "Latin-1",
+            TextEncoding::Windows1252 => "Windows-1252",
+        })
+    }
+}
+
+/// An error given by [`TextEncoding::encode`](enum.TextEncoding.html#method.encode) for a character that
+/// can't be written in an encoding
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncodeError {
+    character: char,
+    position: Position,
+    encoding: TextEncoding,
+}
+
+impl EncodeError {
+    /// The first character that can't be written
+    pub fn character(&self) -> char {
+        self.character
+    }
+
+    /// Where the character is in the text that was written
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// The encoding the text was written in
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "character not in {} at line {}, character {}\nfound `{}`",
+            self.encoding,
+            self.position.line(),
+            self.position.character(),
+            self.character,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {}
+
+/// What [`parse_bytes_lossy`](fn.parse_bytes_lossy.html) does with code that isn't valid UTF-8
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Fallback {
+    /// Reads all of the code as Latin-1
+    Latin1,
+    /// Reads all of the code as Windows-1252, the default, which is what most code that isn't UTF-8 is written in
+    #[default]
+    Windows1252,
+    /// Reads the code as UTF-8, replacing the bytes that aren't valid with `�` (U+FFFD)
+    Replace,
+}
+
+/// The options of [`parse_bytes_lossy_with`](fn.parse_bytes_lossy_with.html)
+#[derive(Clone, Debug, Default)]
+pub struct LossyConfig {
+    /// What is done with code that isn't valid UTF-8. Code that is valid is always read as UTF-8.
+    pub fallback: Fallback,
+    /// How the decoded code is parsed
+    pub parse: ParseConfig,
+}
+
+/// Bytes that weren't valid UTF-8 and were replaced with `�` by [`Fallback::Replace`](enum.Fallback.html#variant.Replace)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replacement {
+    bytes: Range<usize>,
+    position: Position,
+}
+
+impl Replacement {
+    /// The bytes that were replaced, as a range of the bytes that were parsed
+    pub fn bytes(&self) -> Range<usize> {
+        self.bytes.clone()
+    }
+
+    /// Where the `�` they were replaced with is in the decoded code
+    pub fn position(&self) -> Position {
+        self.position
+    }
+}
+
+/// An [`OwnedAst`](ast/owned/struct.OwnedAst.html) parsed from bytes by [`parse_bytes_lossy`](fn.parse_bytes_lossy.html),
+/// along with how they were decoded, so that the code can be written back in the same encoding
+#[derive(Clone, Debug)]
+pub struct LossyAst {
+    ast: OwnedAst,
+    encoding: TextEncoding,
+    replacements: Vec<Replacement>,
+}
+
+impl LossyAst {
+    /// The encoding the code was read in, which is UTF-8 both when the code was valid UTF-8 and when bytes
+    /// that weren't were replaced
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+
+    /// The bytes that weren't valid UTF-8 and were replaced, in the order they're in, which is empty unless
+    /// the fallback is [`Fallback::Replace`](enum.Fallback.html#variant.Replace)
+    pub fn replacements(&self) -> &[Replacement] {
+        &self.replacements
+    }
+
+    /// Prints the Ast the same way as [`print`](fn.print.html) and writes the code in the encoding it was read in,
+    /// so rewriting code that isn't UTF-8 doesn't change its encoding. Replaced bytes can't be written back,
+    /// so code with [replacements](#method.replacements) is written with `�`s in their place.
+    ///
+    /// # Errors
+    /// An [`EncodeError`](struct.EncodeError.html) when the Ast was changed to have a character that the encoding
+    /// doesn't have.
+    pub fn print(&self) -> Result<Vec<u8>, EncodeError> {
+        self.encoding.encode(&print(&self.ast))
+    }
+
+    /// The Ast, without how it was decoded
+    pub fn into_ast(self) -> OwnedAst {
+        self.ast
+    }
+}
+
+impl Deref for LossyAst {
+    type Target = OwnedAst;
+
+    fn deref(&self) -> &Self::Target {
+        &self.ast
+    }
+}
+
+impl DerefMut for LossyAst {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.ast
+    }
+}
+
+/// Creates an [`OwnedAst`](ast/owned/struct.OwnedAst.html) from Lua code that might not be UTF-8, such as old code
+/// written in Latin-1 or Windows-1252 with characters like `é` in its strings and comments. Code that is valid UTF-8
+/// is parsed the same as with [`parse_owned`](fn.parse_owned.html), and other code is read as Windows-1252,
+/// which every byte can be read as. The [`LossyAst`](struct.LossyAst.html) it's in has the encoding it was read in,
+/// and [prints](struct.LossyAst.html#method.print) the code in it again.
+///
+/// # Errors
+/// The same as `parse_owned`, with positions in the decoded code.
+///
+/// ```rust
+/// # use full_moon::TextEncoding;
+/// let ast = full_moon::parse_bytes_lossy(b"print('caf\xE9') -- \x93quoted\x94").unwrap();
+/// assert_eq!(ast.encoding(), TextEncoding::Windows1252);
+/// assert_eq!(ast.source(), "print('café') -- “quoted”");
+/// assert_eq!(ast.print().unwrap(), b"print('caf\xE9') -- \x93quoted\x94");
+///
+/// let ast = full_moon::parse_bytes_lossy("print('café')".as_bytes()).unwrap();
+/// assert_eq!(ast.encoding(), TextEncoding::Utf8);
+/// ```
+pub fn parse_bytes_lossy(bytes: &[u8]) -> Result<LossyAst, Error<'static>> {
+    parse_bytes_lossy_with(bytes, &LossyConfig::default())
+}
+
+/// Creates an [`OwnedAst`](ast/owned/struct.OwnedAst.html) from Lua code that might not be UTF-8 like
+/// [`parse_bytes_lossy`](fn.parse_bytes_lossy.html), using the options of the [config](struct.LossyConfig.html).
+///
+/// # Errors
+/// The same as [`parse_owned_with`](fn.parse_owned_with.html).
+///
+/// ```rust
+/// # use full_moon::{Fallback, LossyConfig, TextEncoding};
+/// let config = LossyConfig {
+///     fallback: Fallback::Replace,
+///     ..LossyConfig::default()
+/// };
+///
+/// let ast = full_moon::parse_bytes_lossy_with(b"x = 1 -- n\xE9\nprint(x)", &config).unwrap();
+/// assert_eq!(ast.encoding(), TextEncoding::Utf8);
+/// assert_eq!(ast.source(), "x = 1 -- n\u{FFFD}\nprint(x)");
+/// assert_eq!(ast.replacements()[0].bytes(), 10..11);
+/// assert_eq!(ast.replacements()[0].position().character(), 11);
+/// ```
+pub fn parse_bytes_lossy_with(
+    bytes: &[u8],
+    config: &LossyConfig,
+) -> Result<LossyAst, Error<'static>> {
+    let mut replacements = Vec::new();
+    let (code, encoding) = match (str::from_utf8(bytes), config.fallback) {
+        (Ok(code), _) => (code.to_owned(), TextEncoding::Utf8),
+        (Err(_), Fallback::Latin1) => (decode(bytes, TextEncoding::Latin1), TextEncoding::Latin1),
+        (Err(_), Fallback::Windows1252) => (
+            decode(bytes, TextEncoding::Windows1252),
+            TextEncoding::Windows1252,
+        ),
+        (Err(_), Fallback::Replace) => {
+            let mut code = String::with_capacity(bytes.len());
+            let mut replaced = Vec::new();
+            let mut rest = bytes;
+            while let Err(error) = str::from_utf8(rest) {
+                let (valid, invalid) = rest.split_at(error.valid_up_to());
+                let length = error.error_len().unwrap_or(invalid.len());
+                let start = bytes.len() - rest.len() + valid.len();
+
+                code.push_str(str::from_utf8(valid).expect("the bytes are valid up to the error"));
+                replaced.push((start..start + length, code.len()));
+                code.push(char::REPLACEMENT_CHARACTER);
+                rest = &invalid[length..];
+            }
+            code.push_str(str::from_utf8(rest).expect("the rest of the bytes are valid"));
+
+            let lines = LineIndex::new(&code);
+            replacements = replaced
+                .into_iter()
+                .map(|(bytes, index)| Replacement {
+                    bytes,
+                    position: lines.character_position(index),
+                })
+                .collect();
+            (code, TextEncoding::Utf8)
+        }
+    };
+
+    Ok(LossyAst {
+        ast: parse_owned_with(code, &config.parse)?,
+        encoding,
+        replacements,
+    })
+}
+
+fn decode(bytes: &[u8], encoding: TextEncoding) -> String {
+    encoding
+        .decode(bytes)
+        .expect("every byte can be read in the encoding")
+}
+
+/// An error given by [`parse_file`](fn.parse_file.html). Only available with the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum FileError {
+    /// The file couldn't be read
+    Io(std::io::Error),
+    /// The code in the file couldn't be parsed
+    Parse(Error<'static>),
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for FileError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileError::Io(error) => error.fmt(formatter),
+            FileError::Parse(error) => error.fmt(formatter),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileError::Io(error) => Some(error),
+            FileError::Parse(error) => Some(error),
+        }
+    }
+}
+
+/// Reads a file of Lua code and parses it like [`parse_bytes_lossy`](fn.parse_bytes_lossy.html),
+/// so code that isn't UTF-8 can be parsed and written back in its encoding with
+/// [`LossyAst::print`](struct.LossyAst.html#method.print). Only available with the `std` feature.
+///
+/// # Errors
+/// [`FileError::Io`](enum.FileError.html#variant.Io) if the file couldn't be read,
+/// and [`FileError::Parse`](enum.FileError.html#variant.Parse) if it couldn't be parsed.
+#[cfg(feature = "std")]
+pub fn parse_file<P: AsRef<std::path::Path>>(path: P) -> Result<LossyAst, FileError> {
+    parse_file_with(path, &LossyConfig::default())
+}
+
+/// Reads a file of Lua code and parses it like [`parse_file`](fn.parse_file.html),
+/// using the options of the [config](struct.LossyConfig.html). Only available with the `std` feature.
+///
+/// # Errors
+/// The same as `parse_file`.
+#[cfg(feature = "std")]
+pub fn parse_file_with<P: AsRef<std::path::Path>>(
+    path: P,
+    config: &LossyConfig,
+) -> Result<LossyAst, FileError> {
+    let bytes = std::fs::read(path).map_err(FileError::Io)?;
+    parse_bytes_lossy_with(&bytes, config).map_err(FileError::Parse)
+}
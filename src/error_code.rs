@@ -0,0 +1,178 @@
+use core::{fmt, str::FromStr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+macro_rules! error_codes {
+    ($($(#[$meta:meta])* $ident:ident => $code:tt, $additional:expr,)+) => {
+        /// A stable identifier for the kind of an error, for linking to documentation or choosing which errors to ignore,
+        /// gotten with [`Error::code`](enum.Error.html#method.code).
+        ///
+        /// Every code has a string form such as `FM1002`, with `FM1` for tokenizer errors and `FM2` for AST errors.
+        /// A code always means the same thing: it is never given to a different kind of error,
+        /// even if the errors it was for are never made anymore. New kinds of errors get new codes.
+        ///
+        /// ```rust
+        /// # use full_moon::ErrorCode;
+        /// let error = full_moon::parse("if x then").unwrap_err();
+        /// assert_eq!(error.code(), ErrorCode::ExpectedEnd);
+        /// assert_eq!(error.code().as_str(), "FM2004");
+        /// assert_eq!("FM2004".parse(), Ok(ErrorCode::ExpectedEnd));
+        /// ```
+        #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+        pub enum ErrorCode {
+            $(
+                $(#[$meta])*
+                #[cfg_attr(feature = "serde", serde(rename = $code))]
+                $ident,
+            )+
+        }
+
+        impl ErrorCode {
+            /// Every error code, in the order of their string forms
+            pub fn all() -> &'static [ErrorCode] {
+                &[$(ErrorCode::$ident,)+]
+            }
+
+            /// The string form of the code, such as `FM1002`
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$ident => $code,)+
+                }
+            }
+
+            // The additional information of unexpected token errors with the code
+            pub(crate) fn additional(self) -> Option<&'static str> {
+                match self {
+                    $(ErrorCode::$ident => $additional,)+
+                }
+            }
+
+            // The most specific code of an unexpected token error with the additional information
+            pub(crate) fn from_additional(additional: Option<&str>) -> Self {
+                $(
+                    let code_additional: Option<&str> = $additional;
+                    if code_additional.is_some() && additional == code_additional {
+                        return ErrorCode::$ident;
+                    }
+                )+
+
+                ErrorCode::UnexpectedToken
+            }
+        }
+
+        impl FromStr for ErrorCode {
+            type Err = ();
+
+            fn from_str(string: &str) -> Result<Self, Self::Err> {
+                Ok(match string {
+                    $($code => ErrorCode::$ident,)+
+                    _ => return Err(()),
+                })
+            }
+        }
+    };
+}
+
+error_codes!(
+    /// A multi-line comment with no closing brackets
+    UnclosedComment => "FM1001", None,
+    /// A string with no closing quote or brackets
+    UnclosedString => "FM1002", None,
+    /// A character that can't start any token, such as `$`
+    UnexpectedCharacter => "FM1003", None,
+    /// An escape in a string that Lua can't read, such as `\300`
+    InvalidEscape => "FM1004", None,
+    /// A number that Lua can't read, such as `0x` or `1e`
+    MalformedNumber => "FM1005", None,
+
+    /// No tokens were given to create the AST, which shouldn't happen normally
+    EmptyTokens => "FM2001", None,
+    /// The tokens given to create the AST had no end of file token, which shouldn't happen normally
+    NoEof => "FM2002", None,
+    /// A token that isn't valid where it is, when none of the more specific codes apply
+    UnexpectedToken => "FM2003", None,
+    /// A block with no `end`, such as `if x then`
+    ExpectedEnd => "FM2004", Some("expected 'end'"),
+    /// A `while` or `for` loop with no `do`
+    ExpectedDo => "FM2005", Some("expected 'do'"),
+    /// An `if` or `elseif` with no `then`
+    ExpectedThen => "FM2006", Some("expected 'then'"),
+    /// A `repeat` with no `until`
+    ExpectedUntil => "FM2007", Some("expected 'until'"),
+    /// A generic `for` loop with no `in`
+    ExpectedIn => "FM2008", Some("expected 'in'"),
+    /// A numeric `for` loop with no `=`
+    ExpectedEquals => "FM2009", Some("expected '='"),
+    /// A function with no `(` before its parameters
+    ExpectedLeftParen => "FM2010", Some("expected '('"),
+    /// Parentheses with no `)`
+    ExpectedRightParen => "FM2011", Some("expected ')'"),
+    /// Square brackets with no `]`
+    ExpectedRightBracket => "FM2012", Some("expected ']'"),
+    /// A table with no `}`
+    ExpectedRightBrace => "FM2013", Some("expected '}'"),
+    /// A numeric `for` loop with no `,` after its start
+    ExpectedComma => "FM2014", Some("expected comma"),
+    /// A missing expression, such as in `local x =`
+    ExpectedExpression => "FM2015", Some("expected expression"),
+    /// An `if`, `elseif`, `while`, or `until` with no condition
+    ExpectedCondition => "FM2016", Some("expected condition"),
+    /// A table field with no value, such as `{ x = }`
+    ExpectedValue => "FM2017", Some("expected value"),
+    /// An assignment with no values, such as `x =`
+    ExpectedValues => "FM2018", Some("expected values"),
+    /// A missing block, such as a `do` that doesn't have one
+    ExpectedBlock => "FM2019", Some("expected block"),
+    /// A table field in square brackets with no key, such as `{ [] = 1 }`
+    ExpectedKey => "FM2020", Some("expected key"),
+    /// A missing name, such as after `.` or `local`
+    ExpectedName => "FM2021", Some("expected name"),
+    /// A `for` loop with no names
+    ExpectedNames => "FM2022", Some("expected names"),
+    /// A method call with no name after the `:`
+    ExpectedMethod => "FM2023", Some("expected method"),
+    /// A function name with no method name after the `:`
+    ExpectedMethodName => "FM2024", Some("expected method name"),
+    /// A method call with no arguments
+    ExpectedArgs => "FM2025", Some("expected args"),
+    /// A function call with no arguments between its parentheses
+    ExpectedArguments => "FM2026", Some("expected arguments"),
+    /// A `function` with no parameters or body
+    ExpectedFunctionBody => "FM2027", Some("expected function body"),
+    /// A `function` statement with no name
+    ExpectedFunctionName => "FM2028", Some("expected function name"),
+    /// A numeric `for` loop with no start, such as `for i = , 2 do`
+    ExpectedStartExpression => "FM2029", Some("expected start expression"),
+    /// A numeric `for` loop with no end, such as `for i = 1, do`
+    ExpectedEndExpression => "FM2030", Some("expected end expression"),
+    /// A numeric `for` loop with no step after its second `,`
+    ExpectedLimitExpression => "FM2031", Some("expected limit expression"),
+    /// A `return` with no values
+    ExpectedReturnValues => "FM2032", Some("return values"),
+    /// A `,` at the end of a list that can't end with one
+    TrailingCharacter => "FM2033", Some("trailing character"),
+    /// A token after the end of the code, such as an `end` outside of any block
+    LeftoverToken => "FM2034", Some("leftover token"),
+    /// Code nested more deeply than the recursion limit, such as thousands of `(`s
+    RecursionLimitExceeded => "FM2035", None,
+    /// Parsing was stopped with a [`CancellationToken`](struct.CancellationToken.html) before it was done
+    Cancelled => "FM2036", None,
+    /// A keyword where there has to be a name, such as `local end = 1`
+    ReservedKeywordAsName => "FM2037", None,
+    /// An end of file token that isn't the last of the tokens passed to [`parse_tokens`](fn.parse_tokens.html)
+    EofBeforeEnd => "FM2038", None,
+    /// A token passed to [`parse_tokens`](fn.parse_tokens.html) that starts before the token before it ends
+    TokenOutOfOrder => "FM2039", None,
+    /// The id of a node that isn't a token passed to [`Ast::replace_token_text`](ast/struct.Ast.html#method.replace_token_text)
+    NotAToken => "FM2040", None,
+    /// An id passed to [`Ast::replace_token_text`](ast/struct.Ast.html#method.replace_token_text)
+    /// that isn't of anything in the Ast, such as a token of another Ast
+    TokenNotInAst => "FM2041", None,
+);
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
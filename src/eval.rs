@@ -0,0 +1,356 @@
+use crate::{
+    ast::{simplified, Ast, Expression, Field, LastStmt, TableConstructor, UnOp, Value},
+    node::Node,
+    tokenizer::{Position, Symbol, TokenType},
+    transform::{evaluate, Constant},
+};
+use std::fmt;
+
+/// The options for [`literal_value_with`](fn.literal_value_with.html) and [`return_value_with`](fn.return_value_with.html).
+/// Use [`EvalConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EvalConfig {
+    /// Whether to evaluate operations on literals, such as `60 * 60` or `"a" .. "b"`, the same ones
+    /// [`fold_constants`](../transform/fn.fold_constants.html) does. False by default, so an operator is
+    /// only a literal in `-` of a number, which is how negative numbers are written.
+    pub fold_operators: bool,
+}
+
+/// A value made only of literals and tables, from [`literal_value`](fn.literal_value.html),
+/// owned so it can outlive the code it came from
+#[derive(Clone, Debug, PartialEq)]
+pub enum LuaValue {
+    /// `nil`
+    Nil,
+    /// `true` or `false`
+    Bool(bool),
+    /// A number, which is always a float, the same as in Lua 5.1
+    Number(f64),
+    /// A string, with its escapes decoded. Lua strings are bytes, so this might not be UTF-8.
+    String(Vec<u8>),
+    /// A table
+    Table(LuaTable),
+}
+
+impl LuaValue {
+    /// The value of a boolean, or None if this is something else
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            LuaValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of a number, or None if this is something else
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            LuaValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of a string, or None if this is something else or isn't UTF-8
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            LuaValue::String(bytes) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+
+    /// The table, or None if this is something else
+    pub fn as_table(&self) -> Option<&LuaTable> {
+        match self {
+            LuaValue::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    fn from_constant(constant: Constant) -> Self {
+        match constant {
+            Constant::Nil => LuaValue::Nil,
+            Constant::Boolean(value) => LuaValue::Bool(value),
+            Constant::Number(value) => LuaValue::Number(value),
+            Constant::String(bytes) => LuaValue::String(bytes),
+        }
+    }
+}
+
+/// The fields of a table constructor, from [`LuaValue::Table`](enum.LuaValue.html#variant.Table)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LuaTable {
+    array: Vec<LuaValue>,
+    map: Vec<(LuaValue, LuaValue)>,
+}
+
+impl LuaTable {
+    /// The fields without keys, such as `1` and `2` in `{ 1, x = 3, 2 }`, in order, so the first is at the key 1.
+    /// `nil`s are kept, so every field is at the key it has in Lua.
+    pub fn array(&self) -> &[LuaValue] {
+        &self.array
+    }
+
+    /// The fields with keys, such as `x = 3` or `[10] = 4`, in the order they were first written in.
+    /// A key given more than once has the last value it was given, and fields set to `nil` aren't included,
+    /// the same as in Lua. Keys are kept as they're written, so `[1] = x` is here even though it is
+    /// the same key as the first field of [`array`](#method.array).
+    pub fn map(&self) -> &[(LuaValue, LuaValue)] {
+        &self.map
+    }
+
+    /// The value of a field with a key, looking in [`array`](#method.array) for whole numbers,
+    /// or None if there isn't one
+    pub fn get(&self, key: &LuaValue) -> Option<&LuaValue> {
+        if let LuaValue::Number(number) = key {
+            if number.fract() == 0.0 && *number >= 1.0 && *number <= self.array.len() as f64 {
+                return Some(&self.array[*number as usize - 1]);
+            }
+        }
+
+        self.map
+            .iter()
+            .find(|(other, _)| !matches!(other, LuaValue::Table(_)) && other == key)
+            .map(|(_, value)| value)
+    }
+
+    /// The value of a field with a string key, such as `name` in `{ name = "x" }`, or None if there isn't one
+    pub fn field(&self, name: &str) -> Option<&LuaValue> {
+        self.get(&LuaValue::String(name.as_bytes().to_vec()))
+    }
+
+    fn insert(&mut self, key: LuaValue, value: LuaValue) {
+        // Tables are only equal to themselves, and two constructors are two tables, so they're always new keys
+        let existing = match key {
+            LuaValue::Table(_) => None,
+            _ => self.map.iter().position(|(other, _)| *other == key),
+        };
+
+        match (existing, value) {
+            (Some(index), LuaValue::Nil) => {
+                self.map.remove(index);
+            }
+            (Some(index), value) => self.map[index].1 = value,
+            (None, LuaValue::Nil) => {}
+            (None, value) => self.map.push((key, value)),
+        }
+    }
+}
+
+/// An error given by [`literal_value`](fn.literal_value.html) when the expression isn't made only of literals
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotLiteral {
+    range: (Position, Position),
+}
+
+impl NotLiteral {
+    /// The start and end of the first part of the expression that isn't a literal, such as `x` in `{ 1, x }`,
+    /// an operation that wasn't evaluated, or a key of a table that Lua can't set, `nil` or NaN
+    pub fn range(&self) -> (Position, Position) {
+        self.range
+    }
+
+    fn new(node: &impl Node) -> Self {
+        Self {
+            range: node.range().unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for NotLiteral {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let (start, _) = self.range;
+        write!(
+            formatter,
+            "the expression at line {}, character {} isn't a literal",
+            start.line(),
+            start.character()
+        )
+    }
+}
+
+impl std::error::Error for NotLiteral {}
+
+/// The value of an expression made only of literals and table constructors, such as `{ name = "x", items = { 1, 2 } }`,
+/// using [`EvalConfig::default()`](struct.EvalConfig.html), for reading Lua files of data without running them.
+/// Names, calls, functions, and `...` aren't literals, and neither are operators other than `-` of a number.
+///
+/// ```rust
+/// # use full_moon::eval::{literal_value, LuaValue};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let ast = full_moon::parse("return { name = \"x\", items = { 1, -2 } }, { value = x }")?;
+/// let returns = ast.nodes().last_stmts().unwrap();
+/// let mut expressions = match returns {
+///     full_moon::ast::LastStmt::Return(returns) => returns.returns().iter(),
+///     _ => unreachable!(),
+/// };
+///
+/// let value = literal_value(expressions.next().unwrap())?;
+/// let table = value.as_table().unwrap();
+/// assert_eq!(table.field("name").and_then(LuaValue::as_str), Some("x"));
+/// let items = table.field("items").and_then(LuaValue::as_table).unwrap();
+/// assert_eq!(items.array(), &[LuaValue::Number(1.0), LuaValue::Number(-2.0)]);
+///
+/// // `x` isn't a literal
+/// let error = literal_value(expressions.next().unwrap()).unwrap_err();
+/// assert_eq!(error.range().0.character(), 53);
+/// # Ok(())
+/// # }
+/// ```
+pub fn literal_value(expression: &Expression) -> Result<LuaValue, NotLiteral> {
+    literal_value_with(expression, &EvalConfig::default())
+}
+
+/// The same as [`literal_value`](fn.literal_value.html), but with options, such as evaluating operations on literals
+pub fn literal_value_with(
+    expression: &Expression,
+    config: &EvalConfig,
+) -> Result<LuaValue, NotLiteral> {
+    match expression {
+        Expression::Parentheses { expression, .. } => literal_value_with(expression, config),
+
+        Expression::Value {
+            value, binop: None, ..
+        } => value_of(value, config),
+
+        // Negative numbers are written with a unary `-`, since there are no literals for them
+        Expression::UnaryOperator {
+            unop: UnOp::Minus(_),
+            expression: operand,
+            ..
+        } if !config.fold_operators => match &**operand {
+            Expression::Value {
+                value, binop: None, ..
+            } => match &**value {
+                Value::Number(_) => match value_of(value, config)? {
+                    LuaValue::Number(number) => Ok(LuaValue::Number(-number)),
+                    _ => unreachable!("numbers are numbers"),
+                },
+                _ => Err(NotLiteral::new(expression)),
+            },
+            _ => Err(NotLiteral::new(expression)),
+        },
+
+        Expression::UnaryOperator { .. } | Expression::Value { .. } if config.fold_operators => {
+            match evaluate(expression) {
+                Some(constant) => Ok(LuaValue::from_constant(constant)),
+                None => Err(first_not_literal(expression, config)
+                    .unwrap_or_else(|| NotLiteral::new(expression))),
+            }
+        }
+
+        _ => Err(NotLiteral::new(expression)),
+    }
+}
+
+/// The value the Ast returns, if it ends with a `return`, made only of literals the same way as
+/// [`literal_value`](fn.literal_value.html), such as for a config file of `return { name = "x" }`.
+/// Using [`EvalConfig::default()`](struct.EvalConfig.html).
+///
+/// Only the first value returned is evaluated, since that's the only one `require` gives,
+/// and the statements before the `return` aren't run. Gives `Ok(None)` if nothing is returned.
+///
+/// ```rust
+/// # use full_moon::eval::{return_value, LuaValue};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let ast = full_moon::parse("-- settings\nreturn {\n    debug = false,\n    ports = { 80, 443 },\n}")?;
+/// let value = return_value(&ast)?.unwrap();
+/// let table = value.as_table().unwrap();
+/// assert_eq!(table.field("debug"), Some(&LuaValue::Bool(false)));
+/// assert_eq!(table.field("ports").and_then(LuaValue::as_table).unwrap().array().len(), 2);
+///
+/// assert_eq!(return_value(&full_moon::parse("local x = 1")?)?, None);
+/// # Ok(())
+/// # }
+/// ```
+pub fn return_value(ast: &Ast) -> Result<Option<LuaValue>, NotLiteral> {
+    return_value_with(ast, &EvalConfig::default())
+}
+
+/// The same as [`return_value`](fn.return_value.html), but with options, such as evaluating operations on literals
+pub fn return_value_with(ast: &Ast, config: &EvalConfig) -> Result<Option<LuaValue>, NotLiteral> {
+    match ast.nodes().last_stmts() {
+        Some(LastStmt::Return(returns)) => returns
+            .returns()
+            .iter()
+            .next()
+            .map(|expression| literal_value_with(expression, config))
+            .transpose(),
+        _ => Ok(None),
+    }
+}
+
+fn value_of(value: &Value, config: &EvalConfig) -> Result<LuaValue, NotLiteral> {
+    match value {
+        Value::ParseExpression(expression) => literal_value_with(expression, config),
+
+        Value::Number(token) => simplified::number(&token.to_string())
+            .map(LuaValue::Number)
+            .ok_or_else(|| NotLiteral::new(value)),
+
+        // Strings with escapes that are too large, which only parse_fallible allows, have no value
+        Value::String(token) => simplified::string(token)
+            .map(LuaValue::String)
+            .ok_or_else(|| NotLiteral::new(value)),
+
+        Value::Symbol(token) => match &*token.token_type() {
+            TokenType::Symbol {
+                symbol: Symbol::Nil,
+            } => Ok(LuaValue::Nil),
+            TokenType::Symbol {
+                symbol: Symbol::True,
+            } => Ok(LuaValue::Bool(true)),
+            TokenType::Symbol {
+                symbol: Symbol::False,
+            } => Ok(LuaValue::Bool(false)),
+            _ => Err(NotLiteral::new(value)),
+        },
+
+        Value::TableConstructor(table) => table_of(table, config).map(LuaValue::Table),
+
+        Value::Function(_) | Value::FunctionCall(_) | Value::Var(_) => Err(NotLiteral::new(value)),
+    }
+}
+
+fn table_of(table: &TableConstructor, config: &EvalConfig) -> Result<LuaTable, NotLiteral> {
+    let mut result = LuaTable::default();
+
+    for (field, _) in table.iter_fields() {
+        match field {
+            Field::NoKey(value) => result.array.push(literal_value_with(value, config)?),
+
+            Field::NameKey { key, value, .. } => {
+                let value = literal_value_with(value, config)?;
+                result.insert(LuaValue::String(key.to_string().into_bytes()), value);
+            }
+
+            Field::ExpressionKey { key, value, .. } => {
+                let key_value = literal_value_with(key, config)?;
+                match key_value {
+                    LuaValue::Nil => return Err(NotLiteral::new(key)),
+                    LuaValue::Number(number) if number.is_nan() => {
+                        return Err(NotLiteral::new(key))
+                    }
+                    _ => {}
+                }
+
+                let value = literal_value_with(value, config)?;
+                result.insert(key_value, value);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+// The first operand of an operation that isn't a literal, or None if they all are,
+// in which case the operation itself can't be evaluated, such as `1 + "x"` or `#{}`
+fn first_not_literal(expression: &Expression, config: &EvalConfig) -> Option<NotLiteral> {
+    match expression {
+        Expression::Value { value, binop, .. } => match value_of(value, config) {
+            Err(error) => Some(error),
+            Ok(_) => first_not_literal(&binop.as_ref()?.rhs, config),
+        },
+        Expression::UnaryOperator { expression, .. } => first_not_literal(expression, config),
+        Expression::Parentheses { expression, .. } => literal_value_with(expression, config).err(),
+        Expression::Error(_) => Some(NotLiteral::new(expression)),
+    }
+}
@@ -0,0 +1,204 @@
+//! The functions are `extern "C"` and never unwind, every panic is caught and turned into an error.
+//! To build a library for C, use `cargo rustc --release --features ffi --crate-type cdylib`, or `staticlib`.
+//! The header, `include/full_moon.h`, is made by [cbindgen](https://github.com/eqrion/cbindgen) with
+//! `cbindgen --config cbindgen.toml --output include/full_moon.h`, so run it again when the functions change.
+//!
+//! Everything the functions give back has to be freed with the `fullmoon_free_*` function for it:
+//! Asts with [`fullmoon_free_ast`](fn.fullmoon_free_ast.html), and the code, JSON, and error messages
+//! with [`fullmoon_free_buffer`](fn.fullmoon_free_buffer.html).
+//!
+//! ```c
+//! const char *code = "local x = 1";
+//! FullMoonAst *ast = fullmoon_parse(code, strlen(code));
+//! if (ast == NULL) {
+//!     FullMoonBuffer error = fullmoon_last_error();
+//!     fprintf(stderr, "%s\n", error.data);
+//!     fullmoon_free_buffer(error);
+//!     return 1;
+//! }
+//!
+//! FullMoonBuffer printed = fullmoon_print(ast);
+//! fwrite(printed.data, 1, printed.len, stdout);
+//! fullmoon_free_buffer(printed);
+//! fullmoon_free_ast(ast);
+//! ```
+use crate::{ast::owned::OwnedAst, JsonConfig};
+use std::{
+    any::Any,
+    cell::RefCell,
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+/// An Ast made by [`fullmoon_parse`](fn.fullmoon_parse.html), which owns its code.
+/// It is opaque to C, which only has pointers to it.
+#[derive(Debug)]
+pub struct FullMoonAst(OwnedAst);
+
+/// Text made by full_moon, which is UTF-8 and owned by full_moon until it's given to
+/// [`fullmoon_free_buffer`](fn.fullmoon_free_buffer.html).
+/// The data is null when there's no text, such as when there's no error.
+#[repr(C)]
+#[derive(Debug)]
+pub struct FullMoonBuffer {
+    /// The bytes of the text, followed by a nul byte that isn't part of the length, so they can be used as a C string
+    /// when the text has no nul bytes in it
+    pub data: *mut c_char,
+    /// How many bytes of text there are, not counting the nul byte at the end
+    pub len: usize,
+}
+
+impl FullMoonBuffer {
+    fn null() -> Self {
+        Self {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn new(text: String) -> Self {
+        let len = text.len();
+        let mut bytes = text.into_bytes();
+        bytes.push(0);
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut c_char;
+        Self { data, len }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: Option<String>) {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = error);
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+// Runs the function without letting a panic unwind into C, which is undefined behavior,
+// giving back the value for errors instead. The last error is cleared first, so it is always from the last call.
+fn catch<T>(error_value: impl FnOnce() -> T, function: impl FnOnce() -> Result<T, String>) -> T {
+    set_last_error(None);
+    let error = match panic::catch_unwind(AssertUnwindSafe(function)) {
+        Ok(Ok(value)) => return value,
+        Ok(Err(error)) => error,
+        Err(payload) => format!("full_moon panicked: {}", panic_message(&*payload)),
+    };
+
+    set_last_error(Some(error));
+    error_value()
+}
+
+/// Parses the code, which is `len` bytes of UTF-8 that don't have to end with a nul byte.
+/// Returns the Ast, or null if the code couldn't be parsed, with the reason in
+/// [`fullmoon_last_error`](fn.fullmoon_last_error.html). The Ast has to be freed with
+/// [`fullmoon_free_ast`](fn.fullmoon_free_ast.html).
+///
+/// # Safety
+/// `src` has to point to at least `len` bytes that can be read, and can only be null if `len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn fullmoon_parse(src: *const c_char, len: usize) -> *mut FullMoonAst {
+    catch(ptr::null_mut, || {
+        let bytes = if src.is_null() {
+            if len != 0 {
+                return Err("the code is null".to_owned());
+            }
+
+            &[]
+        } else {
+            slice::from_raw_parts(src as *const u8, len)
+        };
+
+        let code = std::str::from_utf8(bytes)
+            .map_err(|error| format!("the code isn't valid UTF-8: {}", error))?;
+        let ast = crate::parse_owned(code.to_owned()).map_err(|error| error.to_string())?;
+        Ok(Box::into_raw(Box::new(FullMoonAst(ast))))
+    })
+}
+
+/// The error of the last function that failed on this thread, with where it is in the code for parse errors, such as
+/// `unexpected token at line 1, character 11`. The data is null if the last function called on this thread, other
+/// than this one, didn't fail. The message has to be freed with [`fullmoon_free_buffer`](fn.fullmoon_free_buffer.html).
+#[no_mangle]
+pub extern "C" fn fullmoon_last_error() -> FullMoonBuffer {
+    panic::catch_unwind(|| {
+        LAST_ERROR.with(|last_error| match &*last_error.borrow() {
+            Some(error) => FullMoonBuffer::new(error.clone()),
+            None => FullMoonBuffer::null(),
+        })
+    })
+    .unwrap_or_else(|_| FullMoonBuffer::null())
+}
+
+// The Ast behind the handle, or an error if it is null
+unsafe fn ast<'a>(handle: *const FullMoonAst) -> Result<&'a OwnedAst, String> {
+    handle
+        .as_ref()
+        .map(|handle| &handle.0)
+        .ok_or_else(|| "the Ast is null".to_owned())
+}
+
+/// The code of the Ast, which is the same as the code it was parsed from.
+/// The data is null if the Ast is null, with the reason in [`fullmoon_last_error`](fn.fullmoon_last_error.html).
+/// The code has to be freed with [`fullmoon_free_buffer`](fn.fullmoon_free_buffer.html).
+///
+/// # Safety
+/// `handle` has to be null or an Ast from [`fullmoon_parse`](fn.fullmoon_parse.html) that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fullmoon_print(handle: *const FullMoonAst) -> FullMoonBuffer {
+    catch(FullMoonBuffer::null, || {
+        Ok(FullMoonBuffer::new(crate::print(ast(handle)?)))
+    })
+}
+
+/// The Ast as JSON, the same as [`Ast::to_json`](../ast/struct.Ast.html#method.to_json) with the default
+/// [`JsonConfig`](../struct.JsonConfig.html). The data is null if the Ast is null, with the reason in
+/// [`fullmoon_last_error`](fn.fullmoon_last_error.html).
+/// The JSON has to be freed with [`fullmoon_free_buffer`](fn.fullmoon_free_buffer.html).
+///
+/// # Safety
+/// `handle` has to be null or an Ast from [`fullmoon_parse`](fn.fullmoon_parse.html) that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fullmoon_to_json(handle: *const FullMoonAst) -> FullMoonBuffer {
+    catch(FullMoonBuffer::null, || {
+        Ok(FullMoonBuffer::new(
+            ast(handle)?.to_json(&JsonConfig::default()),
+        ))
+    })
+}
+
+/// Frees an Ast from [`fullmoon_parse`](fn.fullmoon_parse.html). Does nothing if it is null.
+///
+/// # Safety
+/// `handle` has to be null or an Ast from `fullmoon_parse` that hasn't been freed, and it can't be used after this.
+#[no_mangle]
+pub unsafe extern "C" fn fullmoon_free_ast(handle: *mut FullMoonAst) {
+    if !handle.is_null() {
+        let ast = Box::from_raw(handle);
+        let _ = panic::catch_unwind(AssertUnwindSafe(move || drop(ast)));
+    }
+}
+
+/// Frees text from a full_moon function, such as [`fullmoon_print`](fn.fullmoon_print.html).
+/// Does nothing if its data is null.
+///
+/// # Safety
+/// `buffer` has to be from a full_moon function, unchanged, and not freed already. Its data can't be used after this.
+#[no_mangle]
+pub unsafe extern "C" fn fullmoon_free_buffer(buffer: FullMoonBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data as *mut u8,
+            buffer.len + 1,
+        )));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{
+    ast::printer::print_pretty,
+    prelude::*,
+    tokenizer::{self, Token, TokenKind},
+    Error, PrettyConfig,
+};
+
+/// A change to make to code, created by [`format_edits`](fn.format_edits.html) and
+/// [`refactor::rename_local`](refactor/fn.rename_local.html)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    range: (usize, usize),
+    replacement: String,
+}
+
+impl TextEdit {
+    #[cfg(feature = "std")]
+    pub(crate) fn new(range: (usize, usize), replacement: String) -> Self {
+        Self { range, replacement }
+    }
+
+    /// The start and end bytes of the code to replace. For insertions, the start and end are the same.
+    pub fn range(&self) -> (usize, usize) {
+        self.range
+    }
+
+    /// The code to replace the range with. Empty for deletions.
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+}
+
+/// The edits that turn code into what [`print_pretty`](fn.print_pretty.html) would print for it,
+/// so that formatting can be checked or applied without rewriting all of the code.
+///
+/// The whitespace between every pair of tokens is compared with the whitespace between them when pretty printed,
+/// and only the parts that differ are replaced, along with comments that change, such as by losing a `\r`.
+/// The edits are in order and don't overlap, so applying every one of them, last to first, gives the pretty printed code.
+/// Code that is already formatted has no edits.
+///
+/// # Errors
+/// The same as [`parse`](fn.parse.html), if the code can't be parsed.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let edits = full_moon::format_edits("local x=1\nprint(x)\n", &full_moon::PrettyConfig::default())?;
+/// assert_eq!(edits.len(), 2);
+/// assert_eq!(edits[0].range(), (7, 7));
+/// assert_eq!(edits[0].replacement(), " ");
+/// assert_eq!(edits[1].range(), (8, 8));
+/// # Ok(())
+/// # }
+/// ```
+pub fn format_edits<'a>(
+    source: &'a str,
+    config: &PrettyConfig,
+) -> Result<Vec<TextEdit>, Error<'a>> {
+    let ast = crate::parse(source)?;
+    let pretty = print_pretty(&ast, config);
+
+    let mut edits = Vec::new();
+    let original_ranges = token_ranges(ast.iter_tokens());
+    let pretty_ranges = tokenizer::tokens(&pretty)
+        .ok()
+        .map(|tokens| token_ranges(tokens.iter()))
+        .filter(|ranges| ranges.len() == original_ranges.len());
+
+    // The printer never adds or removes tokens other than whitespace, but if it did, the code is replaced at once
+    let pretty_ranges = match pretty_ranges {
+        Some(pretty_ranges) => pretty_ranges,
+        None => {
+            push_edit(&mut edits, source, (0, source.len()), &pretty);
+            return Ok(edits);
+        }
+    };
+
+    let (mut original_end, mut pretty_end) = (0, 0);
+    for (original, pretty_range) in original_ranges.into_iter().zip(pretty_ranges) {
+        push_edit(
+            &mut edits,
+            source,
+            (original_end, original.0),
+            &pretty[pretty_end..pretty_range.0],
+        );
+        push_edit(
+            &mut edits,
+            source,
+            original,
+            &pretty[pretty_range.0..pretty_range.1],
+        );
+        original_end = original.1;
+        pretty_end = pretty_range.1;
+    }
+
+    push_edit(
+        &mut edits,
+        source,
+        (original_end, source.len()),
+        &pretty[pretty_end..],
+    );
+
+    Ok(edits)
+}
+
+// The byte ranges of every token that isn't whitespace, in order
+fn token_ranges<'a: 'b, 'b>(tokens: impl Iterator<Item = &'b Token<'a>>) -> Vec<(usize, usize)> {
+    tokens
+        .filter(|token| !matches!(token.token_kind(), TokenKind::Whitespace | TokenKind::Eof))
+        .map(|token| (token.start_position().bytes(), token.end_position().bytes()))
+        .collect()
+}
+
+// Adds an edit replacing the range of the source with the replacement, leaving out what they start and end with in common
+fn push_edit(edits: &mut Vec<TextEdit>, source: &str, range: (usize, usize), replacement: &str) {
+    let original = &source[range.0..range.1];
+    if original == replacement {
+        return;
+    }
+
+    let prefix: usize = original
+        .chars()
+        .zip(replacement.chars())
+        .take_while(|(original, replacement)| original == replacement)
+        .map(|(character, _)| character.len_utf8())
+        .sum();
+
+    let suffix: usize = original[prefix..]
+        .chars()
+        .rev()
+        .zip(replacement[prefix..].chars().rev())
+        .take_while(|(original, replacement)| original == replacement)
+        .map(|(character, _)| character.len_utf8())
+        .sum();
+
+    edits.push(TextEdit {
+        range: (range.0 + prefix, range.1 - suffix),
+        replacement: replacement[prefix..replacement.len() - suffix].to_owned(),
+    });
+}
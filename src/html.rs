@@ -0,0 +1,157 @@
+use crate::{
+    ast::Ast,
+    tokenizer::{Symbol, TokenType},
+};
+use std::fmt::Write;
+
+/// The options for [`render_html`](fn.render_html.html).
+/// Use [`HtmlConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HtmlConfig {
+    /// The prefix of the ids of empty `<a>` anchors put at the start of every line, followed by the line number,
+    /// so that `Some("L".to_owned())` puts `<a id="L1"></a>` at the start of the first line. None by default,
+    /// which doesn't put any anchors. Code ending with a new line has no anchor after it.
+    pub line_anchors: Option<String>,
+}
+
+/// Renders the code of an [`Ast`](ast/struct.Ast.html) as HTML with every token highlighted by how it was tokenized,
+/// so long strings and comments are highlighted exactly as far as they go.
+///
+/// Every token is put in a `<span>` with one of these classes, which will not change:
+///
+/// | Class           | Tokens                                                             |
+/// |-----------------|--------------------------------------------------------------------|
+/// | `fm-keyword`    | Keywords, such as `local`, `end`, and `and`                        |
+/// | `fm-constant`   | `true`, `false`, and `nil`                                         |
+/// | `fm-operator`   | Every other symbol, such as `+`, `..`, `=`, `,`, and brackets      |
+/// | `fm-identifier` | Names, such as `print`                                             |
+/// | `fm-number`     | Numbers, such as `0x10`                                            |
+/// | `fm-string`     | Strings, including the quotes or brackets around them              |
+/// | `fm-comment`    | Single line and multi-line comments, including the `--` before them |
+///
+/// Whitespace is kept as it is outside of any span, so the output should be put in a `<pre>` element.
+/// The code is HTML-escaped, and otherwise printed the same as [`print`](fn.print.html).
+///
+/// Only available with the `html` feature.
+///
+/// ```rust
+/// # use full_moon::HtmlConfig;
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let ast = full_moon::parse("local s = [[<b>]] -- s")?;
+/// assert_eq!(
+///     full_moon::render_html(&ast, &HtmlConfig::default()),
+///     concat!(
+///         r#"<span class="fm-keyword">local</span> <span class="fm-identifier">s</span> "#,
+///         r#"<span class="fm-operator">=</span> <span class="fm-string">[[&lt;b&gt;]]</span> "#,
+///         r#"<span class="fm-comment">-- s</span>"#,
+///     ),
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn render_html(ast: &Ast, config: &HtmlConfig) -> String {
+    let mut renderer = Renderer {
+        output: String::new(),
+        line: 1,
+        line_start: true,
+        line_anchors: config.line_anchors.as_deref(),
+    };
+
+    for token in ast.iter_tokens() {
+        let class = match &*token.token_type() {
+            TokenType::Eof | TokenType::Whitespace { .. } => None,
+            TokenType::Identifier { .. } => Some("fm-identifier"),
+            TokenType::Number { .. } => Some("fm-number"),
+            TokenType::StringLiteral { .. } => Some("fm-string"),
+            TokenType::SingleLineComment { .. } | TokenType::MultiLineComment { .. } => {
+                Some("fm-comment")
+            }
+            TokenType::Symbol { symbol } => Some(symbol_class(*symbol)),
+        };
+
+        let text = token.to_string();
+        match class {
+            Some(class) if !text.is_empty() => {
+                renderer.anchor();
+                write!(renderer.output, r#"<span class="{}">"#, class)
+                    .expect("writing to a string can't fail");
+                renderer.text(&text);
+                renderer.output.push_str("</span>");
+            }
+
+            _ => renderer.text(&text),
+        }
+    }
+
+    renderer.output
+}
+
+fn symbol_class(symbol: Symbol) -> &'static str {
+    match symbol {
+        Symbol::True | Symbol::False | Symbol::Nil => "fm-constant",
+        _ if symbol.to_string().chars().all(|c| c.is_ascii_alphabetic()) => "fm-keyword",
+        _ => "fm-operator",
+    }
+}
+
+struct Renderer<'b> {
+    output: String,
+    line: usize,
+    // Whether nothing has been printed on the current line yet
+    line_start: bool,
+    line_anchors: Option<&'b str>,
+}
+
+impl Renderer<'_> {
+    fn anchor(&mut self) {
+        if !self.line_start {
+            return;
+        }
+
+        self.line_start = false;
+        if let Some(prefix) = self.line_anchors {
+            write!(
+                self.output,
+                r#"<a id="{}{}"></a>"#,
+                escape(prefix),
+                self.line
+            )
+            .expect("writing to a string can't fail");
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        for character in text.chars() {
+            self.anchor();
+            match character {
+                '\n' => {
+                    self.output.push('\n');
+                    self.line += 1;
+                    self.line_start = true;
+                }
+
+                _ => push_escaped(&mut self.output, character),
+            }
+        }
+    }
+}
+
+fn push_escaped(output: &mut String, character: char) {
+    match character {
+        '&' => output.push_str("&amp;"),
+        '<' => output.push_str("&lt;"),
+        '>' => output.push_str("&gt;"),
+        '"' => output.push_str("&quot;"),
+        '\'' => output.push_str("&#39;"),
+        _ => output.push(character),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut output = String::new();
+    for character in text.chars() {
+        push_escaped(&mut output, character);
+    }
+
+    output
+}
@@ -0,0 +1,245 @@
+use crate::{
+    ast::{
+        punctuated::{Pair, Punctuated},
+        Ast,
+    },
+    tokenizer::{Position, Token, TokenKind, TokenReference},
+};
+use serde_json::{json, Map, Value};
+use std::{borrow::Cow, collections::BTreeMap};
+
+/// The version of the schema of [`Ast::to_json`](ast/struct.Ast.html#method.to_json),
+/// which is changed whenever the output changes in a way that could break a reader of it
+pub const JSON_SCHEMA_VERSION: u64 = 1;
+
+/// The options for [`Ast::to_json`](ast/struct.Ast.html#method.to_json).
+/// Use [`JsonConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonConfig {
+    /// Whether tokens have their `leading_trivia` and `trailing_trivia`, true by default.
+    /// Without them, the comments and whitespace of the code aren't in the output at all.
+    pub trivia: bool,
+    /// Whether nodes, tokens, and trivia have their `range`, true by default
+    pub positions: bool,
+    /// Whether the JSON is indented over multiple lines, false by default
+    pub pretty: bool,
+}
+
+impl Default for JsonConfig {
+    fn default() -> Self {
+        Self {
+            trivia: true,
+            positions: true,
+            pretty: false,
+        }
+    }
+}
+
+// The fields of a node, by name for structs and enum variants with named fields, and in order for the rest
+pub(crate) enum Children {
+    Named(Vec<(&'static str, Value)>),
+    Unnamed(Vec<Value>),
+}
+
+// Implemented for every node with `#[derive(Json)]`, along with the types in their fields
+pub(crate) trait ToJson<'a> {
+    fn to_json(&self, json: &JsonWriter<'a, '_>) -> Value;
+}
+
+pub(crate) struct JsonWriter<'a, 'b> {
+    config: &'b JsonConfig,
+    // The comments and whitespace before and after each token, by its offset in the arena of the Ast
+    trivia: BTreeMap<usize, (Vec<&'b Token<'a>>, Vec<&'b Token<'a>>)>,
+}
+
+impl<'a, 'b> JsonWriter<'a, 'b> {
+    fn new(ast: &'b Ast<'a>, config: &'b JsonConfig) -> Self {
+        Self {
+            config,
+            trivia: ast.trivia_by_token(),
+        }
+    }
+
+    pub(crate) fn node(
+        &self,
+        kind: &str,
+        variant: Option<&str>,
+        range: Option<(Position, Position)>,
+        children: Children,
+    ) -> Value {
+        let mut object = Map::new();
+        object.insert("kind".to_owned(), kind.into());
+        if let Some(variant) = variant {
+            object.insert("variant".to_owned(), variant.into());
+        }
+
+        self.insert_range(&mut object, range);
+        object.insert(
+            "children".to_owned(),
+            match children {
+                Children::Named(children) => Value::Object(
+                    children
+                        .into_iter()
+                        .map(|(name, child)| (name.to_owned(), child))
+                        .collect(),
+                ),
+                Children::Unnamed(children) => Value::Array(children),
+            },
+        );
+
+        Value::Object(object)
+    }
+
+    fn token(&self, token: &TokenReference<'a>) -> Value {
+        let mut object = token_object("Token", token);
+        self.insert_range(
+            &mut object,
+            Some((token.start_position(), token.end_position())),
+        );
+
+        if self.config.trivia {
+            let empty = (Vec::new(), Vec::new());
+            let (leading, trailing) = token
+                .arena_offset()
+                .and_then(|(_, offset)| self.trivia.get(&offset))
+                .unwrap_or(&empty);
+
+            object.insert("leading_trivia".to_owned(), self.trivia(leading));
+            object.insert("trailing_trivia".to_owned(), self.trivia(trailing));
+        }
+
+        Value::Object(object)
+    }
+
+    fn trivia(&self, tokens: &[&Token<'a>]) -> Value {
+        tokens
+            .iter()
+            .map(|token| {
+                let mut object = token_object("Trivia", token);
+                self.insert_range(
+                    &mut object,
+                    Some((token.start_position(), token.end_position())),
+                );
+                Value::Object(object)
+            })
+            .collect()
+    }
+
+    fn insert_range(&self, object: &mut Map<String, Value>, range: Option<(Position, Position)>) {
+        if self.config.positions {
+            object.insert(
+                "range".to_owned(),
+                match range {
+                    Some((start, end)) => json!({
+                        "start": position(start),
+                        "end": position(end),
+                    }),
+                    None => Value::Null,
+                },
+            );
+        }
+    }
+}
+
+fn token_object(kind: &str, token: &Token) -> Map<String, Value> {
+    let token_kind = match token.token_kind() {
+        TokenKind::Eof => "Eof",
+        TokenKind::Identifier => "Identifier",
+        TokenKind::MultiLineComment => "MultiLineComment",
+        TokenKind::Number => "Number",
+        TokenKind::SingleLineComment => "SingleLineComment",
+        TokenKind::StringLiteral => "StringLiteral",
+        TokenKind::Symbol => "Symbol",
+        TokenKind::Whitespace => "Whitespace",
+    };
+
+    let mut object = Map::new();
+    object.insert("kind".to_owned(), kind.into());
+    object.insert("token_kind".to_owned(), token_kind.into());
+    object.insert("text".to_owned(), token.to_string().into());
+    object
+}
+
+fn position(position: Position) -> Value {
+    json!({
+        "bytes": position.bytes(),
+        "line": position.line(),
+        "character": position.character(),
+    })
+}
+
+pub(crate) fn ast_to_json(ast: &Ast, config: &JsonConfig) -> String {
+    let json = JsonWriter::new(ast, config);
+    let value = json!({
+        "schema": JSON_SCHEMA_VERSION,
+        "block": ast.nodes().to_json(&json),
+        "eof": ast.eof().to_json(&json),
+    });
+
+    if config.pretty {
+        serde_json::to_string_pretty(&value)
+    } else {
+        serde_json::to_string(&value)
+    }
+    .expect("a json value can always be serialized")
+}
+
+impl<'a> ToJson<'a> for TokenReference<'a> {
+    fn to_json(&self, json: &JsonWriter<'a, '_>) -> Value {
+        json.token(self)
+    }
+}
+
+impl<'a, T: ToJson<'a>> ToJson<'a> for &T {
+    fn to_json(&self, json: &JsonWriter<'a, '_>) -> Value {
+        (**self).to_json(json)
+    }
+}
+
+impl<'a, T: ToJson<'a>> ToJson<'a> for Box<T> {
+    fn to_json(&self, json: &JsonWriter<'a, '_>) -> Value {
+        (**self).to_json(json)
+    }
+}
+
+impl<'a, T: Clone + ToJson<'a>> ToJson<'a> for Cow<'a, T> {
+    fn to_json(&self, json: &JsonWriter<'a, '_>) -> Value {
+        (**self).to_json(json)
+    }
+}
+
+impl<'a, T: ToJson<'a>> ToJson<'a> for Option<T> {
+    fn to_json(&self, json: &JsonWriter<'a, '_>) -> Value {
+        match self {
+            Some(value) => value.to_json(json),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<'a, T: ToJson<'a>> ToJson<'a> for Vec<T> {
+    fn to_json(&self, json: &JsonWriter<'a, '_>) -> Value {
+        self.iter().map(|value| value.to_json(json)).collect()
+    }
+}
+
+impl<'a, A: ToJson<'a>, B: ToJson<'a>> ToJson<'a> for (A, B) {
+    fn to_json(&self, json: &JsonWriter<'a, '_>) -> Value {
+        Value::Array(vec![self.0.to_json(json), self.1.to_json(json)])
+    }
+}
+
+impl<'a, T: ToJson<'a>> ToJson<'a> for Punctuated<'a, T> {
+    fn to_json(&self, json: &JsonWriter<'a, '_>) -> Value {
+        self.pairs().map(|pair| pair.to_json(json)).collect()
+    }
+}
+
+impl<'a, T: ToJson<'a>> ToJson<'a> for Pair<'a, T> {
+    fn to_json(&self, json: &JsonWriter<'a, '_>) -> Value {
+        json!({
+            "value": self.value().to_json(json),
+            "punctuation": self.punctuation().to_json(json),
+        })
+    }
+}
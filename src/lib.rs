@@ -1,54 +1,278 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
 #![allow(clippy::large_enum_variant)]
 //! # Full Moon
 //!
 //! `full_moon` is a lossless parser for Lua 5.1
 //! Learn more by going to [the repository](https://github.com/Kampfkarren/full-moon)
+//!
+//! ## `no_std`
+//!
+//! Tokenizing, parsing, the nodes of Asts, visitors, and printing only need `alloc`, so they work in `#![no_std]`
+//! programs, such as ones running in WebAssembly sandboxes or on embedded hosts, with the default `std` feature
+//! turned off. Without it, errors only implement `Display` and `Debug`, since `std::error::Error` is part of `std`,
+//! and the modules that need the standard library aren't available: [`analysis`](analysis/index.html),
+//! [`eval`](eval/index.html), [`lsp`](lsp/index.html), [`refactor`](refactor/index.html),
+//! [`transform`](transform/index.html), and the `arbitrary`, `binary`, `diagnostics`, `ffi`, `html`, `json`, `rayon`,
+//! and `source-map` features, which turn on `std` themselves.
+//!
+//! ## Tracing
+//!
+//! With the `tracing` feature, the parser tells [`tracing`](https://docs.rs/tracing) what it's doing, for finding out
+//! why code doesn't parse the way it should. Each production, such as a block, a statement, an expression,
+//! or a suffix of a call, is a span at the trace level named after its parser, such as `ParseLocalAssignment`,
+//! with the `index` of the token it starts at and the `bytes` of that token. Each ends with an event saying whether
+//! it matched, didn't match, which the parser goes back from to try something else, or failed with an error,
+//! and making an error is an event at the debug level with what was expected. With a subscriber such as the one of
+//! `tracing-subscriber`, `RUST_LOG=full_moon=trace` shows every production that was tried. Without the feature,
+//! none of this is compiled in, so parsing is exactly as fast as it is otherwise.
+
+#[macro_use]
+extern crate alloc;
+
+/// Analyses of what code does without running it, such as which local every name refers to.
+/// Only available with the `std` feature.
+#[cfg(feature = "std")]
+pub mod analysis;
 
 /// Utilities for ASTs (Abstract Syntax Trees). Contains all nodes used by Full Moon (such as blocks).
 pub mod ast;
 
+/// Output for looking at the structure of Asts while debugging, such as [`to_dot`](debug/fn.to_dot.html)
+/// to draw them as graphs.
+pub mod debug;
+
+/// The values of expressions made only of literals, such as the tables of Lua files used for data,
+/// through [`literal_value`](eval/fn.literal_value.html). Only available with the `std` feature.
+#[cfg(feature = "std")]
+pub mod eval;
+
+/// Conversions from errors to diagnostics for printing them, only available with the `diagnostics` feature.
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+
+/// The C interface of full_moon, for parsing and printing code from other languages,
+/// only available with the `ffi` feature.
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+/// Outlines of code for language servers, in the same shape as the structures of the
+/// [Language Server Protocol](https://microsoft.github.io/language-server-protocol/), such as
+/// [`document_symbols`](lsp/fn.document_symbols.html), and the encoding of
+/// [`semantic_tokens_data`](lsp/fn.semantic_tokens_data.html). Only available with the `std` feature.
+#[cfg(feature = "std")]
+pub mod lsp;
+
 /// Contains the `Node` trait, implemented on all nodes
 pub mod node;
 
+/// The values that can be interpolated into [`lua_quote!`](macro.lua_quote.html) and the nodes it can make.
+pub mod quote;
+
+/// Refactorings that change the code of an [`Ast`](ast/struct.Ast.html) without changing what it does,
+/// such as [`rename_local`](refactor/fn.rename_local.html). Only available with the `std` feature.
+#[cfg(feature = "std")]
+pub mod refactor;
+
 /// Used for tokenizing, the process of converting the code to individual tokens.
 /// Useful for getting symbols and manually tokenizing without going using an AST.
 pub mod tokenizer;
 
+/// Transformations that rewrite the code of nodes in place, such as their comments, whitespace, and strings.
+/// Only available with the `std` feature.
+#[cfg(feature = "std")]
+pub mod transform;
+
 /// Used to create visitors that recurse through [`Ast`](ast/struct.Ast.html) nodes.
 pub mod visitors;
 
+#[cfg(feature = "binary")]
+mod binary;
+mod detect_version;
+mod diff;
+mod display_rich;
+mod encoding;
+mod error_code;
+mod format_edits;
+#[cfg(feature = "html")]
+mod html;
+#[cfg(feature = "json")]
+mod json;
+mod line_index;
+mod parser;
 mod private;
+mod round_trip;
+mod source_map;
+
+// The names of the prelude of `std` that aren't in the prelude of `core`, so that code that only needs `alloc`
+// can use them the same way with and without the `std` feature
+mod prelude {
+    pub(crate) use alloc::{
+        borrow::ToOwned,
+        boxed::Box,
+        string::{String, ToString},
+        vec::Vec,
+    };
+}
+
+/// Builds a node from Lua code written in Rust, with `#name` to interpolate a variable holding a
+/// [token or node](quote/trait.Interpolate.html), such as the name of a local or an expression.
+/// The node is a [`Stmt`](ast/enum.Stmt.html), [`Expression`](ast/enum.Expression.html),
+/// [`Block`](ast/struct.Block.html), [`LastStmt`](ast/enum.LastStmt.html), or [`Ast`](ast/struct.Ast.html),
+/// whichever is expected where the macro is used, all with the `'static` lifetime. Its code is printed normally,
+/// with one space between tokens where Lua needs or usually has one, and the nodes can be printed with
+/// [`Node::print`](node/trait.Node.html#method.print) and interpolated into other code.
+///
+/// Since the code is read by Rust first, it has a few differences from Lua, which are errors when compiling:
+/// - `#` interpolates, so the length operator is written as `##`, such as `##list`
+/// - Comments are Rust comments, since `--` would be two minuses to Rust, and `- -x` is two minuses
+/// - Strings are double quoted, or single quoted with one character, and escapes are Rust's, which are
+///   the same as the most common ones in Lua, such as `\n`. Long strings are read as brackets.
+///
+/// The code is parsed when the macro runs, since the parser can't run while compiling the crate it's in,
+/// so code that isn't valid Lua or isn't the node that is expected, such as two statements for a `Stmt`, panics.
+///
+/// ```rust
+/// use full_moon::{ast::{Expression, Stmt}, lua_quote, node::{Node, TriviaBehavior}};
+///
+/// let value: Expression = lua_quote!(1 + 2);
+/// let stmt: Stmt = lua_quote! {
+///     local total = #value * ##list
+/// };
+/// assert_eq!(stmt.print(TriviaBehavior::Trim), "local total = (1 + 2) * #list");
+///
+/// let ast = full_moon::parse("local x = 1").unwrap();
+/// let name = match ast.nodes().iter_stmts().next() {
+///     Some(Stmt::LocalAssignment(local)) => local.name_list().iter().next().unwrap(),
+///     _ => unreachable!(),
+/// };
+/// let call: Stmt = lua_quote!(print(#name, t:method(...), { a = 1 }));
+/// assert_eq!(call.print(TriviaBehavior::Trim), "print(x, t:method(...), { a = 1 })");
+/// ```
+///
+/// Interpolating anything other than a token or node, and code that can't be Lua, are errors when compiling:
+///
+/// ```compile_fail
+/// # use full_moon::{ast::Stmt, lua_quote};
+/// let count = 1;
+/// let stmt: Stmt = lua_quote!(local x = #count);
+/// ```
+///
+/// ```compile_fail
+/// # use full_moon::{ast::Stmt, lua_quote};
+/// let stmt: Stmt = lua_quote!(local x = 1 -- comment);
+/// ```
+pub use full_moon_derive::lua_quote;
+
+pub use ast::printer::{
+    print_minified, print_pretty, print_with, Indent, LineEnding, PrettyConfig, PrintConfig,
+};
+pub use ast::{CancellationToken, ParseConfig};
+#[cfg(feature = "binary")]
+pub use binary::{BinaryError, BINARY_FORMAT_VERSION};
+pub use detect_version::{detect_version, DetectedVersion, DialectFeature, LuaVersion};
+pub use diff::{diff, DiffEntry};
+pub use encoding::{
+    parse_bytes_lossy, parse_bytes_lossy_with, EncodeError, Fallback, LossyAst, LossyConfig,
+    Replacement, TextEncoding,
+};
+#[cfg(feature = "std")]
+pub use encoding::{parse_file, parse_file_with, FileError};
+pub use error_code::ErrorCode;
+pub use format_edits::{format_edits, TextEdit};
+#[cfg(feature = "html")]
+pub use html::{render_html, HtmlConfig};
+#[cfg(feature = "json")]
+pub use json::{JsonConfig, JSON_SCHEMA_VERSION};
+pub use line_index::{convert_position, LineIndex, PositionEncoding};
+pub use parser::Parser;
+pub use round_trip::{
+    verify_round_trip, verify_round_trip_reparse, RoundTripFailure, RoundTripMismatch,
+};
+pub use source_map::{print_with_source_map, SourceMap, SourceMapping};
 
-use full_moon_derive::Owned;
-use std::fmt;
+use crate::prelude::*;
+use core::fmt;
 
 #[cfg(all(test, not(feature = "serde")))]
 compile_error!("Serde feature must be enabled for tests");
 
 /// An error type that consists of both [`AstError`](ast/enum.AstError.html) and [`TokenizerError`](tokenizer/enum.TokenizerError.html)
 /// Used by [`parse`](fn.parse)
-#[derive(Clone, Debug, PartialEq, Owned)]
+///
+/// The first line of the `Display` output is always the [`message`](#method.message), followed by
+/// ` at line L, character C` with the [`position`](#method.position) if there is one.
+/// Any lines after it give more details, such as `found `]`` with the code that caused the error,
+/// or what was expected instead, and may change between versions.
+/// The errors it consists of are displayed the same way, and it's displayed as the one in it,
+/// so it has no `source`, which would repeat its message.
+///
+/// ```rust
+/// let error = full_moon::parse("local x = ]").unwrap_err();
+/// assert_eq!(error.message(), "unexpected token");
+/// assert_eq!(error.position().unwrap().bytes(), 10);
+/// assert_eq!(
+///     error.to_string().lines().next(),
+///     Some("unexpected token at line 1, character 11"),
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
 pub enum Error<'a> {
     /// Triggered if there's an issue creating an AST, but tokenizing must have succeeded
     AstError(ast::AstError<'a>),
     /// Triggered if there's an issue when tokenizing, and an AST can't be made
     TokenizerError(tokenizer::TokenizerError),
+    /// Triggered by [`parse_tokens`](fn.parse_tokens.html) if there's an issue creating an AST at one of the tokens
+    TokensError {
+        /// The index of the token that caused the error among the tokens that were passed
+        index: usize,
+        /// The error, as it would be from [`Ast::from_tokens`](ast/struct.Ast.html#method.from_tokens)
+        error: ast::AstError<'a>,
+    },
+}
+
+impl<'a> Error<'a> {
+    /// The stable code of the kind of error, for linking to documentation or choosing which errors to ignore
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::AstError(error) | Error::TokensError { error, .. } => error.code(),
+            Error::TokenizerError(error) => error.code(),
+        }
+    }
+
+    /// What went wrong, without the code that caused it or where it is, such as `unexpected token` or `unclosed string`
+    pub fn message(&self) -> &str {
+        match self {
+            Error::AstError(error) | Error::TokensError { error, .. } => error.message(),
+            Error::TokenizerError(error) => error.message(),
+        }
+    }
+
+    /// The position of the start of the code that caused the error.
+    /// None for errors that aren't caused by any code, such as [`AstError::Empty`](ast/enum.AstError.html#variant.Empty)
+    pub fn position(&self) -> Option<tokenizer::Position> {
+        match self {
+            Error::AstError(error) | Error::TokensError { error, .. } => {
+                error.range().map(|(start, _)| start)
+            }
+            Error::TokenizerError(error) => Some(error.position()),
+        }
+    }
 }
 
 impl<'a> fmt::Display for Error<'a> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Error::AstError(error) => {
-                write!(formatter, "error occurred while creating ast: {}", error)
-            }
-            Error::TokenizerError(error) => {
-                write!(formatter, "error occurred while tokenizing: {}", error)
+            Error::AstError(error) => error.fmt(formatter),
+            Error::TokenizerError(error) => error.fmt(formatter),
+            Error::TokensError { index, error } => {
+                write!(formatter, "{}\nat index {} of the tokens", error, index)
             }
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl<'a> std::error::Error for Error<'a> {}
 
 /// Creates an [`Ast`](ast/struct.Ast.html) from Lua code
@@ -57,18 +281,465 @@ impl<'a> std::error::Error for Error<'a> {}
 /// If the code passed cannot be tokenized, a TokenizerError will be returned.
 /// If the code passed is not valid Lua 5.1 code, an AstError will be returned,
 /// specifically AstError::UnexpectedToken.
+/// The default [`ParseConfig`](struct.ParseConfig.html) is used, so code that is nested too deeply gives
+/// AstError::RecursionLimitExceeded instead of overflowing the stack.
+/// Only the first error is returned: use [`parse_fallible`](fn.parse_fallible.html) for every error along with an Ast.
 ///
 /// ```rust
 /// assert!(full_moon::parse("local x = 1").is_ok());
 /// assert!(full_moon::parse("local x = ").is_err());
 /// ```
 pub fn parse(code: &str) -> Result<ast::Ast, Error> {
-    let tokens = tokenizer::tokens(code).map_err(Error::TokenizerError)?;
-    ast::Ast::from_tokens(tokens).map_err(Error::AstError)
+    parse_with(code, &ParseConfig::default())
 }
 
-/// Prints back Lua code from an [Ast](ast/struct.Ast.html)
+/// Creates an [`Ast`](ast/struct.Ast.html) from Lua code like [`parse`](fn.parse.html),
+/// using the options of the [config](struct.ParseConfig.html).
+///
+/// # Errors
+/// The same as `parse`, with AstError::RecursionLimitExceeded for code nested more deeply than the recursion limit
+/// or the expression depth limit, and AstError::Cancelled if the [cancellation token](struct.CancellationToken.html) is cancelled while parsing.
+/// Only parsing checks the token: the code is always tokenized first, which takes much less time.
+///
+/// This is [`parse_fallible`](fn.parse_fallible.html) with the first error in the code returned instead of the Ast,
+/// so both give the same Ast for valid code.
+///
+/// ```rust
+/// # use full_moon::{ast::AstError, Error, ParseConfig};
+/// let config = ParseConfig {
+///     recursion_limit: 10,
+///     ..ParseConfig::default()
+/// };
+///
+/// assert!(full_moon::parse_with("do do end end", &config).is_ok());
+/// assert!(matches!(
+///     full_moon::parse_with(&format!("{}{}", "do ".repeat(10), "end ".repeat(10)), &config),
+///     Err(Error::AstError(AstError::RecursionLimitExceeded { limit: 10, .. })),
+/// ));
+/// ```
+pub fn parse_with<'a>(code: &'a str, config: &ParseConfig) -> Result<ast::Ast<'a>, Error<'a>> {
+    // Only the first error is returned, so there's no need to find more
+    let config = ParseConfig {
+        max_errors: 1,
+        ..config.clone()
+    };
+
+    parse_fallible(code, &config)
+        .into_result()
+        .map_err(|mut errors| errors.remove(0))
+}
+
+/// Creates an [`Ast`](ast/struct.Ast.html) from Lua code like [`parse`](fn.parse.html), with room for this many tokens
+/// from the start, for when how many there are is known, such as from the size of the file.
+/// Code with more tokens still parses, the same as with `parse`. Use a [`Parser`](struct.Parser.html) to parse many files.
+///
+/// # Errors
+/// The same as `parse`.
+///
+/// ```rust
+/// let code = "local x = 1";
+/// let ast = full_moon::parse_with_capacity(code, code.len() / 2).unwrap();
+/// assert_eq!(ast.nodes(), full_moon::parse(code).unwrap().nodes());
+/// ```
+pub fn parse_with_capacity<'a>(
+    code: &'a str,
+    expected_tokens: usize,
+) -> Result<ast::Ast<'a>, Error<'a>> {
+    Parser::with_capacity(expected_tokens).parse(code)
+}
+
+/// Parses the top level statements of Lua code one at a time as they're iterated over, for code too big to have the
+/// whole [`Ast`](ast/struct.Ast.html) of at once. Each statement is tokenized and parsed only when it's asked for,
+/// and only its own tokens are kept with it, so the tokens and nodes of the statements before it can already be dropped.
+/// The statements are the same as the ones `parse` gives for the code, including the blocks in them.
+/// The `return` or `break` at the end of the code is in [`last_stmt`](ast/stream/struct.StmtStream.html#method.last_stmt).
+///
+/// # Errors
+/// The same as `parse`, as the last item: the statements before the first error are all given before it.
+///
+/// ```rust
+/// # use full_moon::node::{Node, TriviaBehavior};
+/// let code = "local x = 1\nprint(x) -- call\nlocal y = ";
+/// let mut stmts = full_moon::parse_stmts(code);
+/// assert_eq!(stmts.next().unwrap().unwrap().print(TriviaBehavior::Keep), "local x = 1\n");
+/// assert_eq!(stmts.next().unwrap().unwrap().print(TriviaBehavior::Trim), "print(x)");
+/// assert!(stmts.next().unwrap().is_err());
+/// assert!(stmts.next().is_none());
+/// ```
+pub fn parse_stmts(code: &str) -> ast::stream::StmtStream<'_> {
+    parse_stmts_with(code, &ParseConfig::default())
+}
+
+/// Parses the top level statements of Lua code one at a time like [`parse_stmts`](fn.parse_stmts.html),
+/// using the options of the [config](struct.ParseConfig.html). Without
+/// [`preserve_trivia`](struct.ParseConfig.html#structfield.preserve_trivia), comments are dropped along with whitespace.
+///
+/// # Errors
+/// The same as [`parse_with`](fn.parse_with.html), as the last item.
+pub fn parse_stmts_with<'a>(code: &'a str, config: &ParseConfig) -> ast::stream::StmtStream<'a> {
+    ast::stream::StmtStream::new(code, config, false)
+}
+
+/// Parses the top level statements of Lua code one at a time like [`parse_stmts_with`](fn.parse_stmts_with.html),
+/// but keeps going after errors like [`parse_fallible`](fn.parse_fallible.html), giving up to
+/// [`max_errors`](struct.ParseConfig.html#structfield.max_errors) of them in between the statements.
+/// A statement with an error is skipped up to where `parse_fallible` would start parsing again,
+/// and invalid escapes in strings and malformed numbers are given as errors before the statement they're in.
+/// Tokenizing still stops at any other error, which is given once a statement needs the code after it.
+///
+/// ```rust
+/// # use full_moon::{node::{Node, TriviaBehavior}, ParseConfig};
+/// let code = "local x = \nlocal y = 1\ncall(,)\nprint(y)";
+/// let items: Vec<_> = full_moon::parse_stmts_fallible(code, &ParseConfig::default()).collect();
+/// assert_eq!(items.len(), 4);
+/// assert!(items[0].is_err() && items[2].is_err());
+/// assert_eq!(items[3].as_ref().unwrap().print(TriviaBehavior::Trim), "print(y)");
+/// ```
+pub fn parse_stmts_fallible<'a>(
+    code: &'a str,
+    config: &ParseConfig,
+) -> ast::stream::StmtStream<'a> {
+    ast::stream::StmtStream::new(code, config, true)
+}
+
+/// Creates the [`Ast`](ast/struct.Ast.html)s of many pieces of code at once like [`parse`](fn.parse.html), parsing them in
+/// parallel on the threads of rayon's global thread pool, and gives back the result of each one in the same order.
+/// Each thread parses with a [`Parser`](struct.Parser.html), so the buffers are reused between the code it parses.
+/// Only available with the `rayon` feature.
+///
+/// ```rust
+/// let results = full_moon::parse_many(vec!["local x = 1", "local y = ", "return"]);
+/// assert_eq!(full_moon::print(results[0].as_ref().unwrap()), "local x = 1");
+/// assert!(results[1].is_err());
+/// assert!(results[2].is_ok());
+/// ```
+#[cfg(feature = "rayon")]
+pub fn parse_many<'a, I>(sources: I) -> Vec<Result<ast::Ast<'a>, Error<'a>>>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    use rayon::prelude::*;
+
+    let sources: Vec<&'a str> = sources.into_iter().collect();
+    sources
+        .into_par_iter()
+        .map_init(Parser::new, |parser, code| parser.parse(code))
+        .collect()
+}
+
+/// Parses many pieces of code at once like [`parse_many`](fn.parse_many.html), but calls `callback` with the result
+/// of each one as soon as it's parsed, along with where its code was in `sources`, rather than waiting for all of them.
+/// The results are in the order they finish in, and the callback is called on the thread that parsed the code,
+/// so it can be called from many threads at once.
+/// Only available with the `rayon` feature.
+///
+/// ```rust
+/// use std::sync::mpsc;
+///
+/// let (sender, receiver) = mpsc::channel();
+/// let sender = std::sync::Mutex::new(sender);
+/// full_moon::parse_many_for_each(vec!["local x = 1", "local y = "], |index, result| {
+///     sender.lock().unwrap().send((index, result.is_ok())).unwrap();
+/// });
+///
+/// let mut finished: Vec<_> = receiver.try_iter().collect();
+/// finished.sort();
+/// assert_eq!(finished, [(0, true), (1, false)]);
+/// ```
+#[cfg(feature = "rayon")]
+pub fn parse_many_for_each<'a, I, F>(sources: I, callback: F)
+where
+    I: IntoIterator<Item = &'a str>,
+    F: Fn(usize, Result<ast::Ast<'a>, Error<'a>>) + Sync,
+{
+    use rayon::prelude::*;
+
+    let sources: Vec<&'a str> = sources.into_iter().collect();
+    sources
+        .into_par_iter()
+        .enumerate()
+        .for_each_init(Parser::new, |parser, (index, code)| {
+            callback(index, parser.parse(code))
+        });
+}
+
+/// Creates an [`OwnedAst`](ast/owned/struct.OwnedAst.html) from Lua code like [`parse`](fn.parse.html),
+/// which owns the code and every token, so it doesn't borrow anything and can be kept for as long as it's needed.
+///
+/// # Errors
+/// The same as `parse`, with an owned error that doesn't borrow the code either.
+///
+/// ```rust
+/// let ast = full_moon::parse_owned(String::from("local x = 1")).unwrap();
+/// assert_eq!(full_moon::print(&ast), "local x = 1");
+///
+/// let error = full_moon::parse_owned(String::from("local x = ")).unwrap_err();
+/// assert_eq!(error.message(), "unexpected end of code");
+/// ```
+pub fn parse_owned(code: String) -> Result<ast::owned::OwnedAst, Error<'static>> {
+    parse_owned_with(code, &ParseConfig::default())
+}
+
+/// Creates an [`OwnedAst`](ast/owned/struct.OwnedAst.html) from Lua code like [`parse_owned`](fn.parse_owned.html),
+/// using the options of the [config](struct.ParseConfig.html).
+///
+/// # Errors
+/// The same as [`parse_with`](fn.parse_with.html), with an owned error.
+pub fn parse_owned_with(
+    code: String,
+    config: &ParseConfig,
+) -> Result<ast::owned::OwnedAst, Error<'static>> {
+    use ast::owned::Owned;
+
+    let ast = parse_with(&code, config)
+        .map_err(|error| error.owned())?
+        .owned();
+    Ok(ast::owned::OwnedAst::new(code, ast))
+}
+
+/// Creates an [`Ast`](ast/struct.Ast.html) from tokens made elsewhere, such as by a preprocessor, without printing them
+/// and tokenizing the code again. The tokens are parsed like they are by [`Ast::from_tokens`](ast/struct.Ast.html#method.from_tokens),
+/// after checking that they could have come from tokenizing code: the only end of file token is the last one,
+/// and every token starts at or after where the token before it ends. Tokens made from a
+/// [`TokenType`](tokenizer/enum.TokenType.html) have no positions of their own, so they're skipped when checking,
+/// and the Ast needs [`update_positions`](ast/struct.Ast.html#method.update_positions) to give them positions.
+///
+/// # Errors
+/// AstError::Empty if there are no tokens, and AstError::NoEof if the last one isn't an end of file token.
+/// Every other error is a [`TokensError`](enum.Error.html#variant.TokensError) with the index of the token that caused it,
+/// since tokens without positions of their own can't be found by their position: AstError::EofBeforeEnd for an end
+/// of file token before the last token, AstError::TokenOutOfOrder for a token that starts before the token before it
+/// ends, and the same errors as `from_tokens` for tokens that aren't valid Lua 5.1 code.
+///
+/// ```rust
+/// use full_moon::{ast::AstError, tokenizer::{self, Symbol, Token, TokenType}, Error};
+///
+/// let mut tokens = tokenizer::tokens("local x = 1").unwrap();
+/// let ast = full_moon::parse_tokens(tokens.clone()).unwrap();
+/// assert_eq!(full_moon::print(&ast), "local x = 1");
+///
+/// // A second `=` after the first one
+/// tokens.insert(5, Token::from(TokenType::Symbol { symbol: Symbol::Equal }));
+/// assert!(matches!(
+///     full_moon::parse_tokens(tokens),
+///     Err(Error::TokensError { index: 5, error: AstError::UnexpectedToken { .. } }),
+/// ));
+/// ```
+pub fn parse_tokens(tokens: Vec<tokenizer::Token>) -> Result<ast::Ast, Error> {
+    let mut previous_end = None;
+    for (index, token) in tokens.iter().enumerate() {
+        let (start, end) = (token.start_position(), token.end_position());
+        if *token.token_type() == tokenizer::TokenType::Eof && index + 1 < tokens.len() {
+            return Err(Error::TokensError {
+                index,
+                error: ast::AstError::EofBeforeEnd { position: start },
+            });
+        }
+
+        // Tokens made from a token type start and end at the start of the code
+        if start == end && start.bytes() == 0 {
+            continue;
+        }
+
+        if end.bytes() < start.bytes()
+            || previous_end.is_some_and(|previous| start.bytes() < previous)
+        {
+            return Err(Error::TokensError {
+                index,
+                error: ast::AstError::TokenOutOfOrder { position: start },
+            });
+        }
+
+        previous_end = Some(end.bytes());
+    }
+
+    let mut tokens = tokens;
+    ast::Ast::from_tokens_reusing(&mut tokens, &ParseConfig::default(), &mut Vec::new()).map_err(
+        |(error, index)| match index {
+            Some(index) => Error::TokensError { index, error },
+            None => Error::AstError(error),
+        },
+    )
+}
+
+/// The result of [`parse_fallible`](fn.parse_fallible.html): an Ast, and the errors found while making it
+#[derive(Clone, Debug)]
+pub struct ParseResult<'a> {
+    /// The Ast of the code, with the code that couldn't be parsed in error nodes.
+    /// If the code couldn't be tokenized, this has no statements and no tokens other than the end of file.
+    pub ast: ast::Ast<'a>,
+    /// Every error found, in the order of where they are in the code. Empty if the code is valid.
+    pub errors: Vec<Error<'a>>,
+}
+
+impl<'a> ParseResult<'a> {
+    /// The Ast if there were no errors, or every error if there were,
+    /// for when code with errors can't be used at all.
+    ///
+    /// ```rust
+    /// # use full_moon::ParseConfig;
+    /// let result = full_moon::parse_fallible("local x = 1", &ParseConfig::default());
+    /// assert!(result.into_result().is_ok());
+    ///
+    /// let result = full_moon::parse_fallible("local x = \nlocal = 1", &ParseConfig::default());
+    /// assert_eq!(result.into_result().unwrap_err().len(), 2);
+    /// ```
+    pub fn into_result(self) -> Result<ast::Ast<'a>, Vec<Error<'a>>> {
+        if self.errors.is_empty() {
+            Ok(self.ast)
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+/// Creates an [`Ast`](ast/struct.Ast.html) from Lua code along with every error in it, such as for editors and linters,
+/// using the options of the [config](struct.ParseConfig.html). This is [`parse_with`](fn.parse_with.html)
+/// without stopping at the first error: there is always an Ast, and the errors go alongside it.
+///
+/// Up to [`max_errors`](struct.ParseConfig.html#structfield.max_errors) errors are found,
+/// in the same way as [`parse_all_errors`](fn.parse_all_errors.html), which explains how parsing keeps going after them.
+/// Valid code gives no errors and the same Ast as `parse_with`.
+///
+/// ```rust
+/// # use full_moon::ParseConfig;
+/// let code = "local x = \nlocal y = 1\ncall(,)";
+/// let result = full_moon::parse_fallible(code, &ParseConfig::default());
+/// assert_eq!(result.errors.len(), 2);
+/// assert_eq!(result.ast.nodes().iter_stmts().count(), 3);
+/// assert_eq!(full_moon::print(&result.ast), code);
+///
+/// // For callers that can only use code without errors
+/// let ast = full_moon::parse_fallible("local x = 1", &ParseConfig::default())
+///     .into_result()
+///     .unwrap();
+/// assert_eq!(full_moon::print(&ast), "local x = 1");
+/// ```
+pub fn parse_fallible<'a>(code: &'a str, config: &ParseConfig) -> ParseResult<'a> {
+    let (ast, errors) = parse_code(code, config);
+    ParseResult {
+        ast: ast.unwrap_or_else(ast::Ast::empty),
+        errors,
+    }
+}
+
+/// Creates an [`Ast`](ast/struct.Ast.html) from Lua code like [`parse`](fn.parse.html), but keeps going after
+/// an error to find more of them, finding at most `max_errors`.
+///
+/// After a top level statement with an error, parsing starts again at the next statement that is outside of
+/// every block opened since the start of the one with the error, so later errors aren't just the same mistake again.
+/// A block that is never closed, such as from a missing `end`, goes on until the end of the code,
+/// and only has the one error at the end. Tokenizing keeps going after invalid escapes in strings and malformed numbers,
+/// giving an error for each of them, but stops at any other error, so there is only ever one tokenizer error of any other kind.
+/// The errors are in the order of where they are in the code.
+///
+/// The Ast is returned as long as the code could be tokenized, even if there are errors, so that tools can keep
+/// working with the parts of the code that could be parsed. The code that was skipped over after an error is put in
+/// a [`Stmt::Error`](ast/enum.Stmt.html#variant.Error), or in an [`Expression::Error`](ast/enum.Expression.html#variant.Error)
+/// when only the values of an assignment, such as `local x = )`, couldn't be parsed.
+/// Every token is still in the Ast, so [`print`](fn.print.html) gives back the code exactly as it was.
+///
+/// ```rust
+/// let code = "local x = \nlocal y = 1\ncall(,)";
+/// let (ast, errors) = full_moon::parse_all_errors(code, 10);
+/// assert_eq!(errors.len(), 2);
+///
+/// let ast = ast.unwrap();
+/// assert_eq!(ast.nodes().iter_stmts().count(), 3);
+/// assert_eq!(full_moon::print(&ast), code);
+/// ```
+pub fn parse_all_errors<'a>(
+    code: &'a str,
+    max_errors: usize,
+) -> (Option<ast::Ast<'a>>, Vec<Error<'a>>) {
+    let config = ParseConfig {
+        max_errors,
+        ..ParseConfig::default()
+    };
+
+    parse_code(code, &config)
+}
+
+// Tokenizes and parses the code, keeping going after errors to find up to `max_errors` of them.
+// The Ast is None if there was an error that stops tokenizing.
+fn parse_code<'a>(code: &'a str, config: &ParseConfig) -> (Option<ast::Ast<'a>>, Vec<Error<'a>>) {
+    let max_errors = config.max_errors.max(1);
+    let mut tokens = Vec::new();
+    let escape_errors =
+        match tokenizer::tokenize_into(code, true, config.preserve_trivia, &mut tokens) {
+            Ok(escape_errors) => escape_errors,
+            Err(error) => return (None, vec![Error::TokenizerError(error)]),
+        };
+
+    let (ast, ast_errors) = ast::Ast::from_tokens_with_errors(tokens, config);
+    let ast = ast.map(|ast| ast.with_source(code, config));
+    let mut errors: Vec<_> = escape_errors
+        .into_iter()
+        .map(Error::TokenizerError)
+        .chain(ast_errors.into_iter().map(Error::AstError))
+        .collect();
+
+    // Errors with no position, such as from cancelling, go after the rest
+    errors.sort_by_key(|error| {
+        error
+            .position()
+            .map_or(usize::MAX, tokenizer::Position::bytes)
+    });
+    errors.truncate(max_errors);
+    (ast, errors)
+}
+
+/// Prints back Lua code from an [Ast](ast/struct.Ast.html).
+/// Asts parsed with [`preserve_trivia`](struct.ParseConfig.html#structfield.preserve_trivia) off have no whitespace
+/// to print, so their tokens and comments are put at the lines and columns they were at, with new lines and spaces
+/// between them, which is the same code other than the whitespace, and with nothing after the last token.
+///
+/// ```rust
+/// # use full_moon::ParseConfig;
+/// let config = ParseConfig {
+///     preserve_trivia: false,
+///     ..ParseConfig::default()
+/// };
+///
+/// let ast = full_moon::parse_with("if x then\n\tf(x)\t-- call\nend\n", &config).unwrap();
+/// assert_eq!(full_moon::print(&ast), "if x then\n f(x) -- call\nend");
+/// ```
 pub fn print(ast: &ast::Ast) -> String {
-    ast.iter_tokens()
-        .fold(String::new(), |acc, token| acc + &token.to_string())
+    let comments = match &ast.comments {
+        Some(comments) => comments,
+        None => {
+            return ast
+                .iter_tokens()
+                .fold(String::new(), |acc, token| acc + &token.to_string())
+        }
+    };
+
+    let mut output = String::new();
+    let (mut line, mut character) = (1, 1);
+    for token in itertools::Itertools::merge(ast.iter_tokens(), comments) {
+        // Tokens right after a new line start at the end of the line before it, but where they end is always right,
+        // and they're at the start of their line
+        let text = token.to_string();
+        let start = token.start_position();
+        let end = token.end_position();
+        let start_line = end.line() - bytecount::count(text.as_bytes(), b'\n');
+        let start_character = if start.line() < start_line {
+            1
+        } else {
+            start.character()
+        };
+
+        if start_line > line {
+            output.push_str(&"\n".repeat(start_line - line));
+            character = 1;
+        }
+
+        output.push_str(&" ".repeat(start_character.saturating_sub(character)));
+        output.push_str(&text);
+
+        line = end.line();
+        character = end.character();
+    }
+
+    output
 }
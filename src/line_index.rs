@@ -0,0 +1,311 @@
+use crate::prelude::*;
+use crate::tokenizer::Position;
+use core::mem;
+
+/// How the columns of positions on a line are counted, such as by the
+/// [Language Server Protocol](https://microsoft.github.io/language-server-protocol/), which uses UTF-16 code units
+/// unless the client says otherwise. All of them count from 0 at the start of the line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PositionEncoding {
+    /// Bytes of UTF-8, the same as [`Position::bytes`](tokenizer/struct.Position.html#method.bytes) within a line
+    Utf8Bytes,
+    /// UTF-16 code units, where characters outside of the Basic Multilingual Plane, such as most emoji, are 2
+    Utf16,
+    /// Unicode scalar values, which are Rust `char`s, the same as
+    /// [`Position::character`](tokenizer/struct.Position.html#method.character) minus 1
+    CodePoints,
+}
+
+impl PositionEncoding {
+    fn length(self, character: char) -> usize {
+        match self {
+            PositionEncoding::Utf8Bytes => character.len_utf8(),
+            PositionEncoding::Utf16 => character.len_utf16(),
+            PositionEncoding::CodePoints => 1,
+        }
+    }
+}
+
+// A character on a line that isn't one byte, which is the only kind of character
+// that has a different length in different encodings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct WideCharacter {
+    // The byte it starts at on its line
+    start: usize,
+    character: char,
+}
+
+// Converts a column on a line with these wide characters from one encoding to another,
+// None if it is inside of a character or past the end of the line
+fn convert_column(
+    wide_characters: &[WideCharacter],
+    line_length: usize,
+    column: usize,
+    from: PositionEncoding,
+    to: PositionEncoding,
+) -> Option<usize> {
+    // How far along the line each encoding is, at the end of the last character counted
+    let mut from_column = 0;
+    let mut to_column = 0;
+    let mut bytes = 0;
+
+    for wide_character in wide_characters {
+        // The one byte characters before this one are the same in every encoding
+        let narrow = wide_character.start - bytes;
+        if column < from_column + narrow {
+            return Some(to_column + column - from_column);
+        }
+
+        from_column += narrow;
+        to_column += narrow;
+        if column == from_column {
+            return Some(to_column);
+        } else if column < from_column + from.length(wide_character.character) {
+            return None;
+        }
+
+        from_column += from.length(wide_character.character);
+        to_column += to.length(wide_character.character);
+        bytes = wide_character.start + wide_character.character.len_utf8();
+    }
+
+    let narrow = line_length - bytes;
+    if column <= from_column + narrow {
+        Some(to_column + column - from_column)
+    } else {
+        None
+    }
+}
+
+fn wide_characters(line: &str) -> Vec<WideCharacter> {
+    line.char_indices()
+        .filter(|(_, character)| !character.is_ascii())
+        .map(|(start, character)| WideCharacter { start, character })
+        .collect()
+}
+
+/// Converts a column on the line from one encoding to another, such as from the UTF-16 code units of a
+/// position from a language client to the bytes full_moon uses. Columns count from 0 at the start of the line.
+/// None if the column is past the end of the line, or inside of a character, such as between the bytes of `é`.
+/// To convert many positions in the same code, use a [`LineIndex`](struct.LineIndex.html) instead.
+///
+/// ```rust
+/// # use full_moon::{convert_position, PositionEncoding};
+/// let line = "local s = \"😀\" -- 日本語";
+/// // The `--` is after the emoji, which is 4 bytes, 2 UTF-16 code units, and 1 code point
+/// assert_eq!(convert_position(line, 17, PositionEncoding::Utf8Bytes, PositionEncoding::Utf16), Some(15));
+/// assert_eq!(convert_position(line, 15, PositionEncoding::Utf16, PositionEncoding::CodePoints), Some(14));
+/// assert_eq!(convert_position(line, 12, PositionEncoding::Utf8Bytes, PositionEncoding::Utf16), None);
+/// ```
+pub fn convert_position(
+    source_line: &str,
+    position: usize,
+    from: PositionEncoding,
+    to: PositionEncoding,
+) -> Option<usize> {
+    convert_column(
+        &wide_characters(source_line),
+        source_line.len(),
+        position,
+        from,
+        to,
+    )
+}
+
+/// Where every line of some code starts, and what characters on it aren't one byte, for converting positions
+/// between bytes in the code and lines and columns in a [`PositionEncoding`](enum.PositionEncoding.html) quickly.
+/// Lines end after every `\n`, and both lines and columns count from 0, the same as the Language Server Protocol.
+///
+/// The bytes of positions are always right, so converting them is the way to get the line and column of a token
+/// in any encoding, such as for a diagnostic.
+///
+/// ```rust
+/// # use full_moon::PositionEncoding;
+/// let ast = full_moon::parse("print(\"😀\", x)").unwrap();
+/// let x = ast.iter_tokens().find(|token| token.to_string() == "x").unwrap();
+/// let bytes = x.start_position().bytes();
+/// assert_eq!(bytes, 14);
+///
+/// let index = ast.line_index();
+/// assert_eq!(index.line_column(bytes, PositionEncoding::Utf16), Some((0, 12)));
+/// assert_eq!(index.line_column(bytes, PositionEncoding::CodePoints), Some((0, 11)));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineIndex {
+    // The byte each line starts at
+    line_starts: Vec<usize>,
+    // The characters of each line that aren't one byte, for lines that have any
+    wide_characters: Vec<Vec<WideCharacter>>,
+    length: usize,
+}
+
+impl LineIndex {
+    /// Makes the index of the lines of the code
+    pub fn new(code: &str) -> Self {
+        let lines = bytecount::count(code.as_bytes(), b'\n') + 1;
+        let mut line_starts = Vec::with_capacity(lines);
+        let mut wide_characters = Vec::with_capacity(lines);
+        line_starts.push(0);
+        wide_characters.push(Vec::new());
+
+        // Most code is ASCII, so only the bytes that start wider characters are decoded
+        let bytes = code.as_bytes();
+        let mut start = 0;
+        while start < bytes.len() {
+            match bytes[start] {
+                b'\n' => {
+                    start += 1;
+                    line_starts.push(start);
+                    wide_characters.push(Vec::new());
+                }
+
+                byte if byte.is_ascii() => start += 1,
+
+                _ => {
+                    let character = code[start..].chars().next().unwrap();
+                    wide_characters.last_mut().unwrap().push(WideCharacter {
+                        start: start - line_starts.last().unwrap(),
+                        character,
+                    });
+                    start += character.len_utf8();
+                }
+            }
+        }
+
+        Self {
+            line_starts,
+            wide_characters,
+            length: code.len(),
+        }
+    }
+
+    /// How many lines the code has, which is one more than the number of `\n`s in it
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    // The length of the line in bytes, including its `\n`
+    fn line_length(&self, line: usize) -> usize {
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .copied()
+            .unwrap_or(self.length);
+        end - self.line_starts[line]
+    }
+
+    // The line the byte is on, counting from 0
+    fn line(&self, bytes: usize) -> usize {
+        match self.line_starts.binary_search(&bytes) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        }
+    }
+
+    // The position of the character at the byte, with its line and character counting from 1
+    pub(crate) fn character_position(&self, bytes: usize) -> Position {
+        let line = self.line(bytes);
+        let column = bytes - self.line_starts[line];
+        let extra_bytes: usize = self.wide_characters[line]
+            .iter()
+            .take_while(|wide_character| wide_character.start < column)
+            .map(|wide_character| wide_character.character.len_utf8() - 1)
+            .sum();
+
+        Position {
+            bytes,
+            character: column - extra_bytes + 1,
+            line: line + 1,
+        }
+    }
+
+    // The position of a token starting or ending at the byte, which is the same as the tokenizer has always given it:
+    // the start of a line is counted as the character after the `\n` of the line before it
+    pub(crate) fn position(&self, bytes: usize) -> Position {
+        if bytes > 0 && self.line_starts.binary_search(&bytes).is_ok() {
+            Position {
+                bytes,
+                ..self.character_position(bytes - 1)
+            }
+        } else {
+            self.character_position(bytes)
+        }
+    }
+
+    // The bytes the index keeps on the heap
+    pub(crate) fn heap_size(&self) -> usize {
+        self.line_starts.capacity() * mem::size_of::<usize>()
+            + self.wide_characters.capacity() * mem::size_of::<Vec<WideCharacter>>()
+            + self
+                .wide_characters
+                .iter()
+                .map(|line| line.capacity() * mem::size_of::<WideCharacter>())
+                .sum::<usize>()
+    }
+
+    /// The line and the column in the encoding of the byte of the code, such as [`Position::bytes`](tokenizer/struct.Position.html#method.bytes).
+    /// None if the byte is past the end of the code or inside of a character.
+    ///
+    /// ```rust
+    /// # use full_moon::{LineIndex, PositionEncoding};
+    /// let index = LineIndex::new("-- 😀\nlocal x");
+    /// // After the emoji, which is 4 bytes, 2 UTF-16 code units, and 1 code point
+    /// assert_eq!(index.line_column(7, PositionEncoding::Utf16), Some((0, 5)));
+    /// assert_eq!(index.line_column(7, PositionEncoding::CodePoints), Some((0, 4)));
+    /// assert_eq!(index.line_column(14, PositionEncoding::Utf16), Some((1, 6)));
+    /// assert_eq!(index.line_column(4, PositionEncoding::Utf16), None);
+    /// ```
+    pub fn line_column(&self, bytes: usize, encoding: PositionEncoding) -> Option<(usize, usize)> {
+        if bytes > self.length {
+            return None;
+        }
+
+        let line = self.line(bytes);
+        let column = self.convert(
+            line,
+            bytes - self.line_starts[line],
+            PositionEncoding::Utf8Bytes,
+            encoding,
+        )?;
+        Some((line, column))
+    }
+
+    /// The byte of the code at the line and column in the encoding, such as a position from a language client.
+    /// None if the line isn't in the code, or the column is past the end of the line or inside of a character.
+    ///
+    /// ```rust
+    /// # use full_moon::{LineIndex, PositionEncoding};
+    /// let index = LineIndex::new("-- 😀\nlocal x");
+    /// assert_eq!(index.bytes(0, 5, PositionEncoding::Utf16), Some(7));
+    /// assert_eq!(index.bytes(0, 4, PositionEncoding::Utf16), None);
+    /// assert_eq!(index.bytes(1, 6, PositionEncoding::Utf16), Some(14));
+    /// assert_eq!(index.bytes(2, 0, PositionEncoding::Utf16), None);
+    /// ```
+    pub fn bytes(&self, line: usize, column: usize, encoding: PositionEncoding) -> Option<usize> {
+        let start = *self.line_starts.get(line)?;
+        let column = self.convert(line, column, encoding, PositionEncoding::Utf8Bytes)?;
+        Some(start + column)
+    }
+
+    /// Converts a column on the line from one encoding to another, the same as
+    /// [`convert_position`](fn.convert_position.html) for the line. None if the line isn't in the code.
+    pub fn convert(
+        &self,
+        line: usize,
+        column: usize,
+        from: PositionEncoding,
+        to: PositionEncoding,
+    ) -> Option<usize> {
+        if line >= self.line_starts.len() {
+            return None;
+        }
+
+        // The `\n` at the end of the line isn't a column of it, since the column after it starts the next line
+        let mut length = self.line_length(line);
+        if line + 1 < self.line_starts.len() {
+            length -= 1;
+        }
+
+        convert_column(&self.wide_characters[line], length, column, from, to)
+    }
+}
@@ -0,0 +1,538 @@
+use crate::{
+    analysis::{SemanticToken, SemanticTokenKind},
+    ast::{
+        punctuated::Punctuated,
+        simplified::{SExpr, SExprKind},
+        Ast, Block, Expression, Field, FunctionBody, Index, LastStmt, Parameter, Prefix, Stmt,
+        Suffix, TableConstructor, Value, Var,
+    },
+    node::Node,
+    tokenizer::{self, TokenReference},
+    LineIndex, PositionEncoding,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A position in the code, the same as a `Position` of the Language Server Protocol.
+/// Both the line and the character count from 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Position {
+    /// The line of the position
+    pub line: u32,
+    /// The column of the position on its line, in the encoding the symbols were made with,
+    /// which is UTF-16 code units for [`document_symbols`](fn.document_symbols.html)
+    pub character: u32,
+}
+
+/// A range of the code, the same as a `Range` of the Language Server Protocol. The end is exclusive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Range {
+    /// Where the range starts
+    pub start: Position,
+    /// Where the range ends, after its last character
+    pub end: Position,
+}
+
+/// The kinds of [`DocumentSymbol`](struct.DocumentSymbol.html), the same as the `SymbolKind`s of the
+/// Language Server Protocol that they have. They are serialized as the numbers of the protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum SymbolKind {
+    /// A function declared with a method name, such as `function Class:method() end`
+    Method = 6,
+    /// Any other function, such as `local function f() end`, `function M.f() end`, or `M.f = function() end`
+    Function = 12,
+    /// A table, such as `local M = {}`
+    Object = 19,
+}
+
+impl SymbolKind {
+    /// The number of the kind in the Language Server Protocol
+    pub fn number(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for SymbolKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.number())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for SymbolKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match u8::deserialize(deserializer)? {
+            6 => Ok(SymbolKind::Method),
+            12 => Ok(SymbolKind::Function),
+            19 => Ok(SymbolKind::Object),
+            other => Err(serde::de::Error::custom(format!(
+                "unknown symbol kind {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A symbol in the outline of the code, the same as a `DocumentSymbol` of the Language Server Protocol,
+/// made by [`document_symbols`](fn.document_symbols.html)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct DocumentSymbol {
+    /// The full name of the symbol as it is written, such as `M.util:method` for `function M.util:method() end`
+    pub name: String,
+    /// The parameters of a function, such as `(a, b, ...)`, or None for a table
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub detail: Option<String>,
+    /// What the symbol is
+    pub kind: SymbolKind,
+    /// The range of the whole node the symbol is from, such as the whole function declaration, including its body
+    pub range: Range,
+    /// The range of the name of the symbol, which is inside of its range
+    pub selection_range: Range,
+    /// The symbols inside of this one, such as the local functions of a function or the fields of a table
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// The outline of the code of the Ast for the `textDocument/documentSymbol` request of a language server, with
+/// positions in UTF-16 code units, which is what the Language Server Protocol uses by default.
+/// Use [`document_symbols_with`](fn.document_symbols_with.html) for another encoding.
+///
+/// These are the symbols, each with the symbols inside of them as their children:
+/// - Function declarations and local functions, with their full names, such as `M.util:method`.
+/// - Local assignments and assignments of functions and tables, such as `local M = {}` and `M.f = function() end`.
+///   Only assignments to names, and indexes of them with dots such as `M.util.f`, have a symbol.
+/// - Fields of tables that are functions, and fields that are tables with symbols in them, such as
+///   `{ f = function() end }`. Only fields with names or string keys have a symbol.
+/// - The fields of tables that are returned, such as `return { new = function() end }`.
+///
+/// Symbols inside of control flow, such as an `if` or a `do`, are in the outline of the block they're in,
+/// and functions that aren't any of these, such as callbacks passed to functions, aren't in the outline.
+///
+/// ```rust
+/// use full_moon::lsp::{document_symbols, SymbolKind};
+///
+/// let ast = full_moon::parse("local M = {}\n\nM.new = function()\n\tlocal function helper() end\nend").unwrap();
+/// let symbols = document_symbols(&ast);
+///
+/// assert_eq!(symbols[0].name, "M");
+/// assert_eq!(symbols[0].kind, SymbolKind::Object);
+///
+/// assert_eq!(symbols[1].name, "M.new");
+/// assert_eq!(symbols[1].kind, SymbolKind::Function);
+/// assert_eq!(symbols[1].range.start.line, 2);
+/// assert_eq!(symbols[1].selection_range.end.character, 5);
+/// assert_eq!(symbols[1].children[0].name, "helper");
+/// ```
+pub fn document_symbols(ast: &Ast) -> Vec<DocumentSymbol> {
+    document_symbols_with(ast, PositionEncoding::Utf16)
+}
+
+/// The outline of the code of the Ast like [`document_symbols`](fn.document_symbols.html),
+/// with the characters of positions in the encoding, such as one a language client asked for.
+pub fn document_symbols_with(ast: &Ast, encoding: PositionEncoding) -> Vec<DocumentSymbol> {
+    Outline {
+        index: ast.line_index(),
+        encoding,
+    }
+    .block(ast.nodes())
+}
+
+/// The token types of the legend of a language server that sends
+/// [`semantic_tokens_data`](fn.semantic_tokens_data.html), in the order of the numbers it uses for them.
+pub const SEMANTIC_TOKEN_TYPES: &[&str] = &[
+    "variable",
+    "parameter",
+    "property",
+    "method",
+    "function",
+    "keyword",
+    "string",
+    "number",
+    "comment",
+];
+
+/// The token modifiers of the legend of a language server that sends
+/// [`semantic_tokens_data`](fn.semantic_tokens_data.html), in the order of the bits it uses for them.
+/// Globals and upvalues are variables with the `global` and `upvalue` modifiers.
+pub const SEMANTIC_TOKEN_MODIFIERS: &[&str] = &[
+    "declaration",
+    "readonly",
+    "modification",
+    "global",
+    "upvalue",
+];
+
+/// The semantic tokens from [`analysis::semantic_tokens`](../analysis/fn.semantic_tokens.html) encoded as the `data`
+/// of the response to the `textDocument/semanticTokens/full` request of a language server, with positions in UTF-16
+/// code units. Each token is five numbers: its line relative to the token before it, its start relative to the token
+/// before it if they're on the same line, its length, its type in [`SEMANTIC_TOKEN_TYPES`](constant.SEMANTIC_TOKEN_TYPES.html),
+/// and the bits of its modifiers in [`SEMANTIC_TOKEN_MODIFIERS`](constant.SEMANTIC_TOKEN_MODIFIERS.html).
+/// Tokens over more than one line, such as multiline comments, are split into one token for each line, since not
+/// every client supports them. Use [`semantic_tokens_data_with`](fn.semantic_tokens_data_with.html) for another encoding.
+///
+/// ```rust
+/// use full_moon::{analysis::semantic_tokens, lsp::semantic_tokens_data};
+///
+/// let ast = full_moon::parse("local x = 1\nprint(x)").unwrap();
+/// let data = semantic_tokens_data(&ast, &semantic_tokens(&ast));
+///
+/// assert_eq!(data, [
+///     0, 6, 1, 0, 0b1, // `x`, a declared variable
+///     1, 0, 5, 0, 0b1000, // `print`, a global variable
+///     0, 6, 1, 0, 0, // `x`
+/// ]);
+/// ```
+pub fn semantic_tokens_data(ast: &Ast, tokens: &[SemanticToken]) -> Vec<u32> {
+    semantic_tokens_data_with(ast, tokens, PositionEncoding::Utf16)
+}
+
+/// The semantic tokens encoded like [`semantic_tokens_data`](fn.semantic_tokens_data.html),
+/// with the characters of positions in the encoding, such as one a language client asked for.
+pub fn semantic_tokens_data_with(
+    ast: &Ast,
+    tokens: &[SemanticToken],
+    encoding: PositionEncoding,
+) -> Vec<u32> {
+    let index = ast.line_index();
+    let mut data = Vec::with_capacity(tokens.len() * 5);
+    let (mut last_line, mut last_start) = (0, 0);
+
+    for token in tokens {
+        let (token_type, mut modifiers) = match token.kind() {
+            SemanticTokenKind::Local => (0, 0),
+            SemanticTokenKind::Parameter => (1, 0),
+            SemanticTokenKind::Field => (2, 0),
+            SemanticTokenKind::Method => (3, 0),
+            SemanticTokenKind::Function => (4, 0),
+            SemanticTokenKind::Keyword => (5, 0),
+            SemanticTokenKind::String => (6, 0),
+            SemanticTokenKind::Number => (7, 0),
+            SemanticTokenKind::Comment => (8, 0),
+            SemanticTokenKind::Global => (0, 0b1000),
+            SemanticTokenKind::Upvalue => (0, 0b10000),
+        };
+        for (flag, bit) in [
+            (token.is_declaration(), 0b1),
+            (token.is_readonly(), 0b10),
+            (token.is_modification(), 0b100),
+        ] {
+            if flag {
+                modifiers |= bit;
+            }
+        }
+
+        let (start, end) = token.range();
+        let (start, end) = match (
+            index.line_column(start.bytes(), encoding),
+            index.line_column(end.bytes(), encoding),
+        ) {
+            (Some(start), Some(end)) => (start, end),
+            _ => continue,
+        };
+
+        for line in start.0..=end.0 {
+            let from = if line == start.0 { start.1 } else { 0 };
+            let to = if line == end.0 {
+                end.1
+            } else {
+                // The end of the line, before its `\n`
+                match index
+                    .bytes(line + 1, 0, PositionEncoding::Utf8Bytes)
+                    .and_then(|next| index.line_column(next - 1, encoding))
+                {
+                    Some((_, column)) => column,
+                    None => continue,
+                }
+            };
+
+            if to <= from {
+                continue;
+            }
+
+            let delta_start = if line == last_line {
+                from - last_start
+            } else {
+                from
+            };
+            data.extend_from_slice(&[
+                (line - last_line) as u32,
+                delta_start as u32,
+                (to - from) as u32,
+                token_type,
+                modifiers,
+            ]);
+            last_line = line;
+            last_start = from;
+        }
+    }
+
+    data
+}
+
+struct Outline {
+    index: LineIndex,
+    encoding: PositionEncoding,
+}
+
+impl Outline {
+    fn position(&self, position: tokenizer::Position) -> Option<Position> {
+        let (line, character) = self.index.line_column(position.bytes(), self.encoding)?;
+        Some(Position {
+            line: line as u32,
+            character: character as u32,
+        })
+    }
+
+    // None if the node has no range, such as some nodes from parse_fallible
+    fn range(&self, node: &impl Node) -> Option<Range> {
+        let (start, end) = node.range()?;
+        Some(Range {
+            start: self.position(start)?,
+            end: self.position(end)?,
+        })
+    }
+
+    fn block(&self, block: &Block) -> Vec<DocumentSymbol> {
+        let mut symbols = Vec::new();
+        for stmt in block.iter_stmts() {
+            self.stmt(stmt, &mut symbols);
+        }
+
+        // The fields of a table returned from a module are what the module has in it
+        if let Some(LastStmt::Return(r#return)) = block.last_stmts() {
+            for expression in r#return.returns() {
+                if let Some(Value::TableConstructor(table)) = value(expression) {
+                    symbols.extend(self.table(table));
+                }
+            }
+        }
+
+        symbols
+    }
+
+    fn stmt(&self, stmt: &Stmt, symbols: &mut Vec<DocumentSymbol>) {
+        match stmt {
+            Stmt::Assignment(assignment) => {
+                for (var, expression) in assignment
+                    .var_list()
+                    .iter()
+                    .zip(assignment.expr_list().iter())
+                {
+                    if let Some(name) = var_name(var) {
+                        symbols.extend(self.value(
+                            name,
+                            self.range(stmt),
+                            self.range(var),
+                            expression,
+                        ));
+                    }
+                }
+            }
+
+            Stmt::Do(r#do) => symbols.extend(self.block(r#do.block())),
+
+            Stmt::FunctionDeclaration(declaration) => {
+                let function_name = declaration.name();
+                let mut name = join(function_name.names());
+                let kind = match function_name.method_name() {
+                    Some(method) => {
+                        name.push(':');
+                        name.push_str(&method.to_string());
+                        SymbolKind::Method
+                    }
+                    None => SymbolKind::Function,
+                };
+
+                symbols.extend(self.function(
+                    name,
+                    kind,
+                    self.range(stmt),
+                    self.range(function_name),
+                    declaration.body(),
+                ));
+            }
+
+            Stmt::GenericFor(generic_for) => symbols.extend(self.block(generic_for.block())),
+
+            Stmt::If(r#if) => {
+                symbols.extend(self.block(r#if.block()));
+                for else_if in r#if.else_if().into_iter().flatten() {
+                    symbols.extend(self.block(else_if.block()));
+                }
+                if let Some(block) = r#if.else_block() {
+                    symbols.extend(self.block(block));
+                }
+            }
+
+            Stmt::LocalAssignment(local_assignment) => {
+                for (name, expression) in local_assignment
+                    .name_list()
+                    .iter()
+                    .zip(local_assignment.expr_list().iter())
+                {
+                    symbols.extend(self.value(
+                        name.to_string(),
+                        self.range(stmt),
+                        self.range(name),
+                        expression,
+                    ));
+                }
+            }
+
+            Stmt::LocalFunction(local_function) => {
+                let name = local_function.name();
+                symbols.extend(self.function(
+                    name.to_string(),
+                    SymbolKind::Function,
+                    self.range(stmt),
+                    self.range(name),
+                    local_function.func_body(),
+                ));
+            }
+
+            Stmt::NumericFor(numeric_for) => symbols.extend(self.block(numeric_for.block())),
+            Stmt::Repeat(repeat) => symbols.extend(self.block(repeat.block())),
+            Stmt::While(r#while) => symbols.extend(self.block(r#while.block())),
+
+            Stmt::Error(_) | Stmt::FunctionCall(_) => {}
+        }
+    }
+
+    // The symbol of a name given to a function or a table, None for any other value
+    fn value(
+        &self,
+        name: String,
+        range: Option<Range>,
+        selection_range: Option<Range>,
+        expression: &Expression,
+    ) -> Option<DocumentSymbol> {
+        match value(expression)? {
+            Value::Function((_, body)) => {
+                self.function(name, SymbolKind::Function, range, selection_range, body)
+            }
+
+            Value::TableConstructor(table) => Some(DocumentSymbol {
+                name,
+                detail: None,
+                kind: SymbolKind::Object,
+                range: range?,
+                selection_range: selection_range?,
+                children: self.table(table),
+            }),
+
+            _ => None,
+        }
+    }
+
+    fn function(
+        &self,
+        name: String,
+        kind: SymbolKind,
+        range: Option<Range>,
+        selection_range: Option<Range>,
+        body: &FunctionBody,
+    ) -> Option<DocumentSymbol> {
+        Some(DocumentSymbol {
+            name,
+            detail: Some(parameters(body)),
+            kind,
+            range: range?,
+            selection_range: selection_range?,
+            children: self.block(body.block()),
+        })
+    }
+
+    fn table(&self, table: &TableConstructor) -> Vec<DocumentSymbol> {
+        let mut symbols = Vec::new();
+        for (field, _) in table.iter_fields() {
+            let (name, selection_range, value) = match field {
+                Field::NameKey { key, value, .. } => (key.to_string(), self.range(key), value),
+                Field::ExpressionKey { key, value, .. } => match string_key(key) {
+                    Some(name) => (name, self.range(key), value),
+                    None => continue,
+                },
+                Field::NoKey(_) => continue,
+            };
+
+            if let Some(symbol) = self.value(name, self.range(field), selection_range, value) {
+                // Tables in tables are only worth a symbol when there's something in them to find
+                if symbol.kind != SymbolKind::Object || !symbol.children.is_empty() {
+                    symbols.push(symbol);
+                }
+            }
+        }
+
+        symbols
+    }
+}
+
+// The value of an expression that's only a value, such as `function() end` but not `function() end or x`
+fn value<'a, 'b>(expression: &'b Expression<'a>) -> Option<&'b Value<'a>> {
+    match expression {
+        Expression::Value {
+            value, binop: None, ..
+        } => Some(value),
+        _ => None,
+    }
+}
+
+fn join(names: &Punctuated<TokenReference>) -> String {
+    names
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// The name of an assignment to a name, or to indexes of one with dots, such as `M.util.f`
+fn var_name(var: &Var) -> Option<String> {
+    let expression = match var {
+        Var::Name(name) => return Some(name.to_string()),
+        Var::Expression(expression) => expression,
+    };
+
+    let mut name = match expression.prefix() {
+        Prefix::Name(name) => name.to_string(),
+        Prefix::Expression(_) => return None,
+    };
+
+    for suffix in expression.iter_suffixes() {
+        match suffix {
+            Suffix::Index(Index::Dot { name: key, .. }) => {
+                name.push('.');
+                name.push_str(&key.to_string());
+            }
+            _ => return None,
+        }
+    }
+
+    Some(name)
+}
+
+// The name of a field with a string key, such as `["f"] = function() end`
+fn string_key(key: &Expression) -> Option<String> {
+    match SExpr::from(key).kind {
+        SExprKind::String(bytes) => String::from_utf8(bytes).ok(),
+        _ => None,
+    }
+}
+
+fn parameters(body: &FunctionBody) -> String {
+    let parameters: Vec<_> = body
+        .iter_parameters()
+        .map(|parameter| match parameter {
+            Parameter::Ellipse(_) => "...".to_owned(),
+            Parameter::Name(name) => name.to_string(),
+        })
+        .collect();
+
+    format!("({})", parameters.join(", "))
+}
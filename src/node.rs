@@ -1,8 +1,212 @@
 use crate::{
     ast::Ast,
+    prelude::*,
     private,
-    tokenizer::{Position, Token, TokenReference},
+    tokenizer::{Position, Token, TokenReference, TokenType},
+    visitors::{AnyNode, NodeKind, Visit, Visitor},
 };
+use alloc::sync::Arc;
+use core::{
+    fmt::{self, Write},
+    sync::atomic::Ordering,
+};
+use generational_arena::Arena;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The path to a node from the root of an [`Ast`](../ast/struct.Ast.html), as the index of the child to take at
+/// every level. Children of a node are its nodes and tokens in the order of its fields, the same order used by
+/// [`Ast::nodes_with_paths`](../ast/struct.Ast.html#method.nodes_with_paths).
+///
+/// Paths can be stored and used later, such as to find a node during an immutable pass and change it during
+/// a mutable pass with [`Ast::modify_at_path`](../ast/struct.Ast.html#method.modify_at_path).
+/// They only point to the same node as long as the tree above it hasn't changed shape.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct NodePath(Vec<usize>);
+
+impl NodePath {
+    /// The path to the root of the tree, the [`Block`](../ast/struct.Block.html) of the Ast
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// The index of the child taken at every level
+    pub fn indices(&self) -> &[usize] {
+        &self.0
+    }
+
+    /// The path to the child of the current node at the index
+    pub fn child(&self, index: usize) -> Self {
+        let mut indices = self.0.clone();
+        indices.push(index);
+        Self(indices)
+    }
+
+    /// The path to the parent of the current node, None if this is the root
+    pub fn parent(&self) -> Option<Self> {
+        let (_, parent) = self.0.split_last()?;
+        Some(Self(parent.to_vec()))
+    }
+}
+
+impl From<Vec<usize>> for NodePath {
+    fn from(indices: Vec<usize>) -> Self {
+        Self(indices)
+    }
+}
+
+/// An id for a node or token, from [`Node::id`](trait.Node.html#method.id), which can be used
+/// to keep information about nodes, such as the results of an analysis, as the tree changes.
+///
+/// Every node gets a new id when it's created, such as when it's parsed, and keeps it as long as it isn't
+/// replaced, even as the nodes around it and inside of it change. Clones of a node have the same id as it.
+/// Nodes that are only another node or token, such as a [`Stmt`](../ast/enum.Stmt.html) of an
+/// [`Assignment`](../ast/struct.Assignment.html), are replaced along with what they're made of,
+/// so their ids are made from its id, but are still different from it.
+/// Ids are unique within the program, and are different every time it runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(u64);
+
+// Ids made by `NodeId::new` are below this bit, and the bits from it up are the kind of node an id made from
+// another id is for, plus one
+const KIND_SHIFT: u32 = 56;
+
+#[cfg(target_has_atomic = "64")]
+fn next_id() -> u64 {
+    use core::sync::atomic::AtomicU64;
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// Targets without 64 bit atomics, such as some microcontrollers, count behind a lock instead
+#[cfg(not(target_has_atomic = "64"))]
+fn next_id() -> u64 {
+    use core::{cell::UnsafeCell, sync::atomic::AtomicBool};
+
+    struct Counter {
+        locked: AtomicBool,
+        next: UnsafeCell<u64>,
+    }
+
+    // The count is only used while the lock is held
+    unsafe impl Sync for Counter {}
+
+    static COUNTER: Counter = Counter {
+        locked: AtomicBool::new(false),
+        next: UnsafeCell::new(0),
+    };
+
+    while COUNTER
+        .locked
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+
+    // SAFETY: the lock is held, so nothing else is using the count
+    let id = unsafe {
+        let next = &mut *COUNTER.next.get();
+        let id = *next;
+        *next += 1;
+        id
+    };
+
+    COUNTER.locked.store(false, Ordering::Release);
+    id
+}
+
+impl NodeId {
+    pub(crate) fn new() -> Self {
+        Self(next_id())
+    }
+
+    // The id of a node of the kind that's only the node or token with this id, which is different from the ids
+    // of nodes of every other kind made from it, and from ids made by `new`
+    pub(crate) fn wrapped(self, kind: NodeKind) -> Self {
+        Self((self.0 & ((1 << KIND_SHIFT) - 1)) | ((kind as u64 + 1) << KIND_SHIFT))
+    }
+
+    /// The id as a number
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+// Tokens always have an id, unlike nodes in general, so they can be given where an id is wanted
+impl From<&Token<'_>> for NodeId {
+    fn from(token: &Token) -> Self {
+        token.token_type.id
+    }
+}
+
+impl From<&TokenReference<'_>> for NodeId {
+    fn from(token: &TokenReference) -> Self {
+        token.token_type.id
+    }
+}
+
+/// The id of a node, as a field of the node, such as of an
+/// [`Expression::Value`](../ast/enum.Expression.html#variant.Value). Ids are ignored when comparing nodes,
+/// so nodes that are the same apart from their ids are equal. Use `StoredId::default()` to give a node
+/// that's being made a new id.
+#[derive(Clone, Copy)]
+pub struct StoredId(pub(crate) NodeId);
+
+impl StoredId {
+    // The id of nodes only made to be dropped right away, which is never seen
+    pub(crate) const DROPPED: Self = Self(NodeId(u64::MAX));
+
+    pub(crate) fn new() -> Self {
+        Self(NodeId::new())
+    }
+
+    /// The id that's stored
+    pub fn id(self) -> NodeId {
+        self.0
+    }
+}
+
+// New nodes, including ones that are deserialized, get new ids
+impl Default for StoredId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PartialEq for StoredId {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for StoredId {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(formatter)
+    }
+}
+
+/// What to do with the comments and whitespace around a node when using [`Node::print`](trait.Node.html#method.print).
+/// Comments and whitespace inside of the node are always kept.
+///
+/// The trivia after a node is everything up to the end of the line its last token is on, such as a comment
+/// after a statement. The trivia before a node is everything between it and the trivia after the token before it,
+/// such as indentation and the comments on the lines above it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TriviaBehavior {
+    /// Keep the comments and whitespace before and after the node
+    Keep,
+    /// Drop the comments and whitespace before and after the node
+    Trim,
+    /// Keep the comments before and after the node, dropping the whitespace outside of them
+    TrimToComments,
+}
 
 /// Used to represent nodes such as tokens or function definitions
 ///
@@ -17,6 +221,14 @@ pub trait Node: private::Sealed {
     /// Whether another node of the same type is the same as this one semantically, ignoring position
     fn similar(&self, other: &Self) -> bool;
 
+    /// The [id](struct.NodeId.html) of the node, which stays the same as the tree around it changes.
+    /// Every node and token in an Ast has a different id, other than clones of the same node.
+    ///
+    /// Every node and token has an id, so this is only None for containers, such as a Vec or a None Option.
+    fn id(&self) -> Option<NodeId> {
+        None
+    }
+
     /// The full range of a node, if it has both start and end positions
     fn range(&self) -> Option<(Position, Position)> {
         Some((self.start_position()?, self.end_position()?))
@@ -68,6 +280,181 @@ pub trait Node: private::Sealed {
 
         Some((previous, following))
     }
+
+    /// Writes the code of the node to a writer, including the comments and whitespace inside of it,
+    /// without building a `String` first. This is the same code as the node's range in [`print`](../fn.print.html).
+    /// Nothing is written if the node doesn't have a range. Only available with the `std` feature.
+    #[cfg(feature = "std")]
+    fn write_to<W: io::Write>(&self, ast: &Ast, mut writer: W) -> io::Result<()>
+    where
+        Self: Sized,
+    {
+        let (start, end) = match self.range() {
+            Some(range) => range,
+            None => return Ok(()),
+        };
+
+        for token in ast
+            .iter_tokens()
+            .skip_while(|token| Token::start_position(token).bytes() < start.bytes())
+            .take_while(|token| Token::end_position(token).bytes() <= end.bytes())
+        {
+            write!(writer, "{}", token)?;
+        }
+
+        Ok(())
+    }
+
+    /// The code of the node, including the comments and whitespace inside of it, and those
+    /// [around it](enum.TriviaBehavior.html) depending on `trivia`.
+    /// Returns an empty string if the node wasn't parsed from code, such as if it was deserialized.
+    ///
+    /// ```rust
+    /// # use full_moon::node::{Node, TriviaBehavior};
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// let ast = full_moon::parse("local x = 1 -- one\n\n-- two\n  call(x) -- three\nreturn")?;
+    /// let call = ast.nodes().iter_stmts().nth(1).unwrap();
+    ///
+    /// assert_eq!(call.print(TriviaBehavior::Keep), "\n-- two\n  call(x) -- three\n");
+    /// assert_eq!(call.print(TriviaBehavior::Trim), "call(x)");
+    /// assert_eq!(call.print(TriviaBehavior::TrimToComments), "-- two\n  call(x) -- three");
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn print<'a>(&self, trivia: TriviaBehavior) -> String
+    where
+        Self: Sized + Visit<'a>,
+    {
+        let mut range = TokenRange::default();
+        self.visit(&mut range);
+        range.print(trivia)
+    }
+}
+
+// The first and last tokens of a node, for finding the comments and whitespace around and inside of it
+#[derive(Default)]
+pub(crate) struct TokenRange<'a> {
+    pub(crate) arena: Option<Arc<Arena<Token<'a>>>>,
+    pub(crate) range: Option<(usize, usize)>,
+}
+
+impl<'a> TokenRange<'a> {
+    fn token(&mut self, token: &TokenReference<'a>) {
+        let (arena, offset) = match token.arena_offset() {
+            Some(arena_offset) => arena_offset,
+            None => return,
+        };
+
+        match &self.arena {
+            Some(existing) if !Arc::ptr_eq(existing, arena) => return,
+            Some(_) => {}
+            None => self.arena = Some(Arc::clone(arena)),
+        }
+
+        self.range = Some(match self.range {
+            Some((first, last)) => (first.min(offset), last.max(offset)),
+            None => (offset, offset),
+        });
+    }
+
+    // The offsets of the first and last tokens of the trivia before the node, and of the trivia after it.
+    // If there is no trivia before the node, the start is the node's first token, and likewise for the end.
+    pub(crate) fn trivia_bounds(&self) -> Option<(usize, usize)> {
+        let (arena, (first, last)) = match (&self.arena, self.range) {
+            (Some(arena), Some(range)) => (arena, range),
+            _ => return None,
+        };
+
+        let get = |offset: usize| arena.get_unknown_gen(offset).map(|(token, _)| token);
+        let is_trivia =
+            |offset: usize| get(offset).is_some_and(|token| token.token_type().ignore());
+        let ends_line = |offset: usize| {
+            get(offset).is_some_and(|token| match &*token.token_type() {
+                TokenType::Whitespace { characters } => characters.contains('\n'),
+                _ => false,
+            })
+        };
+
+        // The trivia after a token is everything up to the end of its line
+        let trailing_end = |offset: usize| {
+            let mut end = offset;
+            while is_trivia(end + 1) {
+                end += 1;
+                if ends_line(end) {
+                    break;
+                }
+            }
+            end
+        };
+
+        let mut previous = first;
+        while previous > 0 && is_trivia(previous - 1) {
+            previous -= 1;
+        }
+
+        let start = match previous.checked_sub(1) {
+            Some(previous_token) => trailing_end(previous_token) + 1,
+            None => 0,
+        };
+
+        Some((start.min(first), trailing_end(last)))
+    }
+
+    fn print(&self, trivia: TriviaBehavior) -> String {
+        let (arena, (first, last), (start, end)) =
+            match (&self.arena, self.range, self.trivia_bounds()) {
+                (Some(arena), Some(range), Some(bounds)) => (arena, range, bounds),
+                _ => return String::new(),
+            };
+
+        let get = |offset: usize| arena.get_unknown_gen(offset).map(|(token, _)| token);
+        let (mut start, mut end) = match trivia {
+            TriviaBehavior::Trim => (first, last),
+            TriviaBehavior::Keep | TriviaBehavior::TrimToComments => (start, end),
+        };
+
+        if trivia == TriviaBehavior::TrimToComments {
+            let is_whitespace = |offset: usize| {
+                get(offset).is_some_and(|token| {
+                    matches!(&*token.token_type(), TokenType::Whitespace { .. })
+                })
+            };
+
+            while start < first && is_whitespace(start) {
+                start += 1;
+            }
+
+            while end > last && is_whitespace(end) {
+                end -= 1;
+            }
+        }
+
+        let mut output = String::new();
+        for offset in start..=end {
+            if let Some(token) = get(offset) {
+                write!(output, "{}", token).expect("writing to a string can't fail");
+            }
+        }
+
+        output
+    }
+}
+
+impl<'a> Visitor<'a> for TokenRange<'a> {
+    fn visit_node(&mut self, node: AnyNode<'a, '_>) {
+        match node {
+            AnyNode::TokenReference(token) => self.token(token),
+
+            // The tokens of contained spans aren't visited on their own
+            AnyNode::ContainedSpan(span) => {
+                let (open, close) = span.tokens();
+                self.token(open);
+                self.token(close);
+            }
+
+            _ => {}
+        }
+    }
 }
 
 impl<T: Node> Node for &T {
@@ -82,6 +469,10 @@ impl<T: Node> Node for &T {
     fn similar(&self, other: &Self) -> bool {
         (**self).similar(other)
     }
+
+    fn id(&self) -> Option<NodeId> {
+        (**self).id()
+    }
 }
 
 impl<T: Node> Node for &mut T {
@@ -96,6 +487,10 @@ impl<T: Node> Node for &mut T {
     fn similar(&self, other: &Self) -> bool {
         (**self).similar(other)
     }
+
+    fn id(&self) -> Option<NodeId> {
+        (**self).id()
+    }
 }
 
 impl<'a> Node for Token<'a> {
@@ -110,6 +505,10 @@ impl<'a> Node for Token<'a> {
     fn similar(&self, other: &Self) -> bool {
         *self.token_type() == *other.token_type()
     }
+
+    fn id(&self) -> Option<NodeId> {
+        Some(self.token_type.id)
+    }
 }
 
 impl<'a> Node for TokenReference<'a> {
@@ -124,6 +523,10 @@ impl<'a> Node for TokenReference<'a> {
     fn similar(&self, other: &Self) -> bool {
         (**self).similar(other)
     }
+
+    fn id(&self) -> Option<NodeId> {
+        (**self).id()
+    }
 }
 
 impl<T: Node> Node for Option<T> {
@@ -142,6 +545,10 @@ impl<T: Node> Node for Option<T> {
             _ => false,
         }
     }
+
+    fn id(&self) -> Option<NodeId> {
+        self.as_ref()?.id()
+    }
 }
 
 impl<T: Node> Node for Vec<T> {
@@ -165,7 +572,7 @@ impl<T: Node> Node for Vec<T> {
 impl<A: Node, B: Node> Node for (A, B) {
     fn start_position(&self) -> Option<Position> {
         match (self.0.start_position(), self.1.start_position()) {
-            (Some(x), Some(y)) => Some(std::cmp::min(x, y)),
+            (Some(x), Some(y)) => Some(core::cmp::min(x, y)),
             (Some(x), None) => Some(x),
             (None, Some(y)) => Some(y),
             (None, None) => None,
@@ -174,7 +581,7 @@ impl<A: Node, B: Node> Node for (A, B) {
 
     fn end_position(&self) -> Option<Position> {
         match (self.0.end_position(), self.1.end_position()) {
-            (Some(x), Some(y)) => Some(std::cmp::max(x, y)),
+            (Some(x), Some(y)) => Some(core::cmp::max(x, y)),
             (Some(x), None) => Some(x),
             (None, Some(y)) => Some(y),
             (None, None) => None,
@@ -184,4 +591,8 @@ impl<A: Node, B: Node> Node for (A, B) {
     fn similar(&self, other: &Self) -> bool {
         self.0.similar(&other.0) && self.1.similar(&other.1)
     }
+
+    fn id(&self) -> Option<NodeId> {
+        self.0.id().or_else(|| self.1.id())
+    }
 }
@@ -0,0 +1,103 @@
+use crate::{
+    ast::{Ast, ExpectedItem, ParseConfig},
+    prelude::*,
+    tokenizer::{self, Token},
+    Error,
+};
+
+/// Parses code like [`parse_with`](fn.parse_with.html), keeping the buffers it uses between calls, so parsing many files
+/// doesn't grow them from nothing every time. The Asts are the same as from `parse_with`.
+///
+/// ```rust
+/// let mut parser = full_moon::Parser::new();
+/// for code in &["local x = 1", "print(x)", "return"] {
+///     let ast = parser.parse(code).unwrap();
+///     assert_eq!(full_moon::print(&ast), *code);
+/// }
+///
+/// assert!(parser.parse("local x = ").is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Parser {
+    config: ParseConfig,
+    // Always empty between calls, and only kept for its capacity
+    tokens: Vec<Token<'static>>,
+    expected: Vec<ExpectedItem>,
+}
+
+impl Parser {
+    /// A parser that uses the default [`ParseConfig`](struct.ParseConfig.html)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A parser that uses the options of the [config](struct.ParseConfig.html)
+    pub fn with_config(config: ParseConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// A parser that has room for the tokens of code with this many tokens before it needs to grow,
+    /// counting the comments, whitespace, and the end of file.
+    pub fn with_capacity(tokens: usize) -> Self {
+        Self {
+            tokens: Vec::with_capacity(tokens),
+            ..Self::default()
+        }
+    }
+
+    /// The options the parser uses
+    pub fn config(&self) -> &ParseConfig {
+        &self.config
+    }
+
+    /// Creates an [`Ast`](ast/struct.Ast.html) from Lua code, the same as [`parse_with`](fn.parse_with.html) would
+    /// with the config of the parser.
+    ///
+    /// # Errors
+    /// The same as `parse_with`.
+    pub fn parse<'a>(&mut self, code: &'a str) -> Result<Ast<'a>, Error<'a>> {
+        let mut tokens = lifetime(core::mem::take(&mut self.tokens));
+        let result =
+            match tokenizer::tokenize_into(code, false, self.config.preserve_trivia, &mut tokens) {
+                Ok(_) => Ast::from_tokens_reusing(&mut tokens, &self.config, &mut self.expected)
+                    .map(|ast| ast.with_source(code, &self.config))
+                    .map_err(|(error, _)| Error::AstError(error)),
+                Err(error) => Err(Error::TokenizerError(error)),
+            };
+
+        self.tokens = lifetime(tokens);
+        result
+    }
+}
+
+// Empties the vector and gives back its allocation as a vector of tokens with another lifetime.
+// Collecting the same vector into items of the same size reuses its allocation, and there are no items to change.
+fn lifetime<'a, 'b>(mut tokens: Vec<Token<'a>>) -> Vec<Token<'b>> {
+    tokens.clear();
+    tokens
+        .into_iter()
+        .map(|_| unreachable!("the tokens were cleared"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_are_reused() {
+        let mut parser = Parser::new();
+        parser.parse("local x = 1").unwrap();
+        let pointer = parser.tokens.as_ptr();
+        let capacity = parser.tokens.capacity();
+        assert!(capacity >= 8);
+
+        parser.parse("x = 1").unwrap();
+        assert!(parser.tokens.is_empty());
+        assert_eq!(parser.tokens.as_ptr(), pointer);
+        assert_eq!(parser.tokens.capacity(), capacity);
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{
     ast::{Ast, AstError},
+    node::StoredId,
+    prelude::*,
     tokenizer::{Token, TokenReference, TokenType, TokenizerError},
     Error,
 };
-use std::borrow::Cow;
+use alloc::borrow::Cow;
 
 pub trait Sealed {}
 
@@ -13,6 +15,7 @@ impl<T: Clone> Sealed for Cow<'_, T> {}
 impl Sealed for Ast<'_> {}
 impl Sealed for AstError<'_> {}
 impl Sealed for Error<'_> {}
+impl Sealed for StoredId {}
 impl Sealed for Token<'_> {}
 impl Sealed for TokenizerError {}
 impl Sealed for TokenReference<'_> {}
@@ -0,0 +1,175 @@
+use crate::{
+    ast::{Ast, Block, Expression, LastStmt, Stmt},
+    node::{Node, TriviaBehavior},
+    prelude::*,
+    tokenizer::{self, Token, TokenReference},
+    visitors::Visit,
+};
+
+/// The code of a value interpolated into [`lua_quote!`](../macro.lua_quote.html), made by
+/// [`Interpolate`](trait.Interpolate.html)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interpolation(String);
+
+/// Values that can be interpolated into [`lua_quote!`](../macro.lua_quote.html) with `#name`, which are
+/// tokens, such as names, and nodes parsed from code. Expressions are put in parentheses when they
+/// have operators, so they stay together, and `#a * 2` with `a` as `1 + 1` is `(1 + 1) * 2`.
+///
+/// # Panics
+/// Nodes that weren't parsed from code, such as ones that were deserialized, have no code to interpolate,
+/// so interpolating them panics.
+pub trait Interpolate {
+    /// The code of the value to put into the Lua code
+    fn interpolate(&self) -> Interpolation;
+}
+
+impl<T: Interpolate + ?Sized> Interpolate for &T {
+    fn interpolate(&self) -> Interpolation {
+        (**self).interpolate()
+    }
+}
+
+impl Interpolate for Token<'_> {
+    fn interpolate(&self) -> Interpolation {
+        Interpolation(self.to_string())
+    }
+}
+
+impl Interpolate for TokenReference<'_> {
+    fn interpolate(&self) -> Interpolation {
+        (**self).interpolate()
+    }
+}
+
+fn code<'a>(node: &(impl Node + Visit<'a>), kind: &str) -> String {
+    let code = node.print(TriviaBehavior::Trim);
+    if code.is_empty() && node.range().is_none() {
+        panic!(
+            "lua_quote! can't interpolate {} that wasn't parsed from code",
+            kind
+        );
+    }
+
+    code
+}
+
+impl Interpolate for Expression<'_> {
+    fn interpolate(&self) -> Interpolation {
+        let code = code(self, "an expression");
+        match self {
+            Expression::Value { binop: None, .. } | Expression::Parentheses { .. } => {
+                Interpolation(code)
+            }
+            _ => Interpolation(format!("({})", code)),
+        }
+    }
+}
+
+impl Interpolate for Stmt<'_> {
+    fn interpolate(&self) -> Interpolation {
+        Interpolation(code(self, "a statement"))
+    }
+}
+
+impl Interpolate for LastStmt<'_> {
+    fn interpolate(&self) -> Interpolation {
+        Interpolation(code(self, "a statement"))
+    }
+}
+
+impl Interpolate for Block<'_> {
+    fn interpolate(&self) -> Interpolation {
+        // An empty block has no code to print, unlike other nodes
+        if self.iter_stmts().next().is_none() && self.last_stmts().is_none() {
+            return Interpolation(String::new());
+        }
+
+        Interpolation(code(self, "a block"))
+    }
+}
+
+/// The nodes that [`lua_quote!`](../macro.lua_quote.html) can make, which it decides between from the
+/// type that is expected of it
+pub trait Quote: Sized {
+    /// Makes the node from the code, or the reason it can't be made
+    fn quote(code: &str) -> Result<Self, String>;
+}
+
+// Parses the code into an Ast with tokens that don't borrow it, so that the nodes of the Ast can be printed
+// and interpolated into other code, which nodes from `Ast::owned` can't be
+fn parse(code: &str) -> Result<Ast<'static>, String> {
+    let tokens = tokenizer::tokens(code).map_err(|error| error.to_string())?;
+    Ast::from_tokens(tokens.iter().map(Token::detached).collect())
+        .map_err(|error| error.to_string())
+}
+
+impl Quote for Ast<'static> {
+    fn quote(code: &str) -> Result<Self, String> {
+        parse(code)
+    }
+}
+
+impl Quote for Block<'static> {
+    fn quote(code: &str) -> Result<Self, String> {
+        Ok(parse(code)?.nodes().clone())
+    }
+}
+
+impl Quote for Stmt<'static> {
+    fn quote(code: &str) -> Result<Self, String> {
+        let ast = parse(code)?;
+        let mut stmts = ast.nodes().iter_stmts();
+        match (stmts.next(), stmts.next(), ast.nodes().last_stmts()) {
+            (Some(stmt), None, None) => Ok(stmt.clone()),
+            _ => Err("expected one statement".to_owned()),
+        }
+    }
+}
+
+impl Quote for LastStmt<'static> {
+    fn quote(code: &str) -> Result<Self, String> {
+        let ast = parse(code)?;
+        let block = ast.nodes();
+        match block.last_stmts() {
+            Some(last_stmt) if block.iter_stmts().next().is_none() => Ok(last_stmt.clone()),
+            _ => Err("expected one `return` or `break`".to_owned()),
+        }
+    }
+}
+
+impl Quote for Expression<'static> {
+    fn quote(code: &str) -> Result<Self, String> {
+        let ast = parse(&format!("return {}", code))?;
+        match ast.nodes().last_stmts() {
+            Some(LastStmt::Return(r#return)) if r#return.returns().len() == 1 => {
+                Ok(r#return.returns().iter().next().unwrap().clone())
+            }
+            _ => Err("expected one expression".to_owned()),
+        }
+    }
+}
+
+/// Used by [`lua_quote!`](../macro.lua_quote.html) to put the code of the interpolations between the pieces
+/// of code written in it, then make the node from it.
+///
+/// # Panics
+/// Panics if the code isn't the node, with the code in the message.
+#[doc(hidden)]
+pub fn quote<T: Quote>(pieces: &[&str], interpolations: Vec<Interpolation>) -> T {
+    let mut code = String::new();
+    let mut interpolations = interpolations.into_iter();
+    for piece in pieces {
+        code.push_str(piece);
+        if let Some(Interpolation(interpolation)) = interpolations.next() {
+            // The space before nothing, such as an empty block, would be next to the one after it
+            if interpolation.is_empty() && code.ends_with(' ') {
+                code.pop();
+            }
+
+            code.push_str(&interpolation);
+        }
+    }
+
+    T::quote(&code)
+        .unwrap_or_else(|error| panic!("lua_quote! made invalid code `{}`: {}", code, error))
+}
@@ -0,0 +1,259 @@
+use crate::{
+    analysis::scopes::{Resolution, ScopeAnalysis, Variable},
+    ast::Ast,
+    tokenizer::{self, Position, Token, TokenKind, TokenType},
+    TextEdit,
+};
+use std::{borrow::Cow, fmt};
+
+mod extract_function;
+mod inline_local;
+
+pub use self::extract_function::{extract_function, ExtractError, ExtractReport};
+pub use self::inline_local::{inline_local, inline_local_with, InlineConfig, InlineError};
+
+/// What [`rename_local`](fn.rename_local.html) changed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenameReport {
+    positions: Vec<Position>,
+    edits: Vec<TextEdit>,
+}
+
+impl RenameReport {
+    /// Where every name that was renamed started in the code before it was renamed,
+    /// including where the local was declared, in the order they appear in the code
+    pub fn positions(&self) -> &[Position] {
+        &self.positions
+    }
+
+    /// The edits that rename the local in the code it was parsed from, one for each name,
+    /// in order and without overlapping, which only replace the names and none of the whitespace or comments around them
+    pub fn edits(&self) -> &[TextEdit] {
+        &self.edits
+    }
+}
+
+/// An error given by [`rename_local`](fn.rename_local.html) when the local can't be renamed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenameError {
+    /// There is no name at the position
+    NotAName,
+    /// The name at the position is a global, which can't be renamed safely,
+    /// since code anywhere else can use it too
+    Global {
+        /// The name of the global
+        name: String,
+    },
+    /// The name at the position is the implicit `self` of a method, which has no name to rename
+    ImplicitSelf,
+    /// The new name isn't an identifier, such as `1x` or `a.b`
+    InvalidName {
+        /// The new name
+        name: String,
+    },
+    /// The new name is a keyword, such as `end`
+    Keyword {
+        /// The new name
+        name: String,
+    },
+    /// Renaming the local would change what a name refers to, such as when a local with the new name is used
+    /// where the renamed local would shadow it, or the local is used where a local with the new name shadows it
+    Conflict {
+        /// The new name
+        name: String,
+        /// Where what the new name already refers to is: the declaration of the local with the new name,
+        /// or the first use of a global with it
+        position: Position,
+    },
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenameError::NotAName => write!(formatter, "there is no name to rename here"),
+            RenameError::Global { name } => write!(
+                formatter,
+                "`{}` is a global, which can't be renamed safely",
+                name
+            ),
+            RenameError::ImplicitSelf => write!(
+                formatter,
+                "the implicit `self` of a method can't be renamed"
+            ),
+            RenameError::InvalidName { name } => {
+                write!(formatter, "`{}` isn't a valid name", name)
+            }
+            RenameError::Keyword { name } => {
+                write!(formatter, "`{}` is a keyword, so it can't be a name", name)
+            }
+            RenameError::Conflict { name, position } => write!(
+                formatter,
+                "renaming to `{}` would conflict with the `{}` at line {}, character {}",
+                name,
+                name,
+                position.line(),
+                position.character()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+/// Renames the local that is declared or used at the position, which only needs its bytes,
+/// changing the name everywhere the local is used and nothing else,
+/// and keeping the whitespace and comments around every name as they were.
+/// The positions of the tokens of the Ast are updated afterwards for the new name.
+///
+/// # Errors
+/// If there is no local at the position, or the new name isn't a name that can be used for a local,
+/// the Ast is left as it was. Renaming is checked to not change what any name refers to,
+/// so a rename that makes a local shadow another one that is used, or makes a use of the local refer to
+/// another local instead, is a [`Conflict`](enum.RenameError.html#variant.Conflict) rather than changing what the code does.
+///
+/// ```rust
+/// # use full_moon::refactor::{rename_local, RenameError};
+/// let mut ast = full_moon::parse("local x = 1\nprint(x) -- x").unwrap();
+/// let position_of = |ast: &full_moon::ast::Ast, name: &str| {
+///     ast.iter_tokens().find(|token| token.to_string() == name).unwrap().start_position()
+/// };
+///
+/// let declaration = position_of(&ast, "x");
+///
+/// let report = rename_local(&mut ast, declaration, "count").unwrap();
+/// assert_eq!(full_moon::print(&ast), "local count = 1\nprint(count) -- x");
+/// assert_eq!(report.edits().len(), 2);
+///
+/// let print = position_of(&ast, "print");
+/// assert!(matches!(rename_local(&mut ast, print, "show"), Err(RenameError::Global { .. })));
+/// ```
+pub fn rename_local(
+    ast: &mut Ast,
+    at: Position,
+    new_name: &str,
+) -> Result<RenameReport, RenameError> {
+    let analysis = ScopeAnalysis::new(ast);
+    let variable = variable_at(&analysis, at).map_err(|global| match global {
+        Some(name) => RenameError::Global { name },
+        None => RenameError::NotAName,
+    })?;
+
+    if variable.declaration().is_none() {
+        return Err(RenameError::ImplicitSelf);
+    }
+
+    check_name(new_name)?;
+
+    let mut positions = Vec::new();
+    let mut edits = Vec::new();
+    for reference in variable.references() {
+        let token = reference.token();
+        let start = Token::start_position(token);
+        positions.push(start);
+        edits.push(TextEdit::new(
+            (start.bytes(), Token::end_position(token).bytes()),
+            new_name.to_owned(),
+        ));
+    }
+    positions.sort();
+    edits.sort_by_key(TextEdit::range);
+
+    set_name(variable, Cow::Owned(new_name.to_owned()));
+    if let Some(error) = conflict(ast, &analysis, new_name) {
+        set_name(variable, Cow::Owned(variable.name().to_owned()));
+        return Err(error);
+    }
+
+    ast.update_positions();
+    Ok(RenameReport { positions, edits })
+}
+
+// The local with a name at the position, or the name of the global at it if there is one instead
+fn variable_at<'a, 'ast>(
+    analysis: &'a ScopeAnalysis<'ast>,
+    at: Position,
+) -> Result<&'a Variable<'ast>, Option<String>> {
+    let contains = |token: &Token| {
+        (token.start_position().bytes()..=token.end_position().bytes()).contains(&at.bytes())
+    };
+
+    analysis
+        .variables()
+        .find(|variable| {
+            variable
+                .references()
+                .iter()
+                .any(|reference| contains(reference.token()))
+        })
+        .ok_or_else(|| {
+            analysis
+                .globals()
+                .iter()
+                .find(|token| contains(token))
+                .map(|token| token.to_string())
+        })
+}
+
+// Checks that the name is an identifier the same way the tokenizer would read it
+fn check_name(name: &str) -> Result<(), RenameError> {
+    let tokens = tokenizer::tokens(name).map_err(|_| RenameError::InvalidName {
+        name: name.to_owned(),
+    })?;
+
+    match tokens.first().map(|token| token.token_type().kind()) {
+        Some(TokenKind::Identifier) if tokens.len() == 2 => Ok(()),
+        Some(TokenKind::Symbol)
+            if tokens.len() == 2 && matches!(name.chars().next(), Some(c) if c.is_alphabetic()) =>
+        {
+            Err(RenameError::Keyword {
+                name: name.to_owned(),
+            })
+        }
+        _ => Err(RenameError::InvalidName {
+            name: name.to_owned(),
+        }),
+    }
+}
+
+fn set_name<'ast>(variable: &Variable<'ast>, name: Cow<'ast, str>) {
+    for reference in variable.references() {
+        reference
+            .token()
+            .clone()
+            .set_token_type(TokenType::Identifier {
+                identifier: name.clone(),
+            });
+    }
+}
+
+// Finds the first name that refers to something else now that the local has been renamed,
+// which the tokens share with the analysis from before
+fn conflict<'ast>(
+    ast: &Ast<'ast>,
+    before: &ScopeAnalysis<'ast>,
+    new_name: &str,
+) -> Option<RenameError> {
+    let after = ScopeAnalysis::new(ast);
+
+    ast.iter_tokens().find_map(|token| {
+        let (old, new) = (before.resolve(token), after.resolve(token));
+        if old == new {
+            return None;
+        }
+
+        // Either the renamed local now refers to another local, or another name now refers to the renamed local
+        let other = match (old, new) {
+            (_, Resolution::Local(id)) if before.variable(id).name() == new_name => Some(id),
+            (Resolution::Local(id), _) if before.variable(id).name() == new_name => Some(id),
+            _ => None,
+        };
+        let position = other
+            .and_then(|id| before.variable(id).declaration_position())
+            .unwrap_or_else(|| token.start_position());
+
+        Some(RenameError::Conflict {
+            name: new_name.to_owned(),
+            position,
+        })
+    })
+}
@@ -0,0 +1,675 @@
+use super::{check_name, RenameError};
+use crate::{
+    analysis::scopes::{ReferenceKind, Resolution, ScopeAnalysis, Variable},
+    ast::{
+        reparse::SourceEdit, Ast, Block, FunctionBody, GenericFor, LastStmt, NumericFor, Repeat,
+        Stmt, Value, While,
+    },
+    node::Node,
+    tokenizer::{Position, Symbol, Token, TokenKind, TokenReference, TokenType},
+    visitors::{StmtComments, Visit, Visitor},
+    TextEdit,
+};
+use std::fmt;
+
+/// What [`extract_function`](fn.extract_function.html) changed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtractReport {
+    parameters: Vec<String>,
+    returns: Vec<String>,
+    edits: Vec<TextEdit>,
+}
+
+impl ExtractReport {
+    /// The parameters of the new function, which are the locals of the function the statements were in that they use,
+    /// in the order they're declared, with `...` last if the statements use it
+    pub fn parameters(&self) -> &[String] {
+        &self.parameters
+    }
+
+    /// What the new function returns, which are the locals declared by the statements that are used after them,
+    /// in the order they're declared. The call declares them again with its results.
+    pub fn returns(&self) -> &[String] {
+        &self.returns
+    }
+
+    /// The edits that extract the function in the code it was parsed from, in order and without overlapping:
+    /// inserting the new function, then replacing the statements with the call to it
+    pub fn edits(&self) -> &[TextEdit] {
+        &self.edits
+    }
+}
+
+/// An error given by [`extract_function`](fn.extract_function.html) when the statements can't be extracted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExtractError {
+    /// The name of the function isn't an identifier, such as `1x` or `a.b`
+    InvalidName {
+        /// The name
+        name: String,
+    },
+    /// The name of the function is a keyword, such as `end`
+    Keyword {
+        /// The name
+        name: String,
+    },
+    /// The range isn't a run of whole statements in one block, other than whitespace and comments around them
+    NotStatements,
+    /// A `break` or `return` in the statements would leave the new function instead of the loop or function
+    /// the statements are in
+    Escapes {
+        /// Where the `break` or `return` is
+        position: Position,
+    },
+    /// A local the statements set would be set in a copy of it instead, such as a local of the function the
+    /// statements are in, which is given to the new function as a parameter, or a local they declare and give to
+    /// a function in them, which is set after them
+    Written {
+        /// The name of the local
+        name: String,
+        /// Where it is set
+        position: Position,
+    },
+    /// The name of the function is already used where the function would be declared,
+    /// so uses of it would refer to the new function instead
+    Conflict {
+        /// The name
+        name: String,
+        /// Where it is used
+        position: Position,
+    },
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtractError::InvalidName { name } => {
+                write!(formatter, "`{}` isn't a valid name", name)
+            }
+            ExtractError::Keyword { name } => {
+                write!(formatter, "`{}` is a keyword, so it can't be a name", name)
+            }
+            ExtractError::NotStatements => write!(
+                formatter,
+                "the range isn't a run of whole statements in one block"
+            ),
+            ExtractError::Escapes { position } => write!(
+                formatter,
+                "the `break` or `return` at line {}, character {} would leave the new function instead",
+                position.line(),
+                position.character()
+            ),
+            ExtractError::Written { name, position } => write!(
+                formatter,
+                "`{}` is set at line {}, character {}, which would set a copy of it in the new function",
+                name,
+                position.line(),
+                position.character()
+            ),
+            ExtractError::Conflict { name, position } => write!(
+                formatter,
+                "the function would conflict with the `{}` at line {}, character {}",
+                name,
+                position.line(),
+                position.character()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+/// Moves the statements in the range, which only needs its bytes, into a new local function, replacing them with a
+/// call to it, such as turning `local total = a + b; print(total)` in a function into `local function sum(a, b)`
+/// before the function and `local total = sum(a, b)`. The new function is declared right before the statement with
+/// the function the statements are in, along with the comments before it, or right before the statements when they
+/// aren't in a function, and is indented one level more than that statement.
+///
+/// The locals of the function the statements are in that they use become the parameters of the new function, with
+/// `...` if they use it, and the locals they declare that are used after them are returned by it, and declared again
+/// by the call, so `local a, b = name(x, y)`. Locals from outside of that function are used by the new function
+/// the same way. The code is parsed again with [`Ast::reparse`](../ast/struct.Ast.html#method.reparse) afterwards,
+/// so the positions of the tokens of the Ast need to be up to date, such as with
+/// [`update_positions`](../ast/struct.Ast.html#method.update_positions).
+///
+/// # Errors
+/// The range can have whitespace and comments around the statements, but no part of any other statement.
+/// Extracting is checked to not change what the statements do, so a `break` or `return` that would leave the new
+/// function, a parameter that the statements set, and a name for the function that is already used where it would be
+/// declared are errors. Without `goto` in the syntax, those are all of the ways for the statements to leave.
+/// If the statements can't be extracted, the Ast is left as it was.
+///
+/// ```rust
+/// # use full_moon::refactor::extract_function;
+/// let code = "local function area(width, height)\n    local size = width * height\n    return size\nend";
+/// let mut ast = full_moon::parse(code).unwrap();
+///
+/// let position = |bytes| {
+///     ast.iter_tokens().map(|token| token.start_position()).find(|position| position.bytes() == bytes).unwrap()
+/// };
+/// let range = (position(code.find("local size").unwrap()), position(code.find("\n    return").unwrap()));
+/// let report = extract_function(&mut ast, range, "multiply").unwrap();
+///
+/// assert_eq!(report.parameters(), ["width", "height"]);
+/// assert_eq!(report.returns(), ["size"]);
+/// assert_eq!(
+///     full_moon::print(&ast),
+///     "local function multiply(width, height)
+///     local size = width * height
+///     return size
+/// end
+///
+/// local function area(width, height)
+///     local size = multiply(width, height)
+///     return size
+/// end"
+/// );
+/// ```
+pub fn extract_function(
+    ast: &mut Ast,
+    range: (Position, Position),
+    name: &str,
+) -> Result<ExtractReport, ExtractError> {
+    check_name(name).map_err(|error| match error {
+        RenameError::Keyword { name } => ExtractError::Keyword { name },
+        _ => ExtractError::InvalidName {
+            name: name.to_owned(),
+        },
+    })?;
+
+    let mut selection = Selection {
+        range: (range.0.bytes(), range.1.bytes()),
+        found: None,
+    };
+    ast.nodes().visit(&mut selection);
+    let (stmts, selected_last) = selection.found.ok_or(ExtractError::NotStatements)?;
+    if let Some(position) = selected_last {
+        return Err(ExtractError::Escapes { position });
+    }
+
+    let (start, end) = bytes_of(&stmts).ok_or(ExtractError::NotStatements)?;
+    let outside = ast.tokens.iter().any(|(_, token)| {
+        let bytes = token.start_position().bytes();
+        !token.token_type().ignore()
+            && token.token_kind() != TokenKind::Eof
+            && (range.0.bytes()..range.1.bytes()).contains(&bytes)
+            && !(start..end).contains(&bytes)
+    });
+    if outside {
+        return Err(ExtractError::NotStatements);
+    }
+
+    let mut escapes = Escapes::default();
+    for (stmt, _) in &stmts {
+        stmt.visit(&mut escapes);
+    }
+    if let Some(position) = escapes.escape {
+        return Err(ExtractError::Escapes { position });
+    }
+
+    let mut enclosing = Enclosing {
+        selection: (start, end),
+        bodies: Vec::new(),
+        stmts: Vec::new(),
+    };
+    ast.nodes().visit(&mut enclosing);
+    let (insertion, block_end) = match enclosing.insertion() {
+        Some((stmt, block_end)) => (stmt, block_end),
+        None => (
+            (start, end),
+            ast.nodes().range().map_or(end, |(_, end)| end.bytes()),
+        ),
+    };
+
+    // The comments before the statement go before the new function too
+    let insertion = stmt_at(ast, insertion.0)
+        .and_then(|stmt| {
+            StmtComments::new(stmt)
+                .leading()
+                .first()
+                .map(|comment| Token::start_position(comment).bytes())
+        })
+        .unwrap_or(insertion.0);
+
+    let analysis = ScopeAnalysis::new(ast);
+    let in_selection = |token: &Token| (start..end).contains(&token.start_position().bytes());
+
+    let mut parameters: Vec<&Variable> = Vec::new();
+    let mut returns: Vec<&Variable> = Vec::new();
+    for variable in analysis.variables() {
+        let references = variable.references();
+        if !references
+            .iter()
+            .any(|reference| in_selection(reference.token()))
+        {
+            continue;
+        }
+
+        let declared = variable.declaration_position();
+        match declared {
+            // From outside of the function the statements are in, which the new function can use too
+            Some(declared) if declared.bytes() < insertion => {}
+
+            Some(declared) if (start..end).contains(&declared.bytes()) => {
+                if !references
+                    .iter()
+                    .any(|reference| Token::start_position(reference.token()).bytes() >= end)
+                {
+                    continue;
+                }
+
+                // A function in the statements would keep the local of the new function, which is never set again
+                let depth = references
+                    .iter()
+                    .find(|reference| reference.is_declaration())
+                    .map(|reference| reference.function_depth());
+                let captured = references.iter().any(|reference| {
+                    in_selection(reference.token()) && Some(reference.function_depth()) != depth
+                });
+                let written_after = references.iter().find(|reference| {
+                    reference.kind() == ReferenceKind::Write
+                        && Token::start_position(reference.token()).bytes() >= end
+                });
+
+                if let (true, Some(write)) = (captured, written_after) {
+                    return Err(ExtractError::Written {
+                        name: variable.name().to_owned(),
+                        position: Token::start_position(write.token()),
+                    });
+                }
+
+                returns.push(variable);
+            }
+
+            _ => {
+                let write = references.iter().find(|reference| {
+                    reference.kind() == ReferenceKind::Write
+                        && !reference.is_declaration()
+                        && in_selection(reference.token())
+                });
+
+                if let Some(write) = write {
+                    return Err(ExtractError::Written {
+                        name: variable.name().to_owned(),
+                        position: Token::start_position(write.token()),
+                    });
+                }
+
+                parameters.push(variable);
+            }
+        }
+    }
+
+    // Uses of the name after the function is declared would refer to it instead
+    let conflict = ast.tokens.iter().find(|(_, token)| {
+        (insertion..block_end).contains(&Token::start_position(token).bytes())
+            && token.to_string() == name
+            && analysis.resolve(token) != Resolution::NotAVariable
+    });
+    if let Some((_, token)) = conflict {
+        return Err(ExtractError::Conflict {
+            name: name.to_owned(),
+            position: token.start_position(),
+        });
+    }
+
+    parameters.sort_by_key(|variable| variable.declaration_position());
+    returns.sort_by_key(|variable| variable.declaration_position());
+
+    let mut parameters: Vec<String> = parameters
+        .iter()
+        .map(|variable| variable.name().to_owned())
+        .collect();
+    let returns: Vec<String> = returns
+        .iter()
+        .map(|variable| variable.name().to_owned())
+        .collect();
+
+    let mut varargs = Varargs::default();
+    for (stmt, _) in &stmts {
+        stmt.visit(&mut varargs);
+    }
+    if varargs.found {
+        parameters.push("...".to_owned());
+    }
+
+    let code = crate::print(ast);
+    let indent = indentation(&code, insertion).unwrap_or("");
+    let body_indent = format!(
+        "{}{}",
+        indent,
+        if code[..start].contains('\t') {
+            "\t"
+        } else {
+            "    "
+        }
+    );
+    let old_indent = indentation(&code, start).unwrap_or("");
+
+    let mut function = format!(
+        "local function {}({})\n{}",
+        name,
+        parameters.join(", "),
+        body_indent
+    );
+    function.push_str(&reindent(ast, (start, end), old_indent, &body_indent));
+    if !returns.is_empty() {
+        function.push_str(&format!("\n{}return {}", body_indent, returns.join(", ")));
+    }
+    function.push_str(&format!("\n{}end\n\n{}", indent, indent));
+
+    let call = format!("{}({})", name, parameters.join(", "));
+    let call = if returns.is_empty() {
+        call
+    } else {
+        format!("local {} = {}", returns.join(", "), call)
+    };
+
+    let edits = vec![
+        TextEdit::new((insertion, insertion), function.clone()),
+        TextEdit::new((start, end), call.clone()),
+    ];
+
+    let text = format!("{}{}{}", function, &code[insertion..start], call);
+    ast.reparse(SourceEdit::new(insertion..end, text))
+        .expect("the extracted function should parse");
+
+    Ok(ExtractReport {
+        parameters,
+        returns,
+        edits,
+    })
+}
+
+// Statements of a block, with their semicolons
+type Stmts<'ast> = Vec<(Stmt<'ast>, Option<TokenReference<'ast>>)>;
+
+// The start and end bytes of the statements, including the semicolon of the last one
+fn bytes_of(stmts: &Stmts) -> Option<(usize, usize)> {
+    let (first, _) = stmts.first()?;
+    let (last, semicolon) = stmts.last()?;
+    let end = match semicolon {
+        Some(semicolon) => Token::end_position(semicolon),
+        None => last.end_position()?,
+    };
+
+    Some((first.start_position()?.bytes(), end.bytes()))
+}
+
+// The whitespace before the bytes on their line, if there is nothing else before them on it
+fn indentation(code: &str, bytes: usize) -> Option<&str> {
+    let line_start = code[..bytes].rfind('\n').map_or(0, |newline| newline + 1);
+    let indent = &code[line_start..bytes];
+    if indent
+        .chars()
+        .all(|character| character == ' ' || character == '\t')
+    {
+        Some(indent)
+    } else {
+        None
+    }
+}
+
+// The code of the tokens in the range, with the indentation at the start of every line after the first changed from
+// the old one to the new one. Only whitespace is changed, so multiline strings and comments keep their lines.
+fn reindent(ast: &Ast, range: (usize, usize), old_indent: &str, new_indent: &str) -> String {
+    let mut code = String::new();
+    let mut line_start = false;
+
+    for (_, token) in ast.tokens.iter() {
+        if !(range.0..range.1).contains(&token.start_position().bytes()) {
+            continue;
+        }
+
+        let text = token.to_string();
+        if line_start {
+            match &*token.token_type() {
+                TokenType::Whitespace { characters } if !characters.contains('\n') => {
+                    match characters.strip_prefix(old_indent) {
+                        Some(rest) => {
+                            code.push_str(new_indent);
+                            code.push_str(rest);
+                        }
+                        None => code.push_str(characters),
+                    }
+                    line_start = false;
+                    continue;
+                }
+                TokenType::Whitespace { .. } => {}
+                _ if old_indent.is_empty() => code.push_str(new_indent),
+                _ => {}
+            }
+        }
+
+        line_start = text.ends_with('\n');
+        code.push_str(&text);
+    }
+
+    code
+}
+
+// The statement of the Ast that starts at the bytes, for finding its comments
+fn stmt_at<'a, 'ast>(ast: &'a Ast<'ast>, bytes: usize) -> Option<&'a Stmt<'ast>> {
+    fn find<'a, 'ast>(block: &'a Block<'ast>, bytes: usize) -> Option<&'a Stmt<'ast>> {
+        block.iter_stmts().find_map(|stmt| {
+            let (start, end) = stmt.range()?;
+            if start.bytes() == bytes {
+                Some(stmt)
+            } else if start.bytes() < bytes && bytes < end.bytes() {
+                inner_blocks(stmt)
+                    .into_iter()
+                    .find_map(|block| find(block, bytes))
+            } else {
+                None
+            }
+        })
+    }
+
+    find(ast.nodes(), bytes)
+}
+
+// The blocks of a statement, other than those in the functions in its expressions
+fn inner_blocks<'a, 'ast>(stmt: &'a Stmt<'ast>) -> Vec<&'a Block<'ast>> {
+    match stmt {
+        Stmt::Do(do_block) => vec![do_block.block()],
+        Stmt::FunctionDeclaration(declaration) => vec![declaration.body().block()],
+        Stmt::GenericFor(generic_for) => vec![generic_for.block()],
+        Stmt::If(if_statement) => std::iter::once(if_statement.block())
+            .chain(
+                if_statement
+                    .else_if()
+                    .into_iter()
+                    .flatten()
+                    .map(|else_if| else_if.block()),
+            )
+            .chain(if_statement.else_block())
+            .collect(),
+        Stmt::LocalFunction(local_function) => vec![local_function.func_body().block()],
+        Stmt::NumericFor(numeric_for) => vec![numeric_for.block()],
+        Stmt::Repeat(repeat) => vec![repeat.block()],
+        Stmt::While(while_loop) => vec![while_loop.block()],
+        _ => Vec::new(),
+    }
+}
+
+// Finds the outermost block with statements in the range, with where its last statement starts if it's in the range
+struct Selection<'ast> {
+    range: (usize, usize),
+    found: Option<(Stmts<'ast>, Option<Position>)>,
+}
+
+impl<'ast> Visitor<'ast> for Selection<'ast> {
+    fn visit_block(&mut self, block: &Block<'ast>) {
+        if self.found.is_some() {
+            return;
+        }
+
+        let range = self.range;
+        let inside = |node: Option<(Position, Position)>| {
+            node.is_some_and(|(start, end)| range.0 <= start.bytes() && end.bytes() <= range.1)
+        };
+
+        let stmts: Vec<_> = block
+            .stmts
+            .iter()
+            .filter(|(stmt, _)| inside(stmt.range()))
+            .cloned()
+            .collect();
+        let last = block
+            .last_stmts()
+            .filter(|last| inside(last.range()))
+            .and_then(Node::start_position);
+
+        if !stmts.is_empty() || last.is_some() {
+            self.found = Some((stmts, last));
+        }
+    }
+}
+
+// Finds the first `break` or `return` in statements that would leave them
+#[derive(Default)]
+struct Escapes {
+    loops: usize,
+    functions: usize,
+    escape: Option<Position>,
+}
+
+impl<'ast> Visitor<'ast> for Escapes {
+    fn visit_function_body(&mut self, _: &FunctionBody<'ast>) {
+        self.functions += 1;
+    }
+
+    fn visit_function_body_end(&mut self, _: &FunctionBody<'ast>) {
+        self.functions -= 1;
+    }
+
+    fn visit_while(&mut self, _: &While<'ast>) {
+        self.loops += 1;
+    }
+
+    fn visit_while_end(&mut self, _: &While<'ast>) {
+        self.loops -= 1;
+    }
+
+    fn visit_repeat(&mut self, _: &Repeat<'ast>) {
+        self.loops += 1;
+    }
+
+    fn visit_repeat_end(&mut self, _: &Repeat<'ast>) {
+        self.loops -= 1;
+    }
+
+    fn visit_numeric_for(&mut self, _: &NumericFor<'ast>) {
+        self.loops += 1;
+    }
+
+    fn visit_numeric_for_end(&mut self, _: &NumericFor<'ast>) {
+        self.loops -= 1;
+    }
+
+    fn visit_generic_for(&mut self, _: &GenericFor<'ast>) {
+        self.loops += 1;
+    }
+
+    fn visit_generic_for_end(&mut self, _: &GenericFor<'ast>) {
+        self.loops -= 1;
+    }
+
+    fn visit_last_stmt(&mut self, last_stmt: &LastStmt<'ast>) {
+        let escapes = match last_stmt {
+            LastStmt::Break(_) => self.loops == 0 && self.functions == 0,
+            LastStmt::Return(_) => self.functions == 0,
+        };
+
+        if escapes && self.escape.is_none() {
+            self.escape = last_stmt.start_position();
+        }
+    }
+}
+
+// Finds whether statements use `...` outside of the functions in them
+#[derive(Default)]
+struct Varargs {
+    functions: usize,
+    found: bool,
+}
+
+impl<'ast> Visitor<'ast> for Varargs {
+    fn visit_function_body(&mut self, _: &FunctionBody<'ast>) {
+        self.functions += 1;
+    }
+
+    fn visit_function_body_end(&mut self, _: &FunctionBody<'ast>) {
+        self.functions -= 1;
+    }
+
+    fn visit_value(&mut self, value: &Value<'ast>) {
+        if let Value::Symbol(token) = value {
+            if self.functions == 0
+                && matches!(
+                    &*token.token_type(),
+                    TokenType::Symbol {
+                        symbol: Symbol::Ellipse
+                    }
+                )
+            {
+                self.found = true;
+            }
+        }
+    }
+}
+
+// Finds the functions and the statements with the statements in them, with the end of the blocks those statements
+// are in, for finding the innermost statement with the innermost function
+struct Enclosing {
+    selection: (usize, usize),
+    bodies: Vec<(usize, usize)>,
+    stmts: Vec<((usize, usize), usize)>,
+}
+
+impl Enclosing {
+    fn contains(&self, range: Option<(Position, Position)>) -> Option<(usize, usize)> {
+        let (start, end) = range?;
+        let (start, end) = (start.bytes(), end.bytes());
+        if start <= self.selection.0 && self.selection.1 <= end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+
+    // The start and end of the statement with the innermost function, and the end of the block that statement is in
+    fn insertion(&self) -> Option<((usize, usize), usize)> {
+        let body = self.bodies.iter().max_by_key(|(start, _)| start)?;
+        self.stmts
+            .iter()
+            .filter(|((start, end), _)| *start <= body.0 && body.1 <= *end)
+            .max_by_key(|((start, _), _)| start)
+            .copied()
+    }
+}
+
+impl<'ast> Visitor<'ast> for Enclosing {
+    fn visit_function_body(&mut self, body: &FunctionBody<'ast>) {
+        if let Some(range) = self.contains(body.range()) {
+            self.bodies.push(range);
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block<'ast>) {
+        let end = match block.range() {
+            Some((_, end)) => end.bytes(),
+            None => return,
+        };
+
+        let stmts = block
+            .iter_stmts()
+            .map(Node::range)
+            .chain(block.last_stmts().map(Node::range));
+        for stmt in stmts {
+            if let Some(range) = self.contains(stmt) {
+                self.stmts.push((range, end));
+            }
+        }
+    }
+}
@@ -0,0 +1,1164 @@
+use super::variable_at;
+use crate::{
+    analysis::scopes::{
+        ReferenceInfo, ReferenceKind, Resolution, ScopeAnalysis, ScopeId, Variable, VariableId,
+        VariableKind,
+    },
+    ast::{
+        memory::Reborrow,
+        simplified::{SBinOp, UNARY_PRIORITY},
+        span::ContainedSpan,
+        Ast, BinOpRhs, Block, Expression, FunctionCall, LocalAssignment, Prefix, Stmt, Suffix,
+        TableConstructor, Value, Var, VarExpression,
+    },
+    node::{Node, StoredId, TokenRange},
+    tokenizer::{Position, Symbol, Token, TokenKind, TokenReference, TokenType},
+    transform::ensure_valid_spacing,
+    visitors::{ScopeKind, Visit, VisitMut, Visitor, VisitorMut},
+};
+use generational_arena::Arena;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, mem,
+    sync::Arc,
+};
+
+/// The options for [`inline_local_with`](fn.inline_local_with.html).
+/// Use [`InlineConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InlineConfig {
+    /// Whether to keep the comments of the declaration that is removed, which are the comments on the lines
+    /// right before it and after it on its line. They're left where they were, so the comments before it
+    /// go before the code after it. False by default, so they're removed with the declaration.
+    pub keep_comments: bool,
+}
+
+/// An error given by [`inline_local`](fn.inline_local.html) when the local can't be inlined
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InlineError {
+    /// There is no name at the position
+    NotAName,
+    /// The name at the position is a global, which has no declaration to inline
+    Global {
+        /// The name of the global
+        name: String,
+    },
+    /// The local isn't declared by a `local` with a value, such as a parameter, the variable of a loop,
+    /// a `local function`, or `local x` with no value
+    NoInitializer,
+    /// The local is declared with other locals or values, such as `local x, y = 1, 2` or `local x = f, g`,
+    /// which can't be taken apart without changing which values they get
+    MultipleValues,
+    /// The local is set after it is declared, so it doesn't always have the value it's declared with
+    Written {
+        /// Where it is set first
+        position: Position,
+    },
+    /// The value of the local calls a function or uses `...`, which would run again or mean something else
+    /// everywhere the value is put
+    SideEffect {
+        /// Where the call or `...` is
+        position: Position,
+    },
+    /// The value of the local makes a table or a function, and the local is used more than once or in a loop
+    /// or function, where each use would make a different one instead of using the same one
+    NewValue {
+        /// Where the table or function is made
+        position: Position,
+    },
+    /// A name in the value of the local refers to something else where the local is used,
+    /// since a local with the same name is declared in between
+    Shadowed {
+        /// The name
+        name: String,
+        /// Where the local is used
+        position: Position,
+    },
+    /// A local used by the value of the local is set after the value is made, or in a function,
+    /// so the value could be different where the local is used
+    Changed {
+        /// The name of the other local
+        name: String,
+        /// Where the other local is set
+        position: Position,
+    },
+    /// The local is used where its value can't be written, such as `t` in `function t.f() end`,
+    /// when it isn't a name
+    UnsupportedRead {
+        /// Where the local is used
+        position: Position,
+    },
+    /// The value of the local reads a global or a field, and an assignment to it or a call,
+    /// which could set any of them, is between the declaration and where the local is used,
+    /// or the local is used in a function, which could be called after code outside of the Ast changes them
+    MayChange {
+        /// Where the assignment or call is, or where the local is used in a function when there isn't one
+        position: Position,
+    },
+}
+
+impl fmt::Display for InlineError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InlineError::NotAName => write!(formatter, "there is no name to inline here"),
+            InlineError::Global { name } => write!(
+                formatter,
+                "`{}` is a global, which has no declaration to inline",
+                name
+            ),
+            InlineError::NoInitializer => write!(
+                formatter,
+                "the local isn't declared by a `local` with a value, so there is no value to inline"
+            ),
+            InlineError::MultipleValues => write!(
+                formatter,
+                "the local is declared with other locals or values, which can't be taken apart"
+            ),
+            InlineError::Written { position } => write!(
+                formatter,
+                "the local is set at line {}, character {}, so it doesn't always have the value it's declared with",
+                position.line(),
+                position.character()
+            ),
+            InlineError::SideEffect { position } => write!(
+                formatter,
+                "the value of the local has a side effect at line {}, character {}",
+                position.line(),
+                position.character()
+            ),
+            InlineError::NewValue { position } => write!(
+                formatter,
+                "the value of the local makes a new table or function at line {}, character {}, which inlining would make more than once",
+                position.line(),
+                position.character()
+            ),
+            InlineError::Shadowed { name, position } => write!(
+                formatter,
+                "`{}` in the value of the local refers to something else where the local is used at line {}, character {}",
+                name,
+                position.line(),
+                position.character()
+            ),
+            InlineError::Changed { name, position } => write!(
+                formatter,
+                "`{}` in the value of the local is set at line {}, character {}, so the value could be different where the local is used",
+                name,
+                position.line(),
+                position.character()
+            ),
+            InlineError::UnsupportedRead { position } => write!(
+                formatter,
+                "the local is used at line {}, character {} where its value can't be written",
+                position.line(),
+                position.character()
+            ),
+            InlineError::MayChange { position } => write!(
+                formatter,
+                "what the value of the local reads could be changed before the local is used, at line {}, character {}",
+                position.line(),
+                position.character()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InlineError {}
+
+/// Inlines the local that is declared or used at the position, using [`InlineConfig::default()`](struct.InlineConfig.html).
+/// See [`inline_local_with`](fn.inline_local_with.html) for what is changed.
+///
+/// ```rust
+/// # use full_moon::refactor::{inline_local, InlineError};
+/// let mut ast = full_moon::parse("local size = width * 2\nprint(size, -size, size ^ 2)").unwrap();
+/// let position = ast.iter_tokens().find(|token| token.to_string() == "size").unwrap().start_position();
+///
+/// inline_local(&mut ast, position).unwrap();
+/// assert_eq!(full_moon::print(&ast), "print(width * 2, -(width * 2), (width * 2) ^ 2)");
+///
+/// let mut ast = full_moon::parse("local value = compute()\nprint(value)").unwrap();
+/// let position = ast.iter_tokens().find(|token| token.to_string() == "value").unwrap().start_position();
+/// assert!(matches!(inline_local(&mut ast, position), Err(InlineError::SideEffect { .. })));
+/// ```
+pub fn inline_local(ast: &mut Ast, at: Position) -> Result<(), InlineError> {
+    inline_local_with(ast, at, &InlineConfig::default())
+}
+
+/// Inlines the local that is declared or used at the position, which only needs its bytes, replacing every use of it
+/// with a copy of the value it is declared with, and removing its declaration, such as turning
+/// `local total = a + b; print(total)` into `print(a + b)`. The copies are put in parentheses where the operators
+/// around them would hold their operands more tightly than the operators in them, or where they're called or indexed,
+/// such as `("x"):upper()`. Each copy is put between the comments and whitespace of the name it replaces,
+/// and the line of the declaration is removed with it if nothing else is on it.
+/// The positions of the tokens of the Ast are updated afterwards.
+///
+/// # Errors
+/// The local has to be declared on its own with one value, such as `local x = 1`, and never be set afterwards.
+/// Its value can't call anything or use `...`, since those would run where the local is used instead of where it's
+/// declared, and can't make a table or function unless it's used once and not in a loop or function, since every copy
+/// would make a different one. The names in its value have to refer to the same locals and globals where the local is
+/// used, and the locals they refer to can't be set after it is declared or in a function.
+/// When its value reads a global or a field, such as `g` or `t.x`, there can't be an assignment to it or any call
+/// between the declaration and where the local is used, up to the end of a loop it's used in, or anywhere after the
+/// declaration if it's used in a function, since that could change what the value reads.
+/// Indexing and operators are expected to have no side effects, though metatables could give them some.
+/// If it can't be inlined, the Ast is left as it was.
+///
+/// ```rust
+/// # use full_moon::refactor::{inline_local_with, InlineConfig};
+/// let position_of = |ast: &full_moon::ast::Ast, name: &str| {
+///     ast.iter_tokens().find(|token| token.to_string() == name).unwrap().start_position()
+/// };
+///
+/// let code = "-- The name\nlocal name = \"x\" -- not empty\nprint(name:upper())";
+/// let mut ast = full_moon::parse(code).unwrap();
+/// let position = position_of(&ast, "name");
+/// inline_local_with(&mut ast, position, &InlineConfig::default()).unwrap();
+/// assert_eq!(full_moon::print(&ast), "print((\"x\"):upper())");
+///
+/// let mut ast = full_moon::parse(code).unwrap();
+/// let config = InlineConfig {
+///     keep_comments: true,
+/// };
+/// inline_local_with(&mut ast, position, &config).unwrap();
+/// assert_eq!(full_moon::print(&ast), "-- The name\n-- not empty\nprint((\"x\"):upper())");
+/// ```
+pub fn inline_local_with(
+    ast: &mut Ast,
+    at: Position,
+    config: &InlineConfig,
+) -> Result<(), InlineError> {
+    let analysis = ScopeAnalysis::new(ast);
+    let variable = variable_at(&analysis, at).map_err(|global| match global {
+        Some(name) => InlineError::Global { name },
+        None => InlineError::NotAName,
+    })?;
+
+    let declaration = match variable.declaration() {
+        Some(declaration) if variable.kind() == VariableKind::Local => declaration,
+        _ => return Err(InlineError::NoInitializer),
+    };
+
+    let mut finder = FindDeclaration {
+        arena: Arc::clone(&ast.tokens),
+        name: offset(&ast.tokens, declaration),
+        found: None,
+    };
+    ast.nodes().visit(&mut finder);
+    let (statement, semicolon) = finder.found.ok_or(InlineError::NoInitializer)?;
+
+    let mut values = statement.expr_list().iter();
+    let initializer = match (statement.name_list().len(), values.next(), values.next()) {
+        (_, None, _) => return Err(InlineError::NoInitializer),
+        (1, Some(initializer), None) => initializer,
+        _ => return Err(InlineError::MultipleValues),
+    };
+
+    if let Some(write) = variable
+        .references()
+        .iter()
+        .find(|reference| reference.kind() == ReferenceKind::Write && !reference.is_declaration())
+    {
+        return Err(InlineError::Written {
+            position: Token::start_position(write.token()),
+        });
+    }
+
+    let reads: Vec<&ReferenceInfo> = variable
+        .references()
+        .iter()
+        .filter(|reference| reference.kind() == ReferenceKind::Read)
+        .collect();
+
+    check_initializer(ast, &analysis, variable, &statement, initializer, &reads)?;
+
+    let mut contexts = Contexts {
+        arena: Arc::clone(&ast.tokens),
+        reads: reads
+            .iter()
+            .filter_map(|read| offset(&ast.tokens, read.token()))
+            .collect(),
+        contexts: HashMap::new(),
+        left: HashMap::new(),
+    };
+    ast.nodes().visit(&mut contexts);
+
+    let mut parenthesized = HashMap::new();
+    for read in &reads {
+        let read_offset = match offset(&ast.tokens, read.token()) {
+            Some(read_offset) => read_offset,
+            None => continue,
+        };
+
+        let parentheses = match contexts.contexts.get(&read_offset) {
+            Some(Context::Operand { left, right }) => needs_parentheses(initializer, *left, *right),
+            Some(Context::Prefix) => !is_prefix(initializer),
+            None if is_name(initializer) => false,
+            None => {
+                return Err(InlineError::UnsupportedRead {
+                    position: Token::start_position(read.token()),
+                })
+            }
+        };
+        parenthesized.insert(read_offset, parentheses);
+    }
+
+    // Everything has been checked, so nothing can fail from here on
+    let mut statement_range = TokenRange::default();
+    statement.visit(&mut statement_range);
+    if let Some(semicolon) = &semicolon {
+        semicolon.visit(&mut statement_range);
+    }
+    let mut initializer_range = TokenRange::default();
+    initializer.visit(&mut initializer_range);
+
+    let (first, last) = statement_range
+        .range
+        .expect("the declaration should have tokens");
+    let copied = initializer_range
+        .range
+        .expect("the value should have tokens");
+    let dropped = removal(&ast.tokens, first, last, config.keep_comments);
+
+    let old = Arc::clone(&ast.tokens);
+    let Rebuilt {
+        arena: new,
+        offsets,
+        copies,
+    } = rebuild(&old, &dropped, copied, &parenthesized);
+
+    let local_token = offset(&old, statement.local_token()).expect("the declaration is in the Ast");
+    let initializer = initializer.clone();
+    ast.nodes_mut().visit_mut(&mut Inliner {
+        old: &old,
+        new: &new,
+        initializer: &initializer,
+        copies: &copies,
+        declaration: local_token,
+    });
+    ast.nodes_mut().visit_mut(&mut Reborrow {
+        old: &old,
+        new: &new,
+        offsets: &offsets,
+    });
+    ast.tokens = new;
+
+    // Inlining `1` into `x..y` gives `1..y`
+    ensure_valid_spacing(ast);
+    ast.update_positions();
+    Ok(())
+}
+
+// The offset of a token in the arena, if it is in it
+fn offset<'a>(arena: &Arc<Arena<Token<'a>>>, token: &TokenReference<'a>) -> Option<usize> {
+    match token.arena_offset() {
+        Some((token_arena, offset)) if Arc::ptr_eq(token_arena, arena) => Some(offset),
+        _ => None,
+    }
+}
+
+// Checks that the value would be the same wherever the local is read
+fn check_initializer<'ast>(
+    ast: &Ast<'ast>,
+    analysis: &ScopeAnalysis<'ast>,
+    variable: &Variable<'ast>,
+    statement: &LocalAssignment<'ast>,
+    initializer: &Expression<'ast>,
+    reads: &[&ReferenceInfo<'ast>],
+) -> Result<(), InlineError> {
+    let mut visitor = Initializer {
+        analysis,
+        depth: 0,
+        side_effect: None,
+        new_value: None,
+        names: Vec::new(),
+        globals: HashSet::new(),
+        fields: false,
+    };
+    initializer.visit(&mut visitor);
+
+    if let Some(position) = visitor.side_effect {
+        return Err(InlineError::SideEffect { position });
+    }
+
+    if let Some(position) = visitor.new_value {
+        let once = match reads {
+            [] => true,
+            [read] => !in_loop_or_function(analysis, read.scope(), variable.scope()),
+            _ => false,
+        };
+
+        if !once {
+            return Err(InlineError::NewValue { position });
+        }
+    }
+
+    let declared = variable
+        .declaration_position()
+        .expect("locals with values are declared");
+    let start = initializer.start_position().unwrap_or_default();
+
+    for (token, resolution) in &visitor.names {
+        let name = token.to_string();
+
+        // The locals declared inside of the value, such as the parameters of a function, are copied with it
+        let local = match resolution {
+            Resolution::Local(id) => {
+                let other = analysis.variable(*id);
+                if other
+                    .declaration_position()
+                    .is_some_and(|position| position >= start)
+                {
+                    continue;
+                }
+
+                let depth = other
+                    .references()
+                    .iter()
+                    .find(|reference| reference.is_declaration())
+                    .map(ReferenceInfo::function_depth);
+                let write = other.references().iter().find(|reference| {
+                    reference.kind() == ReferenceKind::Write
+                        && !reference.is_declaration()
+                        && (Token::start_position(reference.token()) > declared
+                            || Some(reference.function_depth()) != depth)
+                });
+
+                if let Some(write) = write {
+                    return Err(InlineError::Changed {
+                        name,
+                        position: Token::start_position(write.token()),
+                    });
+                }
+
+                Some(*id)
+            }
+            _ => None,
+        };
+
+        for read in reads {
+            let position = Token::start_position(read.token());
+            if visible(analysis, &name, read.scope(), position, variable.id()) != local {
+                return Err(InlineError::Shadowed { name, position });
+            }
+        }
+    }
+
+    if visitor.globals.is_empty() && !visitor.fields {
+        return Ok(());
+    }
+
+    let mut changes = Changes {
+        analysis,
+        changes: Vec::new(),
+        loops: Vec::new(),
+        functions: Vec::new(),
+    };
+    ast.nodes().visit(&mut changes);
+
+    let declared_end = statement.end_position().unwrap_or_default();
+    let contains = |(start, end): &(Position, Position), position: Position| {
+        *start <= position && position < *end
+    };
+
+    for read in reads {
+        let position = Token::start_position(read.token());
+
+        // The value is read every time the function runs, which could be after anything that comes after it,
+        // and every time the loop comes back around to the read
+        let in_function = changes
+            .functions
+            .iter()
+            .any(|range| contains(range, position) && !contains(range, declared_end));
+        let until = if in_function {
+            None
+        } else {
+            Some(
+                changes
+                    .loops
+                    .iter()
+                    .filter(|range| contains(range, position) && !contains(range, declared_end))
+                    .map(|(_, end)| *end)
+                    .fold(position, Position::max),
+            )
+        };
+
+        let change = changes.changes.iter().find(|change| {
+            let changes_value = match &change.target {
+                Target::Call => true,
+                Target::Global(name) => visitor.globals.contains(name),
+                Target::Field => visitor.fields,
+            };
+
+            changes_value
+                && change.end > declared_end
+                && until.is_none_or(|until| change.end < until)
+        });
+
+        if let Some(change) = change {
+            return Err(InlineError::MayChange {
+                position: change.start,
+            });
+        }
+
+        // The function could also be returned or stored somewhere and called after the Ast's code is done
+        if in_function {
+            return Err(InlineError::MayChange { position });
+        }
+    }
+
+    Ok(())
+}
+
+// Whether a scope is in a loop or a function that the other scope it is in isn't in
+fn in_loop_or_function(analysis: &ScopeAnalysis, scope: ScopeId, outer: ScopeId) -> bool {
+    let mut current = scope;
+    while current != outer {
+        let scope = analysis.scope(current);
+        match scope.kind() {
+            ScopeKind::While
+            | ScopeKind::Repeat
+            | ScopeKind::NumericFor
+            | ScopeKind::GenericFor
+            | ScopeKind::Function => return true,
+            _ => {}
+        }
+
+        current = match scope.parent() {
+            Some(parent) => parent,
+            None => return false,
+        };
+    }
+
+    false
+}
+
+// The local a name refers to at a position in a scope, ignoring the local being inlined, which will be gone.
+// Locals are in the order they're declared, so the last one with the name declared before the position is the one.
+fn visible(
+    analysis: &ScopeAnalysis,
+    name: &str,
+    scope: ScopeId,
+    position: Position,
+    ignored: VariableId,
+) -> Option<VariableId> {
+    let mut current = Some(scope);
+    while let Some(id) = current {
+        let scope = analysis.scope(id);
+        let found = scope
+            .variables()
+            .iter()
+            .rev()
+            .map(|id| analysis.variable(*id))
+            .find(|variable| {
+                variable.id() != ignored
+                    && variable.name() == name
+                    && variable
+                        .declaration_position()
+                        .is_none_or(|declared| declared < position)
+            });
+
+        if let Some(variable) = found {
+            return Some(variable.id());
+        }
+
+        current = scope.parent();
+    }
+
+    None
+}
+
+// The operators of an expression outside of parentheses, with whether it starts with a unary operator
+fn operators(expression: &Expression) -> (bool, Vec<SBinOp>) {
+    let mut operators = Vec::new();
+    let mut current = expression;
+    let unary = matches!(expression, Expression::UnaryOperator { .. });
+
+    loop {
+        match current {
+            Expression::UnaryOperator { expression, .. } => current = expression,
+            Expression::Value {
+                binop: Some(BinOpRhs { bin_op, rhs, .. }),
+                ..
+            } => {
+                operators.push(SBinOp::from(bin_op));
+                current = rhs;
+            }
+            _ => return (unary, operators),
+        }
+    }
+}
+
+// Whether the value needs parentheses between operators that hold the operand on their side as tightly as
+// `left` and `right`, which it does unless every operator in it holds its operands more tightly
+fn needs_parentheses(expression: &Expression, left: u8, right: u8) -> bool {
+    let (unary, operators) = operators(expression);
+    if operators.is_empty() {
+        return unary && UNARY_PRIORITY < right;
+    }
+
+    operators.iter().any(|operator| {
+        let (operator_left, operator_right) = operator.priority();
+        operator_left <= left || operator_right < right
+    })
+}
+
+fn is_name(expression: &Expression) -> bool {
+    matches!(
+        expression,
+        Expression::Value { value, binop: None, ..  } if matches!(&**value, Value::Var(Var::Name(_)))
+    )
+}
+
+// Whether the value can be called or indexed without parentheses, which names, indexes, and parentheses can be
+fn is_prefix(expression: &Expression) -> bool {
+    match expression {
+        Expression::Parentheses { .. } => true,
+        Expression::Value {
+            value, binop: None, ..
+        } => matches!(
+            &**value,
+            Value::Var(_) | Value::ParseExpression(Expression::Parentheses { .. })
+        ),
+        _ => false,
+    }
+}
+
+// Finds out which tokens should be removed with the declaration from `first` to `last`, which are its line if
+// nothing else is on it, and the comment lines right before it unless comments are kept
+fn removal(arena: &Arena<Token>, first: usize, last: usize, keep_comments: bool) -> HashSet<usize> {
+    let get = |offset: usize| arena.get_unknown_gen(offset).map(|(token, _)| token);
+    let whitespace = |offset: usize| {
+        get(offset).and_then(|token| match &*token.token_type() {
+            TokenType::Whitespace { characters } => Some(characters.contains('\n')),
+            _ => None,
+        })
+    };
+    let is_comment = |offset: usize| {
+        get(offset).is_some_and(|token| {
+            matches!(
+                token.token_kind(),
+                TokenKind::SingleLineComment | TokenKind::MultiLineComment
+            )
+        })
+    };
+    let line_start = |offset: usize| offset == 0 || whitespace(offset - 1) == Some(true);
+
+    let mut start = first;
+    while start > 0 && whitespace(start - 1) == Some(false) {
+        start -= 1;
+    }
+
+    let mut end = last;
+    let mut comment = None;
+    while whitespace(end + 1) == Some(false) || is_comment(end + 1) {
+        end += 1;
+        if is_comment(end) && comment.is_none() {
+            comment = Some(end);
+        }
+    }
+
+    let (line_end, ends_line) = match get(end + 1) {
+        None => (end, true),
+        Some(token) if token.token_kind() == TokenKind::Eof => (end, true),
+        Some(_) if whitespace(end + 1) == Some(true) => (end + 1, true),
+        Some(_) => (end, false),
+    };
+
+    if !line_start(start) {
+        return (start..=last).collect();
+    }
+
+    if !ends_line {
+        let mut end = last;
+        while whitespace(end + 1) == Some(false) {
+            end += 1;
+        }
+        return (first..=end).collect();
+    }
+
+    let mut dropped: HashSet<usize> = match comment {
+        Some(comment) if keep_comments => (first..comment).collect(),
+        _ => (start..=line_end).collect(),
+    };
+
+    // The lines right before the declaration with only comments on them
+    let mut line = start;
+    while !keep_comments && line > 0 {
+        let mut previous = line - 1;
+        while previous > 0 && !line_start(previous) {
+            previous -= 1;
+        }
+
+        let only_comments = (previous..line - 1)
+            .all(|offset| whitespace(offset) == Some(false) || is_comment(offset))
+            && (previous..line - 1).any(is_comment);
+        if !only_comments {
+            break;
+        }
+
+        dropped.extend(previous..line);
+        line = previous;
+    }
+
+    dropped
+}
+
+// The tokens of a copy of the value for a read of the local, by the offsets they were copied from,
+// with the offsets of the parentheses around it if it has them
+#[derive(Default)]
+struct ReadCopy {
+    offsets: BTreeMap<usize, usize>,
+    parentheses: Option<(usize, usize)>,
+}
+
+// The new arena, with the offsets of the tokens that were kept and the copies by the offsets of the reads
+struct Rebuilt<'a> {
+    arena: Arc<Arena<Token<'a>>>,
+    offsets: BTreeMap<usize, usize>,
+    copies: HashMap<usize, ReadCopy>,
+}
+
+// Makes the new arena, with the dropped tokens removed and every read replaced by a copy of the tokens from
+// `copied.0` to `copied.1`
+fn rebuild<'a>(
+    old: &Arc<Arena<Token<'a>>>,
+    dropped: &HashSet<usize>,
+    copied: (usize, usize),
+    parenthesized: &HashMap<usize, bool>,
+) -> Rebuilt<'a> {
+    let symbol = |symbol, position| Token::new(TokenType::Symbol { symbol }, position);
+
+    let mut tokens = Vec::with_capacity(old.len());
+    let mut offsets = BTreeMap::new();
+    let mut copies = HashMap::new();
+
+    for (index, token) in old.iter() {
+        let offset = index.into_raw_parts().0;
+
+        if let Some(parentheses) = parenthesized.get(&offset) {
+            let position = token.start_position();
+            let mut copy = ReadCopy::default();
+
+            let open = tokens.len();
+            if *parentheses {
+                tokens.push(symbol(Symbol::LeftParen, position));
+            }
+
+            // The name is pointed at the copy for where it isn't replaced, such as in the name of a function
+            offsets.insert(offset, tokens.len());
+            for copied_offset in copied.0..=copied.1 {
+                if let Some((copied_token, _)) = old.get_unknown_gen(copied_offset) {
+                    copy.offsets.insert(copied_offset, tokens.len());
+                    tokens.push(Token::new(copied_token.token_type().clone(), position));
+                }
+            }
+
+            if *parentheses {
+                copy.parentheses = Some((open, tokens.len()));
+                tokens.push(symbol(Symbol::RightParen, position));
+            }
+
+            copies.insert(offset, copy);
+        } else if !dropped.contains(&offset) {
+            offsets.insert(offset, tokens.len());
+            tokens.push(token.clone());
+        }
+    }
+
+    Rebuilt {
+        arena: Arc::new(tokens.into_iter().collect::<Arena<_>>()),
+        offsets,
+        copies,
+    }
+}
+
+// Finds the `local` statement that declares the name, with its semicolon
+struct FindDeclaration<'a> {
+    arena: Arc<Arena<Token<'a>>>,
+    name: Option<usize>,
+    found: Option<(LocalAssignment<'a>, Option<TokenReference<'a>>)>,
+}
+
+impl<'a> Visitor<'a> for FindDeclaration<'a> {
+    fn visit_block(&mut self, block: &Block<'a>) {
+        for (stmt, semicolon) in &block.stmts {
+            if let Stmt::LocalAssignment(local_assignment) = stmt {
+                if local_assignment
+                    .name_list()
+                    .iter()
+                    .any(|name| offset(&self.arena, name) == self.name)
+                {
+                    self.found = Some((local_assignment.clone(), semicolon.clone()));
+                }
+            }
+        }
+    }
+}
+
+// Finds what in the value would run or be made again if it were copied, and the names that it uses
+struct Initializer<'a, 'ast> {
+    analysis: &'a ScopeAnalysis<'ast>,
+    // How many functions in the value the visitor is in, whose code doesn't run when the value is made
+    depth: usize,
+    side_effect: Option<Position>,
+    new_value: Option<Position>,
+    names: Vec<(TokenReference<'ast>, Resolution)>,
+    // The globals read when the value is made, and whether a field is
+    globals: HashSet<String>,
+    fields: bool,
+}
+
+impl<'ast> Visitor<'ast> for Initializer<'_, 'ast> {
+    fn visit_function_body(&mut self, _: &crate::ast::FunctionBody<'ast>) {
+        self.depth += 1;
+    }
+
+    fn visit_function_body_end(&mut self, _: &crate::ast::FunctionBody<'ast>) {
+        self.depth -= 1;
+    }
+
+    fn visit_function_call(&mut self, call: &FunctionCall<'ast>) {
+        if self.depth == 0 && self.side_effect.is_none() {
+            self.side_effect = call.start_position();
+        }
+    }
+
+    fn visit_value(&mut self, value: &Value<'ast>) {
+        if self.depth > 0 {
+            return;
+        }
+
+        match value {
+            Value::Symbol(token)
+                if matches!(
+                    &*token.token_type(),
+                    TokenType::Symbol {
+                        symbol: Symbol::Ellipse
+                    }
+                ) =>
+            {
+                self.side_effect.get_or_insert(Token::start_position(token));
+            }
+            Value::Function((token, _)) => {
+                self.new_value.get_or_insert(Token::start_position(token));
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_table_constructor(&mut self, table: &TableConstructor<'ast>) {
+        if self.depth == 0 {
+            let (open, _) = table.braces().tokens();
+            self.new_value.get_or_insert(Token::start_position(open));
+        }
+    }
+
+    fn visit_var_expression(&mut self, _: &VarExpression<'ast>) {
+        if self.depth == 0 {
+            self.fields = true;
+        }
+    }
+
+    fn visit_identifier(&mut self, token: &TokenReference<'ast>) {
+        let resolution = self.analysis.resolve(token);
+        if resolution == Resolution::Global && self.depth == 0 {
+            self.globals.insert(token.to_string());
+        }
+
+        if resolution != Resolution::NotAVariable {
+            self.names.push((token.clone(), resolution));
+        }
+    }
+}
+
+// What an assignment or call could change
+enum Target {
+    Global(String),
+    Field,
+    // Anything, since it could run any code
+    Call,
+}
+
+// An assignment or call, which changes what it does at its end, after what it sets to or calls with is evaluated
+struct Change {
+    start: Position,
+    end: Position,
+    target: Target,
+}
+
+// Finds the assignments and calls in the Ast, and where its loops and functions are
+struct Changes<'a, 'ast> {
+    analysis: &'a ScopeAnalysis<'ast>,
+    changes: Vec<Change>,
+    loops: Vec<(Position, Position)>,
+    functions: Vec<(Position, Position)>,
+}
+
+impl<'ast> Changes<'_, 'ast> {
+    fn change(&mut self, node: impl Node, end: Option<Position>, target: Target) {
+        if let (Some(start), Some(end)) = (node.start_position(), end) {
+            self.changes.push(Change { start, end, target });
+        }
+    }
+
+    fn name(&mut self, token: &TokenReference<'ast>, end: Option<Position>) {
+        if self.analysis.resolve(token) == Resolution::Global {
+            self.change(token, end, Target::Global(token.to_string()));
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Changes<'_, 'ast> {
+    fn visit_assignment(&mut self, assignment: &crate::ast::Assignment<'ast>) {
+        let end = assignment.end_position();
+        for var in assignment.var_list() {
+            match var {
+                Var::Name(token) => self.name(token, end),
+                Var::Expression(var) => self.change(var, end, Target::Field),
+            }
+        }
+    }
+
+    fn visit_function_declaration(&mut self, declaration: &crate::ast::FunctionDeclaration<'ast>) {
+        let end = declaration.end_position();
+        let name = declaration.name();
+        match name.names().iter().next() {
+            Some(token) if name.names().len() == 1 && name.method_name().is_none() => {
+                self.name(token, end)
+            }
+            _ => self.change(name, end, Target::Field),
+        }
+    }
+
+    fn visit_function_call(&mut self, call: &FunctionCall<'ast>) {
+        self.change(call, call.end_position(), Target::Call);
+    }
+
+    fn visit_function_body(&mut self, body: &crate::ast::FunctionBody<'ast>) {
+        self.functions.extend(body.range());
+    }
+
+    fn visit_while(&mut self, node: &crate::ast::While<'ast>) {
+        self.loops.extend(node.range());
+    }
+
+    fn visit_repeat(&mut self, node: &crate::ast::Repeat<'ast>) {
+        self.loops.extend(node.range());
+    }
+
+    fn visit_numeric_for(&mut self, node: &crate::ast::NumericFor<'ast>) {
+        self.loops.extend(node.range());
+    }
+
+    fn visit_generic_for(&mut self, node: &crate::ast::GenericFor<'ast>) {
+        self.loops.extend(node.range());
+    }
+}
+
+// Where a read of the local is, for knowing whether its value needs parentheses there
+enum Context {
+    // An operand, with how tightly the operators on each side of it hold it, 0 if there are none
+    Operand { left: u8, right: u8 },
+    // The prefix of a call or index, such as `x` in `x.y` or `x()`
+    Prefix,
+}
+
+struct Contexts<'a> {
+    arena: Arc<Arena<Token<'a>>>,
+    reads: HashSet<usize>,
+    contexts: HashMap<usize, Context>,
+    // How tightly the operator before a read holds it, found before the read is visited
+    left: HashMap<usize, u8>,
+}
+
+impl<'a> Contexts<'a> {
+    // The offset of the read an expression starts with, if it starts with one
+    fn read(&self, expression: &Expression<'a>) -> Option<usize> {
+        match expression {
+            Expression::Value { value, .. } => match &**value {
+                Value::Var(Var::Name(token)) => {
+                    offset(&self.arena, token).filter(|offset| self.reads.contains(offset))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn prefix(&mut self, prefix: &Prefix<'a>) {
+        if let Prefix::Name(token) = prefix {
+            if let Some(offset) =
+                offset(&self.arena, token).filter(|offset| self.reads.contains(offset))
+            {
+                self.contexts.insert(offset, Context::Prefix);
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Contexts<'a> {
+    fn visit_expression(&mut self, expression: &Expression<'a>) {
+        match expression {
+            Expression::UnaryOperator { expression, .. } => {
+                if let Some(read) = self.read(expression) {
+                    self.left.insert(read, UNARY_PRIORITY);
+                }
+            }
+
+            Expression::Value { binop, .. } => {
+                if let Some(read) = self.read(expression) {
+                    let left = self.left.remove(&read).unwrap_or(0);
+                    let right = binop
+                        .as_ref()
+                        .map_or(0, |binop| SBinOp::from(binop.bin_op()).priority().0);
+                    self.contexts.insert(read, Context::Operand { left, right });
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    fn visit_bin_op(&mut self, binop: &BinOpRhs<'a>) {
+        if let Some(read) = self.read(binop.rhs()) {
+            let (_, right) = SBinOp::from(binop.bin_op()).priority();
+            self.left.insert(read, right);
+        }
+    }
+
+    fn visit_function_call(&mut self, call: &FunctionCall<'a>) {
+        self.prefix(call.prefix());
+    }
+
+    fn visit_var_expression(&mut self, var_expression: &VarExpression<'a>) {
+        self.prefix(var_expression.prefix());
+    }
+}
+
+// Replaces the reads of the local with their copies of the value, and removes the declaration
+struct Inliner<'a, 'b> {
+    old: &'b Arc<Arena<Token<'a>>>,
+    new: &'b Arc<Arena<Token<'a>>>,
+    initializer: &'b Expression<'a>,
+    copies: &'b HashMap<usize, ReadCopy>,
+    declaration: usize,
+}
+
+impl<'a> Inliner<'a, '_> {
+    fn token(&self, offset: usize) -> TokenReference<'a> {
+        let (_, index) = self
+            .new
+            .get_unknown_gen(offset)
+            .expect("the copies are in the new arena");
+        TokenReference::Borrowed {
+            arena: Arc::clone(self.new),
+            index,
+        }
+    }
+
+    // The copy of the value for the read with the token, in its parentheses if it has them
+    fn copy(&self, token: &TokenReference<'a>) -> Option<Expression<'a>> {
+        let copy = self.copies.get(&offset(self.old, token)?)?;
+
+        let mut expression = self.initializer.clone();
+        expression.visit_mut(&mut Reborrow {
+            old: self.old,
+            new: self.new,
+            offsets: &copy.offsets,
+        });
+
+        Some(match copy.parentheses {
+            Some((open, close)) => Expression::Parentheses {
+                contained: ContainedSpan::new(self.token(open), self.token(close)),
+                expression: Box::new(expression),
+                id: StoredId::new(),
+            },
+            None => expression,
+        })
+    }
+
+    fn prefix(&self, prefix: &mut Prefix<'a>, suffixes: &mut Vec<Suffix<'a>>) {
+        let copy = match prefix {
+            Prefix::Name(token) => match self.copy(token) {
+                Some(copy) => copy,
+                None => return,
+            },
+            Prefix::Expression(_) => return,
+        };
+
+        *prefix = match copy {
+            Expression::Value {
+                value,
+                binop: None,
+                id,
+            } => match *value {
+                Value::Var(Var::Name(token)) => Prefix::Name(token),
+                Value::Var(Var::Expression(var_expression)) => {
+                    let VarExpression {
+                        prefix,
+                        suffixes: mut copied_suffixes,
+                        ..
+                    } = var_expression;
+                    copied_suffixes.append(suffixes);
+                    *suffixes = copied_suffixes;
+                    prefix
+                }
+                Value::ParseExpression(expression) => Prefix::Expression(expression),
+                value => Prefix::Expression(Expression::Value {
+                    value: Box::new(value),
+                    binop: None,
+                    id,
+                }),
+            },
+            copy => Prefix::Expression(copy),
+        };
+    }
+}
+
+impl<'a> VisitorMut<'a> for Inliner<'a, '_> {
+    fn visit_block(&mut self, block: &mut Block<'a>) {
+        let declaration = self.declaration;
+        let old = self.old;
+        block.stmts.retain(|(stmt, _)| match stmt {
+            Stmt::LocalAssignment(local_assignment) => {
+                offset(old, local_assignment.local_token()) != Some(declaration)
+            }
+            _ => true,
+        });
+    }
+
+    fn visit_expression(&mut self, expression: &mut Expression<'a>) {
+        let value = match expression {
+            Expression::Value { value, .. } => value,
+            _ => return,
+        };
+
+        let copy = match &**value {
+            Value::Var(Var::Name(token)) => match self.copy(token) {
+                Some(copy) => copy,
+                None => return,
+            },
+            _ => return,
+        };
+
+        let _ = mem::replace(
+            &mut **value,
+            match copy {
+                Expression::Value {
+                    value: copied,
+                    binop: None,
+                    ..
+                } => *copied,
+                copy => Value::ParseExpression(copy),
+            },
+        );
+    }
+
+    fn visit_function_call(&mut self, call: &mut FunctionCall<'a>) {
+        self.prefix(&mut call.prefix, &mut call.suffixes);
+    }
+
+    fn visit_var_expression(&mut self, var_expression: &mut VarExpression<'a>) {
+        self.prefix(&mut var_expression.prefix, &mut var_expression.suffixes);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{
+    ast::{owned::Owned, Ast, ParseConfig},
+    diff::{diff, DiffEntry},
+    node::Node,
+    prelude::*,
+    tokenizer::Token,
+    Error,
+};
+use core::fmt;
+
+// How many bytes of code are kept on each side of where the printed code differs
+const CONTEXT_BYTES: usize = 20;
+
+/// Where the code printed by [`verify_round_trip`](fn.verify_round_trip.html) starts being different
+/// from the code it was parsed from
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundTripMismatch {
+    offset: usize,
+    expected: String,
+    found: String,
+    token: Option<Token<'static>>,
+}
+
+impl RoundTripMismatch {
+    /// The first byte that is different, which is the length of the shorter code if one is the start of the other
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The code that was parsed around the offset, up to 20 bytes on each side of it
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    /// The printed code around the offset, up to 20 bytes on each side of it
+    pub fn found(&self) -> &str {
+        &self.found
+    }
+
+    /// The token of the parsed code the offset is in, or the last one before it, such as the token before
+    /// whitespace that wasn't printed. None if the Ast has no tokens before the offset.
+    pub fn token(&self) -> Option<&Token<'static>> {
+        self.token.as_ref()
+    }
+}
+
+impl fmt::Display for RoundTripMismatch {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "printed code differs at byte {}", self.offset)?;
+        if let Some(token) = &self.token {
+            let position = token.start_position();
+            write!(
+                formatter,
+                ", in `{}` at line {}, character {}",
+                token,
+                position.line(),
+                position.character()
+            )?;
+        }
+
+        write!(
+            formatter,
+            "\nexpected: {:?}\n   found: {:?}",
+            self.expected, self.found
+        )
+    }
+}
+
+/// Why code didn't stay the same when it was parsed and printed, given by
+/// [`verify_round_trip`](fn.verify_round_trip.html) and [`verify_round_trip_reparse`](fn.verify_round_trip_reparse.html)
+#[derive(Clone, Debug, PartialEq)]
+pub enum RoundTripFailure {
+    /// The code couldn't be parsed
+    Parse(Error<'static>),
+    /// The printed code isn't the same as the code
+    Mismatch(RoundTripMismatch),
+    /// The printed code couldn't be parsed
+    Reparse(Error<'static>),
+    /// The printed code was parsed to nodes that aren't [similar](node/trait.Node.html#tymethod.similar)
+    /// to the ones of the code, with the first node that differs, where its range is in the code
+    /// and its replacement is the printed code of the node it was parsed to
+    Changed(DiffEntry),
+}
+
+impl fmt::Display for RoundTripFailure {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoundTripFailure::Parse(error) => {
+                write!(formatter, "couldn't parse the code: {}", error)
+            }
+            RoundTripFailure::Mismatch(mismatch) => mismatch.fmt(formatter),
+            RoundTripFailure::Reparse(error) => {
+                write!(formatter, "couldn't parse the printed code: {}", error)
+            }
+            RoundTripFailure::Changed(entry) => {
+                let start = entry.old_range().0;
+                write!(
+                    formatter,
+                    "the printed code parsed differently at line {}, character {}: {:?} is now {:?}",
+                    start.line(),
+                    start.character(),
+                    entry.kind(),
+                    entry.replacement()
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RoundTripFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RoundTripFailure::Parse(error) | RoundTripFailure::Reparse(error) => Some(error),
+            RoundTripFailure::Mismatch(_) | RoundTripFailure::Changed(_) => None,
+        }
+    }
+}
+
+/// Parses the code with the options of the [config](struct.ParseConfig.html) and checks that
+/// [printing](fn.print.html) its [`Ast`](ast/struct.Ast.html) gives back exactly the same code,
+/// such as for checking that the parser keeps every byte of a corpus of code.
+///
+/// Asts parsed with [`preserve_trivia`](struct.ParseConfig.html#structfield.preserve_trivia) off don't keep their
+/// whitespace, so their code is only printed back the same if its whitespace is spaces and new lines, which is
+/// what `print` puts between their tokens.
+/// Use [`verify_round_trip_reparse`](fn.verify_round_trip_reparse.html) for those.
+///
+/// # Errors
+/// [`RoundTripFailure::Parse`](enum.RoundTripFailure.html#variant.Parse) if the code couldn't be parsed,
+/// and [`RoundTripFailure::Mismatch`](enum.RoundTripFailure.html#variant.Mismatch) with where the printed code
+/// starts being different if it isn't the same.
+///
+/// ```rust
+/// # use full_moon::{ParseConfig, RoundTripFailure};
+/// let config = ParseConfig::default();
+/// assert!(full_moon::verify_round_trip("local x = 1 -- one\n", &config).is_ok());
+///
+/// let config = ParseConfig {
+///     preserve_trivia: false,
+///     ..ParseConfig::default()
+/// };
+///
+/// match full_moon::verify_round_trip("local\tx = 1", &config) {
+///     Err(RoundTripFailure::Mismatch(mismatch)) => {
+///         assert_eq!(mismatch.offset(), 5);
+///         assert_eq!(mismatch.expected(), "local\tx = 1");
+///         assert_eq!(mismatch.found(), "local x = 1");
+///         assert_eq!(mismatch.token().unwrap().to_string(), "local");
+///     }
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+pub fn verify_round_trip(code: &str, config: &ParseConfig) -> Result<(), RoundTripFailure> {
+    let ast =
+        crate::parse_with(code, config).map_err(|error| RoundTripFailure::Parse(error.owned()))?;
+    compare_printed(&ast, code, &crate::print(&ast))
+}
+
+/// Checks the code the same as [`verify_round_trip`](fn.verify_round_trip.html), then parses the printed code again
+/// and checks that its nodes are [similar](node/trait.Node.html#tymethod.similar) to the ones of the code,
+/// which ignores their comments and whitespace. The printed code of Asts parsed with
+/// [`preserve_trivia`](struct.ParseConfig.html#structfield.preserve_trivia) off isn't checked, only its nodes,
+/// so this can check code printed without its whitespace.
+///
+/// # Errors
+/// The same as `verify_round_trip`, as well as
+/// [`RoundTripFailure::Reparse`](enum.RoundTripFailure.html#variant.Reparse) if the printed code couldn't be parsed
+/// and [`RoundTripFailure::Changed`](enum.RoundTripFailure.html#variant.Changed) if its nodes aren't similar.
+///
+/// ```rust
+/// # use full_moon::ParseConfig;
+/// let config = ParseConfig {
+///     preserve_trivia: false,
+///     ..ParseConfig::default()
+/// };
+///
+/// let code = "local x =\t1 --[[ one ]] + 2\n\tprint(x)";
+/// assert!(full_moon::verify_round_trip(code, &config).is_err());
+/// assert!(full_moon::verify_round_trip_reparse(code, &config).is_ok());
+/// ```
+pub fn verify_round_trip_reparse(code: &str, config: &ParseConfig) -> Result<(), RoundTripFailure> {
+    let ast =
+        crate::parse_with(code, config).map_err(|error| RoundTripFailure::Parse(error.owned()))?;
+    let printed = crate::print(&ast);
+    if ast.has_trivia() {
+        compare_printed(&ast, code, &printed)?;
+    }
+
+    let reparsed = crate::parse_with(&printed, config)
+        .map_err(|error| RoundTripFailure::Reparse(error.owned()))?;
+
+    // The Asts borrow different code, so they're compared as owned ones
+    let (ast, reparsed) = (ast.owned(), reparsed.owned());
+    if ast.nodes().similar(reparsed.nodes()) {
+        return Ok(());
+    }
+
+    let entry = diff(&ast, &reparsed)
+        .into_iter()
+        .next()
+        .expect("nodes that aren't similar have a difference");
+    Err(RoundTripFailure::Changed(entry))
+}
+
+fn compare_printed(ast: &Ast, code: &str, printed: &str) -> Result<(), RoundTripFailure> {
+    if code == printed {
+        return Ok(());
+    }
+
+    let offset = code
+        .bytes()
+        .zip(printed.bytes())
+        .position(|(expected, found)| expected != found)
+        .unwrap_or_else(|| code.len().min(printed.len()));
+
+    // The comments of Asts without trivia are kept apart from the other tokens
+    let comments = ast.comments().filter(|_| !ast.has_trivia());
+    let token = ast
+        .iter_tokens()
+        .chain(comments)
+        .filter(|token| token.start_bytes() <= offset)
+        .max_by_key(|token| token.start_bytes())
+        .map(|token| token.detached());
+
+    Err(RoundTripFailure::Mismatch(RoundTripMismatch {
+        offset,
+        expected: context(code, offset),
+        found: context(printed, offset),
+        token,
+    }))
+}
+
+// The code up to CONTEXT_BYTES on each side of the offset, without cutting characters in half
+fn context(code: &str, offset: usize) -> String {
+    let mut start = offset.saturating_sub(CONTEXT_BYTES);
+    while !code.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    let mut end = (offset + CONTEXT_BYTES).min(code.len());
+    while !code.is_char_boundary(end) {
+        end += 1;
+    }
+
+    code[start..end].to_owned()
+}
@@ -0,0 +1,247 @@
+use crate::{
+    ast::Ast,
+    prelude::*,
+    tokenizer::{Position, TokenKind},
+};
+use core::fmt::Write;
+
+/// Prints back Lua code from an [`Ast`](ast/struct.Ast.html) like [`print`](fn.print.html), along with a
+/// [`SourceMap`](struct.SourceMap.html) of where every printed token came from in the original code.
+///
+/// The original range of a token is the position it had when printing, so positions shouldn't be updated
+/// with [`Ast::update_positions`](ast/struct.Ast.html#method.update_positions) between parsing and printing.
+/// This makes it possible to map lines in code that was changed by transforms back to the code it came from.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let mut ast = full_moon::parse("local x=1\ncall(x)")?;
+/// full_moon::transform::normalize_spacing(&mut ast);
+///
+/// let (code, source_map) = full_moon::print_with_source_map(&ast);
+/// assert_eq!(code, "local x = 1\ncall(x)");
+///
+/// let call = source_map.mappings().iter().find(|mapping| mapping.output_range().0.line() == 2).unwrap();
+/// assert_eq!(call.output_range().0.bytes(), 12);
+/// assert_eq!(call.original_range().unwrap().0.bytes(), 10);
+/// # Ok(())
+/// # }
+/// ```
+pub fn print_with_source_map(ast: &Ast) -> (String, SourceMap) {
+    let mut output = String::new();
+    let mut position = Position {
+        bytes: 0,
+        character: 1,
+        line: 1,
+    };
+    let mut mappings = Vec::new();
+
+    for token in ast.iter_tokens() {
+        let start = position;
+        write!(output, "{}", token).expect("writing to a string can't fail");
+
+        let text = &output[start.bytes..];
+        if text.is_empty() {
+            continue;
+        }
+
+        for character in text.chars() {
+            if character == '\n' {
+                position.line += 1;
+                position.character = 1;
+            } else {
+                position.character += 1;
+            }
+        }
+        position.bytes = output.len();
+
+        let (original_start, original_end) = (token.start_position(), token.end_position());
+        mappings.push(SourceMapping {
+            output_range: (start, position),
+            original_range: Some((original_start, original_end))
+                .filter(|_| original_start.bytes() != original_end.bytes()),
+            token_kind: token.token_kind(),
+        });
+    }
+
+    (output, SourceMap { mappings })
+}
+
+/// A token in printed code and where it came from in the original code, created by
+/// [`print_with_source_map`](fn.print_with_source_map.html)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceMapping {
+    output_range: (Position, Position),
+    original_range: Option<(Position, Position)>,
+    token_kind: TokenKind,
+}
+
+impl SourceMapping {
+    /// The range of the token in the printed code
+    pub fn output_range(&self) -> (Position, Position) {
+        self.output_range
+    }
+
+    /// The range of the token in the original code, from the position the token had when printing.
+    /// Returns `None` for tokens that weren't in the original code, such as whitespace added by
+    /// [`normalize_spacing`](transform/fn.normalize_spacing.html).
+    pub fn original_range(&self) -> Option<(Position, Position)> {
+        self.original_range
+    }
+
+    /// The [kind](tokenizer/enum.TokenKind.html) of the token
+    pub fn token_kind(&self) -> TokenKind {
+        self.token_kind
+    }
+}
+
+/// The [mappings](struct.SourceMapping.html) of every token in printed code, from
+/// [`print_with_source_map`](fn.print_with_source_map.html)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    mappings: Vec<SourceMapping>,
+}
+
+impl SourceMap {
+    /// The mappings of every token that printed any code, in the order they were printed
+    pub fn mappings(&self) -> &[SourceMapping] {
+        &self.mappings
+    }
+
+    /// The mapping of the token printed at the line and character in the output, both starting from 1,
+    /// such as from an error message
+    pub fn find(&self, line: usize, character: usize) -> Option<&SourceMapping> {
+        let line_character = |position: Position| (position.line(), position.character());
+        let index = self
+            .mappings
+            .partition_point(|mapping| line_character(mapping.output_range.1) <= (line, character));
+
+        self.mappings
+            .get(index)
+            .filter(|mapping| line_character(mapping.output_range.0) <= (line, character))
+    }
+
+    /// Serializes the source map to the JSON format of version 3 of the
+    /// [source map specification](https://sourcemaps.info/spec.html), used by most tools that read source maps.
+    /// Only available with the `source-map` feature flag.
+    ///
+    /// `original_code` is the code the Ast was parsed from, and is used to find the lines and columns of the tokens,
+    /// which are counted in characters. Comments and whitespace aren't mapped.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<std::error::Error>> {
+    /// let code = "local x = 1";
+    /// let (_, source_map) = full_moon::print_with_source_map(&full_moon::parse(code)?);
+    /// assert_eq!(
+    ///     source_map.to_json(code, "out.lua", "in.lua"),
+    ///     r#"{"file":"out.lua","mappings":"AAAA,MAAM,EAAE,EAAE","names":[],"sources":["in.lua"],"version":3}"#
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "source-map")]
+    pub fn to_json(&self, original_code: &str, output_file: &str, source_file: &str) -> String {
+        serde_json::json!({
+            "version": 3,
+            "file": output_file,
+            "sources": [source_file],
+            "names": [],
+            "mappings": self.encode_mappings(original_code),
+        })
+        .to_string()
+    }
+
+    #[cfg(feature = "source-map")]
+    fn encode_mappings(&self, original_code: &str) -> String {
+        let line_starts: Vec<usize> = core::iter::once(0)
+            .chain(
+                original_code
+                    .match_indices('\n')
+                    .map(|(index, _)| index + 1),
+            )
+            .collect();
+
+        // The line and column of a byte in the original code, starting from 0
+        let line_column = |bytes: usize| {
+            let line = line_starts.partition_point(|start| *start <= bytes) - 1;
+            let column = original_code
+                .get(line_starts[line]..bytes)
+                .map_or(0, |text| text.chars().count());
+            (line, column)
+        };
+
+        let mut encoded = String::new();
+        let (mut output_line, mut output_column) = (1, 0);
+        let (mut original_line, mut original_column) = (0, 0);
+        let mut first_in_line = true;
+
+        for mapping in &self.mappings {
+            let original = match mapping.original_range {
+                Some((start, _)) if !is_trivia(mapping.token_kind) => start,
+                _ => continue,
+            };
+
+            let output = mapping.output_range.0;
+            while output_line < output.line() {
+                encoded.push(';');
+                output_line += 1;
+                output_column = 0;
+                first_in_line = true;
+            }
+
+            if !first_in_line {
+                encoded.push(',');
+            }
+
+            let (line, column) = line_column(original.bytes());
+            let column_in_output = output.character() - 1;
+            for value in [
+                column_in_output as i64 - output_column as i64,
+                0,
+                line as i64 - original_line as i64,
+                column as i64 - original_column as i64,
+            ]
+            .iter()
+            {
+                encode_vlq(&mut encoded, *value);
+            }
+
+            output_column = column_in_output;
+            original_line = line;
+            original_column = column;
+            first_in_line = false;
+        }
+
+        fn is_trivia(kind: TokenKind) -> bool {
+            matches!(
+                kind,
+                TokenKind::Whitespace | TokenKind::SingleLineComment | TokenKind::MultiLineComment
+            )
+        }
+
+        encoded
+    }
+}
+
+#[cfg(feature = "source-map")]
+fn encode_vlq(encoded: &mut String, value: i64) {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut value = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+
+    loop {
+        let mut digit = value & 0b11111;
+        value >>= 5;
+        if value > 0 {
+            digit |= 0b100000;
+        }
+
+        encoded.push(BASE64[digit as usize] as char);
+        if value == 0 {
+            break;
+        }
+    }
+}
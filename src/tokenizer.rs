@@ -1,25 +1,32 @@
-use crate::visitors::{Visit, VisitMut, Visitor, VisitorMut};
+use crate::{
+    ast::owned::Owned,
+    line_index::LineIndex,
+    node::NodeId,
+    prelude::*,
+    visitors::{
+        AnyNode, AnyNodeMut, TryVisit, TryVisitor, Visit, VisitMut, VisitNodes, VisitNodesMut,
+        Visitor, VisitorMut,
+    },
+    ErrorCode,
+};
+use alloc::{borrow::Cow, sync::Arc};
 use atomic_refcell::AtomicRefCell;
-use generational_arena::{Arena, Index};
-use lazy_static::lazy_static;
-use regex::{self, Regex};
-#[cfg(feature = "serde")]
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{
-    borrow::Cow,
+use core::{
     cmp::Ordering,
     fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
     str::FromStr,
-    sync::{
-        atomic::{AtomicUsize, Ordering as AtomicOrdering},
-        Arc,
-    },
+    sync::atomic::{AtomicUsize, Ordering as AtomicOrdering},
 };
+use generational_arena::{Arena, Index};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 macro_rules! symbols {
     ($($ident:ident => $string:tt,)+) => {
         /// A literal symbol, used for both words important to syntax (like while) and operators (like +)
-        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+        #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
         #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
         pub enum Symbol {
             $(
@@ -38,6 +45,15 @@ macro_rules! symbols {
             }
         }
 
+        impl Symbol {
+            /// Whether the symbol is a reserved keyword, such as `end` or `nil`, which can't be used as a name
+            pub fn is_keyword(self) -> bool {
+                match self {
+                    $(Symbol::$ident => $string.as_bytes()[0].is_ascii_alphabetic(),)+
+                }
+            }
+        }
+
         impl FromStr for Symbol {
             type Err = ();
 
@@ -49,17 +65,8 @@ macro_rules! symbols {
             }
         }
 
-        lazy_static! {
-            static ref PATTERN_SYMBOL: Regex = Regex::new(
-                &vec![$($string,)+]
-                    .iter()
-                    .map(|x| {
-                        regex::escape(&x.to_string())
-                    })
-                    .collect::<Vec<_>>()
-                    .join("|")
-            ).unwrap();
-        }
+        // Every symbol, in the order they're matched in, so that longer symbols come before the ones they start with
+        const SYMBOLS: &[(&str, Symbol)] = &[$(($string, Symbol::$ident),)+];
     };
 }
 
@@ -115,7 +122,7 @@ symbols!(
 );
 
 /// The possible errors that can happen while tokenizing.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum TokenizerErrorType {
     /// An unclosed multi-line comment was found
@@ -124,12 +131,75 @@ pub enum TokenizerErrorType {
     UnclosedString,
     /// An unexpected token was found
     UnexpectedToken(char),
+    /// An escape in a quoted string that Lua can't read, such as `\300`, which is more than a byte can hold.
+    /// With the `roblox` feature, this is also an escape that Luau doesn't have, such as `\q`,
+    /// or a `\x` or `\u{` escape without all of its digits
+    InvalidEscape {
+        /// The escape, from its `\` up to the first character that made it invalid, such as `\x4` for `\x4g`
+        escape: String,
+        /// Where the `\` of the escape is, which is also the position of the error
+        position: Position,
+    },
+    /// A number that Lua can't read, such as `0x` or `3..14`.
+    /// Like Lua, everything that could be part of a number is read as one before it is checked,
+    /// so `1..2` is one malformed number rather than a concatenation, which needs spaces around the `..`
+    MalformedNumber {
+        /// All of the text that was read as the number
+        text: String,
+        /// Where the number starts, which is also the position of the error
+        position: Position,
+        /// What is wrong with the number
+        reason: MalformedNumberReason,
+    },
 }
 
-/// The type of tokens in parsed code
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// Why a number is malformed, for [`TokenizerErrorType::MalformedNumber`](enum.TokenizerErrorType.html#variant.MalformedNumber)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-#[cfg_attr(feature = "serde", serde(tag = "type"))]
+pub enum MalformedNumberReason {
+    /// A `0x` with no hexadecimal digits after it
+    MissingHexDigits,
+    /// A `0b` with no binary digits after it, with the `roblox` feature
+    MissingBinaryDigits,
+    /// An exponent with no digits, such as `1e` or `1e+`
+    MissingExponentDigits,
+    /// More than one `.`, such as `3..14`
+    MultipleDecimalPoints,
+    /// A character that can't be in the number where it is, such as the `a` of `1a`,
+    /// or the `p` of `0x1p4`, since hexadecimal numbers with exponents are only in Lua 5.2 and later
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for MalformedNumberReason {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MalformedNumberReason::MissingHexDigits => {
+                write!(formatter, "expected hexadecimal digits after `0x`")
+            }
+            MalformedNumberReason::MissingBinaryDigits => {
+                write!(formatter, "expected binary digits after `0b`")
+            }
+            MalformedNumberReason::MissingExponentDigits => {
+                write!(formatter, "expected digits in the exponent")
+            }
+            MalformedNumberReason::MultipleDecimalPoints => {
+                write!(formatter, "a number can only have one `.`")
+            }
+            MalformedNumberReason::InvalidCharacter(character) => {
+                write!(formatter, "`{}` can't be in the number here", character)
+            }
+        }
+    }
+}
+
+/// The type of tokens in parsed code.
+///
+/// The text of tokens made by [`tokens`](fn.tokens.html) borrows the code, so tokenizing doesn't copy it.
+/// The text is only owned when it's made by something else, such as [`long_string`](fn.long_string.html)
+/// normalizing a string, or tokens that were deserialized.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", remote = "Self"))]
 pub enum TokenType<'a> {
     /// End of file, should always be the very last token
     Eof,
@@ -206,6 +276,38 @@ impl<'a> TokenType<'a> {
         }
     }
 
+    /// Converts a string into a string in long brackets with the same value, with the lowest level
+    /// that its contents allow, the same as [`long_string`](fn.long_string.html).
+    /// Strings that are already in long brackets are returned as they are.
+    ///
+    /// Returns None if the token isn't a string, or if its value can't be written in long brackets,
+    /// which have no escapes. Lua reads carriage returns inside of long brackets as new lines,
+    /// so strings with them can't be converted, and neither can strings that aren't valid UTF-8.
+    ///
+    /// ```rust
+    /// # use full_moon::tokenizer::tokens;
+    /// let tokens = tokens(r#""say \"hi\"\n\65""#).unwrap();
+    /// let converted = tokens[0].token_type().to_long_bracket().unwrap();
+    /// assert_eq!(converted.to_string(), "[[say \"hi\"\nA]]");
+    /// ```
+    pub fn to_long_bracket(&self) -> Option<TokenType<'a>> {
+        let literal = match self {
+            TokenType::StringLiteral {
+                multi_line: Some(_),
+                ..
+            } => return Some(self.clone()),
+            TokenType::StringLiteral { literal, .. } => literal,
+            _ => return None,
+        };
+
+        let contents = String::from_utf8(unescape(literal)?).ok()?;
+        if contents.contains('\r') {
+            return None;
+        }
+
+        Some(long_string(&contents).owned())
+    }
+
     /// Returns the [`TokenKind`](enum.TokenKind.html) of the token type.
     ///
     /// ```rust
@@ -234,7 +336,7 @@ impl<'a> TokenType<'a> {
 }
 
 /// The kind of token. Contains no additional data.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum TokenKind {
     /// End of file, should always be the very last token
     Eof,
@@ -255,25 +357,116 @@ pub enum TokenKind {
 }
 
 /// A token such consisting of its [`Position`](struct.Position.html) and a [`TokenType`](enum.TokenType.html)
-#[derive(Clone, Debug)]
-#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
 pub struct Token<'a> {
     pub(crate) start_position: Arc<AtomicPosition>,
     pub(crate) end_position: Arc<AtomicPosition>,
     #[cfg_attr(feature = "serde", serde(borrow))]
-    #[serde(with = "serde_arc_atomic_refcell")]
-    pub(crate) token_type: Arc<AtomicRefCell<TokenType<'a>>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(deserialize_with = "serde_token_type_cell::deserialize")
+    )]
+    pub(crate) token_type: Arc<TokenTypeCell<'a>>,
+    // The lines of the code the token was tokenized from, shared by all of its tokens,
+    // which the lines and characters of positions that only have their bytes are found with
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) lines: Option<Arc<LineIndex>>,
+}
+
+// The type and positions a token had, which are shared with its clones, so they're changed back for all of them
+#[derive(Clone, Debug)]
+pub(crate) struct TokenState<'a> {
+    token: Token<'a>,
+    token_type: TokenType<'a>,
+    start_position: Position,
+    end_position: Position,
+}
+
+impl TokenState<'_> {
+    pub(crate) fn restore(&self) {
+        *self.token.token_type.borrow_mut() = self.token_type.clone();
+        self.token.start_position.store(self.start_position);
+        self.token.end_position.store(self.end_position);
+    }
+}
+
+// The type of a token, shared by the token and its clones along with the token's id,
+// which keeps the id out of the token itself so that tokens stay small
+#[derive(Debug)]
+pub(crate) struct TokenTypeCell<'a> {
+    token_type: AtomicRefCell<TokenType<'a>>,
+    pub(crate) id: NodeId,
+}
+
+impl<'a> TokenTypeCell<'a> {
+    pub(crate) fn new(token_type: TokenType<'a>, id: NodeId) -> Self {
+        Self {
+            token_type: AtomicRefCell::new(token_type),
+            id,
+        }
+    }
+}
+
+impl<'a> Deref for TokenTypeCell<'a> {
+    type Target = AtomicRefCell<TokenType<'a>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.token_type
+    }
+}
+
+impl PartialEq for TokenTypeCell<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type
+    }
 }
 
 impl<'a> Token<'a> {
+    // A token with no width, for when new tokens are added to an Ast before its positions are updated
+    pub(crate) fn new(token_type: TokenType<'a>, position: Position) -> Self {
+        Self {
+            start_position: Arc::new(AtomicPosition::new(position)),
+            end_position: Arc::new(AtomicPosition::new(position)),
+            token_type: Arc::new(TokenTypeCell::new(token_type, NodeId::new())),
+            lines: None,
+        }
+    }
+
+    // A copy of the token that owns its type and doesn't share its positions or type with this one,
+    // unlike `clone` and `owned`, so changing either of them doesn't change the other
+    pub(crate) fn detached<'b>(&self) -> Token<'b> {
+        Token {
+            start_position: Arc::new(AtomicPosition::new(self.start_position())),
+            end_position: Arc::new(AtomicPosition::new(self.end_position())),
+            token_type: Arc::new(TokenTypeCell::new(self.token_type().owned(), NodeId::new())),
+            lines: None,
+        }
+    }
+
     /// The position a token begins at
     pub fn start_position(&self) -> Position {
-        self.start_position.load()
+        self.start_position.load(self.lines.as_deref())
     }
 
     /// The position a token ends at
     pub fn end_position(&self) -> Position {
-        self.end_position.load()
+        self.end_position.load(self.lines.as_deref())
+    }
+
+    // What can be changed about the token through it and its clones, for changing it back with `TokenState::restore`
+    pub(crate) fn state(&self) -> TokenState<'a> {
+        TokenState {
+            token: self.clone(),
+            token_type: self.token_type().clone(),
+            start_position: self.start_position.load(None),
+            end_position: self.end_position.load(None),
+        }
+    }
+
+    // The bytes the token starts at, without finding the line and character
+    pub(crate) fn start_bytes(&self) -> usize {
+        self.start_position.bytes()
     }
 
     /// The [type](enum.TokenType.html) of token as well as the data needed to represent it
@@ -290,32 +483,50 @@ impl<'a> Token<'a> {
 }
 
 impl<'a> fmt::Display for Token<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.token_type().fmt(formatter)
+    }
+}
+
+// Shows the lines and characters of the positions, and leaves out the lines of the code they're found from
+impl<'a> fmt::Debug for Token<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Token")
+            .field("start_position", &self.start_position())
+            .field("end_position", &self.end_position())
+            .field("token_type", &*self.token_type())
+            .field("id", &self.token_type.id)
+            .finish()
+    }
+}
+
+impl<'a> fmt::Display for TokenType<'a> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         use self::TokenType::*;
 
-        match &*self.token_type() {
-            Eof => "".to_string(),
-            Number { text } => text.to_string(),
-            Identifier { identifier } => identifier.to_string(),
+        match self {
+            Eof => Ok(()),
+            Number { text } => formatter.write_str(text),
+            Identifier { identifier } => formatter.write_str(identifier),
             MultiLineComment { blocks, comment } => {
-                format!("--[{0}[{1}]{0}]", "=".repeat(*blocks), comment)
+                write!(formatter, "--[{0}[{1}]{0}]", "=".repeat(*blocks), comment)
             }
-            SingleLineComment { comment } => format!("--{}", comment),
+            SingleLineComment { comment } => write!(formatter, "--{}", comment),
             StringLiteral {
                 literal,
                 multi_line,
                 quote_type,
             } => {
                 if let Some(blocks) = multi_line {
-                    format!("[{0}[{1}]{0}]", "=".repeat(*blocks), literal.to_string())
+                    write!(formatter, "[{0}[{1}]{0}]", "=".repeat(*blocks), literal)
                 } else {
-                    format!("{0}{1}{0}", quote_type.to_string(), literal.to_string())
+                    write!(formatter, "{0}{1}{0}", quote_type, literal)
                 }
             }
-            Symbol { symbol } => symbol.to_string(),
-            Whitespace { characters } => characters.to_string(),
+            Symbol { symbol } => symbol.fmt(formatter),
+            Whitespace { characters } => formatter.write_str(characters),
         }
-        .fmt(formatter)
     }
 }
 
@@ -329,9 +540,47 @@ impl<'a> PartialEq<Self> for Token<'a> {
 
 impl<'a> Eq for Token<'a> {}
 
+// Equal positions have the same bytes, so the lines and characters don't need to be found
+impl Hash for Token<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.start_position.bytes().hash(state);
+        self.end_position.bytes().hash(state);
+        self.token_type().hash(state);
+    }
+}
+
+/// Makes a token of the type that isn't from any code, for making tokens to give to
+/// [`Ast::from_tokens`](../ast/struct.Ast.html#method.from_tokens).
+/// Its positions are all at the start of the code, so call
+/// [`Ast::update_positions`](../ast/struct.Ast.html#method.update_positions) on the Ast made from it.
+impl<'a> From<TokenType<'a>> for Token<'a> {
+    fn from(token_type: TokenType<'a>) -> Self {
+        Token::new(
+            token_type,
+            Position {
+                bytes: 0,
+                character: 1,
+                line: 1,
+            },
+        )
+    }
+}
+
 impl<'a> Ord for Token<'a> {
+    // Positions are ordered by their bytes, so the lines and characters only need to be found
+    // for tokens that are the same otherwise
     fn cmp(&self, other: &Self) -> Ordering {
-        self.start_position().cmp(&other.start_position())
+        let key = |token: &Self| (token.start_position.bytes(), token.end_position.bytes());
+        let position = |token: &Self| {
+            let (start, end) = (token.start_position(), token.end_position());
+            (start.line, start.character, end.line, end.character)
+        };
+
+        key(self)
+            .cmp(&key(other))
+            .then_with(|| self.to_string().cmp(&other.to_string()))
+            .then_with(|| self.token_kind().cmp(&other.token_kind()))
+            .then_with(|| position(self).cmp(&position(other)))
     }
 }
 
@@ -343,6 +592,13 @@ impl<'a> PartialOrd for Token<'a> {
 
 /// A reference to a token used by Ast's.
 /// Dereferences to a [`Token`](struct.Token.html)
+///
+/// Token references are compared and hashed by the tokens they point to, whether they're borrowed from the arena
+/// of an Ast or owned: two are equal when their token types, which include their text, and their start and
+/// end positions are, so the same token from two parses of the same code is equal and has the same hash.
+/// They're ordered by their positions, then by their text, so sorting tokens puts them in the order
+/// they're in the code. Tokens share their types with their clones, so don't change the type of a token
+/// with [`set_token_type`](#method.set_token_type) while it or a clone of it is in a set or map.
 #[derive(Clone)]
 pub enum TokenReference<'a> {
     /// Token is borrowed from an Ast's arena
@@ -358,6 +614,15 @@ pub enum TokenReference<'a> {
 }
 
 impl<'a> TokenReference<'a> {
+    /// The arena the token was borrowed from, and its offset within it.
+    /// Tokens in the arena are in the order they appear in the code.
+    pub(crate) fn arena_offset(&self) -> Option<(&Arc<Arena<Token<'a>>>, usize)> {
+        match self {
+            TokenReference::Borrowed { arena, index } => Some((arena, index.into_raw_parts().0)),
+            TokenReference::Owned(_) => None,
+        }
+    }
+
     /// Sets the type of token. Note that positions will not update after using this function.
     /// If you need them to, call [`Ast::update_positions`](../ast/struct.Ast.html#method.update_positions)
     pub fn set_token_type(&mut self, new_token_type: TokenType<'a>) {
@@ -365,13 +630,13 @@ impl<'a> TokenReference<'a> {
     }
 }
 
-impl<'a> std::borrow::Borrow<Token<'a>> for &TokenReference<'a> {
+impl<'a> core::borrow::Borrow<Token<'a>> for &TokenReference<'a> {
     fn borrow(&self) -> &Token<'a> {
         &**self
     }
 }
 
-impl<'a> std::ops::Deref for TokenReference<'a> {
+impl<'a> core::ops::Deref for TokenReference<'a> {
     type Target = Token<'a>;
 
     fn deref(&self) -> &Self::Target {
@@ -405,6 +670,12 @@ impl<'a> PartialEq<Self> for TokenReference<'a> {
 
 impl<'a> Eq for TokenReference<'a> {}
 
+impl Hash for TokenReference<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
 impl<'a> Ord for TokenReference<'a> {
     fn cmp(&self, other: &Self) -> Ordering {
         (**self).cmp(&**other)
@@ -433,6 +704,7 @@ impl<'de: 'a, 'a> Deserialize<'de> for TokenReference<'a> {
 
 impl<'ast> Visit<'ast> for TokenReference<'ast> {
     fn visit<V: Visitor<'ast>>(&self, visitor: &mut V) {
+        visitor.visit_node(AnyNode::TokenReference(self));
         visitor.visit_token(self);
 
         match self.token_kind() {
@@ -445,6 +717,8 @@ impl<'ast> Visit<'ast> for TokenReference<'ast> {
             TokenKind::Symbol => visitor.visit_symbol(self),
             TokenKind::Whitespace => visitor.visit_whitespace(self),
         }
+
+        visitor.visit_node_end(AnyNode::TokenReference(self));
     }
 }
 
@@ -465,7 +739,44 @@ impl<'ast> VisitMut<'ast> for TokenReference<'ast> {
     }
 }
 
+impl<'ast> TryVisit<'ast> for TokenReference<'ast> {
+    fn try_visit<V: TryVisitor<'ast>>(&self, visitor: &mut V) -> Result<(), V::Error> {
+        visitor.visit_node(AnyNode::TokenReference(self))?;
+        visitor.visit_token(self)?;
+
+        match self.token_kind() {
+            TokenKind::Eof => visitor.visit_eof(self),
+            TokenKind::Identifier => visitor.visit_identifier(self),
+            TokenKind::MultiLineComment => visitor.visit_multi_line_comment(self),
+            TokenKind::Number => visitor.visit_number(self),
+            TokenKind::SingleLineComment => visitor.visit_single_line_comment(self),
+            TokenKind::StringLiteral => visitor.visit_string_literal(self),
+            TokenKind::Symbol => visitor.visit_symbol(self),
+            TokenKind::Whitespace => visitor.visit_whitespace(self),
+        }?;
+
+        visitor.visit_node_end(AnyNode::TokenReference(self))
+    }
+}
+
+impl<'ast> VisitNodes<'ast> for TokenReference<'ast> {
+    fn visit_nodes<'b>(&'b self, nodes: &mut Vec<AnyNode<'ast, 'b>>) {
+        nodes.push(AnyNode::TokenReference(self));
+    }
+}
+
+impl<'ast> VisitNodesMut<'ast> for TokenReference<'ast> {
+    fn visit_nodes_mut<'b>(&'b mut self, nodes: &mut Vec<AnyNodeMut<'ast, 'b>>) {
+        nodes.push(AnyNodeMut::TokenReference(self));
+    }
+}
+
 /// Used to represent exact positions of tokens in code
+///
+/// The [`bytes`](#method.bytes) are always right, but the [`line`](#method.line) and [`character`](#method.character)
+/// of the start of a token at the start of a line are the end of the line before it, since the new line isn't
+/// counted until the character after it. To get the line and column of a position in any encoding, such as the
+/// UTF-16 code units of the Language Server Protocol, convert its bytes with a [`LineIndex`](../struct.LineIndex.html).
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Position {
@@ -480,12 +791,14 @@ impl Position {
         self.bytes
     }
 
-    /// Index of the character on the line for this position
+    /// Index of the character on the line for this position, starting at 1.
+    /// Characters are Unicode scalar values, the same as Rust `char`s, so `é` and `😀` are both 1 character,
+    /// although `é` is 2 bytes and `😀` is 4 bytes and 2 UTF-16 code units.
     pub fn character(self) -> usize {
         self.character
     }
 
-    /// Line the position lies on
+    /// Line the position lies on, starting at 1, where lines end after every `\n`
     pub fn line(self) -> usize {
         self.line
     }
@@ -503,6 +816,8 @@ impl PartialOrd for Position {
     }
 }
 
+// A line of 0 is a position the tokenizer made, which only has its bytes until the line and character are asked for,
+// since finding them from the lines of the code then is faster than counting them for every token
 #[derive(Debug)]
 pub(crate) struct AtomicPosition {
     bytes: AtomicUsize,
@@ -519,11 +834,34 @@ impl AtomicPosition {
         }
     }
 
-    fn load(&self) -> Position {
-        Position {
-            bytes: self.bytes.load(AtomicOrdering::Acquire),
-            character: self.character.load(AtomicOrdering::Acquire),
-            line: self.line.load(AtomicOrdering::Acquire),
+    fn from_bytes(bytes: usize) -> Self {
+        AtomicPosition {
+            bytes: AtomicUsize::new(bytes),
+            character: AtomicUsize::new(0),
+            line: AtomicUsize::new(0),
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        self.bytes.load(AtomicOrdering::Acquire)
+    }
+
+    fn load(&self, lines: Option<&LineIndex>) -> Position {
+        let bytes = self.bytes();
+        match (self.line.load(AtomicOrdering::Acquire), lines) {
+            // Kept once found, with the line stored last since it says whether the character is there yet
+            (0, Some(lines)) => {
+                let position = lines.position(bytes);
+                self.character
+                    .store(position.character, AtomicOrdering::Release);
+                self.line.store(position.line, AtomicOrdering::Release);
+                position
+            }
+            (line, _) => Position {
+                bytes,
+                character: self.character.load(AtomicOrdering::Acquire),
+                line,
+            },
         }
     }
 
@@ -535,26 +873,36 @@ impl AtomicPosition {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for AtomicPosition {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         Ok(AtomicPosition::new(Position::deserialize(deserializer)?))
     }
 }
 
-impl Serialize for AtomicPosition {
+// The same as deriving it, but with the lines and characters of positions that only have their bytes
+#[cfg(feature = "serde")]
+impl Serialize for Token<'_> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        self.load().serialize(serializer)
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("Token", 3)?;
+        state.serialize_field("start_position", &self.start_position())?;
+        state.serialize_field("end_position", &self.end_position())?;
+        state.serialize_field("token_type", &*self.token_type())?;
+        state.end()
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 struct TokenAdvancement<'a> {
+    // How many bytes the token is
     pub advance: usize,
     pub token_type: TokenType<'a>,
 }
 
 /// The types of quotes used in a Lua string
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum StringLiteralQuoteType {
     /// Strings formatted \[\[with brackets\]\]
@@ -576,123 +924,238 @@ impl<'a> fmt::Display for StringLiteralQuoteType {
     }
 }
 
-lazy_static! {
-    static ref PATTERN_IDENTIFIER: Regex = Regex::new(r"[^\W\d]+\w*").unwrap();
-    static ref PATTERN_NUMBER: Regex = {
-        let mut set = Vec::new();
+type Advancement<'a> = Result<Option<TokenAdvancement<'a>>, TokenizerErrorType>;
 
-        // Hexadecimal
-        set.push(r"(0x[A-Fa-f\d]+)");
+// The number of `=` between the brackets of the start of a multiline comment or string, such as `[==[`,
+// if the code starts with one
+fn multi_line_start(code: &str) -> Option<usize> {
+    let rest = code.strip_prefix('[')?;
+    let blocks = rest.bytes().take_while(|&byte| byte == b'=').count();
+    if rest[blocks..].starts_with('[') {
+        Some(blocks)
+    } else {
+        None
+    }
+}
 
-        if cfg!(feature = "roblox") {
-            // Binary literals
-            set.push(r"(0b[01]+)");
-        }
+// The start and end of the brackets closing a multiline comment or string with the number of `=`
+fn multi_line_end(code: &str, blocks: usize) -> Option<(usize, usize)> {
+    let closing = format!("]{}]", "=".repeat(blocks));
+    let start = code.find(&closing)?;
+    Some((start, start + closing.len()))
+}
 
-        // Basic numbers (123456.346e123)
-        set.push(r"(((\d*\.\d+)|(\d+))([eE]-?\d+)?)");
+// The same characters as `\w` in a regular expression, other than marks and connector punctuation
+fn is_identifier_character(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+// The length of the name at the start of the code, which can't start with a digit
+fn identifier_length(code: &str) -> usize {
+    match code.chars().next() {
+        Some(first) if is_identifier_character(first) && !first.is_numeric() => code
+            .char_indices()
+            .find(|(_, character)| !is_identifier_character(*character))
+            .map_or(code.len(), |(index, _)| index),
+        _ => 0,
+    }
+}
 
-        Regex::new(&format!("^({})", set.join("|"))).unwrap()
+fn advance_comment(code: &str) -> Advancement {
+    let rest = match code.strip_prefix("--") {
+        Some(rest) => rest,
+        None => return Ok(None),
     };
 
-    static ref PATTERN_COMMENT_MULTI_LINE_BEGIN: Regex = Regex::new(r"--\[(=*)\[").unwrap();
-    static ref PATTERN_COMMENT_SINGLE_LINE: Regex = Regex::new(r"--([^\n]*)").unwrap();
-    static ref PATTERN_STRING_MULTI_LINE_BEGIN: Regex = Regex::new(r"\[(=*)\[").unwrap();
-    static ref PATTERN_WHITESPACE: Regex = Regex::new(r"(^[^\S\n]+\n?|\n)").unwrap();
+    if let Some(block_count) = multi_line_start(rest) {
+        let beginning = "--[[".len() + block_count;
+        let (end_start, end) = match multi_line_end(code, block_count) {
+            Some(end) => end,
+            None => return Err(TokenizerErrorType::UnclosedComment),
+        };
+
+        return Ok(Some(TokenAdvancement {
+            advance: end,
+            token_type: TokenType::MultiLineComment {
+                blocks: block_count,
+                comment: Cow::from(&code[beginning..end_start]),
+            },
+        }));
+    }
+
+    let comment = &rest[..rest.find('\n').unwrap_or(rest.len())];
+    Ok(Some(TokenAdvancement {
+        advance: comment.len() + 2,
+        token_type: TokenType::SingleLineComment {
+            comment: Cow::from(comment),
+        },
+    }))
 }
 
-type Advancement<'a> = Result<Option<TokenAdvancement<'a>>, TokenizerErrorType>;
+// Reads a number the way Lua does, taking every digit and `.`, then an exponent's sign, then every letter and digit,
+// so that something like `1a` is a malformed number instead of a number and a name. The number is checked after.
+fn advance_number(code: &str) -> Advancement {
+    let bytes = code.as_bytes();
+    match bytes {
+        [b'0'..=b'9', ..] | [b'.', b'0'..=b'9', ..] => {}
+        _ => return Ok(None),
+    }
 
-macro_rules! advance_regex {
-    ($code:expr, $regex:ident, $token_type:ident($find:ident) $block:tt) => {
-        if let Some($find) = $regex.find($code) {
-            if $find.start() != 0 {
-                Ok(None)
-            } else {
-                Ok(Some(TokenAdvancement {
-                    advance: $find.as_str().chars().count(),
-                    token_type: TokenType::$token_type $block,
-                }))
-            }
-        } else {
-            Ok(None)
+    let mut length = bytes
+        .iter()
+        .take_while(|&&byte| byte.is_ascii_digit() || byte == b'.')
+        .count();
+
+    if let Some(b'e') | Some(b'E') = bytes.get(length) {
+        length += 1;
+        if let Some(b'+') | Some(b'-') = bytes.get(length) {
+            length += 1;
         }
-    };
+    }
+
+    length += bytes[length..]
+        .iter()
+        .take_while(|&&byte| byte.is_ascii_alphanumeric() || byte == b'_')
+        .count();
+
+    Ok(Some(TokenAdvancement {
+        advance: length,
+        token_type: TokenType::Number {
+            text: Cow::from(&code[..length]),
+        },
+    }))
 }
 
-fn advance_comment(code: &str) -> Advancement {
-    if let Some(beginning) = PATTERN_COMMENT_MULTI_LINE_BEGIN.find(code) {
-        if beginning.start() == 0 {
-            let block_count = beginning.end() - beginning.start() - "--[[".len();
+// Why the text of a number token isn't a number Lua can read, if it isn't one
+fn malformed_number(text: &str) -> Option<MalformedNumberReason> {
+    if is_number(text) {
+        return None;
+    }
 
-            let end_regex = Regex::new(&format!(r"\]={{{}}}\]", block_count)).unwrap();
+    let bytes = text.as_bytes();
+    let count = |start: usize, digit: fn(&u8) -> bool| {
+        start + bytes[start..].iter().take_while(|byte| digit(byte)).count()
+    };
+    let invalid = |index: usize| {
+        MalformedNumberReason::InvalidCharacter(text[index..].chars().next().unwrap_or('.'))
+    };
 
-            let end_find = match end_regex.find(code) {
-                Some(find) => find,
-                None => return Err(TokenizerErrorType::UnclosedComment),
-            };
+    if let [b'0', b'x', ..] | [b'0', b'X', ..] = bytes {
+        let end = count(2, u8::is_ascii_hexdigit);
+        return Some(if end == 2 {
+            MalformedNumberReason::MissingHexDigits
+        } else {
+            invalid(end)
+        });
+    }
 
-            let comment = &code[beginning.end()..end_find.start()];
+    if cfg!(feature = "roblox") && bytes.starts_with(b"0b") {
+        let end = count(2, |byte| *byte == b'0' || *byte == b'1');
+        return Some(if end == 2 {
+            MalformedNumberReason::MissingBinaryDigits
+        } else {
+            invalid(end)
+        });
+    }
 
-            return Ok(Some(TokenAdvancement {
-                advance: code[beginning.start()..end_find.end()].chars().count(),
-                token_type: TokenType::MultiLineComment {
-                    blocks: block_count,
-                    comment: Cow::from(comment),
-                },
-            }));
+    let mut end = count(0, u8::is_ascii_digit);
+    if bytes.get(end) == Some(&b'.') {
+        end = count(end + 1, u8::is_ascii_digit);
+        if bytes.get(end) == Some(&b'.') {
+            return Some(MalformedNumberReason::MultipleDecimalPoints);
         }
     }
 
-    if let Some(find) = PATTERN_COMMENT_SINGLE_LINE.find(code) {
-        if find.start() == 0 {
-            let comment = &find.as_str()[2..];
+    if let Some(b'e') | Some(b'E') = bytes.get(end) {
+        end += 1;
+        if let Some(b'+') | Some(b'-') = bytes.get(end) {
+            end += 1;
+        }
 
-            return Ok(Some(TokenAdvancement {
-                advance: 2 + comment.chars().count(),
-                token_type: TokenType::SingleLineComment {
-                    comment: Cow::from(comment),
-                },
-            }));
+        let exponent_end = count(end, u8::is_ascii_digit);
+        if exponent_end == end {
+            return Some(MalformedNumberReason::MissingExponentDigits);
         }
+
+        end = exponent_end;
     }
 
-    Ok(None)
+    Some(invalid(end))
 }
 
-fn advance_number(code: &str) -> Advancement {
-    advance_regex!(code, PATTERN_NUMBER, Number(find) {
-        text: Cow::from(find.as_str()),
-    })
+// Whether the text is a hexadecimal number, a binary number with the `roblox` feature,
+// or a decimal number with an optional fraction and exponent, such as `123.456e7`
+fn is_number(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let count = |start: usize, digit: fn(&u8) -> bool| {
+        start + bytes[start..].iter().take_while(|byte| digit(byte)).count()
+    };
+
+    if let [b'0', b'x', digits @ ..] | [b'0', b'X', digits @ ..] = bytes {
+        return !digits.is_empty() && digits.iter().all(u8::is_ascii_hexdigit);
+    }
+
+    if let (true, [b'0', b'b', digits @ ..]) = (cfg!(feature = "roblox"), bytes) {
+        return !digits.is_empty() && digits.iter().all(|digit| *digit == b'0' || *digit == b'1');
+    }
+
+    let mut end = count(0, u8::is_ascii_digit);
+    if bytes.get(end) == Some(&b'.') {
+        let fraction_end = count(end + 1, u8::is_ascii_digit);
+        if end == 0 && fraction_end == 1 {
+            return false;
+        }
+
+        end = fraction_end;
+    } else if end == 0 {
+        return false;
+    }
+
+    if let Some(b'e') | Some(b'E') = bytes.get(end) {
+        let mut exponent = end + 1;
+        if let Some(b'+') | Some(b'-') = bytes.get(exponent) {
+            exponent += 1;
+        }
+
+        let exponent_end = count(exponent, u8::is_ascii_digit);
+        if exponent_end > exponent {
+            end = exponent_end;
+        }
+    }
+
+    end == bytes.len()
 }
 
 fn advance_identifier(code: &str) -> Advancement {
-    advance_regex!(code, PATTERN_IDENTIFIER, Identifier(find) {
-        identifier: Cow::from(find.as_str()),
-    })
+    let length = identifier_length(code);
+    if length == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(TokenAdvancement {
+        advance: length,
+        token_type: TokenType::Identifier {
+            identifier: Cow::from(&code[..length]),
+        },
+    }))
 }
 
 fn advance_quote(code: &str) -> Advancement {
-    if let Some(beginning) = PATTERN_STRING_MULTI_LINE_BEGIN.find(code) {
-        if beginning.start() == 0 {
-            let block_count = beginning.end() - beginning.start() - "[[".len();
-
-            let end_regex = Regex::new(&format!(r"\]{}\]", "=".repeat(block_count))).unwrap();
-
-            let end_find = match end_regex.find(code) {
-                Some(find) => find,
-                None => return Err(TokenizerErrorType::UnclosedString),
-            };
+    if let Some(block_count) = multi_line_start(code) {
+        let beginning = "[[".len() + block_count;
+        let (end_start, end) = match multi_line_end(code, block_count) {
+            Some(end) => end,
+            None => return Err(TokenizerErrorType::UnclosedString),
+        };
 
-            return Ok(Some(TokenAdvancement {
-                advance: code[beginning.start()..end_find.end()].chars().count(),
-                token_type: TokenType::StringLiteral {
-                    multi_line: Some(block_count),
-                    literal: Cow::from(&code[beginning.end()..end_find.start()]),
-                    quote_type: StringLiteralQuoteType::Brackets,
-                },
-            }));
-        }
+        return Ok(Some(TokenAdvancement {
+            advance: end,
+            token_type: TokenType::StringLiteral {
+                multi_line: Some(block_count),
+                literal: Cow::from(&code[beginning..end_start]),
+                quote_type: StringLiteralQuoteType::Brackets,
+            },
+        }));
     }
 
     let quote = if code.starts_with('"') {
@@ -706,14 +1169,14 @@ fn advance_quote(code: &str) -> Advancement {
     let mut end = None;
     let mut escape = false;
 
-    for (char_index, (byte_index, character)) in code.char_indices().enumerate().skip(1) {
+    for (byte_index, character) in code.char_indices().skip(1) {
         if character == '\\' {
             escape = !escape;
         } else if character == quote {
             if escape {
                 escape = false;
             } else {
-                end = Some((char_index, byte_index));
+                end = Some(byte_index);
                 break;
             }
         } else if (character == '\r' || character == '\n') && !escape {
@@ -723,9 +1186,9 @@ fn advance_quote(code: &str) -> Advancement {
         }
     }
 
-    if let Some((char_index, byte_index)) = end {
+    if let Some(byte_index) = end {
         Ok(Some(TokenAdvancement {
-            advance: char_index + 1,
+            advance: byte_index + 1,
             token_type: TokenType::StringLiteral {
                 literal: Cow::from(&code[1..byte_index]),
                 multi_line: None,
@@ -742,31 +1205,46 @@ fn advance_quote(code: &str) -> Advancement {
 }
 
 fn advance_symbol(code: &str) -> Advancement {
-    if code.chars().next().unwrap().is_ascii_alphanumeric() {
-        let identifier = PATTERN_IDENTIFIER.find(code).unwrap();
-        let expected_len = identifier.end() - identifier.start();
-
-        advance_regex!(&code[0..expected_len], PATTERN_SYMBOL, Symbol(find) {
-            symbol: {
-                if find.end() - find.start() == expected_len {
-                    Symbol::from_str(find.as_str()).unwrap()
-                } else {
-                    return Ok(None)
-                }
-            }
-        })
+    // Keywords have to be the whole name, so that `index` isn't `in` followed by `dex`
+    let symbol = if code.starts_with(|character: char| character.is_ascii_alphanumeric()) {
+        let length = identifier_length(code);
+        Symbol::from_str(&code[..length])
+            .ok()
+            .map(|symbol| (length, symbol))
     } else {
-        advance_regex!(code, PATTERN_SYMBOL, Symbol(find) {
-            symbol: Symbol::from_str(find.as_str()).unwrap(),
-        })
-    }
+        SYMBOLS
+            .iter()
+            .find(|(string, _)| code.starts_with(string))
+            .map(|(string, symbol)| (string.len(), *symbol))
+    };
+
+    Ok(symbol.map(|(length, symbol)| TokenAdvancement {
+        advance: length,
+        token_type: TokenType::Symbol { symbol },
+    }))
 }
 
 // Keep finding whitespace until the line ends
 fn advance_whitespace(code: &str) -> Advancement {
-    advance_regex!(code, PATTERN_WHITESPACE, Whitespace(find) {
-        characters: Cow::from(find.as_str()),
-    })
+    let mut length = code
+        .char_indices()
+        .find(|(_, character)| !character.is_whitespace() || *character == '\n')
+        .map_or(code.len(), |(index, _)| index);
+
+    if code[length..].starts_with('\n') {
+        length += 1;
+    }
+
+    if length == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(TokenAdvancement {
+        advance: length,
+        token_type: TokenType::Whitespace {
+            characters: Cow::from(&code[..length]),
+        },
+    }))
 }
 
 /// Information about an error that occurs while tokenizing
@@ -779,24 +1257,103 @@ pub struct TokenizerError {
     position: Position,
 }
 
+impl fmt::Display for TokenizerErrorType {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenizerErrorType::UnclosedComment => write!(formatter, "unclosed comment"),
+            TokenizerErrorType::UnclosedString => write!(formatter, "unclosed string"),
+            TokenizerErrorType::UnexpectedToken(character) => {
+                write!(formatter, "unexpected character {}", character)
+            }
+            TokenizerErrorType::InvalidEscape { escape, .. } => {
+                write!(formatter, "{} `{}`", invalid_escape_message(escape), escape)
+            }
+            TokenizerErrorType::MalformedNumber { text, .. } => {
+                write!(formatter, "malformed number `{}`", text)
+            }
+        }
+    }
+}
+
+// What is wrong with the escape, told apart by how it starts
+fn invalid_escape_message(escape: &str) -> &'static str {
+    let escaped = &escape[1..];
+    if escaped.starts_with(|character: char| character.is_ascii_digit()) {
+        "decimal escape too large"
+    } else if escaped.starts_with('x') {
+        "incomplete hexadecimal escape"
+    } else if escaped.starts_with("u{") && escaped.ends_with('}') {
+        "unicode escape too large"
+    } else if escaped.starts_with('u') {
+        "incomplete unicode escape"
+    } else {
+        "invalid escape sequence"
+    }
+}
+
+impl TokenizerError {
+    /// The type of error
+    pub fn error(&self) -> &TokenizerErrorType {
+        &self.error
+    }
+
+    /// The position of the token that caused the error, or of the escape for an invalid escape
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// The code of the kind of error, see [`ErrorCode`](../enum.ErrorCode.html)
+    pub fn code(&self) -> ErrorCode {
+        match self.error {
+            TokenizerErrorType::UnclosedComment => ErrorCode::UnclosedComment,
+            TokenizerErrorType::UnclosedString => ErrorCode::UnclosedString,
+            TokenizerErrorType::UnexpectedToken(_) => ErrorCode::UnexpectedCharacter,
+            TokenizerErrorType::InvalidEscape { .. } => ErrorCode::InvalidEscape,
+            TokenizerErrorType::MalformedNumber { .. } => ErrorCode::MalformedNumber,
+        }
+    }
+
+    /// What went wrong, without the character or where it is, such as `unclosed string`.
+    /// This is the start of the first line of the `Display` output.
+    pub fn message(&self) -> &str {
+        match &self.error {
+            TokenizerErrorType::UnclosedComment => "unclosed comment",
+            TokenizerErrorType::UnclosedString => "unclosed string",
+            TokenizerErrorType::UnexpectedToken(_) => "unexpected character",
+            TokenizerErrorType::InvalidEscape { escape, .. } => invalid_escape_message(escape),
+            TokenizerErrorType::MalformedNumber { .. } => "malformed number",
+        }
+    }
+}
+
 impl fmt::Display for TokenizerError {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         write!(
             formatter,
-            "{} at line {}, column {}",
-            match self.error {
-                TokenizerErrorType::UnclosedComment => "unclosed comment".to_string(),
-                TokenizerErrorType::UnclosedString => "unclosed string".to_string(),
-                TokenizerErrorType::UnexpectedToken(character) => {
-                    format!("unexpected character {}", character)
-                }
-            },
+            "{} at line {}, character {}",
+            self.message(),
             self.position.line,
             self.position.character,
-        )
+        )?;
+
+        match &self.error {
+            TokenizerErrorType::UnexpectedToken(character) => {
+                write!(formatter, "\nfound `{}`", character)?;
+            }
+            TokenizerErrorType::InvalidEscape { escape, .. } => {
+                write!(formatter, "\nfound `{}`", escape)?;
+            }
+            TokenizerErrorType::MalformedNumber { text, reason, .. } => {
+                write!(formatter, "\nfound `{}`\n{}", text, reason)?;
+            }
+            _ => {}
+        }
+
+        Ok(())
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for TokenizerError {}
 
 /// Returns a list of [`Token`](struct.Token.html) structs.
@@ -814,97 +1371,506 @@ impl std::error::Error for TokenizerError {}
 /// assert!(tokens("--[[ Unclosed comment!").is_err());
 /// ```
 pub fn tokens<'a>(code: &'a str) -> Result<Vec<Token<'a>>, TokenizerError> {
+    tokenize(code, false).map(|(tokens, _)| tokens)
+}
+
+/// Returns a list of [`Token`](struct.Token.html) structs like [`tokens`](fn.tokens.html), but keeps going
+/// after invalid escapes in strings and malformed numbers, returning every one of them along with the tokens.
+/// Strings with invalid escapes are still string tokens, with the escapes left as they are,
+/// and malformed numbers are still number tokens, with all of the text that was read as the number.
+/// You probably want [`parse_all_errors`](../fn.parse_all_errors.html) instead.
+///
+/// # Errors
+///
+/// Every other error stops tokenizing, the same as with `tokens`.
+///
+/// ```rust
+/// # use full_moon::tokenizer::{tokens_all_errors, TokenizerErrorType};
+/// let (tokens, errors) = tokens_all_errors("x = '\\300' .. '\\400'").unwrap();
+/// assert_eq!(tokens.len(), 10);
+/// assert_eq!(errors.len(), 2);
+/// assert_eq!(errors[1].position().bytes(), 15);
+///
+/// assert!(tokens_all_errors("x = '").is_err());
+/// ```
+pub fn tokens_all_errors<'a>(
+    code: &'a str,
+) -> Result<(Vec<Token<'a>>, Vec<TokenizerError>), TokenizerError> {
+    tokenize(code, true)
+}
+
+// The tokens of the code and the invalid escapes in them, which are only kept going past when recovering
+fn tokenize<'a>(
+    code: &'a str,
+    recover: bool,
+) -> Result<(Vec<Token<'a>>, Vec<TokenizerError>), TokenizerError> {
     let mut tokens = Vec::new();
-    let mut position = Position {
-        bytes: 0,
-        character: 1,
-        line: 1,
-    };
+    let errors = tokenize_into(code, recover, true, &mut tokens)?;
+    Ok((tokens, errors))
+}
+
+// Like `tokenize`, but pushes the tokens onto the end of `tokens`, so its capacity can be reused,
+// and only makes tokens for whitespace if `whitespace` is true, which it otherwise only goes past
+pub(crate) fn tokenize_into<'a>(
+    code: &'a str,
+    recover: bool,
+    whitespace: bool,
+    tokens: &mut Vec<Token<'a>>,
+) -> Result<Vec<TokenizerError>, TokenizerError> {
+    let mut lexer = Lexer::new(code, recover, whitespace);
+    while let Some(token) = lexer.next_token()? {
+        tokens.push(token);
+    }
 
-    let mut next_is_new_line = false;
+    Ok(lexer.errors)
+}
 
-    macro_rules! advance {
-        ($function:ident) => {
-            match $function(&code[position.bytes..]) {
-                Ok(Some(advancement)) => {
-                    let start_position = position;
+// Makes the tokens of the code one at a time, for `tokenize_into` and for parsing a statement at a time.
+// The last token is always an Eof, after which there are no more.
+#[derive(Debug)]
+pub(crate) struct Lexer<'a> {
+    code: &'a str,
+    // Only the bytes are counted while tokenizing, and the lines and characters are found with the lines of the code
+    bytes: usize,
+    lines: Arc<LineIndex>,
+    recover: bool,
+    whitespace: bool,
+    finished: bool,
+    // The errors kept going past when recovering, since the tokens they're in were given
+    pub(crate) errors: Vec<TokenizerError>,
+}
 
-                    for character in code[position.bytes..].chars().take(advancement.advance) {
-                        if next_is_new_line {
-                            next_is_new_line = false;
-                            position.line += 1;
-                            position.character = 1;
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(code: &'a str, recover: bool, whitespace: bool) -> Self {
+        Self {
+            code,
+            bytes: 0,
+            lines: Arc::new(LineIndex::new(code)),
+            recover,
+            whitespace,
+            finished: false,
+            errors: Vec::new(),
+        }
+    }
+
+    // Whether the Eof has been given
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Goes on from the byte of the code, without making tokens of what is before it,
+    // such as to go on past a character that can't be tokenized
+    pub(crate) fn skip_to(&mut self, bytes: usize) {
+        self.bytes = bytes;
+    }
+
+    fn token(&self, start: usize, token_type: TokenType<'a>) -> Token<'a> {
+        Token {
+            start_position: Arc::new(AtomicPosition::from_bytes(start)),
+            end_position: Arc::new(AtomicPosition::from_bytes(self.bytes)),
+            token_type: Arc::new(TokenTypeCell::new(token_type, NodeId::new())),
+            lines: Some(Arc::clone(&self.lines)),
+        }
+    }
+
+    pub(crate) fn next_token(&mut self) -> Result<Option<Token<'a>>, TokenizerError> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let code = self.code;
+
+        macro_rules! advance {
+            ($function:ident) => {
+                match $function(&code[self.bytes..]) {
+                    Ok(Some(advancement)) => {
+                        let start = self.bytes;
+
+                        for error in token_errors(&advancement.token_type, start, &self.lines) {
+                            if !self.recover {
+                                return Err(error);
+                            }
+
+                            self.errors.push(error);
                         }
 
-                        if character == '\n' {
-                            next_is_new_line = true;
-                        } else {
-                            position.character += 1;
+                        self.bytes += advancement.advance;
+
+                        if !self.whitespace
+                            && advancement.token_type.kind() == TokenKind::Whitespace
+                        {
+                            continue;
                         }
 
-                        position.bytes += character.len_utf8();
+                        return Ok(Some(self.token(start, advancement.token_type)));
                     }
 
-                    tokens.push(Token {
-                        start_position: Arc::new(AtomicPosition::new(start_position)),
-                        end_position: Arc::new(AtomicPosition::new(position)),
-                        token_type: Arc::new(AtomicRefCell::new(advancement.token_type)),
-                    });
+                    Ok(None) => {}
 
-                    continue;
-                }
+                    Err(error) => {
+                        return Err(TokenizerError {
+                            error,
+                            position: self.lines.position(self.bytes),
+                        });
+                    }
+                };
+            };
+        }
+
+        while code.len() > self.bytes {
+            advance!(advance_whitespace);
+            advance!(advance_comment);
+            advance!(advance_number);
+            advance!(advance_quote);
+            advance!(advance_symbol);
+            advance!(advance_identifier);
+
+            let position = self.lines.position(self.bytes);
+            return Err(TokenizerError {
+                error: TokenizerErrorType::UnexpectedToken(
+                    code[self.bytes..]
+                        .chars()
+                        .next()
+                        .expect("text overflow while giving unexpected token error"),
+                ),
+                position,
+            });
+        }
 
-                Ok(None) => {}
+        self.finished = true;
+        Ok(Some(self.token(self.bytes, TokenType::Eof)))
+    }
+}
 
-                Err(error) => {
-                    return Err(TokenizerError { error, position });
+// The errors in a token that tokenizing can keep going past, such as invalid escapes in a string.
+// The token starts at the byte of the code the lines are of.
+fn token_errors(token_type: &TokenType, start: usize, lines: &LineIndex) -> Vec<TokenizerError> {
+    match token_type {
+        TokenType::StringLiteral {
+            literal,
+            multi_line: None,
+            ..
+        } => invalid_escapes(literal)
+            .into_iter()
+            .map(|(offset, escape)| {
+                // The escape is after the quote
+                let position = lines.character_position(start + 1 + offset);
+                TokenizerError {
+                    error: TokenizerErrorType::InvalidEscape {
+                        escape: escape.to_owned(),
+                        position,
+                    },
+                    position,
                 }
-            };
+            })
+            .collect(),
+
+        TokenType::Number { text } => malformed_number(text)
+            .map(|reason| {
+                let position = lines.character_position(start);
+                TokenizerError {
+                    error: TokenizerErrorType::MalformedNumber {
+                        text: text.to_string(),
+                        position,
+                        reason,
+                    },
+                    position,
+                }
+            })
+            .into_iter()
+            .collect(),
+
+        _ => Vec::new(),
+    }
+}
+
+/// A string in long brackets with the contents, such as `[[hello]]`, using the lowest level of `=`s
+/// that doesn't let the contents end the string early, so `a]]b` is put in `[=[a]]b]=]`.
+///
+/// Lua skips a new line right after the opening bracket, so one is added for contents that start with a new line.
+/// Long brackets have no escapes, and Lua reads carriage returns inside of them as new lines,
+/// so contents with carriage returns won't have the same value.
+///
+/// ```rust
+/// # use full_moon::tokenizer::long_string;
+/// assert_eq!(long_string("hello").to_string(), "[[hello]]");
+/// assert_eq!(long_string("a]]b").to_string(), "[=[a]]b]=]");
+/// assert_eq!(long_string("a]").to_string(), "[=[a]]=]");
+/// assert_eq!(long_string("\nhello").to_string(), "[[\n\nhello]]");
+/// ```
+pub fn long_string<'a>(contents: &'a str) -> TokenType<'a> {
+    let literal = if contents.starts_with('\n') {
+        Cow::Owned(format!("\n{}", contents))
+    } else {
+        Cow::Borrowed(contents)
+    };
+
+    TokenType::StringLiteral {
+        literal,
+        multi_line: Some(long_bracket_level(contents)),
+        quote_type: StringLiteralQuoteType::Brackets,
+    }
+}
+
+/// A multi-line comment with the contents, such as `--[[hello]]`, using the lowest level of `=`s
+/// that doesn't let the contents end the comment early, the same as [`long_string`](fn.long_string.html).
+///
+/// ```rust
+/// # use full_moon::tokenizer::long_comment;
+/// assert_eq!(long_comment(" hello ").to_string(), "--[[ hello ]]");
+/// assert_eq!(long_comment(" t[x[1]] ").to_string(), "--[=[ t[x[1]] ]=]");
+/// ```
+pub fn long_comment<'a>(contents: &'a str) -> TokenType<'a> {
+    TokenType::MultiLineComment {
+        blocks: long_bracket_level(contents),
+        comment: Cow::Borrowed(contents),
+    }
+}
+
+// The lowest level of long brackets that the contents can go in.
+// The contents can't have a closing bracket of that level, or end with one missing its last `]`.
+fn long_bracket_level(contents: &str) -> usize {
+    let bytes = contents.as_bytes();
+    let mut used = Vec::new();
+
+    for (index, _) in contents.match_indices(']') {
+        let equals = bytes[index + 1..]
+            .iter()
+            .take_while(|&&byte| byte == b'=')
+            .count();
+
+        if let None | Some(b']') = bytes.get(index + 1 + equals) {
+            used.push(equals);
+        }
+    }
+
+    (0..)
+        .find(|level| !used.contains(level))
+        .expect("ran out of levels")
+}
+
+// The bytes that the contents of a quoted string stand for, as Lua 5.1 reads them,
+// or None if the string has an escape that is too large, such as `\256`
+pub(crate) fn unescape(literal: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(literal.len());
+
+    for piece in string_pieces(literal) {
+        let escaped = match piece.strip_prefix('\\') {
+            Some(escaped) => escaped,
+            None => {
+                bytes.extend_from_slice(piece.as_bytes());
+                continue;
+            }
         };
+
+        match escaped {
+            "a" => bytes.push(7),
+            "b" => bytes.push(8),
+            "f" => bytes.push(12),
+            "n" | "\n" | "\r" | "\r\n" | "\n\r" => bytes.push(b'\n'),
+            "r" => bytes.push(b'\r'),
+            "t" => bytes.push(b'\t'),
+            "v" => bytes.push(11),
+            digits if digits.starts_with(|character: char| character.is_ascii_digit()) => {
+                bytes.push(digits.parse().ok()?)
+            }
+            escaped => bytes.extend_from_slice(escaped.as_bytes()),
+        }
     }
 
-    while code.bytes().count() > position.bytes {
-        advance!(advance_whitespace);
-        advance!(advance_comment);
-        advance!(advance_number);
-        advance!(advance_quote);
-        advance!(advance_symbol);
-        advance!(advance_identifier);
+    Some(bytes)
+}
 
-        return Err(TokenizerError {
-            error: TokenizerErrorType::UnexpectedToken(
-                code.chars()
-                    .nth(position.character - 1)
-                    .expect("text overflow while giving unexpected token error"),
-            ),
-            position,
-        });
+// Every escape in the contents of a quoted string that Lua can't read, with the byte it starts at, such as `\300`,
+// which is too large for a byte. Lua 5.1 reads any other character after a `\` as the character itself, but with
+// the `roblox` feature, escapes that Luau doesn't have are invalid too, along with `\x` and `\u{` escapes missing digits.
+fn invalid_escapes(literal: &str) -> Vec<(usize, &str)> {
+    let mut escapes = Vec::new();
+    let mut offset = 0;
+
+    for piece in string_pieces(literal) {
+        let start = offset;
+        offset += piece.len();
+
+        let escaped = match piece.strip_prefix('\\') {
+            Some(escaped) => escaped,
+            None => continue,
+        };
+
+        if escaped.starts_with(|character: char| character.is_ascii_digit()) {
+            if escaped.parse::<u16>().map_or(true, |value| value > 255) {
+                escapes.push((start, piece));
+            }
+
+            continue;
+        }
+
+        if !cfg!(feature = "roblox") {
+            continue;
+        }
+
+        let rest = &literal[offset..];
+        let hex_digits = |text: &str| {
+            text.bytes()
+                .take_while(|byte| byte.is_ascii_hexdigit())
+                .count()
+        };
+
+        let length = match escaped {
+            "a" | "b" | "f" | "n" | "r" | "t" | "v" | "z" | "\\" | "\"" | "'" => None,
+            line_break if line_break.starts_with(['\r', '\n']) => None,
+
+            "x" => {
+                let digits = hex_digits(rest).min(2);
+                if digits < 2 {
+                    Some(piece.len() + digits)
+                } else {
+                    None
+                }
+            }
+
+            "u" => match rest.strip_prefix('{') {
+                Some(braced) => {
+                    let digits = hex_digits(braced);
+                    let value = u32::from_str_radix(&braced[..digits], 16);
+                    if digits == 0 || !braced[digits..].starts_with('}') {
+                        Some(piece.len() + 1 + digits)
+                    } else if value.map_or(true, |value| value > 0x10FFFF) {
+                        Some(piece.len() + 1 + digits + 1)
+                    } else {
+                        None
+                    }
+                }
+
+                None => Some(piece.len()),
+            },
+
+            _ => Some(piece.len()),
+        };
+
+        if let Some(length) = length {
+            escapes.push((start, &literal[start..start + length]));
+        }
     }
 
-    tokens.push(Token {
-        start_position: Arc::new(AtomicPosition::new(position)),
-        end_position: Arc::new(AtomicPosition::new(position)),
-        token_type: Arc::new(AtomicRefCell::new(TokenType::Eof)),
-    });
+    escapes
+}
+
+// Splits the contents of a quoted string into escapes, which start with `\`, and single characters
+pub(crate) fn string_pieces(literal: &str) -> impl Iterator<Item = &str> {
+    let mut rest = literal;
+
+    core::iter::from_fn(move || {
+        let mut chars = rest.chars();
+        let length = match chars.next()? {
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some(escaped) => escaped,
+                    None => return Some(core::mem::take(&mut rest)),
+                };
+
+                let extra = match (escaped, chars.next()) {
+                    // A line break, which can be written as `\r\n` or `\n\r` as well
+                    ('\r', Some('\n')) | ('\n', Some('\r')) => 1,
+                    // Up to three digits
+                    (digit, next) if digit.is_ascii_digit() => {
+                        let next_is_digit = next.is_some_and(|next| next.is_ascii_digit());
+                        if next_is_digit && chars.next().is_some_and(|next| next.is_ascii_digit()) {
+                            2
+                        } else if next_is_digit {
+                            1
+                        } else {
+                            0
+                        }
+                    }
+                    _ => 0,
+                };
+
+                1 + escaped.len_utf8() + extra
+            }
+            character => character.len_utf8(),
+        };
+
+        let (piece, remaining) = rest.split_at(length);
+        rest = remaining;
+        Some(piece)
+    })
+}
 
-    Ok(tokens)
+// Token types are tagged by a field in formats meant to be read by people, such as JSON, but deserializing them
+// that way buffers all of each of them, so other formats, such as `Ast::to_bytes`, tag them the usual way
+#[cfg(feature = "serde")]
+#[derive(Deserialize, Serialize)]
+#[serde(remote = "TokenType")]
+enum ExternallyTaggedTokenType<'a> {
+    Eof,
+    Identifier {
+        #[serde(borrow)]
+        identifier: Cow<'a, str>,
+    },
+    MultiLineComment {
+        blocks: usize,
+        #[serde(borrow)]
+        comment: Cow<'a, str>,
+    },
+    Number {
+        #[serde(borrow)]
+        text: Cow<'a, str>,
+    },
+    SingleLineComment {
+        #[serde(borrow)]
+        comment: Cow<'a, str>,
+    },
+    StringLiteral {
+        #[serde(borrow)]
+        literal: Cow<'a, str>,
+        multi_line: Option<usize>,
+        quote_type: StringLiteralQuoteType,
+    },
+    Symbol {
+        symbol: Symbol,
+    },
+    Whitespace {
+        #[serde(borrow)]
+        characters: Cow<'a, str>,
+    },
 }
 
 #[cfg(feature = "serde")]
-mod serde_arc_atomic_refcell {
-    use super::*;
+impl<'a> Serialize for TokenType<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            TokenType::serialize(self, serializer)
+        } else {
+            ExternallyTaggedTokenType::serialize(self, serializer)
+        }
+    }
+}
 
-    pub fn serialize<S: Serializer, T: Serialize>(
-        this: &Arc<AtomicRefCell<T>>,
-        serializer: S,
-    ) -> Result<S::Ok, S::Error> {
-        this.borrow().serialize(serializer)
+#[cfg(feature = "serde")]
+impl<'de: 'a, 'a> Deserialize<'de> for TokenType<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            TokenType::deserialize(deserializer)
+        } else {
+            ExternallyTaggedTokenType::deserialize(deserializer)
+        }
     }
+}
+
+#[cfg(feature = "serde")]
+mod serde_token_type_cell {
+    use super::*;
 
-    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    // Tokens that are deserialized get new ids. The type is deserialized through its Deserialize impl,
+    // since `TokenType::deserialize` is the one made for it by `remote = "Self"`
+    pub fn deserialize<'de: 'a, 'a, D: Deserializer<'de>>(
         deserializer: D,
-    ) -> Result<Arc<AtomicRefCell<T>>, D::Error> {
-        Ok(Arc::new(AtomicRefCell::new(T::deserialize(deserializer)?)))
+    ) -> Result<Arc<TokenTypeCell<'a>>, D::Error> {
+        Ok(Arc::new(TokenTypeCell::new(
+            <TokenType as Deserialize>::deserialize(deserializer)?,
+            NodeId::new(),
+        )))
     }
 }
 
@@ -970,6 +1936,29 @@ mod tests {
                 },
             }))
         );
+
+        // Everything that could be in a number is read, even if it isn't one
+        assert_eq!(
+            advance_number("3..14 x"),
+            Ok(Some(TokenAdvancement {
+                advance: 5,
+                token_type: TokenType::Number {
+                    text: Cow::from("3..14"),
+                },
+            }))
+        );
+
+        assert_eq!(
+            advance_number("1e+x"),
+            Ok(Some(TokenAdvancement {
+                advance: 4,
+                token_type: TokenType::Number {
+                    text: Cow::from("1e+x"),
+                },
+            }))
+        );
+
+        test_advancer!(advance_number(".."), Ok(None));
     }
 
     #[test]
@@ -1121,17 +2110,195 @@ mod tests {
                     line: 1,
                 })),
 
-                token_type: Arc::new(AtomicRefCell::new(TokenType::Whitespace {
-                    characters: Cow::from("\n")
-                })),
+                token_type: Arc::new(TokenTypeCell::new(
+                    TokenType::Whitespace {
+                        characters: Cow::from("\n")
+                    },
+                    NodeId::new()
+                )),
+
+                lines: None,
             }
         );
     }
 
+    #[test]
+    fn test_text_is_borrowed() {
+        let code = "-- hi\n--[[ long ]] local x = 1.5 .. 'a\\n' .. [[b]]\t";
+        for token in tokens(code).unwrap() {
+            let text = match &*token.token_type() {
+                TokenType::Identifier { identifier } => matches!(identifier, Cow::Borrowed(_)),
+                TokenType::MultiLineComment { comment, .. }
+                | TokenType::SingleLineComment { comment } => matches!(comment, Cow::Borrowed(_)),
+                TokenType::Number { text } => matches!(text, Cow::Borrowed(_)),
+                TokenType::StringLiteral { literal, .. } => matches!(literal, Cow::Borrowed(_)),
+                TokenType::Whitespace { characters } => matches!(characters, Cow::Borrowed(_)),
+                TokenType::Eof | TokenType::Symbol { .. } => true,
+            };
+
+            assert!(text, "{:?} owns its text", token);
+        }
+    }
+
     #[test]
     fn test_fuzzer() {
         let _ = tokens("*ա");
         let _ = tokens("̹(");
         let _ = tokens("¹;");
     }
+
+    #[test]
+    fn test_unexpected_character() {
+        let error = tokens("x = 1\ny = ¹ & b").unwrap_err();
+        assert_eq!(error.error(), &TokenizerErrorType::UnexpectedToken('¹'));
+        assert_eq!(error.position().bytes(), 10);
+    }
+
+    #[test]
+    fn test_string_pieces() {
+        assert_eq!(
+            string_pieces(r#"a\n\"\\\65\1234\'b"#).collect::<Vec<_>>(),
+            vec!["a", "\\n", "\\\"", "\\\\", "\\65", "\\123", "4", "\\'", "b"]
+        );
+
+        assert_eq!(
+            string_pieces("é\\\r\nx\\").collect::<Vec<_>>(),
+            vec!["é", "\\\r\n", "x", "\\"]
+        );
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(
+            unescape(
+                r#"a\n\t\"\\\65\0659\255\
+b"#
+            ),
+            Some(b"a\n\t\"\\AA9\xff\nb".to_vec())
+        );
+
+        assert_eq!(unescape(r#"\256"#), None);
+    }
+
+    #[test]
+    fn test_malformed_numbers() {
+        use MalformedNumberReason::*;
+
+        for text in &["1", "1.", ".5", "1.5e-3", "1E+5", "0xFF", "0X1e"] {
+            assert_eq!(malformed_number(text), None, "{:?} is a number", text);
+        }
+
+        assert_eq!(malformed_number("0x"), Some(MissingHexDigits));
+        assert_eq!(malformed_number("0xg"), Some(MissingHexDigits));
+        assert_eq!(malformed_number("1e"), Some(MissingExponentDigits));
+        assert_eq!(malformed_number("1.5E-"), Some(MissingExponentDigits));
+        assert_eq!(malformed_number("3..14"), Some(MultipleDecimalPoints));
+        assert_eq!(malformed_number(".5.5"), Some(MultipleDecimalPoints));
+        assert_eq!(malformed_number("0x1p4"), Some(InvalidCharacter('p')));
+        assert_eq!(malformed_number("1e5x"), Some(InvalidCharacter('x')));
+        assert_eq!(malformed_number("1_000"), Some(InvalidCharacter('_')));
+
+        if cfg!(feature = "roblox") {
+            assert_eq!(malformed_number("0b101"), None);
+            assert_eq!(malformed_number("0b"), Some(MissingBinaryDigits));
+            assert_eq!(malformed_number("0b102"), Some(InvalidCharacter('2')));
+        } else {
+            assert_eq!(malformed_number("0b101"), Some(InvalidCharacter('b')));
+        }
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        assert_eq!(
+            invalid_escapes(r#"a\255\256\\300\0400\999"#),
+            vec![(5, "\\256"), (19, "\\999")]
+        );
+        assert_eq!(invalid_escapes(r#"\n\"\'\\\65"#), Vec::new());
+
+        let luau_escapes = r#"\q\x4g\x41\u{12\u{110000}\u{10FFFF}\u\z"#;
+        if cfg!(feature = "roblox") {
+            assert_eq!(
+                invalid_escapes(luau_escapes),
+                vec![
+                    (0, "\\q"),
+                    (2, "\\x4"),
+                    (10, "\\u{12"),
+                    (15, "\\u{110000}"),
+                    (35, "\\u"),
+                ]
+            );
+        } else {
+            assert_eq!(invalid_escapes(luau_escapes), Vec::new());
+        }
+    }
+
+    // Counts positions character by character, the way tokenizing did before it only counted bytes
+    fn counted_positions(code: &str) -> Vec<(Position, Position)> {
+        let mut position = Position {
+            bytes: 0,
+            character: 1,
+            line: 1,
+        };
+        let mut next_is_new_line = false;
+        let mut positions = Vec::new();
+
+        for token in tokens(code).unwrap() {
+            let start = position;
+            for character in token.to_string().chars() {
+                if next_is_new_line {
+                    next_is_new_line = false;
+                    position.line += 1;
+                    position.character = 1;
+                }
+
+                if character == '\n' {
+                    next_is_new_line = true;
+                } else {
+                    position.character += 1;
+                }
+
+                position.bytes += character.len_utf8();
+            }
+
+            positions.push((start, position));
+        }
+
+        positions
+    }
+
+    #[test]
+    fn test_positions_from_bytes() {
+        for code in &[
+            "local x = 1\r\nlocal y = 2\r\n\r\nprint(x, y)\r\n",
+            "local s = \"日本語\" -- 😀\nlocal é = [[\nmulti\r\nline😀]] print(s)\n\n",
+            "--[==[ 😀\n\n]==]\n\tx()",
+            "\n",
+            "",
+        ] {
+            let positions: Vec<_> = tokens(code)
+                .unwrap()
+                .iter()
+                .map(|token| (token.start_position(), token.end_position()))
+                .collect();
+            assert_eq!(positions, counted_positions(code), "{:?}", code);
+        }
+
+        // The positions are resolved rather than left as the bytes they're stored as
+        let debug = format!("{:?}", tokens("x = y").unwrap()[4]);
+        assert!(debug.starts_with(
+            "Token { start_position: Position { bytes: 4, character: 5, line: 1 }, \
+             end_position: Position { bytes: 5, character: 6, line: 1 }, \
+             token_type: Identifier { identifier: \"y\" }, id: NodeId("
+        ));
+
+        let error = tokens("-- 😀\nlocal s = '日本\\300'").unwrap_err();
+        assert_eq!(
+            error.position(),
+            Position {
+                bytes: 25,
+                character: 14,
+                line: 2,
+            }
+        );
+    }
 }
@@ -0,0 +1,967 @@
+//! Tokens are shared with the [`Ast`](../ast/struct.Ast.html) the nodes came from, so the changes
+//! show up when printing it.
+use crate::{
+    ast::{memory::Reborrow, printer::needs_separator, Assignment, Ast, BinOpRhs, LocalAssignment},
+    node::{Node, TokenRange},
+    tokenizer::{
+        string_pieces, StringLiteralQuoteType, Symbol, Token, TokenKind, TokenReference, TokenType,
+    },
+    visitors::{AnyNode, Visit, VisitMut, Visitor, VisitorMut},
+    Indent,
+};
+use generational_arena::Arena;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+mod dead_branches;
+mod fold_constants;
+
+pub use self::dead_branches::{
+    eliminate_dead_branches, eliminate_dead_branches_with, DeadBranchConfig,
+};
+pub use self::fold_constants::{fold_constants, fold_constants_with, FoldConfig};
+
+pub(crate) use self::fold_constants::{evaluate, truthiness, Constant};
+
+/// The options for [`reindent`](fn.reindent.html)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndentConfig {
+    /// The indentation for every level of nesting, 4 spaces by default
+    pub indent: Indent,
+}
+
+impl Default for IndentConfig {
+    fn default() -> Self {
+        Self {
+            indent: Indent::Spaces(4),
+        }
+    }
+}
+
+/// Rewrites the indentation of every line in a node so that the node is `depth` levels deep,
+/// such as after moving a statement into a block that is nested more deeply.
+///
+/// - Statements in a block are indented one level more than the construct the block is in,
+///   and `end`, `else`, `elseif`, and `until` are lined up with the construct
+/// - Lines inside of parentheses, brackets, and braces are indented one level more than the line they were opened on,
+///   and a closing bracket at the start of a line is lined up with that line
+/// - Any other line that continues a statement, such as part of a long expression, is indented one level more
+///   than the statement
+///
+/// Comments on their own lines are indented like statements. If the node starts its line, the indentation
+/// before it is rewritten too, unless the node is at the very start of the code with no whitespace before it.
+/// Only whitespace at the start of lines changes, so the insides of multi-line strings and comments stay the same.
+///
+/// Positions are not updated. If you need them to be, call
+/// [`Ast::update_positions`](../ast/struct.Ast.html#method.update_positions) afterwards.
+///
+/// ```rust
+/// # use full_moon::transform::{reindent, IndentConfig};
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let mut ast = full_moon::parse("if x then\ncall(\n1)\n  end")?;
+/// reindent(ast.nodes_mut(), &IndentConfig::default(), 0);
+/// assert_eq!(full_moon::print(&ast), "if x then\n    call(\n        1)\nend");
+/// # Ok(())
+/// # }
+/// ```
+pub fn reindent<'a, N: Node + Visit<'a>>(node: &mut N, config: &IndentConfig, depth: usize) {
+    let mut structure = Structure::default();
+    node.visit(&mut structure);
+
+    let (arena, (first, last)) = match (&structure.arena, structure.range) {
+        (Some(arena), Some(range)) => (Arc::clone(arena), range),
+        _ => return,
+    };
+
+    let indent = config.indent.whitespace();
+    // The first token of every line so far, and how deep the line is
+    let mut lines: Vec<(usize, usize)> = Vec::new();
+    let line_depth = |lines: &[(usize, usize)], offset: usize| {
+        lines
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= offset)
+            .map_or(depth, |(_, depth)| *depth)
+    };
+
+    for offset in first..=last {
+        let token = match arena.get_unknown_gen(offset) {
+            Some((token, _)) if token.token_kind() != TokenKind::Whitespace => token,
+            _ => continue,
+        };
+
+        let indentation = match line_indentation(&arena, offset) {
+            Some(indentation) => indentation,
+            None => continue,
+        };
+
+        let statement_like = offset == first
+            || structure.statement_starts.contains(&offset)
+            || token.token_type().ignore();
+
+        let container = structure
+            .containers
+            .iter()
+            .filter(|container| container.open < offset && offset <= container.close)
+            .min_by_key(|container| container.close - container.open);
+
+        let level = match container {
+            Some(container) if container.close == offset => line_depth(&lines, container.anchor),
+            Some(container) if !container.block || statement_like => {
+                line_depth(&lines, container.anchor) + 1
+            }
+            Some(container) => line_depth(&lines, container.anchor) + 2,
+            None if statement_like => depth,
+            None => depth + 1,
+        };
+
+        lines.push((offset, level));
+
+        if let Some(whitespace) = indentation.and_then(|index| arena.get_unknown_gen(index)) {
+            let mut token_type = whitespace.0.token_type.borrow_mut();
+            if let TokenType::Whitespace { characters } = &mut *token_type {
+                let line_start = characters.rfind('\n').map_or(0, |index| index + 1);
+                let mut new_characters = characters[..line_start].to_owned();
+                for _ in 0..level {
+                    new_characters.push_str(&indent);
+                }
+
+                *characters = Cow::Owned(new_characters);
+            }
+        }
+    }
+}
+
+// Whether the token at the offset starts a line, and if so, the whitespace token before it to put indentation in.
+// At the start of the code, there might be no whitespace token to use.
+fn line_indentation(arena: &Arena<Token>, offset: usize) -> Option<Option<usize>> {
+    let whitespace = |offset: usize| {
+        arena
+            .get_unknown_gen(offset)
+            .and_then(|(token, _)| match &*token.token_type() {
+                TokenType::Whitespace { characters } => Some(characters.contains('\n')),
+                _ => None,
+            })
+    };
+
+    if offset == 0 {
+        return Some(None);
+    }
+
+    match whitespace(offset - 1) {
+        Some(true) => Some(Some(offset - 1)),
+        Some(false) if offset == 1 || whitespace(offset - 2) == Some(true) => {
+            Some(Some(offset - 1))
+        }
+        _ => None,
+    }
+}
+
+// Something that indents the lines inside of it, either a block or a contained span
+struct Container {
+    // The token on the line the container is indented relative to
+    anchor: usize,
+    open: usize,
+    close: usize,
+    block: bool,
+}
+
+// The offsets in the arena of the tokens in a node that decide how each line is indented
+#[derive(Default)]
+struct Structure<'a> {
+    arena: Option<Arc<Arena<Token<'a>>>>,
+    range: Option<(usize, usize)>,
+    containers: Vec<Container>,
+    statement_starts: HashSet<usize>,
+    // The first token of every statement being visited
+    statements: Vec<Option<usize>>,
+}
+
+impl<'a> Structure<'a> {
+    fn offset(&mut self, token: &TokenReference<'a>) -> Option<usize> {
+        let (arena, offset) = token.arena_offset()?;
+        match &self.arena {
+            Some(existing) if !Arc::ptr_eq(existing, arena) => None,
+            Some(_) => Some(offset),
+            None => {
+                self.arena = Some(Arc::clone(arena));
+                Some(offset)
+            }
+        }
+    }
+
+    fn token(&mut self, token: &TokenReference<'a>) {
+        if let Some(offset) = self.offset(token) {
+            self.range = Some(match self.range {
+                Some((first, last)) => (first.min(offset), last.max(offset)),
+                None => (offset, offset),
+            });
+
+            self.statement_contains(offset);
+        }
+    }
+
+    fn statement_contains(&mut self, offset: usize) {
+        if let Some(start) = self.statements.last_mut() {
+            *start = Some(start.map_or(offset, |start| start.min(offset)));
+        }
+    }
+
+    fn container(
+        &mut self,
+        anchor: &TokenReference<'a>,
+        open: &TokenReference<'a>,
+        close: &TokenReference<'a>,
+        block: bool,
+    ) {
+        if let (Some(anchor), Some(open), Some(close)) =
+            (self.offset(anchor), self.offset(open), self.offset(close))
+        {
+            self.containers.push(Container {
+                anchor,
+                open,
+                close,
+                block,
+            });
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Structure<'a> {
+    fn visit_node(&mut self, node: AnyNode<'a, '_>) {
+        match node {
+            AnyNode::TokenReference(token) => self.token(token),
+
+            // The tokens of contained spans aren't visited on their own
+            AnyNode::ContainedSpan(span) => {
+                let (open, close) = span.tokens();
+                self.token(open);
+                self.token(close);
+                self.container(open, open, close, false);
+            }
+
+            AnyNode::Stmt(_) | AnyNode::LastStmt(_) => self.statements.push(None),
+
+            AnyNode::Do(do_block) => self.container(
+                do_block.do_token(),
+                do_block.do_token(),
+                do_block.end_token(),
+                true,
+            ),
+
+            AnyNode::While(while_loop) => self.container(
+                while_loop.while_token(),
+                while_loop.do_token(),
+                while_loop.end_token(),
+                true,
+            ),
+
+            AnyNode::Repeat(repeat) => self.container(
+                repeat.repeat_token(),
+                repeat.repeat_token(),
+                repeat.until_token(),
+                true,
+            ),
+
+            AnyNode::NumericFor(numeric_for) => self.container(
+                numeric_for.for_token(),
+                numeric_for.do_token(),
+                numeric_for.end_token(),
+                true,
+            ),
+
+            AnyNode::GenericFor(generic_for) => self.container(
+                generic_for.for_token(),
+                generic_for.do_token(),
+                generic_for.end_token(),
+                true,
+            ),
+
+            AnyNode::FunctionBody(body) => {
+                let (open, close) = body.parameters_parantheses().tokens();
+                self.container(open, close, body.end_token(), true);
+            }
+
+            AnyNode::If(if_block) => {
+                let (mut anchor, mut open) = (if_block.if_token(), if_block.then_token());
+
+                for else_if in if_block.else_if().into_iter().flatten() {
+                    self.container(anchor, open, else_if.else_if_token(), true);
+                    anchor = else_if.else_if_token();
+                    open = else_if.then_token();
+                }
+
+                if let Some(else_token) = if_block.else_token() {
+                    self.container(anchor, open, else_token, true);
+                    anchor = else_token;
+                    open = else_token;
+                }
+
+                self.container(anchor, open, if_block.end_token(), true);
+            }
+
+            _ => {}
+        }
+    }
+
+    fn visit_node_end(&mut self, node: AnyNode<'a, '_>) {
+        if let AnyNode::Stmt(_) | AnyNode::LastStmt(_) = node {
+            if let Some(Some(start)) = self.statements.pop() {
+                self.statement_starts.insert(start);
+                self.statement_contains(start);
+            }
+        }
+    }
+}
+
+/// Normalizes the spacing of an [`Ast`](../ast/struct.Ast.html) without changing which lines anything is on:
+/// - Exactly one space around binary operators, such as `a + b`, and around the `=` of assignments
+/// - No space before commas, and one space after them, except before the `}` ending a table
+/// - No whitespace at the end of lines
+///
+/// Indentation is left alone, as are comments, and whitespace is never added directly next to a comment.
+/// Unary operators aren't changed, so `a - -b` stays as it is. Spaces around binary operators are never removed,
+/// which matters for `..` next to numbers, since `1 .. 2` can't be written as `1..2`.
+///
+/// Whitespace tokens are added to the Ast where there was no whitespace before. They have no width,
+/// since positions are not updated and still point into the original code. If you need them to be updated,
+/// call [`Ast::update_positions`](../ast/struct.Ast.html#method.update_positions) afterwards.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let mut ast = full_moon::parse("local x=a+-b  \ncall(x , 1,2)")?;
+/// full_moon::transform::normalize_spacing(&mut ast);
+/// assert_eq!(full_moon::print(&ast), "local x = a + -b\ncall(x, 1, 2)");
+/// # Ok(())
+/// # }
+/// ```
+pub fn normalize_spacing(ast: &mut Ast) {
+    let mut spacing = Spacing {
+        arena: Arc::clone(&ast.tokens),
+        insertions: BTreeMap::new(),
+    };
+
+    ast.nodes_mut().visit_mut(&mut spacing);
+
+    // Trailing whitespace
+    let arena = &spacing.arena;
+    for (index, token) in arena.iter() {
+        let offset = index.into_raw_parts().0;
+        let next_kind = arena
+            .get_unknown_gen(offset + 1)
+            .map(|(next, _)| next.token_kind());
+
+        let mut token_type = token.token_type.borrow_mut();
+        if let TokenType::Whitespace { characters } = &mut *token_type {
+            if characters.ends_with('\n') {
+                let trimmed = characters.trim_start_matches(|c| c != '\n');
+                if trimmed.len() != characters.len() {
+                    *characters = Cow::Owned(trimmed.to_owned());
+                }
+            } else if next_kind == Some(TokenKind::Eof) {
+                *characters = Cow::Borrowed("");
+            }
+        }
+    }
+
+    insert_whitespace(ast, &spacing.insertions);
+}
+
+// Sets the whitespace between tokens, remembering where whitespace has to be added
+struct Spacing<'a> {
+    arena: Arc<Arena<Token<'a>>>,
+    // The whitespace to add before tokens, by their offset
+    insertions: BTreeMap<usize, &'static str>,
+}
+
+impl<'a> Spacing<'a> {
+    fn get(&self, offset: usize) -> Option<&Token<'a>> {
+        self.arena.get_unknown_gen(offset).map(|(token, _)| token)
+    }
+
+    fn offset(&self, token: &TokenReference<'a>) -> Option<usize> {
+        match token.arena_offset() {
+            Some((arena, offset)) if Arc::ptr_eq(arena, &self.arena) => Some(offset),
+            _ => None,
+        }
+    }
+
+    // Sets the whitespace between the tokens at `offset - 1` and `offset`, or between them and
+    // the whitespace token in between. Whitespace at the start of lines or next to comments isn't changed.
+    fn set_gap(&mut self, before: usize, after: usize, whitespace: &'static str) {
+        let (before_token, after_token) = match (self.get(before), self.get(after)) {
+            (Some(before_token), Some(after_token)) => (before_token, after_token),
+            _ => return,
+        };
+
+        match (before_token.token_kind(), after_token.token_kind()) {
+            (TokenKind::Whitespace, _) | (_, TokenKind::Whitespace) => {
+                let whitespace_token = if before_token.token_kind() == TokenKind::Whitespace {
+                    before_token
+                } else {
+                    after_token
+                };
+
+                let mut token_type = whitespace_token.token_type.borrow_mut();
+                if let TokenType::Whitespace { characters } = &mut *token_type {
+                    if !characters.contains('\n') {
+                        *characters = Cow::Borrowed(whitespace);
+                    }
+                }
+            }
+
+            (before_kind, after_kind)
+                if !is_comment(before_kind)
+                    && !is_comment(after_kind)
+                    && !whitespace.is_empty() =>
+            {
+                self.insertions.insert(after, whitespace);
+            }
+
+            _ => {}
+        }
+    }
+
+    fn space_around(&mut self, token: &TokenReference<'a>) {
+        if let Some(offset) = self.offset(token) {
+            if let Some(before) = self.whitespace_before(offset) {
+                self.set_gap(before, offset, " ");
+            }
+
+            self.set_gap(offset, offset + 1, " ");
+        }
+    }
+
+    // The token before the gap in front of a token, or None if the token starts a line
+    fn whitespace_before(&self, offset: usize) -> Option<usize> {
+        let before = offset.checked_sub(1)?;
+        let is_newline = |offset: usize| match self.get(offset).map(|token| token.token_type()) {
+            Some(token_type) => match &*token_type {
+                TokenType::Whitespace { characters } => characters.contains('\n'),
+                _ => false,
+            },
+            None => true,
+        };
+
+        let indentation = self.get(before)?.token_kind() == TokenKind::Whitespace
+            && (before == 0 || is_newline(before - 1));
+
+        if is_newline(before) || indentation {
+            None
+        } else {
+            Some(before)
+        }
+    }
+}
+
+impl<'a> VisitorMut<'a> for Spacing<'a> {
+    fn visit_bin_op(&mut self, bin_op: &mut BinOpRhs<'a>) {
+        self.space_around(bin_op.bin_op().token());
+    }
+
+    fn visit_assignment(&mut self, assignment: &mut Assignment<'a>) {
+        self.space_around(assignment.equal_token());
+    }
+
+    fn visit_local_assignment(&mut self, local_assignment: &mut LocalAssignment<'a>) {
+        if let Some(equal_token) = local_assignment.equal_token() {
+            self.space_around(equal_token);
+        }
+    }
+
+    fn visit_symbol(&mut self, token: &mut TokenReference<'a>) {
+        if *token.token_type()
+            != (TokenType::Symbol {
+                symbol: Symbol::Comma,
+            })
+        {
+            return;
+        }
+
+        if let Some(offset) = self.offset(token) {
+            if let Some(before) = self.whitespace_before(offset) {
+                self.set_gap(before, offset, "");
+            }
+
+            let closes_table = self.get(offset + 1).is_some_and(|next| {
+                *next.token_type()
+                    == (TokenType::Symbol {
+                        symbol: Symbol::RightBrace,
+                    })
+            });
+
+            if !closes_table {
+                self.set_gap(offset, offset + 1, " ");
+            }
+        }
+    }
+}
+
+fn ends_line(token: &Token) -> bool {
+    match &*token.token_type() {
+        TokenType::Whitespace { characters } => characters.contains('\n'),
+        _ => false,
+    }
+}
+
+fn is_comment(kind: TokenKind) -> bool {
+    kind == TokenKind::SingleLineComment || kind == TokenKind::MultiLineComment
+}
+
+/// Adds whitespace to an [`Ast`](../ast/struct.Ast.html) wherever two tokens would otherwise run together
+/// when printed, so that the printed code is read back as the same tokens. This is needed after changing tokens,
+/// such as by emptying the whitespace between them or by changing a symbol into a name, which can print code
+/// like `localx=1`.
+///
+/// - A space is added between two tokens that would be read as one, such as two names, a number followed by `.`,
+///   or two `-`s
+/// - A new line is added after a single line comment that is followed by more code on the same line
+///
+/// Existing comments and whitespace are never changed, and nothing is added where it isn't needed.
+///
+/// Whitespace tokens are added with no width, since positions are not updated. If you need them to be updated,
+/// call [`Ast::update_positions`](../ast/struct.Ast.html#method.update_positions) afterwards.
+///
+/// ```rust
+/// # use std::borrow::Cow;
+/// # use full_moon::tokenizer::{TokenReference, TokenType};
+/// # use full_moon::visitors::VisitorMut;
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// struct RemoveWhitespace;
+///
+/// impl<'ast> VisitorMut<'ast> for RemoveWhitespace {
+///     fn visit_whitespace(&mut self, token: &mut TokenReference<'ast>) {
+///         token.set_token_type(TokenType::Whitespace {
+///             characters: Cow::Borrowed(""),
+///         });
+///     }
+/// }
+///
+/// let mut ast = full_moon::parse("local x = 1 .. 2 - -y -- done\nreturn x")?;
+/// RemoveWhitespace.visit_ast(&mut ast);
+/// assert_eq!(full_moon::print(&ast), "localx=1..2--y-- donereturnx");
+///
+/// full_moon::transform::ensure_valid_spacing(&mut ast);
+/// assert_eq!(full_moon::print(&ast), "local x=1 .. 2- -y-- done\nreturn x");
+/// # Ok(())
+/// # }
+/// ```
+pub fn ensure_valid_spacing(ast: &mut Ast) {
+    let mut insertions = BTreeMap::new();
+    // The last token that prints anything, and whether it is a number
+    let mut previous: Option<(String, bool)> = None;
+    let mut in_comment = false;
+
+    for (index, token) in ast.tokens.iter() {
+        let offset = index.into_raw_parts().0;
+        let text = token.to_string();
+        if text.is_empty() {
+            continue;
+        }
+
+        match &*token.token_type() {
+            TokenType::Whitespace { characters } => {
+                if characters.contains('\n') {
+                    in_comment = false;
+                }
+            }
+
+            token_type => {
+                if in_comment {
+                    insertions.insert(offset, "\n");
+                } else if let Some((previous_text, previous_number)) = &previous {
+                    if needs_separator(previous_text, *previous_number, &text) {
+                        insertions.insert(offset, " ");
+                    }
+                }
+
+                in_comment = token_type.kind() == TokenKind::SingleLineComment;
+            }
+        }
+
+        let number = token.token_kind() == TokenKind::Number;
+        previous = Some((text, number));
+    }
+
+    insert_whitespace(ast, &insertions);
+}
+
+/// How [`transfer_trivia`](fn.transfer_trivia.html) combines the trivia of the old node with the trivia
+/// the new node already has
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TriviaMerge {
+    /// Drop the trivia the new node already has, so it only has the trivia of the old node
+    Replace,
+    /// Keep the trivia the new node already has, with the trivia of the old node added outside of it.
+    /// The old node's leading trivia comes before the new node's, and its trailing trivia after the new node's,
+    /// at the end of the same line.
+    Append,
+}
+
+/// Copies the comments and whitespace around the old node `from` onto the new node `to`, for when `to`
+/// is going to replace `from`. The leading trivia goes before the first token of `to`, and the trailing trivia
+/// after its last token, using the same definitions as [`TriviaBehavior`](../node/enum.TriviaBehavior.html).
+/// `merge` decides what happens to the trivia `to` already has.
+///
+/// Trivia is stored next to the tokens it surrounds, so `to` is given its own copy of its tokens with the new trivia
+/// around them. Changing the comments and whitespace of `to` afterwards doesn't change the code it was parsed from.
+/// Nothing happens if `to` has no tokens from parsed code, such as if it was deserialized or made with
+/// [`owned`](../ast/owned/trait.Owned.html#tymethod.owned), so the new node should be parsed from code.
+///
+/// The new trivia has no width, since positions are not updated. Inside of `to`, positions still point into
+/// the code it was parsed from.
+///
+/// To replace a node in a [`VisitorMut`](../visitors/trait.VisitorMut.html), parse the code for the new node,
+/// transfer the trivia of the old node onto a clone of it, and then put it in place of the old node:
+///
+/// ```rust
+/// # use full_moon::ast::Stmt;
+/// # use full_moon::node::{Node, TriviaBehavior};
+/// # use full_moon::transform::{transfer_trivia, TriviaMerge};
+/// # use full_moon::visitors::VisitorMut;
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// struct Replace<'ast> {
+///     replacement: Stmt<'ast>,
+/// }
+///
+/// impl<'ast> VisitorMut<'ast> for Replace<'ast> {
+///     fn visit_stmt(&mut self, stmt: &mut Stmt<'ast>) {
+///         let mut new_stmt = self.replacement.clone();
+///         transfer_trivia(&*stmt, &mut new_stmt, TriviaMerge::Replace);
+///         *stmt = new_stmt;
+///     }
+/// }
+///
+/// let mut ast = full_moon::parse("-- License\nlocal x = 1 -- one\n")?;
+/// let replacement = full_moon::parse("call()")?;
+///
+/// Replace {
+///     replacement: replacement.nodes().iter_stmts().next().unwrap().clone(),
+/// }
+/// .visit_ast(&mut ast);
+///
+/// let stmt = ast.nodes().iter_stmts().next().unwrap();
+/// assert_eq!(stmt.print(TriviaBehavior::Keep), "-- License\ncall() -- one\n");
+/// # Ok(())
+/// # }
+/// ```
+pub fn transfer_trivia<'a, F, T>(from: &F, to: &mut T, merge: TriviaMerge)
+where
+    F: Visit<'a>,
+    T: Visit<'a> + VisitMut<'a>,
+{
+    let mut old_range = TokenRange::default();
+    from.visit(&mut old_range);
+
+    let mut new_range = TokenRange::default();
+    to.visit(&mut new_range);
+
+    let (arena, (first, last), (start, end)) =
+        match (&new_range.arena, new_range.range, new_range.trivia_bounds()) {
+            (Some(arena), Some(range), Some(bounds)) => (arena, range, bounds),
+            _ => return,
+        };
+
+    let get = |arena: &Arena<Token<'a>>, offset: usize| {
+        arena
+            .get_unknown_gen(offset)
+            .map(|(token, _)| token.clone())
+    };
+
+    let (leading_position, trailing_position) = match (get(arena, first), get(arena, last)) {
+        (Some(first), Some(last)) => (first.start_position(), last.end_position()),
+        _ => return,
+    };
+
+    // The trivia of the old node, copied so that changing it doesn't change the old node's code
+    let copy = |offsets: std::ops::Range<usize>, position| -> Vec<Token<'a>> {
+        let old_arena = match &old_range.arena {
+            Some(old_arena) => old_arena,
+            None => return Vec::new(),
+        };
+
+        offsets
+            .filter_map(|offset| get(old_arena, offset))
+            .map(|token| Token::new(token.token_type().clone(), position))
+            .collect()
+    };
+
+    let (old_leading, old_trailing) = match (old_range.range, old_range.trivia_bounds()) {
+        (Some((old_first, old_last)), Some((old_start, old_end))) => (
+            copy(old_start..old_first, leading_position),
+            copy(old_last + 1..old_end + 1, trailing_position),
+        ),
+        _ => (Vec::new(), Vec::new()),
+    };
+
+    let (kept_start, kept_end) = match merge {
+        TriviaMerge::Replace => (first, last),
+        TriviaMerge::Append => (start, end),
+    };
+
+    // When both nodes end their lines, the trailing trivia of the old node goes before the new node's line ending
+    let kept_end = if kept_end > last
+        && get(arena, kept_end).is_some_and(|token| ends_line(&token))
+        && old_trailing.last().is_some_and(ends_line)
+    {
+        kept_end - 1
+    } else {
+        kept_end
+    };
+
+    let mut tokens = old_leading;
+    let mut offsets = BTreeMap::new();
+    for offset in kept_start..=kept_end {
+        if let Some(token) = get(arena, offset) {
+            offsets.insert(offset, tokens.len());
+            tokens.push(token);
+        }
+    }
+    tokens.extend(old_trailing);
+
+    let old = Arc::clone(arena);
+    let new = Arc::new(tokens.into_iter().collect::<Arena<_>>());
+    to.visit_mut(&mut Reborrow {
+        old: &old,
+        new: &new,
+        offsets: &offsets,
+    });
+}
+
+/// The quotes for [`normalize_string_quotes`](fn.normalize_string_quotes.html) to put strings in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QuoteStyle {
+    /// Strings such as "hello"
+    Double,
+    /// Strings such as 'hello'
+    Single,
+}
+
+impl QuoteStyle {
+    fn quote_type(self) -> StringLiteralQuoteType {
+        match self {
+            QuoteStyle::Double => StringLiteralQuoteType::Double,
+            QuoteStyle::Single => StringLiteralQuoteType::Single,
+        }
+    }
+}
+
+/// The options for [`normalize_string_quotes`](fn.normalize_string_quotes.html)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuoteConfig {
+    /// The quotes to put strings in, double quotes by default
+    pub style: QuoteStyle,
+    /// Whether to leave strings in their quotes if changing them would take more escapes than it removes,
+    /// such as `'say "hi"'`. True by default.
+    pub avoid_escapes: bool,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        Self {
+            style: QuoteStyle::Double,
+            avoid_escapes: true,
+        }
+    }
+}
+
+/// Rewrites the strings of an [`Ast`](../ast/struct.Ast.html) to use the quotes of `config.style`.
+/// Quotes inside of the strings are escaped or unescaped to match, and every other escape, such as `\n` or `\65`,
+/// is kept as it was, so the value of every string stays the same.
+/// Strings in long brackets, such as `[[hello]]`, aren't changed.
+///
+/// Positions are not updated. If you need them to be, call
+/// [`Ast::update_positions`](../ast/struct.Ast.html#method.update_positions) afterwards.
+///
+/// ```rust
+/// # use full_moon::transform::{normalize_string_quotes, QuoteConfig};
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let mut ast = full_moon::parse(r#"print('hello', 'it\'s', 'say "hi"', [[long]])"#)?;
+/// normalize_string_quotes(&mut ast, &QuoteConfig::default());
+/// assert_eq!(full_moon::print(&ast), r#"print("hello", "it's", 'say "hi"', [[long]])"#);
+/// # Ok(())
+/// # }
+/// ```
+pub fn normalize_string_quotes(ast: &mut Ast, config: &QuoteConfig) {
+    let quote_type = config.style.quote_type();
+
+    for (_, token) in ast.tokens.iter() {
+        let mut token_type = token.token_type.borrow_mut();
+        if let TokenType::StringLiteral {
+            literal,
+            multi_line: None,
+            quote_type: old_quote_type,
+        } = &mut *token_type
+        {
+            if *old_quote_type == quote_type || *old_quote_type == StringLiteralQuoteType::Brackets
+            {
+                continue;
+            }
+
+            let (requoted, added, removed) = requote(literal, *old_quote_type, quote_type);
+            if config.avoid_escapes && added > removed {
+                continue;
+            }
+
+            *literal = Cow::Owned(requoted);
+            *old_quote_type = quote_type;
+        }
+    }
+}
+
+// The contents of a string moved from one kind of quotes to another,
+// along with how many escapes had to be added and how many could be removed
+fn requote(
+    literal: &str,
+    from: StringLiteralQuoteType,
+    to: StringLiteralQuoteType,
+) -> (String, usize, usize) {
+    let (from, to) = (from.to_string(), to.to_string());
+    let mut requoted = String::with_capacity(literal.len());
+    let (mut added, mut removed) = (0, 0);
+
+    for piece in string_pieces(literal) {
+        if piece == to {
+            requoted.push('\\');
+            added += 1;
+        } else if piece.strip_prefix('\\') == Some(&from) {
+            requoted.push_str(&from);
+            removed += 1;
+            continue;
+        }
+
+        requoted.push_str(piece);
+    }
+
+    (requoted, added, removed)
+}
+
+// Adds whitespace tokens to the Ast before the tokens at the offsets
+fn insert_whitespace<'a>(ast: &mut Ast<'a>, insertions: &BTreeMap<usize, &'static str>) {
+    let insertions = insertions
+        .iter()
+        .filter_map(|(offset, whitespace)| {
+            let position = ast.tokens.get_unknown_gen(*offset)?.0.start_position();
+            let token = Token::new(
+                TokenType::Whitespace {
+                    characters: Cow::Borrowed(whitespace),
+                },
+                position,
+            );
+            Some((*offset, vec![token]))
+        })
+        .collect();
+
+    rebuild_arena(ast, &HashSet::new(), insertions);
+}
+
+// Removes the tokens at the offsets in `dropped` from the Ast, and adds the tokens of `insertions`
+// before the tokens at their offsets. Tokens are kept in the order they appear in the code in the arena,
+// so it has to be rebuilt, and every reference into it is pointed at the new one.
+fn rebuild_arena<'a>(
+    ast: &mut Ast<'a>,
+    dropped: &HashSet<usize>,
+    mut insertions: BTreeMap<usize, Vec<Token<'a>>>,
+) {
+    if dropped.is_empty() && insertions.is_empty() {
+        return;
+    }
+
+    let old = Arc::clone(&ast.tokens);
+    let mut tokens = Vec::with_capacity(old.len() + insertions.len());
+    let mut offsets = BTreeMap::new();
+
+    for (index, token) in old.iter() {
+        let offset = index.into_raw_parts().0;
+        if let Some(inserted) = insertions.remove(&offset) {
+            tokens.extend(inserted);
+        }
+
+        if !dropped.contains(&offset) {
+            offsets.insert(offset, tokens.len());
+            tokens.push(token.clone());
+        }
+    }
+
+    let new = Arc::new(tokens.into_iter().collect::<Arena<_>>());
+    ast.nodes_mut().visit_mut(&mut Reborrow {
+        old: &old,
+        new: &new,
+        offsets: &offsets,
+    });
+
+    ast.tokens = new;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, print, tokenizer::unescape};
+    use std::fs;
+
+    fn decoded_strings(code: &str) -> Vec<Vec<u8>> {
+        parse(code)
+            .unwrap()
+            .iter_tokens()
+            .filter_map(|token| match &*token.token_type() {
+                TokenType::StringLiteral {
+                    literal,
+                    multi_line: None,
+                    ..
+                } => Some(unescape(literal).unwrap()),
+                TokenType::StringLiteral { literal, .. } => Some(literal.as_bytes().to_vec()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn assert_round_trips(code: &str) {
+        for &style in &[QuoteStyle::Double, QuoteStyle::Single] {
+            for &avoid_escapes in &[true, false] {
+                let mut ast = parse(code).unwrap();
+                normalize_string_quotes(
+                    &mut ast,
+                    &QuoteConfig {
+                        style,
+                        avoid_escapes,
+                    },
+                );
+
+                let normalized = print(&ast);
+                assert_eq!(
+                    decoded_strings(&normalized),
+                    decoded_strings(code),
+                    "{:?} changed the value of a string in {:?}",
+                    normalized,
+                    code
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_quotes_round_trip() {
+        assert_round_trips(r#"local x = 'a"b', "a'b", 'it\'s', "say \"hi\"", '\\\'', "\\\"""#);
+        assert_round_trips(r#"local x = '\65\066\0677', "\n\t\a\v\f\b\r", '\"\'', "\'\"""#);
+        assert_round_trips("local x = 'line\\\nbreak', \"line\\\nbreak\", 'é\"ü'");
+    }
+
+    #[test]
+    #[cfg_attr(feature = "no-source-tests", ignore)]
+    fn test_quotes_round_trip_pass_cases() {
+        let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+            .expect("couldn't read directory")
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let source =
+                fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+            assert_round_trips(&source);
+        }
+    }
+}
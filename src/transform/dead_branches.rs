@@ -0,0 +1,704 @@
+use super::{ensure_valid_spacing, fold_constants::truthiness, rebuild_arena};
+use crate::{
+    ast::{Ast, Block, Do, ElseIf, Expression, If, LastStmt, Repeat, Stmt},
+    node::{StoredId, TokenRange},
+    tokenizer::{Symbol, Token, TokenKind, TokenReference, TokenType},
+    visitors::{Visit, VisitMut, Visitor, VisitorMut},
+};
+use generational_arena::Arena;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet, VecDeque},
+    mem,
+    sync::Arc,
+};
+
+/// The options for [`eliminate_dead_branches_with`](fn.eliminate_dead_branches_with.html).
+/// Use [`DeadBranchConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeadBranchConfig {
+    /// Whether to keep the comments in code that is removed by moving them to before the statement
+    /// the code was in, each on its own line, instead of removing them with the code. False by default.
+    pub hoist_comments: bool,
+}
+
+/// Removes the code of an [`Ast`](../ast/struct.Ast.html) that can never run because of a condition that is
+/// always true or always false, using [`DeadBranchConfig::default()`](struct.DeadBranchConfig.html).
+/// See [`eliminate_dead_branches_with`](fn.eliminate_dead_branches_with.html) for what is removed.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let mut ast = full_moon::parse("if true then\n    call()\nelse\n    -- unused\n    other()\nend\nwhile false do end\n")?;
+/// full_moon::transform::eliminate_dead_branches(&mut ast);
+/// assert_eq!(full_moon::print(&ast), "call()\n");
+/// # Ok(())
+/// # }
+/// ```
+pub fn eliminate_dead_branches(ast: &mut Ast) {
+    eliminate_dead_branches_with(ast, &DeadBranchConfig::default());
+}
+
+/// Removes the code of an [`Ast`](../ast/struct.Ast.html) that can never run because of a condition that is
+/// always true or always false. A condition is only known to be one or the other if it only involves literals,
+/// such as `false`, `nil`, `1`, or `not (1 == 2)`, the same way as [`fold_constants`](fn.fold_constants.html).
+///
+/// - `if` and `elseif` arms with conditions that are always false are removed. An `elseif` whose condition
+///   is always true becomes the `else`, and the arms after it are removed.
+/// - An `if` statement left with only its `else`, or with an `if` that is always true, is replaced by the
+///   statements of that block, moved out of it with the indentation they had relative to the block.
+///   If the block declares locals, which would be in scope for the code after the statement,
+///   or ends with a `return` or `break` that wouldn't be at the end of the block it's moved into,
+///   it becomes a `do` block instead. An `if` statement left with nothing is removed.
+/// - `while` loops whose conditions are always false are removed.
+/// - `repeat` loops with `until` conditions that are always true become `do` blocks,
+///   unless they have a `break` for the loop.
+///
+/// The lines the removed code was on are removed with it. Comments in the removed code are removed too,
+/// unless `config.hoist_comments` is set. Statements inside of kept blocks are checked too, including
+/// the blocks of functions.
+///
+/// Tokens are removed from the Ast, and comments are added with no width, since positions are not updated.
+/// If you need them to be, call [`Ast::update_positions`](../ast/struct.Ast.html#method.update_positions) afterwards.
+///
+/// ```rust
+/// # use full_moon::transform::{eliminate_dead_branches_with, DeadBranchConfig};
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let code = "local function f(x)
+///     if DEBUG then
+///         log(x)
+///     elseif false then
+///         -- old
+///         legacy(x)
+///     elseif true then
+///         run(x)
+///     else
+///         never(x)
+///     end
+///     repeat
+///         local y = x
+///     until true
+/// end
+/// ";
+///
+/// let mut ast = full_moon::parse(code)?;
+/// eliminate_dead_branches_with(&mut ast, &DeadBranchConfig::default());
+/// assert_eq!(full_moon::print(&ast), "local function f(x)
+///     if DEBUG then
+///         log(x)
+///     else
+///         run(x)
+///     end
+///     do
+///         local y = x
+///     end
+/// end
+/// ");
+///
+/// let mut ast = full_moon::parse(code)?;
+/// let config = DeadBranchConfig {
+///     hoist_comments: true,
+/// };
+/// eliminate_dead_branches_with(&mut ast, &config);
+/// assert!(full_moon::print(&ast).starts_with("local function f(x)
+///     -- old
+///     if DEBUG then"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn eliminate_dead_branches_with(ast: &mut Ast, config: &DeadBranchConfig) {
+    let mut eliminator = Eliminator {
+        arena: Arc::clone(&ast.tokens),
+        config,
+        dropped: HashSet::new(),
+        insertions: BTreeMap::new(),
+    };
+
+    ast.nodes_mut().visit_mut(&mut eliminator);
+
+    let Eliminator {
+        dropped,
+        insertions,
+        ..
+    } = eliminator;
+    if !dropped.is_empty() {
+        rebuild_arena(ast, &dropped, insertions);
+        ensure_valid_spacing(ast);
+    }
+}
+
+struct Eliminator<'a, 'b> {
+    arena: Arc<Arena<Token<'a>>>,
+    config: &'b DeadBranchConfig,
+    // The offsets of the tokens to remove, and the comments to add before the tokens at offsets
+    dropped: HashSet<usize>,
+    insertions: BTreeMap<usize, Vec<Token<'a>>>,
+}
+
+// What a statement becomes
+enum Rewrite<'a> {
+    Keep(Stmt<'a>),
+    Remove,
+    // The statements of a block, which are moved into the block the statement was in
+    Splice(Block<'a>),
+}
+
+// An `if`, `elseif`, or `else` and the block after it, with the offsets of its first token and of its `then`
+struct Arm<'a> {
+    keyword: TokenReference<'a>,
+    condition: Option<Expression<'a>>,
+    then_token: Option<TokenReference<'a>>,
+    block: Block<'a>,
+    offset: usize,
+    then_offset: Option<usize>,
+    // Whether the condition is always true or always false, with `else` always being true
+    truthiness: Option<bool>,
+}
+
+impl<'a> Eliminator<'a, '_> {
+    fn get(&self, offset: usize) -> Option<&Token<'a>> {
+        self.arena.get_unknown_gen(offset).map(|(token, _)| token)
+    }
+
+    fn offset(&self, token: &TokenReference<'a>) -> Option<usize> {
+        match token.arena_offset() {
+            Some((arena, offset)) if Arc::ptr_eq(arena, &self.arena) => Some(offset),
+            _ => None,
+        }
+    }
+
+    fn range<N: Visit<'a>>(&self, node: &N) -> TokenRange<'a> {
+        let mut range = TokenRange::default();
+        node.visit(&mut range);
+        range
+    }
+
+    // Some(true) for whitespace that ends a line, Some(false) for other whitespace, and None for other tokens
+    fn whitespace(&self, offset: usize) -> Option<bool> {
+        match &*self.get(offset)?.token_type() {
+            TokenType::Whitespace { characters } => Some(characters.contains('\n')),
+            _ => None,
+        }
+    }
+
+    // The whitespace at the start of the line of the token at the offset, or nothing if it doesn't start its line
+    fn indentation(&self, offset: usize) -> String {
+        let line_start = |offset: usize| offset == 0 || self.whitespace(offset - 1) == Some(true);
+        if line_start(offset) {
+            return String::new();
+        }
+
+        match self.whitespace(offset - 1) {
+            Some(false) if line_start(offset - 1) => self
+                .get(offset - 1)
+                .map_or_else(String::new, |token| token.to_string()),
+            _ => String::new(),
+        }
+    }
+
+    fn retype(&self, offset: usize, symbol: Symbol) {
+        if let Some(token) = self.get(offset) {
+            *token.token_type.borrow_mut() = TokenType::Symbol { symbol };
+        }
+    }
+
+    // Removes the tokens from `first` to `last`, along with the whitespace around them on their line.
+    // If nothing else is on their lines, the lines are removed entirely.
+    fn remove(&mut self, first: usize, last: usize, statement: usize) {
+        if first > last {
+            return;
+        }
+
+        let mut start = first;
+        while start > 0 && self.whitespace(start - 1) == Some(false) {
+            start -= 1;
+        }
+        let starts_line = start == 0 || self.whitespace(start - 1) == Some(true);
+
+        let mut end = last;
+        let (ends_line, line_end) = if self.whitespace(last) == Some(true) {
+            (true, last)
+        } else {
+            while self.whitespace(end + 1) == Some(false) {
+                end += 1;
+            }
+
+            match self.get(end + 1) {
+                Some(token) if token.token_kind() == TokenKind::Eof => (true, end),
+                Some(_) if self.whitespace(end + 1) == Some(true) => (true, end + 1),
+                Some(_) => (false, end),
+                None => (true, end),
+            }
+        };
+
+        let (start, end) = match (starts_line, ends_line) {
+            (true, true) => (start, line_end),
+            (false, true) => (start, end),
+            // Code in the middle of a line keeps the whitespace on one side of it
+            (false, false) if end == last && self.whitespace(last).is_none() => (start, end),
+            (_, false) => (first, end),
+        };
+
+        if self.config.hoist_comments {
+            self.hoist(first, last, statement);
+        }
+
+        self.dropped.extend(start..=end);
+    }
+
+    // Copies the comments from `first` to `last` to before the line of the statement starting at `statement`
+    fn hoist(&mut self, first: usize, last: usize, statement: usize) {
+        let indentation = self.indentation(statement);
+        let line_start = if indentation.is_empty() {
+            statement
+        } else {
+            statement - 1
+        };
+
+        let position = match self.get(line_start) {
+            Some(token) => token.start_position(),
+            None => return,
+        };
+
+        let whitespace = |characters: String| {
+            Token::new(
+                TokenType::Whitespace {
+                    characters: Cow::Owned(characters),
+                },
+                position,
+            )
+        };
+
+        let mut hoisted = Vec::new();
+        for offset in first..=last {
+            let comment = match self.get(offset) {
+                Some(token) if is_comment(token.token_kind()) => token.token_type().clone(),
+                _ => continue,
+            };
+
+            if !indentation.is_empty() {
+                hoisted.push(whitespace(indentation.clone()));
+            }
+            hoisted.push(Token::new(comment, position));
+            hoisted.push(whitespace("\n".to_owned()));
+        }
+
+        self.insertions
+            .entry(line_start)
+            .or_default()
+            .extend(hoisted);
+    }
+
+    fn rewrite(&mut self, stmt: Stmt<'a>, range: (usize, usize), ends_block: bool) -> Rewrite<'a> {
+        match stmt {
+            Stmt::If(if_stmt) => self.rewrite_if(if_stmt, range, ends_block),
+
+            Stmt::While(while_loop) if truthiness(while_loop.condition()) == Some(false) => {
+                self.remove(range.0, range.1, range.0);
+                Rewrite::Remove
+            }
+
+            Stmt::Repeat(repeat)
+                if truthiness(repeat.until()) == Some(true) && !breaks(repeat.block()) =>
+            {
+                let (until, last) = match (
+                    self.offset(&repeat.until_token),
+                    self.range(&repeat.until).range,
+                ) {
+                    (Some(until), Some((_, last))) => (until, last),
+                    _ => return Rewrite::Keep(Stmt::Repeat(repeat)),
+                };
+
+                let Repeat {
+                    repeat_token,
+                    block,
+                    until_token,
+                    ..
+                } = repeat;
+
+                self.retype(range.0, Symbol::Do);
+                self.retype(until, Symbol::End);
+                self.remove(until + 1, last, range.0);
+
+                Rewrite::Keep(Stmt::Do(Do {
+                    do_token: repeat_token,
+                    block,
+                    end_token: until_token,
+                    id: StoredId::new(),
+                }))
+            }
+
+            stmt => Rewrite::Keep(stmt),
+        }
+    }
+
+    fn rewrite_if(
+        &mut self,
+        if_stmt: If<'a>,
+        range: (usize, usize),
+        ends_block: bool,
+    ) -> Rewrite<'a> {
+        let known = std::iter::once(if_stmt.condition())
+            .chain(if_stmt.else_if.iter().flatten().map(ElseIf::condition))
+            .any(|condition| truthiness(condition).is_some());
+        let end = self.offset(&if_stmt.end_token);
+
+        let arms = match (known, end, self.arms(&if_stmt)) {
+            (true, Some(_), Some(arms)) => arms,
+            _ => return Rewrite::Keep(Stmt::If(if_stmt)),
+        };
+        let end = end.expect("the end was found");
+
+        let If {
+            if_token,
+            condition,
+            then_token,
+            block,
+            else_if,
+            else_token,
+            r#else,
+            end_token,
+            id,
+        } = if_stmt;
+
+        let mut blocks = vec![(if_token, Some(condition), Some(then_token), block)];
+        for else_if in else_if.into_iter().flatten() {
+            blocks.push((
+                else_if.else_if_token,
+                Some(else_if.condition),
+                Some(else_if.then_token),
+                else_if.block,
+            ));
+        }
+        if let (Some(else_token), Some(else_block)) = (else_token, r#else) {
+            blocks.push((else_token, None, None, else_block));
+        }
+
+        let arms: Vec<Arm<'a>> = blocks
+            .into_iter()
+            .zip(arms)
+            .map(
+                |((keyword, condition, then_token, block), (offset, then_offset, truthiness))| {
+                    Arm {
+                        keyword,
+                        condition,
+                        then_token,
+                        block,
+                        offset,
+                        then_offset,
+                        truthiness,
+                    }
+                },
+            )
+            .collect();
+
+        // The arms that can run, up to the first that always runs when it's reached
+        let next_offsets: Vec<usize> = arms
+            .iter()
+            .skip(1)
+            .map(|arm| arm.offset)
+            .chain(std::iter::once(end))
+            .collect();
+        let mut kept: Vec<(Arm<'a>, usize)> = Vec::new();
+        let mut removed: Vec<(usize, usize)> = Vec::new();
+
+        for (arm, next) in arms.into_iter().zip(next_offsets) {
+            let always = kept
+                .last()
+                .is_some_and(|(last, _)| last.truthiness == Some(true));
+            if always || arm.truthiness == Some(false) {
+                match removed.last_mut() {
+                    Some((_, last)) if *last + 1 == arm.offset => *last = next - 1,
+                    _ => removed.push((arm.offset, next - 1)),
+                }
+            } else {
+                kept.push((arm, next));
+            }
+        }
+
+        let first_kept = kept.first().map(|(arm, _)| arm.truthiness);
+        match first_kept {
+            None => {
+                self.remove(range.0, range.1, range.0);
+                Rewrite::Remove
+            }
+
+            // Only one arm is left, and it always runs
+            Some(Some(true)) => {
+                let (arm, _) = kept.pop().expect("there is an arm left");
+                self.unwrap(arm, range, end, end_token, ends_block)
+            }
+
+            Some(_) => {
+                for (first, last) in removed {
+                    self.remove(first, last, range.0);
+                }
+
+                let mut kept = kept.into_iter().map(|(arm, _)| arm);
+                let first = kept.next().expect("there is an arm left");
+                self.retype(first.offset, Symbol::If);
+
+                let mut if_stmt = If {
+                    if_token: first.keyword,
+                    condition: first.condition.expect("the first arm left has a condition"),
+                    then_token: first.then_token.expect("arms with conditions have `then`"),
+                    block: first.block,
+                    else_if: None,
+                    else_token: None,
+                    r#else: None,
+                    end_token,
+                    id,
+                };
+
+                let mut else_ifs = Vec::new();
+                for arm in kept {
+                    match (arm.truthiness, arm.condition, arm.then_token) {
+                        (None, Some(condition), Some(then_token)) => else_ifs.push(ElseIf {
+                            else_if_token: arm.keyword,
+                            condition,
+                            then_token,
+                            block: arm.block,
+                            id: StoredId::new(),
+                        }),
+
+                        // An `elseif` that always runs is the `else`
+                        (_, condition, _) => {
+                            if condition.is_some() {
+                                self.retype(arm.offset, Symbol::Else);
+                                if let Some(then_offset) = arm.then_offset {
+                                    self.remove(arm.offset + 1, then_offset, range.0);
+                                }
+                            }
+
+                            if_stmt.else_token = Some(arm.keyword);
+                            if_stmt.r#else = Some(arm.block);
+                        }
+                    }
+                }
+
+                if !else_ifs.is_empty() {
+                    if_stmt.else_if = Some(else_ifs);
+                }
+
+                Rewrite::Keep(Stmt::If(if_stmt))
+            }
+        }
+    }
+
+    // The offsets of the first tokens and the `then` tokens of every arm, and whether they always run
+    #[allow(clippy::type_complexity)]
+    fn arms(&self, if_stmt: &If<'a>) -> Option<Vec<(usize, Option<usize>, Option<bool>)>> {
+        let mut arms = vec![(
+            self.offset(if_stmt.if_token())?,
+            Some(self.offset(if_stmt.then_token())?),
+            truthiness(if_stmt.condition()),
+        )];
+
+        for else_if in if_stmt.else_if.iter().flatten() {
+            arms.push((
+                self.offset(else_if.else_if_token())?,
+                Some(self.offset(else_if.then_token())?),
+                truthiness(else_if.condition()),
+            ));
+        }
+
+        if let Some(else_token) = &if_stmt.else_token {
+            arms.push((self.offset(else_token)?, None, Some(true)));
+        }
+
+        Some(arms)
+    }
+
+    // Replaces an `if` statement with the block of the only arm that's left
+    fn unwrap(
+        &mut self,
+        arm: Arm<'a>,
+        range: (usize, usize),
+        end: usize,
+        end_token: TokenReference<'a>,
+        ends_block: bool,
+    ) -> Rewrite<'a> {
+        let (lead, trail) = match self.range(&arm.block).trivia_bounds() {
+            Some(bounds) => bounds,
+            None => {
+                self.remove(range.0, range.1, range.0);
+                return Rewrite::Remove;
+            }
+        };
+
+        let declares = arm
+            .block
+            .iter_stmts()
+            .any(|stmt| matches!(stmt, Stmt::LocalAssignment(_) | Stmt::LocalFunction(_)));
+
+        if declares || (arm.block.last_stmt.is_some() && !ends_block) {
+            if arm.offset > range.0 {
+                self.remove(range.0, arm.offset - 1, range.0);
+            }
+            self.retype(arm.offset, Symbol::Do);
+            if let Some(then_offset) = arm.then_offset {
+                self.remove(arm.offset + 1, then_offset, range.0);
+            }
+            if trail + 1 < end {
+                self.remove(trail + 1, end - 1, range.0);
+            }
+
+            return Rewrite::Keep(Stmt::Do(Do {
+                do_token: arm.keyword,
+                block: arm.block,
+                end_token,
+                id: StoredId::new(),
+            }));
+        }
+
+        // The lines of the block are moved out of it, so they're indented as deep as the statement was
+        let statement_indentation = self.indentation(range.0);
+        let first_statement = self.range(&arm.block).range.map(|(first, _)| first);
+        let block_indentation =
+            first_statement.map_or_else(String::new, |first| self.indentation(first));
+
+        if !block_indentation.is_empty() {
+            for offset in lead..=trail {
+                let starts_line = offset == 0 || self.whitespace(offset - 1) == Some(true);
+                let token = match self.get(offset) {
+                    Some(token) if starts_line && self.whitespace(offset) == Some(false) => token,
+                    _ => continue,
+                };
+
+                let mut token_type = token.token_type.borrow_mut();
+                if let TokenType::Whitespace { characters } = &mut *token_type {
+                    if let Some(rest) = characters.strip_prefix(block_indentation.as_str()) {
+                        *characters = Cow::Owned(format!("{}{}", statement_indentation, rest));
+                    }
+                }
+            }
+        }
+
+        self.remove(range.0, lead - 1, range.0);
+        self.remove(trail + 1, end, range.0);
+        Rewrite::Splice(arm.block)
+    }
+}
+
+impl<'a> VisitorMut<'a> for Eliminator<'a, '_> {
+    fn visit_block(&mut self, block: &mut Block<'a>) {
+        // Statements moved out of a block are checked again, since they're in a different block now
+        let mut stmts: VecDeque<_> = mem::take(&mut block.stmts).into();
+
+        while let Some((stmt, semicolon)) = stmts.pop_front() {
+            let range = match (self.range(&stmt).range, &semicolon) {
+                (Some((first, _)), Some(semicolon)) => {
+                    self.offset(semicolon).map(|semicolon| (first, semicolon))
+                }
+                (range, _) => range,
+            };
+
+            let range = match range {
+                Some(range) => range,
+                None => {
+                    block.stmts.push((stmt, semicolon));
+                    continue;
+                }
+            };
+
+            let ends_block = stmts.is_empty() && block.last_stmt.is_none();
+            match self.rewrite(stmt, range, ends_block) {
+                Rewrite::Keep(stmt) => block.stmts.push((stmt, semicolon)),
+                Rewrite::Remove => {}
+                Rewrite::Splice(mut spliced) => {
+                    // The semicolon after the statement goes after the last of the statements moved out,
+                    // unless it already has one
+                    if let Some(semicolon) = semicolon {
+                        let last = match &mut spliced.last_stmt {
+                            Some((_, last)) => Some(last),
+                            None => spliced.stmts.last_mut().map(|(_, last)| last),
+                        };
+
+                        match last {
+                            Some(last) if last.is_none() => *last = Some(semicolon),
+                            _ => {
+                                self.dropped.insert(range.1);
+                            }
+                        }
+                    }
+
+                    for stmt in spliced.stmts.into_iter().rev() {
+                        stmts.push_front(stmt);
+                    }
+
+                    if spliced.last_stmt.is_some() {
+                        block.last_stmt = spliced.last_stmt;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_comment(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::SingleLineComment | TokenKind::MultiLineComment
+    )
+}
+
+// Whether a block has a `break` for the loop it's in, rather than for a loop inside it
+fn breaks(block: &Block) -> bool {
+    let mut breaks = Breaks::default();
+    block.visit(&mut breaks);
+    breaks.found
+}
+
+#[derive(Default)]
+struct Breaks {
+    loops: usize,
+    found: bool,
+}
+
+impl<'ast> Visitor<'ast> for Breaks {
+    fn visit_last_stmt(&mut self, last_stmt: &LastStmt<'ast>) {
+        if let LastStmt::Break(_) = last_stmt {
+            self.found |= self.loops == 0;
+        }
+    }
+
+    fn visit_while(&mut self, _: &crate::ast::While<'ast>) {
+        self.loops += 1;
+    }
+
+    fn visit_while_end(&mut self, _: &crate::ast::While<'ast>) {
+        self.loops -= 1;
+    }
+
+    fn visit_repeat(&mut self, _: &Repeat<'ast>) {
+        self.loops += 1;
+    }
+
+    fn visit_repeat_end(&mut self, _: &Repeat<'ast>) {
+        self.loops -= 1;
+    }
+
+    fn visit_numeric_for(&mut self, _: &crate::ast::NumericFor<'ast>) {
+        self.loops += 1;
+    }
+
+    fn visit_numeric_for_end(&mut self, _: &crate::ast::NumericFor<'ast>) {
+        self.loops -= 1;
+    }
+
+    fn visit_generic_for(&mut self, _: &crate::ast::GenericFor<'ast>) {
+        self.loops += 1;
+    }
+
+    fn visit_generic_for_end(&mut self, _: &crate::ast::GenericFor<'ast>) {
+        self.loops -= 1;
+    }
+
+    // A `break` in a function is for a loop in the function
+    fn visit_function_body(&mut self, _: &crate::ast::FunctionBody<'ast>) {
+        self.loops += 1;
+    }
+
+    fn visit_function_body_end(&mut self, _: &crate::ast::FunctionBody<'ast>) {
+        self.loops -= 1;
+    }
+}
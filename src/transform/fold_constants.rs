@@ -0,0 +1,825 @@
+use super::ensure_valid_spacing;
+use crate::{
+    ast::{
+        owned::Owned,
+        simplified::{self, SBinOp, UNARY_PRIORITY},
+        Ast, BinOp, BinOpRhs, Expression, UnOp, Value,
+    },
+    node::{StoredId, TokenRange},
+    tokenizer::{Position, StringLiteralQuoteType, Symbol, Token, TokenReference, TokenType},
+    visitors::{Visit, VisitMut, VisitorMut},
+};
+use generational_arena::Arena;
+use std::{borrow::Cow, mem, ops::Range, sync::Arc};
+
+/// The options for [`fold_constants_with`](fn.fold_constants_with.html).
+/// Use [`FoldConfig::default()`](#impl-Default) for the fields you don't want to change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FoldConfig {
+    /// Whether to fold `..` with a number, such as `1 .. "st"` into `"1st"`. Numbers are turned into strings
+    /// with 14 significant digits the way Lua 5.1 does, which other versions of Lua don't always agree with,
+    /// such as on `0.1 + 0.2`, so this is false by default.
+    pub fold_number_concatenation: bool,
+}
+
+/// Replaces the parts of the expressions of an [`Ast`](../ast/struct.Ast.html) that only involve literals
+/// with the values they evaluate to, such as `60 * 60` with `3600`, using [`FoldConfig::default()`](struct.FoldConfig.html).
+/// See [`fold_constants_with`](fn.fold_constants_with.html) for what is folded.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let mut ast = full_moon::parse("local timeout = 60 * 60 -- an hour\nlocal name = \"full\" .. \"moon\"")?;
+/// full_moon::transform::fold_constants(&mut ast);
+/// assert_eq!(full_moon::print(&ast), "local timeout = 3600 -- an hour\nlocal name = \"fullmoon\"");
+/// # Ok(())
+/// # }
+/// ```
+pub fn fold_constants(ast: &mut Ast) {
+    fold_constants_with(ast, &FoldConfig::default());
+}
+
+/// Replaces the parts of the expressions of an [`Ast`](../ast/struct.Ast.html) that only involve literals
+/// with the values they evaluate to. Folded code is replaced by a literal [`Value`](../ast/enum.Value.html) in the Ast,
+/// whose token keeps the comments and whitespace that were before the folded code. Comments inside of the folded code
+/// are removed with it.
+///
+/// - Arithmetic on numbers, with every number being a float, the same as in Lua 5.1 and Luau,
+///   so `7 / 2` is `3.5` and `-7 % 3` is `2`
+/// - `..` of strings, and of numbers if `config.fold_number_concatenation` is set
+/// - `-` of numbers, `#` of strings, `not` of any literal, and `==` and `~=` of any literals
+/// - `<`, `<=`, `>`, and `>=` of numbers. Strings are compared by the locale of the program running the code,
+///   so they aren't folded.
+/// - `and` and `or` whose left operand is a literal, keeping the right operand if it is what the operation
+///   evaluates to, so `true and x` becomes `x` and `false and x` becomes `false`. A right operand that is a call
+///   or `...` keeps the operator, which truncates it to one value, so `true and f()` is left as it is.
+///
+/// Only literals are folded, so there is never a call or anything else with a side effect to lose. Operations that
+/// would be an error when the code runs, such as `1 + "2"` or `#5`, or that can't be written as a single literal,
+/// such as `1 - 2`, `1 / 0`, and `0 / 0`, are left as they are. Parentheses around folded code are removed,
+/// so `(1 + 2) * x` becomes `3 * x`, but parentheses around a literal that was already there, such as `(1) * x`,
+/// are not.
+///
+/// Positions are not updated. If you need them to be, call
+/// [`Ast::update_positions`](../ast/struct.Ast.html#method.update_positions) afterwards.
+///
+/// ```rust
+/// # use full_moon::transform::{fold_constants_with, FoldConfig};
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let code = "return (2 ^ 10) * size, debug and \"x\" or \"y\", 1 .. \"st\", true and return_value";
+///
+/// let mut ast = full_moon::parse(code)?;
+/// fold_constants_with(&mut ast, &FoldConfig::default());
+/// assert_eq!(full_moon::print(&ast), "return 1024 * size, debug and \"x\" or \"y\", 1 .. \"st\", return_value");
+///
+/// let mut ast = full_moon::parse(code)?;
+/// let config = FoldConfig {
+///     fold_number_concatenation: true,
+/// };
+/// fold_constants_with(&mut ast, &config);
+/// assert_eq!(full_moon::print(&ast), "return 1024 * size, debug and \"x\" or \"y\", \"1st\", return_value");
+/// # Ok(())
+/// # }
+/// ```
+pub fn fold_constants_with(ast: &mut Ast, config: &FoldConfig) {
+    let mut folder = Folder {
+        arena: Arc::clone(&ast.tokens),
+        config,
+        in_chain: false,
+        folded: false,
+    };
+
+    ast.nodes_mut().visit_mut(&mut folder);
+
+    // Folding `return(1)` gives `return1`
+    if folder.folded {
+        ensure_valid_spacing(ast);
+    }
+}
+
+// Whether an operand can evaluate to more than one value, which is a call or `...`
+fn multiple_values(term: &Expression) -> bool {
+    match term {
+        Expression::Value { value, .. } => match &**value {
+            Value::FunctionCall(_) => true,
+            Value::Symbol(token) => matches!(
+                &*token.token_type(),
+                TokenType::Symbol {
+                    symbol: Symbol::Ellipse
+                }
+            ),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// The value of an expression, if it only involves literals, such as `2 ^ 10`.
+// This includes values that have no literal, such as `1 / 0`.
+pub(crate) fn evaluate(expression: &Expression) -> Option<Constant> {
+    let mut folder = Folder {
+        arena: Arc::new(Arena::new()),
+        config: &FoldConfig::default(),
+        in_chain: false,
+        folded: false,
+    };
+
+    // The tokens of an owned copy aren't in the arena, so folding it only evaluates it
+    let (value, _) = folder.fold(&mut expression.owned());
+    value
+}
+
+// Whether an expression is always truthy or always falsy, if only literals decide which it is, such as `not nil`
+pub(crate) fn truthiness(expression: &Expression) -> Option<bool> {
+    evaluate(expression).map(|value| value.is_truthy())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Constant {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(Vec<u8>),
+}
+
+impl Constant {
+    fn is_truthy(&self) -> bool {
+        !matches!(self, Constant::Nil | Constant::Boolean(false))
+    }
+
+    // The token of the literal for the constant, or None if there isn't one. Negative numbers are written with
+    // a unary `-` rather than as one token, and infinity and NaN have no literals.
+    fn token_type<'a>(&self) -> Option<TokenType<'a>> {
+        let symbol = |symbol| TokenType::Symbol { symbol };
+
+        Some(match self {
+            Constant::Nil => symbol(Symbol::Nil),
+            Constant::Boolean(true) => symbol(Symbol::True),
+            Constant::Boolean(false) => symbol(Symbol::False),
+            Constant::Number(number) if number.is_finite() && number.is_sign_positive() => {
+                TokenType::Number {
+                    text: Cow::Owned(number_literal(*number)),
+                }
+            }
+            Constant::Number(_) => return None,
+            Constant::String(bytes) => TokenType::StringLiteral {
+                literal: Cow::Owned(escape(bytes)),
+                multi_line: None,
+                quote_type: StringLiteralQuoteType::Double,
+            },
+        })
+    }
+
+    fn value<'a>(&self, token: TokenReference<'a>) -> Value<'a> {
+        match self {
+            Constant::Nil | Constant::Boolean(_) => Value::Symbol(token),
+            Constant::Number(_) => Value::Number(token),
+            Constant::String(_) => Value::String(token),
+        }
+    }
+
+    fn concatenation(&self, config: &FoldConfig) -> Option<Vec<u8>> {
+        match self {
+            Constant::String(bytes) => Some(bytes.clone()),
+            Constant::Number(number) if config.fold_number_concatenation && number.is_finite() => {
+                Some(lua_number_string(*number).into_bytes())
+            }
+            _ => None,
+        }
+    }
+}
+
+// The shortest number literal that is read back as the same number
+fn number_literal(number: f64) -> String {
+    let plain = number.to_string();
+    let scientific = format!("{:e}", number);
+    if scientific.len() < plain.len() {
+        scientific
+    } else {
+        plain
+    }
+}
+
+// The string Lua 5.1 turns a number into, the same as C's `%.14g`
+fn lua_number_string(number: f64) -> String {
+    let trim = |digits: &str| {
+        if digits.contains('.') {
+            digits
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_owned()
+        } else {
+            digits.to_owned()
+        }
+    };
+
+    let scientific = format!("{:.13e}", number);
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("scientific notation has an exponent");
+    let exponent: i32 = exponent.parse().expect("exponents are integers");
+
+    if !(-4..14).contains(&exponent) {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", trim(mantissa), sign, exponent.abs())
+    } else {
+        trim(&format!("{:.*}", (13 - exponent) as usize, number))
+    }
+}
+
+// The contents of a double quoted string literal with the bytes as its value
+fn escape(bytes: &[u8]) -> String {
+    let mut literal = String::with_capacity(bytes.len());
+    match std::str::from_utf8(bytes) {
+        Ok(text) => {
+            for character in text.chars() {
+                escape_character(&mut literal, character);
+            }
+        }
+
+        Err(_) => {
+            for &byte in bytes {
+                if byte.is_ascii() {
+                    escape_character(&mut literal, char::from(byte));
+                } else {
+                    literal.push_str(&format!("\\{:03}", byte));
+                }
+            }
+        }
+    }
+
+    literal
+}
+
+fn escape_character(literal: &mut String, character: char) {
+    match character {
+        '"' => literal.push_str("\\\""),
+        '\\' => literal.push_str("\\\\"),
+        '\n' => literal.push_str("\\n"),
+        '\r' => literal.push_str("\\r"),
+        '\t' => literal.push_str("\\t"),
+        // Always three digits, so a digit after the escape isn't read as part of it
+        character if character.is_ascii_control() => {
+            literal.push_str(&format!("\\{:03}", character as u32))
+        }
+        character => literal.push(character),
+    }
+}
+
+fn unary(unop: &UnOp, operand: &Constant) -> Option<Constant> {
+    Some(match (unop, operand) {
+        (UnOp::Minus(_), Constant::Number(number)) => Constant::Number(-number),
+        (UnOp::Not(_), operand) => Constant::Boolean(!operand.is_truthy()),
+        (UnOp::Hash(_), Constant::String(bytes)) => Constant::Number(bytes.len() as f64),
+        _ => return None,
+    })
+}
+
+fn binary(
+    bin_op: &BinOp,
+    left: &Constant,
+    right: Option<&Constant>,
+    config: &FoldConfig,
+) -> Option<Constant> {
+    // `and` and `or` only need their right operand if that's what they evaluate to
+    match bin_op {
+        BinOp::And(_) if !left.is_truthy() => return Some(left.clone()),
+        BinOp::Or(_) if left.is_truthy() => return Some(left.clone()),
+        BinOp::And(_) | BinOp::Or(_) => return right.cloned(),
+        _ => {}
+    }
+
+    let right = right?;
+    if let BinOp::TwoEqual(_) | BinOp::TildeEqual(_) = bin_op {
+        return Some(Constant::Boolean(
+            (left == right) == matches!(bin_op, BinOp::TwoEqual(_)),
+        ));
+    }
+
+    if let BinOp::TwoDots(_) = bin_op {
+        let mut bytes = left.concatenation(config)?;
+        bytes.extend(right.concatenation(config)?);
+        return Some(Constant::String(bytes));
+    }
+
+    let (left, right) = match (left, right) {
+        (Constant::Number(left), Constant::Number(right)) => (*left, *right),
+        _ => return None,
+    };
+
+    Some(match bin_op {
+        BinOp::Plus(_) => Constant::Number(left + right),
+        BinOp::Minus(_) => Constant::Number(left - right),
+        BinOp::Star(_) => Constant::Number(left * right),
+        BinOp::Slash(_) => Constant::Number(left / right),
+        BinOp::Percent(_) => Constant::Number(left - (left / right).floor() * right),
+        BinOp::Caret(_) => Constant::Number(left.powf(right)),
+        BinOp::LessThan(_) => Constant::Boolean(left < right),
+        BinOp::LessThanEqual(_) => Constant::Boolean(left <= right),
+        BinOp::GreaterThan(_) => Constant::Boolean(left > right),
+        BinOp::GreaterThanEqual(_) => Constant::Boolean(left >= right),
+        _ => return None,
+    })
+}
+
+struct Folder<'a, 'b> {
+    arena: Arc<Arena<Token<'a>>>,
+    config: &'b FoldConfig,
+    // Whether the next expression visited continues the one before it, such as the `2 * 3` of `1 + 2 * 3`
+    in_chain: bool,
+    folded: bool,
+}
+
+// An operand of a chain of binary operations, with the unary operators before it.
+// Whatever is folded away is None.
+struct Operand<'a> {
+    unops: Vec<Option<UnOp<'a>>>,
+    // A value, or what ends the chain, such as an expression in parentheses
+    term: Option<Expression<'a>>,
+}
+
+// An operation of a chain nested by precedence, with the operand and unary operator it starts at,
+// and the operand it ends at
+struct Operation {
+    kind: OperationKind,
+    value: Option<Constant>,
+    start: (usize, usize),
+    end: usize,
+}
+
+enum OperationKind {
+    // An operand, and whether it is a literal in parentheses made by folding what was in them
+    Operand(bool),
+    Unary(usize),
+    Binary { left: usize, right: usize },
+}
+
+// An operator waiting for the operand after it, as its operand and unary operator,
+// or as which binary operator it is
+enum Pending {
+    Unary(usize, usize),
+    Binary(usize),
+}
+
+impl<'a> Folder<'a, '_> {
+    // Folds the chain of operations in an expression, giving its value if it is a constant and whether it is
+    // now a literal made by folding it
+    fn fold(&mut self, expression: &mut Expression<'a>) -> (Option<Constant>, bool) {
+        let (mut operands, mut operators) = flatten(mem::replace(expression, placeholder()));
+        let mut operations = Vec::new();
+        let mut stack = Vec::new();
+        let mut pending = Vec::new();
+        let config = self.config;
+
+        let apply = |operations: &mut Vec<Operation>,
+                     stack: &mut Vec<usize>,
+                     pending: Pending,
+                     operands: &[Operand<'a>],
+                     operators: &[Option<BinOp<'a>>]| {
+            let right = stack.pop().expect("every operator has an operand after it");
+            let operation = match pending {
+                Pending::Unary(operand, unop) => Operation {
+                    kind: OperationKind::Unary(right),
+                    value: operands[operand].unops[unop]
+                        .as_ref()
+                        .zip(operations[right].value.as_ref())
+                        .and_then(|(unop, value)| self::unary(unop, value)),
+                    start: (operand, unop),
+                    end: operations[right].end,
+                },
+
+                Pending::Binary(operator) => {
+                    let left = stack
+                        .pop()
+                        .expect("every binary operator has an operand before it");
+                    Operation {
+                        kind: OperationKind::Binary { left, right },
+                        value: operators[operator]
+                            .as_ref()
+                            .zip(operations[left].value.as_ref())
+                            .and_then(|(bin_op, left_value)| {
+                                binary(bin_op, left_value, operations[right].value.as_ref(), config)
+                            }),
+                        start: operations[left].start,
+                        end: operations[right].end,
+                    }
+                }
+            };
+
+            stack.push(operations.len());
+            operations.push(operation);
+        };
+
+        // Nests the operations the same way as the simplified Ast, with a stack of the operators
+        // waiting for their operands
+        for index in 0..operands.len() {
+            for unop in 0..operands[index].unops.len() {
+                pending.push(Pending::Unary(index, unop));
+            }
+
+            let (value, folded) = match &mut operands[index].term {
+                Some(term) => self.term_value(term),
+                None => (None, false),
+            };
+            stack.push(operations.len());
+            operations.push(Operation {
+                kind: OperationKind::Operand(folded),
+                value,
+                start: (index, operands[index].unops.len()),
+                end: index,
+            });
+
+            let priority = match operators.get(index) {
+                Some(Some(bin_op)) => SBinOp::from(bin_op).priority().0,
+                _ => continue,
+            };
+
+            while let Some(last) = pending.pop() {
+                let right_priority = match last {
+                    Pending::Unary(..) => UNARY_PRIORITY,
+                    Pending::Binary(operator) => match &operators[operator] {
+                        Some(bin_op) => SBinOp::from(bin_op).priority().1,
+                        None => 0,
+                    },
+                };
+
+                if priority > right_priority {
+                    pending.push(last);
+                    break;
+                }
+
+                apply(&mut operations, &mut stack, last, &operands, &operators);
+            }
+
+            pending.push(Pending::Binary(index));
+        }
+
+        while let Some(last) = pending.pop() {
+            apply(&mut operations, &mut stack, last, &operands, &operators);
+        }
+
+        let root = stack.pop().expect("every expression has an operand");
+        let mut literal = false;
+        let mut work = vec![root];
+
+        while let Some(index) = work.pop() {
+            let operation = &operations[index];
+            let token_type = operation.value.as_ref().and_then(Constant::token_type);
+            let foldable = match operation.kind {
+                OperationKind::Operand(folded) => folded,
+                OperationKind::Unary(_) | OperationKind::Binary { .. } => true,
+            };
+
+            if let (Some(token_type), true) = (token_type, foldable) {
+                let value = operation
+                    .value
+                    .as_ref()
+                    .expect("only constants have tokens");
+                if self.replace(&mut operands, &mut operators, operation, value, token_type) {
+                    literal |= index == root;
+                    continue;
+                }
+            }
+
+            match operation.kind {
+                OperationKind::Operand(_) => {}
+                OperationKind::Unary(operand) => work.push(operand),
+                OperationKind::Binary { left, right } => {
+                    let left_value = &operations[left].value;
+                    // Without the operator, a call or `...` would no longer be truncated to one value
+                    let truncated = matches!(operations[right].kind, OperationKind::Operand(_))
+                        && operands[operations[right].end]
+                            .term
+                            .as_ref()
+                            .is_some_and(multiple_values);
+                    let keeps_right = !truncated
+                        && operators[operations[left].end]
+                            .as_ref()
+                            .zip(left_value.as_ref())
+                            .is_some_and(|(bin_op, left_value)| match bin_op {
+                                BinOp::And(_) => left_value.is_truthy(),
+                                BinOp::Or(_) => !left_value.is_truthy(),
+                                _ => false,
+                            });
+
+                    if keeps_right
+                        && self.remove(
+                            &mut operands,
+                            &mut operators,
+                            operation.start,
+                            operations[right].start,
+                        )
+                    {
+                        work.push(right);
+                    } else {
+                        work.push(right);
+                        work.push(left);
+                    }
+                }
+            }
+        }
+
+        *expression = assemble(operands, operators);
+        (operations[root].value.clone(), literal)
+    }
+
+    // The value of an operand, folding what is in it if it is in parentheses
+    fn term_value(&mut self, term: &mut Expression<'a>) -> (Option<Constant>, bool) {
+        let value = match term {
+            Expression::Value { value, .. } => value,
+            Expression::Parentheses { expression, .. } => return self.fold(expression),
+            _ => return (None, false),
+        };
+
+        let constant = match &mut **value {
+            Value::Number(token) => simplified::number(&token.to_string()).map(Constant::Number),
+            Value::String(token) => simplified::string(token).map(Constant::String),
+            Value::Symbol(token) => match &*token.token_type() {
+                TokenType::Symbol {
+                    symbol: Symbol::Nil,
+                } => Some(Constant::Nil),
+                TokenType::Symbol {
+                    symbol: Symbol::True,
+                } => Some(Constant::Boolean(true)),
+                TokenType::Symbol {
+                    symbol: Symbol::False,
+                } => Some(Constant::Boolean(false)),
+                _ => None,
+            },
+            Value::ParseExpression(Expression::Parentheses { expression, .. }) => {
+                return self.fold(expression)
+            }
+            _ => None,
+        };
+
+        (constant, false)
+    }
+
+    fn offset(&self, token: &TokenReference<'a>) -> Option<usize> {
+        match token.arena_offset() {
+            Some((arena, offset)) if Arc::ptr_eq(arena, &self.arena) => Some(offset),
+            _ => None,
+        }
+    }
+
+    // The offsets of the first and last tokens of an operand, or of the unary operator it starts at
+    fn operand_offsets(&self, operand: &Operand<'a>, unop: usize) -> Option<(usize, usize)> {
+        let mut range = TokenRange::default();
+        operand.term.as_ref()?.visit(&mut range);
+        let (first, last) = range.range?;
+
+        match operand.unops.get(unop) {
+            Some(Some(unop)) => Some((self.offset(unop.token())?, last)),
+            _ => Some((first, last)),
+        }
+    }
+
+    fn empty(&self, offsets: Range<usize>) {
+        for offset in offsets {
+            if let Some((token, _)) = self.arena.get_unknown_gen(offset) {
+                *token.token_type.borrow_mut() = TokenType::Whitespace {
+                    characters: Cow::Borrowed(""),
+                };
+            }
+        }
+    }
+
+    // Replaces an operation with a literal, giving whether it could be
+    fn replace(
+        &mut self,
+        operands: &mut [Operand<'a>],
+        operators: &mut [Option<BinOp<'a>>],
+        operation: &Operation,
+        value: &Constant,
+        token_type: TokenType<'a>,
+    ) -> bool {
+        let (start, unop) = operation.start;
+        let (first, last) = match (
+            self.operand_offsets(&operands[start], unop),
+            self.operand_offsets(&operands[operation.end], 0),
+        ) {
+            (Some((first, _)), Some((_, last))) => (first, last),
+            _ => return false,
+        };
+
+        let index = match self.arena.get_unknown_gen(first) {
+            Some((token, index)) => {
+                *token.token_type.borrow_mut() = token_type;
+                index
+            }
+            None => return false,
+        };
+        self.empty(first + 1..last + 1);
+
+        let token = TokenReference::Borrowed {
+            arena: Arc::clone(&self.arena),
+            index,
+        };
+
+        let end_unops = operands[operation.end].unops.len();
+        self.clear(
+            operands,
+            operators,
+            operation.start,
+            (operation.end, end_unops),
+        );
+        operands[operation.end].term = None;
+        operands[start].term = Some(Expression::Value {
+            value: Box::new(value.value(token)),
+            binop: None,
+            id: StoredId::new(),
+        });
+
+        self.folded = true;
+        true
+    }
+
+    // Removes everything from the start of an operation to the start of another, giving whether it could be
+    fn remove(
+        &mut self,
+        operands: &mut [Operand<'a>],
+        operators: &mut [Option<BinOp<'a>>],
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> bool {
+        match (
+            self.operand_offsets(&operands[start.0], start.1),
+            self.operand_offsets(&operands[end.0], end.1),
+        ) {
+            (Some((first, _)), Some((end, _))) => self.empty(first..end),
+            _ => return false,
+        }
+
+        self.clear(operands, operators, start, end);
+        self.folded = true;
+        true
+    }
+
+    // Removes the unary operators, operands, and binary operators from one operand and unary operator
+    // up to another, not including the operand the end is in
+    fn clear(
+        &self,
+        operands: &mut [Operand<'a>],
+        operators: &mut [Option<BinOp<'a>>],
+        start: (usize, usize),
+        end: (usize, usize),
+    ) {
+        for (index, operand) in operands
+            .iter_mut()
+            .enumerate()
+            .take(end.0 + 1)
+            .skip(start.0)
+        {
+            let unops_start = if index == start.0 { start.1 } else { 0 };
+            let unops_end = if index == end.0 {
+                end.1
+            } else {
+                operand.unops.len()
+            };
+
+            for unop in operand.unops.iter_mut().take(unops_end).skip(unops_start) {
+                *unop = None;
+            }
+
+            if index < end.0 {
+                operand.term = None;
+            }
+        }
+
+        for operator in operators.iter_mut().take(end.0).skip(start.0) {
+            *operator = None;
+        }
+    }
+}
+
+impl<'a> VisitorMut<'a> for Folder<'a, '_> {
+    // The operands and operators after the first of a chain are in expressions inside of it, which can't be
+    // folded on their own, since `2 * 3` isn't an operation of `2 * 3 ^ 2`
+    fn visit_expression(&mut self, expression: &mut Expression<'a>) {
+        if !mem::replace(&mut self.in_chain, false) {
+            self.fold(expression);
+        }
+
+        self.in_chain = matches!(expression, Expression::UnaryOperator { .. });
+    }
+
+    fn visit_bin_op(&mut self, _: &mut BinOpRhs<'a>) {
+        self.in_chain = true;
+    }
+}
+
+// Something to put in place of an expression while it is taken apart
+fn placeholder<'a>() -> Expression<'a> {
+    Expression::Value {
+        value: Box::new(Value::Symbol(TokenReference::Owned(Token::new(
+            TokenType::Symbol {
+                symbol: Symbol::Nil,
+            },
+            Position::default(),
+        )))),
+        binop: None,
+        id: StoredId::new(),
+    }
+}
+
+fn flatten(mut expression: Expression) -> (Vec<Operand>, Vec<Option<BinOp>>) {
+    let mut operands = Vec::new();
+    let mut operators = Vec::new();
+    let mut unops = Vec::new();
+
+    loop {
+        match expression {
+            Expression::UnaryOperator {
+                unop,
+                expression: operand,
+                ..
+            } => {
+                unops.push(Some(unop));
+                expression = *operand;
+            }
+
+            Expression::Value {
+                value,
+                binop: Some(binop),
+                ..
+            } => {
+                operands.push(Operand {
+                    unops: mem::take(&mut unops),
+                    term: Some(Expression::Value {
+                        value,
+                        binop: None,
+                        id: StoredId::new(),
+                    }),
+                });
+                operators.push(Some(binop.bin_op));
+                expression = *binop.rhs;
+            }
+
+            term => {
+                operands.push(Operand {
+                    unops,
+                    term: Some(term),
+                });
+                return (operands, operators);
+            }
+        }
+    }
+}
+
+// Puts what is left of a flattened chain back together. The unary operators of operands that were folded away
+// belong to the operand after them.
+fn assemble<'a>(operands: Vec<Operand<'a>>, operators: Vec<Option<BinOp<'a>>>) -> Expression<'a> {
+    let mut terms = Vec::new();
+    let mut bin_ops = Vec::new();
+    let mut unops = Vec::new();
+    let mut operators = operators.into_iter();
+
+    for operand in operands {
+        unops.extend(operand.unops.into_iter().flatten());
+        if let Some(term) = operand.term {
+            terms.push((mem::take(&mut unops), term));
+        }
+
+        if let Some(Some(bin_op)) = operators.next() {
+            bin_ops.push(bin_op);
+        }
+    }
+
+    let with_unops = |unops: Vec<UnOp<'a>>, term| {
+        unops
+            .into_iter()
+            .rev()
+            .fold(term, |expression, unop| Expression::UnaryOperator {
+                unop,
+                expression: Box::new(expression),
+                id: StoredId::new(),
+            })
+    };
+
+    let (last_unops, last) = terms.pop().expect("a chain always has an operand left");
+    let mut expression = with_unops(last_unops, last);
+
+    while let Some((unops, term)) = terms.pop() {
+        let bin_op = bin_ops
+            .pop()
+            .expect("there is an operator between every two operands");
+        let value = match term {
+            Expression::Value { value, .. } => value,
+            term => Box::new(Value::ParseExpression(term)),
+        };
+
+        expression = with_unops(
+            unops,
+            Expression::Value {
+                value,
+                binop: Some(BinOpRhs {
+                    bin_op,
+                    rhs: Box::new(expression),
+                    id: StoredId::new(),
+                }),
+                id: StoredId::new(),
+            },
+        );
+    }
+
+    expression
+}
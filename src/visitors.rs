@@ -1,15 +1,22 @@
 use crate::{
     ast::{span::ContainedSpan, *},
+    node::{Node, NodeId, NodePath},
+    prelude::*,
     private::Sealed,
-    tokenizer::TokenReference,
+    tokenizer::{Position, Symbol, Token, TokenKind, TokenReference, TokenType},
 };
-use std::{borrow::Cow, sync::Arc};
+use alloc::{borrow::Cow, collections::BTreeMap, sync::Arc};
+use core::marker::PhantomData;
+
+mod scoped;
+
+pub use self::scoped::{Local, Scope, ScopeKind, ScopedVisitor, Scopes};
 
 macro_rules! create_visitor {
     (ast: {
-        $($visit_name:ident => $ast_type:ident,)+
+        $($name:ident => $ast_type:ident,)+
     }, token: {
-        $($visit_token:ident,)+
+        $($token_name:ident,)+
     }) => {
         /// A trait that implements functions to listen for specific nodes/tokens.
         /// Unlike [`VisitorMut`](trait.VisitorMut.html), nodes/tokens passed are immutable.
@@ -36,6 +43,20 @@ macro_rules! create_visitor {
         /// # Ok(())
         /// # }
         /// ```
+        ///
+        /// # Traversal order
+        /// [`visit_ast`](#method.visit_ast) first visits every token in the code, including comments and whitespace,
+        /// in the order they appear. It then walks the nodes pre-order, and for each node calls:
+        /// 1. `visit_node`, then the method specific to the node's type, such as `visit_local_assignment`
+        /// 2. the same methods for every child of the node, in the order they appear in the code
+        /// 3. the `_end` method specific to the node's type, such as `visit_local_assignment_end`, then `visit_node_end`
+        ///
+        /// Use [`Ast::visit_with_order`](../ast/struct.Ast.html#method.visit_with_order) to call the methods
+        /// specific to a node's type after its children instead.
+        ///
+        /// Comments and whitespace are not part of any node, so they are only visited during the first pass.
+        /// They are visited independently of the tokens they are next to, but in source order together with them,
+        /// so every comment in the code is passed to `visit_single_line_comment` or `visit_multi_line_comment`.
         pub trait Visitor<'ast> {
             /// Visit the nodes of an [`Ast`](../ast/struct.Ast.html)
             fn visit_ast(&mut self, ast: &Ast<'ast>) where Self: Sized {
@@ -49,23 +70,36 @@ macro_rules! create_visitor {
                 ast.nodes().visit(self);
             }
 
+            /// Called for every node and token, before the method specific to its type.
+            /// Use [`AnyNode::kind`](enum.AnyNode.html#method.kind) to find out what it is.
+            fn visit_node(&mut self, _node: AnyNode<'ast, '_>) { }
+
+            /// Called for every node and token, after the `_end` method specific to its type
+            fn visit_node_end(&mut self, _node: AnyNode<'ast, '_>) { }
+
+            #[allow(missing_docs)]
+            fn visit_anonymous_call(&mut self, _node: &FunctionArgs<'ast>) { }
+            #[allow(missing_docs)]
+            fn visit_anonymous_call_end(&mut self, _node: &FunctionArgs<'ast>) { }
+
             paste::item! {
                 $(
                     #[allow(missing_docs)]
-                    fn $visit_name(&mut self, _node: &$ast_type<'ast>) { }
+                    fn [<visit_ $name>](&mut self, _node: &$ast_type<'ast>) { }
                     #[allow(missing_docs)]
-                    fn [<$visit_name _end>](&mut self, _node: &$ast_type<'ast>) { }
+                    fn [<visit_ $name _end>](&mut self, _node: &$ast_type<'ast>) { }
                 )+
-            }
 
-            $(
-                #[allow(missing_docs)]
-                fn $visit_token(&mut self, _token: &TokenReference<'ast>) { }
-            )+
+                $(
+                    #[allow(missing_docs)]
+                    fn [<visit_ $token_name>](&mut self, _token: &TokenReference<'ast>) { }
+                )+
+            }
         }
 
         /// A trait that implements functions to listen for specific nodes/tokens.
         /// Unlike [`Visitor`](trait.Visitor.html), nodes/tokens passed are mutable.
+        /// Nodes are visited in the same order as [`Visitor`](trait.Visitor.html#traversal-order).
         pub trait VisitorMut<'ast> {
             /// Visit the nodes of an [`Ast`](../ast/struct.Ast.html)
             fn visit_ast(&mut self, ast: &mut Ast<'ast>) where Self: Sized {
@@ -79,19 +113,452 @@ macro_rules! create_visitor {
                 ast.nodes_mut().visit_mut(self);
             }
 
+            #[allow(missing_docs)]
+            fn visit_anonymous_call(&mut self, _node: &mut FunctionArgs<'ast>) { }
+            #[allow(missing_docs)]
+            fn visit_anonymous_call_end(&mut self, _node: &mut FunctionArgs<'ast>) { }
+
             paste::item! {
                 $(
                     #[allow(missing_docs)]
-                    fn $visit_name(&mut self, _node: &mut $ast_type<'ast>) { }
+                    fn [<visit_ $name>](&mut self, _node: &mut $ast_type<'ast>) { }
+                    #[allow(missing_docs)]
+                    fn [<visit_ $name _end>](&mut self, _node: &mut $ast_type<'ast>) { }
+                )+
+
+                $(
                     #[allow(missing_docs)]
-                    fn [<$visit_name _end>](&mut self, _node: &mut $ast_type<'ast>) { }
+                    fn [<visit_ $token_name>](&mut self, _token: &mut TokenReference<'ast>) { }
                 )+
             }
+        }
+
+        /// A trait that implements functions to listen for specific nodes/tokens, like [`Visitor`](trait.Visitor.html),
+        /// but where every function can fail. The first error stops the traversal, and is returned from
+        /// [`visit_ast`](#method.visit_ast). Nodes are visited in the same order as [`Visitor`](trait.Visitor.html#traversal-order).
+        ///
+        /// ```rust
+        /// # use full_moon::ast;
+        /// # use full_moon::visitors::*;
+        /// # fn main() -> Result<(), Box<std::error::Error>> {
+        /// // A visitor that fails on the first global function declaration
+        /// struct NoGlobalFunctions;
+        ///
+        /// impl<'ast> TryVisitor<'ast> for NoGlobalFunctions {
+        ///     type Error = String;
+        ///
+        ///     fn visit_function_declaration(&mut self, _: &ast::FunctionDeclaration<'ast>) -> Result<(), String> {
+        ///         Err("global function declared".to_owned())
+        ///     }
+        /// }
+        ///
+        /// let ast = full_moon::parse("local function x() end function y() end")?;
+        /// assert_eq!(NoGlobalFunctions.visit_ast(&ast), Err("global function declared".to_owned()));
+        /// # Ok(())
+        /// # }
+        /// ```
+        pub trait TryVisitor<'ast> {
+            /// The error returned when visiting fails
+            type Error;
+
+            /// Visit the nodes of an [`Ast`](../ast/struct.Ast.html), stopping at the first error
+            fn visit_ast(&mut self, ast: &Ast<'ast>) -> Result<(), Self::Error> where Self: Sized {
+                for (index, _) in Arc::clone(&ast.tokens).iter() {
+                    TokenReference::Borrowed {
+                        arena: Arc::clone(&ast.tokens),
+                        index,
+                    }.try_visit(self)?;
+                }
+
+                ast.nodes().try_visit(self)
+            }
+
+            /// Called for every node and token, before the function specific to its type
+            fn visit_node(&mut self, _node: AnyNode<'ast, '_>) -> Result<(), Self::Error> {
+                Ok(())
+            }
+
+            /// Called for every node and token, after the `_end` function specific to its type
+            fn visit_node_end(&mut self, _node: AnyNode<'ast, '_>) -> Result<(), Self::Error> {
+                Ok(())
+            }
+
+            #[allow(missing_docs)]
+            fn visit_anonymous_call(&mut self, _node: &FunctionArgs<'ast>) -> Result<(), Self::Error> {
+                Ok(())
+            }
 
+            #[allow(missing_docs)]
+            fn visit_anonymous_call_end(&mut self, _node: &FunctionArgs<'ast>) -> Result<(), Self::Error> {
+                Ok(())
+            }
+
+            paste::item! {
+                $(
+                    #[allow(missing_docs)]
+                    fn [<visit_ $name>](&mut self, _node: &$ast_type<'ast>) -> Result<(), Self::Error> {
+                        Ok(())
+                    }
+
+                    #[allow(missing_docs)]
+                    fn [<visit_ $name _end>](&mut self, _node: &$ast_type<'ast>) -> Result<(), Self::Error> {
+                        Ok(())
+                    }
+                )+
+
+                $(
+                    #[allow(missing_docs)]
+                    fn [<visit_ $token_name>](&mut self, _token: &TokenReference<'ast>) -> Result<(), Self::Error> {
+                        Ok(())
+                    }
+                )+
+            }
+        }
+
+        pub(crate) struct PostOrder<'v, V>(pub(crate) &'v mut V);
+
+        impl<'ast, V: Visitor<'ast>> Visitor<'ast> for PostOrder<'_, V> {
+            fn visit_node_end(&mut self, node: AnyNode<'ast, '_>) {
+                self.0.visit_node(node);
+                self.0.visit_node_end(node);
+            }
+
+            paste::item! {
+                $(
+                    fn [<visit_ $name _end>](&mut self, node: &$ast_type<'ast>) {
+                        self.0.[<visit_ $name>](node);
+                        self.0.[<visit_ $name _end>](node);
+                    }
+                )+
+
+                $(
+                    fn [<visit_ $token_name>](&mut self, token: &TokenReference<'ast>) {
+                        self.0.[<visit_ $token_name>](token);
+                    }
+                )+
+            }
+        }
+
+        pub(crate) struct PostOrderMut<'v, V>(pub(crate) &'v mut V);
+
+        impl<'ast, V: VisitorMut<'ast>> VisitorMut<'ast> for PostOrderMut<'_, V> {
+            paste::item! {
+                $(
+                    fn [<visit_ $name _end>](&mut self, node: &mut $ast_type<'ast>) {
+                        self.0.[<visit_ $name>](node);
+                        self.0.[<visit_ $name _end>](node);
+                    }
+                )+
+
+                $(
+                    fn [<visit_ $token_name>](&mut self, token: &mut TokenReference<'ast>) {
+                        self.0.[<visit_ $token_name>](token);
+                    }
+                )+
+            }
+        }
+
+        pub(crate) struct Scoped<'v, V>(pub(crate) &'v mut V);
+
+        impl<'ast, V: ScopedVisitor<'ast>> Visitor<'ast> for Scoped<'_, V> {
+            fn visit_node(&mut self, node: AnyNode<'ast, '_>) {
+                scoped::enter_node(self.0, node);
+                self.0.visit_node(node);
+            }
+
+            fn visit_node_end(&mut self, node: AnyNode<'ast, '_>) {
+                self.0.visit_node_end(node);
+                scoped::exit_node(self.0, node);
+            }
+
+            fn visit_anonymous_call(&mut self, node: &FunctionArgs<'ast>) {
+                self.0.visit_anonymous_call(node);
+            }
+
+            fn visit_anonymous_call_end(&mut self, node: &FunctionArgs<'ast>) {
+                self.0.visit_anonymous_call_end(node);
+            }
+
+            paste::item! {
+                $(
+                    fn [<visit_ $name>](&mut self, node: &$ast_type<'ast>) {
+                        self.0.[<visit_ $name>](node);
+                    }
+
+                    fn [<visit_ $name _end>](&mut self, node: &$ast_type<'ast>) {
+                        self.0.[<visit_ $name _end>](node);
+                    }
+                )+
+
+                $(
+                    fn [<visit_ $token_name>](&mut self, token: &TokenReference<'ast>) {
+                        self.0.[<visit_ $token_name>](token);
+                    }
+                )+
+            }
+        }
+
+        create_visitor_builder!(
+            /// Builds a [`Visitor`](trait.Visitor.html) out of closures, one per node or token kind.
+            /// Kinds without a closure are ignored.
+            ///
+            /// ```rust
+            /// # use full_moon::visitors::*;
+            /// # fn main() -> Result<(), Box<std::error::Error>> {
+            /// let mut calls = 0;
+            /// VisitorBuilder::new()
+            ///     .on_function_call(|_| calls += 1)
+            ///     .build()
+            ///     .visit_ast(&full_moon::parse("foo(bar())")?);
+            /// assert_eq!(calls, 2);
+            /// # Ok(())
+            /// # }
+            /// ```
+            VisitorBuilder,
+            /// A [`Visitor`](trait.Visitor.html) created by [`VisitorBuilder`](struct.VisitorBuilder.html)
+            ClosureVisitor,
+            Visitor, [],
+            ast: { $($name => $ast_type,)+ },
+            token: { $($token_name,)+ }
+        );
+
+        create_visitor_builder!(
+            /// Builds a [`VisitorMut`](trait.VisitorMut.html) out of closures, one per node or token kind.
+            /// Kinds without a closure are ignored.
+            VisitorMutBuilder,
+            /// A [`VisitorMut`](trait.VisitorMut.html) created by [`VisitorMutBuilder`](struct.VisitorMutBuilder.html)
+            ClosureVisitorMut,
+            VisitorMut, [mut],
+            ast: { $($name => $ast_type,)+ },
+            token: { $($token_name,)+ }
+        );
+
+        /// A reference to any node or token in the AST, with one variant per type.
+        /// Passed to [`Visitor::visit_node`](trait.Visitor.html#method.visit_node).
+        #[derive(Clone, Copy, Debug)]
+        #[allow(missing_docs)]
+        pub enum AnyNode<'ast, 'b> {
+            $(
+                $ast_type(&'b $ast_type<'ast>),
+            )+
+            BinOp(&'b BinOp<'ast>),
+            TokenReference(&'b TokenReference<'ast>),
+        }
+
+        /// The type of an [`AnyNode`](enum.AnyNode.html), without the node itself
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[allow(missing_docs)]
+        pub enum NodeKind {
+            $(
+                $ast_type,
+            )+
+            BinOp,
+            TokenReference,
+        }
+
+        impl<'ast, 'b> AnyNode<'ast, 'b> {
+            /// The type of the node
+            pub fn kind(self) -> NodeKind {
+                match self {
+                    $(
+                        AnyNode::$ast_type(_) => NodeKind::$ast_type,
+                    )+
+                    AnyNode::BinOp(_) => NodeKind::BinOp,
+                    AnyNode::TokenReference(_) => NodeKind::TokenReference,
+                }
+            }
+
+            /// The full range of the node, if it has both start and end positions
+            pub fn range(self) -> Option<(Position, Position)> {
+                match self {
+                    $(
+                        AnyNode::$ast_type(node) => node.range(),
+                    )+
+                    AnyNode::BinOp(node) => node.range(),
+                    AnyNode::TokenReference(token) => token.range(),
+                }
+            }
+
+            /// The [id](../node/struct.NodeId.html) of the node, see [`Node::id`](../node/trait.Node.html#method.id)
+            pub fn id(self) -> NodeId {
+                match self {
+                    $(
+                        AnyNode::$ast_type(node) => node.id(),
+                    )+
+                    AnyNode::BinOp(node) => node.id(),
+                    AnyNode::TokenReference(token) => token.id(),
+                }
+                .expect("every node has an id")
+            }
+
+            // Whether both nodes are of the same type and similar, see Node::similar
+            pub(crate) fn similar(self, other: AnyNode<'ast, '_>) -> bool {
+                match (self, other) {
+                    $(
+                        (AnyNode::$ast_type(node), AnyNode::$ast_type(other)) => node.similar(other),
+                    )+
+                    (AnyNode::BinOp(node), AnyNode::BinOp(other)) => node.similar(other),
+                    (AnyNode::TokenReference(token), AnyNode::TokenReference(other)) => token.similar(other),
+                    _ => false,
+                }
+            }
+
+            pub(crate) fn visit_children(self, nodes: &mut Vec<AnyNode<'ast, 'b>>) {
+                match self {
+                    $(
+                        AnyNode::$ast_type(node) => node.visit_children(nodes),
+                    )+
+                    AnyNode::BinOp(node) => node.visit_children(nodes),
+                    AnyNode::TokenReference(_) => {}
+                }
+            }
+        }
+
+        /// A mutable reference to any node or token in the AST, with one variant per type.
+        /// The mutable counterpart to [`AnyNode`](enum.AnyNode.html).
+        #[derive(Debug)]
+        #[allow(missing_docs)]
+        pub enum AnyNodeMut<'ast, 'b> {
             $(
-                #[allow(missing_docs)]
-                fn $visit_token(&mut self, _token: &mut TokenReference<'ast>) { }
+                $ast_type(&'b mut $ast_type<'ast>),
             )+
+            BinOp(&'b mut BinOp<'ast>),
+            TokenReference(&'b mut TokenReference<'ast>),
+        }
+
+        impl<'ast, 'b> AnyNodeMut<'ast, 'b> {
+            /// The type of the node
+            pub fn kind(&self) -> NodeKind {
+                match self {
+                    $(
+                        AnyNodeMut::$ast_type(_) => NodeKind::$ast_type,
+                    )+
+                    AnyNodeMut::BinOp(_) => NodeKind::BinOp,
+                    AnyNodeMut::TokenReference(_) => NodeKind::TokenReference,
+                }
+            }
+
+            pub(crate) fn visit_children_mut(self, nodes: &mut Vec<AnyNodeMut<'ast, 'b>>) {
+                match self {
+                    $(
+                        AnyNodeMut::$ast_type(node) => node.visit_children_mut(nodes),
+                    )+
+                    AnyNodeMut::BinOp(node) => node.visit_children_mut(nodes),
+                    AnyNodeMut::TokenReference(_) => {}
+                }
+            }
+        }
+
+        $(
+            impl<'ast> FromAnyNode<'ast> for $ast_type<'ast> {
+                fn from_any_node<'b>(node: AnyNode<'ast, 'b>) -> Option<&'b Self> {
+                    match node {
+                        AnyNode::$ast_type(node) => Some(node),
+                        _ => None,
+                    }
+                }
+            }
+        )+
+
+        impl<'ast> FromAnyNode<'ast> for BinOp<'ast> {
+            fn from_any_node<'b>(node: AnyNode<'ast, 'b>) -> Option<&'b Self> {
+                match node {
+                    AnyNode::BinOp(node) => Some(node),
+                    _ => None,
+                }
+            }
+        }
+
+        impl<'ast> FromAnyNode<'ast> for TokenReference<'ast> {
+            fn from_any_node<'b>(node: AnyNode<'ast, 'b>) -> Option<&'b Self> {
+                match node {
+                    AnyNode::TokenReference(token) => Some(token),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+macro_rules! visitor_ref {
+    ([] $ty:ty) => {
+        &$ty
+    };
+    ([mut] $ty:ty) => {
+        &mut $ty
+    };
+}
+
+macro_rules! create_visitor_builder {
+    (
+        $(#[$builder_meta:meta])* $builder:ident,
+        $(#[$visitor_meta:meta])* $visitor:ident,
+        $visitor_trait:ident, $mutability:tt,
+        ast: { $($name:ident => $ast_type:ident,)+ },
+        token: { $($token_name:ident,)+ }
+    ) => {
+        paste::item! {
+            $(#[$builder_meta])*
+            #[derive(Default)]
+            pub struct $builder<'ast, 'b> {
+                $(
+                    [<visit_ $name>]: Option<Box<dyn FnMut(visitor_ref!($mutability $ast_type<'ast>)) + 'b>>,
+                )+
+                $(
+                    [<visit_ $token_name>]: Option<Box<dyn FnMut(visitor_ref!($mutability TokenReference<'ast>)) + 'b>>,
+                )+
+            }
+
+            impl<'ast, 'b> $builder<'ast, 'b> {
+                /// Creates a builder with no closures registered
+                pub fn new() -> Self {
+                    Self::default()
+                }
+
+                $(
+                    #[allow(missing_docs)]
+                    pub fn [<on_ $name>](mut self, callback: impl FnMut(visitor_ref!($mutability $ast_type<'ast>)) + 'b) -> Self {
+                        self.[<visit_ $name>] = Some(Box::new(callback));
+                        self
+                    }
+                )+
+
+                $(
+                    #[allow(missing_docs)]
+                    pub fn [<on_ $token_name>](mut self, callback: impl FnMut(visitor_ref!($mutability TokenReference<'ast>)) + 'b) -> Self {
+                        self.[<visit_ $token_name>] = Some(Box::new(callback));
+                        self
+                    }
+                )+
+
+                /// Finishes the builder, returning the visitor
+                pub fn build(self) -> $visitor<'ast, 'b> {
+                    $visitor { callbacks: self }
+                }
+            }
+
+            $(#[$visitor_meta])*
+            pub struct $visitor<'ast, 'b> {
+                callbacks: $builder<'ast, 'b>,
+            }
+
+            impl<'ast, 'b> $visitor_trait<'ast> for $visitor<'ast, 'b> {
+                $(
+                    fn [<visit_ $name>](&mut self, node: visitor_ref!($mutability $ast_type<'ast>)) {
+                        if let Some(callback) = &mut self.callbacks.[<visit_ $name>] {
+                            callback(node);
+                        }
+                    }
+                )+
+
+                $(
+                    fn [<visit_ $token_name>](&mut self, token: visitor_ref!($mutability TokenReference<'ast>)) {
+                        if let Some(callback) = &mut self.callbacks.[<visit_ $token_name>] {
+                            callback(token);
+                        }
+                    }
+                )+
+            }
         }
     };
 }
@@ -106,6 +573,138 @@ pub trait VisitMut<'ast>: Sealed {
     fn visit_mut<V: VisitorMut<'ast>>(&mut self, visitor: &mut V);
 }
 
+/// The order nodes are visited in, used by [`Ast::visit_with_order`](../ast/struct.Ast.html#method.visit_with_order)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraversalOrder {
+    /// Parents are visited before their children, the default when using [`Visitor::visit_ast`](trait.Visitor.html#method.visit_ast)
+    PreOrder,
+    /// Children are visited before their parents.
+    /// The methods specific to a node's type, such as `visit_local_assignment`, are called right before its `_end` method.
+    PostOrder,
+}
+
+/// Implemented for every type that can be found in the AST, used by
+/// [`Ast::nodes_of_type`](../ast/struct.Ast.html#method.nodes_of_type) to pick out nodes of that type
+pub trait FromAnyNode<'ast>: Sealed + Sized {
+    /// Returns the node if it is of this type
+    fn from_any_node<'b>(node: AnyNode<'ast, 'b>) -> Option<&'b Self>;
+}
+
+#[doc(hidden)]
+pub trait TryVisit<'ast>: Sealed {
+    fn try_visit<V: TryVisitor<'ast>>(&self, visitor: &mut V) -> Result<(), V::Error>;
+}
+
+#[doc(hidden)]
+pub trait VisitNodes<'ast>: Sealed {
+    fn visit_nodes<'b>(&'b self, nodes: &mut Vec<AnyNode<'ast, 'b>>);
+
+    fn visit_children<'b>(&'b self, _nodes: &mut Vec<AnyNode<'ast, 'b>>) {}
+}
+
+#[doc(hidden)]
+pub trait VisitNodesMut<'ast>: Sealed {
+    fn visit_nodes_mut<'b>(&'b mut self, nodes: &mut Vec<AnyNodeMut<'ast, 'b>>);
+
+    fn visit_children_mut<'b>(&'b mut self, _nodes: &mut Vec<AnyNodeMut<'ast, 'b>>) {}
+}
+
+/// A lazy, pre-order iterator over a node and everything inside of it
+pub struct Nodes<'ast, 'b> {
+    stack: Vec<AnyNode<'ast, 'b>>,
+}
+
+impl<'ast, 'b> Nodes<'ast, 'b> {
+    pub(crate) fn new<T: VisitNodes<'ast>>(root: &'b T) -> Self {
+        let mut stack = Vec::new();
+        root.visit_nodes(&mut stack);
+        stack.reverse();
+        Self { stack }
+    }
+}
+
+impl<'ast, 'b> Iterator for Nodes<'ast, 'b> {
+    type Item = AnyNode<'ast, 'b>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        let len = self.stack.len();
+        node.visit_children(&mut self.stack);
+        self.stack[len..].reverse();
+        Some(node)
+    }
+}
+
+/// A lazy, pre-order iterator over every node of an Ast along with its [path](../node/struct.NodePath.html),
+/// created by [`Ast::nodes_with_paths`](../ast/struct.Ast.html#method.nodes_with_paths)
+pub struct NodesWithPaths<'ast, 'b> {
+    stack: Vec<(NodePath, AnyNode<'ast, 'b>)>,
+}
+
+impl<'ast, 'b> NodesWithPaths<'ast, 'b> {
+    pub(crate) fn new(root: AnyNode<'ast, 'b>) -> Self {
+        Self {
+            stack: vec![(NodePath::root(), root)],
+        }
+    }
+}
+
+impl<'ast, 'b> Iterator for NodesWithPaths<'ast, 'b> {
+    type Item = (NodePath, AnyNode<'ast, 'b>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.stack.pop()?;
+
+        let mut children = Vec::new();
+        node.visit_children(&mut children);
+        self.stack.extend(
+            children
+                .into_iter()
+                .enumerate()
+                .rev()
+                .map(|(index, child)| (path.child(index), child)),
+        );
+
+        Some((path, node))
+    }
+}
+
+/// A lazy iterator over every node of type `T` in a tree,
+/// created by [`Ast::nodes_of_type`](../ast/struct.Ast.html#method.nodes_of_type)
+pub struct NodesOfType<'ast, 'b, T> {
+    nodes: Nodes<'ast, 'b>,
+    marker: PhantomData<&'b T>,
+}
+
+impl<'ast, 'b, T> NodesOfType<'ast, 'b, T> {
+    pub(crate) fn new(nodes: Nodes<'ast, 'b>) -> Self {
+        Self {
+            nodes,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'ast, 'b, T: FromAnyNode<'ast>> Iterator for NodesOfType<'ast, 'b, T> {
+    type Item = &'b T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.nodes.find_map(T::from_any_node)
+    }
+}
+
+impl<'ast, T: Visit<'ast>> Visit<'ast> for &T {
+    fn visit<V: Visitor<'ast>>(&self, visitor: &mut V) {
+        (**self).visit(visitor);
+    }
+}
+
+impl<'ast, T: Visit<'ast>> Visit<'ast> for &mut T {
+    fn visit<V: Visitor<'ast>>(&self, visitor: &mut V) {
+        (**self).visit(visitor);
+    }
+}
+
 impl<'ast, T: Visit<'ast>> Visit<'ast> for Vec<T> {
     fn visit<V: Visitor<'ast>>(&self, visitor: &mut V) {
         for item in self {
@@ -158,11 +757,9 @@ impl<'ast, T: Clone + Visit<'ast>> Visit<'ast> for Cow<'ast, T> {
     }
 }
 
-impl<'ast, T: Clone + VisitMut<'ast>> VisitMut<'ast> for Cow<'ast, T> {
-    fn visit_mut<V: VisitorMut<'ast>>(&mut self, visitor: &mut V) {
-        self.to_mut().visit_mut(visitor);
-    }
-}
+// There's no VisitMut for Cow, since visiting it mutably would have to clone what it borrows with `to_mut`,
+// even for visitors that don't change anything, which for a whole tree of nodes is most of the Ast.
+// Nodes own their children instead, and anything borrowed from the code is in their tokens.
 
 impl<'ast, T: Visit<'ast>> Visit<'ast> for Box<T> {
     fn visit<V: Visitor<'ast>>(&self, visitor: &mut V) {
@@ -176,50 +773,440 @@ impl<'ast, T: VisitMut<'ast>> VisitMut<'ast> for Box<T> {
     }
 }
 
+impl<'ast, T: TryVisit<'ast>> TryVisit<'ast> for Vec<T> {
+    fn try_visit<V: TryVisitor<'ast>>(&self, visitor: &mut V) -> Result<(), V::Error> {
+        for item in self {
+            item.try_visit(visitor)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'ast, T: TryVisit<'ast>> TryVisit<'ast> for Option<T> {
+    fn try_visit<V: TryVisitor<'ast>>(&self, visitor: &mut V) -> Result<(), V::Error> {
+        match self {
+            Some(item) => item.try_visit(visitor),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'ast, A: TryVisit<'ast>, B: TryVisit<'ast>> TryVisit<'ast> for (A, B) {
+    fn try_visit<V: TryVisitor<'ast>>(&self, visitor: &mut V) -> Result<(), V::Error> {
+        self.0.try_visit(visitor)?;
+        self.1.try_visit(visitor)
+    }
+}
+
+impl<'ast, T: Clone + TryVisit<'ast>> TryVisit<'ast> for Cow<'ast, T> {
+    fn try_visit<V: TryVisitor<'ast>>(&self, visitor: &mut V) -> Result<(), V::Error> {
+        (**self).try_visit(visitor)
+    }
+}
+
+impl<'ast, T: TryVisit<'ast>> TryVisit<'ast> for Box<T> {
+    fn try_visit<V: TryVisitor<'ast>>(&self, visitor: &mut V) -> Result<(), V::Error> {
+        (**self).try_visit(visitor)
+    }
+}
+
+impl<'ast, T: VisitNodes<'ast>> VisitNodes<'ast> for Vec<T> {
+    fn visit_nodes<'b>(&'b self, nodes: &mut Vec<AnyNode<'ast, 'b>>) {
+        for item in self {
+            item.visit_nodes(nodes);
+        }
+    }
+}
+
+impl<'ast, T: VisitNodes<'ast>> VisitNodes<'ast> for Option<T> {
+    fn visit_nodes<'b>(&'b self, nodes: &mut Vec<AnyNode<'ast, 'b>>) {
+        if let Some(item) = self {
+            item.visit_nodes(nodes);
+        }
+    }
+}
+
+impl<'ast, A: VisitNodes<'ast>, B: VisitNodes<'ast>> VisitNodes<'ast> for (A, B) {
+    fn visit_nodes<'b>(&'b self, nodes: &mut Vec<AnyNode<'ast, 'b>>) {
+        self.0.visit_nodes(nodes);
+        self.1.visit_nodes(nodes);
+    }
+}
+
+impl<'ast, T: Clone + VisitNodes<'ast>> VisitNodes<'ast> for Cow<'ast, T> {
+    fn visit_nodes<'b>(&'b self, nodes: &mut Vec<AnyNode<'ast, 'b>>) {
+        (**self).visit_nodes(nodes);
+    }
+}
+
+impl<'ast, T: VisitNodes<'ast>> VisitNodes<'ast> for Box<T> {
+    fn visit_nodes<'b>(&'b self, nodes: &mut Vec<AnyNode<'ast, 'b>>) {
+        (**self).visit_nodes(nodes);
+    }
+}
+
+impl<'ast, T: VisitNodesMut<'ast>> VisitNodesMut<'ast> for Vec<T> {
+    fn visit_nodes_mut<'b>(&'b mut self, nodes: &mut Vec<AnyNodeMut<'ast, 'b>>) {
+        for item in self {
+            item.visit_nodes_mut(nodes);
+        }
+    }
+}
+
+impl<'ast, T: VisitNodesMut<'ast>> VisitNodesMut<'ast> for Option<T> {
+    fn visit_nodes_mut<'b>(&'b mut self, nodes: &mut Vec<AnyNodeMut<'ast, 'b>>) {
+        if let Some(item) = self {
+            item.visit_nodes_mut(nodes);
+        }
+    }
+}
+
+impl<'ast, A: VisitNodesMut<'ast>, B: VisitNodesMut<'ast>> VisitNodesMut<'ast> for (A, B) {
+    fn visit_nodes_mut<'b>(&'b mut self, nodes: &mut Vec<AnyNodeMut<'ast, 'b>>) {
+        self.0.visit_nodes_mut(nodes);
+        self.1.visit_nodes_mut(nodes);
+    }
+}
+
+// No VisitNodesMut for Cow either, for the same reason as VisitMut
+
+impl<'ast, T: VisitNodesMut<'ast>> VisitNodesMut<'ast> for Box<T> {
+    fn visit_nodes_mut<'b>(&'b mut self, nodes: &mut Vec<AnyNodeMut<'ast, 'b>>) {
+        (**self).visit_nodes_mut(nodes);
+    }
+}
+
 create_visitor!(ast: {
-    visit_anonymous_call => FunctionArgs,
-    visit_assignment => Assignment,
-    visit_bin_op => BinOpRhs,
-    visit_block => Block,
-    visit_call => Call,
-    visit_contained_span => ContainedSpan,
-    visit_do => Do,
-    visit_else_if => ElseIf,
-    visit_expression => Expression,
-    visit_field => Field,
-    visit_function_args => FunctionArgs,
-    visit_function_body => FunctionBody,
-    visit_function_call => FunctionCall,
-    visit_function_declaration => FunctionDeclaration,
-    visit_function_name => FunctionName,
-    visit_generic_for => GenericFor,
-    visit_if => If,
-    visit_index => Index,
-    visit_local_assignment => LocalAssignment,
-    visit_local_function => LocalFunction,
-    visit_last_stmt => LastStmt,
-    visit_method_call => MethodCall,
-    visit_numeric_for => NumericFor,
-    visit_parameter => Parameter,
-    visit_prefix => Prefix,
-    visit_return => Return,
-    visit_repeat => Repeat,
-    visit_stmt => Stmt,
-    visit_suffix => Suffix,
-    visit_table_constructor => TableConstructor,
-    visit_un_op => UnOp,
-    visit_value => Value,
-    visit_var => Var,
-    visit_var_expression => VarExpression,
-    visit_while => While,
+    assignment => Assignment,
+    bin_op => BinOpRhs,
+    block => Block,
+    call => Call,
+    contained_span => ContainedSpan,
+    do => Do,
+    else_if => ElseIf,
+    error_expression => ErrorExpression,
+    error_stmt => ErrorStmt,
+    expression => Expression,
+    field => Field,
+    function_args => FunctionArgs,
+    function_body => FunctionBody,
+    function_call => FunctionCall,
+    function_declaration => FunctionDeclaration,
+    function_name => FunctionName,
+    generic_for => GenericFor,
+    if => If,
+    index => Index,
+    local_assignment => LocalAssignment,
+    local_function => LocalFunction,
+    last_stmt => LastStmt,
+    method_call => MethodCall,
+    numeric_for => NumericFor,
+    parameter => Parameter,
+    prefix => Prefix,
+    return => Return,
+    repeat => Repeat,
+    stmt => Stmt,
+    suffix => Suffix,
+    table_constructor => TableConstructor,
+    un_op => UnOp,
+    value => Value,
+    var => Var,
+    var_expression => VarExpression,
+    while => While,
 }, token: {
-    visit_eof,
-    visit_identifier,
-    visit_multi_line_comment,
-    visit_number,
-    visit_single_line_comment,
-    visit_string_literal,
-    visit_symbol,
-    visit_token,
-    visit_whitespace,
+    eof,
+    identifier,
+    multi_line_comment,
+    number,
+    single_line_comment,
+    string_literal,
+    symbol,
+    token,
+    whitespace,
 });
+
+/// The comments that belong to a [statement](../ast/enum.Stmt.html).
+/// Refer to [`DocumentedVisitor`](trait.DocumentedVisitor.html) for how comments are associated.
+#[derive(Clone, Debug, Default)]
+pub struct StmtComments<'ast, 'b> {
+    leading: Vec<&'b Token<'ast>>,
+    trailing: Vec<&'b Token<'ast>>,
+}
+
+impl<'ast, 'b> StmtComments<'ast, 'b> {
+    /// Finds the comments that belong to the statement.
+    /// Statements that were not parsed, and so have no surrounding code, have no comments.
+    pub fn new(stmt: &'b Stmt<'ast>) -> Self {
+        Self::find(stmt).unwrap_or_default()
+    }
+
+    fn find(stmt: &'b Stmt<'ast>) -> Option<Self> {
+        let (start, end) = stmt.range()?;
+        let arena = Nodes::new(stmt).find_map(|node| match node {
+            AnyNode::TokenReference(token) => token.arena_offset().map(|(arena, _)| arena),
+            _ => None,
+        })?;
+
+        let token_at = |offset| arena.get_unknown_gen(offset).map(|(token, _)| token);
+        let has_new_line = |token: &Token| token.to_string().contains('\n');
+
+        // Binary search for the token starting at the position, tokens are sorted in the arena
+        let offset_at = |position: Position| {
+            let (mut low, mut high) = (0, arena.len());
+
+            while low < high {
+                let middle = (low + high) / 2;
+                let bytes = token_at(middle)?.start_position().bytes();
+
+                if bytes < position.bytes() {
+                    low = middle + 1;
+                } else if bytes > position.bytes() {
+                    high = middle;
+                } else {
+                    return Some(middle);
+                }
+            }
+
+            None
+        };
+
+        // The whitespace before a token at the start of a line, None if it isn't at the start of one
+        let indentation_of = |offset: usize| {
+            let mut indentation = String::new();
+
+            for before in (0..offset).rev() {
+                match token_at(before) {
+                    Some(token) if token.token_kind() == TokenKind::Whitespace => {
+                        if has_new_line(token) {
+                            break;
+                        }
+
+                        indentation.insert_str(0, &token.to_string());
+                    }
+
+                    Some(_) => return None,
+                    None => break,
+                }
+            }
+
+            Some(indentation)
+        };
+
+        let mut leading = Vec::new();
+        let mut new_lines = 0;
+
+        let first = offset_at(start)?;
+        let indentation = indentation_of(first);
+
+        for offset in (0..first).rev() {
+            let token = token_at(offset)?;
+
+            match token.token_kind() {
+                TokenKind::Whitespace => {
+                    new_lines += bytecount::count(token.to_string().as_bytes(), b'\n');
+
+                    if new_lines > 1 {
+                        break;
+                    }
+                }
+
+                TokenKind::SingleLineComment | TokenKind::MultiLineComment
+                    if indentation.is_some() && indentation_of(offset) == indentation =>
+                {
+                    leading.push(token);
+                    new_lines = 0;
+                }
+
+                _ => break,
+            }
+        }
+
+        leading.reverse();
+
+        let mut trailing = Vec::new();
+        let mut seen_semicolon = false;
+
+        for offset in offset_at(end)?.. {
+            let token = match token_at(offset) {
+                Some(token) => token,
+                None => break,
+            };
+
+            match &*token.token_type() {
+                TokenType::Whitespace { .. } => {
+                    if has_new_line(token) {
+                        break;
+                    }
+                }
+
+                TokenType::SingleLineComment { .. } | TokenType::MultiLineComment { .. } => {
+                    trailing.push(token);
+                }
+
+                TokenType::Symbol {
+                    symbol: Symbol::Semicolon,
+                } if !seen_semicolon => {
+                    seen_semicolon = true;
+                }
+
+                _ => break,
+            }
+        }
+
+        Some(Self { leading, trailing })
+    }
+
+    /// Comments on the lines right before the statement
+    pub fn leading(&self) -> &[&'b Token<'ast>] {
+        &self.leading
+    }
+
+    /// Comments after the statement, on the line it ends on
+    pub fn trailing(&self) -> &[&'b Token<'ast>] {
+        &self.trailing
+    }
+}
+
+/// A visitor that is given every [statement](../ast/enum.Stmt.html) along with the comments that belong to it,
+/// useful for documentation generators or comment directives.
+///
+/// A comment belongs to a statement if either:
+/// - It is on its own line at the same indentation as the statement, right before it or another comment belonging to it.
+/// - It is after the statement on the line the statement ends on, with only whitespace or a semicolon in between.
+///
+/// A blank line between a comment and the statement means the comment does not belong to it.
+///
+/// Comments that are at the end of a block, such as right before `end`, do not belong to any statement.
+///
+/// ```rust
+/// # use full_moon::ast;
+/// # use full_moon::visitors::*;
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// #[derive(Default)]
+/// struct Ignored(usize);
+///
+/// impl<'ast> DocumentedVisitor<'ast> for Ignored {
+///     fn visit_stmt_with_comments(&mut self, _: &ast::Stmt<'ast>, comments: &StmtComments<'ast, '_>) {
+///         if comments.trailing().iter().any(|comment| comment.to_string() == "-- ignore") {
+///             self.0 += 1;
+///         }
+///     }
+/// }
+///
+/// let mut visitor = Ignored::default();
+/// visitor.visit_ast(&full_moon::parse("x = 1 -- ignore\ny = 2")?);
+/// assert_eq!(visitor.0, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub trait DocumentedVisitor<'ast> {
+    /// Visit every statement in the [`Ast`](../ast/struct.Ast.html), including nested ones, in the order they appear
+    fn visit_ast(&mut self, ast: &Ast<'ast>)
+    where
+        Self: Sized,
+    {
+        for stmt in ast.nodes_of_type::<Stmt>() {
+            self.visit_stmt_with_comments(stmt, &StmtComments::new(stmt));
+        }
+    }
+
+    /// Called for every statement, with the comments belonging to it
+    fn visit_stmt_with_comments(&mut self, stmt: &Stmt<'ast>, comments: &StmtComments<'ast, '_>);
+}
+
+/// A visitor that collects statistics about the code it visits, such as how many of every node there are.
+///
+/// ```rust
+/// # use full_moon::{tokenizer::TokenKind, visitors::*};
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// let mut statistics = Statistics::default();
+/// statistics.visit_ast(&full_moon::parse("local function f() if x then return end end")?);
+///
+/// assert_eq!(statistics.node_count(NodeKind::If), 1);
+/// assert_eq!(statistics.token_count(TokenKind::Identifier), 2);
+/// assert_eq!(statistics.max_block_depth(), 3);
+/// assert_eq!(statistics.function_statements(), &[2]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Statistics {
+    nodes: BTreeMap<NodeKind, usize>,
+    tokens: BTreeMap<TokenKind, usize>,
+    block_depth: usize,
+    max_block_depth: usize,
+    function_statements: Vec<usize>,
+    current_functions: Vec<usize>,
+}
+
+impl Statistics {
+    /// How many nodes of the kind were visited. Tokens are counted separately, by [`token_count`](#method.token_count).
+    pub fn node_count(&self, kind: NodeKind) -> usize {
+        self.nodes.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// How many tokens of the kind there are, including comments and whitespace
+    pub fn token_count(&self, kind: TokenKind) -> usize {
+        self.tokens.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// How deeply blocks are nested, the top level block being a depth of 1
+    pub fn max_block_depth(&self) -> usize {
+        self.max_block_depth
+    }
+
+    /// How many statements are in the body of every function, in the order the functions appear.
+    /// Statements in nested blocks such as `if` statements are counted, statements in nested functions are not.
+    pub fn function_statements(&self) -> &[usize] {
+        &self.function_statements
+    }
+
+    fn count_statement(&mut self) {
+        if let Some(&function) = self.current_functions.last() {
+            self.function_statements[function] += 1;
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Statistics {
+    fn visit_ast(&mut self, ast: &Ast<'ast>) {
+        for token in ast.iter_tokens() {
+            *self.tokens.entry(token.token_kind()).or_insert(0) += 1;
+        }
+
+        ast.nodes().visit(self);
+    }
+
+    fn visit_node(&mut self, node: AnyNode<'ast, '_>) {
+        if node.kind() != NodeKind::TokenReference {
+            *self.nodes.entry(node.kind()).or_insert(0) += 1;
+        }
+    }
+
+    fn visit_block(&mut self, _: &Block<'ast>) {
+        self.block_depth += 1;
+        self.max_block_depth = self.max_block_depth.max(self.block_depth);
+    }
+
+    fn visit_block_end(&mut self, _: &Block<'ast>) {
+        self.block_depth -= 1;
+    }
+
+    fn visit_function_body(&mut self, _: &FunctionBody<'ast>) {
+        self.current_functions.push(self.function_statements.len());
+        self.function_statements.push(0);
+    }
+
+    fn visit_function_body_end(&mut self, _: &FunctionBody<'ast>) {
+        self.current_functions.pop();
+    }
+
+    fn visit_stmt(&mut self, _: &Stmt<'ast>) {
+        self.count_statement();
+    }
+
+    fn visit_last_stmt(&mut self, _: &LastStmt<'ast>) {
+        self.count_statement();
+    }
+}
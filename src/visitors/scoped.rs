@@ -0,0 +1,286 @@
+use super::{AnyNode, Scoped, Visitor};
+use crate::{
+    ast::{Ast, Parameter},
+    prelude::*,
+    tokenizer::TokenReference,
+};
+use core::mem;
+
+/// The kind of construct that opened a [`Scope`](struct.Scope.html)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScopeKind {
+    /// The top level of the file
+    File,
+    /// The body of a function, including its parameters
+    Function,
+    /// The body of a `do` block
+    Do,
+    /// The body of a `while` loop
+    While,
+    /// The body of a `repeat` loop, which includes the `until` condition
+    Repeat,
+    /// The body of an `if` statement, or of its `else` branch
+    If,
+    /// The body of an `elseif` branch
+    ElseIf,
+    /// The body of a numeric `for` loop, including the control variable
+    NumericFor,
+    /// The body of a generic `for` loop, including the names it declares
+    GenericFor,
+}
+
+/// A local variable declared in a [`Scope`](struct.Scope.html)
+#[derive(Clone, Debug)]
+pub struct Local<'ast> {
+    name: String,
+    token: Option<TokenReference<'ast>>,
+}
+
+impl<'ast> Local<'ast> {
+    /// The name of the local
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The token the local was declared with.
+    /// Returns `None` for the implicit `self` parameter of methods, such as `function x:y() end`.
+    pub fn token(&self) -> Option<&TokenReference<'ast>> {
+        self.token.as_ref()
+    }
+}
+
+/// A single scope, with the locals declared in it so far
+#[derive(Clone, Debug)]
+pub struct Scope<'ast> {
+    kind: ScopeKind,
+    locals: Vec<Local<'ast>>,
+}
+
+impl<'ast> Scope<'ast> {
+    /// The kind of construct that opened the scope
+    pub fn kind(&self) -> ScopeKind {
+        self.kind
+    }
+
+    /// The locals declared in the scope so far, in the order they were declared
+    pub fn locals(&self) -> &[Local<'ast>] {
+        &self.locals
+    }
+
+    /// Looks up a local declared in this scope so far, not including enclosing scopes.
+    /// If the name was declared more than once, the latest declaration is returned.
+    /// Use [`Scopes::lookup`](struct.Scopes.html#method.lookup) to also look through enclosing scopes.
+    pub fn lookup(&self, name: &str) -> Option<&Local<'ast>> {
+        self.locals.iter().rev().find(|local| local.name == name)
+    }
+}
+
+/// The stack of scopes tracked by a [`ScopedVisitor`](trait.ScopedVisitor.html)
+#[derive(Clone, Debug, Default)]
+pub struct Scopes<'ast> {
+    scopes: Vec<Scope<'ast>>,
+    // The constructs currently being visited, with the locals they declare in their body
+    constructs: Vec<(ScopeKind, Vec<Local<'ast>>)>,
+    method_self: bool,
+}
+
+impl<'ast> Scopes<'ast> {
+    /// The innermost scope, or `None` if nothing is being visited
+    pub fn current_scope(&self) -> Option<&Scope<'ast>> {
+        self.scopes.last()
+    }
+
+    /// Every scope that is open, from the innermost to the outermost
+    pub fn iter(&self) -> impl Iterator<Item = &Scope<'ast>> {
+        self.scopes.iter().rev()
+    }
+
+    /// Looks up a local visible from the current scope, starting with the innermost scope
+    pub fn lookup(&self, name: &str) -> Option<&Local<'ast>> {
+        self.iter().find_map(|scope| scope.lookup(name))
+    }
+
+    fn declare(&mut self, token: &TokenReference<'ast>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.locals.push(local(token));
+        }
+    }
+
+    fn enter_construct(&mut self, kind: ScopeKind, locals: Vec<Local<'ast>>) {
+        self.constructs.push((kind, locals));
+    }
+
+    fn current_construct(&self) -> Option<ScopeKind> {
+        self.constructs.last().map(|(kind, _)| *kind)
+    }
+}
+
+/// A [`Visitor`](trait.Visitor.html) that is told when scopes are entered and exited,
+/// and can look up the locals declared in them through [`Scopes`](struct.Scopes.html).
+///
+/// Locals are declared the way Lua declares them:
+/// - `local` names are declared after the whole statement, so `local x = x` refers to the `x` from before
+/// - `local function` names are declared before the body, so the function can call itself
+/// - Function parameters, and `self` for methods, are declared in the function's scope
+/// - `for` loop variables are declared in the loop's scope, so the loop's expressions can't see them
+/// - The scope of a `repeat` loop stays open until after the `until` condition, so the condition can see its locals
+///
+/// Scopes are entered before `visit_block`, and exited after `visit_block_end`, or after `visit_repeat_end` for `repeat` loops.
+/// Names from `local` statements are declared after `visit_local_assignment_end`.
+///
+/// ```rust
+/// # use full_moon::ast;
+/// # use full_moon::visitors::*;
+/// # fn main() -> Result<(), Box<std::error::Error>> {
+/// #[derive(Default)]
+/// struct GlobalFinder<'ast> {
+///     scopes: Scopes<'ast>,
+///     globals: Vec<String>,
+/// }
+///
+/// impl<'ast> Visitor<'ast> for GlobalFinder<'ast> {
+///     fn visit_var(&mut self, var: &ast::Var<'ast>) {
+///         if let ast::Var::Name(name) = var {
+///             if self.scopes.lookup(&name.to_string()).is_none() {
+///                 self.globals.push(name.to_string());
+///             }
+///         }
+///     }
+/// }
+///
+/// impl<'ast> ScopedVisitor<'ast> for GlobalFinder<'ast> {
+///     fn scopes_mut(&mut self) -> &mut Scopes<'ast> {
+///         &mut self.scopes
+///     }
+/// }
+///
+/// let ast = full_moon::parse("local x = 1 y = x + z")?;
+/// let mut visitor = GlobalFinder::default();
+/// visitor.visit_ast_scoped(&ast);
+/// assert_eq!(visitor.globals, vec!["y", "z"]);
+/// # Ok(())
+/// # }
+/// ```
+pub trait ScopedVisitor<'ast>: Visitor<'ast> {
+    /// The scopes tracked while visiting, usually a field of the visitor
+    fn scopes_mut(&mut self) -> &mut Scopes<'ast>;
+
+    /// Called when a scope is entered, after it is pushed onto the [`Scopes`](struct.Scopes.html)
+    fn enter_scope(&mut self, _kind: ScopeKind) {}
+
+    /// Called when a scope is exited, before it is popped off the [`Scopes`](struct.Scopes.html)
+    fn exit_scope(&mut self) {}
+
+    /// Visit the nodes of an [`Ast`](../ast/struct.Ast.html), tracking scopes along the way
+    fn visit_ast_scoped(&mut self, ast: &Ast<'ast>)
+    where
+        Self: Sized,
+    {
+        Scoped(self).visit_ast(ast);
+    }
+}
+
+fn local<'ast>(token: &TokenReference<'ast>) -> Local<'ast> {
+    Local {
+        name: token.to_string(),
+        token: Some(token.clone()),
+    }
+}
+
+pub(crate) fn enter_node<'ast, V: ScopedVisitor<'ast>>(visitor: &mut V, node: AnyNode<'ast, '_>) {
+    let scopes = visitor.scopes_mut();
+
+    match node {
+        AnyNode::Block(_) => {
+            let kind = scopes.current_construct().unwrap_or(ScopeKind::File);
+            let locals = match scopes.constructs.last_mut() {
+                Some((_, locals)) => mem::take(locals),
+                None => Vec::new(),
+            };
+
+            scopes.scopes.push(Scope { kind, locals });
+            visitor.enter_scope(kind);
+        }
+
+        AnyNode::FunctionDeclaration(declaration) => {
+            scopes.method_self = declaration.name().method_name().is_some();
+        }
+
+        AnyNode::FunctionBody(body) => {
+            let mut locals = Vec::new();
+            if mem::replace(&mut scopes.method_self, false) {
+                locals.push(Local {
+                    name: "self".to_owned(),
+                    token: None,
+                });
+            }
+
+            for parameter in body.iter_parameters() {
+                if let Parameter::Name(name) = parameter {
+                    locals.push(local(name));
+                }
+            }
+
+            scopes.enter_construct(ScopeKind::Function, locals);
+        }
+
+        AnyNode::LocalFunction(local_function) => scopes.declare(local_function.name()),
+        AnyNode::Do(_) => scopes.enter_construct(ScopeKind::Do, Vec::new()),
+        AnyNode::While(_) => scopes.enter_construct(ScopeKind::While, Vec::new()),
+        AnyNode::Repeat(_) => scopes.enter_construct(ScopeKind::Repeat, Vec::new()),
+        AnyNode::If(_) => scopes.enter_construct(ScopeKind::If, Vec::new()),
+        AnyNode::ElseIf(_) => scopes.enter_construct(ScopeKind::ElseIf, Vec::new()),
+
+        AnyNode::NumericFor(numeric_for) => scopes.enter_construct(
+            ScopeKind::NumericFor,
+            vec![local(numeric_for.index_variable())],
+        ),
+
+        AnyNode::GenericFor(generic_for) => scopes.enter_construct(
+            ScopeKind::GenericFor,
+            generic_for.names().iter().map(local).collect(),
+        ),
+
+        _ => {}
+    }
+}
+
+pub(crate) fn exit_node<'ast, V: ScopedVisitor<'ast>>(visitor: &mut V, node: AnyNode<'ast, '_>) {
+    match node {
+        // The body of a repeat loop is exited after the until condition instead
+        AnyNode::Block(_)
+            if visitor.scopes_mut().current_construct() != Some(ScopeKind::Repeat) =>
+        {
+            exit_scope(visitor);
+        }
+
+        AnyNode::Repeat(_) => {
+            exit_scope(visitor);
+            visitor.scopes_mut().constructs.pop();
+        }
+
+        AnyNode::FunctionBody(_)
+        | AnyNode::Do(_)
+        | AnyNode::While(_)
+        | AnyNode::If(_)
+        | AnyNode::ElseIf(_)
+        | AnyNode::NumericFor(_)
+        | AnyNode::GenericFor(_) => {
+            visitor.scopes_mut().constructs.pop();
+        }
+
+        AnyNode::LocalAssignment(local_assignment) => {
+            let scopes = visitor.scopes_mut();
+            for name in local_assignment.name_list().iter() {
+                scopes.declare(name);
+            }
+        }
+
+        _ => {}
+    }
+}
+
+fn exit_scope<'ast, V: ScopedVisitor<'ast>>(visitor: &mut V) {
+    visitor.exit_scope();
+    visitor.scopes_mut().scopes.pop();
+}
@@ -0,0 +1,122 @@
+use full_moon::{ast::Stmt, node::Node};
+
+// Each statement as its first line of code, with how deep it is
+fn all_stmts(code: &str) -> Vec<(String, usize)> {
+    let ast = full_moon::parse(code).unwrap();
+    ast.all_stmts()
+        .map(|(stmt, depth)| {
+            let code = ast.source_of(stmt).unwrap();
+            (code.lines().next().unwrap().to_owned(), depth)
+        })
+        .collect()
+}
+
+#[test]
+fn test_blocks() {
+    let code = r#"
+        local a = 1
+        if a then
+            do
+                b()
+            end
+        elseif c then
+            for i = 1, 2 do
+                d()
+            end
+        else
+            while e do e() end
+        end
+        repeat
+            f()
+        until g
+        for k, v in pairs(t) do
+            function h() i() end
+        end
+        local function j()
+            return
+        end
+        k()
+    "#;
+
+    assert_eq!(
+        all_stmts(code),
+        [
+            ("local a = 1", 0),
+            ("if a then", 0),
+            ("do", 1),
+            ("b()", 2),
+            ("for i = 1, 2 do", 1),
+            ("d()", 2),
+            ("while e do e() end", 1),
+            ("e()", 2),
+            ("repeat", 0),
+            ("f()", 1),
+            ("for k, v in pairs(t) do", 0),
+            ("function h() i() end", 1),
+            ("i()", 2),
+            ("local function j()", 0),
+            ("k()", 0),
+        ]
+        .iter()
+        .map(|&(code, depth)| (code.to_owned(), depth))
+        .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_functions_in_expressions() {
+    let code = r#"
+        local x = call(function() a() end, { key = function() b() end }):method(function() c() end)
+        t[function() d() end] = (function() e() end)()
+        if not function() f() end then end
+        return function()
+            g(function() h() end)
+        end, function() i() end
+    "#;
+
+    let stmts = all_stmts(code);
+    let names: Vec<_> = stmts
+        .iter()
+        .filter(|(code, _)| code.len() == 3)
+        .map(|(code, depth)| (code.as_str(), *depth))
+        .collect();
+
+    assert_eq!(
+        names,
+        [
+            ("a()", 1),
+            ("b()", 1),
+            ("c()", 1),
+            ("d()", 1),
+            ("e()", 1),
+            ("f()", 1),
+            ("h()", 2),
+            ("i()", 1),
+        ]
+    );
+    assert_eq!(stmts.len(), 12);
+    assert_eq!(stmts[11], ("i()".to_owned(), 1));
+}
+
+#[test]
+fn test_deep_nesting() {
+    let mut code = String::new();
+    for _ in 0..20 {
+        code.push_str("do ");
+    }
+    code.push_str("x()");
+    for _ in 0..20 {
+        code.push_str(" end");
+    }
+
+    let ast = full_moon::parse(&code).unwrap();
+    let (deepest, depth) = ast.all_stmts().last().unwrap();
+    assert!(matches!(deepest, Stmt::FunctionCall(_)));
+    assert_eq!(depth, 20);
+
+    let mut stmts = ast.all_stmts();
+    let (first, depth) = stmts.next().unwrap();
+    assert_eq!(depth, 0);
+    assert_eq!(first.start_position(), ast.nodes().start_position());
+    assert_eq!(stmts.nth(9).unwrap().1, 10);
+}
@@ -0,0 +1,94 @@
+use full_moon::{
+    ast::{Ast, Stmt},
+    parse, print_minified, print_pretty,
+    visitors::VisitorMut,
+    PrettyConfig,
+};
+
+// Replaces every `do end` with the statement in `replacement`
+fn replace_do<'a>(code: &'a str, replacement: &'a str) -> Ast<'a> {
+    struct ReplaceDo<'ast>(Stmt<'ast>);
+
+    impl<'ast> VisitorMut<'ast> for ReplaceDo<'ast> {
+        fn visit_stmt(&mut self, stmt: &mut Stmt<'ast>) {
+            if let Stmt::Do(_) = stmt {
+                *stmt = self.0.clone();
+            }
+        }
+    }
+
+    let mut ast = parse(code).unwrap();
+    let stmt = parse(replacement)
+        .unwrap()
+        .nodes()
+        .iter_stmts()
+        .next()
+        .unwrap()
+        .clone();
+    ReplaceDo(stmt).visit_ast(&mut ast);
+    ast
+}
+
+fn bytes(ast: &Ast) -> Vec<usize> {
+    ast.ambiguous_statements()
+        .iter()
+        .map(|position| position.bytes())
+        .collect()
+}
+
+#[test]
+fn test_parsed_code_is_never_ambiguous() {
+    assert!(parse("f()\n(g)()")
+        .unwrap()
+        .ambiguous_statements()
+        .is_empty());
+    // Numbers can't be called, so there is nothing to continue
+    assert!(parse("f(); (g)() x = 1 (t).y = 2")
+        .unwrap()
+        .ambiguous_statements()
+        .is_empty());
+}
+
+#[test]
+fn test_ambiguous_statements() {
+    assert_eq!(bytes(&replace_do("do end\n(g)()", "f()")), vec![7]);
+    assert_eq!(bytes(&replace_do("do end\n(t).x = 1", "x = y")), vec![7]);
+    assert_eq!(
+        bytes(&replace_do("do end\n(g)()", "repeat until x")),
+        vec![7]
+    );
+    assert_eq!(
+        bytes(&replace_do("if x then do end\n(g)() end", "local x = -y")),
+        vec![17]
+    );
+
+    // Statements that end with keywords, or are followed by a semicolon, can be followed by anything
+    assert!(bytes(&replace_do("do end\n(g)()", "while x do end")).is_empty());
+    assert!(bytes(&replace_do("do end\n(g)()", "x = 1")).is_empty());
+    assert!(bytes(&replace_do("do end\n(g)()", "local x")).is_empty());
+    assert!(bytes(&replace_do("do end;\n(g)()", "f()")).is_empty());
+    assert!(bytes(&replace_do("do end\ng()", "f()")).is_empty());
+}
+
+#[test]
+fn test_print_ambiguous_statements() {
+    let ast = replace_do("do end\n(g)()\nx = 1", "f()");
+    assert_eq!(
+        print_pretty(&ast, &PrettyConfig::default()),
+        "f();\n(g)()\nx = 1\n"
+    );
+    assert_eq!(print_minified(&ast), "f();(g)()x=1");
+
+    let config = PrettyConfig {
+        separate_ambiguous_statements: false,
+        ..PrettyConfig::default()
+    };
+    assert_eq!(print_pretty(&ast, &config), "f()\n(g)()\nx = 1\n");
+
+    // Once printed with a semicolon, it's the same code
+    let reparsed = parse("f();\n(g)()\nx = 1").unwrap();
+    assert_eq!(
+        print_pretty(&reparsed, &PrettyConfig::default()),
+        "f();\n(g)()\nx = 1\n"
+    );
+}
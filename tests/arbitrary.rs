@@ -0,0 +1,72 @@
+#![cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
+use full_moon::{
+    ast::{
+        arbitrary::{arbitrary_with, ArbitraryConfig},
+        Ast,
+    },
+    node::Node,
+    parse, print,
+};
+use pretty_assertions::assert_eq;
+
+// The same inputs on every run, from a xorshift generator
+fn inputs(count: usize, length: usize) -> Vec<Vec<u8>> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    (0..count)
+        .map(|_| {
+            (0..length)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Generates an Ast from the input, which gives the same Ast when it's printed and parsed again
+fn assert_round_trip(input: &[u8], config: &ArbitraryConfig) {
+    let ast = arbitrary_with(&mut Unstructured::new(input), config).unwrap();
+    let code = print(&ast);
+    let reparsed = parse(&code).unwrap_or_else(|error| panic!("{}\n{}", error, code));
+    assert!(reparsed.nodes().similar(ast.nodes()), "{}", code);
+    assert_eq!(print(&reparsed), code);
+}
+
+#[test]
+fn test_round_trip() {
+    for input in inputs(500, 1024) {
+        assert_round_trip(&input, &ArbitraryConfig::default());
+    }
+}
+
+#[test]
+fn test_config() {
+    let config = ArbitraryConfig {
+        max_depth: 8,
+        max_stmts: 8,
+    };
+    for input in inputs(100, 4096) {
+        assert_round_trip(&input, &config);
+    }
+
+    // Blocks at the maximum depth are empty, so there's nothing in the chunk
+    let config = ArbitraryConfig {
+        max_depth: 0,
+        ..ArbitraryConfig::default()
+    };
+    let ast = arbitrary_with(&mut Unstructured::new(&inputs(1, 64)[0]), &config).unwrap();
+    assert_eq!(print(&ast), "");
+}
+
+#[test]
+fn test_input_runs_out() {
+    // Input that runs out always gives the simplest choices, so the code still ends
+    let ast = Ast::arbitrary(&mut Unstructured::new(&[])).unwrap();
+    assert_eq!(print(&ast), "");
+
+    assert_round_trip(&[255; 16], &ArbitraryConfig::default());
+}
@@ -0,0 +1,165 @@
+#![cfg(feature = "binary")]
+use full_moon::{
+    ast::Ast, parse, parse_fallible, print, tokenizer::TokenReference, visitors::Visitor,
+    BinaryError, ParseConfig, BINARY_FORMAT_VERSION,
+};
+use std::fs;
+
+// The magic bytes, the format version, and the length and text of the version of full_moon
+const HEADER_LENGTH: usize = 8 + 4 + 1 + env!("CARGO_PKG_VERSION").len();
+
+#[derive(Default)]
+struct TokenVisitor(Vec<String>);
+
+impl<'ast> Visitor<'ast> for TokenVisitor {
+    fn visit_token(&mut self, token: &TokenReference<'ast>) {
+        self.0.push(token.to_string());
+    }
+}
+
+fn visited_tokens(ast: &Ast) -> Vec<String> {
+    let mut visitor = TokenVisitor::default();
+    visitor.visit_ast(ast);
+    visitor.0
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_binary_pass_cases() {
+    for entry in fs::read_dir("./tests/cases/pass").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).unwrap();
+
+        let bytes = ast.to_bytes();
+        let decoded = Ast::from_bytes(&bytes).expect("couldn't decode");
+        assert_eq!(print(&decoded), source, "{:?}", path);
+        assert_eq!(decoded.nodes(), ast.nodes(), "{:?}", path);
+        assert_eq!(visited_tokens(&decoded), visited_tokens(&ast), "{:?}", path);
+
+        let json = serde_json::to_string(&ast).unwrap();
+        assert!(bytes.len() < json.len(), "{:?}", path);
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_binary_fail_cases() {
+    for entry in fs::read_dir("./tests/cases/fail/parser").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse_fallible(&source, &ParseConfig::default()).ast;
+
+        let bytes = ast.to_bytes();
+        let decoded = Ast::from_bytes(&bytes).expect("couldn't decode");
+        assert_eq!(print(&decoded), source, "{:?}", path);
+        assert_eq!(decoded.nodes(), ast.nodes(), "{:?}", path);
+    }
+}
+
+#[test]
+fn test_binary_without_trivia() {
+    let config = ParseConfig {
+        preserve_trivia: false,
+        ..ParseConfig::default()
+    };
+
+    let ast = full_moon::parse_with("local x = 1 -- one\nprint(x)", &config).unwrap();
+    let bytes = ast.to_bytes();
+    let decoded = Ast::from_bytes(&bytes).expect("couldn't decode");
+    assert!(!decoded.has_trivia());
+    assert_eq!(decoded.comments().count(), 1);
+    assert_eq!(print(&decoded), "local x = 1 -- one\nprint(x)");
+
+    let json = serde_json::to_string(&ast).unwrap();
+    let deserialized: Ast = serde_json::from_str(&json).unwrap();
+    assert_eq!(print(&deserialized), "local x = 1 -- one\nprint(x)");
+}
+
+#[test]
+fn test_binary_header() {
+    let bytes = parse("local x = 1").unwrap().to_bytes();
+
+    assert_eq!(Ast::from_bytes(b"").unwrap_err(), BinaryError::NotAnAst);
+    assert_eq!(
+        Ast::from_bytes(b"local x = 1").unwrap_err(),
+        BinaryError::NotAnAst
+    );
+    assert_eq!(
+        Ast::from_bytes(&bytes[..HEADER_LENGTH - 1]).unwrap_err(),
+        BinaryError::NotAnAst
+    );
+
+    let mut stale_format = bytes.clone();
+    stale_format[8..12].copy_from_slice(&(BINARY_FORMAT_VERSION + 1).to_le_bytes());
+    assert_eq!(
+        Ast::from_bytes(&stale_format).unwrap_err(),
+        BinaryError::Version {
+            format_version: BINARY_FORMAT_VERSION + 1,
+            full_moon_version: env!("CARGO_PKG_VERSION").to_owned(),
+        }
+    );
+
+    let mut stale_version = bytes[..12].to_vec();
+    stale_version.push(5);
+    stale_version.extend_from_slice(b"0.1.0");
+    stale_version.extend_from_slice(&bytes[HEADER_LENGTH..]);
+    assert_eq!(
+        Ast::from_bytes(&stale_version).unwrap_err(),
+        BinaryError::Version {
+            format_version: BINARY_FORMAT_VERSION,
+            full_moon_version: "0.1.0".to_owned(),
+        }
+    );
+}
+
+#[test]
+fn test_binary_corrupted() {
+    let bytes = parse("local x = { 1, 'two', y = 3.5 } -- comment\nprint(#x)")
+        .unwrap()
+        .to_bytes();
+
+    for length in HEADER_LENGTH..bytes.len() {
+        match Ast::from_bytes(&bytes[..length]) {
+            Err(BinaryError::Invalid { .. }) => {}
+            other => panic!("truncated to {} bytes: {:?}", length, other),
+        }
+    }
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(
+        Ast::from_bytes(&trailing).unwrap_err(),
+        BinaryError::Invalid {
+            offset: bytes.len(),
+            message: "unexpected bytes after the end of the ast".to_owned(),
+        }
+    );
+
+    // Changing any byte can give an Ast or an error, but never panics
+    for index in HEADER_LENGTH..bytes.len() {
+        for &byte in &[0, 1, 7, 9, 10, 11, 0x7f, 0xff, bytes[index].wrapping_add(1)] {
+            let mut corrupted = bytes.clone();
+            corrupted[index] = byte;
+            let _ = Ast::from_bytes(&corrupted);
+        }
+    }
+}
+
+#[test]
+fn test_binary_nested_too_deeply() {
+    let nested = |depth| format!("x = {}1{}", "{".repeat(depth), "}".repeat(depth));
+
+    let code = nested(40);
+    let bytes = parse(&code).unwrap().to_bytes();
+    assert_eq!(print(&Ast::from_bytes(&bytes).unwrap()), code);
+
+    // Code nested deeper than can be read safely gives an error instead of overflowing the stack
+    let bytes = parse(&nested(150)).unwrap().to_bytes();
+    match Ast::from_bytes(&bytes) {
+        Err(BinaryError::Invalid { message, .. }) => {
+            assert_eq!(message, "lists and maps are nested too deeply")
+        }
+        other => panic!("expected an error, got {:?}", other),
+    }
+}
@@ -0,0 +1,194 @@
+use full_moon::{
+    analysis::{call_graph, CallGraph, CallGraphNode, Callee},
+    parse,
+};
+use pretty_assertions::assert_eq;
+
+// The name of the function a node is, or `file`
+fn node_name(graph: &CallGraph, node: CallGraphNode) -> String {
+    match node {
+        CallGraphNode::File => "file".to_owned(),
+        CallGraphNode::Function(id) => graph.function(id).unwrap().name().to_owned(),
+    }
+}
+
+// Every call as the caller, the callee, and the names of the functions it calls
+fn calls(code: &str) -> Vec<String> {
+    let ast = parse(code).unwrap();
+    let graph = call_graph(&ast);
+
+    graph
+        .calls()
+        .iter()
+        .map(|call| {
+            let callee = match call.callee() {
+                Callee::Local { name, .. } => format!("local {}", name),
+                Callee::Global { name } => format!("global {}", name),
+                Callee::Path { path } => format!("path {}", path),
+                Callee::Method { receiver, method } => format!("method {} {}", receiver, method),
+                Callee::Unknown => "unknown".to_owned(),
+            };
+
+            let targets: Vec<_> = call
+                .targets()
+                .iter()
+                .map(|target| node_name(&graph, CallGraphNode::Function(*target)))
+                .collect();
+
+            format!(
+                "{} -> {} [{}]",
+                node_name(&graph, call.caller()),
+                callee,
+                targets.join(", ")
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_functions() {
+    let code = "local function a() end
+local b = function() end
+function c() end
+local M = {}
+function M.d() end
+function M:e() end
+M.f = function() end
+g = function() end
+t[1] = function() end
+print(function() end)
+";
+    let ast = parse(code).unwrap();
+    let graph = call_graph(&ast);
+
+    let names: Vec<_> = graph
+        .functions()
+        .iter()
+        .map(|function| function.name())
+        .collect();
+    assert_eq!(names, vec!["a", "b", "c", "M.d", "M:e", "M.f", "g"]);
+
+    let range = graph.functions()[0].range();
+    assert_eq!(&code[range.0.bytes()..range.1.bytes()], "function a() end");
+}
+
+#[test]
+fn test_calls() {
+    let code = "local function helper() end
+local M = {}
+function M.run(x)
+    helper()
+    M.other()
+    print(x)
+    pcall(function() helper() end)
+end
+function M.other() end
+M.run(1)
+x.y.z()
+handlers[1]()
+local chosen = (f or g)()
+make()()
+";
+
+    assert_eq!(
+        calls(code),
+        vec![
+            "M.run -> local helper [helper]",
+            "M.run -> path M.other [M.other]",
+            "M.run -> global print []",
+            "M.run -> global pcall []",
+            "M.run -> local helper [helper]",
+            "file -> path M.run [M.run]",
+            "file -> path x.y.z []",
+            "file -> unknown []",
+            "file -> unknown []",
+            "file -> global make []",
+            "file -> unknown []",
+        ]
+    );
+}
+
+#[test]
+fn test_methods() {
+    let code = "local Class = {}
+function Class:new() return self:init() end
+function Class:init() self.items = {} end
+function Class.static() end
+local object = Class:new()
+object:init()
+Class.static()
+local repeated = (\"x\"):rep(2)
+";
+
+    assert_eq!(
+        calls(code),
+        vec![
+            "Class:new -> method self init [Class:init]",
+            "file -> method Class new [Class:new]",
+            "file -> method object init []",
+            "file -> path Class.static [Class.static]",
+            "file -> method (\"x\") rep []",
+        ]
+    );
+}
+
+#[test]
+fn test_shadowing() {
+    // Calls are matched by what their names refer to, not only by the names
+    let code = "local function f() end
+do
+    local function f() end
+    f()
+end
+local M = {}
+function M.g() end
+do
+    local M = {}
+    M.g()
+end
+";
+
+    let ast = parse(code).unwrap();
+    let graph = call_graph(&ast);
+    let targets: Vec<_> = graph
+        .calls()
+        .iter()
+        .map(|call| {
+            call.targets()
+                .iter()
+                .map(|target| graph.function(*target).unwrap().range().0.line())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    assert_eq!(targets, vec![vec![3], vec![]]);
+}
+
+#[test]
+fn test_reachable() {
+    let code = "local function a() b() end
+function b() end
+function c() d() end
+function d() c() end
+local function e() end
+a()
+";
+    let ast = parse(code).unwrap();
+    let graph = call_graph(&ast);
+
+    let names = |roots: Vec<CallGraphNode>| {
+        let reachable = graph.reachable(roots);
+        graph
+            .functions()
+            .iter()
+            .filter(|function| reachable.contains(&function.id()))
+            .map(|function| function.name().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(names(vec![CallGraphNode::File]), vec!["a", "b"]);
+
+    let c = graph.functions()[2].id();
+    assert_eq!(names(vec![CallGraphNode::Function(c)]), vec!["c", "d"]);
+    assert_eq!(names(Vec::new()), Vec::<String>::new());
+}
@@ -0,0 +1,102 @@
+use full_moon::{ast::AstError, parse_with, CancellationToken, Error, ParseConfig};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+fn parse_cancellable<'a>(
+    code: &'a str,
+    cancellation: &CancellationToken,
+) -> Result<(), AstError<'a>> {
+    match parse_with(
+        code,
+        &ParseConfig {
+            cancellation: Some(cancellation.clone()),
+            ..ParseConfig::default()
+        },
+    ) {
+        Ok(_) => Ok(()),
+        Err(Error::AstError(error)) => Err(error),
+        Err(error) => panic!("{:?} couldn't be tokenized: {}", code, error),
+    }
+}
+
+#[test]
+fn test_not_cancelled() {
+    let cancellation = CancellationToken::new();
+    assert!(!cancellation.is_cancelled());
+    assert_eq!(
+        parse_cancellable("local x = { 1, f(2) }", &cancellation),
+        Ok(())
+    );
+    assert!(parse_cancellable("local x = ", &cancellation).is_err());
+}
+
+#[test]
+fn test_cancelled() {
+    let cancellation = CancellationToken::new();
+    let clone = cancellation.clone();
+    clone.cancel();
+    assert!(cancellation.is_cancelled());
+    assert_eq!(cancellation, clone);
+    assert_ne!(cancellation, CancellationToken::new());
+
+    for code in &[
+        "local x = 1",
+        "return",
+        "x = (1 + 2)",
+        "call()",
+        // Cancelled before the error is found
+        "local x = ",
+    ] {
+        assert_eq!(
+            parse_cancellable(code, &cancellation),
+            Err(AstError::Cancelled),
+            "{:?} wasn't cancelled",
+            code
+        );
+    }
+
+    let error = Error::AstError(parse_cancellable("x = 1", &cancellation).unwrap_err());
+    assert_eq!(error.to_string(), "parsing was cancelled");
+    assert_eq!(error.position(), None);
+}
+
+#[test]
+fn test_cancelled_from_flag() {
+    let flag = Arc::new(AtomicBool::new(false));
+    let cancellation = CancellationToken::from(Arc::clone(&flag));
+    assert_eq!(parse_cancellable("x = 1", &cancellation), Ok(()));
+
+    flag.store(true, Ordering::Relaxed);
+    assert_eq!(
+        parse_cancellable("x = 1", &cancellation),
+        Err(AstError::Cancelled)
+    );
+}
+
+#[test]
+fn test_cancelled_while_parsing() {
+    let code = "local x = { 1, { 2 }, f(3) + 4 * -5 }\n".repeat(20_000);
+    let cancellation = CancellationToken::new();
+
+    let canceller = {
+        let cancellation = cancellation.clone();
+        thread::spawn(move || cancellation.cancel())
+    };
+
+    // The parse might finish before the token is cancelled, but it mustn't fail any other way
+    match parse_cancellable(&code, &cancellation) {
+        Ok(()) | Err(AstError::Cancelled) => {}
+        Err(error) => panic!("parsing failed with {}", error),
+    }
+
+    canceller.join().unwrap();
+    assert_eq!(
+        parse_cancellable(&code, &cancellation),
+        Err(AstError::Cancelled)
+    );
+}
@@ -0,0 +1,119 @@
+use full_moon::{
+    analysis::{
+        captures,
+        scopes::{FunctionId, ScopeAnalysis},
+    },
+    ast::FunctionBody,
+    parse,
+    visitors::Visitor,
+};
+use pretty_assertions::assert_eq;
+
+#[derive(Default)]
+struct Bodies<'ast> {
+    bodies: Vec<FunctionBody<'ast>>,
+}
+
+impl<'ast> Visitor<'ast> for Bodies<'ast> {
+    fn visit_function_body(&mut self, body: &FunctionBody<'ast>) {
+        self.bodies.push(body.clone());
+    }
+}
+
+// The captures of every function in the order they start in, as the name, whether it's read only,
+// and the bytes of the names in the function that refer to it
+fn captured(code: &str) -> Vec<Vec<(String, bool, Vec<usize>)>> {
+    let ast = parse(code).unwrap();
+    let analysis = ScopeAnalysis::new(&ast);
+    let mut captures = captures(&ast);
+
+    let mut visitor = Bodies::default();
+    visitor.visit_ast(&ast);
+    let ids: Vec<FunctionId> = visitor
+        .bodies
+        .iter()
+        .map(|body| analysis.function_id(body).unwrap())
+        .collect();
+    assert_eq!(ids.len(), captures.len());
+
+    ids.iter()
+        .map(|id| {
+            captures
+                .remove(id)
+                .unwrap()
+                .iter()
+                .map(|capture| {
+                    assert!(analysis.scope(capture.scope()).function() != Some(*id));
+                    (
+                        capture.name().to_owned(),
+                        capture.is_read_only(),
+                        capture
+                            .positions()
+                            .iter()
+                            .map(|position| position.bytes())
+                            .collect(),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_nested_captures() {
+    let code = "local a, b = 1, 2
+local function outer(x)
+  local c = a
+  return function()
+    return function() b = b + x end
+  end
+end
+";
+
+    assert_eq!(
+        captured(code),
+        vec![
+            vec![
+                ("a".to_owned(), true, vec![54]),
+                ("b".to_owned(), false, vec![98, 102]),
+            ],
+            vec![
+                ("b".to_owned(), false, vec![98, 102]),
+                ("x".to_owned(), true, vec![106]),
+            ],
+            vec![
+                ("b".to_owned(), false, vec![98, 102]),
+                ("x".to_owned(), true, vec![106]),
+            ],
+        ]
+    );
+}
+
+#[test]
+fn test_functions_without_captures() {
+    assert_eq!(
+        captured("local function f(x) local y = x return function(z) return z end end"),
+        vec![vec![], vec![]]
+    );
+
+    // Calling a local function from itself captures it
+    assert_eq!(
+        captured("local function f(n) return f(n - 1) end"),
+        vec![vec![("f".to_owned(), true, vec![27])]]
+    );
+}
+
+#[test]
+fn test_captured_loop_variables() {
+    let code = "local callbacks = {}
+for i = 1, 3 do
+  callbacks[i] = function() return i end
+end
+function callbacks.run(self) return self end
+";
+
+    assert_eq!(
+        captured(code),
+        vec![vec![("i".to_owned(), true, vec![72])], vec![]]
+    );
+}
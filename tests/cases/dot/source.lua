@@ -0,0 +1,11 @@
+-- Counts down
+local function countdown(n)
+	while n > 0 do
+		print(n)
+		n = n - 1
+	end
+
+	if n == 0 then
+		return "done"
+	end
+end
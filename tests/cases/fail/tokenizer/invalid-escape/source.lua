@@ -0,0 +1 @@
+local s = "a long string with a bad escape at the end\300"
\ No newline at end of file
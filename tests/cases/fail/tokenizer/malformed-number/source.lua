@@ -0,0 +1 @@
+local pi = 3..14
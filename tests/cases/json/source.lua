@@ -0,0 +1,6 @@
+-- A representative program for the JSON output
+local t = { x = "a", [1] = 0x10 }
+
+function t:f(n) -- a method
+	if n > 0 then return n * 2 else print(-n .. "!") end
+end
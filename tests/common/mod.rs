@@ -0,0 +1,25 @@
+// Helpers shared by the tests, which each test only uses some of
+#![allow(dead_code)]
+
+use full_moon::ParseConfig;
+use std::fs;
+
+// The source.lua of every case in the directory, sorted so the order is the same everywhere
+pub fn read_sources(directory: &str) -> Vec<String> {
+    let mut sources: Vec<_> = fs::read_dir(directory)
+        .expect("couldn't read directory")
+        .map(|entry| {
+            fs::read_to_string(entry.unwrap().path().join("source.lua"))
+                .expect("couldn't read source.lua")
+        })
+        .collect();
+    sources.sort();
+    sources
+}
+
+pub fn without_trivia() -> ParseConfig {
+    ParseConfig {
+        preserve_trivia: false,
+        ..ParseConfig::default()
+    }
+}
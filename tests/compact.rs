@@ -0,0 +1,139 @@
+use full_moon::{
+    ast::{Ast, Stmt},
+    parse, print,
+    visitors::VisitorMut,
+};
+use pretty_assertions::assert_eq;
+use std::fs;
+
+// Replaces the statement at the index in the top level block
+struct ReplaceStmt<'ast> {
+    index: usize,
+    replacement: Stmt<'ast>,
+    seen: usize,
+}
+
+impl<'ast> VisitorMut<'ast> for ReplaceStmt<'ast> {
+    fn visit_stmt(&mut self, stmt: &mut Stmt<'ast>) {
+        if self.seen == self.index {
+            *stmt = self.replacement.clone();
+        }
+
+        self.seen += 1;
+    }
+}
+
+fn replace_stmt<'ast>(ast: &mut Ast<'ast>, index: usize, replacement: &Ast<'ast>) {
+    ReplaceStmt {
+        index,
+        replacement: replacement.nodes().iter_stmts().next().unwrap().clone(),
+        seen: 0,
+    }
+    .visit_ast(ast);
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_compact_keeps_parsed_code() {
+    for entry in fs::read_dir("./tests/cases/pass").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).unwrap();
+        let mut compacted = ast.clone();
+        let report = compacted.memory_usage();
+        compacted.compact();
+
+        assert_eq!(print(&compacted), source, "{:?}", path);
+        assert_eq!(compacted.nodes(), ast.nodes(), "{:?}", path);
+        assert_eq!(compacted.memory_usage(), report, "{:?}", path);
+    }
+}
+
+#[test]
+fn test_compact_drops_replaced_tokens() {
+    let code = "local a = 1 -- one\n\n-- two\nlocal b = 2 -- three\nlocal c = 3\n";
+    let mut ast = parse(code).unwrap();
+    let replacement = parse("b = 4").unwrap();
+    replace_stmt(&mut ast, 1, &replacement);
+
+    let clone = ast.clone();
+    let positions: Vec<_> = ast
+        .iter_tokens()
+        .skip_while(|token| token.to_string() != "c")
+        .map(|token| {
+            (
+                token.to_string(),
+                token.start_position(),
+                token.end_position(),
+            )
+        })
+        .collect();
+
+    ast.compact();
+    assert_eq!(print(&ast), "local a = 1 -- one\nlocal c = 3\n");
+    assert_eq!(print(&clone), code);
+    assert_eq!(ast.nodes(), clone.nodes());
+    assert_eq!(
+        ast.iter_tokens()
+            .skip_while(|token| token.to_string() != "c")
+            .map(|token| (
+                token.to_string(),
+                token.start_position(),
+                token.end_position()
+            ))
+            .collect::<Vec<_>>(),
+        positions
+    );
+
+    ast.update_positions();
+    let printed = print(&ast);
+    let reparsed = parse(&printed).unwrap();
+    assert_eq!(
+        ast.iter_tokens().collect::<Vec<_>>(),
+        reparsed.iter_tokens().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_compact_after_many_replacements() {
+    let code: String = (0..100)
+        .map(|index| format!("local x{} = {} -- {}\n", index, index, index))
+        .collect();
+    let mut ast = parse(&code).unwrap();
+    let baseline = ast.memory_usage();
+
+    // Each replacement is parsed from its own code, like an editor parsing only what was typed
+    let snippets: Vec<String> = (0..3_000)
+        .map(|edit| format!("x{} = {}\n", edit % 100, edit))
+        .collect();
+    for (edit, snippet) in snippets.iter().enumerate() {
+        let replacement = parse(snippet).unwrap();
+        replace_stmt(&mut ast, edit % 100, &replacement);
+    }
+
+    let edited = ast.memory_usage();
+    ast.compact();
+    let compacted = ast.memory_usage();
+
+    // What the statements that are there now take when parsed together
+    let equivalent: String = snippets[snippets.len() - 100..].concat();
+    let expected = parse(&equivalent).unwrap().memory_usage();
+
+    assert_eq!(compacted.node_count(), expected.node_count());
+    assert!(compacted.token_arena_bytes() < edited.token_arena_bytes());
+    // The tokens of the code that was parsed first are all gone
+    assert!(
+        edited.total_bytes() - compacted.total_bytes() > baseline.token_arena_bytes() * 9 / 10,
+        "{:?} {:?} {:?}",
+        baseline,
+        edited,
+        compacted
+    );
+    assert!(
+        compacted.total_bytes() < expected.total_bytes() * 5 / 4,
+        "{:?} {:?}",
+        compacted,
+        expected
+    );
+    assert_eq!(print(&ast), "");
+}
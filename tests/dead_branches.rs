@@ -0,0 +1,220 @@
+use full_moon::{
+    parse, print,
+    transform::{eliminate_dead_branches, eliminate_dead_branches_with, DeadBranchConfig},
+};
+use pretty_assertions::assert_eq;
+
+fn eliminated(code: &str) -> String {
+    let mut ast = parse(code).unwrap();
+    eliminate_dead_branches(&mut ast);
+    print(&ast)
+}
+
+fn hoisted(code: &str) -> String {
+    let mut ast = parse(code).unwrap();
+    eliminate_dead_branches_with(
+        &mut ast,
+        &DeadBranchConfig {
+            hoist_comments: true,
+        },
+    );
+    print(&ast)
+}
+
+#[test]
+fn test_if_splicing() {
+    assert_eq!(
+        eliminated(
+            "local function f()
+    before()
+    if true then
+        a()
+        if x then
+            b()
+        end
+    else
+        c()
+    end
+    after()
+end
+"
+        ),
+        "local function f()
+    before()
+    a()
+    if x then
+        b()
+    end
+    after()
+end
+"
+    );
+
+    assert_eq!(
+        eliminated("if false then\n    a()\nelse\n    b()\nend\nif nil then a() end\nc()\n"),
+        "b()\nc()\n"
+    );
+
+    // The semicolon after the statement moves to the last statement of the block
+    assert_eq!(
+        eliminated("x = 1 if 1 then y = 2 end; z = 3"),
+        "x = 1 y = 2; z = 3"
+    );
+    assert_eq!(eliminated("if true then a(); end; b()"), "a(); b()");
+
+    // Blocks with locals, or ending with `return` before the end of the block they'd be in, stay blocks
+    assert_eq!(
+        eliminated("if true then\n    local x = 1\n    print(x)\nend\n"),
+        "do\n    local x = 1\n    print(x)\nend\n"
+    );
+    assert_eq!(
+        eliminated("if true then\n    return 1\nend\nprint(2)\n"),
+        "do\n    return 1\nend\nprint(2)\n"
+    );
+    assert_eq!(
+        eliminated("if true then\n    return 1\nend\n"),
+        "return 1\n"
+    );
+}
+
+#[test]
+fn test_if_arms() {
+    assert_eq!(
+        eliminated(
+            "if a then
+    one()
+elseif false then
+    two()
+elseif b then
+    three()
+elseif 1 == 1 then
+    four()
+else
+    five()
+end
+"
+        ),
+        "if a then
+    one()
+elseif b then
+    three()
+else
+    four()
+end
+"
+    );
+
+    // The first arm left becomes the `if`
+    assert_eq!(
+        eliminated("if false then\n    a()\nelseif x then\n    b()\nend\n"),
+        "if x then\n    b()\nend\n"
+    );
+
+    // Conditions that aren't known keep the statement as it is
+    assert_eq!(
+        eliminated("if x then a() else b() end"),
+        "if x then a() else b() end"
+    );
+}
+
+#[test]
+fn test_loops() {
+    assert_eq!(
+        eliminated("a()\nwhile false do\n    b()\nend\nwhile nil == false do end\nc()\n"),
+        "a()\nc()\n"
+    );
+
+    assert_eq!(
+        eliminated("repeat\n    local x = f()\n    g(x)\nuntil true\n"),
+        "do\n    local x = f()\n    g(x)\nend\n"
+    );
+
+    // A `break` for the loop keeps it a loop, but not a `break` for a loop inside of it
+    assert_eq!(
+        eliminated("repeat if x then break end until true"),
+        "repeat if x then break end until true"
+    );
+    assert_eq!(
+        eliminated("repeat while x do break end until true"),
+        "do while x do break end end"
+    );
+
+    assert_eq!(
+        eliminated("while true do\n    f()\nend\nrepeat f() until x\n"),
+        "while true do\n    f()\nend\nrepeat f() until x\n"
+    );
+}
+
+#[test]
+fn test_nested() {
+    // Code moved out of a removed statement is checked too
+    assert_eq!(
+        eliminated(
+            "if true then
+    if false then
+        a()
+    else
+        b()
+    end
+end
+"
+        ),
+        "b()\n"
+    );
+
+    assert_eq!(
+        eliminated("local f = function()\n    while false do end\n    return 1\nend\n"),
+        "local f = function()\n    return 1\nend\n"
+    );
+}
+
+#[test]
+fn test_comments() {
+    let code = "local function f()
+    -- keep
+    if false then
+        -- removed
+        a() --[[ also removed ]]
+    else
+        b() -- kept
+    end
+end
+";
+
+    assert_eq!(
+        eliminated(code),
+        "local function f()
+    -- keep
+    b() -- kept
+end
+"
+    );
+
+    assert_eq!(
+        hoisted(code),
+        "local function f()
+    -- keep
+    -- removed
+    --[[ also removed ]]
+    b() -- kept
+end
+"
+    );
+
+    assert_eq!(
+        hoisted("while false do\n    -- comment\nend\n"),
+        "-- comment\n"
+    );
+}
+
+#[test]
+fn test_reparse() {
+    let mut ast =
+        parse("if true then x = 1 elseif y then x = 2 end\nwhile false do end\nreturn x").unwrap();
+    eliminate_dead_branches(&mut ast);
+    ast.update_positions();
+    let printed = print(&ast);
+    let reparsed = parse(&printed).unwrap();
+    assert_eq!(printed, "x = 1\nreturn x");
+    assert_eq!(ast.nodes(), reparsed.nodes());
+}
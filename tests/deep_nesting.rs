@@ -0,0 +1,122 @@
+use full_moon::{
+    ast::{Ast, Expression, Field, Stmt, Value},
+    parse_with, Error, ParseConfig,
+};
+
+// Parses the code with no limit on how deeply expressions are nested, since parsing and dropping them doesn't recurse
+fn parse(code: &str) -> Result<Ast<'_>, Error<'_>> {
+    parse_with(
+        code,
+        &ParseConfig {
+            expression_depth_limit: usize::MAX,
+            ..ParseConfig::default()
+        },
+    )
+}
+
+// The expression assigned by the first statement, which has to be an assignment
+fn assigned<'a, 'b>(stmt: Option<&'b Stmt<'a>>) -> &'b Expression<'a> {
+    match stmt {
+        Some(Stmt::Assignment(assignment)) => assignment.expr_list().iter().next().unwrap(),
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+}
+
+// How many tables are in each other in the expression, going down the last field of each one
+fn table_depth(mut expression: &Expression) -> usize {
+    let mut depth = 0;
+
+    loop {
+        let table = match expression {
+            Expression::Value { value, .. } => match &**value {
+                Value::TableConstructor(table) => table,
+                _ => return depth,
+            },
+            _ => return depth,
+        };
+
+        depth += 1;
+        expression = match table.iter_fields().last() {
+            Some((Field::ExpressionKey { value, .. }, _))
+            | Some((Field::NameKey { value, .. }, _))
+            | Some((Field::NoKey(value), _)) => value,
+            None => return depth,
+        };
+    }
+}
+
+#[test]
+fn test_deep_tables() {
+    let code = format!("x = {}{}", "{".repeat(100_000), "}".repeat(100_000));
+    let ast = parse(&code).unwrap();
+    assert_eq!(
+        table_depth(assigned(ast.nodes().iter_stmts().next())),
+        100_000
+    );
+    drop(ast);
+
+    let code = format!(
+        "x = {}1{}",
+        "{ a = 1, [{}] = ".repeat(10_000),
+        " }".repeat(10_000)
+    );
+    let ast = parse(&code).unwrap();
+    assert_eq!(
+        table_depth(assigned(ast.nodes().iter_stmts().next())),
+        10_000
+    );
+}
+
+#[test]
+fn test_deep_binary_operators() {
+    let code = format!("x = 1{}", " + 1".repeat(100_000));
+    let ast = parse(&code).unwrap();
+
+    let mut expression = assigned(ast.nodes().iter_stmts().next());
+    let mut operators = 0;
+    while let Expression::Value {
+        binop: Some(binop), ..
+    } = expression
+    {
+        operators += 1;
+        expression = binop.rhs();
+    }
+
+    assert_eq!(operators, 100_000);
+    drop(ast);
+
+    assert!(parse(&format!("x = 1{}", " + -1".repeat(100_000))).is_ok());
+}
+
+#[test]
+fn test_deep_prefixes() {
+    for code in &[
+        format!("x = {}1{}", "(".repeat(100_000), ")".repeat(100_000)),
+        format!("x = {}1{}", "f(".repeat(100_000), ")".repeat(100_000)),
+        format!("x = {}1{}", "t[".repeat(100_000), "]".repeat(100_000)),
+        format!("x = {}1{}", "a:b(1, ".repeat(100_000), ")".repeat(100_000)),
+        format!("x = {}{}", "f{ ".repeat(100_000), " }".repeat(100_000)),
+        format!("{}1{}", "f(".repeat(100_000), ")".repeat(100_000)),
+    ] {
+        assert!(parse(code).is_ok());
+    }
+}
+
+#[test]
+fn test_deep_errors() {
+    // What was parsed before the error is dropped without recursing too
+    for code in &[
+        format!("x = {}{} + )", "{".repeat(100_000), "}".repeat(100_000)),
+        format!("x = {{ {}{}, )", "{".repeat(100_000), "}".repeat(100_000)),
+        format!("x = {}1, )", "f(".repeat(100_000)),
+        format!("{}1{} )", "(".repeat(100_000), ")".repeat(100_000)),
+    ] {
+        assert!(parse(code).is_err());
+    }
+}
+
+#[test]
+fn test_deep_suffixes() {
+    assert!(parse(&format!("x = a{}", ".b".repeat(100_000))).is_ok());
+    assert!(parse(&format!("a{}", "()".repeat(100_000))).is_ok());
+}
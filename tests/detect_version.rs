@@ -0,0 +1,147 @@
+use full_moon::{detect_version, DialectFeature, LuaVersion};
+
+fn features(code: &str) -> Vec<DialectFeature> {
+    detect_version(code).features().collect()
+}
+
+#[test]
+fn test_features() {
+    use DialectFeature::*;
+
+    assert_eq!(
+        features("for i = 1, 10 do if i > 5 then goto continue end ::continue:: end"),
+        vec![Goto]
+    );
+    assert_eq!(features("x = a // b"), vec![FloorDivision]);
+    assert_eq!(features("x = a & b | c ~ d"), vec![BitwiseOperators]);
+    assert_eq!(features("x = ~a"), vec![BitwiseOperators]);
+    assert_eq!(features("x = a << 2"), vec![BitwiseOperators]);
+    assert_eq!(features("x = a >> b"), vec![BitwiseOperators]);
+    assert_eq!(
+        features("local f <close>, x <const> = io.open(name), 1"),
+        vec![Attributes]
+    );
+    assert_eq!(features("local x = (y :: any) :: number"), vec![TypeCasts]);
+    assert_eq!(features("print(`hello {name}`)"), vec![InterpolatedStrings]);
+    assert_eq!(features("x = 0b101"), vec![BinaryNumbers]);
+    assert_eq!(features("x = 1LL + 0x10ull"), vec![IntegerSuffixes]);
+}
+
+#[test]
+fn test_lua_51() {
+    let code = r#"
+        local x = a ~= b and a < b and a > (b / c)
+        local t = { a = 1, [b] = 2 }
+        print(t.a:upper(), ...)
+        local goto = 1
+        print(goto, x, 1e5, 0x10)
+    "#;
+    let detected = detect_version(code);
+    assert_eq!(detected.features().count(), 0);
+    assert_eq!(detected.version(), Some(LuaVersion::Lua51));
+
+    // Luau's types can end in `>>`, which isn't an operator when there isn't a value after it
+    assert_eq!(features("local x: Array<Array<number>> = {}"), Vec::new());
+}
+
+#[test]
+fn test_strings_and_comments() {
+    let code = r#"
+        -- goto continue, a // b, local x <const>
+        --[[ x :: number, `hello` & 0b1 ]]
+        print("a & b", 'a // b', [[::label:: 1LL | ~]])
+    "#;
+    assert_eq!(features(code), Vec::new());
+
+    // The text of strings in backticks isn't read as code, even when it has quotes or escaped backticks in it
+    let code = "print(`it's \\` a & b`, \"x\") -- `\nx = a // b";
+    let detected = detect_version(code);
+    assert_eq!(
+        detected.features().collect::<Vec<_>>(),
+        vec![
+            DialectFeature::InterpolatedStrings,
+            DialectFeature::FloorDivision
+        ]
+    );
+    let floor_division = detected.evidence(DialectFeature::FloorDivision).unwrap();
+    assert_eq!(floor_division.line(), 2);
+    assert_eq!(floor_division.character(), 7);
+    assert_eq!(&code[floor_division.bytes()..], "// b");
+}
+
+#[test]
+fn test_evidence() {
+    let code = "x = a & b\nlocal y <const> = c & d\ngoto done";
+    let detected = detect_version(code);
+    assert_eq!(
+        detected.features().collect::<Vec<_>>(),
+        vec![
+            DialectFeature::BitwiseOperators,
+            DialectFeature::Attributes,
+            DialectFeature::Goto
+        ]
+    );
+
+    // Only the first evidence of each feature is kept
+    let bitwise = detected.evidence(DialectFeature::BitwiseOperators).unwrap();
+    assert_eq!((bitwise.bytes(), bitwise.line()), (6, 1));
+    assert_eq!(
+        detected
+            .evidence(DialectFeature::Attributes)
+            .unwrap()
+            .bytes(),
+        18
+    );
+    assert_eq!(detected.evidence(DialectFeature::Goto).unwrap().bytes(), 34);
+    assert!(detected.contains(DialectFeature::Goto));
+    assert!(!detected.contains(DialectFeature::TypeCasts));
+    assert_eq!(detected.evidence(DialectFeature::TypeCasts), None);
+}
+
+#[test]
+fn test_version() {
+    let version = |code| detect_version(code).version();
+
+    assert_eq!(version("goto done ::done::"), Some(LuaVersion::Lua52));
+    assert_eq!(
+        version("goto done ::done:: x = a // b"),
+        Some(LuaVersion::Lua53)
+    );
+    assert_eq!(version("x = a // b"), Some(LuaVersion::Lua53));
+    assert_eq!(version("local x <const> = a & b"), Some(LuaVersion::Lua54));
+    assert_eq!(
+        version("goto done ::done:: x = 1LL"),
+        Some(LuaVersion::LuaJit)
+    );
+    assert_eq!(version("x = 0b1 // y :: number"), Some(LuaVersion::Luau));
+
+    // No version has them all
+    assert_eq!(version("x = 1LL // 2"), None);
+    assert_eq!(version("local x <const> = `a`"), None);
+
+    assert!(LuaVersion::Lua53.has(DialectFeature::BitwiseOperators));
+    assert!(!LuaVersion::Luau.has(DialectFeature::BitwiseOperators));
+}
+
+#[test]
+fn test_errors() {
+    // Code is looked at up to what can't be tokenized
+    assert_eq!(
+        features("x = a // b\nprint('unclosed"),
+        vec![DialectFeature::FloorDivision]
+    );
+    assert_eq!(
+        features("x = a // b\n--[[ unclosed x & y"),
+        vec![DialectFeature::FloorDivision]
+    );
+
+    // Other characters that can't be tokenized are gone past
+    assert_eq!(
+        features("x = a @ b $ c // d"),
+        vec![DialectFeature::FloorDivision]
+    );
+    assert_eq!(
+        features("print(`unclosed & x"),
+        vec![DialectFeature::InterpolatedStrings]
+    );
+}
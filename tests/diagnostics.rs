@@ -0,0 +1,69 @@
+#![cfg(feature = "diagnostics")]
+use full_moon::{
+    diagnostics::{
+        self,
+        codespan_reporting::diagnostic::{LabelStyle, Severity},
+    },
+    parse,
+};
+
+// The style, byte range, and message of every label
+fn labels(code: &str) -> Vec<(LabelStyle, (usize, usize), String)> {
+    let diagnostic = diagnostics::diagnostic(&parse(code).unwrap_err(), ());
+    assert_eq!(diagnostic.severity, Severity::Error);
+
+    diagnostic
+        .labels
+        .into_iter()
+        .map(|label| {
+            (
+                label.style,
+                (label.range.start, label.range.end),
+                label.message,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_diagnostic_labels() {
+    assert_eq!(
+        labels("local x = ]"),
+        vec![(
+            LabelStyle::Primary,
+            (10, 11),
+            "expected an expression".to_owned()
+        )]
+    );
+
+    assert_eq!(
+        labels("x = 1\ncall(1 2)"),
+        vec![
+            (
+                LabelStyle::Primary,
+                (13, 14),
+                "expected one of `,` or `)`".to_owned()
+            ),
+            (
+                LabelStyle::Secondary,
+                (10, 11),
+                "`(` opened here".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_diagnostic_message() {
+    let error = parse("while x do").unwrap_err();
+    assert_eq!(
+        diagnostics::diagnostic(&error, ()).message,
+        "unexpected end of code"
+    );
+
+    let error = parse("x = 'unclosed").unwrap_err();
+    let diagnostic = diagnostics::diagnostic(&error, 1);
+    assert_eq!(diagnostic.message, "unclosed string");
+    assert_eq!(diagnostic.labels[0].file_id, 1);
+    assert_eq!(diagnostic.labels[0].range, 4..5);
+}
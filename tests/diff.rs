@@ -0,0 +1,128 @@
+use full_moon::{diff, node::Node, parse, visitors::NodeKind, DiffEntry};
+use std::fs;
+
+// Applies the entries to the old code, last to first
+fn apply(code: &str, entries: &[DiffEntry]) -> String {
+    let mut code = code.to_owned();
+    for entry in entries.iter().rev() {
+        let (start, end) = entry.old_range();
+        code.replace_range(start.bytes()..end.bytes(), entry.replacement());
+    }
+
+    code
+}
+
+fn assert_diff(old_code: &str, new_code: &str) -> Vec<DiffEntry> {
+    let old = parse(old_code).unwrap();
+    let new = parse(new_code).unwrap();
+    let entries = diff(&old, &new);
+
+    let applied = apply(old_code, &entries);
+    let applied_ast = parse(&applied)
+        .unwrap_or_else(|error| panic!("couldn't parse applied diff {:?}: {}", applied, error));
+    assert!(
+        applied_ast.nodes().similar(new.nodes()),
+        "applied diff {:?} isn't similar to {:?}",
+        applied,
+        new_code
+    );
+
+    entries
+}
+
+fn summarize(entries: &[DiffEntry]) -> Vec<(NodeKind, usize, usize, &str)> {
+    entries
+        .iter()
+        .map(|entry| {
+            let (start, end) = entry.old_range();
+            (
+                entry.kind(),
+                start.bytes(),
+                end.bytes(),
+                entry.replacement(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_diff_ignores_trivia() {
+    assert!(assert_diff("local x = 1 -- one\nprint(x)", "local x=1\n\n\tprint( x )").is_empty());
+}
+
+#[test]
+fn test_diff_changed_token() {
+    let entries = assert_diff("call(a, b, c)", "call(a, d, c)");
+    assert_eq!(
+        summarize(&entries),
+        vec![(NodeKind::TokenReference, 8, 9, "d")]
+    );
+}
+
+#[test]
+fn test_diff_changed_subtree() {
+    let entries = assert_diff("x = f(1)\ny = 2", "x = f(1, 2)\ny = 2");
+    assert_eq!(
+        summarize(&entries),
+        vec![(NodeKind::FunctionArgs, 5, 8, "(1, 2)")]
+    );
+}
+
+#[test]
+fn test_diff_inserted_statement() {
+    let entries = assert_diff("a()\nc()", "a()\nb()\nc()");
+    assert_eq!(summarize(&entries), vec![(NodeKind::Stmt, 3, 3, "\nb()")]);
+}
+
+#[test]
+fn test_diff_deleted_statement() {
+    let entries = assert_diff("a(); b(); c()", "a(); c()");
+    assert_eq!(summarize(&entries), vec![(NodeKind::Stmt, 4, 9, "")]);
+}
+
+#[test]
+fn test_diff_inserted_into_empty_block() {
+    let entries = assert_diff(
+        "local function f() end",
+        "local function f()\n\treturn 1\nend",
+    );
+    assert_eq!(
+        summarize(&entries),
+        vec![(NodeKind::LastStmt, 18, 18, "\n\treturn 1")]
+    );
+}
+
+#[test]
+fn test_diff_replaced_statement() {
+    let entries = assert_diff("a()\nlocal x = 1\nc()", "a()\nwhile true do end\nc()");
+    assert_eq!(
+        summarize(&entries),
+        vec![(NodeKind::Stmt, 4, 15, "while true do end")]
+    );
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_diff_between_pass_cases() {
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    let sources: Vec<_> = paths
+        .iter()
+        .map(|path| fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua"))
+        .collect();
+
+    for pair in sources.windows(2) {
+        assert_diff(&pair[0], &pair[1]);
+        assert!(assert_diff(&pair[0], &pair[0]).is_empty());
+    }
+}
+
+#[test]
+fn test_diff_semicolon() {
+    let entries = assert_diff("a()\nb()", "a()\nb();");
+    assert_eq!(summarize(&entries), vec![(NodeKind::Stmt, 4, 7, "b();")]);
+}
@@ -0,0 +1,116 @@
+use full_moon::{
+    analysis::{directives, Attachment},
+    ast::Ast,
+    node::{Node, TriviaBehavior},
+    parse, parse_with, ParseConfig,
+};
+use pretty_assertions::assert_eq;
+
+// The key and values of every directive, with the code of what it applies to
+fn attached(ast: &Ast, prefix: &str) -> Vec<(String, Vec<String>, String)> {
+    directives(ast, prefix)
+        .iter()
+        .map(|directive| {
+            let attachment = match directive.attachment() {
+                Attachment::Leading(stmt) => {
+                    format!("leading {}", stmt.print(TriviaBehavior::Trim))
+                }
+                Attachment::Trailing(stmt) => {
+                    format!("trailing {}", stmt.print(TriviaBehavior::Trim))
+                }
+                Attachment::Chunk(block) => {
+                    assert!(std::ptr::eq(block, ast.nodes()));
+                    "chunk".to_owned()
+                }
+                Attachment::Detached => "detached".to_owned(),
+            };
+
+            (
+                directive.key().to_owned(),
+                directive.values().to_vec(),
+                attachment,
+            )
+        })
+        .collect()
+}
+
+fn directive(key: &str, values: &[&str], attachment: &str) -> (String, Vec<String>, String) {
+    (
+        key.to_owned(),
+        values.iter().map(|value| value.to_string()).collect(),
+        attachment.to_owned(),
+    )
+}
+
+#[test]
+fn test_attachment() {
+    let code = "-- lint: strict
+
+-- lint: ignore unused_local, shadowing
+local x = 1
+print(x) -- lint: ignore global-call
+local function f()
+    return x -- lint: allow
+    -- lint: end
+end
+-- lint: end";
+    let ast = parse(code).unwrap();
+    assert_eq!(
+        attached(&ast, "lint:"),
+        vec![
+            directive("strict", &[], "chunk"),
+            directive(
+                "ignore",
+                &["unused_local", "shadowing"],
+                "leading local x = 1"
+            ),
+            directive("ignore", &["global-call"], "trailing print(x)"),
+            // There is no statement for the return, so it applies to nothing, like the comment before `end`
+            directive("allow", &[], "detached"),
+            directive("end", &[], "detached"),
+            directive("end", &[], "detached"),
+        ]
+    );
+}
+
+#[test]
+fn test_syntax() {
+    let code = "--!strict
+--[[ lint: ignore  a ,, b ]]
+--[==[lint: only]==]
+-- lint:
+-- not lint: ignore x
+-- lintx: ignore x
+x = 1";
+    let ast = parse(code).unwrap();
+    assert_eq!(
+        attached(&ast, "lint:"),
+        vec![
+            directive("ignore", &["a", "b"], "leading x = 1"),
+            directive("only", &[], "leading x = 1"),
+        ]
+    );
+
+    let directives = directives(&ast, "!");
+    assert_eq!(directives.len(), 1);
+    assert_eq!(directives[0].key(), "strict");
+    let (start, end) = directives[0].range();
+    assert_eq!(&code[start.bytes()..end.bytes()], "--!strict");
+}
+
+#[test]
+fn test_without_trivia() {
+    let config = ParseConfig {
+        preserve_trivia: false,
+        ..ParseConfig::default()
+    };
+    let code = "--!strict\nlocal x = 1 --!ignore";
+    let ast = parse_with(code, &config).unwrap();
+    assert_eq!(
+        attached(&ast, "!"),
+        vec![
+            directive("strict", &[], "chunk"),
+            directive("ignore", &[], "detached"),
+        ]
+    );
+}
@@ -0,0 +1,139 @@
+use full_moon::parse;
+
+fn rich(code: &str) -> String {
+    parse(code)
+        .unwrap_err()
+        .display_rich(code, Some("test.lua"))
+}
+
+#[test]
+fn test_display_rich() {
+    assert_eq!(
+        rich("call(1 2)"),
+        concat!(
+            "error: unexpected token `2`\n",
+            " --> test.lua:1:8\n",
+            "  |\n",
+            "1 | call(1 2)\n",
+            "  |        ^ expected one of `,` or `)`\n",
+            "  = note: to close `(` on line 1, column 5\n",
+        )
+    );
+
+    assert_eq!(
+        parse("x = = 1").unwrap_err().display_rich("x = = 1", None),
+        concat!(
+            "error: unexpected token `=`\n",
+            " --> 1:5\n",
+            "  |\n",
+            "1 | x = = 1\n",
+            "  |     ^ expected an expression\n",
+        )
+    );
+}
+
+#[test]
+fn test_display_rich_end_of_code() {
+    assert_eq!(
+        rich("do\n  call()\n"),
+        concat!(
+            "error: unexpected end of code\n",
+            " --> test.lua:3:1\n",
+            "  |\n",
+            "3 |\n",
+            "  | ^ expected one of `break`, `end`, `return` or a statement\n",
+            "  = note: to close `do` on line 1, column 1\n",
+        )
+    );
+}
+
+#[test]
+fn test_display_rich_line_numbers() {
+    let code = format!("{}call(1 2)", "\n".repeat(9));
+    assert_eq!(
+        rich(&code),
+        concat!(
+            "error: unexpected token `2`\n",
+            "  --> test.lua:10:8\n",
+            "   |\n",
+            "10 | call(1 2)\n",
+            "   |        ^ expected one of `,` or `)`\n",
+            "   = note: to close `(` on line 10, column 5\n",
+        )
+    );
+}
+
+#[test]
+fn test_display_rich_tabs() {
+    // The column counts the tabs as one character, but they're printed as four spaces
+    assert_eq!(
+        rich("\tif x\t]"),
+        concat!(
+            "error: unexpected token `]`\n",
+            " --> test.lua:1:7\n",
+            "  |\n",
+            "1 |     if x    ]\n",
+            "  |             ^ expected `then`\n",
+        )
+    );
+}
+
+#[test]
+fn test_display_rich_multiple_lines() {
+    assert_eq!(
+        rich("x = 1 [[a\nb]] y"),
+        concat!(
+            "error: unexpected token\n",
+            " --> test.lua:1:7\n",
+            "  |\n",
+            "1 | x = 1 [[a\n",
+            "  |       ^^^\n",
+            "2 | b]] y\n",
+            "  | ^^^ expected one of `break`, `return`, `,`, a statement or the end of the code\n",
+        )
+    );
+
+    assert_eq!(
+        rich("x = 1 [[a\nb\nc]]"),
+        concat!(
+            "error: unexpected token\n",
+            " --> test.lua:1:7\n",
+            "  |\n",
+            "1 | x = 1 [[a\n",
+            "  |       ^^^\n",
+            " ...\n",
+            "3 | c]]\n",
+            "  | ^^^ expected one of `break`, `return`, `,`, a statement or the end of the code\n",
+        )
+    );
+}
+
+#[test]
+fn test_display_rich_long_lines() {
+    let code = format!("x = {}]", "a + ".repeat(50));
+    let rendered = rich(&code);
+    let lines: Vec<_> = rendered.lines().collect();
+
+    assert_eq!(lines[1], " --> test.lua:1:205");
+    assert!(lines[3].starts_with("1 | ..."));
+    assert!(lines[3].ends_with("a + ]"));
+    assert_eq!(lines[3].len(), "1 | ...".len() + 100);
+
+    // The `^` is still under the `]`
+    let caret = lines[4].find('^').unwrap();
+    assert_eq!(&lines[3][caret..caret + 1], "]");
+}
+
+#[test]
+fn test_display_rich_tokenizer_errors() {
+    assert_eq!(
+        rich("x = 'abc"),
+        concat!(
+            "error: unclosed string\n",
+            " --> test.lua:1:5\n",
+            "  |\n",
+            "1 | x = 'abc\n",
+            "  |     ^\n",
+        )
+    );
+}
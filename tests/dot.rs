@@ -0,0 +1,95 @@
+use full_moon::{
+    debug::{to_dot, to_dot_with, DotConfig},
+    parse,
+};
+use pretty_assertions::assert_eq;
+use std::fs;
+
+// Checks the output against the dot file, or writes it if there isn't one yet
+fn check_fixture(file: &str, dot: &str) {
+    let path = format!("./tests/cases/dot/{}", file);
+    match fs::read_to_string(&path) {
+        Ok(expected) => assert_eq!(dot, expected, "{}", path),
+        Err(_) => fs::write(&path, dot).expect("couldn't write dot file"),
+    }
+}
+
+#[test]
+fn test_dot_fixture() {
+    let source = fs::read_to_string("./tests/cases/dot/source.lua").unwrap();
+    let ast = parse(&source).unwrap();
+
+    check_fixture("default.dot", &to_dot(&ast));
+    check_fixture(
+        "trivia.dot",
+        &to_dot_with(
+            &ast,
+            &DotConfig {
+                trivia: true,
+                ..DotConfig::default()
+            },
+        ),
+    );
+    check_fixture(
+        "collapsed.dot",
+        &to_dot_with(
+            &ast,
+            &DotConfig {
+                max_depth: Some(6),
+                collapse_tokens: true,
+                ..DotConfig::default()
+            },
+        ),
+    );
+}
+
+fn count_tokens(dot: &str) -> usize {
+    dot.lines()
+        .filter(|line| line.contains("shape=ellipse"))
+        .count()
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_dot_pass_cases() {
+    for entry in fs::read_dir("./tests/cases/pass").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).unwrap();
+
+        // Every token is in the graph once, and with trivia, so is every comment and whitespace
+        let tokens = ast
+            .iter_tokens()
+            .filter(|token| !token.token_type().ignore());
+        assert_eq!(count_tokens(&to_dot(&ast)), tokens.count(), "{:?}", path);
+
+        let config = DotConfig {
+            trivia: true,
+            ..DotConfig::default()
+        };
+        let dot = to_dot_with(&ast, &config);
+        assert_eq!(count_tokens(&dot), ast.iter_tokens().count(), "{:?}", path);
+
+        // Every node but the root has one edge to it
+        let edges = dot.lines().filter(|line| line.contains(" -> ")).count();
+        let nodes = dot.lines().filter(|line| line.contains(" [label=")).count() - edges;
+        assert_eq!(edges, nodes - 1, "{:?}", path);
+    }
+}
+
+#[test]
+fn test_dot_max_depth() {
+    let ast = parse("if x then call() end").unwrap();
+    let config = DotConfig {
+        max_depth: Some(4),
+        ..DotConfig::default()
+    };
+
+    let dot = to_dot_with(&ast, &config);
+    assert!(dot.contains(r#"[label="Stmt::If"]"#));
+    assert!(dot.contains(r#"[label="If"]"#));
+    assert!(dot.contains(r#"[label="Expression::Value\n..."]"#));
+    assert!(dot.contains(r#"[label="condition"]"#));
+    assert!(dot.contains(r#"[label="Block\n..."]"#));
+    assert!(!dot.contains("call"));
+}
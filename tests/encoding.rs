@@ -0,0 +1,135 @@
+use full_moon::{
+    parse_bytes_lossy, parse_bytes_lossy_with, parse_file, parse_file_with,
+    tokenizer::{TokenReference, TokenType},
+    visitors::VisitorMut,
+    Fallback, FileError, LossyConfig, TextEncoding,
+};
+use std::{borrow::Cow, fs};
+
+fn fallback(fallback: Fallback) -> LossyConfig {
+    LossyConfig {
+        fallback,
+        ..LossyConfig::default()
+    }
+}
+
+#[test]
+fn test_every_byte() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    for encoding in [TextEncoding::Latin1, TextEncoding::Windows1252] {
+        let text = encoding.decode(&bytes).unwrap();
+        assert_eq!(text.chars().count(), 256);
+        assert_eq!(encoding.encode(&text).unwrap(), bytes);
+    }
+
+    assert_eq!(TextEncoding::Latin1.decode(b"\x93").unwrap(), "\u{93}");
+    assert_eq!(
+        TextEncoding::Windows1252.decode(b"\x93\x81").unwrap(),
+        "\u{201C}\u{81}"
+    );
+    assert_eq!(TextEncoding::Utf8.encode("€").unwrap(), "€".as_bytes());
+}
+
+#[test]
+fn test_fallbacks() {
+    let code = b"local s = '\xC0 \x80' -- \xE9t\xE9\nprint(s)";
+
+    let ast = parse_bytes_lossy_with(code, &fallback(Fallback::Latin1)).unwrap();
+    assert_eq!(ast.encoding(), TextEncoding::Latin1);
+    assert_eq!(ast.source(), "local s = 'À \u{80}' -- été\nprint(s)");
+    assert!(ast.replacements().is_empty());
+    assert_eq!(ast.print().unwrap(), code);
+
+    let ast = parse_bytes_lossy(code).unwrap();
+    assert_eq!(ast.encoding(), TextEncoding::Windows1252);
+    assert_eq!(ast.source(), "local s = 'À €' -- été\nprint(s)");
+    assert_eq!(ast.print().unwrap(), code);
+
+    // Code that is valid UTF-8 is always read as UTF-8
+    let ast = parse_bytes_lossy_with("x = 'été'".as_bytes(), &fallback(Fallback::Latin1)).unwrap();
+    assert_eq!(ast.encoding(), TextEncoding::Utf8);
+    assert_eq!(ast.print().unwrap(), "x = 'été'".as_bytes());
+}
+
+#[test]
+fn test_replace() {
+    // `\xE9` is a lone byte, `\xF0\x9F` is the start of a character that isn't finished, and the last is cut off
+    let code = b"x = '\xE9' -- \xF0\x9F!\ny = 1 -- \xC3";
+    let ast = parse_bytes_lossy_with(code, &fallback(Fallback::Replace)).unwrap();
+    assert_eq!(ast.encoding(), TextEncoding::Utf8);
+    assert_eq!(
+        ast.source(),
+        "x = '\u{FFFD}' -- \u{FFFD}!\ny = 1 -- \u{FFFD}"
+    );
+
+    let replacements = ast.replacements();
+    assert_eq!(replacements.len(), 3);
+    assert_eq!(replacements[0].bytes(), 5..6);
+    assert_eq!(replacements[1].bytes(), 11..13);
+    assert_eq!(replacements[2].bytes(), 24..25);
+    let position = replacements[2].position();
+    assert_eq!((position.line(), position.character()), (2, 10));
+    assert_eq!(&ast.source()[position.bytes()..], "\u{FFFD}");
+
+    // The replaced bytes can't be written back
+    assert_eq!(
+        ast.print().unwrap(),
+        "x = '\u{FFFD}' -- \u{FFFD}!\ny = 1 -- \u{FFFD}".as_bytes()
+    );
+}
+
+#[test]
+fn test_print_changes() {
+    struct Rename(&'static str);
+
+    impl VisitorMut<'static> for Rename {
+        fn visit_identifier(&mut self, token: &mut TokenReference<'static>) {
+            token.set_token_type(TokenType::Identifier {
+                identifier: Cow::Borrowed(self.0),
+            });
+        }
+    }
+
+    // Errors are in the decoded code
+    let error = parse_bytes_lossy(b"-- \xE9\xE9\nx = = 1").unwrap_err();
+    assert_eq!(error.position().unwrap().bytes(), 12);
+    assert_eq!(error.message(), "unexpected token");
+
+    let mut ast = parse_bytes_lossy(b"x = '\x80' -- \xE9").unwrap();
+    Rename("y").visit_ast(&mut ast);
+    assert_eq!(ast.print().unwrap(), b"y = '\x80' -- \xE9");
+
+    Rename("sigma_\u{3C3}").visit_ast(&mut ast);
+    let error = ast.print().unwrap_err();
+    assert_eq!(error.character(), '\u{3C3}');
+    assert_eq!(error.encoding(), TextEncoding::Windows1252);
+    assert_eq!(error.position().bytes(), 6);
+    assert_eq!(
+        error.to_string(),
+        "character not in Windows-1252 at line 1, character 7\nfound `\u{3C3}`"
+    );
+}
+
+#[test]
+fn test_parse_file() {
+    let path = std::env::temp_dir().join(format!("full_moon_encoding_{}.lua", std::process::id()));
+    fs::write(&path, b"print('\xE9') -- \x93\x94").unwrap();
+
+    let ast = parse_file(&path).unwrap();
+    assert_eq!(ast.encoding(), TextEncoding::Windows1252);
+    assert_eq!(ast.source(), "print('é') -- “”");
+
+    let ast = parse_file_with(&path, &fallback(Fallback::Latin1)).unwrap();
+    assert_eq!(ast.source(), "print('é') -- \u{93}\u{94}");
+    fs::write(&path, ast.print().unwrap()).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"print('\xE9') -- \x93\x94");
+
+    fs::write(&path, b"print(\xE9").unwrap();
+    match parse_file(&path) {
+        Err(FileError::Parse(error)) => assert_eq!(error.message(), "unexpected end of code"),
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(parse_file(&path), Err(FileError::Io(_))));
+}
@@ -0,0 +1,129 @@
+use full_moon::{
+    ast::{owned::Owned, Ast},
+    node::Node,
+    parse, print,
+    tokenizer::{Symbol, TokenReference, TokenType},
+    transform::ensure_valid_spacing,
+    visitors::VisitorMut,
+};
+use std::{borrow::Cow, fs};
+
+// Empties whitespace tokens, choosing which with a pseudorandom generator so that every seed removes different ones
+struct RemoveWhitespace {
+    state: u64,
+}
+
+impl RemoveWhitespace {
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
+
+impl<'ast> VisitorMut<'ast> for RemoveWhitespace {
+    fn visit_whitespace(&mut self, token: &mut TokenReference<'ast>) {
+        if self.state == 0 || self.next() & 1 == 0 {
+            token.set_token_type(TokenType::Whitespace {
+                characters: Cow::Borrowed(""),
+            });
+        }
+    }
+}
+
+fn assert_reparses(ast: &mut Ast, original: &Ast) {
+    ensure_valid_spacing(ast);
+    let code = print(ast);
+    let reparsed =
+        parse(&code).unwrap_or_else(|error| panic!("couldn't parse {:?}: {}", code, error));
+
+    assert!(
+        reparsed.nodes().owned().similar(&original.nodes().owned()),
+        "{:?} parsed to different code",
+        code
+    );
+}
+
+#[test]
+fn test_ensure_valid_spacing_unchanged() {
+    let code = "local x = 1 -- one\nreturn x .. 'a'";
+    let mut ast = parse(code).unwrap();
+    ensure_valid_spacing(&mut ast);
+    assert_eq!(print(&ast), code);
+}
+
+#[test]
+fn test_ensure_valid_spacing_separators() {
+    for (code, expected) in &[
+        ("local function f() end", "local function f()end"),
+        ("x = 1 .. 2 .. a", "x=1 .. 2 ..a"),
+        ("x = a - -b", "x=a- -b"),
+        ("x = a[ [[s]] ]", "x=a[ [[s]]]"),
+        ("x = t[ [=[s]=] ]", "x=t[ [=[s]=]]"),
+        ("x = a == b", "x=a==b"),
+        ("x = 0x1 and 1e5", "x=0x1 and 1e5"),
+        ("-- comment\nx = 1", "-- comment\nx=1"),
+        ("x = 1 --[[ a ]] y = 2", "x=1--[[ a ]]y=2"),
+    ] {
+        let mut ast = parse(code).unwrap();
+        let original = parse(code).unwrap();
+        RemoveWhitespace { state: 0 }.visit_ast(&mut ast);
+        assert_reparses(&mut ast, &original);
+        assert_eq!(print(&ast), *expected);
+    }
+}
+
+#[test]
+fn test_ensure_valid_spacing_comments() {
+    let code = "x = 1 -- one\ny = 2";
+    let mut ast = parse(code).unwrap();
+    RemoveWhitespace { state: 0 }.visit_ast(&mut ast);
+    ensure_valid_spacing(&mut ast);
+    assert_eq!(print(&ast), "x=1-- one\ny=2");
+}
+
+#[test]
+fn test_ensure_valid_spacing_changed_tokens() {
+    struct Rename;
+
+    impl<'ast> VisitorMut<'ast> for Rename {
+        fn visit_symbol(&mut self, token: &mut TokenReference<'ast>) {
+            if *token.token_type()
+                == (TokenType::Symbol {
+                    symbol: Symbol::Plus,
+                })
+            {
+                token.set_token_type(TokenType::Symbol {
+                    symbol: Symbol::And,
+                });
+            }
+        }
+    }
+
+    let mut ast = parse("x = a+b").unwrap();
+    Rename.visit_ast(&mut ast);
+    ensure_valid_spacing(&mut ast);
+    assert_eq!(print(&ast), "x = a and b");
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_ensure_valid_spacing_pass_cases() {
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let original = parse(&source).expect("couldn't parse");
+
+        for seed in 0..8 {
+            let mut ast = parse(&source).unwrap();
+            RemoveWhitespace { state: seed }.visit_ast(&mut ast);
+            assert_reparses(&mut ast, &original);
+        }
+    }
+}
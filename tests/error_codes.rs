@@ -0,0 +1,201 @@
+use full_moon::{
+    ast::Ast, parse, parse_tokens, parse_with, tokenizer, CancellationToken, ErrorCode, ParseConfig,
+};
+use std::collections::HashSet;
+
+// Code that causes an error with the code, for every code that parsing can give
+fn example(code: ErrorCode) -> Option<&'static str> {
+    use ErrorCode::*;
+
+    Some(match code {
+        UnclosedComment => "--[[ comment",
+        UnclosedString => "x = 'string",
+        UnexpectedCharacter => "x = $",
+        InvalidEscape => "x = '\\300'",
+        MalformedNumber => "x = 0x",
+
+        // Made from tokens instead of code below
+        EmptyTokens | NoEof | EofBeforeEnd | TokenOutOfOrder => return None,
+        // Given by replacing the text of tokens instead of parsing
+        NotAToken | TokenNotInAst => return None,
+        ExpectedEnd => "if x then",
+        ExpectedDo => "while x",
+        ExpectedThen => "if x",
+        ExpectedUntil => "repeat x()",
+        ExpectedIn => "for a, b",
+        ExpectedEquals => "t = { [1] 2 }",
+        ExpectedLeftParen => "function f",
+        ExpectedRightParen => "call(1",
+        ExpectedRightBracket => "x = t[1",
+        ExpectedRightBrace => "t = { 1",
+        ExpectedComma => "for i = 1 do end",
+        ExpectedExpression => "local x = ]",
+        ExpectedCondition => "while do end",
+        ExpectedValue => "t = { x = }",
+        ExpectedValues => "x.y =",
+        ExpectedKey => "t = { [] = 1 }",
+        ExpectedName => "x = a.()",
+        ExpectedNames => "for 1 in x do end",
+        ExpectedMethod => "a:()",
+        ExpectedMethodName => "function a:() end",
+        ExpectedArgs => "a:b",
+        ExpectedFunctionName => "function () end",
+        ExpectedStartExpression => "for i = do end",
+        ExpectedEndExpression => "for i = 1, do end",
+        ExpectedLimitExpression => "for i = 1, 2, do end",
+        LeftoverToken => "x = 1 end",
+        ReservedKeywordAsName => "local end = 1",
+
+        // Made with a lower recursion limit and a cancelled token below
+        RecursionLimitExceeded | Cancelled => return None,
+
+        // None of these can happen: every error made while parsing has a more specific code than UnexpectedToken,
+        // blocks, arguments, and return values can all be empty, function bodies give ExpectedLeftParen,
+        // and lists stop before a trailing comma, which gives LeftoverToken
+        UnexpectedToken | ExpectedBlock | ExpectedArguments | ExpectedReturnValues
+        | ExpectedFunctionBody | TrailingCharacter => return None,
+    })
+}
+
+#[test]
+fn test_error_codes() {
+    for &code in ErrorCode::all() {
+        if let Some(example) = example(code) {
+            assert_eq!(
+                parse(example).unwrap_err().code(),
+                code,
+                "wrong code for {:?}",
+                example
+            );
+        }
+    }
+
+    assert_eq!(
+        Ast::from_tokens(Vec::new()).unwrap_err().code(),
+        ErrorCode::EmptyTokens
+    );
+
+    let mut tokens = tokenizer::tokens("x = 1").unwrap();
+    tokens.pop();
+    assert_eq!(
+        Ast::from_tokens(tokens).unwrap_err().code(),
+        ErrorCode::NoEof
+    );
+
+    let mut tokens = tokenizer::tokens("x = 1").unwrap();
+    tokens.insert(0, tokens.last().unwrap().clone());
+    assert_eq!(
+        parse_tokens(tokens).unwrap_err().code(),
+        ErrorCode::EofBeforeEnd
+    );
+
+    let mut tokens = tokenizer::tokens("x = 1").unwrap();
+    tokens.swap(0, 4);
+    assert_eq!(
+        parse_tokens(tokens).unwrap_err().code(),
+        ErrorCode::TokenOutOfOrder
+    );
+
+    assert_eq!(
+        parse_with(
+            "do do end end",
+            &ParseConfig {
+                recursion_limit: 2,
+                ..ParseConfig::default()
+            }
+        )
+        .unwrap_err()
+        .code(),
+        ErrorCode::RecursionLimitExceeded
+    );
+
+    let cancellation = CancellationToken::new();
+    cancellation.cancel();
+    assert_eq!(
+        parse_with(
+            "x = 1",
+            &ParseConfig {
+                cancellation: Some(cancellation),
+                ..ParseConfig::default()
+            }
+        )
+        .unwrap_err()
+        .code(),
+        ErrorCode::Cancelled
+    );
+}
+
+#[test]
+fn test_error_code_strings() {
+    let mut strings = HashSet::new();
+
+    for &code in ErrorCode::all() {
+        let string = code.as_str();
+        assert!(strings.insert(string), "{} is used twice", string);
+        assert_eq!(string.parse(), Ok(code));
+        assert_eq!(code.to_string(), string);
+    }
+
+    assert_eq!("FM0000".parse::<ErrorCode>(), Err(()));
+}
+
+#[test]
+fn test_error_codes_are_stable() {
+    // Codes can never change, so new codes have to be added to the end of this list
+    let strings: Vec<_> = ErrorCode::all()
+        .iter()
+        .map(|code| format!("{:?} {}", code, code))
+        .collect();
+
+    assert_eq!(
+        strings,
+        vec![
+            "UnclosedComment FM1001",
+            "UnclosedString FM1002",
+            "UnexpectedCharacter FM1003",
+            "InvalidEscape FM1004",
+            "MalformedNumber FM1005",
+            "EmptyTokens FM2001",
+            "NoEof FM2002",
+            "UnexpectedToken FM2003",
+            "ExpectedEnd FM2004",
+            "ExpectedDo FM2005",
+            "ExpectedThen FM2006",
+            "ExpectedUntil FM2007",
+            "ExpectedIn FM2008",
+            "ExpectedEquals FM2009",
+            "ExpectedLeftParen FM2010",
+            "ExpectedRightParen FM2011",
+            "ExpectedRightBracket FM2012",
+            "ExpectedRightBrace FM2013",
+            "ExpectedComma FM2014",
+            "ExpectedExpression FM2015",
+            "ExpectedCondition FM2016",
+            "ExpectedValue FM2017",
+            "ExpectedValues FM2018",
+            "ExpectedBlock FM2019",
+            "ExpectedKey FM2020",
+            "ExpectedName FM2021",
+            "ExpectedNames FM2022",
+            "ExpectedMethod FM2023",
+            "ExpectedMethodName FM2024",
+            "ExpectedArgs FM2025",
+            "ExpectedArguments FM2026",
+            "ExpectedFunctionBody FM2027",
+            "ExpectedFunctionName FM2028",
+            "ExpectedStartExpression FM2029",
+            "ExpectedEndExpression FM2030",
+            "ExpectedLimitExpression FM2031",
+            "ExpectedReturnValues FM2032",
+            "TrailingCharacter FM2033",
+            "LeftoverToken FM2034",
+            "RecursionLimitExceeded FM2035",
+            "Cancelled FM2036",
+            "ReservedKeywordAsName FM2037",
+            "EofBeforeEnd FM2038",
+            "TokenOutOfOrder FM2039",
+            "NotAToken FM2040",
+            "TokenNotInAst FM2041",
+        ]
+    );
+}
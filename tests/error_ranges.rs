@@ -0,0 +1,76 @@
+use full_moon::{ast::AstError, parse, Error};
+
+fn error(code: &str) -> AstError<'_> {
+    match parse(code) {
+        Err(Error::AstError(error)) => error,
+        other => panic!("{:?} didn't fail to parse: {:?}", code, other),
+    }
+}
+
+// The text of the code in the error's range, and in its opening range if it has one
+fn ranges(code: &str) -> (&str, Option<&str>) {
+    let error = error(code);
+    let text = |(start, end): (
+        full_moon::tokenizer::Position,
+        full_moon::tokenizer::Position,
+    )| { &code[start.bytes()..end.bytes()] };
+
+    (
+        text(error.range().expect("no range")),
+        error.opening_range().map(text),
+    )
+}
+
+#[test]
+fn test_error_ranges() {
+    assert_eq!(ranges("local x = ]"), ("]", None));
+    assert_eq!(ranges("local 1 = 2"), ("1", None));
+    assert_eq!(ranges("x = 1 +"), ("", None));
+    assert_eq!(ranges("call(1 2)"), ("2", Some("(")));
+    assert_eq!(ranges("x = (1 + 2"), ("", Some("(")));
+    assert_eq!(ranges("x = t[1"), ("", Some("[")));
+    assert_eq!(ranges("x = { 1, 2 return"), ("return", Some("{")));
+    assert_eq!(ranges("x = { [1 = 2 }"), ("=", Some("[")));
+    assert_eq!(ranges("x = 'long string' )"), (")", None));
+}
+
+#[test]
+fn test_error_opening_ranges() {
+    assert_eq!(ranges("do\ncall()"), ("", Some("do")));
+    assert_eq!(ranges("while x do call() until"), ("until", Some("while")));
+    assert_eq!(ranges("for i = 1, 2 do"), ("", Some("for")));
+    assert_eq!(ranges("for k, v in pairs(t) do"), ("", Some("for")));
+    assert_eq!(ranges("if x then elseif y then else"), ("", Some("if")));
+    assert_eq!(ranges("repeat x() end"), ("end", Some("repeat")));
+    assert_eq!(
+        ranges("local function f() return 1"),
+        ("", Some("function"))
+    );
+    assert_eq!(ranges("function t.f(a, b end"), ("end", Some("(")));
+    assert_eq!(ranges("x = function() x = 1"), ("", Some("function")));
+}
+
+#[test]
+fn test_error_nested_opening() {
+    // The error is about the block that wasn't closed, not the ones closed inside of it
+    let code = "do\n  while x do\n    call()\n  end";
+    let nested = error(code);
+    let (start, end) = nested.opening_range().unwrap();
+    assert_eq!((start.bytes(), end.bytes()), (0, 2));
+    assert_eq!(
+        nested
+            .range()
+            .map(|(start, end)| (start.bytes(), end.bytes())),
+        Some((code.len(), code.len()))
+    );
+
+    let (start, _) = error("if x then\n  call(\nend").opening_range().unwrap();
+    assert_eq!(start.bytes(), 16);
+}
+
+#[test]
+fn test_error_display_opening() {
+    assert!(error("do call()")
+        .to_string()
+        .contains("\nto close `do` on line 1, character 1\n"));
+}
@@ -0,0 +1,65 @@
+use full_moon::{parse, Error};
+use std::error::Error as _;
+
+#[test]
+fn test_error_display() {
+    assert_eq!(
+        parse("local x = ]").unwrap_err().to_string(),
+        "unexpected token at line 1, character 11\nfound `]`\nadditional information: expected expression\nexpected an expression"
+    );
+
+    assert_eq!(
+        parse("while x do").unwrap_err().to_string(),
+        "unexpected end of code at line 1, character 11\nadditional information: expected 'end'\nto close `while` on line 1, character 1\nexpected one of `break`, `end`, `return` or a statement"
+    );
+
+    assert_eq!(
+        parse("x = 'unclosed").unwrap_err().to_string(),
+        "unclosed string at line 1, character 5"
+    );
+
+    assert_eq!(
+        parse("x = $").unwrap_err().to_string(),
+        "unexpected character at line 1, character 5\nfound `$`"
+    );
+}
+
+#[test]
+fn test_error_message_and_position() {
+    for code in &["local x = ]", "while x do", "x = 'unclosed", "x = $"] {
+        let error = parse(code).unwrap_err();
+        let position = error.position().unwrap();
+
+        assert_eq!(
+            error.to_string().lines().next().unwrap(),
+            format!(
+                "{} at line {}, character {}",
+                error.message(),
+                position.line(),
+                position.character()
+            )
+        );
+
+        // The inner errors are displayed the same way
+        let inner = match &error {
+            Error::AstError(error) => error.to_string(),
+            Error::TokenizerError(error) => error.to_string(),
+            Error::TokensError { .. } => unreachable!(),
+        };
+        assert_eq!(inner, error.to_string());
+    }
+
+    let error = parse("x = 1 end").unwrap_err();
+    assert_eq!(error.message(), "unexpected token");
+    assert_eq!(error.position().unwrap().bytes(), 6);
+}
+
+#[test]
+fn test_error_source() {
+    // The errors are displayed as the error in them, so they aren't their source too
+    let error = parse("x = 'unclosed").unwrap_err();
+    assert_eq!(error.to_string(), "unclosed string at line 1, character 5");
+    assert!(error.source().is_none());
+
+    assert!(parse("local x = ]").unwrap_err().source().is_none());
+}
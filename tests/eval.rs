@@ -0,0 +1,175 @@
+use full_moon::{
+    ast::{Expression, LastStmt},
+    eval::{literal_value, literal_value_with, return_value, EvalConfig, LuaValue},
+    parse,
+};
+use pretty_assertions::assert_eq;
+
+// The value of `return <expression>`, or the line and character of the part that isn't a literal
+fn value_with(expression: &str, config: &EvalConfig) -> Result<LuaValue, (usize, usize)> {
+    let code = format!("return {}", expression);
+    let ast = parse(&code).unwrap();
+    let expression: &Expression = match ast.nodes().last_stmts() {
+        Some(LastStmt::Return(returns)) => returns.returns().iter().next().unwrap(),
+        _ => unreachable!(),
+    };
+
+    literal_value_with(expression, config).map_err(|error| {
+        let (start, _) = error.range();
+        (start.line(), start.character())
+    })
+}
+
+fn value(expression: &str) -> Result<LuaValue, (usize, usize)> {
+    value_with(expression, &EvalConfig::default())
+}
+
+fn folded(expression: &str) -> Result<LuaValue, (usize, usize)> {
+    value_with(
+        expression,
+        &EvalConfig {
+            fold_operators: true,
+        },
+    )
+}
+
+fn string(value: &str) -> LuaValue {
+    LuaValue::String(value.as_bytes().to_vec())
+}
+
+#[test]
+fn test_literals() {
+    assert_eq!(value("nil"), Ok(LuaValue::Nil));
+    assert_eq!(value("true"), Ok(LuaValue::Bool(true)));
+    assert_eq!(value("false"), Ok(LuaValue::Bool(false)));
+    assert_eq!(value("0x10"), Ok(LuaValue::Number(16.0)));
+    assert_eq!(value("-1.5"), Ok(LuaValue::Number(-1.5)));
+    assert_eq!(value("(((2)))"), Ok(LuaValue::Number(2.0)));
+    assert_eq!(value("'a\\tb'"), Ok(string("a\tb")));
+    assert_eq!(value("[[\nlong]]"), Ok(string("long")));
+}
+
+#[test]
+fn test_tables() {
+    let value = value("{ 1, nil, name = 'x', [10] = true, nested = { 'a' }, 2 }").unwrap();
+    let table = value.as_table().unwrap();
+
+    assert_eq!(
+        table.array(),
+        &[LuaValue::Number(1.0), LuaValue::Nil, LuaValue::Number(2.0)]
+    );
+    assert_eq!(table.field("name"), Some(&string("x")));
+    assert_eq!(
+        table.get(&LuaValue::Number(10.0)),
+        Some(&LuaValue::Bool(true))
+    );
+    assert_eq!(
+        table.get(&LuaValue::Number(3.0)),
+        Some(&LuaValue::Number(2.0))
+    );
+    assert_eq!(table.get(&LuaValue::Number(4.0)), None);
+    assert_eq!(
+        table
+            .field("nested")
+            .and_then(LuaValue::as_table)
+            .map(|nested| nested.array().to_vec()),
+        Some(vec![string("a")])
+    );
+}
+
+#[test]
+fn test_keys() {
+    let value = value("{ a = 1, b = 2, a = 3, ['b'] = nil, c = nil, [1.0] = 'x' }").unwrap();
+    assert_eq!(
+        value.as_table().unwrap().map(),
+        &[
+            (string("a"), LuaValue::Number(3.0)),
+            (LuaValue::Number(1.0), string("x")),
+        ]
+    );
+
+    // Lua can't set these keys, and `0/0` is the only way to write NaN
+    assert_eq!(folded("{ [nil] = 1 }"), Err((1, 11)));
+    assert_eq!(folded("{ [0/0] = 1 }"), Err((1, 11)));
+}
+
+#[test]
+fn test_not_literal() {
+    assert_eq!(value("{ 1, f() }"), Err((1, 13)));
+    assert_eq!(value("{ x = { y = z } }"), Err((1, 20)));
+    assert_eq!(value("{ [k] = 1 }"), Err((1, 11)));
+    assert_eq!(value("function() end"), Err((1, 8)));
+    assert_eq!(value("..."), Err((1, 8)));
+    assert_eq!(value("-x"), Err((1, 8)));
+    assert_eq!(value("-(1)"), Err((1, 8)));
+    assert_eq!(value("60 * 60"), Err((1, 8)));
+    assert_eq!(value("not true"), Err((1, 8)));
+}
+
+#[test]
+fn test_fold_operators() {
+    assert_eq!(folded("60 * 60"), Ok(LuaValue::Number(3600.0)));
+    assert_eq!(folded("'a' .. 'b'"), Ok(string("ab")));
+    assert_eq!(folded("-(1)"), Ok(LuaValue::Number(-1.0)));
+    assert_eq!(folded("not nil"), Ok(LuaValue::Bool(true)));
+    assert_eq!(
+        folded("{ timeout = 2 ^ 3 }")
+            .unwrap()
+            .as_table()
+            .unwrap()
+            .field("timeout"),
+        Some(&LuaValue::Number(8.0))
+    );
+
+    // The first operand that isn't a literal, or the whole operation if they all are
+    assert_eq!(folded("1 + x * 2"), Err((1, 12)));
+    assert_eq!(folded("1 + (2 + y)"), Err((1, 17)));
+    assert_eq!(folded("1 + 'x'"), Err((1, 8)));
+}
+
+#[test]
+fn test_return_value() {
+    let ast = parse(
+        "local unused = require('x')
+
+-- The settings
+return {
+    name = \"server\",
+    ports = { 80, 443 },
+}, 'ignored'",
+    )
+    .unwrap();
+    let value = return_value(&ast).unwrap().unwrap();
+    let table = value.as_table().unwrap();
+    assert_eq!(
+        table.field("name").and_then(LuaValue::as_str),
+        Some("server")
+    );
+    assert_eq!(
+        table
+            .field("ports")
+            .and_then(LuaValue::as_table)
+            .unwrap()
+            .array(),
+        &[LuaValue::Number(80.0), LuaValue::Number(443.0)]
+    );
+
+    assert_eq!(return_value(&parse("return").unwrap()), Ok(None));
+    assert_eq!(return_value(&parse("local x = 1").unwrap()), Ok(None));
+
+    let error = return_value(&parse("return { x }").unwrap()).unwrap_err();
+    assert_eq!(error.range().0.character(), 10);
+    assert_eq!(
+        error.to_string(),
+        "the expression at line 1, character 10 isn't a literal"
+    );
+}
+
+#[test]
+fn test_literal_value() {
+    let ast = parse("return { 'a' }").unwrap();
+    if let Some(LastStmt::Return(returns)) = ast.nodes().last_stmts() {
+        let value = literal_value(returns.returns().iter().next().unwrap()).unwrap();
+        assert_eq!(value.as_table().unwrap().array(), &[string("a")]);
+    }
+}
@@ -0,0 +1,94 @@
+use full_moon::{
+    ast::{AstError, ExpectedItem},
+    parse,
+    tokenizer::Symbol,
+    Error,
+};
+
+fn error(code: &str) -> AstError<'_> {
+    match parse(code) {
+        Err(Error::AstError(error)) => error,
+        other => panic!("{:?} didn't fail to parse: {:?}", code, other),
+    }
+}
+
+fn expected(code: &str) -> Vec<ExpectedItem> {
+    match error(code) {
+        AstError::UnexpectedToken { expected, .. } => expected,
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+// The line of the error message saying what was expected
+fn message(code: &str) -> String {
+    error(code).to_string().lines().last().unwrap().to_owned()
+}
+
+#[test]
+fn test_expected_items() {
+    use ExpectedItem::{Eof, Expression, Identifier, Statement};
+
+    assert_eq!(
+        expected("call(a b)"),
+        vec![
+            ExpectedItem::Symbol(Symbol::Comma),
+            ExpectedItem::Symbol(Symbol::RightParen)
+        ]
+    );
+    assert_eq!(
+        expected("call(]"),
+        vec![ExpectedItem::Symbol(Symbol::RightParen), Expression]
+    );
+    assert_eq!(expected("local x = ]"), vec![Expression]);
+    assert_eq!(expected("if x ]"), vec![ExpectedItem::Symbol(Symbol::Then)]);
+    assert_eq!(
+        expected("local 1"),
+        vec![ExpectedItem::Symbol(Symbol::Function), Identifier]
+    );
+    assert_eq!(
+        expected("t = { 1 2 }"),
+        vec![
+            ExpectedItem::Symbol(Symbol::Comma),
+            ExpectedItem::Symbol(Symbol::RightBrace),
+            ExpectedItem::Symbol(Symbol::Semicolon)
+        ]
+    );
+    assert_eq!(
+        expected("function f()\n"),
+        vec![
+            ExpectedItem::Symbol(Symbol::Break),
+            ExpectedItem::Symbol(Symbol::End),
+            ExpectedItem::Symbol(Symbol::Return),
+            Statement
+        ]
+    );
+    assert_eq!(
+        expected("return 1 2"),
+        vec![ExpectedItem::Symbol(Symbol::Comma), Eof]
+    );
+}
+
+#[test]
+fn test_expected_items_skip_operators() {
+    // Operators and calls could always follow a value, so they aren't listed
+    assert_eq!(expected("x = a b"), expected("x = 1 b"));
+    assert_eq!(
+        expected("if a.b ]"),
+        vec![ExpectedItem::Symbol(Symbol::Then)]
+    );
+}
+
+#[test]
+fn test_expected_items_message() {
+    assert_eq!(message("if x ]"), "expected `then`");
+    assert_eq!(message("call(a b)"), "expected one of `,` or `)`");
+    assert_eq!(message("call(a, ]"), "expected an expression");
+    assert_eq!(
+        message("while x do y( end"),
+        "expected one of `)` or an expression"
+    );
+    assert_eq!(
+        message("x = 1\nend"),
+        "expected one of `break`, `return`, `,`, a statement or the end of the code"
+    );
+}
@@ -0,0 +1,274 @@
+use full_moon::{
+    parse, print,
+    refactor::{extract_function, ExtractError, ExtractReport},
+    tokenizer::Position,
+};
+use pretty_assertions::assert_eq;
+
+// The position of the token that starts at the bytes
+fn position(code: &str, bytes: usize) -> Position {
+    parse(code)
+        .unwrap()
+        .iter_tokens()
+        .map(|token| token.start_position())
+        .find(|position| position.bytes() == bytes)
+        .unwrap()
+}
+
+// The code after extracting the statements from the start of the first text to the start of the second,
+// or to the end if there is no second
+fn extract(
+    code: &str,
+    from: &str,
+    to: Option<&str>,
+    name: &str,
+) -> Result<(String, ExtractReport), ExtractError> {
+    let start = code.find(from).unwrap();
+    let end = to.map_or(code.len(), |to| start + code[start..].find(to).unwrap());
+
+    let mut ast = parse(code).unwrap();
+    let report = extract_function(&mut ast, (position(code, start), position(code, end)), name)?;
+    Ok((print(&ast), report))
+}
+
+#[test]
+fn test_extract() {
+    let code = "local scale = 2
+
+-- Draws the shape
+function M.draw(self, shape)
+    local x, y = shape.x, shape.y
+    local width = shape.width * scale
+    local height = self.height
+    print(width)
+    return x + y + width + height
+end";
+    let (extracted, report) =
+        extract(code, "local width", Some("\n    return"), "measure").unwrap();
+    assert_eq!(
+        extracted,
+        "local scale = 2
+
+local function measure(self, shape)
+    local width = shape.width * scale
+    local height = self.height
+    print(width)
+    return width, height
+end
+
+-- Draws the shape
+function M.draw(self, shape)
+    local x, y = shape.x, shape.y
+    local width, height = measure(self, shape)
+    return x + y + width + height
+end"
+    );
+    assert_eq!(report.parameters(), ["self", "shape"]);
+    assert_eq!(report.returns(), ["width", "height"]);
+
+    // The edits are in the code from before
+    let edits: Vec<(usize, usize)> = report.edits().iter().map(|edit| edit.range()).collect();
+    let start = code.find("local width").unwrap();
+    let end = code.find("\n    return").unwrap();
+    assert_eq!(edits, vec![(17, 17), (start, end)]);
+    assert_eq!(
+        report.edits()[1].replacement(),
+        "local width, height = measure(self, shape)"
+    );
+}
+
+#[test]
+fn test_methods_and_expressions() {
+    // The implicit `self` of a method is a parameter too, and functions in expressions are declared before
+    // the statements they're in
+    let code = "M.f = setmetatable({}, {
+    __index = function(t, k)
+        print(k)
+    end,
+})";
+    let (extracted, report) = extract(code, "print(k)", Some("\n    end"), "log").unwrap();
+    assert_eq!(
+        extracted,
+        "local function log(k)
+    print(k)
+end
+
+M.f = setmetatable({}, {
+    __index = function(t, k)
+        log(k)
+    end,
+})"
+    );
+    assert_eq!(report.parameters(), ["k"]);
+
+    let code = "function M:f()\n    self.x = 1\nend";
+    let (extracted, _) = extract(code, "self.x", Some("\nend"), "set").unwrap();
+    assert_eq!(
+        extracted,
+        "local function set(self)\n    self.x = 1\nend\n\nfunction M:f()\n    set(self)\nend"
+    );
+}
+
+#[test]
+fn test_top_level() {
+    // Outside of a function, the locals before the statements can be used in the new function as they are
+    let code = "local a = 1\nprint(a)\nlocal b = a\nprint(b)";
+    let (extracted, report) = extract(code, "print(a)", Some("\nprint(b)"), "f").unwrap();
+    assert_eq!(
+        extracted,
+        "local a = 1\nlocal function f()\n    print(a)\n    local b = a\n    return b\nend\n\nlocal b = f()\nprint(b)"
+    );
+    assert!(report.parameters().is_empty());
+}
+
+#[test]
+fn test_varargs_and_loops() {
+    let code = "local function f(...)
+    for i = 1, 10 do
+        if i > 5 then break end
+    end
+    while true do
+        print(...)
+        break
+    end
+end";
+    let (extracted, report) = extract(code, "for i", Some("\nend"), "loops").unwrap();
+    assert_eq!(report.parameters(), ["..."]);
+    assert_eq!(
+        extracted,
+        "local function loops(...)
+    for i = 1, 10 do
+        if i > 5 then break end
+    end
+    while true do
+        print(...)
+        break
+    end
+end
+
+local function f(...)
+    loops(...)
+end"
+    );
+}
+
+#[test]
+fn test_nested_indentation() {
+    let code =
+        "local function f(x)\n\tif x then\n\t\tprint(x)\n\t\tprint([[\n  long]])\n\tend\nend";
+    let (extracted, _) = extract(code, "print(x)", Some("\n\tend"), "show").unwrap();
+    assert_eq!(
+        extracted,
+        "local function show(x)\n\tprint(x)\n\tprint([[\n  long]])\nend\n\nlocal function f(x)\n\tif x then\n\t\tshow(x)\n\tend\nend"
+    );
+}
+
+#[test]
+fn test_not_statements() {
+    let code = "local function f()\n    local x = 1\n    print(x)\nend";
+    assert_eq!(
+        extract(code, "x = 1", Some("\n    print"), "g").unwrap_err(),
+        ExtractError::NotStatements
+    );
+    assert_eq!(
+        extract(code, "local x", Some("(x)"), "g").unwrap_err(),
+        ExtractError::NotStatements
+    );
+    assert_eq!(
+        extract(code, "local x", Some("\nend"), "g").map(|(code, _)| code),
+        Ok("local function g()\n    local x = 1\n    print(x)\nend\n\nlocal function f()\n    g()\nend".to_owned())
+    );
+
+    // From a block into the one around it
+    let code = "do\n    print(1)\nend\nprint(2)";
+    assert_eq!(
+        extract(code, "print(1)", None, "g").unwrap_err(),
+        ExtractError::NotStatements
+    );
+}
+
+#[test]
+fn test_escapes() {
+    let code = "local function f(t)\n    for _, v in t do\n        print(v)\n        if v then break end\n    end\nend";
+    match extract(code, "print(v)", Some("\n    end"), "g") {
+        Err(ExtractError::Escapes { position }) => {
+            assert_eq!(position.bytes(), code.find("break").unwrap())
+        }
+        other => panic!("expected an escape, got {:?}", other),
+    }
+
+    let code = "local function f()\n    print(1)\n    return 2\nend";
+    assert!(matches!(
+        extract(code, "print(1)", Some("\nend"), "g"),
+        Err(ExtractError::Escapes { .. })
+    ));
+
+    let code = "local function f()\n    if x then return end\n    print(1)\nend";
+    assert!(matches!(
+        extract(code, "if x", Some("\nend"), "g"),
+        Err(ExtractError::Escapes { .. })
+    ));
+
+    // A return in a function in the statements is fine
+    let code = "local function f()\n    local g = function() return 1 end\n    print(g)\nend";
+    assert!(extract(code, "local g", Some("\nend"), "h").is_ok());
+}
+
+#[test]
+fn test_written() {
+    let code = "local function f(x)\n    x = x + 1\n    print(x)\nend";
+    match extract(code, "x = x", Some("\n    print"), "g") {
+        Err(ExtractError::Written { name, position }) => {
+            assert_eq!(name, "x");
+            assert_eq!(position.bytes(), 24);
+        }
+        other => panic!("expected written, got {:?}", other),
+    }
+
+    // A local of the new function kept by a function in it wouldn't be the one set after the call
+    let code = "local function f()\n    local x = 1\n    local get = function() return x end\n    x = 2\n    return get()\nend";
+    assert!(matches!(
+        extract(code, "local x", Some("\n    x = 2"), "g"),
+        Err(ExtractError::Written { .. })
+    ));
+
+    // Locals from outside of the function are shared
+    let code = "local count = 0\nlocal function f()\n    count = count + 1\nend";
+    assert!(extract(code, "count = count", Some("\nend"), "g").is_ok());
+}
+
+#[test]
+fn test_names() {
+    let code = "local function f()\n    print(1)\nend\nreturn g";
+    assert_eq!(
+        extract(code, "print(1)", Some("\nend"), "end").unwrap_err(),
+        ExtractError::Keyword {
+            name: "end".to_owned()
+        }
+    );
+    assert_eq!(
+        extract(code, "print(1)", Some("\nend"), "a.b").unwrap_err(),
+        ExtractError::InvalidName {
+            name: "a.b".to_owned()
+        }
+    );
+
+    match extract(code, "print(1)", Some("\nend"), "g") {
+        Err(ExtractError::Conflict { name, position }) => {
+            assert_eq!(name, "g");
+            assert_eq!(position.bytes(), code.len() - 1);
+        }
+        other => panic!("expected a conflict, got {:?}", other),
+    }
+
+    // The Ast is left as it was
+    let mut ast = parse(code).unwrap();
+    let range = (position(code, 23), position(code, 31));
+    assert!(extract_function(&mut ast, range, "g").is_err());
+    assert_eq!(print(&ast), code);
+
+    assert_eq!(
+        ExtractError::NotStatements.to_string(),
+        "the range isn't a run of whole statements in one block"
+    );
+}
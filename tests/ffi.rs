@@ -0,0 +1,113 @@
+#![cfg(feature = "ffi")]
+use full_moon::ffi::*;
+use std::{os::raw::c_char, ptr, slice};
+
+// The text of a buffer, freeing the buffer
+unsafe fn take(buffer: FullMoonBuffer) -> Option<String> {
+    if buffer.data.is_null() {
+        return None;
+    }
+
+    let bytes = slice::from_raw_parts(buffer.data as *const u8, buffer.len + 1);
+    assert_eq!(bytes[buffer.len], 0, "buffers end with a nul byte");
+    let text = String::from_utf8(bytes[..buffer.len].to_vec()).unwrap();
+    fullmoon_free_buffer(buffer);
+    Some(text)
+}
+
+unsafe fn parse(code: &str) -> *mut FullMoonAst {
+    fullmoon_parse(code.as_ptr() as *const c_char, code.len())
+}
+
+#[test]
+fn test_round_trip() {
+    unsafe {
+        for code in &[
+            "local x = 1 -- comment\nprint(x)",
+            "local s = '\0 and 😀'",
+            "",
+        ] {
+            let ast = parse(code);
+            assert!(!ast.is_null(), "{:?}", code);
+            assert_eq!(take(fullmoon_last_error()), None);
+            assert_eq!(take(fullmoon_print(ast)).as_deref(), Some(*code));
+            fullmoon_free_ast(ast);
+        }
+    }
+}
+
+#[test]
+fn test_code_without_nul() {
+    let code = "local x = 1garbage";
+    unsafe {
+        let ast = fullmoon_parse(code.as_ptr() as *const c_char, 11);
+        assert_eq!(take(fullmoon_print(ast)).as_deref(), Some("local x = 1"));
+        fullmoon_free_ast(ast);
+    }
+}
+
+#[test]
+fn test_json() {
+    unsafe {
+        let ast = parse("x = 1");
+        let json: serde_json::Value =
+            serde_json::from_str(&take(fullmoon_to_json(ast)).unwrap()).unwrap();
+        assert_eq!(
+            json["block"]["children"]["stmts"][0][0]["variant"],
+            "Assignment"
+        );
+        fullmoon_free_ast(ast);
+    }
+}
+
+#[test]
+fn test_errors() {
+    unsafe {
+        assert!(parse("local x = 1 +").is_null());
+        let error = take(fullmoon_last_error()).unwrap();
+        assert!(
+            error.starts_with("unexpected end of code at line 1"),
+            "{}",
+            error
+        );
+        // The error stays until another function is called
+        assert_eq!(take(fullmoon_last_error()), Some(error));
+
+        let invalid = [b'x', b' ', b'=', b' ', 0xFF];
+        assert!(fullmoon_parse(invalid.as_ptr() as *const c_char, invalid.len()).is_null());
+        assert!(take(fullmoon_last_error())
+            .unwrap()
+            .starts_with("the code isn't valid UTF-8"));
+
+        assert!(fullmoon_parse(ptr::null(), 1).is_null());
+        assert!(take(fullmoon_last_error()).is_some());
+
+        assert!(take(fullmoon_print(ptr::null())).is_none());
+        assert_eq!(
+            take(fullmoon_last_error()).as_deref(),
+            Some("the Ast is null")
+        );
+        assert!(take(fullmoon_to_json(ptr::null())).is_none());
+
+        // Empty code can be null, and a successful call clears the error
+        let ast = fullmoon_parse(ptr::null(), 0);
+        assert!(!ast.is_null());
+        assert_eq!(take(fullmoon_last_error()), None);
+        fullmoon_free_ast(ast);
+
+        fullmoon_free_ast(ptr::null_mut());
+        fullmoon_free_buffer(fullmoon_last_error());
+    }
+}
+
+#[test]
+fn test_errors_are_per_thread() {
+    unsafe {
+        assert!(parse("local = 1").is_null());
+        let other = std::thread::spawn(|| take(fullmoon_last_error()))
+            .join()
+            .unwrap();
+        assert_eq!(other, None);
+        assert!(take(fullmoon_last_error()).is_some());
+    }
+}
@@ -0,0 +1,102 @@
+use full_moon::{
+    parse, print,
+    transform::{fold_constants, fold_constants_with, FoldConfig},
+};
+use pretty_assertions::assert_eq;
+
+fn folded(code: &str) -> String {
+    let mut ast = parse(code).unwrap();
+    fold_constants(&mut ast);
+    print(&ast)
+}
+
+#[test]
+fn test_arithmetic() {
+    assert_eq!(
+        folded("local x = 1 + 2 * 3, 7 / 2, -7 % 3, 2 ^ 3 ^ 2, -2 ^ 2 + 5, 0x10 + 1, 1e300 * 10"),
+        "local x = 7, 3.5, 2, 512, 1, 17, 1e301"
+    );
+
+    // The operators on either side of a fold keep their precedence
+    assert_eq!(
+        folded("local x = a * 2 + 3, 2 + 3 * a, a .. 1 + 2, -(2 + 3), (1 + 2) * a, (1) * a, ((4))"),
+        "local x = a * 2 + 3, 2 + 3 * a, a .. 3, -5, 3 * a, (1) * a, ((4))"
+    );
+
+    // Results without a literal of their own aren't folded
+    assert_eq!(
+        folded("local x = 1 - 2, 1 / 0, 0 / 0, -0 * 1, a - (0 - 1), 2 ^ (0 - 1)"),
+        "local x = 1 - 2, 1 / 0, 0 / 0, -0 * 1, a - (0 - 1), 0.5"
+    );
+}
+
+#[test]
+fn test_strings_and_comparisons() {
+    assert_eq!(
+        folded(
+            r#"local x = "a" .. 'b' .. [[c]], "\"" .. "\n\0" .. "1", #"four", "a" == 'a', 1 == "1", nil ~= false"#
+        ),
+        r#"local x = "abc", "\"\n\0001", 4, true, false, true"#
+    );
+
+    assert_eq!(
+        folded(r#"local x = 1 < 2, 2 <= 1, not nil, not 0, "a" < "b", 1 < "2", 1 + "2", #5"#),
+        r#"local x = true, false, true, false, "a" < "b", 1 < "2", 1 + "2", #5"#
+    );
+}
+
+#[test]
+fn test_logical_operators() {
+    assert_eq!(
+        folded(
+            "local x = true and y, false and f(), nil or y, 1 or f(), x or true and y, y and true"
+        ),
+        "local x = y, false, y, 1, x or y, y and true"
+    );
+
+    assert_eq!(
+        folded("local x = true and false or 3, (false or nil) and y, not (1 == 1) or z"),
+        "local x = 3, nil, z"
+    );
+
+    // The operator truncates a call or `...` to one value, so it's kept in front of them
+    assert_eq!(
+        folded("print(nil or f(), false or ..., true and (f()), 1 and t.f())\nreturn true and f()"),
+        "print(nil or f(), false or ..., (f()), 1 and t.f())\nreturn true and f()"
+    );
+}
+
+#[test]
+fn test_number_concatenation() {
+    let code = "local x = 1 .. \"\", 0.1 + 0.2 .. \"\", 1e15 .. \"\", 2 .. 3";
+    assert_eq!(
+        folded(code),
+        "local x = 1 .. \"\", 0.30000000000000004 .. \"\", 1e15 .. \"\", 2 .. 3"
+    );
+
+    let mut ast = parse(code).unwrap();
+    fold_constants_with(
+        &mut ast,
+        &FoldConfig {
+            fold_number_concatenation: true,
+        },
+    );
+    assert_eq!(print(&ast), "local x = \"1\", \"0.3\", \"1e+15\", \"23\"");
+}
+
+#[test]
+fn test_trivia() {
+    // Comments and whitespace before and after the folded code are kept, and the ones inside of it are removed
+    assert_eq!(
+        folded("local x = --[[ before ]] 1 + --[[ inside ]] 2 -- after\nreturn(1 + 1),-- comment\n-(1 + 1)"),
+        "local x = --[[ before ]] 3 -- after\nreturn 2,-- comment\n-2"
+    );
+
+    // The folded code is a literal in the Ast too
+    let mut ast = parse("call(1 + 2, \"a\" .. \"b\", x, true and y)").unwrap();
+    fold_constants(&mut ast);
+    ast.update_positions();
+    let printed = print(&ast);
+    let reparsed = parse(&printed).unwrap();
+    assert_eq!(ast.nodes(), reparsed.nodes());
+}
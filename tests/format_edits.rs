@@ -0,0 +1,108 @@
+use full_moon::{format_edits, parse, print_pretty, Indent, LineEnding, PrettyConfig, TextEdit};
+use std::fs;
+
+// Applies the edits to the code, last to first
+fn apply(code: &str, edits: &[TextEdit]) -> String {
+    let mut code = code.to_owned();
+    for edit in edits.iter().rev() {
+        let (start, end) = edit.range();
+        code.replace_range(start..end, edit.replacement());
+    }
+
+    code
+}
+
+fn summarize(edits: &[TextEdit]) -> Vec<(usize, usize, &str)> {
+    edits
+        .iter()
+        .map(|edit| (edit.range().0, edit.range().1, edit.replacement()))
+        .collect()
+}
+
+#[test]
+fn test_format_edits() {
+    let code = "local function f(a,b)\nreturn a+b end";
+    let edits = format_edits(code, &PrettyConfig::default()).unwrap();
+    assert_eq!(
+        summarize(&edits),
+        vec![
+            (19, 19, " "),
+            (22, 22, "    "),
+            (30, 30, " "),
+            (31, 31, " "),
+            (32, 33, "\n"),
+            (36, 36, "\n"),
+        ]
+    );
+    assert_eq!(
+        apply(code, &edits),
+        "local function f(a, b)\n    return a + b\nend\n"
+    );
+}
+
+#[test]
+fn test_format_edits_formatted() {
+    let code = "local x = 1 -- one\nif x then\n    call(x)\nend\n";
+    assert!(format_edits(code, &PrettyConfig::default())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_format_edits_comments() {
+    // Single line comments lose the `\r` before their line ending
+    let code = "x = 1 -- one\r\ny = 2\r\n";
+    let edits = format_edits(code, &PrettyConfig::default()).unwrap();
+    assert_eq!(summarize(&edits), vec![(12, 13, ""), (19, 20, "")]);
+    assert_eq!(apply(code, &edits), "x = 1 -- one\ny = 2\n");
+}
+
+#[test]
+fn test_format_edits_error() {
+    assert!(format_edits("local x = ", &PrettyConfig::default()).is_err());
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_format_edits_pass_cases() {
+    let configs = vec![
+        PrettyConfig::default(),
+        PrettyConfig {
+            indent: Indent::Tabs,
+            line_ending: LineEnding::CrLf,
+            space_after_commas: false,
+            max_width: 40,
+            ..PrettyConfig::default()
+        },
+    ];
+
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).expect("couldn't parse");
+
+        for config in &configs {
+            let edits = format_edits(&source, config).unwrap();
+            assert!(
+                edits
+                    .windows(2)
+                    .all(|pair| pair[0].range().1 <= pair[1].range().0),
+                "{:?} has overlapping edits",
+                path
+            );
+
+            let pretty = print_pretty(&ast, config);
+            assert_eq!(apply(&source, &edits), pretty, "{:?}", path);
+            assert!(
+                format_edits(&pretty, config).unwrap().is_empty(),
+                "{:?}",
+                path
+            );
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use full_moon::{analysis::function_metrics, parse};
+use pretty_assertions::assert_eq;
+
+// The metrics of a function as its name, cyclomatic complexity, statement count, parameter count,
+// max nesting depth, and line span
+type Metrics = (Option<String>, usize, usize, usize, usize, usize);
+
+fn metrics(code: &str) -> Vec<Metrics> {
+    function_metrics(&parse(code).unwrap())
+        .iter()
+        .map(|metrics| {
+            (
+                metrics.name().map(ToOwned::to_owned),
+                metrics.cyclomatic_complexity(),
+                metrics.statement_count(),
+                metrics.parameter_count(),
+                metrics.max_nesting_depth(),
+                metrics.line_span(),
+            )
+        })
+        .collect()
+}
+
+fn named(name: &str) -> Option<String> {
+    Some(name.to_owned())
+}
+
+#[test]
+fn test_complexity() {
+    let code = "local function process(items, ...)
+    local total = 0
+    for i, item in ipairs(items) do
+        if item.skip and not item.force then
+            break
+        elseif item.value then
+            while item.value > 0 or item.retry do
+                item.value = item.value - 1
+            end
+        else
+            total = total + (item.default or 0)
+        end
+    end
+    local check = function(x) return x and x > 0 end
+    repeat
+        total = total - 1
+    until total < 10 or check(total and 1)
+    return total
+end
+";
+
+    assert_eq!(
+        metrics(code),
+        vec![
+            // 1, the for loop, the if and its `and`, the elseif, the while loop and its `or`,
+            // and the repeat loop and both its `or` and the `and` in the call in its condition.
+            // The `or` in the else block and the `and` in `check` aren't in conditions of `process`.
+            (named("process"), 10, 11, 2, 3, 19),
+            (named("check"), 1, 1, 1, 0, 1),
+        ]
+    );
+}
+
+#[test]
+fn test_names_and_parameters() {
+    let code = "local M = {}
+function M:method(a) for i = 1, a do if i then end end
+end
+M.field = function() end
+local t = { key = function(a, b, c) end }
+call(function() end)
+return function(...) end
+";
+
+    assert_eq!(
+        metrics(code),
+        vec![
+            // The implicit `self` isn't a parameter
+            (named("M:method"), 3, 2, 1, 2, 2),
+            (named("M.field"), 1, 0, 0, 0, 1),
+            (named("key"), 1, 0, 3, 0, 1),
+            (None, 1, 0, 0, 0, 1),
+            (None, 1, 0, 1, 0, 1),
+        ]
+    );
+}
+
+#[test]
+fn test_nested_functions() {
+    // The code of a function in another function only counts for the function it's in
+    let code = "local function outer()
+    local function inner()
+        if x then
+            if y or z then
+                return
+            end
+        end
+    end
+    return inner
+end
+";
+
+    assert_eq!(
+        metrics(code),
+        vec![
+            (named("outer"), 1, 2, 0, 0, 10),
+            (named("inner"), 4, 3, 0, 2, 7),
+        ]
+    );
+
+    let ast = parse(code).unwrap();
+    let metrics = function_metrics(&ast);
+    let (start, end) = metrics[1].range();
+    assert_eq!(
+        &code[start.bytes()..end.bytes()],
+        "function inner()
+        if x then
+            if y or z then
+                return
+            end
+        end
+    end"
+    );
+}
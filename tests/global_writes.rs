@@ -0,0 +1,123 @@
+use full_moon::{
+    analysis::{global_writes, global_writes_with, GlobalWriteKind, GlobalWritesConfig},
+    parse,
+};
+use pretty_assertions::assert_eq;
+
+fn names(code: &str, config: &GlobalWritesConfig) -> Vec<String> {
+    global_writes_with(&parse(code).unwrap(), config)
+        .iter()
+        .map(|write| write.token().to_string())
+        .collect()
+}
+
+#[test]
+fn test_writes() {
+    let code = "local a
+a, b = 1, 2
+function c() end
+function a.d() end
+function e:f() end
+local function g() h = 1 end
+for i = 1, 10 do i = i + 1 end
+t.x, t[1] = 1, 2
+_G.y = 1
+_G = {}
+";
+
+    let ast = parse(code).unwrap();
+    let writes = global_writes(&ast);
+    let summary: Vec<_> = writes
+        .iter()
+        .map(|write| (write.token().to_string(), write.kind()))
+        .collect();
+
+    assert_eq!(
+        summary,
+        vec![
+            ("b".to_owned(), GlobalWriteKind::Assignment),
+            ("c".to_owned(), GlobalWriteKind::FunctionDeclaration),
+            ("h".to_owned(), GlobalWriteKind::Assignment),
+        ]
+    );
+
+    let (start, end) = writes[1].range();
+    assert_eq!(&code[start.bytes()..end.bytes()], "function c() end");
+    assert!(writes[0].function().is_none());
+    assert!(writes[2].function().is_some());
+}
+
+#[test]
+fn test_locals_declared_later() {
+    // The local is declared after the function, so the function sets the global
+    assert_eq!(
+        names(
+            "local function f() x = 1 end\nlocal x\nx = 2",
+            &GlobalWritesConfig::default()
+        ),
+        vec!["x"]
+    );
+}
+
+#[test]
+fn test_allowed() {
+    let config = GlobalWritesConfig {
+        allowed: vec!["MyModule".to_owned()],
+        ..GlobalWritesConfig::default()
+    };
+
+    assert_eq!(
+        names("MyModule = {}\n_G = {}\nother = 1", &config),
+        vec!["_G", "other"]
+    );
+}
+
+#[test]
+fn test_intentional() {
+    let code = "before = 1
+local function f()
+    setfenv(1, {})
+    inside = 1
+    local function g() nested = 1 end
+end
+function f2() sibling = 1 end
+module(\"name\")
+after = 1
+";
+
+    assert_eq!(
+        names(code, &GlobalWritesConfig::default()),
+        vec!["before", "f2", "sibling"]
+    );
+
+    let config = GlobalWritesConfig {
+        include_intentional: true,
+        ..GlobalWritesConfig::default()
+    };
+    let writes = global_writes_with(&parse(code).unwrap(), &config);
+    let summary: Vec<_> = writes
+        .iter()
+        .map(|write| (write.token().to_string(), write.is_intentional()))
+        .collect();
+
+    assert_eq!(
+        summary,
+        vec![
+            ("before".to_owned(), false),
+            ("inside".to_owned(), true),
+            ("nested".to_owned(), true),
+            ("f2".to_owned(), false),
+            ("sibling".to_owned(), false),
+            ("after".to_owned(), true),
+        ]
+    );
+
+    // A local named `module` isn't the global
+    assert_eq!(
+        names(
+            "local module = print\nmodule(\"name\")\nx = 1",
+            &GlobalWritesConfig::default()
+        ),
+        vec!["x"]
+    );
+}
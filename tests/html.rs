@@ -0,0 +1,90 @@
+#![cfg(feature = "html")]
+use full_moon::{parse, print, render_html, HtmlConfig};
+use std::fs;
+
+// The code with every tag taken out and every entity unescaped
+fn strip_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = &rest[rest[start..].find('>').unwrap() + start + 1..];
+    }
+    text.push_str(rest);
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[test]
+fn test_render_html_classes() {
+    let ast = parse("if not x then y = nil + 0x10 .. 'a' end").unwrap();
+    assert_eq!(
+        render_html(&ast, &HtmlConfig::default()),
+        concat!(
+            r#"<span class="fm-keyword">if</span> <span class="fm-keyword">not</span> "#,
+            r#"<span class="fm-identifier">x</span> <span class="fm-keyword">then</span> "#,
+            r#"<span class="fm-identifier">y</span> <span class="fm-operator">=</span> "#,
+            r#"<span class="fm-constant">nil</span> <span class="fm-operator">+</span> "#,
+            r#"<span class="fm-number">0x10</span> <span class="fm-operator">..</span> "#,
+            r#"<span class="fm-string">&#39;a&#39;</span> <span class="fm-keyword">end</span>"#,
+        )
+    );
+}
+
+#[test]
+fn test_render_html_long_brackets() {
+    let ast = parse("local s = [==[ ]] -- not a comment ]==] --[[ a\n\"b\" ]] f()").unwrap();
+    assert_eq!(
+        render_html(&ast, &HtmlConfig::default()),
+        concat!(
+            r#"<span class="fm-keyword">local</span> <span class="fm-identifier">s</span> "#,
+            r#"<span class="fm-operator">=</span> <span class="fm-string">[==[ ]] -- not a comment ]==]</span> "#,
+            "<span class=\"fm-comment\">--[[ a\n&quot;b&quot; ]]</span> ",
+            r#"<span class="fm-identifier">f</span><span class="fm-operator">(</span><span class="fm-operator">)</span>"#,
+        )
+    );
+}
+
+#[test]
+fn test_render_html_line_anchors() {
+    let ast = parse("x = 1\n\n--[[\n]] y = 2\n").unwrap();
+    let config = HtmlConfig {
+        line_anchors: Some("L".to_owned()),
+    };
+
+    assert_eq!(
+        render_html(&ast, &config),
+        concat!(
+            r#"<a id="L1"></a><span class="fm-identifier">x</span> <span class="fm-operator">=</span> "#,
+            "<span class=\"fm-number\">1</span>\n<a id=\"L2\"></a>\n",
+            "<a id=\"L3\"></a><span class=\"fm-comment\">--[[\n<a id=\"L4\"></a>]]</span> ",
+            r#"<span class="fm-identifier">y</span> <span class="fm-operator">=</span> "#,
+            "<span class=\"fm-number\">2</span>\n",
+        )
+    );
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_render_html_pass_cases() {
+    let config = HtmlConfig {
+        line_anchors: Some("line-".to_owned()),
+    };
+
+    for entry in fs::read_dir("./tests/cases/pass").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).expect("couldn't parse");
+
+        assert_eq!(
+            strip_html(&render_html(&ast, &config)),
+            print(&ast),
+            "{:?}",
+            path
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use full_moon::{
+    parse, print,
+    refactor::{inline_local, inline_local_with, InlineConfig, InlineError},
+    tokenizer::Position,
+};
+use pretty_assertions::assert_eq;
+
+// The position of the first token with the text
+fn position(code: &str, text: &str) -> Position {
+    parse(code)
+        .unwrap()
+        .iter_tokens()
+        .find(|token| token.to_string() == text)
+        .unwrap()
+        .start_position()
+}
+
+// The code after inlining the first local with the name
+fn inline_with(code: &str, name: &str, config: &InlineConfig) -> Result<String, InlineError> {
+    let mut ast = parse(code).unwrap();
+    inline_local_with(&mut ast, position(code, name), config)?;
+    Ok(print(&ast))
+}
+
+fn inline(code: &str, name: &str) -> Result<String, InlineError> {
+    inline_with(code, name, &InlineConfig::default())
+}
+
+// The kind of an error and the bytes of its position, for the errors that have one
+fn error_at(code: &str, name: &str) -> (&'static str, usize) {
+    let error = inline(code, name).unwrap_err();
+    let (kind, position) = match &error {
+        InlineError::Written { position } => ("written", position),
+        InlineError::SideEffect { position } => ("side effect", position),
+        InlineError::NewValue { position } => ("new value", position),
+        InlineError::Shadowed { position, .. } => ("shadowed", position),
+        InlineError::Changed { position, .. } => ("changed", position),
+        InlineError::UnsupportedRead { position } => ("unsupported", position),
+        InlineError::MayChange { position } => ("may change", position),
+        _ => panic!("{:?} has no position", error),
+    };
+
+    (kind, position.bytes())
+}
+
+#[test]
+fn test_inline() {
+    let code =
+        "local a, b = 1, 2\nlocal total = a + b\nprint(total, { total }, f(total))\nreturn total";
+    assert_eq!(
+        inline(code, "total").unwrap(),
+        "local a, b = 1, 2\nprint(a + b, { a + b }, f(a + b))\nreturn a + b"
+    );
+
+    // From a read of the local too
+    let mut ast = parse("local y = 2\nprint(y)").unwrap();
+    let read = ast
+        .iter_tokens()
+        .filter(|token| token.to_string() == "y")
+        .nth(1)
+        .unwrap()
+        .start_position();
+    inline_local(&mut ast, read).unwrap();
+    assert_eq!(print(&ast), "print(2)");
+
+    // A local that is never read is removed
+    assert_eq!(inline("local x = a\nreturn", "x").unwrap(), "return");
+}
+
+#[test]
+fn test_statement_placement() {
+    assert_eq!(
+        inline("print(1) local x = 2 print(x)", "x").unwrap(),
+        "print(1) print(2)"
+    );
+    assert_eq!(inline("local x = 2; print(x)", "x").unwrap(), "print(2)");
+    assert_eq!(
+        inline("do\n    local x = 2\n    print(x)\nend", "x").unwrap(),
+        "do\n    print(2)\nend"
+    );
+}
+
+#[test]
+fn test_comments() {
+    let code = "print(1)\n-- The count\n-- of things\nlocal count = n -- never nil\n\nprint(count)";
+    assert_eq!(inline(code, "count").unwrap(), "print(1)\n\nprint(n)");
+
+    let config = InlineConfig {
+        keep_comments: true,
+    };
+    assert_eq!(
+        inline_with(code, "count", &config).unwrap(),
+        "print(1)\n-- The count\n-- of things\n-- never nil\n\nprint(n)"
+    );
+
+    // A comment separated by a blank line isn't about the declaration
+    assert_eq!(
+        inline("-- Header\n\nlocal x = 1\nprint(x)", "x").unwrap(),
+        "-- Header\n\nprint(1)"
+    );
+
+    // The comments of the read are kept around the value
+    assert_eq!(
+        inline("local x = a + b\nprint(--[[ sum ]] x --[[ done ]])", "x").unwrap(),
+        "print(--[[ sum ]] a + b --[[ done ]])"
+    );
+}
+
+#[test]
+fn test_parentheses() {
+    let code = "local sum = a + b\nprint(sum * 2, 2 * sum, sum + 2, 2 - sum, -sum, sum == c, #sum)";
+    assert_eq!(
+        inline(code, "sum").unwrap(),
+        "print((a + b) * 2, 2 * (a + b), a + b + 2, 2 - (a + b), -(a + b), a + b == c, #(a + b))"
+    );
+
+    // `..` and `^` hold the operand on their right more loosely than the one on their left
+    let code = "local s = a .. b\nprint(s .. c, c .. s)";
+    assert_eq!(
+        inline(code, "s").unwrap(),
+        "print((a .. b) .. c, c .. a .. b)"
+    );
+    let code = "local p = -a\nprint(p ^ 2, 2 ^ p, p + 1)";
+    assert_eq!(
+        inline(code, "p").unwrap(),
+        "print((-a) ^ 2, 2 ^ -a, -a + 1)"
+    );
+
+    // Values that are called or indexed need parentheses unless they're names, indexes, or parentheses already
+    let code = "local s = \"x\"\nprint(s:upper(), s.len)";
+    assert_eq!(
+        inline(code, "s").unwrap(),
+        "print((\"x\"):upper(), (\"x\").len)"
+    );
+    let code = "local t = M.x\nt[1] = t.y()";
+    assert_eq!(inline(code, "t").unwrap(), "M.x[1] = M.x.y()");
+    let code = "local f = (a or b)\nf()";
+    assert_eq!(inline(code, "f").unwrap(), "(a or b)()");
+}
+
+#[test]
+fn test_spacing() {
+    assert_eq!(
+        inline("local one = 1\nprint(one..x)", "one").unwrap(),
+        "print(1 ..x)"
+    );
+}
+
+#[test]
+fn test_refused() {
+    let written = "local x = 1\nx = 2\nprint(x)";
+    assert_eq!(error_at(written, "x"), ("written", 12));
+
+    assert_eq!(
+        inline("local x\nprint(x)", "x"),
+        Err(InlineError::NoInitializer)
+    );
+    assert_eq!(
+        inline("local function f() end\nf()", "f"),
+        Err(InlineError::NoInitializer)
+    );
+    assert_eq!(
+        inline("for i = 1, 2 do print(i) end", "i"),
+        Err(InlineError::NoInitializer)
+    );
+    assert_eq!(
+        inline("local x, y = 1, 2\nprint(x)", "x"),
+        Err(InlineError::MultipleValues)
+    );
+    assert_eq!(
+        inline("local x = 1, 2\nprint(x)", "x"),
+        Err(InlineError::MultipleValues)
+    );
+    assert_eq!(inline("return 1", "1"), Err(InlineError::NotAName));
+    assert_eq!(
+        inline("print(x)", "print"),
+        Err(InlineError::Global {
+            name: "print".to_owned()
+        })
+    );
+
+    // The Ast is left as it was
+    let mut ast = parse(written).unwrap();
+    assert!(inline_local(&mut ast, position(written, "x")).is_err());
+    assert_eq!(print(&ast), written);
+}
+
+#[test]
+fn test_side_effects() {
+    let side_effect = ("side effect", 10);
+    assert_eq!(error_at("local x = f()\nprint(x)", "x"), side_effect);
+    assert_eq!(error_at("local x = t:m()\nprint(x)", "x"), side_effect);
+    assert_eq!(
+        error_at("local x = 1 + g(2)\nprint(x)", "x"),
+        ("side effect", 14)
+    );
+    assert_eq!(error_at("local x = ...\nprint(x)", "x"), side_effect);
+
+    // The code of a function only runs when it's called
+    assert_eq!(
+        inline("local f = function(...) return g(...) end\nt.f = f", "f").unwrap(),
+        "t.f = function(...) return g(...) end"
+    );
+}
+
+#[test]
+fn test_new_values() {
+    assert_eq!(
+        inline("local t = {}\nsetmetatable(t, m)", "t").unwrap(),
+        "setmetatable({}, m)"
+    );
+
+    let new_value = ("new value", 10);
+    assert_eq!(error_at("local t = {}\nprint(t, t)", "t"), new_value);
+    assert_eq!(
+        error_at("local t = {}\nfor i = 1, 2 do print(t) end", "t"),
+        new_value
+    );
+    assert_eq!(
+        error_at(
+            "local t = function() end\nreturn function() return t end",
+            "t"
+        ),
+        new_value
+    );
+}
+
+#[test]
+fn test_names() {
+    // Another local with the same name is declared between the declaration and the read
+    let code = "local a = 1\nlocal x = a\ndo\n    local a = 2\n    print(x)\nend";
+    match inline(code, "x") {
+        Err(InlineError::Shadowed { name, position }) => {
+            assert_eq!(name, "a");
+            assert_eq!(position.bytes(), 53);
+        }
+        other => panic!("expected shadowed, got {:?}", other),
+    }
+
+    // A global too
+    let code = "local x = a\ndo\n    local a = 2\n    print(x)\nend";
+    assert!(matches!(
+        inline(code, "x"),
+        Err(InlineError::Shadowed { .. })
+    ));
+
+    // A local the value uses is set after the declaration
+    let code = "local a = 1\nlocal x = a\na = 2\nprint(x)";
+    assert_eq!(error_at(code, "x"), ("changed", 24));
+
+    // Setting it before the declaration is fine, unless it's set in a function
+    let code = "local a = 1\na = 2\nlocal x = a\nprint(x)";
+    assert_eq!(inline(code, "x").unwrap(), "local a = 1\na = 2\nprint(a)");
+    let code = "local a = 1\nlocal function f() a = 2 end\nlocal x = a\nprint(x)";
+    assert!(matches!(
+        inline(code, "x"),
+        Err(InlineError::Changed { .. })
+    ));
+
+    // Only names for the value can be replaced in the name of a function
+    let code = "local t = M\nfunction t.f() end";
+    assert_eq!(inline(code, "t").unwrap(), "function M.f() end");
+    let code = "local t = M.x\nfunction t.f() end";
+    assert_eq!(error_at(code, "t"), ("unsupported", 23));
+}
+
+#[test]
+fn test_globals_and_fields() {
+    // What the value reads is set or could be set by a call before the local is used
+    assert_eq!(
+        error_at("local v = t.x\nt.x = 5\nprint(v)", "v"),
+        ("may change", 14)
+    );
+    assert_eq!(
+        error_at("local v = g\ng = 2\nprint(v)", "v"),
+        ("may change", 12)
+    );
+    assert_eq!(
+        error_at("local v = g\nf()\nprint(v)", "v"),
+        ("may change", 12)
+    );
+    assert_eq!(
+        error_at("local v = t[1]\nfunction t.f() end\nprint(v)", "v"),
+        ("may change", 24)
+    );
+
+    // Up to the end of a loop the local is used in, or anywhere after it's declared when it's used in a function
+    assert_eq!(
+        error_at("local v = g\nwhile c do\n    x = v\n    g = 1\nend", "v"),
+        ("may change", 37)
+    );
+    assert_eq!(
+        error_at("local v = g\nlocal function f() return v end\ng = 1", "v"),
+        ("may change", 44)
+    );
+
+    // Or when the function could be called after the Ast's code is done, even with nothing in it changing the value
+    assert_eq!(
+        error_at("local x = g\nreturn function() return x end", "x"),
+        ("may change", 37)
+    );
+    assert_eq!(
+        error_at("local x = a\nfunction t.f() return x end", "x"),
+        ("may change", 34)
+    );
+
+    // Other globals and locals, and what comes after the last use, don't change it
+    assert_eq!(
+        inline(
+            "local v = g\nh = 2\nlocal t = {}\nt.x = 1\nprint(v)\ng = 3",
+            "v"
+        )
+        .unwrap(),
+        "h = 2\nlocal t = {}\nt.x = 1\nprint(g)\ng = 3"
+    );
+    assert_eq!(
+        inline("local a = 1\nlocal v = a + 1\nf()\nprint(v)", "v").unwrap(),
+        "local a = 1\nf()\nprint(a + 1)"
+    );
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(
+        InlineError::Global {
+            name: "x".to_owned()
+        }
+        .to_string(),
+        "`x` is a global, which has no declaration to inline"
+    );
+    assert_eq!(
+        inline("local x = f()\nprint(x)", "x")
+            .unwrap_err()
+            .to_string(),
+        "the value of the local has a side effect at line 1, character 11"
+    );
+}
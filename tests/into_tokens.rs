@@ -0,0 +1,119 @@
+use full_moon::{
+    ast::{owned::Owned, Ast, AstError},
+    node::Node,
+    parse, print,
+    tokenizer::{self, Token, TokenReference, TokenType},
+    visitors::{VisitMut, VisitorMut},
+};
+use pretty_assertions::assert_eq;
+use std::{borrow::Cow, fs, thread};
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_into_tokens_round_trip() {
+    for entry in fs::read_dir("./tests/cases/pass").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        // Owned so that it can be compared to the Ast made from the tokens, which is 'static
+        let ast = parse(&source).unwrap().owned();
+
+        let tokens = ast.clone().into_tokens();
+        let expected: Vec<_> = tokenizer::tokens(&source)
+            .unwrap()
+            .iter()
+            .map(Owned::owned)
+            .collect();
+        assert_eq!(tokens, expected, "{:?}", path);
+
+        let parsed = Ast::from_tokens(tokens).unwrap();
+        assert_eq!(print(&parsed), source, "{:?}", path);
+        assert_eq!(parsed.nodes(), ast.nodes(), "{:?}", path);
+    }
+}
+
+struct Rename;
+
+impl<'ast> VisitorMut<'ast> for Rename {
+    fn visit_identifier(&mut self, token: &mut TokenReference<'ast>) {
+        token.set_token_type(TokenType::Identifier {
+            identifier: Cow::Borrowed("y"),
+        });
+    }
+}
+
+// Made in a function that drops the code, so the tokens can't borrow it
+fn tokens_of(code: String) -> Vec<Token<'static>> {
+    parse(&code).unwrap().into_tokens()
+}
+
+#[test]
+fn test_tokens_are_detached() {
+    let tokens = tokens_of(String::from("local x = 1"));
+    let tokens = thread::spawn(move || tokens).join().unwrap();
+    assert_eq!(print(&Ast::from_tokens(tokens).unwrap()), "local x = 1");
+
+    // Changing the Ast after taking its tokens doesn't change them
+    let mut ast = parse("local x = 1").unwrap();
+    let tokens = ast.clone().into_tokens();
+    ast.nodes_mut().visit_mut(&mut Rename);
+    assert_eq!(print(&ast), "local y = 1");
+    assert_eq!(print(&Ast::from_tokens(tokens).unwrap()), "local x = 1");
+}
+
+#[test]
+fn test_lex_transform_parse() {
+    // Replace `x` with the call `f(x)`, which adds tokens that aren't from any code
+    let mut tokens = Vec::new();
+    for token in tokenizer::tokens("local y = x + 1").unwrap() {
+        if token.to_string() == "x" {
+            tokens.extend(
+                vec![
+                    TokenType::Identifier {
+                        identifier: Cow::Borrowed("f"),
+                    },
+                    TokenType::Symbol {
+                        symbol: tokenizer::Symbol::LeftParen,
+                    },
+                    TokenType::Identifier {
+                        identifier: Cow::Borrowed("x"),
+                    },
+                    TokenType::Symbol {
+                        symbol: tokenizer::Symbol::RightParen,
+                    },
+                ]
+                .into_iter()
+                .map(Token::from),
+            );
+        } else {
+            tokens.push(token);
+        }
+    }
+
+    let mut ast = Ast::from_tokens(tokens).unwrap();
+    ast.update_positions();
+    assert_eq!(print(&ast), "local y = f(x) + 1");
+
+    let stmt = ast.nodes().iter_stmts().next().unwrap();
+    let (start, end) = stmt.range().unwrap();
+    assert_eq!((start.bytes(), end.bytes()), (0, 18));
+}
+
+#[test]
+fn test_from_tokens_errors() {
+    assert_eq!(Ast::from_tokens(Vec::new()).unwrap_err(), AstError::Empty);
+
+    let mut tokens = parse("local x = 1").unwrap().into_tokens();
+    tokens.pop();
+    assert_eq!(Ast::from_tokens(tokens).unwrap_err(), AstError::NoEof);
+
+    let tokens: Vec<_> = parse("local x = 1")
+        .unwrap()
+        .into_tokens()
+        .into_iter()
+        .filter(|token| token.to_string() != "=")
+        .collect();
+    assert!(matches!(
+        Ast::from_tokens(tokens),
+        Err(AstError::UnexpectedToken { .. })
+    ));
+}
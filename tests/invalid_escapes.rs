@@ -0,0 +1,135 @@
+use full_moon::{
+    parse, parse_all_errors, print,
+    tokenizer::{tokens, tokens_all_errors, TokenizerError, TokenizerErrorType},
+    Error, ErrorCode,
+};
+
+fn error(code: &str) -> TokenizerError {
+    match tokens(code) {
+        Err(error) => error,
+        Ok(_) => panic!("{:?} was tokenized", code),
+    }
+}
+
+// The escape of the error, and the line, character, and byte it's at
+fn invalid_escape(code: &str) -> (String, (usize, usize, usize)) {
+    match error(code).error() {
+        TokenizerErrorType::InvalidEscape { escape, position } => (
+            escape.clone(),
+            (position.line(), position.character(), position.bytes()),
+        ),
+        other => panic!("{:?} didn't have an invalid escape: {:?}", code, other),
+    }
+}
+
+#[test]
+fn test_invalid_escape_position() {
+    let code = format!("x = \"{}\\300{}\"", "a".repeat(100), "b".repeat(100));
+    assert_eq!(invalid_escape(&code), ("\\300".to_owned(), (1, 106, 105)));
+
+    // The escape is found past new lines in the string and before it
+    assert_eq!(
+        invalid_escape("x = 1\nx = 'é\\\n  \\256'"),
+        ("\\256".to_owned(), (3, 3, 17))
+    );
+
+    let error = error("x = '\\999'");
+    assert_eq!(error.position().bytes(), 5);
+    assert_eq!(error.code(), ErrorCode::InvalidEscape);
+}
+
+#[test]
+fn test_valid_escapes() {
+    for code in &[
+        r#"x = "\a\b\f\n\r\t\v\\\"\'""#,
+        r#"x = '\0\65\255\0659'"#,
+        "x = '\\\nb'",
+        // Long strings have no escapes
+        r#"x = [[\300]]"#,
+    ] {
+        assert!(tokens(code).is_ok(), "{:?} wasn't tokenized", code);
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "roblox", ignore)]
+fn test_lua_escapes() {
+    // Lua 5.1 reads any other character after a `\` as the character itself
+    assert!(tokens(r#"x = "\q\x\u{""#).is_ok());
+}
+
+#[test]
+#[cfg_attr(not(feature = "roblox"), ignore)]
+fn test_luau_escapes() {
+    assert!(tokens(r#"x = "\x41\u{1F600}\z  ""#).is_ok());
+
+    assert_eq!(invalid_escape(r#"x = "\q""#).0, "\\q");
+    assert_eq!(invalid_escape(r#"x = "\x4g""#).0, "\\x4");
+    assert_eq!(invalid_escape(r#"x = "\x""#).0, "\\x");
+    assert_eq!(invalid_escape(r#"x = "\u{12""#).0, "\\u{12");
+    assert_eq!(invalid_escape(r#"x = "\u12""#).0, "\\u");
+    assert_eq!(invalid_escape(r#"x = "\u{110000}""#).0, "\\u{110000}");
+}
+
+#[test]
+fn test_invalid_escape_messages() {
+    let decimal = error("x = 'ab\\300'");
+    assert_eq!(decimal.message(), "decimal escape too large");
+    assert_eq!(
+        decimal.to_string(),
+        "decimal escape too large at line 1, character 8\nfound `\\300`"
+    );
+
+    let code = "x = 'ab\\300'";
+    assert_eq!(
+        parse(code).unwrap_err().display_rich(code, None),
+        concat!(
+            "error: decimal escape too large `\\300`\n",
+            " --> 1:8\n",
+            "  |\n",
+            "1 | x = 'ab\\300'\n",
+            "  |        ^^^^\n",
+        )
+    );
+
+    if cfg!(feature = "roblox") {
+        for (code, message) in &[
+            ("x = '\\q'", "invalid escape sequence"),
+            ("x = '\\x4'", "incomplete hexadecimal escape"),
+            ("x = '\\u{1'", "incomplete unicode escape"),
+            ("x = '\\u{FFFFFF}'", "unicode escape too large"),
+        ] {
+            assert_eq!(error(code).message(), *message);
+        }
+    }
+}
+
+#[test]
+fn test_invalid_escape_recovery() {
+    let (tokens, errors) = tokens_all_errors("x = '\\300'\ny = '\\400' .. 1").unwrap();
+    assert_eq!(tokens.len(), 16);
+    assert_eq!(
+        errors
+            .iter()
+            .map(|error| error.position().line())
+            .collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+
+    // Other errors stop tokenizing
+    assert!(tokens_all_errors("x = '\\300'\ny = 'unclosed").is_err());
+
+    let code = "x = '\\300'\nlocal = 1\ny = '\\400'";
+    let (ast, errors) = parse_all_errors(code, 10);
+    assert_eq!(print(&ast.unwrap()), code);
+    assert!(matches!(
+        errors.as_slice(),
+        [
+            Error::TokenizerError(_),
+            Error::AstError(_),
+            Error::TokenizerError(_)
+        ]
+    ));
+
+    assert_eq!(parse_all_errors(code, 2).1.len(), 2);
+}
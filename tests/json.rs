@@ -0,0 +1,114 @@
+#![cfg(feature = "json")]
+use full_moon::{parse, print, JsonConfig, JSON_SCHEMA_VERSION};
+use pretty_assertions::assert_eq;
+use serde_json::Value;
+use std::fs;
+
+// Checks the output against the json file, or writes it if there isn't one yet
+fn check_fixture(file: &str, json: &str) {
+    let path = format!("./tests/cases/json/{}", file);
+    match fs::read_to_string(&path) {
+        Ok(expected) => assert_eq!(json, expected.trim_end(), "{}", path),
+        Err(_) => fs::write(&path, json).expect("couldn't write json file"),
+    }
+}
+
+#[test]
+fn test_json_fixture() {
+    let source = fs::read_to_string("./tests/cases/json/source.lua").unwrap();
+    let ast = parse(&source).unwrap();
+
+    let config = JsonConfig {
+        pretty: true,
+        ..JsonConfig::default()
+    };
+    check_fixture("full.json", &ast.to_json(&config));
+
+    let config = JsonConfig {
+        trivia: false,
+        positions: false,
+        pretty: true,
+    };
+    check_fixture("compact.json", &ast.to_json(&config));
+}
+
+// Every token in the json, as where it starts and its code along with its trivia
+fn tokens(value: &Value, output: &mut Vec<(u64, String)>) {
+    match value {
+        Value::Object(object) if object.get("kind") == Some(&Value::from("Token")) => {
+            let mut code = String::new();
+            for trivia in object["leading_trivia"].as_array().unwrap() {
+                code.push_str(trivia["text"].as_str().unwrap());
+            }
+            code.push_str(object["text"].as_str().unwrap());
+            for trivia in object["trailing_trivia"].as_array().unwrap() {
+                code.push_str(trivia["text"].as_str().unwrap());
+            }
+
+            output.push((object["range"]["start"]["bytes"].as_u64().unwrap(), code));
+        }
+
+        Value::Object(object) => {
+            for value in object.values() {
+                tokens(value, output);
+            }
+        }
+
+        Value::Array(values) => {
+            for value in values {
+                tokens(value, output);
+            }
+        }
+
+        _ => {}
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_json_pass_cases() {
+    for entry in fs::read_dir("./tests/cases/pass").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).unwrap();
+
+        let json: Value = serde_json::from_str(&ast.to_json(&JsonConfig::default())).unwrap();
+        assert_eq!(json["schema"], JSON_SCHEMA_VERSION, "{:?}", path);
+
+        // Every token and piece of trivia is in the json exactly once
+        let mut output = Vec::new();
+        tokens(&json["block"], &mut output);
+        tokens(&json["eof"], &mut output);
+        output.sort();
+        let code: String = output.into_iter().map(|(_, code)| code).collect();
+        assert_eq!(code, print(&ast), "{:?}", path);
+    }
+}
+
+#[test]
+fn test_json_trivia() {
+    let ast = parse("-- before\nlocal x = 1 -- after\n\n-- end\n").unwrap();
+    let json: Value = serde_json::from_str(&ast.to_json(&JsonConfig::default())).unwrap();
+
+    let assignment = &json["block"]["children"]["stmts"][0][0]["children"][0];
+    let local = &assignment["children"]["local_token"];
+    assert_eq!(local["leading_trivia"][0]["text"], "-- before");
+    assert_eq!(local["leading_trivia"][1]["text"], "\n");
+
+    let one = &assignment["children"]["expr_list"][0]["value"]["children"]["value"];
+    let one = &one["children"][0];
+    assert_eq!(one["text"], "1");
+    assert_eq!(
+        one["trailing_trivia"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|trivia| trivia["text"].as_str().unwrap())
+            .collect::<Vec<_>>(),
+        vec![" ", "-- after", "\n"]
+    );
+
+    let eof = &json["eof"];
+    assert_eq!(eof["token_kind"], "Eof");
+    assert_eq!(eof["leading_trivia"].as_array().unwrap().len(), 3);
+}
@@ -0,0 +1,85 @@
+use full_moon::{
+    ast::owned::Owned, node::Node, parse, print, print_pretty, print_with, LineEnding,
+    PrettyConfig, PrintConfig,
+};
+use std::fs;
+
+fn print_line_ending(code: &str, line_ending: LineEnding) -> String {
+    print_with(&parse(code).unwrap(), &PrintConfig { line_ending })
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_line_endings_pass_cases() {
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).expect("couldn't parse");
+
+        assert_eq!(
+            print_line_ending(&source, LineEnding::Preserve),
+            print(&ast)
+        );
+
+        let lf = print_line_ending(&source, LineEnding::Lf);
+        let crlf = print_line_ending(&source, LineEnding::CrLf);
+        for code in &[&lf, &crlf] {
+            let new_ast = parse(code).unwrap();
+            assert!(
+                new_ast.nodes().owned().similar(&ast.nodes().owned()),
+                "{:?} printed different code {:?}",
+                path,
+                code
+            );
+        }
+
+        assert_eq!(print_line_ending(&crlf, LineEnding::Lf), lf, "{:?}", path);
+        assert_eq!(print_line_ending(&lf, LineEnding::CrLf), crlf, "{:?}", path);
+    }
+}
+
+#[test]
+fn test_line_endings_whitespace_and_comments() {
+    let source = "local x = 1 -- one\r\n\r\nlocal y = 2\n  -- two\r\nreturn x\r\n";
+    assert_eq!(
+        print_line_ending(source, LineEnding::Lf),
+        "local x = 1 -- one\n\nlocal y = 2\n  -- two\nreturn x\n"
+    );
+    assert_eq!(
+        print_line_ending(source, LineEnding::CrLf),
+        "local x = 1 -- one\r\n\r\nlocal y = 2\r\n  -- two\r\nreturn x\r\n"
+    );
+    assert_eq!(print_line_ending(source, LineEnding::Preserve), source);
+}
+
+#[test]
+fn test_line_endings_strings_untouched() {
+    let source = "local a = [[x\r\ny]]\nlocal b = \"x\\ny\"\n--[==[ long\r\ncomment ]==]\n";
+    assert_eq!(
+        print_line_ending(source, LineEnding::CrLf),
+        "local a = [[x\r\ny]]\r\nlocal b = \"x\\ny\"\r\n--[==[ long\r\ncomment ]==]\r\n"
+    );
+    assert_eq!(print_line_ending(source, LineEnding::Lf), source);
+}
+
+#[test]
+fn test_line_endings_pretty_preserve() {
+    let config = PrettyConfig {
+        line_ending: LineEnding::Preserve,
+        ..PrettyConfig::default()
+    };
+
+    let ast = parse("local x = 1\r\nlocal y = 2").unwrap();
+    assert_eq!(
+        print_pretty(&ast, &config),
+        "local x = 1\r\nlocal y = 2\r\n"
+    );
+
+    let ast = parse("local x = 1 local y = 2").unwrap();
+    assert_eq!(print_pretty(&ast, &config), "local x = 1\nlocal y = 2\n");
+}
@@ -0,0 +1,114 @@
+use full_moon::{convert_position, parse, LineIndex, PositionEncoding};
+
+const ENCODINGS: [PositionEncoding; 3] = [
+    PositionEncoding::Utf8Bytes,
+    PositionEncoding::Utf16,
+    PositionEncoding::CodePoints,
+];
+
+// The column of the end of the text in the encoding, counted the slow way
+fn column(text: &str, encoding: PositionEncoding) -> usize {
+    match encoding {
+        PositionEncoding::Utf8Bytes => text.len(),
+        PositionEncoding::Utf16 => text.encode_utf16().count(),
+        PositionEncoding::CodePoints => text.chars().count(),
+    }
+}
+
+const SOURCE: &str =
+    "-- 絵文字: 😀👍🏽\nlocal greeting = \"こんにちは 🌏\" .. name\nprint(greeting, \"é\", x)\n";
+
+#[test]
+fn test_line_index_tokens() {
+    let ast = parse(SOURCE).unwrap();
+    let index = ast.line_index();
+    assert_eq!(index.line_count(), 4);
+
+    for token in ast.iter_tokens() {
+        let bytes = token.start_position().bytes();
+        let line = SOURCE[..bytes].matches('\n').count();
+        let line_start = SOURCE[..bytes].rfind('\n').map_or(0, |start| start + 1);
+
+        for &encoding in &ENCODINGS {
+            let expected = column(&SOURCE[line_start..bytes], encoding);
+            assert_eq!(
+                index.line_column(bytes, encoding),
+                Some((line, expected)),
+                "{:?} {:?}",
+                token,
+                encoding
+            );
+            assert_eq!(index.bytes(line, expected, encoding), Some(bytes));
+        }
+    }
+}
+
+#[test]
+fn test_position_character_counts_code_points() {
+    let ast = parse(SOURCE).unwrap();
+    let index = ast.line_index();
+
+    // The end of a token is never at the start of a line, so its line and character are right
+    for token in ast.iter_tokens() {
+        let end = token.end_position();
+        let (line, column) = index
+            .line_column(end.bytes(), PositionEncoding::CodePoints)
+            .unwrap();
+
+        if column > 0 {
+            assert_eq!(end.line(), line + 1, "{:?}", token);
+            assert_eq!(end.character(), column + 1, "{:?}", token);
+        }
+    }
+}
+
+#[test]
+fn test_convert_position() {
+    use PositionEncoding::*;
+
+    // 日 is 3 bytes and 1 UTF-16 code unit, and 😀 is 4 bytes and 2 UTF-16 code units
+    let line = "日本 😀x";
+    assert_eq!(convert_position(line, 0, Utf8Bytes, Utf16), Some(0));
+    assert_eq!(convert_position(line, 6, Utf8Bytes, Utf16), Some(2));
+    assert_eq!(convert_position(line, 11, Utf8Bytes, Utf16), Some(5));
+    assert_eq!(convert_position(line, 11, Utf8Bytes, CodePoints), Some(4));
+    assert_eq!(convert_position(line, 12, Utf8Bytes, CodePoints), Some(5));
+    assert_eq!(convert_position(line, 5, Utf16, Utf8Bytes), Some(11));
+    assert_eq!(convert_position(line, 4, CodePoints, Utf16), Some(5));
+
+    // Inside of a character, or past the end of the line
+    assert_eq!(convert_position(line, 1, Utf8Bytes, Utf16), None);
+    assert_eq!(convert_position(line, 9, Utf8Bytes, Utf16), None);
+    assert_eq!(convert_position(line, 4, Utf16, CodePoints), None);
+    assert_eq!(convert_position(line, 13, Utf8Bytes, Utf16), None);
+    assert_eq!(convert_position(line, 6, Utf16, Utf8Bytes), Some(12));
+    assert_eq!(convert_position(line, 7, Utf16, Utf8Bytes), None);
+
+    for &from in &ENCODINGS {
+        for &to in &ENCODINGS {
+            assert_eq!(convert_position("", 0, from, to), Some(0));
+            assert_eq!(convert_position("ascii", 3, from, to), Some(3));
+        }
+    }
+}
+
+#[test]
+fn test_line_index_lines() {
+    let index = LineIndex::new("a\n😀\n");
+    assert_eq!(index.line_count(), 3);
+    assert_eq!(index.line_column(1, PositionEncoding::Utf16), Some((0, 1)));
+    assert_eq!(index.line_column(2, PositionEncoding::Utf16), Some((1, 0)));
+    assert_eq!(index.line_column(6, PositionEncoding::Utf16), Some((1, 2)));
+    assert_eq!(index.line_column(7, PositionEncoding::Utf16), Some((2, 0)));
+    assert_eq!(index.line_column(8, PositionEncoding::Utf16), None);
+
+    // The new line isn't a column of the line, so there is nothing after it
+    assert_eq!(index.bytes(0, 1, PositionEncoding::Utf16), Some(1));
+    assert_eq!(index.bytes(0, 2, PositionEncoding::Utf16), None);
+    assert_eq!(index.bytes(2, 0, PositionEncoding::Utf16), Some(7));
+    assert_eq!(index.bytes(3, 0, PositionEncoding::Utf16), None);
+    assert_eq!(
+        index.convert(1, 2, PositionEncoding::Utf16, PositionEncoding::CodePoints),
+        Some(1)
+    );
+}
@@ -0,0 +1,80 @@
+use full_moon::tokenizer::{long_comment, long_string, tokens, TokenType};
+
+// The value of the first string in the code, as Lua reads a string in long brackets
+fn long_string_value(code: &str) -> String {
+    let tokens = tokens(code).unwrap();
+    let token_type = tokens[0].token_type();
+    match &*token_type {
+        TokenType::StringLiteral {
+            literal,
+            multi_line: Some(_),
+            ..
+        } => literal.strip_prefix('\n').unwrap_or(literal).to_owned(),
+        other => panic!("expected a string in long brackets, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_long_string_levels() {
+    for (contents, expected) in &[
+        ("", "[[]]"),
+        ("a]b", "[[a]b]]"),
+        ("a]]b", "[=[a]]b]=]"),
+        ("a]=]b]]", "[==[a]=]b]]]==]"),
+        ("a]==]b", "[[a]==]b]]"),
+        ("a]", "[=[a]]=]"),
+        ("a]=", "[[a]=]]"),
+        ("a]=]", "[==[a]=]]==]"),
+        ("x[[y]]", "[=[x[[y]]]=]"),
+    ] {
+        let token_type = long_string(contents);
+        assert_eq!(token_type.to_string(), *expected);
+        assert_eq!(long_string_value(expected), *contents);
+    }
+}
+
+#[test]
+fn test_long_string_leading_new_line() {
+    assert_eq!(long_string("\nx").to_string(), "[[\n\nx]]");
+    assert_eq!(long_string_value("[[\n\nx]]"), "\nx");
+    assert_eq!(long_string("x\n").to_string(), "[[x\n]]");
+}
+
+#[test]
+fn test_long_comment() {
+    assert_eq!(long_comment("a]]").to_string(), "--[=[a]]]=]");
+
+    let code = long_comment(" a]] ]=] ").to_string();
+    let tokens = tokens(&code).unwrap();
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[0].to_string(), code);
+}
+
+#[test]
+fn test_to_long_bracket() {
+    for (code, expected) in &[
+        (r#""hello""#, "[[hello]]"),
+        (r#"'a\'b\"c'"#, r#"[[a'b"c]]"#),
+        (r#""\65\t\\n""#, "[[A\t\\n]]"),
+        (r#""\na""#, "[[\n\na]]"),
+        (
+            r#""line\
+break""#,
+            "[[line\nbreak]]",
+        ),
+        (r#""t[1]]""#, "[=[t[1]]]=]"),
+        ("[==[x]==]", "[==[x]==]"),
+    ] {
+        let tokens = tokens(code).unwrap();
+        let converted = tokens[0].token_type().to_long_bracket().unwrap();
+        assert_eq!(converted.to_string(), *expected);
+    }
+}
+
+#[test]
+fn test_to_long_bracket_impossible() {
+    for code in &[r#""a\rb""#, r#""\255""#, "x", "-- comment"] {
+        let tokens = tokens(code).unwrap();
+        assert_eq!(tokens[0].token_type().to_long_bracket(), None, "{}", code);
+    }
+}
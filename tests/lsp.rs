@@ -0,0 +1,192 @@
+use full_moon::{
+    lsp::{document_symbols, document_symbols_with, DocumentSymbol, Position, Range, SymbolKind},
+    parse, parse_fallible, ParseConfig, PositionEncoding,
+};
+use pretty_assertions::assert_eq;
+
+// Each symbol as `kind name detail`, with its children indented under it
+fn outline(code: &str) -> String {
+    fn write(symbols: &[DocumentSymbol], depth: usize, output: &mut String) {
+        for symbol in symbols {
+            output.push_str(&format!(
+                "{}{:?} {}{}\n",
+                "  ".repeat(depth),
+                symbol.kind,
+                symbol.name,
+                symbol.detail.as_deref().unwrap_or_default(),
+            ));
+            write(&symbol.children, depth + 1, output);
+        }
+    }
+
+    let mut output = String::new();
+    write(&document_symbols(&parse(code).unwrap()), 0, &mut output);
+    output
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+    Range {
+        start: Position {
+            line: start.0,
+            character: start.1,
+        },
+        end: Position {
+            line: end.0,
+            character: end.1,
+        },
+    }
+}
+
+#[test]
+fn test_outline() {
+    let code = r#"
+local M = {}
+local count = 0
+
+function M.new(name, ...)
+    local function helper() end
+    return setmetatable({}, M)
+end
+
+function M.util.Class:method(x)
+end
+
+M.create = function(options)
+    local inner = function() end
+end
+
+M.value = 1
+M[1] = function() end
+M.f().g = function() end
+x = function() end
+
+local t = {
+    a = function(self) end,
+    ["b"] = function() end,
+    [c] = function() end,
+    nested = { d = function() end },
+    empty = {},
+    1,
+    e = 2,
+}
+
+if ready then
+    local function in_if() end
+else
+    do
+        local function in_do() end
+    end
+end
+
+pcall(function()
+    local function callback() end
+end)
+
+return {
+    exported = function() end,
+}
+"#;
+
+    assert_eq!(
+        outline(code),
+        "\
+Object M
+Function M.new(name, ...)
+  Function helper()
+Method M.util.Class:method(x)
+Function M.create(options)
+  Function inner()
+Function x()
+Object t
+  Function a(self)
+  Function b()
+  Object nested
+    Function d()
+Function in_if()
+Function in_do()
+Function exported()
+"
+    );
+}
+
+#[test]
+fn test_assigned_function_ranges() {
+    let code = "local M = {}\n\nM.foo = function(a)\n\treturn a\nend\n";
+    let symbols = document_symbols(&parse(code).unwrap());
+    let foo = &symbols[1];
+
+    assert_eq!(foo.name, "M.foo");
+    assert_eq!(foo.kind, SymbolKind::Function);
+    assert_eq!(foo.detail.as_deref(), Some("(a)"));
+    // The whole assignment, with the name being only the part before the `=`
+    assert_eq!(foo.range, range((2, 0), (4, 3)));
+    assert_eq!(foo.selection_range, range((2, 0), (2, 5)));
+}
+
+#[test]
+fn test_declaration_ranges() {
+    let code = "-- comment\nfunction M.a:b()\nend\nlocal function f() end";
+    let symbols = document_symbols(&parse(code).unwrap());
+
+    assert_eq!(symbols[0].range, range((1, 0), (2, 3)));
+    assert_eq!(symbols[0].selection_range, range((1, 9), (1, 14)));
+    assert_eq!(symbols[1].range, range((3, 0), (3, 22)));
+    assert_eq!(symbols[1].selection_range, range((3, 15), (3, 16)));
+
+    let fields = document_symbols(&parse("local t = {\n\tf = function() end,\n}").unwrap());
+    let f = &fields[0].children[0];
+    assert_eq!(f.range, range((1, 1), (1, 19)));
+    assert_eq!(f.selection_range, range((1, 1), (1, 2)));
+}
+
+#[test]
+fn test_encodings() {
+    let code = "local s = '😀' local function f() end";
+    let ast = parse(code).unwrap();
+
+    let utf16 = &document_symbols(&ast)[0];
+    assert_eq!(utf16.selection_range, range((0, 30), (0, 31)));
+
+    let bytes = &document_symbols_with(&ast, PositionEncoding::Utf8Bytes)[0];
+    assert_eq!(bytes.selection_range, range((0, 32), (0, 33)));
+}
+
+#[test]
+fn test_errors() {
+    let result = parse_fallible(
+        "local function f() end\nlocal = 1\nlocal function g() end",
+        &ParseConfig::default(),
+    );
+    let names: Vec<_> = document_symbols(&result.ast)
+        .into_iter()
+        .map(|symbol| symbol.name)
+        .collect();
+    assert_eq!(names, vec!["f", "g"]);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_serde() {
+    let symbols = document_symbols(&parse("local function f() end").unwrap());
+    assert_eq!(
+        serde_json::to_value(&symbols).unwrap(),
+        serde_json::json!([{
+            "name": "f",
+            "detail": "()",
+            "kind": 12,
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 0, "character": 22 },
+            },
+            "selectionRange": {
+                "start": { "line": 0, "character": 15 },
+                "end": { "line": 0, "character": 16 },
+            },
+            "children": [],
+        }])
+    );
+
+    let round_trip: Vec<DocumentSymbol> =
+        serde_json::from_value(serde_json::to_value(&symbols).unwrap()).unwrap();
+    assert_eq!(round_trip, symbols);
+}
@@ -0,0 +1,146 @@
+use full_moon::{
+    ast::{Ast, Block, Expression, LastStmt, Stmt},
+    lua_quote,
+    node::{Node, TriviaBehavior},
+    print,
+    tokenizer::TokenReference,
+};
+use pretty_assertions::assert_eq;
+
+fn code<'a>(node: &(impl Node + full_moon::visitors::Visit<'a>)) -> String {
+    node.print(TriviaBehavior::Trim)
+}
+
+fn local_name(ast: &Ast) -> TokenReference<'static> {
+    use full_moon::ast::owned::Owned;
+    match ast.nodes().iter_stmts().next() {
+        Some(Stmt::LocalAssignment(local)) => local.name_list().iter().next().unwrap().owned(),
+        other => panic!("expected a local assignment, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_spacing() {
+    let cases: Vec<(Stmt, &str)> = vec![
+        (lua_quote!(local x = 1), "local x = 1"),
+        (lua_quote!(x = -y ^ 2 - 1), "x = -y ^ 2 - 1"),
+        (lua_quote!(x = a - -b), "x = a - -b"),
+        (lua_quote!(x = a.b.c:d(1, 2)[3]), "x = a.b.c:d(1, 2)[3]"),
+        (
+            lua_quote!(x = { 1, y = 2; [3] = 4 }),
+            "x = { 1, y = 2; [3] = 4 }",
+        ),
+        (lua_quote!(x = {}), "x = {}"),
+        (lua_quote!(x = a.."b"..'c'), "x = a .. \"b\" .. 'c'"),
+        (
+            lua_quote!(x = a ~= b and c <= d or e >= f == g),
+            "x = a ~= b and c <= d or e >= f == g",
+        ),
+        (lua_quote!(x = ##t + ##(t)), "x = #t + #(t)"),
+        (lua_quote!(x = not(a)), "x = not (a)"),
+        (lua_quote!(f "string"), "f \"string\""),
+        (lua_quote!(f { 1 }), "f { 1 }"),
+        (
+            lua_quote!(local function f(a, ...) return ... end),
+            "local function f(a, ...) return ... end",
+        ),
+        (
+            lua_quote!(local f = function(a) end),
+            "local f = function(a) end",
+        ),
+        (
+            lua_quote!(if (a) then elseif b then else end),
+            "if (a) then elseif b then else end",
+        ),
+        (
+            lua_quote!(for i = 1, 10 do print(i) end),
+            "for i = 1, 10 do print(i) end",
+        ),
+        (lua_quote!(r#type = 1), "type = 1"),
+    ];
+
+    for (stmt, expected) in cases {
+        assert_eq!(code(&stmt), expected);
+    }
+}
+
+#[test]
+fn test_interpolation() {
+    let ast = full_moon::parse("local counter = 0").unwrap();
+    let name = local_name(&ast);
+    let sum: Expression = lua_quote!(a + b);
+    let call: Expression = lua_quote!(f(1));
+
+    let stmt: Stmt = lua_quote!(#name = #sum * #call + #name);
+    assert_eq!(code(&stmt), "counter = (a + b) * f(1) + counter");
+
+    // References to tokens and nodes can be interpolated too
+    let name = &name;
+    let stmt: Stmt = lua_quote!(local #name = ##(#sum));
+    assert_eq!(code(&stmt), "local counter = #((a + b))");
+
+    // Interpolations at the start and end
+    let value: Expression = lua_quote!(#call);
+    assert_eq!(code(&value), "f(1)");
+}
+
+#[test]
+fn test_nodes_from_other_code() {
+    let ast = full_moon::parse("local x = a  +  b -- comment\nprint(x)").unwrap();
+    let (value, call) = match (
+        ast.nodes().iter_stmts().next(),
+        ast.nodes().iter_stmts().nth(1),
+    ) {
+        (Some(Stmt::LocalAssignment(local)), Some(call)) => {
+            (local.expr_list().iter().next().unwrap(), call)
+        }
+        other => panic!("{:?}", other),
+    };
+
+    // The code of the node is kept as it was written, other than the trivia around it
+    let wrapped: Stmt = lua_quote!(do local y = #value #call end);
+    assert_eq!(code(&wrapped), "do local y = (a  +  b) print(x) end");
+}
+
+#[test]
+fn test_node_kinds() {
+    let block: Block = lua_quote! {
+        local a = 1
+        local b = 2
+        return a + b
+    };
+    assert_eq!(block.iter_stmts().count(), 2);
+    assert!(block.last_stmts().is_some());
+
+    let last_stmt: LastStmt = lua_quote!(return 1, 2);
+    assert!(matches!(last_stmt, LastStmt::Return(_)));
+
+    let body: Block = lua_quote!(print(1) print(2));
+    let empty: Block = lua_quote!();
+    let function: Stmt = lua_quote!(local function f() #body #empty end);
+    assert_eq!(code(&function), "local function f() print(1) print(2) end");
+
+    let ast: Ast = lua_quote! {
+        local x = 1
+        print(x)
+    };
+    assert_eq!(print(&ast), "local x = 1 print(x)");
+}
+
+#[test]
+#[should_panic(expected = "lua_quote! made invalid code `local = 1`")]
+fn test_invalid_code() {
+    let _: Stmt = lua_quote!(local = 1);
+}
+
+#[test]
+#[should_panic(expected = "expected one statement")]
+fn test_wrong_node() {
+    let _: Stmt = lua_quote!(a() b());
+}
+
+#[test]
+#[should_panic(expected = "expected one expression")]
+fn test_two_expressions() {
+    let _: Expression = lua_quote!(1, 2);
+}
@@ -0,0 +1,154 @@
+use full_moon::{
+    parse, parse_all_errors, print,
+    tokenizer::{tokens, tokens_all_errors, MalformedNumberReason, TokenType, TokenizerErrorType},
+    Error, ErrorCode,
+};
+
+// The text of the malformed number, why it's malformed, and the byte it's at
+fn malformed(code: &str) -> (String, MalformedNumberReason, usize) {
+    let error = match tokens(code) {
+        Err(error) => error,
+        Ok(_) => panic!("{:?} was tokenized", code),
+    };
+
+    match error.error() {
+        TokenizerErrorType::MalformedNumber {
+            text,
+            position,
+            reason,
+        } => {
+            assert_eq!(error.position(), *position);
+            (text.clone(), *reason, position.bytes())
+        }
+        other => panic!("{:?} didn't have a malformed number: {:?}", code, other),
+    }
+}
+
+#[test]
+fn test_malformed_numbers() {
+    use MalformedNumberReason::*;
+
+    assert_eq!(malformed("x = 0x"), ("0x".to_owned(), MissingHexDigits, 4));
+    assert_eq!(
+        malformed("x = 1e"),
+        ("1e".to_owned(), MissingExponentDigits, 4)
+    );
+    assert_eq!(
+        malformed("x = 1e+ 2"),
+        ("1e+".to_owned(), MissingExponentDigits, 4)
+    );
+    assert_eq!(
+        malformed("x = 0x1p"),
+        ("0x1p".to_owned(), InvalidCharacter('p'), 4)
+    );
+    assert_eq!(
+        malformed("x = 3..14"),
+        ("3..14".to_owned(), MultipleDecimalPoints, 4)
+    );
+    assert_eq!(
+        malformed("x = 10abc + 1"),
+        ("10abc".to_owned(), InvalidCharacter('a'), 4)
+    );
+
+    // Lua 5.1 has no hexadecimal numbers with exponents
+    assert_eq!(
+        malformed("x = 0x1p4"),
+        ("0x1p4".to_owned(), InvalidCharacter('p'), 4)
+    );
+}
+
+#[test]
+fn test_valid_numbers() {
+    for number in &[
+        "1", "1e5", "1E5", "1e+5", "1e-5", "1.", ".5", "1.5e10", "0x1F", "0XaB", "0x1e",
+    ] {
+        let code = format!("x = {}", number);
+        let tokens = tokens(&code).unwrap_or_else(|error| panic!("{:?}: {}", number, error));
+        assert_eq!(
+            *tokens[4].token_type(),
+            TokenType::Number {
+                text: (*number).into()
+            }
+        );
+    }
+
+    // Concatenating numbers needs spaces, but nothing else next to a number does
+    assert!(parse("x = 1 .. 2").is_ok());
+    assert!(parse("x = t[1]..2").is_ok());
+    assert!(parse("x = 1+2-3*4/5^6%7").is_ok());
+}
+
+#[test]
+fn test_malformed_number_messages() {
+    let code = "local pi = 3..14";
+    let error = parse(code).unwrap_err();
+    assert_eq!(error.code(), ErrorCode::MalformedNumber);
+    assert_eq!(error.message(), "malformed number");
+    assert_eq!(
+        error.to_string(),
+        "malformed number at line 1, character 12\nfound `3..14`\na number can only have one `.`"
+    );
+    assert_eq!(
+        error.display_rich(code, None),
+        concat!(
+            "error: malformed number `3..14`\n",
+            " --> 1:12\n",
+            "  |\n",
+            "1 | local pi = 3..14\n",
+            "  |            ^^^^^ a number can only have one `.`\n",
+        )
+    );
+
+    // The position is on the line the number is on, even right after a new line
+    let error = parse("x = 1\n0x").unwrap_err();
+    let position = error.position().unwrap();
+    assert_eq!((position.line(), position.character()), (2, 1));
+}
+
+#[test]
+fn test_malformed_number_recovery() {
+    let (tokens, errors) = tokens_all_errors("x = 0x + 1e").unwrap();
+    assert_eq!(tokens.len(), 10);
+    assert_eq!(
+        *tokens[4].token_type(),
+        TokenType::Number { text: "0x".into() }
+    );
+    assert_eq!(
+        errors.iter().map(|error| error.code()).collect::<Vec<_>>(),
+        vec![ErrorCode::MalformedNumber; 2]
+    );
+
+    let code = "x = 3..14\nlocal = 1\ny = 0x";
+    let (ast, errors) = parse_all_errors(code, 10);
+    assert_eq!(print(&ast.unwrap()), code);
+    assert!(matches!(
+        errors.as_slice(),
+        [
+            Error::TokenizerError(_),
+            Error::AstError(_),
+            Error::TokenizerError(_)
+        ]
+    ));
+}
+
+#[test]
+#[cfg_attr(not(feature = "roblox"), ignore)]
+fn test_malformed_binary_numbers() {
+    assert!(tokens("x = 0b101").is_ok());
+    assert_eq!(
+        malformed("x = 0b"),
+        (
+            "0b".to_owned(),
+            MalformedNumberReason::MissingBinaryDigits,
+            4
+        )
+    );
+    assert_eq!(
+        malformed("x = 0b12"),
+        (
+            "0b12".to_owned(),
+            MalformedNumberReason::InvalidCharacter('2'),
+            4
+        )
+    );
+}
@@ -0,0 +1,83 @@
+// Only one test, since the allocator counts the allocations of every thread
+use full_moon::{ast::Ast, print, ParseConfig};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct Counting;
+
+// The bytes allocated and not freed yet
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(pointer, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// The bytes allocated by making something and still in use after, which is what it takes on the heap
+fn measure<T>(make: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let made = make();
+    (made, ALLOCATED.load(Ordering::Relaxed) - before)
+}
+
+fn check(name: &str, ast: &Ast, measured: usize) {
+    let report = ast.memory_usage();
+    assert!(
+        report.total_bytes() * 50 >= measured * 49 && report.total_bytes() * 50 <= measured * 51,
+        "{} reported {} bytes, but {} were measured",
+        name,
+        report.total_bytes(),
+        measured
+    );
+}
+
+#[test]
+fn test_memory_usage_matches_the_heap() {
+    let code = include_str!("../benches/date.lua");
+    let without_trivia = ParseConfig {
+        preserve_trivia: false,
+        ..ParseConfig::default()
+    };
+
+    // The tables for tokenizing are made the first time something is parsed, and kept after
+    full_moon::parse(code).unwrap();
+
+    let (mut ast, measured) = measure(|| full_moon::parse(code).unwrap());
+    check("parse", &ast, measured);
+    let report = ast.memory_usage();
+    assert_eq!(report.string_bytes(), 0);
+    assert!(report.node_count() > 1_000);
+    // Most of the heap is the tokens and the nodes, which are about the same size
+    assert!(report.token_arena_bytes() * 2 > report.node_bytes());
+    assert!(report.node_bytes() * 2 > report.token_arena_bytes());
+
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    ast.shrink_to_fit();
+    let freed = before - ALLOCATED.load(Ordering::Relaxed);
+    let shrunk = ast.memory_usage();
+    assert!(shrunk.total_bytes() < report.total_bytes());
+    assert_eq!(shrunk.node_count(), report.node_count());
+    check("shrunk", &ast, measured - freed);
+    assert_eq!(print(&ast), code);
+
+    let (ast, measured) = measure(|| full_moon::parse_with(code, &without_trivia).unwrap());
+    check("without trivia", &ast, measured);
+    assert!(ast.memory_usage().token_arena_bytes() < report.token_arena_bytes());
+
+    let (owned, measured) = measure(|| full_moon::parse_owned(code.to_owned()).unwrap());
+    let report = owned.memory_usage();
+    assert!(report.string_bytes() > 0);
+    check("owned", &owned, measured - code.len());
+}
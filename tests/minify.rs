@@ -0,0 +1,82 @@
+use full_moon::{ast::owned::Owned, node::Node, parse, print_minified, tokenizer::TokenKind};
+use std::fs;
+
+fn assert_minifies_to(code: &str, expected: &str) {
+    let ast = parse(code).unwrap();
+    let minified = print_minified(&ast);
+    assert_eq!(minified, expected);
+
+    let minified_ast = parse(&minified).unwrap();
+    assert!(minified_ast.nodes().owned().similar(&ast.nodes().owned()));
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_minify_round_trip_pass_cases() {
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).expect("couldn't parse");
+
+        let minified = print_minified(&ast);
+        let minified_ast = parse(&minified).unwrap_or_else(|error| {
+            panic!(
+                "{:?} printed unparsable code {:?}: {}",
+                path, minified, error
+            )
+        });
+
+        assert!(
+            minified_ast.nodes().owned().similar(&ast.nodes().owned()),
+            "{:?} printed different code {:?}",
+            path,
+            minified
+        );
+
+        assert!(
+            minified_ast.iter_tokens().all(|token| !matches!(
+                token.token_kind(),
+                TokenKind::SingleLineComment | TokenKind::MultiLineComment
+            )),
+            "{:?} kept comments",
+            path
+        );
+
+        assert_eq!(print_minified(&minified_ast), minified, "{:?}", path);
+    }
+}
+
+#[test]
+fn test_minify_whitespace_and_comments() {
+    assert_minifies_to(
+        "-- header\nlocal x = 1 -- one\n\nif x then\n    print(x, --[[ two ]] 2)\nend\n",
+        "local x=1 if x then print(x,2)end",
+    );
+}
+
+#[test]
+fn test_minify_required_spaces() {
+    assert_minifies_to(
+        "x = a - -b .. c and not d x = 1 .. 2 t[ [[s]] ] = 1 return x",
+        "x=a- -b..c and not d x=1 .. 2 t[ [[s]]]=1 return x",
+    );
+
+    // Numbers take every letter, digit, and `.` after them, even after a `.` they end with
+    assert_minifies_to(
+        "local x = 1.\nlocal y = 1.e1\nz = 0x1",
+        "local x=1. local y=1.e1 z=0x1",
+    );
+}
+
+#[test]
+fn test_minify_ambiguous_calls() {
+    assert_minifies_to("local x = y;\n(f)()", "local x=y;(f)()");
+    assert_minifies_to("f();\n(g or h)(1)", "f();(g or h)(1)");
+    assert_minifies_to("x = 1;\n(t).y = 2", "x=1;(t).y=2");
+    assert_minifies_to("do (f)() end (g)()", "do(f)()end(g)()");
+}
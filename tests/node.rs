@@ -1,4 +1,9 @@
-use full_moon::{node::Node, parse};
+use full_moon::{
+    ast::Expression,
+    node::{Node, TriviaBehavior},
+    parse,
+};
+use std::fs;
 
 #[test]
 fn surrounding_ignore_tokens() {
@@ -27,3 +32,99 @@ fn test_similar() {
     assert!(stmts[1].similar(stmts[0]));
     assert!(!stmts[0].similar(stmts[2]));
 }
+
+#[test]
+fn test_print_trivia_behavior() {
+    let ast = parse(
+        "-- header\n--[[ license ]]\n\n  local x = { -- inside\n    1 } -- after x\n\n\n\t-- before y\n  y = x --[[ a ]] -- b\n\n",
+    )
+    .unwrap();
+    let stmts: Vec<_> = ast.nodes().iter_stmts().collect();
+
+    assert_eq!(
+        stmts[0].print(TriviaBehavior::Keep),
+        "-- header\n--[[ license ]]\n\n  local x = { -- inside\n    1 } -- after x\n"
+    );
+    assert_eq!(
+        stmts[0].print(TriviaBehavior::Trim),
+        "local x = { -- inside\n    1 }"
+    );
+    assert_eq!(
+        stmts[0].print(TriviaBehavior::TrimToComments),
+        "-- header\n--[[ license ]]\n\n  local x = { -- inside\n    1 } -- after x"
+    );
+
+    assert_eq!(
+        stmts[1].print(TriviaBehavior::Keep),
+        "\n\n\t-- before y\n  y = x --[[ a ]] -- b\n"
+    );
+    assert_eq!(stmts[1].print(TriviaBehavior::Trim), "y = x");
+    assert_eq!(
+        stmts[1].print(TriviaBehavior::TrimToComments),
+        "-- before y\n  y = x --[[ a ]] -- b"
+    );
+}
+
+#[test]
+fn test_print_expressions() {
+    let ast = parse("call(\n  -- first\n  a + b, --[[ second ]] (c)\n)").unwrap();
+    let expressions: Vec<_> = ast.nodes_of_type::<Expression>().collect();
+
+    let first = expressions
+        .iter()
+        .find(|expression| expression.print(TriviaBehavior::Trim) == "a + b")
+        .unwrap();
+    assert_eq!(first.print(TriviaBehavior::Keep), "  -- first\n  a + b");
+    assert_eq!(
+        first.print(TriviaBehavior::TrimToComments),
+        "-- first\n  a + b"
+    );
+
+    let second = expressions
+        .iter()
+        .find(|expression| expression.print(TriviaBehavior::Trim) == "(c)")
+        .unwrap();
+    assert_eq!(second.print(TriviaBehavior::Keep), "(c)\n");
+    assert_eq!(second.print(TriviaBehavior::TrimToComments), "(c)");
+}
+
+#[test]
+fn test_print_owned() {
+    use full_moon::ast::owned::Owned;
+
+    let ast = parse("local x = 1").unwrap();
+    let stmt = ast.nodes().iter_stmts().next().unwrap().owned();
+    assert_eq!(stmt.print(TriviaBehavior::Keep), "");
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_print_pass_cases() {
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).expect("couldn't parse");
+
+        for stmt in ast.nodes().iter_stmts() {
+            let mut code = Vec::new();
+            stmt.write_to(&ast, &mut code).unwrap();
+            assert_eq!(
+                stmt.print(TriviaBehavior::Trim).as_bytes(),
+                &code[..],
+                "{:?}",
+                path
+            );
+
+            let with_comments = stmt.print(TriviaBehavior::TrimToComments);
+            let kept = stmt.print(TriviaBehavior::Keep);
+            assert!(with_comments.contains(&stmt.print(TriviaBehavior::Trim)));
+            assert!(kept.contains(with_comments.as_str()), "{:?}", path);
+            assert!(source.contains(kept.as_str()), "{:?}", path);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use full_moon::{
+    ast::{
+        Assignment, BinOpRhs, Expression, FunctionCall, LocalAssignment, TableConstructor, Value,
+        Var,
+    },
+    lua_quote,
+    node::Node,
+    parse,
+    visitors::{NodeKind, VisitorMut},
+};
+use std::collections::HashSet;
+
+#[test]
+fn test_node_by_id() {
+    let ast =
+        parse("local t = { 1, x = f(2) + 3 }\nt.x = -t[1]\nlocal x = a + b * 2\nprint(x)").unwrap();
+
+    // Every node has its own id, even one that's made of only one other node or token
+    let mut ids = HashSet::new();
+    for (_, node) in ast.nodes_with_paths() {
+        assert!(ids.insert(node.id()), "{:?} shares an id", node.kind());
+
+        let found = ast.node_by_id(node.id()).unwrap();
+        assert_eq!(found.kind(), node.kind());
+        assert_eq!(found.range(), node.range());
+    }
+
+    let local = ast.nodes_of_type::<LocalAssignment>().nth(1).unwrap();
+    let stmt = ast.nodes().iter_stmts().nth(2).unwrap();
+    assert_ne!(stmt.id(), local.id());
+    assert_eq!(
+        ast.node_by_id(local.id().unwrap()).unwrap().kind(),
+        NodeKind::LocalAssignment
+    );
+
+    // A binary expression isn't the same node as the value on its left or its operator
+    let binary = ast
+        .nodes_of_type::<Expression>()
+        .find(|expression| matches!(expression, Expression::Value { binop: Some(_), .. }))
+        .unwrap();
+    let binop = ast.first_node_of_type::<BinOpRhs>().unwrap();
+    let value = match binary {
+        Expression::Value { value, .. } => value,
+        _ => unreachable!(),
+    };
+    assert_ne!(binary.id(), binop.id());
+    assert_ne!(binop.id(), binop.bin_op().id());
+    assert_ne!(binary.id(), value.id());
+    assert_eq!(
+        ast.node_by_id(value.id().unwrap()).unwrap().kind(),
+        NodeKind::Value
+    );
+
+    // Including the tokens of contained spans
+    let braces = ast
+        .first_node_of_type::<TableConstructor>()
+        .unwrap()
+        .braces();
+    let (open, _) = braces.tokens();
+    assert_ne!(braces.id(), open.id());
+    let found = ast.node_by_id(open.id().unwrap()).unwrap();
+    assert_eq!(found.kind(), NodeKind::TokenReference);
+    assert_eq!(found.range(), open.range());
+
+    // Ids are only in the Ast they were made in
+    let other =
+        parse("local t = { 1, x = f(2) + 3 }\nt.x = -t[1]\nlocal x = a + b * 2\nprint(x)").unwrap();
+    assert_eq!(other.nodes(), ast.nodes());
+    assert_ne!(other.nodes().id(), ast.nodes().id());
+    assert!(other.node_by_id(binop.id().unwrap()).is_none());
+}
+
+#[test]
+fn test_ids_survive_changes() {
+    // Replaces every number with a call
+    struct ReplaceNumbers;
+
+    impl VisitorMut<'static> for ReplaceNumbers {
+        fn visit_expression(&mut self, expression: &mut Expression<'static>) {
+            if let Expression::Value { value, .. } = expression {
+                if let Value::Number(_) = **value {
+                    *expression = lua_quote!(g());
+                }
+            }
+        }
+    }
+
+    let mut ast = parse("local x = f(1)\nx = 2").unwrap();
+    let local = ast.first_node_of_type::<LocalAssignment>().unwrap().id();
+    let call = ast.first_node_of_type::<FunctionCall>().unwrap().id();
+    let assignment = ast.first_node_of_type::<Assignment>().unwrap().id();
+    let number = ast
+        .nodes_of_type::<Value>()
+        .find(|value| matches!(value, Value::Number(_)))
+        .unwrap()
+        .id();
+    let block = ast.nodes().id();
+
+    ReplaceNumbers.visit_ast(&mut ast);
+    assert!(ast
+        .nodes()
+        .similar(parse("local x = f(g())\nx = g()").unwrap().nodes()));
+
+    assert_eq!(ast.nodes().id(), block);
+    assert_eq!(
+        ast.first_node_of_type::<LocalAssignment>().unwrap().id(),
+        local
+    );
+    assert_eq!(ast.first_node_of_type::<FunctionCall>().unwrap().id(), call);
+    assert_eq!(
+        ast.first_node_of_type::<Assignment>().unwrap().id(),
+        assignment
+    );
+
+    // The new calls are new nodes, and the numbers they replaced aren't in the Ast anymore
+    let calls: Vec<_> = ast.nodes_of_type::<FunctionCall>().map(Node::id).collect();
+    assert_eq!(calls.len(), 3);
+    assert_ne!(calls[1], calls[2]);
+    assert!(!calls.contains(&number));
+    assert!(ast.node_by_id(number.unwrap()).is_none());
+}
+
+#[test]
+fn test_ids_survive_replaced_children() {
+    // Replaces the name `a` with `b`, and the value `c` with `2`
+    struct ReplaceChildren;
+
+    impl VisitorMut<'static> for ReplaceChildren {
+        fn visit_var(&mut self, var: &mut Var<'static>) {
+            if let Var::Name(name) = var {
+                if name.to_string() == "a" {
+                    *name = match lua_quote!(b) {
+                        Expression::Value { value, .. } => match *value {
+                            Value::Var(Var::Name(name)) => name,
+                            _ => unreachable!(),
+                        },
+                        _ => unreachable!(),
+                    };
+                }
+            }
+        }
+
+        fn visit_expression(&mut self, expression: &mut Expression<'static>) {
+            if let Expression::Value { value, .. } = expression {
+                if matches!(&**value, Value::Var(Var::Name(name)) if name.to_string() == "c") {
+                    *value = match lua_quote!(2) {
+                        Expression::Value { value, .. } => value,
+                        _ => unreachable!(),
+                    };
+                }
+            }
+        }
+    }
+
+    let mut ast = parse("local x = a + c").unwrap();
+    let ids = |ast: &full_moon::ast::Ast<'static>| -> Vec<_> {
+        ast.nodes_of_type::<Expression>().map(Node::id).collect()
+    };
+    let expressions = ids(&ast);
+    let name = ast.first_node_of_type::<Var>().unwrap().id();
+
+    ReplaceChildren.visit_ast(&mut ast);
+    assert!(ast
+        .nodes()
+        .similar(parse("local x = b + 2").unwrap().nodes()));
+    assert_eq!(ids(&ast), expressions);
+    assert_ne!(ast.first_node_of_type::<Var>().unwrap().id(), name);
+}
+
+#[test]
+fn test_clones() {
+    let ast = parse("local x = 1").unwrap();
+    let clone = ast.clone();
+    assert_eq!(clone.nodes().id(), ast.nodes().id());
+
+    let local = ast.first_node_of_type::<LocalAssignment>().unwrap();
+    assert_eq!(
+        clone.first_node_of_type::<LocalAssignment>().unwrap().id(),
+        local.id()
+    );
+    assert_eq!(local.clone().id(), local.id());
+    assert_eq!(local.local_token().clone().id(), local.local_token().id());
+}
@@ -0,0 +1,72 @@
+use full_moon::{
+    node::NodePath,
+    parse, print,
+    tokenizer::{TokenKind, TokenType},
+    visitors::{AnyNode, AnyNodeMut, NodeKind},
+};
+use std::borrow::Cow;
+
+#[test]
+fn test_paths_round_trip() {
+    let code = parse("local a = 1\nlocal b = 2\nif a == b then print(a) end").unwrap();
+
+    for (path, node) in code.nodes_with_paths() {
+        let found = code.get_path(&path).unwrap();
+        assert_eq!(found.kind(), node.kind());
+        assert_eq!(found.range(), node.range());
+    }
+
+    assert_eq!(
+        code.get_path(&NodePath::root()).unwrap().kind(),
+        NodeKind::Block
+    );
+    assert!(code.get_path(&NodePath::from(vec![100])).is_none());
+}
+
+#[test]
+fn test_modify_at_path() {
+    let mut code = parse("local a = 1\nlocal b = 2\nif a == b then print(a) end").unwrap();
+
+    // Find the left operand of the `if` condition in an immutable pass
+    let (if_path, _) = code
+        .nodes_with_paths()
+        .find(|(_, node)| node.kind() == NodeKind::If)
+        .unwrap();
+
+    let (path, _) = code
+        .nodes_with_paths()
+        .find(|(path, node)| match node {
+            AnyNode::TokenReference(token) => {
+                path.indices().starts_with(if_path.indices())
+                    && token.token_kind() == TokenKind::Identifier
+            }
+            _ => false,
+        })
+        .unwrap();
+
+    // Paths can be stored between runs
+    let serialized = serde_json::to_string(&path).unwrap();
+    let path: NodePath = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(
+        path.parent()
+            .unwrap()
+            .child(*path.indices().last().unwrap()),
+        path
+    );
+
+    let renamed = code.modify_at_path(&path, |node| match node {
+        AnyNodeMut::TokenReference(token) => {
+            token.set_token_type(TokenType::Identifier {
+                identifier: Cow::from("c"),
+            });
+            true
+        }
+        _ => false,
+    });
+
+    assert_eq!(renamed, Some(true));
+    assert_eq!(
+        print(&code),
+        "local a = 1\nlocal b = 2\nif c == b then print(a) end"
+    );
+}
@@ -0,0 +1,111 @@
+use full_moon::{
+    ast::owned::Owned, node::Node, parse, print, tokenizer::TokenKind, transform::normalize_spacing,
+};
+use std::fs;
+
+fn normalized(code: &str) -> String {
+    let mut ast = parse(code).unwrap();
+    normalize_spacing(&mut ast);
+    print(&ast)
+}
+
+fn comments(code: &str) -> Vec<String> {
+    parse(code)
+        .unwrap()
+        .iter_tokens()
+        .filter(|token| {
+            matches!(
+                token.token_kind(),
+                TokenKind::SingleLineComment | TokenKind::MultiLineComment
+            )
+        })
+        .map(|token| token.to_string())
+        .collect()
+}
+
+#[test]
+fn test_normalize_operators_and_commas() {
+    assert_eq!(
+        normalized("local a,b=1 ,2\nx   =a*b+c..d and not e\nf(a  ,b,c)"),
+        "local a, b = 1, 2\nx = a * b + c .. d and not e\nf(a, b, c)"
+    );
+}
+
+#[test]
+fn test_normalize_unary_and_numbers() {
+    assert_eq!(
+        normalized("x = a- -b\ny = -a+#t\nz = 1 ..2 ..  3"),
+        "x = a - -b\ny = -a + #t\nz = 1 .. 2 .. 3"
+    );
+}
+
+#[test]
+fn test_normalize_keeps_lines_and_comments() {
+    assert_eq!(
+        normalized("x = a +\n    b\n    +  c\nt = {1,2,}\nf(a --[[ a ]] ,b) -- done   \ny = a--[[ c ]]+b\n"),
+        "x = a +\n    b\n    + c\nt = {1, 2,}\nf(a --[[ a ]], b) -- done   \ny = a--[[ c ]]+ b\n"
+    );
+}
+
+#[test]
+fn test_normalize_trailing_whitespace() {
+    assert_eq!(
+        normalized("local x = 1   \n\t\n   \nprint(x)  "),
+        "local x = 1\n\n\nprint(x)"
+    );
+}
+
+#[test]
+fn test_normalize_positions() {
+    let mut ast = parse("x=1\ny=x+2").unwrap();
+    normalize_spacing(&mut ast);
+
+    let stmt = ast.nodes().iter_stmts().nth(1).unwrap();
+    assert_eq!(stmt.start_position().unwrap().bytes(), 4);
+
+    ast.update_positions();
+    let code = print(&ast);
+    for token in ast.iter_tokens() {
+        let (start, end) = (token.start_position().bytes(), token.end_position().bytes());
+        assert_eq!(&code[start..end], token.to_string());
+    }
+
+    let stmt = ast.nodes().iter_stmts().nth(1).unwrap();
+    assert_eq!(stmt.start_position().unwrap().bytes(), 6);
+    assert_eq!(stmt.end_position().unwrap().bytes(), code.len());
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_normalize_pass_cases() {
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let code = normalized(&source);
+        let new_ast = parse(&code).unwrap_or_else(|error| {
+            panic!(
+                "{:?} normalized to unparsable code {:?}: {}",
+                path, code, error
+            )
+        });
+
+        assert!(
+            new_ast
+                .nodes()
+                .owned()
+                .similar(&parse(&source).unwrap().nodes().owned()),
+            "{:?} normalized to different code {:?}",
+            path,
+            code
+        );
+
+        assert_eq!(code.lines().count(), source.lines().count(), "{:?}", path);
+        assert_eq!(comments(&code), comments(&source), "{:?}", path);
+        assert_eq!(normalized(&code), code, "{:?}", path);
+    }
+}
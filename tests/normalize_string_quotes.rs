@@ -0,0 +1,68 @@
+use full_moon::{
+    parse, print,
+    transform::{normalize_string_quotes, QuoteConfig, QuoteStyle},
+};
+
+fn normalized(code: &str, config: &QuoteConfig) -> String {
+    let mut ast = parse(code).unwrap();
+    normalize_string_quotes(&mut ast, config);
+    print(&ast)
+}
+
+#[test]
+fn test_double_quotes() {
+    assert_eq!(
+        normalized(
+            r#"local x = 'a', "b", 'it\'s', 'a\nb\65', ''"#,
+            &QuoteConfig::default()
+        ),
+        r#"local x = "a", "b", "it's", "a\nb\65", """#
+    );
+}
+
+#[test]
+fn test_single_quotes() {
+    let config = QuoteConfig {
+        style: QuoteStyle::Single,
+        ..QuoteConfig::default()
+    };
+
+    assert_eq!(
+        normalized(r#"local x = "a", 'b', "say \"hi\"", "\\""#, &config),
+        r#"local x = 'a', 'b', 'say "hi"', '\\'"#
+    );
+}
+
+#[test]
+fn test_escaping_quotes() {
+    // The first string would need more escapes, and the second needs as many as it removes
+    assert_eq!(
+        normalized(r#"x = 'say "hi"', '\'a\' "b"'"#, &QuoteConfig::default()),
+        r#"x = 'say "hi"', "'a' \"b\"""#
+    );
+
+    let config = QuoteConfig {
+        avoid_escapes: false,
+        ..QuoteConfig::default()
+    };
+
+    assert_eq!(
+        normalized(r#"x = 'say "hi"', '\"', '\\"'"#, &config),
+        r#"x = "say \"hi\"", "\"", "\\\"""#
+    );
+}
+
+#[test]
+fn test_long_strings_unchanged() {
+    let code = "x = [[it's]], [==['a']==]";
+    assert_eq!(normalized(code, &QuoteConfig::default()), code);
+}
+
+#[test]
+fn test_comments_unchanged() {
+    let code = "-- 'single'\nx = 'a' --[[ 'b' ]]";
+    assert_eq!(
+        normalized(code, &QuoteConfig::default()),
+        "-- 'single'\nx = \"a\" --[[ 'b' ]]"
+    );
+}
@@ -0,0 +1,75 @@
+use full_moon::{
+    ast::owned::OwnedAst,
+    parse_owned, parse_owned_with, print,
+    tokenizer::{TokenReference, TokenType},
+    visitors::{Visit, Visitor, VisitorMut},
+    Error, ParseConfig,
+};
+use std::thread;
+
+fn assert_static<T: Send + Sync + 'static>(_: &T) {}
+
+// The code is made and dropped in the function, so the Ast can't borrow from it
+fn parse_module(name: &str) -> OwnedAst {
+    parse_owned(format!("local {} = require(script.{})", name, name)).unwrap()
+}
+
+#[test]
+fn test_owned_ast() {
+    let ast = parse_module("module");
+    assert_static(&ast);
+    assert_eq!(ast.source(), "local module = require(script.module)");
+    assert_eq!(print(&ast), ast.source());
+
+    let printed = thread::spawn(move || print(&ast)).join().unwrap();
+    assert_eq!(printed, "local module = require(script.module)");
+}
+
+#[derive(Default)]
+struct Names(Vec<String>);
+
+impl<'ast> Visitor<'ast> for Names {
+    fn visit_identifier(&mut self, token: &TokenReference<'ast>) {
+        self.0.push(token.to_string());
+    }
+}
+
+struct Rename;
+
+impl<'ast> VisitorMut<'ast> for Rename {
+    fn visit_identifier(&mut self, token: &mut TokenReference<'ast>) {
+        if token.to_string() == "module" {
+            token.set_token_type(TokenType::Identifier {
+                identifier: "renamed".into(),
+            });
+        }
+    }
+}
+
+#[test]
+fn test_owned_ast_visitors() {
+    let mut ast = parse_module("module");
+
+    let mut names = Names::default();
+    ast.nodes().visit(&mut names);
+    assert_eq!(names.0, vec!["module", "require", "script", "module"]);
+
+    Rename.visit_ast(&mut ast);
+    assert_eq!(print(&ast), "local renamed = require(script.renamed)");
+    assert_eq!(ast.source(), "local module = require(script.module)");
+
+    let (source, ast) = ast.into_parts();
+    drop(source);
+    assert_eq!(print(&ast), "local renamed = require(script.renamed)");
+}
+
+#[test]
+fn test_owned_errors() {
+    let error = parse_owned(String::from("local x = ")).unwrap_err();
+    assert_static(&error);
+    assert_eq!(error.message(), "unexpected end of code");
+
+    let error: Error<'static> =
+        parse_owned_with(String::from("local = 1"), &ParseConfig::default()).unwrap_err();
+    assert!(error.to_string().contains("expected"), "{}", error);
+}
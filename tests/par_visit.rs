@@ -0,0 +1,55 @@
+#![cfg(feature = "rayon")]
+use full_moon::{ast, parse, visitors::Visitor};
+
+#[derive(Default)]
+struct LocalCounter {
+    locals: usize,
+    returns: usize,
+}
+
+impl Visitor<'_> for LocalCounter {
+    fn visit_local_assignment(&mut self, _: &ast::LocalAssignment) {
+        self.locals += 1;
+    }
+
+    fn visit_return(&mut self, _: &ast::Return) {
+        self.returns += 1;
+    }
+}
+
+#[test]
+fn test_par_visit() {
+    let mut code = String::new();
+    for index in 0..100 {
+        code.push_str(&format!(
+            "local x{} = function() local y = {} end\n",
+            index, index
+        ));
+    }
+    code.push_str("return x1");
+
+    let ast = parse(&code).unwrap();
+    let visitors = ast.par_visit(LocalCounter::default);
+
+    assert!(!visitors.is_empty());
+    assert_eq!(
+        visitors.iter().map(|visitor| visitor.locals).sum::<usize>(),
+        200
+    );
+    assert_eq!(visitors.last().unwrap().returns, 1);
+    assert_eq!(
+        visitors
+            .iter()
+            .map(|visitor| visitor.returns)
+            .sum::<usize>(),
+        1
+    );
+}
+
+#[test]
+fn test_par_visit_empty() {
+    let ast = parse("return").unwrap();
+    let visitors = ast.par_visit(LocalCounter::default);
+    assert_eq!(visitors.len(), 1);
+    assert_eq!(visitors[0].returns, 1);
+}
@@ -0,0 +1,259 @@
+use full_moon::{
+    ast::{Ast, ErrorExpression, ErrorStmt, Expression, LastStmt, Stmt},
+    node::{Node, TriviaBehavior},
+    parse, parse_all_errors, print,
+    visitors::Visitor,
+    Error,
+};
+use std::fs;
+
+// The text of the token each error is at, and its additional information
+fn errors(code: &str) -> Vec<(String, String)> {
+    let (ast, errors) = parse_all_errors(code, 100);
+    assert_eq!(print(&ast.unwrap()), code);
+
+    errors
+        .into_iter()
+        .map(|error| match error {
+            Error::AstError(full_moon::ast::AstError::UnexpectedToken {
+                token,
+                additional,
+                ..
+            }) => (
+                token.to_string(),
+                additional
+                    .map(|additional| additional.into_owned())
+                    .unwrap_or_default(),
+            ),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect()
+}
+
+fn error(token: &str, additional: &str) -> (String, String) {
+    (token.to_owned(), additional.to_owned())
+}
+
+#[test]
+fn test_parse_all_errors_valid() {
+    let (ast, errors) = parse_all_errors("local x = 1\nprint(x)", 10);
+    assert!(ast.is_some());
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn test_parse_all_errors_statements() {
+    assert_eq!(
+        errors("local x = \nlocal y = 1\ncall(,)\nz = = 2\nw = 3"),
+        vec![
+            error("local", "expected expression"),
+            error(",", "expected ')'"),
+            error("=", "expected values"),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_all_errors_blocks() {
+    // The rest of a block with an error is skipped, but not the code after it
+    assert_eq!(
+        errors(
+            "function f()\n  x = = 1\n  if y then\n    z = = 2\n  end\nend\nfunction g()\n  return = 1\nend\n"
+        ),
+        vec![error("=", "expected values"), error("=", "expected 'end'")]
+    );
+
+    assert_eq!(
+        errors("while x do y( end\nwhile x do end\nrepeat until"),
+        vec![
+            error("end", "expected ')'"),
+            error("", "expected condition")
+        ]
+    );
+}
+
+#[test]
+fn test_parse_all_errors_missing_end() {
+    // A missing `end` makes the rest of the code part of the block, and is only one error
+    assert_eq!(
+        errors("function f()\n  if x then\n    y()\nend\nfunction g()\nend\nfunction h()\nend\n"),
+        vec![error("", "expected 'end'")]
+    );
+
+    assert_eq!(
+        errors("function f()\n  x = = 1\n\nfunction g()\nend\nfunction h()\nend\n"),
+        vec![error("=", "expected values")]
+    );
+}
+
+#[test]
+fn test_parse_all_errors_stray_tokens() {
+    assert_eq!(
+        errors("x = 1\nend\ny = 2\n)\nreturn x z = 3"),
+        vec![
+            error("end", "leftover token"),
+            error(")", "leftover token"),
+            error("z", "leftover token"),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_all_errors_max_errors() {
+    let code = "x = = 1\n".repeat(10);
+    assert_eq!(parse_all_errors(&code, 3).1.len(), 3);
+    assert_eq!(parse_all_errors(&code, 0).1.len(), 1);
+    assert_eq!(parse_all_errors(&code, 100).1.len(), 10);
+}
+
+#[test]
+fn test_parse_all_errors_tokenizer() {
+    let (ast, errors) = parse_all_errors("x = 1\ny = 'unclosed", 10);
+    assert!(ast.is_none());
+    assert!(matches!(errors.as_slice(), [Error::TokenizerError(_)]));
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_parse_all_errors_fail_cases() {
+    for entry in fs::read_dir("./tests/cases/fail/parser").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+
+        // The first error is the one parse finds
+        let (ast, errors) = parse_all_errors(&source, 10);
+        assert_eq!(errors[0], parse(&source).unwrap_err(), "{:?}", path);
+        assert_eq!(print(&ast.unwrap()), source, "{:?}", path);
+    }
+}
+
+// The code of each top level statement, with error statements in brackets
+fn stmts(ast: &Ast) -> Vec<String> {
+    ast.nodes()
+        .iter_stmts()
+        .map(|stmt| match stmt {
+            Stmt::Error(error) => format!(
+                "[{}]",
+                error
+                    .tokens()
+                    .map(|token| token.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            other => other.print(TriviaBehavior::Keep),
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_all_errors_partial_ast() {
+    let code = "x = 1\ncall(,)\nif x then\nend\nfunction f() y = = 2 end\nz = 3\n";
+    let (ast, errors) = parse_all_errors(code, 10);
+    let ast = ast.unwrap();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        stmts(&ast),
+        vec![
+            "x = 1\n",
+            "[call ( , )]",
+            "if x then\nend\n",
+            "[function f ( ) y = = 2 end]",
+            "z = 3\n",
+        ]
+    );
+
+    // The healthy statements keep their positions
+    let last = ast.nodes().iter_stmts().last().unwrap();
+    assert_eq!(
+        last.start_position().unwrap().bytes(),
+        code.find("z = 3").unwrap()
+    );
+    assert_eq!(print(&ast), code);
+}
+
+#[test]
+fn test_parse_all_errors_error_expressions() {
+    let code = "local x = )\ny, z = 1 +\nreturn";
+    let (ast, errors) = parse_all_errors(code, 10);
+    let ast = ast.unwrap();
+    assert_eq!(errors.len(), 2);
+
+    let values: Vec<_> = ast
+        .nodes()
+        .iter_stmts()
+        .map(|stmt| {
+            let values = match stmt {
+                Stmt::LocalAssignment(local) => local.expr_list(),
+                Stmt::Assignment(assignment) => assignment.expr_list(),
+                other => panic!("unexpected statement {:?}", other),
+            };
+
+            match values.iter().collect::<Vec<_>>().as_slice() {
+                [Expression::Error(error)] => error.tokens().count(),
+                other => panic!("unexpected values {:?}", other),
+            }
+        })
+        .collect();
+
+    // Parsing starts again at `return`, since it always starts a statement
+    assert_eq!(values, vec![1, 2]);
+    assert!(ast.nodes().last_stmts().is_some());
+
+    let (ast, _) = parse_all_errors("x = 1\nreturn )", 10);
+    let ast = ast.unwrap();
+    match ast.nodes().last_stmts() {
+        Some(LastStmt::Return(ret)) => match ret.returns().iter().next() {
+            Some(Expression::Error(error)) => assert_eq!(error.tokens().count(), 1),
+            other => panic!("unexpected return value {:?}", other),
+        },
+        other => panic!("unexpected last statement {:?}", other),
+    }
+
+    // A missing value at the end of the code is an error expression with no tokens
+    let (ast, _) = parse_all_errors("local x =", 10);
+    let ast = ast.unwrap();
+    match ast.nodes().iter_stmts().next() {
+        Some(Stmt::LocalAssignment(local)) => match local.expr_list().iter().next() {
+            Some(Expression::Error(error)) => {
+                assert_eq!(error.tokens().count(), 0);
+                assert!(error.range().is_none());
+            }
+            other => panic!("unexpected value {:?}", other),
+        },
+        other => panic!("unexpected statement {:?}", other),
+    };
+}
+
+#[derive(Default)]
+struct CountErrors {
+    stmts: usize,
+    expressions: usize,
+    calls: usize,
+}
+
+impl<'ast> Visitor<'ast> for CountErrors {
+    fn visit_error_stmt(&mut self, _: &ErrorStmt<'ast>) {
+        self.stmts += 1;
+    }
+
+    fn visit_error_expression(&mut self, _: &ErrorExpression<'ast>) {
+        self.expressions += 1;
+    }
+
+    fn visit_function_call(&mut self, _: &full_moon::ast::FunctionCall<'ast>) {
+        self.calls += 1;
+    }
+}
+
+#[test]
+fn test_parse_all_errors_visitors() {
+    let (ast, _) = parse_all_errors("a()\nb(,)\nlocal x = )\nc()\nend", 10);
+    let mut visitor = CountErrors::default();
+    visitor.visit_ast(&ast.unwrap());
+
+    assert_eq!(visitor.stmts, 2);
+    assert_eq!(visitor.expressions, 1);
+    // Calls in error statements aren't parsed, so they aren't visited
+    assert_eq!(visitor.calls, 2);
+}
@@ -0,0 +1,136 @@
+use full_moon::{
+    ast::{AstError, Stmt},
+    parse, parse_fallible, print, CancellationToken, Error, ErrorCode, ParseConfig,
+};
+use std::fs;
+
+#[test]
+fn test_parse_fallible_valid() {
+    for code in &[
+        "",
+        "-- just a comment\n",
+        "local x = 1\nprint(x)",
+        "function f(a, ...) return a, ... end",
+        "local t = { 1, [2] = 3, x = { y = 4 } }",
+    ] {
+        let result = parse_fallible(code, &ParseConfig::default());
+        assert_eq!(result.errors, Vec::new(), "{:?}", code);
+        assert_eq!(
+            result.ast.nodes(),
+            parse(code).unwrap().nodes(),
+            "{:?}",
+            code
+        );
+        assert_eq!(print(&result.ast), *code);
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_parse_fallible_pass_cases() {
+    for entry in fs::read_dir("./tests/cases/pass").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+
+        let result = parse_fallible(&source, &ParseConfig::default());
+        assert_eq!(result.errors, Vec::new(), "{:?}", path);
+        assert_eq!(
+            result.ast.nodes(),
+            parse(&source).unwrap().nodes(),
+            "{:?}",
+            path
+        );
+    }
+}
+
+#[test]
+fn test_parse_fallible_errors() {
+    let code = "local x = \nlocal = 1\ncall(,)\ny = '\\300'";
+    let result = parse_fallible(code, &ParseConfig::default());
+    assert_eq!(print(&result.ast), code);
+    assert!(matches!(
+        result.errors.as_slice(),
+        [
+            Error::AstError(_),
+            Error::AstError(_),
+            Error::AstError(_),
+            Error::TokenizerError(_)
+        ]
+    ));
+
+    // parse stops at tokenizer errors first, but the first error is the same without them
+    assert_eq!(
+        result.errors[0],
+        parse("local x = \nlocal = 1").unwrap_err()
+    );
+
+    let errors = result.into_result().unwrap_err();
+    assert_eq!(errors.len(), 4);
+    assert_eq!(errors[3].code(), ErrorCode::InvalidEscape);
+}
+
+#[test]
+fn test_parse_fallible_tokenizer_error() {
+    let result = parse_fallible("x = 1\ny = 'unclosed", &ParseConfig::default());
+    assert!(matches!(
+        result.errors.as_slice(),
+        [Error::TokenizerError(_)]
+    ));
+    assert_eq!(result.ast.nodes().iter_stmts().count(), 0);
+    assert_eq!(print(&result.ast), "");
+}
+
+#[test]
+fn test_parse_fallible_config() {
+    let code = "x = = 1\n".repeat(10);
+    let config = ParseConfig {
+        max_errors: 3,
+        ..ParseConfig::default()
+    };
+    assert_eq!(parse_fallible(&code, &config).errors.len(), 3);
+    assert_eq!(
+        parse_fallible(&code, &ParseConfig::default()).errors.len(),
+        10
+    );
+
+    let code = "do do do end end end\ny = 2";
+    let config = ParseConfig {
+        recursion_limit: 3,
+        ..ParseConfig::default()
+    };
+    let result = parse_fallible(code, &config);
+    assert!(matches!(
+        result.errors.as_slice(),
+        [Error::AstError(AstError::RecursionLimitExceeded {
+            limit: 3,
+            ..
+        })]
+    ));
+    assert_eq!(print(&result.ast), code);
+}
+
+#[test]
+fn test_parse_fallible_cancelled() {
+    let cancellation = CancellationToken::new();
+    cancellation.cancel();
+
+    let code = "local x = 1\ncall(,)\nlocal y = 2";
+    let config = ParseConfig {
+        cancellation: Some(cancellation),
+        ..ParseConfig::default()
+    };
+    let result = parse_fallible(code, &config);
+    assert_eq!(result.errors, vec![Error::AstError(AstError::Cancelled)]);
+
+    // The code that wasn't parsed is all one error statement
+    assert_eq!(print(&result.ast), code);
+    assert!(matches!(
+        result
+            .ast
+            .nodes()
+            .iter_stmts()
+            .collect::<Vec<_>>()
+            .as_slice(),
+        [Stmt::Error(_)]
+    ));
+}
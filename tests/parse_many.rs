@@ -0,0 +1,77 @@
+#![cfg(feature = "rayon")]
+use full_moon::{parse, parse_many, parse_many_for_each, print};
+use pretty_assertions::assert_eq;
+use std::sync::Mutex;
+
+mod common;
+use common::read_sources;
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_parse_many_is_the_same_as_parse() {
+    let mut sources = read_sources("./tests/cases/pass");
+    sources.extend(read_sources("./tests/cases/fail/parser"));
+
+    let results = parse_many(sources.iter().map(String::as_str));
+    assert_eq!(results.len(), sources.len());
+
+    for (source, result) in sources.iter().zip(results) {
+        match (result, parse(source)) {
+            (Ok(ast), Ok(expected)) => {
+                assert_eq!(ast.nodes(), expected.nodes());
+                assert_eq!(print(&ast), *source);
+            }
+
+            (Err(error), Err(expected)) => assert_eq!(error, expected),
+
+            (result, expected) => panic!(
+                "parse_many gave {:?}, but parse gave {:?} for {:?}",
+                result.err(),
+                expected.err(),
+                source
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_parse_many_keeps_the_order() {
+    let sources: Vec<String> = (0..500).map(|x| format!("local x = {}", x)).collect();
+    let results = parse_many(sources.iter().map(String::as_str));
+    for (source, result) in sources.iter().zip(results) {
+        assert_eq!(print(&result.unwrap()), *source);
+    }
+
+    assert!(parse_many(Vec::new()).is_empty());
+}
+
+#[test]
+fn test_parse_many_for_each() {
+    let sources: Vec<String> = (0..500)
+        .map(|x| {
+            if x % 7 == 0 {
+                format!("local x = {} +", x)
+            } else {
+                format!("local x = {}", x)
+            }
+        })
+        .collect();
+
+    let finished = Mutex::new(Vec::new());
+    parse_many_for_each(sources.iter().map(String::as_str), |index, result| {
+        let printed = result.map(|ast| print(&ast)).ok();
+        finished.lock().unwrap().push((index, printed));
+    });
+
+    let mut finished = finished.into_inner().unwrap();
+    finished.sort();
+    assert_eq!(finished.len(), sources.len());
+    for (number, (index, printed)) in finished.into_iter().enumerate() {
+        assert_eq!(index, number);
+        if number % 7 == 0 {
+            assert_eq!(printed, None);
+        } else {
+            assert_eq!(printed.as_ref(), Some(&sources[number]));
+        }
+    }
+}
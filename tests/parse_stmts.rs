@@ -0,0 +1,166 @@
+use full_moon::{
+    ast::Stmt,
+    node::{Node, TriviaBehavior},
+    parse, parse_fallible, parse_stmts, parse_stmts_fallible, parse_stmts_with, Error, ParseConfig,
+};
+use pretty_assertions::assert_eq;
+
+mod common;
+use common::{read_sources, without_trivia};
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_parse_stmts_is_the_same_as_parse() {
+    for source in read_sources("./tests/cases/pass") {
+        let ast = parse(&source).unwrap();
+        let mut stream = parse_stmts(&source);
+        let stmts: Vec<Stmt> = stream.by_ref().map(Result::unwrap).collect();
+        let expected: Vec<&Stmt> = ast.nodes().iter_stmts().collect();
+
+        assert_eq!(stmts.iter().collect::<Vec<_>>(), expected, "{}", source);
+        assert_eq!(stream.last_stmt(), ast.nodes().last_stmts(), "{}", source);
+
+        for (stmt, expected) in stmts.iter().zip(expected) {
+            assert_eq!(
+                stmt.print(TriviaBehavior::Keep),
+                expected.print(TriviaBehavior::Keep)
+            );
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_parse_stmts_errors_are_the_same_as_parse() {
+    for source in read_sources("./tests/cases/fail/parser") {
+        let error = parse(&source).unwrap_err();
+        let items: Vec<_> = parse_stmts(&source).collect();
+        let (last, stmts) = items.split_last().expect("there should be an error");
+
+        assert!(stmts.iter().all(Result::is_ok), "{}", source);
+        assert_eq!(last.as_ref().unwrap_err(), &error, "{}", source);
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_parse_stmts_fallible_errors_are_the_same_as_parse_fallible() {
+    let config = ParseConfig::default();
+    for source in read_sources("./tests/cases/fail/parser") {
+        let expected = parse_fallible(&source, &config).errors;
+        let errors: Vec<Error> = parse_stmts_fallible(&source, &config)
+            .filter_map(Result::err)
+            .collect();
+
+        assert_eq!(errors, expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_parse_stmts_statements_are_on_their_own() {
+    // Long enough that neither of the first two statements fits in the tokens lexed for it at first
+    let list = (0..100)
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let code = format!(
+        "-- one\nlocal x = {{ {} }} -- two\n\nf\n(x)({})\nreturn x;\n-- three\n",
+        list, list
+    );
+
+    let mut stream = parse_stmts(&code);
+    let stmts: Vec<_> = stream.by_ref().map(Result::unwrap).collect();
+    assert_eq!(stmts.len(), 2);
+    assert_eq!(
+        stmts[0].print(TriviaBehavior::Keep),
+        format!("-- one\nlocal x = {{ {} }} -- two\n", list)
+    );
+    assert_eq!(
+        stmts[1].print(TriviaBehavior::Keep),
+        format!("\nf\n(x)({})\n", list)
+    );
+    assert_eq!(
+        stream.last_stmt().unwrap().print(TriviaBehavior::Trim),
+        "return x"
+    );
+
+    let ast = parse(&code).unwrap();
+    let expected: Vec<_> = ast.nodes().iter_stmts().collect();
+    assert_eq!(stmts.iter().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn test_parse_stmts_stops_at_errors() {
+    let mut stream = parse_stmts("local x = 1\nreturn x\nlocal y = 2");
+    assert!(stream.next().unwrap().is_ok());
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+    assert!(stream.last_stmt().is_none());
+
+    // Tokenizing stops at the error, but the statements before it are still given
+    let mut stream = parse_stmts("local x = 1\nlocal y = 'unclosed");
+    assert!(stream.next().unwrap().is_ok());
+    assert!(matches!(stream.next(), Some(Err(Error::TokenizerError(_)))));
+    assert!(stream.next().is_none());
+
+    assert!(parse_stmts("").next().is_none());
+    assert!(parse_stmts("-- only a comment\n").next().is_none());
+}
+
+#[test]
+fn test_parse_stmts_fallible_keeps_going() {
+    let code = "local x = '\\300'\nlocal y = \nif x then\n  call(,)\nend\nprint(x, y)\nreturn";
+    let expected = parse_fallible(code, &ParseConfig::default()).errors;
+    let mut stream = parse_stmts_fallible(code, &ParseConfig::default());
+    let items: Vec<_> = stream.by_ref().collect();
+
+    assert_eq!(items.len(), 5);
+    assert!(matches!(items[0], Err(Error::TokenizerError(_))));
+    assert_eq!(
+        items[1].as_ref().unwrap().print(TriviaBehavior::Trim),
+        "local x = '\\300'"
+    );
+    // The `if` starts the statement after `local y = `, and has an error of its own
+    assert!(matches!(items[2], Err(Error::AstError(_))));
+    assert!(matches!(items[3], Err(Error::AstError(_))));
+    assert_eq!(
+        items[4].as_ref().unwrap().print(TriviaBehavior::Trim),
+        "print(x, y)"
+    );
+    assert_eq!(
+        items
+            .iter()
+            .filter_map(|item| item.clone().err())
+            .collect::<Vec<_>>(),
+        expected
+    );
+    assert_eq!(
+        stream.last_stmt().unwrap().print(TriviaBehavior::Trim),
+        "return"
+    );
+
+    let config = ParseConfig {
+        max_errors: 1,
+        ..ParseConfig::default()
+    };
+
+    let errors = parse_stmts_fallible("local x = \nlocal y = \nlocal z = 1", &config)
+        .filter(Result::is_err)
+        .count();
+    assert_eq!(errors, 1);
+}
+
+#[test]
+fn test_parse_stmts_without_trivia() {
+    let config = without_trivia();
+
+    let code = "local x = 1 -- one\nprint(x)";
+    let stmts: Vec<_> = parse_stmts_with(code, &config)
+        .map(Result::unwrap)
+        .collect();
+    let ast = full_moon::parse_with(code, &config).unwrap();
+    assert_eq!(
+        stmts.iter().collect::<Vec<_>>(),
+        ast.nodes().iter_stmts().collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,134 @@
+use full_moon::{
+    ast::AstError,
+    parse_tokens, print,
+    tokenizer::{self, Symbol, Token, TokenType},
+    Error,
+};
+use pretty_assertions::assert_eq;
+use std::borrow::Cow;
+
+fn identifier(name: &str) -> Token<'_> {
+    Token::from(TokenType::Identifier {
+        identifier: Cow::Borrowed(name),
+    })
+}
+
+fn space() -> Token<'static> {
+    Token::from(TokenType::Whitespace {
+        characters: Cow::Borrowed(" "),
+    })
+}
+
+// The index and code of the error, for errors at a token
+fn error_index(tokens: Vec<Token>) -> (usize, String) {
+    match parse_tokens(tokens) {
+        Err(error @ Error::TokensError { .. }) => match &error {
+            Error::TokensError { index, .. } => (*index, error.code().to_string()),
+            _ => unreachable!(),
+        },
+        other => panic!("expected an error at a token, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_tokens() {
+    let code = "local x = 1 -- one\nprint(x)";
+    let ast = parse_tokens(tokenizer::tokens(code).unwrap()).unwrap();
+    assert_eq!(print(&ast), code);
+    assert_eq!(ast.nodes(), full_moon::parse(code).unwrap().nodes());
+
+    // Tokens made from a token type have no positions to check, until they get them from the Ast
+    let mut tokens = tokenizer::tokens(code).unwrap();
+    let eof = tokens.pop().unwrap();
+    tokens.extend(vec![
+        Token::from(TokenType::Whitespace {
+            characters: Cow::Borrowed("\n"),
+        }),
+        identifier("print"),
+        Token::from(TokenType::Symbol {
+            symbol: Symbol::LeftParen,
+        }),
+        identifier("y"),
+        Token::from(TokenType::Symbol {
+            symbol: Symbol::RightParen,
+        }),
+        eof,
+    ]);
+
+    let mut ast = parse_tokens(tokens).unwrap();
+    assert_eq!(ast.nodes().iter_stmts().count(), 3);
+    ast.update_positions();
+    assert_eq!(print(&ast), "local x = 1 -- one\nprint(x)\nprint(y)");
+}
+
+#[test]
+fn test_invariants() {
+    assert_eq!(
+        parse_tokens(Vec::new()).unwrap_err(),
+        Error::AstError(AstError::Empty)
+    );
+
+    let mut tokens = tokenizer::tokens("x = 1").unwrap();
+    tokens.pop();
+    assert_eq!(
+        parse_tokens(tokens).unwrap_err(),
+        Error::AstError(AstError::NoEof)
+    );
+
+    // An end of file token from the tokenizer in the middle
+    let mut tokens = tokenizer::tokens("x = 1").unwrap();
+    let eof = tokens.last().unwrap().clone();
+    tokens.insert(2, eof);
+    assert_eq!(error_index(tokens), (2, "FM2038".to_owned()));
+
+    // The `1` is moved before the `=`, which starts before the `1` ends
+    let mut tokens = tokenizer::tokens("x = 1").unwrap();
+    let one = tokens.remove(4);
+    tokens.insert(1, one);
+    assert_eq!(error_index(tokens), (2, "FM2039".to_owned()));
+
+    // Tokens without positions don't count, even between ones that are out of order
+    let mut tokens = tokenizer::tokens("x = 1").unwrap();
+    tokens.insert(1, space());
+    tokens.swap(0, 4);
+    assert_eq!(error_index(tokens), (2, "FM2039".to_owned()));
+}
+
+#[test]
+fn test_parse_errors() {
+    // `local x = = 1`, where the second `=` has no position, so only its index says where it is
+    let mut tokens = tokenizer::tokens("local x = 1").unwrap();
+    tokens.insert(5, space());
+    tokens.insert(
+        6,
+        Token::from(TokenType::Symbol {
+            symbol: Symbol::Equal,
+        }),
+    );
+
+    let error = parse_tokens(tokens).unwrap_err();
+    assert_eq!(error.code().to_string(), "FM2015");
+    assert_eq!(error.message(), "unexpected token");
+    match &error {
+        Error::TokensError {
+            index,
+            error: AstError::UnexpectedToken { token, .. },
+        } => {
+            assert_eq!(*index, 6);
+            assert_eq!(token.to_string(), "=");
+        }
+        _ => panic!("expected an unexpected token, got {:?}", error),
+    }
+    assert!(error.to_string().ends_with("\nat index 6 of the tokens"));
+
+    // A token left after the code
+    let mut tokens = tokenizer::tokens("x = 1").unwrap();
+    tokens.insert(5, space());
+    tokens.insert(
+        6,
+        Token::from(TokenType::Symbol {
+            symbol: Symbol::End,
+        }),
+    );
+    assert_eq!(error_index(tokens), (6, "FM2034".to_owned()));
+}
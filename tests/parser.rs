@@ -0,0 +1,66 @@
+use full_moon::{ast::AstError, parse, parse_with_capacity, print, Error, ParseConfig, Parser};
+use pretty_assertions::assert_eq;
+
+mod common;
+use common::read_sources;
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_parser_is_the_same_as_parse() {
+    let mut parser = Parser::new();
+    let mut sources = read_sources("./tests/cases/pass");
+    sources.extend(read_sources("./tests/cases/fail/parser"));
+    sources.extend(read_sources("./tests/cases/fail/tokenizer"));
+
+    // Twice, so the second time uses the buffers from parsing every file
+    for _ in 0..2 {
+        for source in &sources {
+            match (parser.parse(source), parse(source)) {
+                (Ok(parsed), Ok(expected)) => {
+                    assert_eq!(parsed.nodes(), expected.nodes());
+                    assert_eq!(
+                        parsed.iter_tokens().collect::<Vec<_>>(),
+                        expected.iter_tokens().collect::<Vec<_>>()
+                    );
+                    assert_eq!(print(&parsed), *source);
+                }
+
+                (parsed, expected) => assert_eq!(parsed.err(), expected.err(), "{}", source),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_with_capacity() {
+    let code = "local x = { 1, 2, 3 }\nprint(#x)";
+    for capacity in &[0, 1, 10, 1000] {
+        let ast = parse_with_capacity(code, *capacity).unwrap();
+        assert_eq!(ast.nodes(), parse(code).unwrap().nodes());
+        assert_eq!(print(&ast), code);
+    }
+
+    assert_eq!(
+        parse_with_capacity("local x = ", 4).unwrap_err(),
+        parse("local x = ").unwrap_err()
+    );
+}
+
+#[test]
+fn test_parser_with_config() {
+    let mut parser = Parser::with_config(ParseConfig {
+        recursion_limit: 3,
+        ..ParseConfig::default()
+    });
+
+    assert_eq!(parser.config().recursion_limit, 3);
+    assert!(parser.parse("x = (1)").is_ok());
+    assert!(matches!(
+        parser.parse("do do do end end end"),
+        Err(Error::AstError(AstError::RecursionLimitExceeded {
+            limit: 3,
+            ..
+        }))
+    ));
+    assert!(parser.parse("x = (1)").is_ok());
+}
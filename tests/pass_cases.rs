@@ -49,6 +49,15 @@ fn test_pass_cases() {
                 .collect::<Vec<_>>(),
         );
 
+        let sexpr_path = path.join("ast.sexpr");
+        let sexpr = ast.to_sexpr();
+
+        if let Ok(expected_sexpr) = fs::read_to_string(&sexpr_path) {
+            assert_eq!(sexpr, expected_sexpr, "{:?}", sexpr_path);
+        } else {
+            fs::write(&sexpr_path, sexpr).expect("couldn't write sexpr file");
+        }
+
         let ast_path = path.join("ast.json");
 
         if let Ok(ast_file) = fs::read_to_string(&ast_path) {
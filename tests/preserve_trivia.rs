@@ -0,0 +1,126 @@
+use full_moon::{
+    ast::Ast,
+    parse, parse_fallible, parse_with, print,
+    tokenizer::{Token, TokenKind, TokenReference},
+    visitors::Visitor,
+    Parser,
+};
+use pretty_assertions::assert_eq;
+use std::fs;
+
+mod common;
+use common::without_trivia;
+
+// The tokens visited other than comments and whitespace, which only Asts with trivia have to visit
+#[derive(Default)]
+struct TokenVisitor(Vec<String>);
+
+impl<'ast> Visitor<'ast> for TokenVisitor {
+    fn visit_token(&mut self, token: &TokenReference<'ast>) {
+        if !token.token_type().ignore() {
+            self.0.push(token.to_string());
+        }
+    }
+}
+
+fn visited_tokens(ast: &Ast) -> Vec<String> {
+    let mut visitor = TokenVisitor::default();
+    visitor.visit_ast(ast);
+    visitor.0
+}
+
+// The tokens and the lines and characters they end at, which are the same after printing an Ast without trivia,
+// other than the end of the file, which has nothing before it
+fn layout<'a, 'b>(tokens: impl Iterator<Item = &'b Token<'a>>) -> Vec<(String, usize, usize)>
+where
+    'a: 'b,
+{
+    tokens
+        .filter(|token| !matches!(token.token_kind(), TokenKind::Whitespace | TokenKind::Eof))
+        .map(|token| {
+            let end = token.end_position();
+            (token.to_string(), end.line(), end.character())
+        })
+        .collect()
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_pass_cases_without_trivia() {
+    for entry in fs::read_dir("./tests/cases/pass").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).unwrap();
+        let without = parse_with(&source, &without_trivia()).unwrap();
+
+        assert!(ast.has_trivia());
+        assert!(!without.has_trivia());
+        assert_eq!(without.nodes(), ast.nodes(), "{:?}", path);
+        assert_eq!(visited_tokens(&without), visited_tokens(&ast), "{:?}", path);
+        assert_eq!(
+            without.comments().collect::<Vec<_>>(),
+            ast.comments().collect::<Vec<_>>(),
+            "{:?}",
+            path
+        );
+        assert_eq!(
+            without.iter_tokens().collect::<Vec<_>>(),
+            ast.iter_tokens()
+                .filter(|token| !token.token_type().ignore())
+                .collect::<Vec<_>>(),
+            "{:?}",
+            path
+        );
+
+        let printed = print(&without);
+        let reparsed = parse(&printed).unwrap();
+        assert_eq!(
+            layout(reparsed.iter_tokens()),
+            layout(ast.iter_tokens()),
+            "{:?}",
+            path
+        );
+    }
+}
+
+#[test]
+fn test_from_tokens_without_trivia() {
+    let tokens = full_moon::tokenizer::tokens("local x = 1 --[[ one ]] + 2").unwrap();
+    let ast = Ast::from_tokens_with(tokens, &without_trivia()).unwrap();
+    assert_eq!(ast.iter_tokens().count(), 7);
+    assert_eq!(ast.comments().count(), 1);
+    assert_eq!(print(&ast), "local x = 1 --[[ one ]] + 2");
+}
+
+#[test]
+fn test_parse_fallible_without_trivia() {
+    let result = parse_fallible("local x = 1 -- one\nlocal = 2\n", &without_trivia());
+    assert_eq!(result.errors.len(), 1);
+    assert!(!result.ast.has_trivia());
+    assert_eq!(result.ast.comments().count(), 1);
+    assert!(result
+        .ast
+        .iter_tokens()
+        .all(|token| !token.token_type().ignore()));
+}
+
+#[test]
+fn test_parser_without_trivia() {
+    let mut parser = Parser::with_config(without_trivia());
+    for _ in 0..2 {
+        let ast = parser.parse("local x = 1\n\nprint(x)\n").unwrap();
+        assert!(!ast.has_trivia());
+        assert_eq!(ast.iter_tokens().count(), 9);
+        assert_eq!(print(&ast), "local x = 1\n\nprint(x)");
+    }
+}
+
+#[test]
+fn test_errors_without_trivia() {
+    let code = "local x = \n\n  -- comment\n  end";
+    match (parse_with(code, &without_trivia()), parse(code)) {
+        (Err(error), Err(expected)) => assert_eq!(error, expected),
+
+        other => panic!("expected an ast error, got {:?}", other),
+    }
+}
@@ -0,0 +1,212 @@
+use full_moon::{
+    ast::owned::Owned, node::Node, parse, print_pretty, tokenizer::TokenKind, Indent, LineEnding,
+    PrettyConfig,
+};
+use std::fs;
+
+fn configs() -> Vec<PrettyConfig> {
+    vec![
+        PrettyConfig::default(),
+        PrettyConfig {
+            indent: Indent::Tabs,
+            line_ending: LineEnding::CrLf,
+            spaces_around_operators: false,
+            space_after_commas: false,
+            collapse_single_statement_blocks: true,
+            max_width: 120,
+            separate_ambiguous_statements: true,
+        },
+        PrettyConfig {
+            indent: Indent::Spaces(2),
+            collapse_single_statement_blocks: true,
+            ..PrettyConfig::default()
+        },
+        PrettyConfig {
+            max_width: 20,
+            ..PrettyConfig::default()
+        },
+    ]
+}
+
+fn comments(code: &str) -> Vec<String> {
+    parse(code)
+        .unwrap()
+        .iter_tokens()
+        .filter(|token| {
+            matches!(
+                token.token_kind(),
+                TokenKind::SingleLineComment | TokenKind::MultiLineComment
+            )
+        })
+        .map(|token| token.to_string().trim_end_matches('\r').to_owned())
+        .collect()
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_pretty_round_trip_pass_cases() {
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).expect("couldn't parse");
+
+        for config in configs() {
+            let pretty = print_pretty(&ast, &config);
+            let pretty_ast = parse(&pretty).unwrap_or_else(|error| {
+                panic!("{:?} printed unparsable code {:?}: {}", path, pretty, error)
+            });
+
+            assert!(
+                pretty_ast.nodes().owned().similar(&ast.nodes().owned()),
+                "{:?} printed different code {:?}",
+                path,
+                pretty
+            );
+            assert_eq!(comments(&pretty), comments(&source), "{:?}", path);
+            assert_eq!(print_pretty(&pretty_ast, &config), pretty, "{:?}", path);
+        }
+    }
+}
+
+#[test]
+fn test_pretty_indentation() {
+    let ast =
+        parse("local function f(a,b) if a then return a+b elseif b then do end else x=-1 end end")
+            .unwrap();
+    assert_eq!(
+        print_pretty(&ast, &PrettyConfig::default()),
+        "local function f(a, b)\n    if a then\n        return a + b\n    elseif b then\n        do end\n    else\n        x = -1\n    end\nend\n"
+    );
+}
+
+#[test]
+fn test_pretty_config() {
+    let ast =
+        parse("while x do f(1,2) end\nrepeat y = y*2 until y>10  t = {1,2;a=3,[4]=5}").unwrap();
+    assert_eq!(
+        print_pretty(
+            &ast,
+            &PrettyConfig {
+                indent: Indent::Tabs,
+                line_ending: LineEnding::CrLf,
+                spaces_around_operators: false,
+                space_after_commas: false,
+                collapse_single_statement_blocks: true,
+                max_width: 120,
+                separate_ambiguous_statements: true,
+            }
+        ),
+        "while x do f(1,2) end\r\nrepeat y = y*2 until y>10\r\nt = { 1,2;a = 3,[4] = 5 }\r\n"
+    );
+}
+
+#[test]
+fn test_pretty_required_spaces() {
+    let ast = parse("x = a - -b .. c and not d x = 1 .. 2 t[ [[s]] ] = 1").unwrap();
+    let config = PrettyConfig {
+        spaces_around_operators: false,
+        ..PrettyConfig::default()
+    };
+
+    assert_eq!(
+        print_pretty(&ast, &config),
+        "x = a- -b..c and not d\nx = 1 .. 2\nt[ [[s]]] = 1\n"
+    );
+}
+
+#[test]
+fn test_pretty_comments() {
+    let ast = parse(
+        "-- header\n\nlocal x = 1 -- one\n  -- before y\nlocal y = { -- table\n 2 }\nif x then\n-- empty\nend -- done\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        print_pretty(&ast, &PrettyConfig::default()),
+        "-- header\nlocal x = 1 -- one\n-- before y\nlocal y = { -- table\n    2\n}\nif x then\n    -- empty\nend -- done\n"
+    );
+}
+
+fn print_narrow(code: &str) -> String {
+    print_pretty(
+        &parse(code).unwrap(),
+        &PrettyConfig {
+            max_width: 20,
+            ..PrettyConfig::default()
+        },
+    )
+}
+
+#[test]
+fn test_pretty_wrap_arguments() {
+    assert_eq!(print_narrow("call(a, b)"), "call(a, b)\n");
+    assert_eq!(
+        print_narrow("call(first, second, third)"),
+        "call(\n    first,\n    second,\n    third\n)\n"
+    );
+    assert_eq!(
+        print_narrow("if x then call(first, second) end"),
+        "if x then\n    call(\n        first,\n        second\n    )\nend\n"
+    );
+}
+
+#[test]
+fn test_pretty_wrap_tables() {
+    assert_eq!(
+        print_narrow("t = { a = 1, { 2, 3 }, [4] = 5 }"),
+        "t = {\n    a = 1,\n    { 2, 3 },\n    [4] = 5\n}\n"
+    );
+    assert_eq!(
+        print_narrow("t = { 1; 2; 3; 4; 5; 6; }"),
+        "t = {\n    1;\n    2;\n    3;\n    4;\n    5;\n    6;\n}\n"
+    );
+}
+
+#[test]
+fn test_pretty_wrap_parameters() {
+    assert_eq!(
+        print_narrow("function f(first, second, ...) end"),
+        "function f(\n    first,\n    second,\n    ...\n) end\n"
+    );
+    assert_eq!(
+        print_narrow("pcall(function() return 1 end)"),
+        "pcall(function()\n    return 1\nend)\n"
+    );
+}
+
+#[test]
+fn test_pretty_wrap_comments() {
+    assert_eq!(
+        print_narrow("call(a, -- a\n-- before b\nb, --[[ c ]] c)"),
+        "call(\n    a, -- a\n    -- before b\n    b,\n    --[[ c ]] c\n)\n"
+    );
+    assert_eq!(
+        print_pretty(
+            &parse("t = { 1, -- one\n2 }").unwrap(),
+            &PrettyConfig::default()
+        ),
+        "t = {\n    1, -- one\n    2\n}\n"
+    );
+}
+
+#[test]
+fn test_pretty_token_kinds_unchanged() {
+    let source = "local s = [==[ long\nstring ]==] --[[ long\ncomment ]] print(s)";
+    let ast = parse(source).unwrap();
+    let pretty = print_pretty(&ast, &PrettyConfig::default());
+    let pretty = parse(&pretty).unwrap();
+
+    let kinds = |ast: &full_moon::ast::Ast| {
+        ast.iter_tokens()
+            .map(|token| token.token_kind())
+            .filter(|kind| *kind != TokenKind::Whitespace)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(kinds(&ast), kinds(&pretty));
+}
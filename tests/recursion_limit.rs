@@ -0,0 +1,281 @@
+use full_moon::{
+    ast::AstError, parse, parse_all_errors, parse_with, visitors::Visitor, Error, ErrorCode,
+    ParseConfig,
+};
+use std::thread;
+
+fn parse_with_config(code: &str, config: ParseConfig) -> Result<(), AstError<'_>> {
+    match parse_with(code, &config) {
+        Ok(_) => Ok(()),
+        Err(Error::AstError(error)) => Err(error),
+        Err(error) => panic!("{:?} couldn't be tokenized: {}", code, error),
+    }
+}
+
+// Expressions are limited separately, so they have no limit here
+fn parse_with_limit(code: &str, recursion_limit: usize) -> Result<(), AstError<'_>> {
+    parse_with_config(
+        code,
+        ParseConfig {
+            recursion_limit,
+            expression_depth_limit: usize::MAX,
+            ..ParseConfig::default()
+        },
+    )
+}
+
+fn parse_with_expression_limit(
+    code: &str,
+    expression_depth_limit: usize,
+) -> Result<(), AstError<'_>> {
+    parse_with_config(
+        code,
+        ParseConfig {
+            expression_depth_limit,
+            ..ParseConfig::default()
+        },
+    )
+}
+
+// Checks that the code nested `limit` levels deep parses, and that one more level is too many.
+// `nested` makes the code nested `depth` levels deep, counting the block of the whole code.
+fn assert_limit(limit: usize, nested: impl Fn(usize) -> String) {
+    assert_limit_with(limit, parse_with_limit, nested);
+}
+
+fn assert_limit_with(
+    limit: usize,
+    parse: impl for<'a> Fn(&'a str, usize) -> Result<(), AstError<'a>>,
+    nested: impl Fn(usize) -> String,
+) {
+    for depth in &[limit - 1, limit] {
+        let code = nested(*depth);
+        if let Err(error) = parse(&code, limit) {
+            panic!("{:?} went past the limit of {}: {}", code, limit, error);
+        }
+    }
+
+    let code = nested(limit + 1);
+    match parse(&code, limit) {
+        Err(AstError::RecursionLimitExceeded {
+            limit: error_limit, ..
+        }) => assert_eq!(error_limit, limit),
+        other => panic!(
+            "{:?} didn't go past the limit of {}: {:?}",
+            code, limit, other
+        ),
+    }
+}
+
+#[test]
+fn test_recursion_limit_blocks() {
+    assert_limit(30, |depth| {
+        format!("{}{}", "do ".repeat(depth - 1), "end ".repeat(depth - 1))
+    });
+
+    assert_limit(30, |depth| {
+        format!(
+            "{}{}",
+            "if x then ".repeat(depth - 1),
+            "end ".repeat(depth - 1)
+        )
+    });
+
+    // Every function is both an expression and a block
+    assert_limit(30, |depth| {
+        let functions = (depth - 1) / 2;
+        let code = format!(
+            "{}{}",
+            "x = function() ".repeat(functions),
+            "end ".repeat(functions)
+        );
+
+        if depth % 2 == 0 {
+            code
+        } else {
+            format!("do {} end", code)
+        }
+    });
+}
+
+#[test]
+fn test_recursion_limit_uncounted() {
+    // Expressions only count towards the expression depth limit, so however deeply they're nested in each other,
+    // they're one level
+    for code in &[
+        format!("x = {}{}", "{".repeat(1000), "}".repeat(1000)),
+        format!("x = {}1{}", "{ a = ".repeat(1000), " }".repeat(1000)),
+        format!("x = {}1{}", "{ [{ 1 }] = ".repeat(1000), " }".repeat(1000)),
+        format!("f{}{}", "{".repeat(1000), "}".repeat(1000)),
+        format!("x = 1{}", " + 1".repeat(1000)),
+        format!("x = {}1", "- ".repeat(1000)),
+        format!("x = {}1", "not ".repeat(1000)),
+        format!("x = {}1{}", "(".repeat(1000), ")".repeat(1000)),
+        format!("x = {}1{}", "f(".repeat(1000), ")".repeat(1000)),
+        format!("x = {}1{}", "t[".repeat(1000), "]".repeat(1000)),
+        format!("x = {}1{}", "a:b(".repeat(1000), ")".repeat(1000)),
+        format!("x = {}1{}", "f{ ".repeat(1000), " }".repeat(1000)),
+        format!("x = {{ {}1{} }}", "(".repeat(1000), ")".repeat(1000)),
+    ] {
+        assert!(
+            parse_with_limit(code, 2).is_ok(),
+            "{:?} went past the limit",
+            code
+        );
+    }
+
+    // Suffixes after each other aren't nested in each other either
+    assert!(parse_with_limit(&format!("x = a{}", ".b".repeat(1000)), 2).is_ok());
+    assert!(parse_with_limit(&format!("a{}", "()".repeat(1000)), 2).is_ok());
+
+    // What's in a function in an expression still counts, since it's a block
+    assert_limit(8, |depth| {
+        let functions = (depth - 1) / 2;
+        let code = format!(
+            "x = {}{}",
+            "f(function() return ".repeat(functions),
+            "end)".repeat(functions)
+        );
+
+        if depth % 2 == 0 {
+            code
+        } else {
+            format!("do {} end", code)
+        }
+    });
+}
+
+#[test]
+fn test_expression_depth_limit() {
+    // `depth` is how many expressions are in each other, not counting the outermost one
+    for nested in &[
+        |depth: usize| format!("x = {}1{}", "(".repeat(depth), ")".repeat(depth)),
+        |depth: usize| format!("x = {}1{}", "f(".repeat(depth), ")".repeat(depth)),
+        |depth: usize| format!("x = {}1{}", "t[".repeat(depth), "]".repeat(depth)),
+        |depth: usize| format!("x = {}1{}", "{ ".repeat(depth), " }".repeat(depth)),
+        |depth: usize| format!("x = {}1{}", "{ [1] = ".repeat(depth), " }".repeat(depth)),
+        |depth: usize| format!("x = 1{}", " + 1".repeat(depth)),
+        |depth: usize| format!("x = {}1", "- ".repeat(depth)),
+        |depth: usize| format!("x = {}1", "not ".repeat(depth)),
+        |depth: usize| format!("{}1{}", "f(".repeat(depth), ")".repeat(depth)),
+    ] {
+        assert_limit_with(30, parse_with_expression_limit, nested);
+    }
+
+    // Expressions in a function in an expression count along with the ones the function is in
+    assert_limit_with(30, parse_with_expression_limit, |depth| {
+        format!(
+            "x = {}function() return {}1{} end{}",
+            "(".repeat(depth / 2),
+            "(".repeat(depth - depth / 2),
+            ")".repeat(depth - depth / 2),
+            ")".repeat(depth / 2)
+        )
+    });
+
+    // Suffixes after each other aren't nested in each other
+    assert!(parse_with_expression_limit(&format!("x = a{}", ".b".repeat(1000)), 2).is_ok());
+    assert!(parse_with_expression_limit(&format!("a{}", "()".repeat(1000)), 2).is_ok());
+}
+
+#[test]
+fn test_expression_depth_limit_default() {
+    struct Visit;
+    impl Visitor<'_> for Visit {}
+
+    assert_eq!(ParseConfig::default().expression_depth_limit, 200);
+
+    // Code nested as deeply as the default allows can be used on a thread with a 2 MiB stack, even in debug builds.
+    // Each level of `f{` is two, since the table is in the call and its field is in the table.
+    thread::Builder::new()
+        .stack_size(2 * 1024 * 1024)
+        .spawn(|| {
+            for (nested, levels) in &[
+                (
+                    (|depth: usize| format!("x = {}1{}", "f(".repeat(depth), ")".repeat(depth)))
+                        as fn(usize) -> String,
+                    200,
+                ),
+                (
+                    |depth| format!("x = {}1{}", "{ ".repeat(depth), " }".repeat(depth)),
+                    200,
+                ),
+                (
+                    |depth| format!("x = {}1{}", "f{ ".repeat(depth), " }".repeat(depth)),
+                    100,
+                ),
+                (|depth| format!("x = 1{}", " + 1".repeat(depth)), 200),
+            ] {
+                let code = nested(*levels);
+                let ast = parse(&code).unwrap();
+                let cloned = ast.clone();
+                Visit.visit_ast(&cloned);
+                assert_eq!(cloned.nodes(), ast.nodes());
+                assert!(!format!("{:?}", cloned.nodes()).is_empty());
+
+                match parse(&nested(levels + 1)) {
+                    Err(Error::AstError(AstError::RecursionLimitExceeded {
+                        limit: 200, ..
+                    })) => {}
+                    other => panic!(
+                        "parsing didn't stop at the expression depth limit: {:?}",
+                        other
+                    ),
+                }
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn test_recursion_limit_error() {
+    let error = parse_with(
+        "do do do do end end end end",
+        &ParseConfig {
+            recursion_limit: 4,
+            ..ParseConfig::default()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(error.code(), ErrorCode::RecursionLimitExceeded);
+    assert_eq!(error.message(), "code is nested too deeply");
+    assert_eq!(error.position().unwrap().bytes(), 12);
+    assert_eq!(
+        error.to_string(),
+        "code is nested too deeply at line 1, character 13\nthe recursion limit is 4"
+    );
+}
+
+#[test]
+fn test_recursion_limit_default() {
+    assert_eq!(ParseConfig::default().recursion_limit, 200);
+
+    // Debug builds take a lot of stack for every level, so this runs with more than the default for tests
+    thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(|| {
+            for code in &[
+                format!("{}{}", "do ".repeat(5000), "end ".repeat(5000)),
+                format!(
+                    "x = {}{}",
+                    "function() return ".repeat(5000),
+                    "end ".repeat(5000)
+                ),
+            ] {
+                match parse(code) {
+                    Err(Error::AstError(AstError::RecursionLimitExceeded {
+                        limit: 200, ..
+                    })) => {}
+                    other => panic!("parsing didn't stop at the recursion limit: {:?}", other),
+                }
+
+                let (_, errors) = parse_all_errors(code, 10);
+                assert_eq!(errors[0].code(), ErrorCode::RecursionLimitExceeded);
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
@@ -0,0 +1,110 @@
+use full_moon::{
+    node::Node,
+    parse, print,
+    transform::{reindent, IndentConfig},
+    visitors::NodeKind,
+    Indent,
+};
+use std::fs;
+
+fn reindented(code: &str, config: &IndentConfig, depth: usize) -> String {
+    let mut ast = parse(code).unwrap();
+    reindent(ast.nodes_mut(), config, depth);
+    print(&ast)
+}
+
+#[test]
+fn test_reindent_blocks() {
+    let code = "local function f(a)\nif a then\n  return 1\n      elseif b then\nfor i = 1, 2 do\nprint(i)\nend\nelse\n        repeat\nx()\n  until x\nend\nend\n";
+    assert_eq!(
+        reindented(code, &IndentConfig::default(), 0),
+        "local function f(a)\n    if a then\n        return 1\n    elseif b then\n        for i = 1, 2 do\n            print(i)\n        end\n    else\n        repeat\n            x()\n        until x\n    end\nend\n"
+    );
+}
+
+#[test]
+fn test_reindent_depth_and_config() {
+    let code = "\ndo\nx = 1\nend\ny = 2";
+    let config = IndentConfig {
+        indent: Indent::Tabs,
+    };
+
+    assert_eq!(
+        reindented(code, &config, 2),
+        "\n\t\tdo\n\t\t\tx = 1\n\t\tend\n\t\ty = 2"
+    );
+}
+
+#[test]
+fn test_reindent_continuation_lines() {
+    let code = "local t = {\na = 1,\nb = {\nc = 2,\n},\n}\nlocal x = a and\nb\ncall(function()\nreturn 1\nend)\n";
+    assert_eq!(
+        reindented(code, &IndentConfig::default(), 0),
+        "local t = {\n    a = 1,\n    b = {\n        c = 2,\n    },\n}\nlocal x = a and\n    b\ncall(function()\n    return 1\nend)\n"
+    );
+}
+
+#[test]
+fn test_reindent_comments_and_strings() {
+    let code =
+        "do\n-- comment\n  local s = [[\n  keep\n]] --[[ long\n    comment ]]\n\nreturn s\nend";
+    assert_eq!(
+        reindented(code, &IndentConfig::default(), 0),
+        "do\n    -- comment\n    local s = [[\n  keep\n]] --[[ long\n    comment ]]\n\n    return s\nend"
+    );
+}
+
+#[test]
+fn test_reindent_subtree() {
+    let mut ast = parse("do\n  if x then\n  y()\n  end\nend").unwrap();
+    let (path, _) = ast
+        .nodes_with_paths()
+        .find(|(_, node)| node.kind() == NodeKind::If)
+        .unwrap();
+
+    ast.modify_at_path(&path, |node| {
+        if let full_moon::visitors::AnyNodeMut::If(if_block) = node {
+            reindent(if_block, &IndentConfig::default(), 3);
+        }
+    });
+
+    assert_eq!(
+        print(&ast),
+        "do\n            if x then\n                y()\n            end\nend"
+    );
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_reindent_pass_cases() {
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let once = reindented(&source, &IndentConfig::default(), 1);
+        let new_ast = parse(&once).unwrap_or_else(|error| {
+            panic!(
+                "{:?} reindented to unparsable code {:?}: {}",
+                path, once, error
+            )
+        });
+
+        assert!(
+            new_ast.nodes().similar(parse(&source).unwrap().nodes()),
+            "{:?} reindented to different code {:?}",
+            path,
+            once
+        );
+
+        assert_eq!(
+            reindented(&once, &IndentConfig::default(), 1),
+            once,
+            "{:?}",
+            path
+        );
+    }
+}
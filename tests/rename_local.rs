@@ -0,0 +1,144 @@
+use full_moon::{
+    parse, print,
+    refactor::{rename_local, RenameError},
+    tokenizer::Position,
+};
+use pretty_assertions::assert_eq;
+
+// The position of the token that starts at the bytes
+fn position(code: &str, bytes: usize) -> Position {
+    parse(code)
+        .unwrap()
+        .iter_tokens()
+        .map(|token| token.start_position())
+        .find(|position| position.bytes() == bytes)
+        .unwrap()
+}
+
+// The code after renaming the local at the bytes
+fn rename(code: &str, bytes: usize, new_name: &str) -> Result<String, RenameError> {
+    let mut ast = parse(code).unwrap();
+    rename_local(&mut ast, position(code, bytes), new_name)?;
+    Ok(print(&ast))
+}
+
+#[test]
+fn test_rename() {
+    let code = "local x = 1 -- x\nlocal function f(x) return x end\nprint(x, f(x), \"x\", t.x)";
+
+    // From the declaration, or from any name that refers to the local
+    for bytes in &[6, 56, 61] {
+        assert_eq!(
+            rename(code, *bytes, "count").unwrap(),
+            "local count = 1 -- x\nlocal function f(x) return x end\nprint(count, f(count), \"x\", t.x)"
+        );
+    }
+
+    assert_eq!(
+        rename(code, 34, "value").unwrap(),
+        "local x = 1 -- x\nlocal function f(value) return value end\nprint(x, f(x), \"x\", t.x)"
+    );
+
+    // Renaming to the same name changes nothing
+    assert_eq!(rename(code, 6, "x").unwrap(), code);
+}
+
+#[test]
+fn test_report() {
+    let code = "local x = 1\nx = x + 1";
+    let mut ast = parse(code).unwrap();
+    let report = rename_local(&mut ast, position(code, 6), "total").unwrap();
+
+    let positions: Vec<_> = report
+        .positions()
+        .iter()
+        .map(|position| position.bytes())
+        .collect();
+    assert_eq!(positions, vec![6, 12, 16]);
+
+    let edits: Vec<_> = report
+        .edits()
+        .iter()
+        .map(|edit| (edit.range(), edit.replacement()))
+        .collect();
+    assert_eq!(
+        edits,
+        vec![((6, 7), "total"), ((12, 13), "total"), ((16, 17), "total")]
+    );
+
+    // Applying the edits last to first gives the same code
+    let mut edited = code.to_owned();
+    for edit in report.edits().iter().rev() {
+        edited.replace_range(edit.range().0..edit.range().1, edit.replacement());
+    }
+    assert_eq!(edited, print(&ast));
+
+    // The positions are updated for the new names
+    let last = ast
+        .iter_tokens()
+        .filter(|token| token.to_string() == "total")
+        .nth(2);
+    assert_eq!(last.unwrap().start_position().bytes(), 24);
+}
+
+#[test]
+fn test_invalid() {
+    let code = "local x = 1\nfunction t:f() return self, y end";
+
+    assert_eq!(rename(code, 34, "z"), Err(RenameError::ImplicitSelf));
+    assert_eq!(
+        rename(code, 40, "z"),
+        Err(RenameError::Global {
+            name: "y".to_owned()
+        })
+    );
+    assert_eq!(rename(code, 12, "z"), Err(RenameError::NotAName));
+
+    for name in &["1x", "a.b", "", "x y", "--"] {
+        assert_eq!(
+            rename(code, 6, name),
+            Err(RenameError::InvalidName {
+                name: name.to_string()
+            })
+        );
+    }
+
+    assert_eq!(
+        rename(code, 6, "end"),
+        Err(RenameError::Keyword {
+            name: "end".to_owned()
+        })
+    );
+}
+
+#[test]
+fn test_conflicts() {
+    let conflict = |code: &str, bytes: usize, new_name: &str| match rename(code, bytes, new_name) {
+        Err(RenameError::Conflict { name, position }) => {
+            assert_eq!(name, new_name);
+            Some(position.bytes())
+        }
+        Err(error) => panic!("{}", error),
+        Ok(_) => None,
+    };
+
+    // A use of the local would refer to the inner local with the new name instead
+    let code = "local x = 1 do local y = 2 print(x, y) end";
+    assert_eq!(conflict(code, 6, "y"), Some(21));
+
+    // The renamed local would shadow an outer local or a global that is used
+    assert_eq!(conflict(code, 21, "x"), Some(6));
+    assert_eq!(conflict(code, 21, "print"), Some(27));
+
+    // Shadowing that changes nothing isn't a conflict
+    assert_eq!(
+        conflict("local x = 1 do local y = 2 print(y) end", 21, "x"),
+        None
+    );
+    assert_eq!(conflict("local a = 1 local b = a print(b)", 18, "a"), None);
+
+    // A failed rename leaves the Ast as it was
+    let mut ast = parse(code).unwrap();
+    assert!(rename_local(&mut ast, position(code, 6), "y").is_err());
+    assert_eq!(print(&ast), code);
+}
@@ -0,0 +1,179 @@
+use full_moon::{
+    ast::{reparse::SourceEdit, Ast},
+    node::{Node, TriviaBehavior},
+    parse, print,
+};
+use pretty_assertions::assert_eq;
+
+mod common;
+use common::read_sources;
+
+fn stmts(ast: &Ast) -> Vec<String> {
+    ast.nodes()
+        .iter_stmts()
+        .map(|stmt| stmt.print(TriviaBehavior::Keep))
+        .chain(
+            ast.nodes()
+                .last_stmts()
+                .map(|last_stmt| last_stmt.print(TriviaBehavior::Keep)),
+        )
+        .collect()
+}
+
+// Reparses the edit, and checks the Ast is the same as parsing the edited code, returning whether all of it was parsed
+fn check(code: &str, range: std::ops::Range<usize>, text: &str) -> Option<bool> {
+    let mut ast = parse(code).unwrap();
+    let mut new_code = code.to_owned();
+    new_code.replace_range(range.clone(), text);
+
+    match (
+        ast.reparse(SourceEdit::new(range.clone(), text)),
+        parse(&new_code),
+    ) {
+        (Ok(info), Ok(expected)) => {
+            assert_eq!(
+                ast.nodes(),
+                expected.nodes(),
+                "{:?} {:?} {:?}",
+                code,
+                range,
+                text
+            );
+            assert_eq!(
+                ast.iter_tokens().collect::<Vec<_>>(),
+                expected.iter_tokens().collect::<Vec<_>>(),
+                "{:?}",
+                new_code
+            );
+            assert_eq!(print(&ast), new_code);
+            assert_eq!(stmts(&ast), stmts(&expected), "{:?}", new_code);
+            Some(info.is_full())
+        }
+
+        (Err(_), Err(_)) => {
+            assert_eq!(print(&ast), code);
+            None
+        }
+
+        (reparsed, expected) => panic!(
+            "reparsing {:?} gave {:?}, but parsing it gave {:?}",
+            new_code,
+            reparsed.err(),
+            expected.err()
+        ),
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_reparse_is_the_same_as_parse() {
+    let edits = [
+        "",
+        " ",
+        "x",
+        "-",
+        "(",
+        "end",
+        "-- comment\n",
+        "\"",
+        "local y = 2\n",
+    ];
+
+    let mut partial = 0;
+    for code in read_sources("./tests/cases/pass") {
+        let boundaries: Vec<usize> = (0..=code.len())
+            .filter(|index| code.is_char_boundary(*index))
+            .collect();
+
+        for (number, start) in boundaries.iter().enumerate() {
+            for text in &edits {
+                check(&code, *start..*start, text);
+                if let Some(end) = boundaries.get(number + 1) {
+                    if check(&code, *start..*end, text) == Some(false) {
+                        partial += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    assert!(partial > 0);
+}
+
+#[test]
+fn test_reparse_only_the_statement() {
+    let code = "local a = 1\nlocal b = 2\n\nfunction f()\n\tif a then\n\t\treturn b\n\tend\nend\n";
+    let mut ast = parse(code).unwrap();
+    let info = ast.reparse(SourceEdit::new(58..59, "a + b")).unwrap();
+    assert!(!info.is_full());
+    assert_eq!(
+        print(&ast),
+        "local a = 1\nlocal b = 2\n\nfunction f()\n\tif a then\n\t\treturn a + b\n\tend\nend\n"
+    );
+
+    let (start, end) = info.range();
+    assert_eq!((start.line(), end.line()), (5, 7));
+    assert_eq!(
+        &print(&ast)[start.bytes()..end.bytes()],
+        "\n\t\treturn a + b\n\t"
+    );
+
+    // Inserting a statement between two others
+    let info = ast.reparse(SourceEdit::new(12..12, "a = 3\n")).unwrap();
+    assert!(!info.is_full());
+    assert_eq!(ast.nodes().iter_stmts().count(), 4);
+    assert_eq!(check(code, 12..12, "a = 3\n"), Some(false));
+}
+
+#[test]
+fn test_reparse_keeps_clones() {
+    let mut ast = parse("local a = 1\nlocal b = 2\n").unwrap();
+    let clone = ast.clone();
+    ast.reparse(SourceEdit::new(0..0, "local z = 0\n")).unwrap();
+    assert_eq!(print(&clone), "local a = 1\nlocal b = 2\n");
+    assert_eq!(print(&ast), "local z = 0\nlocal a = 1\nlocal b = 2\n");
+}
+
+#[test]
+fn test_reparse_falls_back() {
+    // Closing a block earlier, and turning the rest of the code into a comment
+    assert_eq!(
+        check("do\n\tlocal x = 1\nend\nx = 2\n", 16..16, "end\n"),
+        None
+    );
+    assert_eq!(
+        check("do\n\tlocal x = 1\nend\n", 16..16, "end\ndo\n"),
+        Some(true)
+    );
+    assert_eq!(
+        check(
+            "local x = 1\nlocal y = 2\nlocal z = 3 --]]\n",
+            12..12,
+            "--[["
+        ),
+        Some(true)
+    );
+
+    // `(g)()` calls `f` when the statement before it ends in a name
+    assert_eq!(check("a = f\nb = 1\n(g)()\n", 6..12, ""), Some(true));
+    assert_eq!(
+        check("a = f\nb = 1\nc = 2\n", 12..17, "(g)()\n"),
+        Some(true)
+    );
+
+    // A `return` has to be the last statement
+    assert_eq!(check("local x = 1\nlocal y = 2\n", 0..11, "return"), None);
+    assert_eq!(
+        check("local x = 1\nlocal y = 2\n", 12..23, "return"),
+        Some(false)
+    );
+}
+
+#[test]
+fn test_reparse_errors() {
+    let mut ast = parse("local x = 1\n").unwrap();
+    assert!(ast.reparse(SourceEdit::new(10..11, "")).is_err());
+    assert_eq!(print(&ast), "local x = 1\n");
+    assert!(ast.reparse(SourceEdit::new(10..11, "\"")).is_err());
+    assert_eq!(print(&ast), "local x = 1\n");
+}
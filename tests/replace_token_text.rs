@@ -0,0 +1,245 @@
+use full_moon::{
+    ast::{Ast, AstError, ExpectedItem},
+    node::Node,
+    tokenizer::{Position, Symbol, Token, TokenKind},
+    Error, ErrorCode, ParseConfig,
+};
+
+mod common;
+use common::without_trivia;
+
+// Every token, including the comments of Asts without trivia, which are kept apart from the other tokens
+fn tokens<'a, 'b>(ast: &'b Ast<'a>) -> impl Iterator<Item = &'b Token<'a>> {
+    let comments = ast.comments().filter(move |_| !ast.has_trivia());
+    ast.iter_tokens().chain(comments)
+}
+
+// The text and positions of every token
+fn positions(ast: &Ast) -> Vec<(String, Position, Position)> {
+    tokens(ast)
+        .map(|token| {
+            (
+                token.to_string(),
+                token.start_position(),
+                token.end_position(),
+            )
+        })
+        .collect()
+}
+
+// Checks that the positions of the Ast are where they would be if its code was parsed again.
+// Whitespace is left out, since whitespace next to other whitespace is read as one token when it's parsed again
+fn assert_positions(ast: &Ast, config: &ParseConfig, message: &str) {
+    let printed = full_moon::print(ast);
+    let expected = full_moon::parse_with(&printed, config).unwrap();
+    fn significant(ast: &Ast) -> Vec<(String, Position, Position)> {
+        let mut positions = positions(ast);
+        positions.retain(|(text, _, _)| text.is_empty() || !text.trim().is_empty());
+        positions
+    }
+
+    let (positions, expected) = (significant(ast), significant(&expected));
+    for (position, expected) in positions.iter().zip(&expected) {
+        assert_eq!(position, expected, "{} in {:?}", message, printed);
+    }
+    assert_eq!(
+        positions.len(),
+        expected.len(),
+        "{} in {:?}",
+        message,
+        printed
+    );
+}
+
+fn find<'a>(ast: &Ast<'a>, text: &str) -> Token<'a> {
+    tokens(ast)
+        .find(|token| token.to_string() == text)
+        .unwrap_or_else(|| panic!("no `{}`", text))
+        .clone()
+}
+
+// Replaces the text of the first token with the old text, then checks the positions
+fn replace(code: &str, config: &ParseConfig, old: &str, new: &str) -> String {
+    let mut ast = full_moon::parse_with(code, config).unwrap();
+    let token = find(&ast, old);
+    ast.replace_token_text(&token, new).unwrap();
+    assert_positions(&ast, config, &format!("{:?} to {:?}", old, new));
+    full_moon::print(&ast)
+}
+
+const CODE: &str = "local speed = 0.5 -- half\nlocal name = \"x\" .. 'y'\n\nif speed then\n\tprint(name, [[a\nb]])\nend --[[ one\ntwo ]] return speed";
+
+#[test]
+fn test_same_line() {
+    for config in &[ParseConfig::default(), without_trivia()] {
+        let code = replace(CODE, config, "0.5", "0.25");
+        assert!(code.starts_with("local speed = 0.25 -- half\n"));
+
+        replace(CODE, config, "speed", "s");
+        replace(CODE, config, "name", "longer_name");
+        replace(CODE, config, "\"x\"", "'😀'");
+        replace(CODE, config, "-- half", "-- a longer comment");
+    }
+
+    replace(CODE, &ParseConfig::default(), " ", "    ");
+    replace(CODE, &ParseConfig::default(), "\t", "  ");
+}
+
+#[test]
+fn test_multi_line_text() {
+    for config in &[ParseConfig::default(), without_trivia()] {
+        // More lines
+        let code = replace(CODE, config, "\"x\"", "[[x\n\n  y]]");
+        assert!(code.contains("local name = [[x\n\n  y]] .. 'y'\n"));
+        replace(
+            CODE,
+            config,
+            "--[[ one\ntwo ]]",
+            "--[==[\n\n\none\ntwo\nthree ]==]",
+        );
+
+        // Fewer lines
+        replace(CODE, config, "[[a\nb]]", "'ab'");
+        replace(CODE, config, "--[[ one\ntwo ]]", "--[[ one ]]");
+
+        // The same number of lines, at different characters
+        replace(CODE, config, "[[a\nb]]", "[[abc\n]]");
+    }
+
+    // New lines of whitespace, which end right after the new line
+    let config = ParseConfig::default();
+    replace(CODE, &config, " ", "\n");
+    let code = "local x = 1\n\n\treturn x\n";
+    assert_eq!(
+        replace(code, &config, "\n", " "),
+        "local x = 1 \n\treturn x\n"
+    );
+    assert_eq!(
+        replace(code, &config, "\t", "\n"),
+        "local x = 1\n\n\nreturn x\n"
+    );
+}
+
+#[test]
+fn test_after_update_positions() {
+    let mut ast = full_moon::parse(CODE).unwrap();
+    ast.update_positions();
+    let string = find(&ast, "[[a\nb]]");
+    ast.replace_token_text(&string, "[[\n\na]]").unwrap();
+    ast.replace_token_text(&find(&ast, "speed"), "s").unwrap();
+    ast.replace_token_text(&string, "'a'").unwrap();
+
+    let config = ParseConfig::default();
+    assert_positions(&ast, &config, "the replacements");
+    ast.update_positions();
+    assert_positions(&ast, &config, "updating the positions");
+}
+
+#[test]
+fn test_every_token() {
+    // Every token that isn't a symbol, replaced by text of its kind with a different length and number of lines,
+    // other than the whitespace after a single line comment, which would be read as part of the comment
+    let replacements = |kind| match kind {
+        TokenKind::Identifier => ["n", "much_longer_name"],
+        TokenKind::Number => ["1", "0x123456"],
+        TokenKind::StringLiteral => ["''", "[[\nmany\nlines\n]]"],
+        TokenKind::SingleLineComment => ["--", "-- a comment"],
+        TokenKind::MultiLineComment => ["--[[]]", "--[[\n\n]]"],
+        TokenKind::Whitespace => [" ", "  \n"],
+        _ => unreachable!(),
+    };
+
+    for config in &[ParseConfig::default(), without_trivia()] {
+        let ast = full_moon::parse_with(CODE, config).unwrap();
+        let count = positions(&ast).len();
+
+        for index in 0..count {
+            for text_index in 0..2 {
+                let mut ast = full_moon::parse_with(CODE, config).unwrap();
+                let token = tokens(&ast).nth(index).unwrap().clone();
+                let after_comment = index > 0
+                    && tokens(&ast).nth(index - 1).unwrap().token_kind()
+                        == TokenKind::SingleLineComment;
+                if after_comment || matches!(token.token_kind(), TokenKind::Symbol | TokenKind::Eof)
+                {
+                    continue;
+                }
+
+                let text = replacements(token.token_kind())[text_index];
+                ast.replace_token_text(&token, text).unwrap();
+                assert_positions(
+                    &ast,
+                    config,
+                    &format!("{:?} to {:?}", token.to_string(), text),
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_errors() {
+    let mut ast = full_moon::parse("local x = 1 + y -- y").unwrap();
+    let before = positions(&ast);
+    let error = |ast: &mut Ast, old, new| ast.replace_token_text(&find(ast, old), new).unwrap_err();
+
+    match error(&mut ast, "x", "end") {
+        Error::AstError(AstError::UnexpectedToken {
+            token, expected, ..
+        }) => {
+            assert_eq!(token.to_string(), "end");
+            assert_eq!(expected, [ExpectedItem::Identifier]);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    assert_eq!(error(&mut ast, "1", "x").code(), ErrorCode::UnexpectedToken);
+    assert_eq!(
+        error(&mut ast, "1", "1 + 2").code(),
+        ErrorCode::LeftoverToken
+    );
+    assert_eq!(error(&mut ast, "1", "").message(), "unexpected end of code");
+    assert_eq!(
+        error(&mut ast, "1", "1e").code(),
+        ErrorCode::MalformedNumber
+    );
+    assert_eq!(
+        error(&mut ast, "-- y", "-- y\n").code(),
+        ErrorCode::LeftoverToken
+    );
+    assert_eq!(
+        error(&mut ast, " ", "  \n  ").code(),
+        ErrorCode::LeftoverToken
+    );
+    assert_eq!(error(&mut ast, " ", "--").message(), "unexpected token");
+
+    match error(&mut ast, "+", "-") {
+        Error::AstError(AstError::UnexpectedToken { expected, .. }) => {
+            assert_eq!(expected, [ExpectedItem::Symbol(Symbol::Plus)])
+        }
+        other => panic!("{:?}", other),
+    }
+
+    // Nothing was changed by the errors, and symbols can be replaced by themselves
+    assert_eq!(positions(&ast), before);
+    ast.replace_token_text(&find(&ast, "+"), "+").unwrap();
+    assert_eq!(positions(&ast), before);
+}
+
+#[test]
+fn test_not_a_token() {
+    let mut ast = full_moon::parse("local x = 1").unwrap();
+    let before = positions(&ast);
+
+    let stmt = ast.nodes().iter_stmts().next().unwrap().id().unwrap();
+    let error = ast.replace_token_text(stmt, "y").unwrap_err();
+    assert_eq!(error, Error::AstError(AstError::NotAToken));
+    assert_eq!(error.code(), ErrorCode::NotAToken);
+
+    let other = full_moon::parse("local x = 1").unwrap();
+    let error = ast.replace_token_text(&find(&other, "x"), "y").unwrap_err();
+    assert_eq!(error, Error::AstError(AstError::TokenNotInAst));
+    assert_eq!(error.code(), ErrorCode::TokenNotInAst);
+
+    assert_eq!(positions(&ast), before);
+}
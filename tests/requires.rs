@@ -0,0 +1,94 @@
+use full_moon::{
+    analysis::{requires, requires_with, RequiresConfig},
+    parse,
+};
+use pretty_assertions::assert_eq;
+
+// Every require as the function, the module or `dynamic`, and the local it's assigned to if there is one
+fn modules(code: &str, config: &RequiresConfig) -> Vec<String> {
+    let ast = parse(code).unwrap();
+
+    requires_with(&ast, config)
+        .iter()
+        .map(|require| {
+            let mut found = format!(
+                "{} {}",
+                require.function(),
+                require.module().unwrap_or("dynamic")
+            );
+            if let Some(local) = require.local() {
+                found.push_str(&format!(" as {}", local));
+            }
+
+            found
+        })
+        .collect()
+}
+
+#[test]
+fn test_requires() {
+    let code = r#"local json = require("json")
+local a, b = require "a", require [[b]]
+require("lib\46escaped\65")
+local fields = require("fields").field
+do
+    local require = function() end
+    require("shadowed")
+end
+local function load(require) return require("parameter") end
+t.require("index")
+t:require("method")
+"#;
+
+    assert_eq!(
+        modules(code, &RequiresConfig::default()),
+        vec![
+            "require json as json",
+            "require a as a",
+            "require b as b",
+            "require lib.escapedA",
+            "require fields",
+        ]
+    );
+}
+
+#[test]
+fn test_dynamic() {
+    let code = "require(name)\nrequire(\"a\" .. b)\nrequire { \"table\" }\nlocal m = require()";
+    let ast = parse(code).unwrap();
+    let requires = requires(&ast);
+
+    assert_eq!(requires.len(), 4);
+    assert!(requires.iter().all(|require| require.is_dynamic()));
+    assert_eq!(requires[3].local().unwrap().to_string(), "m");
+}
+
+#[test]
+fn test_ranges() {
+    let code = "local x = require(\"x\") -- comment\nprint(require \"y\")";
+    let ast = parse(code).unwrap();
+
+    let ranges: Vec<_> = requires(&ast)
+        .iter()
+        .map(|require| (require.range().0.bytes(), require.range().1.bytes()))
+        .collect();
+
+    assert_eq!(ranges, vec![(10, 22), (40, 51)]);
+    assert_eq!(&code[10..22], "require(\"x\")");
+    assert_eq!(&code[40..51], "require \"y\"");
+}
+
+#[test]
+fn test_other_functions() {
+    let config = RequiresConfig {
+        functions: vec!["import".to_owned(), "dofile".to_owned()],
+    };
+
+    assert_eq!(
+        modules(
+            "local x = import(\"x\")\ndofile \"init.lua\"\nrequire(\"y\")",
+            &config
+        ),
+        vec!["import x as x", "dofile init.lua"]
+    );
+}
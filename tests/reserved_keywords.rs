@@ -0,0 +1,93 @@
+use full_moon::{ast::AstError, parse, tokenizer::Symbol, Error};
+
+// The keyword used as a name in the code and the byte it starts at
+fn reserved_keyword(code: &str) -> (Symbol, usize) {
+    match parse(code) {
+        Err(Error::AstError(AstError::ReservedKeywordAsName { keyword, position })) => {
+            (keyword, position.bytes())
+        }
+        other => panic!("{:?} didn't use a keyword as a name: {:?}", code, other),
+    }
+}
+
+#[test]
+fn test_local_names() {
+    assert_eq!(reserved_keyword("local end = 1"), (Symbol::End, 6));
+    assert_eq!(reserved_keyword("local a, nil = 1"), (Symbol::Nil, 9));
+    assert_eq!(reserved_keyword("local until"), (Symbol::Until, 6));
+}
+
+#[test]
+fn test_local_function_names() {
+    assert_eq!(
+        reserved_keyword("local function end() end"),
+        (Symbol::End, 15)
+    );
+}
+
+#[test]
+fn test_function_names() {
+    assert_eq!(reserved_keyword("function end() end"), (Symbol::End, 9));
+    assert_eq!(reserved_keyword("function a.end() end"), (Symbol::End, 11));
+    assert_eq!(
+        reserved_keyword("function a.b:then() end"),
+        (Symbol::Then, 13)
+    );
+}
+
+#[test]
+fn test_parameters() {
+    assert_eq!(reserved_keyword("function f(end) end"), (Symbol::End, 11));
+    assert_eq!(
+        reserved_keyword("local f = function(a, true) end"),
+        (Symbol::True, 22)
+    );
+    assert!(parse("function f(a, ...) end").is_ok());
+}
+
+#[test]
+fn test_for_names() {
+    assert_eq!(reserved_keyword("for end = 1, 2 do end"), (Symbol::End, 4));
+    assert_eq!(reserved_keyword("for k, in in t do end"), (Symbol::In, 7));
+}
+
+#[test]
+fn test_fields_and_methods() {
+    assert_eq!(reserved_keyword("x = a.end"), (Symbol::End, 6));
+    assert_eq!(reserved_keyword("a.b.while = 1"), (Symbol::While, 4));
+    assert_eq!(reserved_keyword("a:end()"), (Symbol::End, 2));
+}
+
+#[test]
+fn test_table_keys() {
+    // Keywords can't be table keys either, but a `}` could be missing before them, so they aren't called names
+    for code in &["x = { end = 1 }", "x = { a = 1, nil = 2 }"] {
+        match parse(code) {
+            Err(Error::AstError(AstError::UnexpectedToken { .. })) => {}
+            other => panic!("{:?} didn't have an unexpected token: {:?}", code, other),
+        }
+    }
+
+    assert!(parse("x = { ['end'] = 1 }").is_ok());
+}
+
+#[test]
+fn test_reserved_keyword_message() {
+    let code = "local end = 1";
+    let error = parse(code).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "reserved keyword used as a name at line 1, character 7\n\
+         `end` is a reserved keyword and cannot be used as a name here"
+    );
+    assert_eq!(
+        error.display_rich(code, None),
+        concat!(
+            "error: reserved keyword used as a name\n",
+            " --> 1:7\n",
+            "  |\n",
+            "1 | local end = 1\n",
+            "  |       ^^^ `end` is a reserved keyword and cannot be used as a name here\n",
+        )
+    );
+}
@@ -0,0 +1,95 @@
+use full_moon::{ParseConfig, RoundTripFailure, RoundTripMismatch};
+use std::fs;
+
+mod common;
+use common::without_trivia;
+
+fn find_mismatch(code: &str, config: &ParseConfig) -> RoundTripMismatch {
+    match full_moon::verify_round_trip(code, config) {
+        Err(RoundTripFailure::Mismatch(mismatch)) => mismatch,
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_pass_cases() {
+    for entry in fs::read_dir("./tests/cases/pass").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+
+        let config = ParseConfig::default();
+        if let Err(failure) = full_moon::verify_round_trip(&source, &config) {
+            panic!("{:?}: {}", path, failure);
+        }
+
+        for config in &[config, without_trivia()] {
+            if let Err(failure) = full_moon::verify_round_trip_reparse(&source, config) {
+                panic!("{:?}: {}", path, failure);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_mismatch() {
+    let code =
+        "local first_long_name = 1\n\tlocal second_long_name = 2 -- two\nreturn first_long_name";
+    let mismatch = find_mismatch(code, &without_trivia());
+    assert_eq!(mismatch.offset(), 26);
+    assert_eq!(
+        mismatch.expected(),
+        "first_long_name = 1\n\tlocal second_long_n"
+    );
+    assert_eq!(
+        mismatch.found(),
+        "first_long_name = 1\n local second_long_n"
+    );
+
+    // The tab is printed as a space, and is in whitespace, which isn't kept, so the token is the one before it
+    let token = mismatch.token().unwrap();
+    assert_eq!(token.to_string(), "1");
+    assert_eq!(token.start_position().line(), 1);
+    assert_eq!(
+        mismatch.to_string(),
+        "printed code differs at byte 26, in `1` at line 1, character 25\n\
+         expected: \"first_long_name = 1\\n\\tlocal second_long_n\"\n   \
+         found: \"first_long_name = 1\\n local second_long_n\""
+    );
+
+    // Comments are tokens too
+    let mismatch = find_mismatch("x = 1 --[[ one ]]\t-- two", &without_trivia());
+    assert_eq!(mismatch.offset(), 17);
+    assert_eq!(mismatch.token().unwrap().to_string(), "--[[ one ]]");
+}
+
+#[test]
+fn test_context_characters() {
+    // The context doesn't cut the multi-byte characters around it in half
+    let code = "local s = '😀😀😀😀😀😀'\treturn s";
+    let mismatch = find_mismatch(code, &without_trivia());
+    assert_eq!(mismatch.offset(), 36);
+    assert_eq!(mismatch.expected(), "😀😀😀😀😀'\treturn s");
+    assert_eq!(mismatch.found(), "😀😀😀😀😀' return s");
+}
+
+#[test]
+fn test_errors() {
+    let config = ParseConfig::default();
+    for verify in &[
+        full_moon::verify_round_trip,
+        full_moon::verify_round_trip_reparse,
+    ] {
+        match verify("local = 1", &config) {
+            Err(RoundTripFailure::Parse(error)) => {
+                assert_eq!(
+                    error.to_string(),
+                    full_moon::parse("local = 1").unwrap_err().to_string()
+                )
+            }
+            other => panic!("{:?}", other),
+        }
+
+        assert!(verify("local x = '😀' -- one\r\n\treturn x", &config).is_ok());
+    }
+}
@@ -0,0 +1,272 @@
+use full_moon::{
+    analysis::scopes::{ReferenceKind, Resolution, ScopeAnalysis, VariableKind},
+    parse,
+    visitors::ScopeKind,
+};
+use pretty_assertions::assert_eq;
+
+// What every name in the code resolves to, as the name followed by where it was declared, or `global`
+fn resolve(code: &str) -> Vec<String> {
+    let ast = parse(code).unwrap();
+    let analysis = ScopeAnalysis::new(&ast);
+
+    ast.iter_tokens()
+        .filter_map(|token| {
+            let resolved = match analysis.resolve(token) {
+                Resolution::Local(id) => match analysis.variable(id).declaration_position() {
+                    Some(position) => format!("local {}", position.bytes()),
+                    None => "self".to_owned(),
+                },
+                Resolution::Global => "global".to_owned(),
+                Resolution::NotAVariable => return None,
+            };
+
+            Some(format!(
+                "{} {} {}",
+                token,
+                token.start_position().bytes(),
+                resolved
+            ))
+        })
+        .collect()
+}
+
+#[test]
+fn test_shadowing() {
+    assert_eq!(
+        resolve("local x = 1 local x = x do local x = x end print(x)"),
+        vec![
+            "x 6 local 6",
+            "x 18 local 18",
+            "x 22 local 6",
+            "x 33 local 33",
+            "x 37 local 18",
+            "print 43 global",
+            "x 49 local 18",
+        ]
+    );
+}
+
+#[test]
+fn test_local_functions() {
+    // A local function can call itself, but a function assigned to a local can't
+    assert_eq!(
+        resolve("local function f() f() end local g = function() g() end"),
+        vec![
+            "f 15 local 15",
+            "f 19 local 15",
+            "g 33 local 33",
+            "g 48 global",
+        ]
+    );
+
+    // Declaring a function with the name of a local assigns to it
+    assert_eq!(
+        resolve("local t = {} function t.f() end function g() end"),
+        vec!["t 6 local 6", "t 22 local 6", "g 41 global"]
+    );
+}
+
+#[test]
+fn test_parameters() {
+    assert_eq!(
+        resolve("function t:f(a, ...) return self, a, b end"),
+        vec![
+            "t 9 global",
+            "a 13 local 13",
+            "self 28 self",
+            "a 34 local 13",
+            "b 37 global",
+        ]
+    );
+}
+
+#[test]
+fn test_loops() {
+    assert_eq!(
+        resolve("for i = i, 10 do print(i) end for k, v in pairs(k) do end"),
+        vec![
+            "i 4 local 4",
+            "i 8 global",
+            "print 17 global",
+            "i 23 local 4",
+            "k 34 local 34",
+            "v 37 local 37",
+            "pairs 42 global",
+            "k 48 global",
+        ]
+    );
+
+    // The condition of a repeat loop can see the locals of its body
+    assert_eq!(
+        resolve("repeat local done = true until done print(done)"),
+        vec![
+            "done 13 local 13",
+            "done 31 local 13",
+            "print 36 global",
+            "done 42 global",
+        ]
+    );
+}
+
+#[test]
+fn test_variables_and_scopes() {
+    let ast =
+        parse("local a, b = 1\nfunction f(c)\n  for i = 1, c do\n    a = a + i\n  end\nend\n")
+            .unwrap();
+    let analysis = ScopeAnalysis::new(&ast);
+
+    let variables: Vec<_> = analysis
+        .variables()
+        .map(|variable| {
+            (
+                variable.name().to_owned(),
+                variable.kind(),
+                variable.declaration_position().unwrap().line(),
+                variable
+                    .reference_positions()
+                    .iter()
+                    .map(|position| position.line())
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        variables,
+        vec![
+            ("a".to_owned(), VariableKind::Local, 1, vec![1, 4, 4]),
+            ("b".to_owned(), VariableKind::Local, 1, vec![1]),
+            ("c".to_owned(), VariableKind::Parameter, 2, vec![2, 3]),
+            ("i".to_owned(), VariableKind::NumericFor, 3, vec![3, 4]),
+        ]
+    );
+
+    let scopes: Vec<_> = analysis
+        .scopes()
+        .map(|scope| {
+            (
+                scope.kind(),
+                scope.parent().map(|parent| analysis.scope(parent).kind()),
+                scope.variables().len(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        scopes,
+        vec![
+            (ScopeKind::File, None, 2),
+            (ScopeKind::Function, Some(ScopeKind::File), 1),
+            (ScopeKind::NumericFor, Some(ScopeKind::Function), 1),
+        ]
+    );
+
+    assert_eq!(
+        analysis
+            .globals()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        vec!["f"]
+    );
+}
+
+// A reference as its line, whether it is a write, and its function depth
+type Reference = (usize, ReferenceKind, usize);
+
+// Every reference to every variable
+fn references(code: &str) -> Vec<(String, Vec<Reference>)> {
+    let ast = parse(code).unwrap();
+    let analysis = ScopeAnalysis::new(&ast);
+
+    analysis
+        .variables()
+        .map(|variable| {
+            let references = analysis
+                .references(variable.id())
+                .iter()
+                .map(|reference| {
+                    (
+                        reference.token().start_position().line(),
+                        reference.kind(),
+                        reference.function_depth(),
+                    )
+                })
+                .collect();
+
+            (variable.name().to_owned(), references)
+        })
+        .collect()
+}
+
+#[test]
+fn test_references_of_shadowed_locals() {
+    use ReferenceKind::*;
+
+    let code = "local x = 1
+do
+  local x = x
+  x = x + 1
+end
+if x then x = 2 end
+";
+
+    assert_eq!(
+        references(code),
+        vec![
+            (
+                "x".to_owned(),
+                vec![(1, Write, 0), (3, Read, 0), (6, Read, 0), (6, Write, 0)]
+            ),
+            (
+                "x".to_owned(),
+                vec![(3, Write, 0), (4, Write, 0), (4, Read, 0)]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_references_in_closures() {
+    use ReferenceKind::*;
+
+    let code = "local count = 0
+local t = {}
+function t.increment(by)
+  return function()
+    count = count + by
+    t.last = count
+  end
+end
+for i = 1, 2 do t[i] = i end
+";
+
+    assert_eq!(
+        references(code),
+        vec![
+            (
+                "count".to_owned(),
+                vec![(1, Write, 0), (5, Write, 2), (5, Read, 2), (6, Read, 2)]
+            ),
+            (
+                "t".to_owned(),
+                vec![(2, Write, 0), (3, Read, 0), (6, Read, 2), (9, Read, 0)]
+            ),
+            ("by".to_owned(), vec![(3, Write, 1), (5, Read, 2)]),
+            (
+                "i".to_owned(),
+                vec![(9, Write, 0), (9, Read, 0), (9, Read, 0)]
+            ),
+        ]
+    );
+
+    let ast = parse("local function f() end function f() end").unwrap();
+    let analysis = ScopeAnalysis::new(&ast);
+    let f = analysis.variables().next().unwrap();
+    let references = analysis.references(f.id());
+    assert_eq!(references.len(), 2);
+    assert!(references[0].is_declaration());
+    assert!(!references[1].is_declaration());
+    assert_eq!(references[1].kind(), Write);
+}
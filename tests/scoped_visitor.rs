@@ -0,0 +1,204 @@
+use full_moon::{
+    ast, parse,
+    visitors::{ScopeKind, ScopedVisitor, Scopes, Visitor},
+};
+
+// Records, for every name used, whether it resolved to a local and which scope it is in
+#[derive(Default)]
+struct Resolver<'ast> {
+    scopes: Scopes<'ast>,
+    events: Vec<String>,
+}
+
+impl<'ast> Resolver<'ast> {
+    fn resolve(&mut self, name: &str) {
+        let resolved = match self.scopes.lookup(name) {
+            Some(local) => format!(
+                "{} local {}",
+                name,
+                local.token().unwrap().start_position().bytes()
+            ),
+            None => format!("{} global", name),
+        };
+
+        self.events.push(resolved);
+    }
+}
+
+impl<'ast> Visitor<'ast> for Resolver<'ast> {
+    fn visit_var(&mut self, var: &ast::Var<'ast>) {
+        if let ast::Var::Name(name) = var {
+            self.resolve(&name.to_string());
+        }
+    }
+
+    fn visit_prefix(&mut self, prefix: &ast::Prefix<'ast>) {
+        if let ast::Prefix::Name(name) = prefix {
+            self.resolve(&name.to_string());
+        }
+    }
+}
+
+impl<'ast> ScopedVisitor<'ast> for Resolver<'ast> {
+    fn scopes_mut(&mut self) -> &mut Scopes<'ast> {
+        &mut self.scopes
+    }
+
+    fn enter_scope(&mut self, kind: ScopeKind) {
+        self.events.push(format!("enter {:?}", kind));
+    }
+
+    fn exit_scope(&mut self) {
+        let kind = self.scopes.current_scope().unwrap().kind();
+        self.events.push(format!("exit {:?}", kind));
+    }
+}
+
+fn resolve(code: &str) -> Vec<String> {
+    let ast = parse(code).unwrap();
+    let mut resolver = Resolver::default();
+    resolver.visit_ast_scoped(&ast);
+    assert!(resolver.scopes.current_scope().is_none());
+    resolver.events
+}
+
+#[test]
+fn test_repeat_until_sees_body_locals() {
+    assert_eq!(
+        resolve("repeat local x = 1 until x\nx()"),
+        vec![
+            "enter File",
+            "enter Repeat",
+            "x local 13",
+            "exit Repeat",
+            "x global",
+            "exit File",
+        ]
+    );
+}
+
+#[test]
+fn test_numeric_for_variable_scope() {
+    assert_eq!(
+        resolve("local i = 1 for i = i, i + 1 do print(i) end print(i)"),
+        vec![
+            "enter File",
+            "i local 6",
+            "i local 6",
+            "enter NumericFor",
+            "print global",
+            "i local 16",
+            "exit NumericFor",
+            "print global",
+            "i local 6",
+            "exit File",
+        ]
+    );
+}
+
+#[test]
+fn test_numeric_for_variable_not_visible_in_nested_function_in_range() {
+    assert_eq!(
+        resolve("for i = (function() return i end)(), 2 do end"),
+        vec![
+            "enter File",
+            "enter Function",
+            "i global",
+            "exit Function",
+            "enter NumericFor",
+            "exit NumericFor",
+            "exit File",
+        ]
+    );
+}
+
+#[test]
+fn test_generic_for_names() {
+    assert_eq!(
+        resolve("for k, v in pairs(k) do f(k, v) end"),
+        vec![
+            "enter File",
+            "pairs global",
+            "k global",
+            "enter GenericFor",
+            "f global",
+            "k local 4",
+            "v local 7",
+            "exit GenericFor",
+            "exit File",
+        ]
+    );
+}
+
+#[test]
+fn test_local_declaration_order() {
+    assert_eq!(
+        resolve("local x = x local function f() f() end"),
+        vec![
+            "enter File",
+            "x global",
+            "enter Function",
+            "f local 27",
+            "exit Function",
+            "exit File",
+        ]
+    );
+}
+
+#[test]
+fn test_function_parameters_and_self() {
+    let ast = parse("function t:m(a, ...) return self, a, b end").unwrap();
+
+    #[derive(Default)]
+    struct Locals<'ast> {
+        scopes: Scopes<'ast>,
+        locals: Vec<Vec<String>>,
+    }
+
+    impl<'ast> Visitor<'ast> for Locals<'ast> {
+        fn visit_return(&mut self, _: &ast::Return<'ast>) {
+            let scope = self.scopes.current_scope().unwrap();
+            assert_eq!(scope.kind(), ScopeKind::Function);
+            assert!(scope.lookup("self").unwrap().token().is_none());
+            assert!(scope.lookup("t").is_none());
+
+            self.locals.push(
+                scope
+                    .locals()
+                    .iter()
+                    .map(|local| local.name().to_owned())
+                    .collect(),
+            );
+        }
+    }
+
+    impl<'ast> ScopedVisitor<'ast> for Locals<'ast> {
+        fn scopes_mut(&mut self) -> &mut Scopes<'ast> {
+            &mut self.scopes
+        }
+    }
+
+    let mut visitor = Locals::default();
+    visitor.visit_ast_scoped(&ast);
+    assert_eq!(visitor.locals, vec![vec!["self", "a"]]);
+}
+
+#[test]
+fn test_if_branches() {
+    assert_eq!(
+        resolve("if a then local b elseif b then local c else c() end"),
+        vec![
+            "enter File",
+            "a global",
+            "enter If",
+            "exit If",
+            "b global",
+            "enter ElseIf",
+            "exit ElseIf",
+            "enter If",
+            "c global",
+            "exit If",
+            "exit File",
+        ]
+    );
+}
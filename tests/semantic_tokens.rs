@@ -0,0 +1,145 @@
+use full_moon::{
+    analysis::{semantic_tokens, semantic_tokens_with, SemanticTokenKind, SemanticTokensConfig},
+    lsp::{semantic_tokens_data, semantic_tokens_data_with},
+    parse, PositionEncoding,
+};
+use pretty_assertions::assert_eq;
+
+// The text of every token with its kind and its modifiers, as `d` for declaration, `r` for readonly,
+// and `m` for modification
+fn classified(
+    code: &str,
+    config: &SemanticTokensConfig,
+) -> Vec<(String, SemanticTokenKind, String)> {
+    let ast = parse(code).unwrap();
+    semantic_tokens_with(&ast, config)
+        .iter()
+        .map(|token| {
+            let (start, end) = token.range();
+            let modifiers = [
+                (token.is_declaration(), 'd'),
+                (token.is_readonly(), 'r'),
+                (token.is_modification(), 'm'),
+            ]
+            .iter()
+            .filter(|(flag, _)| *flag)
+            .map(|(_, letter)| *letter)
+            .collect();
+
+            (
+                code[start.bytes()..end.bytes()].to_owned(),
+                token.kind(),
+                modifiers,
+            )
+        })
+        .collect()
+}
+
+fn token(
+    text: &str,
+    kind: SemanticTokenKind,
+    modifiers: &str,
+) -> (String, SemanticTokenKind, String) {
+    (text.to_owned(), kind, modifiers.to_owned())
+}
+
+#[test]
+fn test_variables() {
+    use SemanticTokenKind::*;
+
+    let code = "local x = 1
+x = x + 1
+count = 0
+local function f(a, ...)
+    a = x
+    return f(a)
+end
+for i, v in pairs({}) do print(i, v) end";
+    assert_eq!(
+        classified(code, &SemanticTokensConfig::default()),
+        vec![
+            token("x", Local, "d"),
+            token("x", Local, "m"),
+            token("x", Local, ""),
+            token("count", Global, "m"),
+            token("f", Function, "d"),
+            token("a", Parameter, "d"),
+            token("a", Parameter, "m"),
+            token("x", Upvalue, ""),
+            token("f", Function, ""),
+            token("a", Parameter, ""),
+            token("i", Local, "dr"),
+            token("v", Local, "dr"),
+            token("pairs", Global, ""),
+            token("print", Global, ""),
+            token("i", Local, "r"),
+            token("v", Local, "r"),
+        ]
+    );
+}
+
+#[test]
+fn test_names() {
+    use SemanticTokenKind::*;
+
+    let code = "local M = { name = 1 }
+function M.util.new() end
+function M:get() return function() return self.name end end
+M.name = M:get()";
+    assert_eq!(
+        classified(code, &SemanticTokensConfig::default()),
+        vec![
+            token("M", Local, "d"),
+            token("name", Field, "d"),
+            token("M", Local, ""),
+            token("util", Field, ""),
+            token("new", Function, "d"),
+            token("M", Local, ""),
+            token("get", Method, "d"),
+            token("self", Upvalue, ""),
+            token("name", Field, ""),
+            token("M", Local, ""),
+            token("name", Field, "m"),
+            token("M", Local, ""),
+            token("get", Method, ""),
+        ]
+    );
+}
+
+#[test]
+fn test_lexical() {
+    use SemanticTokenKind::*;
+
+    let code = "--[[ a\nb ]] local s = \"x\" .. 1 and nil";
+    let config = SemanticTokensConfig { lexical: true };
+    assert_eq!(
+        classified(code, &config),
+        vec![
+            token("--[[ a\nb ]]", Comment, ""),
+            token("local", Keyword, ""),
+            token("s", Local, "d"),
+            token("\"x\"", String, ""),
+            token("1", Number, ""),
+            token("and", Keyword, ""),
+            token("nil", Keyword, ""),
+        ]
+    );
+
+    // The comment is split into its lines
+    let ast = parse(code).unwrap();
+    let data = semantic_tokens_data(&ast, &semantic_tokens_with(&ast, &config));
+    assert_eq!(&data[..15], &[0, 0, 6, 8, 0, 1, 0, 4, 8, 0, 0, 5, 5, 5, 0]);
+}
+
+#[test]
+fn test_encoding() {
+    let code = "local s = '😀' print(s)";
+    let ast = parse(code).unwrap();
+    let tokens = semantic_tokens(&ast);
+
+    // The emoji is 2 UTF-16 code units, but 1 code point
+    let data = semantic_tokens_data(&ast, &tokens);
+    assert_eq!(data, [0, 6, 1, 0, 1, 0, 9, 5, 0, 8, 0, 6, 1, 0, 0]);
+    let data = semantic_tokens_data_with(&ast, &tokens, PositionEncoding::CodePoints);
+    assert_eq!(data, [0, 6, 1, 0, 1, 0, 8, 5, 0, 8, 0, 6, 1, 0, 0]);
+}
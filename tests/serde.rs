@@ -0,0 +1,104 @@
+use full_moon::{
+    ast::Ast,
+    parse, parse_fallible, print,
+    tokenizer::{TokenReference, TokenType},
+    visitors::{VisitMut, Visitor, VisitorMut},
+    ParseConfig,
+};
+use std::fs;
+
+fn round_trip<'a>(ast: &Ast, json: &'a mut String) -> Ast<'a> {
+    *json = serde_json::to_string(ast).expect("couldn't serialize");
+    serde_json::from_str(json).expect("couldn't deserialize")
+}
+
+#[derive(Default)]
+struct TokenVisitor(Vec<String>);
+
+impl<'ast> Visitor<'ast> for TokenVisitor {
+    fn visit_token(&mut self, token: &TokenReference<'ast>) {
+        self.0.push(token.to_string());
+    }
+}
+
+fn visited_tokens(ast: &Ast) -> Vec<String> {
+    let mut visitor = TokenVisitor::default();
+    visitor.visit_ast(ast);
+    visitor.0
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_serde_pass_cases() {
+    for entry in fs::read_dir("./tests/cases/pass").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).unwrap();
+
+        let mut json = String::new();
+        let deserialized = round_trip(&ast, &mut json);
+        assert_eq!(print(&deserialized), source, "{:?}", path);
+        assert_eq!(deserialized.nodes(), ast.nodes(), "{:?}", path);
+        assert_eq!(
+            visited_tokens(&deserialized),
+            visited_tokens(&ast),
+            "{:?}",
+            path
+        );
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_serde_fail_cases() {
+    for entry in fs::read_dir("./tests/cases/fail/parser").expect("couldn't read directory") {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse_fallible(&source, &ParseConfig::default()).ast;
+
+        let mut json = String::new();
+        let deserialized = round_trip(&ast, &mut json);
+        assert_eq!(print(&deserialized), source, "{:?}", path);
+        assert_eq!(deserialized.nodes(), ast.nodes(), "{:?}", path);
+    }
+}
+
+struct Rename;
+
+impl<'ast> VisitorMut<'ast> for Rename {
+    fn visit_identifier(&mut self, token: &mut TokenReference<'ast>) {
+        if token.to_string() == "x" {
+            token.set_token_type(TokenType::Identifier {
+                identifier: "renamed".into(),
+            });
+        }
+    }
+}
+
+#[test]
+fn test_serde_mutation() {
+    let ast = parse("local x = 1 -- x\nprint(x)").unwrap();
+    let mut json = String::new();
+    let mut deserialized = round_trip(&ast, &mut json);
+
+    // The tokens of the nodes are the tokens that are printed, so changing them changes the code
+    deserialized.nodes_mut().visit_mut(&mut Rename);
+    assert_eq!(
+        print(&deserialized),
+        "local renamed = 1 -- x\nprint(renamed)"
+    );
+    assert_eq!(print(&ast), "local x = 1 -- x\nprint(x)");
+}
+
+#[test]
+fn test_serde_mismatched_tokens() {
+    let json = serde_json::to_value(parse("local x = 1").unwrap()).unwrap();
+    let mut mismatched = json.clone();
+    mismatched["tokens"] =
+        serde_json::to_value(parse("local y = 1").unwrap()).unwrap()["tokens"].clone();
+
+    let json = json.to_string();
+    let mismatched = mismatched.to_string();
+    assert!(serde_json::from_str::<Ast>(&json).is_ok());
+    assert!(serde_json::from_str::<Ast>(&mismatched).is_err());
+}
@@ -0,0 +1,92 @@
+use full_moon::{debug::SexprConfig, parse};
+use pretty_assertions::assert_eq;
+
+fn sexpr(code: &str) -> String {
+    parse(code).unwrap().to_sexpr()
+}
+
+#[test]
+fn test_sexpr_ignores_trivia() {
+    let expected = "\
+(block
+  (local_assignment
+    x
+    (value
+      (table_constructor
+        (value (number 1))
+        (value (function_call (name f) (parentheses (value (name a)))))))))
+";
+
+    assert_eq!(sexpr("local x = { 1, f(a) } -- comment\n"), expected);
+    assert_eq!(sexpr("--[[ before ]] local\tx={1;f (a),}"), expected);
+}
+
+#[test]
+fn test_sexpr_symbols() {
+    assert_eq!(
+        sexpr("while true do break end return"),
+        "(block (while (value (symbol true)) (block (break))) (return))\n"
+    );
+
+    assert_eq!(
+        sexpr("x = not -a .. b"),
+        "\
+(block
+  (assignment
+    (name x)
+    (unary_operator
+      (not)
+      (unary_operator
+        (minus -)
+        (value (name a) (bin_op_rhs (two_dots ..) (value (name b))))))))
+"
+    );
+
+    assert_eq!(
+        sexpr("function t.a:b(...) end"),
+        "\
+(block
+  (function_declaration
+    (function_name t a : b)
+    (function_body (ellipse ...) (block))))
+"
+    );
+}
+
+#[test]
+fn test_sexpr_strings() {
+    assert_eq!(
+        sexpr("x = [[\none\ntwo]] .. 'three'"),
+        r#"(block
+  (assignment
+    (name x)
+    (value
+      (string [[\none\ntwo]])
+      (bin_op_rhs (two_dots ..) (value (string 'three'))))))
+"#
+    );
+}
+
+#[test]
+fn test_sexpr_positions() {
+    let ast = parse("local x\nx = 1").unwrap();
+    assert_eq!(
+        ast.to_sexpr_with(&SexprConfig { positions: true }),
+        "\
+(block@0..13
+  (local_assignment@0..7 x@6..7)
+  (assignment@8..13 (name@8..9 x@8..9) (value@12..13 (number@12..13 1@12..13))))
+"
+    );
+}
+
+#[test]
+fn test_sexpr_empty() {
+    assert_eq!(sexpr(""), "(block)\n");
+    assert_eq!(
+        parse("-- only a comment")
+            .unwrap()
+            .to_sexpr_with(&SexprConfig { positions: true }),
+        "(block)\n"
+    );
+}
@@ -0,0 +1,134 @@
+use full_moon::{
+    analysis::{scopes::VariableKind, shadowing, ShadowKind},
+    parse,
+};
+use pretty_assertions::assert_eq;
+
+// Every local that shadows another as its name, the lines both were declared on, and how it shadows it
+fn shadows(code: &str) -> Vec<(String, usize, Option<usize>, ShadowKind)> {
+    shadowing(&parse(code).unwrap())
+        .iter()
+        .map(|shadow| {
+            (
+                shadow.name().to_owned(),
+                shadow.position().line(),
+                shadow.shadowed_position().map(|position| position.line()),
+                shadow.kind(),
+            )
+        })
+        .collect()
+}
+
+fn shadow(
+    name: &str,
+    line: usize,
+    shadowed: usize,
+    kind: ShadowKind,
+) -> (String, usize, Option<usize>, ShadowKind) {
+    (name.to_owned(), line, Some(shadowed), kind)
+}
+
+#[test]
+fn test_kinds() {
+    let code = "local x = 1
+local x = 2
+do
+    local x = 3
+end
+local function f(x, y, y)
+    local x = 4
+    local g = function()
+        local y = 5
+    end
+end
+local function f() end
+";
+
+    assert_eq!(
+        shadows(code),
+        vec![
+            shadow("x", 2, 1, ShadowKind::Redeclaration),
+            shadow("x", 4, 2, ShadowKind::SameFunction),
+            shadow("x", 6, 2, ShadowKind::Closure),
+            shadow("y", 6, 6, ShadowKind::Redeclaration),
+            shadow("x", 7, 6, ShadowKind::Redeclaration),
+            shadow("y", 9, 6, ShadowKind::Closure),
+            shadow("f", 12, 6, ShadowKind::Redeclaration),
+        ]
+    );
+
+    let ast = parse(code).unwrap();
+    let shadows = shadowing(&ast);
+    assert_eq!(shadows[2].shadowed_kind(), VariableKind::Local);
+    assert_eq!(shadows[4].shadowed_kind(), VariableKind::Parameter);
+}
+
+#[test]
+fn test_loops() {
+    let code = "local i, k = 1, 2
+for i = i, 10 do
+    local i = i
+end
+for k, v, v in pairs(t) do
+    for i = 1, 2 do end
+end
+";
+
+    assert_eq!(
+        shadows(code),
+        vec![
+            shadow("i", 2, 1, ShadowKind::LoopVariable),
+            shadow("i", 3, 2, ShadowKind::Redeclaration),
+            shadow("k", 5, 1, ShadowKind::LoopVariable),
+            shadow("v", 5, 5, ShadowKind::Redeclaration),
+            shadow("i", 6, 1, ShadowKind::LoopVariable),
+        ]
+    );
+}
+
+#[test]
+fn test_repeat() {
+    // The condition of a repeat loop is in the scope of its body, but the locals after the loop aren't
+    let code = "local x
+repeat
+    local x = f()
+until x
+local x
+repeat local y until y
+local y
+";
+
+    assert_eq!(
+        shadows(code),
+        vec![
+            shadow("x", 3, 1, ShadowKind::SameFunction),
+            shadow("x", 5, 1, ShadowKind::Redeclaration),
+        ]
+    );
+}
+
+#[test]
+fn test_visibility() {
+    // Locals declared after a function or a block aren't visible in it
+    assert_eq!(
+        shadows("local function f() local x end\ndo local y end\nlocal x, y\n"),
+        vec![]
+    );
+
+    // The implicit `self` of a method can be shadowed, but doesn't shadow anything
+    let ast = parse("local self\nfunction t:m()\n    local self = 1\nend").unwrap();
+    let shadows: Vec<_> = shadowing(&ast)
+        .iter()
+        .map(|shadow| {
+            (
+                shadow.shadowed_kind(),
+                shadow.shadowed_position(),
+                shadow.kind(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        shadows,
+        vec![(VariableKind::SelfParameter, None, ShadowKind::Redeclaration)]
+    );
+}
@@ -0,0 +1,351 @@
+use full_moon::{
+    ast::simplified::{SBlock, SExpr, SExprKind, SField, SFunction, SStmtKind},
+    parse, parse_fallible, ParseConfig,
+};
+use pretty_assertions::assert_eq;
+
+fn block(code: &str) -> SBlock {
+    SBlock::from(&parse(code).unwrap())
+}
+
+fn expression(code: &str) -> SExpr {
+    match block(&format!("return {}", code)).stmts.remove(0).kind {
+        SStmtKind::Return(mut values) => values.remove(0),
+        other => panic!("expected a return, got {:?}", other),
+    }
+}
+
+// Writes the expression with every operation in parentheses, to check how they are nested
+fn describe(expression: &SExpr) -> String {
+    let list = |expressions: &[SExpr]| {
+        expressions
+            .iter()
+            .map(describe)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match &expression.kind {
+        SExprKind::Binary {
+            operator,
+            left,
+            right,
+        } => format!("({} {:?} {})", describe(left), operator, describe(right)),
+        SExprKind::Boolean(value) => value.to_string(),
+        SExprKind::Call {
+            function,
+            arguments,
+        } => format!("{}({})", describe(function), list(arguments)),
+        SExprKind::Error => "error".to_owned(),
+        SExprKind::Function(_) => "function".to_owned(),
+        SExprKind::Index { object, key } => format!("{}[{}]", describe(object), describe(key)),
+        SExprKind::MethodCall {
+            object,
+            method,
+            arguments,
+        } => format!("{}:{}({})", describe(object), method, list(arguments)),
+        SExprKind::Name(name) => name.clone(),
+        SExprKind::Nil => "nil".to_owned(),
+        SExprKind::Number(value) => value.to_string(),
+        SExprKind::Parentheses(inner) => format!("<{}>", describe(inner)),
+        SExprKind::String(bytes) => format!("{:?}", String::from_utf8_lossy(bytes)),
+        SExprKind::Table(fields) => format!("{{{}}}", fields.len()),
+        SExprKind::Unary { operator, operand } => format!("({:?} {})", operator, describe(operand)),
+        SExprKind::Vararg => "...".to_owned(),
+    }
+}
+
+#[test]
+fn test_precedence() {
+    for (code, expected) in &[
+        ("1 * 2 + 3", "((1 Star 2) Plus 3)"),
+        ("1 + 2 * 3", "(1 Plus (2 Star 3))"),
+        ("a - b - c", "((a Minus b) Minus c)"),
+        ("a .. b .. c", "(a TwoDots (b TwoDots c))"),
+        ("a ^ b ^ c", "(a Caret (b Caret c))"),
+        ("-x ^ 2", "(Minus (x Caret 2))"),
+        ("-x + 1", "((Minus x) Plus 1)"),
+        ("2 ^ -3 ^ 2", "(2 Caret (Minus (3 Caret 2)))"),
+        ("not a == b", "((Not a) TwoEqual b)"),
+        ("a or b and c", "(a Or (b And c))"),
+        ("a < b .. c", "(a LessThan (b TwoDots c))"),
+        ("#t % 2 ~= 0", "(((Hash t) Percent 2) TildeEqual 0)"),
+        ("not not x", "(Not (Not x))"),
+        ("(1 + 2) * 3", "(<(1 Plus 2)> Star 3)"),
+        (
+            "a + b * c ^ d .. e",
+            "((a Plus (b Star (c Caret d))) TwoDots e)",
+        ),
+    ] {
+        assert_eq!(describe(&expression(code)), *expected, "{}", code);
+    }
+}
+
+#[test]
+fn test_literals() {
+    for (code, expected) in &[
+        ("1", SExprKind::Number(1.0)),
+        ("0.5", SExprKind::Number(0.5)),
+        (".5", SExprKind::Number(0.5)),
+        ("3.", SExprKind::Number(3.0)),
+        ("1e3", SExprKind::Number(1000.0)),
+        ("2E-1", SExprKind::Number(0.2)),
+        ("0xFF", SExprKind::Number(255.0)),
+        ("0Xa", SExprKind::Number(10.0)),
+        ("true", SExprKind::Boolean(true)),
+        ("false", SExprKind::Boolean(false)),
+        ("nil", SExprKind::Nil),
+        ("...", SExprKind::Vararg),
+        (r#""a\nb""#, SExprKind::String(b"a\nb".to_vec())),
+        (r"'\65\066\\'", SExprKind::String(b"AB\\".to_vec())),
+        (r#""\255""#, SExprKind::String(vec![255])),
+        ("'日本'", SExprKind::String("日本".as_bytes().to_vec())),
+        (
+            "[[\nfirst\nsecond]]",
+            SExprKind::String(b"first\nsecond".to_vec()),
+        ),
+        (
+            "[==[\r\na\r\nb]]]==]",
+            SExprKind::String(b"a\nb]]".to_vec()),
+        ),
+        ("[[\n\nx]]", SExprKind::String(b"\nx".to_vec())),
+        ("[[x\n\r\ry]]", SExprKind::String(b"x\n\ny".to_vec())),
+    ] {
+        assert_eq!(expression(code).kind, *expected, "{}", code);
+    }
+}
+
+#[test]
+fn test_suffixes() {
+    for (code, expected) in &[
+        ("a.b:c(1)[\"d\"]()", r#"a["b"]:c(1)["d"]()"#),
+        ("f \"x\" { 1, 2 }", r#"f("x")({2})"#),
+        ("(f)(...)", "<f>(...)"),
+        ("('x'):rep(2)", "<\"x\">:rep(2)"),
+        ("t[i + 1].x", r#"t[(i Plus 1)]["x"]"#),
+    ] {
+        assert_eq!(describe(&expression(code)), *expected, "{}", code);
+    }
+}
+
+#[test]
+fn test_tables() {
+    let fields = match expression("{ 1, x = 2, [\"y\"] = 3; f() }").kind {
+        SExprKind::Table(fields) => fields,
+        other => panic!("expected a table, got {:?}", other),
+    };
+
+    let described: Vec<_> = fields
+        .iter()
+        .map(|field| match field {
+            SField::Keyed { key, value } => format!("{} = {}", describe(key), describe(value)),
+            SField::Positional(value) => describe(value),
+        })
+        .collect();
+
+    assert_eq!(described, vec!["1", r#""x" = 2"#, r#""y" = 3"#, "f()"]);
+}
+
+#[test]
+fn test_statements() {
+    let code = r#"
+        local a, b = 1
+        x, t.y = a
+        print("hi")
+        do local c end
+        function t.u:v(p, ...) return self end
+        local function w() end
+        for i = 1, 10, 2 do break end
+        for k, v in pairs(t) do end
+        if a then elseif b then else end
+        while a do end
+        repeat until b
+        return a, b
+    "#;
+
+    let kinds: Vec<_> = block(code)
+        .stmts
+        .into_iter()
+        .map(|stmt| stmt.kind)
+        .collect();
+    assert_eq!(kinds.len(), 12);
+
+    match &kinds[0] {
+        SStmtKind::Local { names, values } => {
+            assert_eq!(names, &["a", "b"]);
+            assert_eq!(values.len(), 1);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    match &kinds[1] {
+        SStmtKind::Assignment { targets, values } => {
+            let targets: Vec<_> = targets.iter().map(describe).collect();
+            assert_eq!(targets, vec!["x", r#"t["y"]"#]);
+            assert_eq!(values.len(), 1);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    match &kinds[2] {
+        SStmtKind::Call(call) => assert_eq!(describe(call), r#"print("hi")"#),
+        other => panic!("{:?}", other),
+    }
+
+    match &kinds[3] {
+        SStmtKind::Do(body) => assert_eq!(body.stmts.len(), 1),
+        other => panic!("{:?}", other),
+    }
+
+    match &kinds[4] {
+        SStmtKind::FunctionDeclaration {
+            names,
+            method,
+            function:
+                SFunction {
+                    parameters,
+                    variadic,
+                    body,
+                    ..
+                },
+        } => {
+            assert_eq!(names, &["t", "u"]);
+            assert_eq!(method.as_deref(), Some("v"));
+            assert_eq!(parameters, &["p"]);
+            assert!(variadic);
+            assert_eq!(body.stmts.len(), 1);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    match &kinds[5] {
+        SStmtKind::LocalFunction { name, function } => {
+            assert_eq!(name, "w");
+            assert!(!function.variadic);
+            assert!(function.body.stmts.is_empty());
+        }
+        other => panic!("{:?}", other),
+    }
+
+    match &kinds[6] {
+        SStmtKind::NumericFor {
+            name,
+            start,
+            end,
+            step,
+            body,
+        } => {
+            assert_eq!(name, "i");
+            assert_eq!(start.kind, SExprKind::Number(1.0));
+            assert_eq!(end.kind, SExprKind::Number(10.0));
+            assert_eq!(step.as_ref().unwrap().kind, SExprKind::Number(2.0));
+            assert_eq!(body.stmts[0].kind, SStmtKind::Break);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    match &kinds[7] {
+        SStmtKind::GenericFor { names, values, .. } => {
+            assert_eq!(names, &["k", "v"]);
+            assert_eq!(describe(&values[0]), "pairs(t)");
+        }
+        other => panic!("{:?}", other),
+    }
+
+    match &kinds[8] {
+        SStmtKind::If {
+            branches,
+            else_block,
+        } => {
+            let conditions: Vec<_> = branches
+                .iter()
+                .map(|(condition, _)| describe(condition))
+                .collect();
+            assert_eq!(conditions, vec!["a", "b"]);
+            assert!(else_block.is_some());
+        }
+        other => panic!("{:?}", other),
+    }
+
+    assert!(matches!(kinds[9], SStmtKind::While { .. }));
+    assert!(matches!(kinds[10], SStmtKind::Repeat { .. }));
+
+    match &kinds[11] {
+        SStmtKind::Return(values) => assert_eq!(values.len(), 2),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_trivia_is_ignored() {
+    fn without_ranges(block: &SBlock) -> String {
+        let debug = format!("{:?}", block);
+        regex::Regex::new(r"range: (Some\(\(Position \{[^}]*\}, Position \{[^}]*\}\)\)|None)")
+            .unwrap()
+            .replace_all(&debug, "")
+            .into_owned()
+    }
+
+    assert_eq!(
+        without_ranges(&block("local x = { 1, f(a) } -- comment")),
+        without_ranges(&block("--[[ before ]] local\tx={1;f (a),}")),
+    );
+}
+
+#[test]
+fn test_ranges() {
+    let code = "local x = a + b * c\nprint(x:y(1))";
+    let simplified = block(code);
+    let text = |expression: &SExpr| {
+        let (start, end) = expression.range.unwrap();
+        &code[start.bytes()..end.bytes()]
+    };
+
+    match &simplified.stmts[0].kind {
+        SStmtKind::Local { values, .. } => {
+            assert_eq!(text(&values[0]), "a + b * c");
+            match &values[0].kind {
+                SExprKind::Binary { right, .. } => assert_eq!(text(right), "b * c"),
+                other => panic!("{:?}", other),
+            }
+        }
+        other => panic!("{:?}", other),
+    }
+
+    match &simplified.stmts[1].kind {
+        SStmtKind::Call(call) => {
+            assert_eq!(text(call), "print(x:y(1))");
+            match &call.kind {
+                SExprKind::Call { arguments, .. } => assert_eq!(text(&arguments[0]), "x:y(1)"),
+                other => panic!("{:?}", other),
+            }
+        }
+        other => panic!("{:?}", other),
+    }
+
+    assert_eq!(
+        expression("-x").range.map(|(start, _)| start.bytes()),
+        Some(7)
+    );
+    assert_eq!(block("").range, None);
+}
+
+#[test]
+fn test_errors() {
+    let result = parse_fallible("local x = \nlocal = 1\nprint(1)", &ParseConfig::default());
+    let simplified = SBlock::from(&result.ast);
+
+    let kinds: Vec<_> = simplified.stmts.iter().map(|stmt| &stmt.kind).collect();
+    match kinds[0] {
+        SStmtKind::Local { values, .. } => assert_eq!(values[0].kind, SExprKind::Error),
+        other => panic!("{:?}", other),
+    }
+    assert!(kinds.contains(&&SStmtKind::Error));
+    assert!(matches!(kinds.last(), Some(SStmtKind::Call(_))));
+}
+
+#[test]
+#[cfg_attr(not(feature = "roblox"), ignore)]
+fn test_binary_literals() {
+    assert_eq!(expression("0b101").kind, SExprKind::Number(5.0));
+}
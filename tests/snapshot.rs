@@ -0,0 +1,157 @@
+use full_moon::{
+    ast::{reparse::SourceEdit, Ast, Stmt},
+    node::{Node, NodeId},
+    tokenizer::{Position, TokenReference, TokenType},
+    visitors::{Visit, Visitor, VisitorMut},
+    ParseConfig,
+};
+use std::collections::HashSet;
+
+// Appends a letter to every identifier, once for each token, which the tokens of nodes are visited twice for
+struct Rename(char, HashSet<NodeId>);
+
+fn rename(letter: char) -> Rename {
+    Rename(letter, HashSet::new())
+}
+
+impl<'a> VisitorMut<'a> for Rename {
+    fn visit_identifier(&mut self, token: &mut TokenReference<'a>) {
+        if !self.1.insert(token.id().unwrap()) {
+            return;
+        }
+
+        let name = format!("{}{}", token, self.0);
+        token.set_token_type(TokenType::Identifier {
+            identifier: name.into(),
+        });
+    }
+}
+
+// Replaces the first statement
+struct Replace<'a>(Option<Stmt<'a>>);
+
+impl<'a> VisitorMut<'a> for Replace<'a> {
+    fn visit_stmt(&mut self, stmt: &mut Stmt<'a>) {
+        if let Some(replacement) = self.0.take() {
+            *stmt = replacement;
+        }
+    }
+}
+
+// The names in the nodes of an Ast, which don't have to be from its arena
+fn names(ast: &Ast) -> Vec<String> {
+    struct Names(Vec<String>);
+
+    impl<'a> Visitor<'a> for Names {
+        fn visit_identifier(&mut self, token: &TokenReference<'a>) {
+            self.0.push(token.to_string());
+        }
+    }
+
+    let mut names = Names(Vec::new());
+    ast.nodes().visit(&mut names);
+    names.0
+}
+
+// What an Ast looks like, to check that restoring a snapshot gives it back exactly
+fn state(ast: &Ast) -> (String, Vec<(Position, Position)>) {
+    (
+        full_moon::print(ast),
+        ast.iter_tokens()
+            .map(|token| (token.start_position(), token.end_position()))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_restore() {
+    let code = "local x = 1\nprint(x) -- x";
+    let mut ast = full_moon::parse(code).unwrap();
+    let before = state(&ast);
+    let first = ast.snapshot();
+
+    rename('y').visit_ast(&mut ast);
+    ast.update_positions();
+    let renamed = state(&ast);
+    assert_eq!(renamed.0, "local xy = 1\nprinty(xy) -- x");
+    let second = ast.snapshot();
+
+    ast.reparse(SourceEdit::new(0..0, "local z = 2\n")).unwrap();
+    assert_eq!(
+        full_moon::print(&ast),
+        "local z = 2\nlocal xy = 1\nprinty(xy) -- x"
+    );
+
+    // Restoring an older snapshot, then a newer one
+    ast.restore(first.clone());
+    assert_eq!(state(&ast), before);
+    ast.restore(second);
+    assert_eq!(state(&ast), renamed);
+    ast.restore(first);
+    assert_eq!(state(&ast), before);
+    assert_eq!(ast.nodes(), full_moon::parse(code).unwrap().nodes());
+}
+
+#[test]
+fn test_new_tokens() {
+    let mut ast = full_moon::parse_with(
+        "local x = 1 -- one\ncall(x)",
+        &ParseConfig {
+            preserve_trivia: false,
+            ..ParseConfig::default()
+        },
+    )
+    .unwrap();
+
+    // A statement whose tokens aren't from the arena of the Ast
+    let other = full_moon::parse("local y = 2").unwrap();
+    let stmt = other.nodes().iter_stmts().next().unwrap().clone();
+    Replace(Some(stmt)).visit_ast(&mut ast);
+    let snapshot = ast.snapshot();
+    assert_eq!(names(&ast), ["y", "call", "x"]);
+
+    rename('w').visit_ast(&mut ast);
+    assert_eq!(names(&ast), ["yw", "callw", "xw"]);
+    assert_eq!(full_moon::print(&other), "local yw = 2");
+    ast.restore(snapshot);
+    assert_eq!(names(&ast), ["y", "call", "x"]);
+    assert_eq!(full_moon::print(&other), "local y = 2");
+}
+
+#[test]
+fn test_stress() {
+    let mut ast = full_moon::parse("local a = 1\nlocal b = a + 2\nprint(a, b) -- done\n").unwrap();
+    let mut snapshots = vec![(ast.snapshot(), state(&ast))];
+
+    // The same edits every time, chosen by a linear congruential generator
+    let mut seed = 12345u32;
+    let mut next = |limit: usize| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (seed >> 16) as usize % limit
+    };
+
+    for step in 0..200 {
+        match next(6) {
+            0 => rename(char::from(b'a' + next(26) as u8)).visit_ast(&mut ast),
+            1 => ast.update_positions(),
+            2 => {
+                let code = full_moon::print(&ast);
+                let line = format!("local n{} = {}\n", step, step);
+                let start = code.find('\n').map_or(0, |end| end + 1);
+                ast.reparse(SourceEdit::new(start..start, line)).unwrap();
+            }
+            3 => ast.compact(),
+            4 => snapshots.push((ast.snapshot(), state(&ast))),
+            _ => {
+                let (snapshot, expected) = &snapshots[next(snapshots.len())];
+                ast.restore(snapshot.clone());
+                assert_eq!(&state(&ast), expected, "step {}", step);
+            }
+        }
+    }
+
+    for (snapshot, expected) in snapshots.into_iter().rev() {
+        ast.restore(snapshot);
+        assert_eq!(state(&ast), expected);
+    }
+}
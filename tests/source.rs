@@ -0,0 +1,146 @@
+use full_moon::{
+    ast::{owned::Owned, LocalAssignment},
+    tokenizer::{TokenReference, TokenType},
+    visitors::VisitorMut,
+    ParseConfig,
+};
+
+mod common;
+use common::without_trivia;
+
+struct Rename;
+
+impl<'a> VisitorMut<'a> for Rename {
+    fn visit_identifier(&mut self, token: &mut TokenReference<'a>) {
+        if token.token_type().to_string() == "x" {
+            token.set_token_type(TokenType::Identifier {
+                identifier: "renamed".into(),
+            });
+        }
+    }
+}
+
+#[test]
+fn test_source_of() {
+    let code = "local x  =  { 1,--[[ one ]]2 }\n\n-- call\nprint( x )  ";
+    for config in [ParseConfig::default(), without_trivia()] {
+        let ast = full_moon::parse_with(code, &config).unwrap();
+        assert_eq!(ast.source(), Some(code));
+
+        let mut stmts = ast.nodes().iter_stmts();
+        assert_eq!(
+            ast.source_of(stmts.next().unwrap()),
+            Some("local x  =  { 1,--[[ one ]]2 }")
+        );
+        assert_eq!(ast.source_of(stmts.next().unwrap()), Some("print( x )"));
+        assert_eq!(ast.source_of(ast.nodes()), Some(code.trim_end()));
+
+        let local = ast.first_node_of_type::<LocalAssignment>().unwrap();
+        let table = local.expr_list().iter().next().unwrap();
+        assert_eq!(ast.source_of(table), Some("{ 1,--[[ one ]]2 }"));
+        assert_eq!(ast.source_of(local.local_token()), Some("local"));
+    }
+
+    // Nodes without tokens have no code
+    let ast = full_moon::parse("").unwrap();
+    assert_eq!(ast.source(), Some(""));
+    assert_eq!(ast.source_of(ast.nodes()), None);
+}
+
+#[test]
+fn test_changed_tokens() {
+    let code = "local x = 1\nlocal y = 2 -- two\nprint(x)";
+    for config in [ParseConfig::default(), without_trivia()] {
+        let mut ast = full_moon::parse_with(code, &config).unwrap();
+        Rename.visit_ast(&mut ast);
+        assert_eq!(ast.source(), Some(code));
+
+        let stmts: Vec<_> = ast.nodes().iter_stmts().collect();
+        assert_eq!(ast.source_of(stmts[0]), None);
+        assert_eq!(ast.source_of(stmts[1]), Some("local y = 2"));
+        assert_eq!(ast.source_of(stmts[2]), None);
+        assert_eq!(ast.source_of(ast.nodes()), None);
+
+        assert_eq!(ast.stale_source_of(stmts[0]), Some("local x = 1"));
+        assert_eq!(ast.stale_source_of(stmts[2]), Some("print(x)"));
+    }
+}
+
+#[test]
+fn test_changed_nodes() {
+    let ast = full_moon::parse("local a, b, c = 1, 2, 3").unwrap();
+    let local = ast.first_node_of_type::<LocalAssignment>().unwrap();
+    assert_eq!(
+        ast.source_of(&local.clone()),
+        Some("local a, b, c = 1, 2, 3")
+    );
+
+    // A name that was taken out, whose tokens are still in the Ast
+    let mut removed = local.clone();
+    let names = removed.name_list_mut();
+    let c = names.pop().unwrap();
+    names.pop();
+    names.push(c);
+    assert_eq!(ast.source_of(&removed), None);
+    assert_eq!(
+        ast.stale_source_of(&removed),
+        Some("local a, b, c = 1, 2, 3")
+    );
+
+    // Names that were moved around
+    let mut swapped = local.clone();
+    let names = swapped.name_list_mut();
+    let (c, b, a) = (
+        names.pop().unwrap(),
+        names.pop().unwrap(),
+        names.pop().unwrap(),
+    );
+    names.push(c);
+    names.push(b);
+    names.push(a);
+    assert_eq!(ast.source_of(&swapped), None);
+
+    // Nodes of other Asts
+    let other = full_moon::parse("local a, b, c = 1, 2, 3").unwrap();
+    assert_eq!(ast.source_of(other.nodes()), None);
+    assert_eq!(
+        ast.stale_source_of(other.nodes()),
+        Some("local a, b, c = 1, 2, 3")
+    );
+}
+
+#[test]
+fn test_retain_source() {
+    let code = "local x = 1";
+    let config = ParseConfig {
+        retain_source: false,
+        ..ParseConfig::default()
+    };
+    let ast = full_moon::parse_with(code, &config).unwrap();
+    assert_eq!(ast.source(), None);
+    assert_eq!(ast.source_of(ast.nodes()), None);
+    assert_eq!(ast.stale_source_of(ast.nodes()), None);
+
+    let mut parser = full_moon::Parser::with_config(config);
+    assert_eq!(parser.parse(code).unwrap().source(), None);
+    assert_eq!(
+        full_moon::Parser::new().parse(code).unwrap().source(),
+        Some(code)
+    );
+
+    let (ast, _) = full_moon::parse_all_errors("local x = )", 10);
+    assert_eq!(ast.unwrap().source(), Some("local x = )"));
+    let tokens = full_moon::tokenizer::tokens(code).unwrap();
+    assert_eq!(full_moon::parse_tokens(tokens).unwrap().source(), None);
+}
+
+#[test]
+fn test_owned() {
+    let ast = full_moon::parse("local x = 1").unwrap().owned();
+    assert_eq!(ast.source(), Some("local x = 1"));
+    assert!(ast.memory_usage().string_bytes() >= "local x = 1".len());
+
+    // The nodes of owned Asts don't borrow their tokens from it
+    assert_eq!(ast.source_of(ast.nodes()), None);
+    assert_eq!(ast.stale_source_of(ast.nodes()), Some("local x = 1"));
+}
@@ -0,0 +1,91 @@
+use full_moon::{
+    parse, print, print_with_source_map,
+    tokenizer::TokenKind,
+    transform::{normalize_spacing, reindent, IndentConfig},
+};
+use std::fs;
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_source_map_unchanged_pass_cases() {
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).expect("couldn't parse");
+        let (code, source_map) = print_with_source_map(&ast);
+        assert_eq!(code, print(&ast));
+
+        for mapping in source_map.mappings() {
+            let (output_start, output_end) = mapping.output_range();
+            let (original_start, original_end) = mapping
+                .original_range()
+                .expect("token wasn't in the original code");
+            assert_eq!(output_start.bytes(), original_start.bytes(), "{:?}", path);
+            assert_eq!(output_end.bytes(), original_end.bytes(), "{:?}", path);
+        }
+    }
+}
+
+#[test]
+fn test_source_map_after_transforms() {
+    let source = "do\nlocal x=1\nif x then\nprint(x ,2)  \nend\nend";
+    let mut ast = parse(source).unwrap();
+    normalize_spacing(&mut ast);
+    reindent(ast.nodes_mut(), &IndentConfig::default(), 0);
+
+    let (code, source_map) = print_with_source_map(&ast);
+    assert_eq!(
+        code,
+        "do\n    local x = 1\n    if x then\n        print(x, 2)\n    end\nend"
+    );
+
+    let mut synthetic = 0;
+    for mapping in source_map.mappings() {
+        let (output_start, output_end) = mapping.output_range();
+        match mapping.original_range() {
+            Some((original_start, original_end)) => {
+                if mapping.token_kind() != TokenKind::Whitespace {
+                    assert_eq!(
+                        &code[output_start.bytes()..output_end.bytes()],
+                        &source[original_start.bytes()..original_end.bytes()]
+                    );
+                }
+            }
+
+            None => {
+                assert_eq!(mapping.token_kind(), TokenKind::Whitespace);
+                synthetic += 1;
+            }
+        }
+    }
+
+    // The spaces added around `=` and after the comma
+    assert_eq!(synthetic, 3);
+
+    let print_mapping = source_map.find(4, 11).unwrap();
+    assert_eq!(print_mapping.output_range().0.line(), 4);
+    assert_eq!(print_mapping.output_range().0.character(), 9);
+    assert_eq!(
+        print_mapping.original_range().unwrap().0.bytes(),
+        source.find("print").unwrap()
+    );
+}
+
+#[test]
+#[cfg(feature = "source-map")]
+fn test_source_map_json() {
+    let source = "a = 1\n\n  b = {\n\t2 }";
+    let (_, source_map) = print_with_source_map(&parse(source).unwrap());
+    let json: serde_json::Value =
+        serde_json::from_str(&source_map.to_json(source, "out.lua", "in.lua")).unwrap();
+
+    assert_eq!(json["version"], 3);
+    assert_eq!(json["file"], "out.lua");
+    assert_eq!(json["sources"], serde_json::json!(["in.lua"]));
+    assert_eq!(json["mappings"], "AAAA,EAAE,EAAE;;EAEF,EAAE,EAAE;CACL,EAAE");
+}
@@ -0,0 +1,107 @@
+use full_moon::{
+    parse,
+    tokenizer::TokenKind,
+    visitors::{NodeKind, Statistics, Visitor},
+};
+use std::fs;
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_statistics_of_pass_cases() {
+    let mut statistics = Statistics::default();
+    let mut max_block_depth = 0;
+
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua");
+        let ast = parse(&source).expect("couldn't parse");
+
+        let mut case_statistics = Statistics::default();
+        case_statistics.visit_ast(&ast);
+        max_block_depth = max_block_depth.max(case_statistics.max_block_depth());
+
+        statistics.visit_ast(&ast);
+    }
+
+    // Golden values guarding against traversal regressions, update them when the corpus changes
+    let nodes = [
+        (NodeKind::Block, 92),
+        (NodeKind::Stmt, 98),
+        (NodeKind::LastStmt, 6),
+        (NodeKind::Expression, 144),
+        (NodeKind::Value, 137),
+        (NodeKind::FunctionCall, 43),
+        (NodeKind::FunctionBody, 11),
+        (NodeKind::TableConstructor, 10),
+        (NodeKind::Field, 19),
+        (NodeKind::BinOpRhs, 12),
+        (NodeKind::BinOp, 12),
+        (NodeKind::UnOp, 6),
+        (NodeKind::ContainedSpan, 78),
+    ];
+
+    let tokens = [
+        (TokenKind::Eof, 63),
+        (TokenKind::Identifier, 163),
+        (TokenKind::MultiLineComment, 7),
+        (TokenKind::Number, 57),
+        (TokenKind::SingleLineComment, 6),
+        (TokenKind::StringLiteral, 21),
+        (TokenKind::Symbol, 411),
+        (TokenKind::Whitespace, 394),
+    ];
+
+    for (kind, count) in &nodes {
+        assert_eq!(
+            statistics.node_count(*kind),
+            *count,
+            "{:?} count changed",
+            kind
+        );
+    }
+
+    for (kind, count) in &tokens {
+        assert_eq!(
+            statistics.token_count(*kind),
+            *count,
+            "{:?} count changed",
+            kind
+        );
+    }
+
+    assert_eq!(max_block_depth, 2);
+    assert_eq!(
+        statistics.function_statements(),
+        &[1, 1, 0, 0, 1, 0, 1, 0, 0, 0, 0]
+    );
+}
+
+#[test]
+fn test_function_statements() {
+    let mut statistics = Statistics::default();
+    statistics.visit_ast(
+        &parse(
+            r#"
+            local function outer()
+                local x = 1
+                local inner = function()
+                    return x
+                end
+
+                while true do
+                    break
+                end
+            end
+            "#,
+        )
+        .unwrap(),
+    );
+
+    assert_eq!(statistics.function_statements(), &[4, 1]);
+    assert_eq!(statistics.max_block_depth(), 3);
+}
@@ -0,0 +1,105 @@
+use full_moon::{
+    ast, parse,
+    visitors::{DocumentedVisitor, StmtComments},
+};
+
+#[derive(Default)]
+struct CommentCollector {
+    stmts: Vec<(String, Vec<String>, Vec<String>)>,
+}
+
+impl<'ast> DocumentedVisitor<'ast> for CommentCollector {
+    fn visit_stmt_with_comments(
+        &mut self,
+        stmt: &ast::Stmt<'ast>,
+        comments: &StmtComments<'ast, '_>,
+    ) {
+        let name = match stmt {
+            ast::Stmt::LocalAssignment(local) => {
+                local.name_list().iter().next().unwrap().to_string()
+            }
+            ast::Stmt::LocalFunction(function) => function.name().to_string(),
+            ast::Stmt::FunctionCall(_) => "call".to_owned(),
+            ast::Stmt::If(_) => "if".to_owned(),
+            _ => "other".to_owned(),
+        };
+
+        let to_strings = |comments: &[&full_moon::tokenizer::Token]| {
+            comments.iter().map(|comment| comment.to_string()).collect()
+        };
+
+        self.stmts.push((
+            name,
+            to_strings(comments.leading()),
+            to_strings(comments.trailing()),
+        ));
+    }
+}
+
+fn collect(code: &str) -> Vec<(String, Vec<String>, Vec<String>)> {
+    let mut visitor = CommentCollector::default();
+    visitor.visit_ast(&parse(code).unwrap());
+    visitor.stmts
+}
+
+fn stmt(name: &str, leading: &[&str], trailing: &[&str]) -> (String, Vec<String>, Vec<String>) {
+    (
+        name.to_owned(),
+        leading.iter().map(|comment| comment.to_string()).collect(),
+        trailing.iter().map(|comment| comment.to_string()).collect(),
+    )
+}
+
+#[test]
+fn test_comments_between_statements() {
+    assert_eq!(
+        collect(
+            r#"-- about a
+-- more about a
+local a = 1 -- trailing a
+-- about b
+local b = 2; -- trailing b
+-- separated by a blank line
+
+local c = 3 local d = 4 -- trailing d"#
+        ),
+        vec![
+            stmt("a", &["-- about a", "-- more about a"], &["-- trailing a"]),
+            stmt("b", &["-- about b"], &["-- trailing b"]),
+            stmt("c", &[], &[]),
+            stmt("d", &[], &["-- trailing d"]),
+        ]
+    );
+}
+
+#[test]
+fn test_comments_at_end_of_block() {
+    assert_eq!(
+        collect(
+            r#"if x then -- trailing if
+    -- about call
+    call()
+    -- before end
+end -- after end
+--[[ end of file ]]"#
+        ),
+        vec![
+            stmt("if", &[], &["-- after end"]),
+            stmt("call", &["-- about call"], &[]),
+        ]
+    );
+}
+
+#[test]
+fn test_comments_need_same_indentation() {
+    assert_eq!(
+        collect(
+            r#"local function f()
+-- not indented
+    --[[ indented ]]
+    local a = 1
+end"#
+        ),
+        vec![stmt("f", &[], &[]), stmt("a", &["--[[ indented ]]"], &[])]
+    );
+}
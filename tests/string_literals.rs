@@ -0,0 +1,112 @@
+use full_moon::{
+    analysis::{string_literals, StringContext, TableKey},
+    parse,
+};
+use pretty_assertions::assert_eq;
+
+fn contexts(code: &str) -> Vec<(String, StringContext)> {
+    string_literals(&parse(code).unwrap())
+        .iter()
+        .map(|string| (string.value().unwrap().to_owned(), string.context().clone()))
+        .collect()
+}
+
+fn call(function: &str, index: usize) -> StringContext {
+    StringContext::CallArgument {
+        function: function.to_owned(),
+        index,
+    }
+}
+
+fn assignment(target: &str) -> StringContext {
+    StringContext::Assignment {
+        target: target.to_owned(),
+    }
+}
+
+fn concatenation(context: StringContext) -> StringContext {
+    StringContext::Concatenation(Box::new(context))
+}
+
+#[test]
+fn test_calls() {
+    assert_eq!(
+        contexts(
+            "translate(\"a\", 'b')\nlog \"c\"\nui:label [[d]]\nM.l10n.get(x, (\"e\"))\nf()(\"f\")\nlocal x = g(\"h\").field"
+        ),
+        vec![
+            ("a".to_owned(), call("translate", 0)),
+            ("b".to_owned(), call("translate", 1)),
+            ("c".to_owned(), call("log", 0)),
+            ("d".to_owned(), call("ui:label", 0)),
+            ("e".to_owned(), call("M.l10n.get", 1)),
+            ("f".to_owned(), call("f()", 0)),
+            ("h".to_owned(), call("g", 0)),
+        ]
+    );
+}
+
+#[test]
+fn test_tables_and_assignments() {
+    assert_eq!(
+        contexts(
+            "local a, b = \"a\", {\n    \"one\",\n    title = \"two\",\n    [ \"key\" ] = \"three\",\n    f(),\n    \"four\",\n}\nt.x = \"five\""
+        ),
+        vec![
+            ("a".to_owned(), assignment("a")),
+            ("one".to_owned(), StringContext::TableValue { key: TableKey::Index(1) }),
+            ("two".to_owned(), StringContext::TableValue { key: TableKey::Name("title".to_owned()) }),
+            ("key".to_owned(), StringContext::Other),
+            (
+                "three".to_owned(),
+                StringContext::TableValue { key: TableKey::Expression("\"key\"".to_owned()) }
+            ),
+            ("four".to_owned(), StringContext::TableValue { key: TableKey::Index(3) }),
+            ("five".to_owned(), assignment("t.x")),
+        ]
+    );
+}
+
+#[test]
+fn test_operators() {
+    assert_eq!(
+        contexts(
+            "print(\"a\" .. x .. (\"b\" .. y), \"c\" .. n + 1, x == \"d\", \"e\" .. x == y, #\"f\", x or \"g\")"
+        ),
+        vec![
+            ("a".to_owned(), concatenation(call("print", 0))),
+            ("b".to_owned(), concatenation(call("print", 0))),
+            ("c".to_owned(), concatenation(call("print", 1))),
+            ("d".to_owned(), StringContext::Other),
+            ("e".to_owned(), concatenation(StringContext::Other)),
+            ("f".to_owned(), StringContext::Other),
+            ("g".to_owned(), StringContext::Other),
+        ]
+    );
+}
+
+#[test]
+fn test_values() {
+    let code =
+        "local x = \"tab\\t\\65\"\nlocal y = [==[\nlong ]] string]==]\nif x == 'a\\98' then end";
+    let ast = parse(code).unwrap();
+    let strings = string_literals(&ast);
+
+    let values: Vec<_> = strings
+        .iter()
+        .map(|string| (string.value().unwrap(), string.raw()))
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            ("tab\tA", "\"tab\\t\\65\"".to_owned()),
+            ("long ]] string", "[==[\nlong ]] string]==]".to_owned()),
+            ("ab", "'a\\98'".to_owned()),
+        ]
+    );
+
+    for string in &strings {
+        let (start, end) = string.range();
+        assert_eq!(&code[start.bytes()..end.bytes()], string.raw());
+    }
+}
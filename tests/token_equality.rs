@@ -0,0 +1,88 @@
+use full_moon::{
+    ast::owned::Owned,
+    tokenizer::{TokenReference, TokenType},
+    visitors::{Visit, Visitor},
+};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet, HashSet},
+    hash::{Hash, Hasher},
+};
+
+#[derive(Default)]
+struct Tokens<'a>(Vec<TokenReference<'a>>);
+
+impl<'a> Visitor<'a> for Tokens<'a> {
+    fn visit_token(&mut self, token: &TokenReference<'a>) {
+        self.0.push(token.clone());
+    }
+}
+
+fn tokens<'a>(ast: &full_moon::ast::Ast<'a>) -> Vec<TokenReference<'a>> {
+    // Only the tokens of the nodes, which are in the order they're in the code
+    let mut tokens = Tokens::default();
+    ast.nodes().visit(&mut tokens);
+    tokens.0
+}
+
+fn hash(token: &TokenReference) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    token.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+#[allow(clippy::mutable_key_type)]
+fn test_two_parses() {
+    let code = "local x = 1 -- one\nprint(x, 'x')";
+    let first = full_moon::parse(code).unwrap();
+    let second = full_moon::parse(code).unwrap();
+    let owned = first.clone().owned();
+
+    let (first, second, owned) = (tokens(&first), tokens(&second), tokens(&owned));
+    assert_eq!(first, second);
+    assert_eq!(first, owned);
+    for ((first, second), owned) in first.iter().zip(&second).zip(&owned) {
+        assert_eq!(hash(first), hash(second));
+        assert_eq!(hash(first), hash(owned));
+        assert_eq!(first.cmp(owned), std::cmp::Ordering::Equal);
+    }
+
+    let reported: HashSet<_> = first.iter().collect();
+    assert!(second.iter().all(|token| reported.contains(token)));
+    assert_eq!(reported.len(), first.len());
+}
+
+#[test]
+fn test_different_tokens() {
+    let code = "x = x";
+    let ast = full_moon::parse(code).unwrap();
+    let tokens = tokens(&ast);
+    let (first, second) = (&tokens[0], &tokens[2]);
+
+    // The same text at different positions
+    assert_eq!(first.to_string(), second.to_string());
+    assert_ne!(first, second);
+    assert!(first < second);
+
+    // The same position with different text
+    let mut renamed = first.clone().owned();
+    renamed.set_token_type(TokenType::Identifier {
+        identifier: "y".into(),
+    });
+    assert_ne!(first, &renamed);
+    assert!(first < &renamed);
+}
+
+#[test]
+#[allow(clippy::mutable_key_type)]
+fn test_ordered() {
+    let code = "local a, b = b, a";
+    let ast = full_moon::parse(code).unwrap();
+    let tokens = tokens(&ast);
+
+    let mut shuffled = tokens.clone();
+    shuffled.reverse();
+    shuffled.extend(tokens.iter().cloned());
+    let sorted: BTreeSet<_> = shuffled.into_iter().collect();
+    assert_eq!(sorted.into_iter().collect::<Vec<_>>(), tokens);
+}
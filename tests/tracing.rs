@@ -0,0 +1,126 @@
+#![cfg(feature = "tracing")]
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Metadata, Subscriber,
+};
+
+// Keeps what the parser did as lines of text, with spans as `>` when they're entered and `<` when they're exited
+#[derive(Clone, Default)]
+struct Recorder {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<HashMap<u64, String>>>,
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+// The fields of a span or event, as `name=value`
+#[derive(Default)]
+struct Fields(Vec<String>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.push(format!("{}={:?}", field.name(), value));
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target().starts_with("full_moon")
+    }
+
+    fn new_span(&self, span: &Attributes) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        self.spans.lock().unwrap().insert(
+            id,
+            format!("{} {}", span.metadata().name(), fields.0.join(" ")),
+        );
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _: &Id, _: &Record) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.lines.lock().unwrap().push(fields.0.join(" "));
+    }
+
+    fn enter(&self, span: &Id) {
+        let name = self.spans.lock().unwrap()[&span.into_u64()].clone();
+        self.lines.lock().unwrap().push(format!("> {}", name));
+    }
+
+    fn exit(&self, span: &Id) {
+        let name = self.spans.lock().unwrap()[&span.into_u64()].clone();
+        self.lines.lock().unwrap().push(format!("< {}", name));
+    }
+}
+
+fn trace(code: &str) -> Vec<String> {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let _ = full_moon::parse(code);
+    });
+
+    let lines = recorder.lines.lock().unwrap().clone();
+    lines
+}
+
+#[test]
+fn test_parse_path() {
+    let lines = trace("local x = f(1)");
+    let position = |line: &str| {
+        lines
+            .iter()
+            .position(|found| found == line)
+            .unwrap_or_else(|| panic!("`{}` isn't in {:#?}", line, lines))
+    };
+
+    // Tokens are counted with the whitespace between them
+    let block = position("> ParseBlock index=0 bytes=0");
+    let local = position("> ParseLocalAssignment index=0 bytes=0");
+    let expression = position("> ParseExpression index=6 bytes=10");
+    let value = position("> ParseValue index=6 bytes=10");
+    assert!(block < local && local < expression && expression < value);
+
+    // Statements that start with `local` are tried as local functions first, until `function` isn't found
+    let local_function = position("> ParseLocalFunction index=0 bytes=0");
+    assert_eq!(local_function + 9, local);
+    assert_eq!(lines[local - 2], "message=no match, backtracking");
+    assert_eq!(lines[local - 1], "< ParseLocalFunction index=0 bytes=0");
+
+    assert!(lines[local..].contains(&String::from("message=matched end=9")));
+    assert_eq!(lines.last().unwrap(), "< ParseBlock index=0 bytes=0");
+}
+
+#[test]
+fn test_errors() {
+    let lines = trace("local x = (1");
+    let error = lines
+        .iter()
+        .position(|line| line.starts_with("message=unexpected token index=8 bytes=12 expected="))
+        .unwrap_or_else(|| panic!("no error in {:#?}", lines));
+    assert!(lines[error].contains("RightParen"));
+
+    // The error is passed up by every production it's in, out to the block, before the code is parsed again to find more
+    assert_eq!(lines[error + 1], "message=error");
+    assert_eq!(lines[error + 2], "< ParseExpression index=6 bytes=10");
+    let block = error
+        + lines[error..]
+            .iter()
+            .position(|line| line == "< ParseBlock index=0 bytes=0")
+            .unwrap();
+    assert_eq!(lines[block - 1], "message=error");
+}
@@ -0,0 +1,134 @@
+use full_moon::{
+    ast::{owned::Owned, Expression, Stmt},
+    node::{Node, TriviaBehavior},
+    parse,
+    transform::{transfer_trivia, TriviaMerge},
+    visitors::VisitorMut,
+};
+
+struct ReplaceStmts<'ast> {
+    replacement: Stmt<'ast>,
+    merge: TriviaMerge,
+}
+
+impl<'ast> VisitorMut<'ast> for ReplaceStmts<'ast> {
+    fn visit_stmt(&mut self, stmt: &mut Stmt<'ast>) {
+        let mut new_stmt = self.replacement.clone();
+        transfer_trivia(&*stmt, &mut new_stmt, self.merge);
+        *stmt = new_stmt;
+    }
+}
+
+fn replaced(code: &str, replacement: &str, merge: TriviaMerge) -> Vec<String> {
+    let mut ast = parse(code).unwrap();
+    let replacement_ast = parse(replacement).unwrap();
+
+    ReplaceStmts {
+        replacement: replacement_ast.nodes().iter_stmts().next().unwrap().clone(),
+        merge,
+    }
+    .visit_ast(&mut ast);
+
+    ast.nodes()
+        .iter_stmts()
+        .map(|stmt| stmt.print(TriviaBehavior::Keep))
+        .collect()
+}
+
+#[test]
+fn test_transfer_replace() {
+    assert_eq!(
+        replaced(
+            "-- License\n\nlocal x = 1 -- one\ncall() --[[ two ]]",
+            "\n  -- replacement\nfoo() -- foo\n",
+            TriviaMerge::Replace,
+        ),
+        vec!["-- License\n\nfoo() -- one\n", "foo() --[[ two ]]"],
+    );
+}
+
+#[test]
+fn test_transfer_append() {
+    assert_eq!(
+        replaced(
+            "-- License\nlocal x = 1 -- one\ncall()",
+            "-- replacement\nfoo() --[[ foo ]]\n",
+            TriviaMerge::Append,
+        ),
+        vec![
+            "-- License\n-- replacement\nfoo() --[[ foo ]] -- one\n",
+            "-- replacement\nfoo() --[[ foo ]]\n",
+        ],
+    );
+}
+
+#[test]
+fn test_transfer_without_trivia() {
+    // Replacing drops the trivia of the new node even if the old node had none
+    assert_eq!(
+        replaced("x = 1", "-- foo\nfoo() -- foo", TriviaMerge::Replace),
+        vec!["foo()"],
+    );
+
+    assert_eq!(
+        replaced("x = 1", "-- foo\nfoo() -- foo", TriviaMerge::Append),
+        vec!["-- foo\nfoo() -- foo"],
+    );
+}
+
+#[test]
+fn test_transfer_keeps_inner_trivia() {
+    let ast = parse("-- License\nreturn 1").unwrap();
+    let old = ast.nodes().last_stmts().unwrap();
+
+    let replacement = parse("call(a, -- a\n  b)").unwrap();
+    let mut new = replacement.nodes().iter_stmts().next().unwrap().clone();
+    transfer_trivia(old, &mut new, TriviaMerge::Replace);
+
+    assert_eq!(
+        new.print(TriviaBehavior::Keep),
+        "-- License\ncall(a, -- a\n  b)"
+    );
+
+    // The code the new node was parsed from doesn't change
+    let original = replacement.nodes().iter_stmts().next().unwrap();
+    assert_eq!(original.print(TriviaBehavior::Keep), "call(a, -- a\n  b)");
+    assert!(new.similar(original));
+}
+
+fn first_expression<'a, 'b>(ast: &'b full_moon::ast::Ast<'a>) -> &'b Expression<'a> {
+    match ast.nodes().iter_stmts().next().unwrap() {
+        Stmt::LocalAssignment(local) => local.expr_list().iter().next().unwrap(),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_transfer_expressions() {
+    let ast = parse("local x =\n  --[[ old ]] a + b").unwrap();
+    let replacement = parse("local y =\n--[[ new ]] c").unwrap();
+
+    let mut new = first_expression(&replacement).clone();
+    transfer_trivia(first_expression(&ast), &mut new, TriviaMerge::Append);
+    assert_eq!(
+        new.print(TriviaBehavior::Keep),
+        "  --[[ old ]] --[[ new ]] c"
+    );
+}
+
+#[test]
+fn test_transfer_owned() {
+    let ast = parse("-- License\nlocal x = 1").unwrap();
+    let old = ast.nodes().iter_stmts().next().unwrap();
+
+    // Nodes that weren't parsed from code have no trivia to change
+    let mut new = parse("foo()")
+        .unwrap()
+        .nodes()
+        .iter_stmts()
+        .next()
+        .unwrap()
+        .owned();
+    transfer_trivia(old, &mut new, TriviaMerge::Replace);
+    assert_eq!(new.print(TriviaBehavior::Keep), "");
+}
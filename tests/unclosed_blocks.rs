@@ -0,0 +1,97 @@
+use full_moon::{ast::AstError, parse, tokenizer::Symbol, Error};
+
+fn error(code: &str) -> AstError<'_> {
+    match parse(code) {
+        Err(Error::AstError(error)) => error,
+        other => panic!("{:?} didn't fail to parse: {:?}", code, other),
+    }
+}
+
+// The symbol of the innermost unclosed block and the byte it starts at
+fn unclosed(code: &str) -> Option<(Symbol, usize)> {
+    let error = error(code);
+    assert_eq!(
+        error.range().map(|(start, _)| start.bytes()),
+        Some(code.len()),
+        "{:?} doesn't fail at the end",
+        code
+    );
+
+    error
+        .unclosed()
+        .map(|(symbol, position)| (symbol, position.bytes()))
+}
+
+#[test]
+fn test_unclosed_functions() {
+    assert_eq!(
+        unclosed("function f()\n  call()"),
+        Some((Symbol::Function, 0))
+    );
+    assert_eq!(
+        unclosed("local function f()\n"),
+        Some((Symbol::Function, 6))
+    );
+    assert_eq!(unclosed("x = function()"), Some((Symbol::Function, 4)));
+    assert_eq!(unclosed("x = function(a, b"), Some((Symbol::LeftParen, 12)));
+    assert_eq!(
+        unclosed("function f()\n  local x ="),
+        Some((Symbol::Function, 0))
+    );
+}
+
+#[test]
+fn test_unclosed_ifs() {
+    assert_eq!(unclosed("if x then\n"), Some((Symbol::If, 0)));
+    assert_eq!(unclosed("if x"), Some((Symbol::If, 0)));
+    assert_eq!(unclosed("if x then else"), Some((Symbol::If, 0)));
+    assert_eq!(unclosed("if x then elseif y"), Some((Symbol::If, 0)));
+}
+
+#[test]
+fn test_unclosed_loops() {
+    assert_eq!(unclosed("do call()"), Some((Symbol::Do, 0)));
+    assert_eq!(unclosed("while x"), Some((Symbol::While, 0)));
+    assert_eq!(unclosed("while x do"), Some((Symbol::While, 0)));
+    assert_eq!(unclosed("for i = 1, 2 do"), Some((Symbol::For, 0)));
+    assert_eq!(unclosed("for i = 1,"), Some((Symbol::For, 0)));
+    assert_eq!(unclosed("for k, v in pairs(t) do"), Some((Symbol::For, 0)));
+    assert_eq!(unclosed("repeat call()"), Some((Symbol::Repeat, 0)));
+}
+
+#[test]
+fn test_unclosed_innermost() {
+    assert_eq!(
+        unclosed("function f()\n  if x then\n    while y do"),
+        Some((Symbol::While, 29))
+    );
+
+    // The `end` closes the `if`, leaving the function without one
+    assert_eq!(
+        unclosed("function f()\n  if x then\n    call()\nend"),
+        Some((Symbol::Function, 0))
+    );
+
+    // The condition after `until` isn't in the `repeat` anymore
+    assert_eq!(unclosed("do repeat until"), Some((Symbol::Do, 0)));
+    assert_eq!(unclosed("local x ="), None);
+}
+
+#[test]
+fn test_unclosed_only_at_end() {
+    let inside = error("function f()\n  local x = ]\nend");
+    assert!(inside.opening_range().is_none());
+    assert!(inside.unclosed().is_none());
+
+    // Errors for tokens that close blocks have the block they're for, but it is only unclosed at the end
+    let closing = error("do call() ]");
+    assert!(closing.opening_range().is_some());
+    assert!(closing.unclosed().is_none());
+}
+
+#[test]
+fn test_unclosed_display() {
+    assert!(error("if x")
+        .to_string()
+        .contains("\nto close `if` on line 1, character 1\n"));
+}
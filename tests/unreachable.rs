@@ -0,0 +1,175 @@
+use full_moon::{analysis::unreachable, parse};
+use pretty_assertions::assert_eq;
+
+// The code of every run of statements that can't run
+fn unreachable_code(code: &str) -> Vec<&str> {
+    unreachable(&parse(code).unwrap())
+        .iter()
+        .map(|(start, end)| code[start.bytes()..end.bytes()].trim())
+        .collect()
+}
+
+#[test]
+fn test_branches() {
+    let code = "if x then
+    return 1
+elseif y then
+    error('y')
+else
+    return 3
+end
+print(x)
+print(y)";
+    assert_eq!(unreachable_code(code), vec!["print(x)\nprint(y)"]);
+
+    // Without an `else`, the code after runs when no condition is true
+    let empty: Vec<&str> = Vec::new();
+    assert_eq!(unreachable_code("if x then return 1 end print(x)"), empty);
+    assert_eq!(
+        unreachable_code("if x then return 1 elseif y then end print(x)"),
+        empty
+    );
+
+    // A condition that is always true makes the branches after it never taken
+    assert_eq!(
+        unreachable_code("if true then return elseif x then end print(x)"),
+        vec!["print(x)"]
+    );
+    assert_eq!(
+        unreachable_code("if false then return else end print(x)"),
+        empty
+    );
+}
+
+#[test]
+fn test_loops() {
+    let empty: Vec<&str> = Vec::new();
+    assert_eq!(
+        unreachable_code("while true do end print(x)"),
+        vec!["print(x)"]
+    );
+    assert_eq!(unreachable_code("while x do end print(x)"), empty);
+    assert_eq!(
+        unreachable_code("repeat until false print(x)"),
+        vec!["print(x)"]
+    );
+    assert_eq!(unreachable_code("repeat until x print(x)"), empty);
+    assert_eq!(
+        unreachable_code("for i = 1, 10 do return end print(x)"),
+        empty
+    );
+
+    // The `until` of a body that never finishes is never checked
+    assert_eq!(
+        unreachable_code("repeat return until x print(x)"),
+        vec!["print(x)"]
+    );
+
+    // A break stops the loop it is in
+    assert_eq!(
+        unreachable_code("while true do if x then break end end print(x)"),
+        empty
+    );
+    assert_eq!(
+        unreachable_code("repeat do break end until false print(x)"),
+        empty
+    );
+}
+
+#[test]
+fn test_nested_loops() {
+    // The breaks only stop the loops inside, so the outer loop never stops
+    let code = "while true do
+    for i = 1, 10 do
+        break
+    end
+    while x do
+        if y then break end
+    end
+end
+print(x)";
+    assert_eq!(unreachable_code(code), vec!["print(x)"]);
+
+    // The break that would stop the middle loop is after a loop that never stops,
+    // so the break of the outer loop can't run either
+    let code = "while true do
+    while true do
+        while true do end
+        break
+    end
+    if x then break end
+end
+print(x)";
+    assert_eq!(
+        unreachable_code(code),
+        vec!["break", "if x then break end", "print(x)"]
+    );
+
+    // Both branches leave the loop inside, so the rest of it can't run, but only one stops the outer loop too
+    let code = "repeat
+    while true do
+        if x then
+            return
+        else
+            break
+        end
+        print(x)
+    end
+until false
+print(y)";
+    assert_eq!(unreachable_code(code), vec!["print(x)", "print(y)"]);
+}
+
+#[test]
+fn test_error() {
+    let empty: Vec<&str> = Vec::new();
+    assert_eq!(
+        unreachable_code("error('x') local y = 1 print(y) return y"),
+        vec!["local y = 1 print(y) return y"]
+    );
+    assert_eq!(unreachable_code("error 'x' print(y)"), vec!["print(y)"]);
+
+    // Only a call to the global `error` never returns
+    assert_eq!(
+        unreachable_code("local error = print error('x') print(y)"),
+        empty
+    );
+    assert_eq!(unreachable_code("M.error('x') print(y)"), empty);
+    assert_eq!(unreachable_code("error('x'):y() print(y)"), empty);
+}
+
+#[test]
+fn test_functions() {
+    let code = "local function f()
+    do
+        return
+    end
+    print(1)
+end
+local g = function()
+    while true do
+        break
+    end
+    print(2)
+end
+return function()
+    error('x')
+    return
+end";
+    assert_eq!(unreachable_code(code), vec!["print(1)", "return"]);
+
+    // A return in a function doesn't stop the code the function is in
+    let empty: Vec<&str> = Vec::new();
+    assert_eq!(unreachable_code("f(function() return end) print(x)"), empty);
+
+    // The functions in code that can't run aren't reported again
+    let code = "do return end
+local f = function()
+    error('x')
+    print(1)
+end";
+    assert_eq!(
+        unreachable_code(code),
+        vec!["local f = function()\n    error('x')\n    print(1)\nend"]
+    );
+}
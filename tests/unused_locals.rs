@@ -0,0 +1,100 @@
+use full_moon::{
+    analysis::{
+        scopes::VariableKind, unused_locals, unused_locals_with, UnusedKind, UnusedLocalsConfig,
+    },
+    parse,
+};
+use pretty_assertions::assert_eq;
+
+fn unused(code: &str) -> Vec<(String, VariableKind, UnusedKind)> {
+    let ast = parse(code).unwrap();
+    unused_locals(&ast)
+        .iter()
+        .map(|local| (local.token().to_string(), local.kind(), local.unused()))
+        .collect()
+}
+
+#[test]
+fn test_multiple_assignment() {
+    assert_eq!(
+        unused("local a, b, c = f()\nprint(b)\nc = 1"),
+        vec![
+            ("a".to_owned(), VariableKind::Local, UnusedKind::NeverUsed),
+            (
+                "c".to_owned(),
+                VariableKind::Local,
+                UnusedKind::AssignedButNeverRead
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_parameters_and_loops() {
+    assert_eq!(
+        unused("function f(a, b, ...) return b end\nfor i = 1, 10 do end\nfor k, v in pairs(t) do print(v) end\nfunction t:m() end"),
+        vec![
+            ("a".to_owned(), VariableKind::Parameter, UnusedKind::NeverUsed),
+            ("i".to_owned(), VariableKind::NumericFor, UnusedKind::NeverUsed),
+            ("k".to_owned(), VariableKind::GenericFor, UnusedKind::NeverUsed),
+        ]
+    );
+}
+
+#[test]
+fn test_used_in_closures() {
+    assert_eq!(
+        unused("local count = 0\nreturn function() return function() count = count + 1 end end"),
+        Vec::new()
+    );
+
+    // Setting a local from a closure doesn't read it
+    assert_eq!(
+        unused("local last\nreturn function(x) last = x end"),
+        vec![(
+            "last".to_owned(),
+            VariableKind::Local,
+            UnusedKind::AssignedButNeverRead
+        )]
+    );
+}
+
+#[test]
+fn test_recursive_local_functions() {
+    assert_eq!(
+        unused("local function loop() return function() loop() end end\nlocal function used(n) if n > 0 then used(n - 1) end end\nused(1)"),
+        vec![(
+            "loop".to_owned(),
+            VariableKind::LocalFunction,
+            UnusedKind::NeverUsed
+        )]
+    );
+}
+
+#[test]
+fn test_ignore_pattern() {
+    let code = "local _unused, unused_ok, other = 1, 2, 3";
+    assert_eq!(
+        unused(code)
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect::<Vec<_>>(),
+        vec!["unused_ok", "other"]
+    );
+
+    let config = UnusedLocalsConfig {
+        ignore_prefix: "unused",
+    };
+    let ast = parse(code).unwrap();
+    assert_eq!(
+        unused_locals_with(&ast, &config)
+            .iter()
+            .map(|local| local.token().to_string())
+            .collect::<Vec<_>>(),
+        vec!["_unused", "other"]
+    );
+
+    // Nothing is ignored without a prefix
+    let config = UnusedLocalsConfig { ignore_prefix: "" };
+    assert_eq!(unused_locals_with(&ast, &config).len(), 3);
+}
@@ -0,0 +1,74 @@
+// Only one test, since the allocator counts the allocations of every thread
+use full_moon::{
+    ast::Ast,
+    tokenizer::TokenReference,
+    visitors::{VisitMut, VisitorMut},
+};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocated_by(function: impl FnOnce()) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    function();
+    ALLOCATED.load(Ordering::Relaxed) - before
+}
+
+struct Nothing;
+
+impl<'ast> VisitorMut<'ast> for Nothing {}
+
+// Visits every token without changing any of them
+#[derive(Default)]
+struct CountTokens(usize);
+
+impl<'ast> VisitorMut<'ast> for CountTokens {
+    fn visit_token(&mut self, _token: &mut TokenReference<'ast>) {
+        self.0 += 1;
+    }
+}
+
+#[test]
+fn test_visitor_mut_doesnt_clone_nodes() {
+    let code = format!("do\n{}\nend\n", include_str!("../benches/t.lua")).repeat(4);
+    let mut ast: Option<Ast> = None;
+    let parsing = allocated_by(|| ast = Some(full_moon::parse(&code).unwrap()));
+    let mut ast = ast.unwrap();
+
+    let visiting = allocated_by(|| Nothing.visit_ast(&mut ast));
+    assert!(
+        visiting < parsing / 100,
+        "visiting allocated {} bytes, and parsing {}",
+        visiting,
+        parsing
+    );
+
+    let mut counter = CountTokens::default();
+    let counting = allocated_by(|| ast.nodes_mut().visit_mut(&mut counter));
+    assert!(counter.0 > 5_000);
+    assert!(
+        counting < parsing / 100,
+        "visiting allocated {} bytes, and parsing {}",
+        counting,
+        parsing
+    );
+}
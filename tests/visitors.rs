@@ -1,6 +1,9 @@
 use full_moon::{
     ast, parse, print, tokenizer,
-    visitors::{Visitor, VisitorMut},
+    visitors::{
+        AnyNode, NodeKind, TraversalOrder, TryVisitor, Visitor, VisitorBuilder, VisitorMut,
+        VisitorMutBuilder,
+    },
 };
 use std::borrow::Cow;
 
@@ -157,3 +160,259 @@ fn test_end_visit() {
     assert_eq!(visitor.called_at, 2);
     assert_eq!(visitor.if_end_at, 3);
 }
+
+#[test]
+fn test_visitor_builder() {
+    let mut called = Vec::new();
+    let mut locals = 0;
+
+    VisitorBuilder::new()
+        .on_function_call(|call| match call.prefix() {
+            ast::Prefix::Name(token) => called.push(token.to_string()),
+            _ => unreachable!(),
+        })
+        .on_local_assignment(|_| locals += 1)
+        .build()
+        .visit_ast(&parse("local x = foo(bar())").unwrap());
+
+    assert_eq!(called, vec!["foo", "bar"]);
+    assert_eq!(locals, 1);
+}
+
+#[test]
+fn test_visitor_mut_builder() {
+    let mut code = parse("local dogs, snakes = 1").unwrap();
+
+    VisitorMutBuilder::new()
+        .on_local_assignment(|assignment| {
+            for name in assignment.name_list_mut().iter_mut() {
+                let identifier = name.to_string().replace("s", "sss");
+                name.set_token_type(tokenizer::TokenType::Identifier {
+                    identifier: Cow::from(identifier),
+                });
+            }
+        })
+        .build()
+        .visit_ast(&mut code);
+
+    assert_eq!(print(&code), "local dogsss, sssnakesss = 1");
+}
+
+#[test]
+fn test_nodes_of_type() {
+    let code = parse("local x = foo(bar(1), 2) baz()").unwrap();
+
+    let called: Vec<_> = code
+        .nodes_of_type::<ast::FunctionCall>()
+        .map(|call| match call.prefix() {
+            ast::Prefix::Name(token) => token.to_string(),
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(called, vec!["foo", "bar", "baz"]);
+
+    let identifiers: Vec<_> = code
+        .nodes_of_type::<tokenizer::TokenReference>()
+        .filter(|token| token.token_kind() == tokenizer::TokenKind::Identifier)
+        .map(|token| token.to_string())
+        .collect();
+    assert_eq!(identifiers, vec!["x", "foo", "bar", "baz"]);
+
+    let first = code.first_node_of_type::<ast::LocalAssignment>().unwrap();
+    assert_eq!(first.name_list().iter().next().unwrap().to_string(), "x");
+    assert!(code.first_node_of_type::<ast::While>().is_none());
+}
+
+#[test]
+fn test_visit_node() {
+    #[derive(Default)]
+    struct KindVisitor {
+        kinds: Vec<NodeKind>,
+        log: Vec<String>,
+    }
+
+    impl Visitor<'_> for KindVisitor {
+        fn visit_node(&mut self, node: AnyNode) {
+            if node.kind() != NodeKind::TokenReference {
+                self.kinds.push(node.kind());
+            }
+
+            if node.kind() == NodeKind::LocalAssignment {
+                let (start, end) = node.range().unwrap();
+                assert_eq!((start.bytes(), end.bytes()), (0, 11));
+                self.log.push("node".to_owned());
+            }
+        }
+
+        fn visit_node_end(&mut self, node: AnyNode) {
+            if node.kind() == NodeKind::LocalAssignment {
+                self.log.push("node end".to_owned());
+            }
+        }
+
+        fn visit_local_assignment(&mut self, _: &ast::LocalAssignment) {
+            self.log.push("local assignment".to_owned());
+        }
+
+        fn visit_local_assignment_end(&mut self, _: &ast::LocalAssignment) {
+            self.log.push("local assignment end".to_owned());
+        }
+    }
+
+    let mut visitor = KindVisitor::default();
+    visitor.visit_ast(&parse("local x = 1").unwrap());
+
+    assert_eq!(
+        visitor.kinds,
+        vec![
+            NodeKind::Block,
+            NodeKind::Stmt,
+            NodeKind::LocalAssignment,
+            NodeKind::Expression,
+            NodeKind::Value,
+        ]
+    );
+
+    assert_eq!(
+        visitor.log,
+        vec![
+            "node",
+            "local assignment",
+            "local assignment end",
+            "node end"
+        ]
+    );
+}
+
+#[derive(Default)]
+struct OrderLogger {
+    log: Vec<String>,
+}
+
+impl Visitor<'_> for OrderLogger {
+    fn visit_function_call(&mut self, call: &ast::FunctionCall) {
+        self.log.push(format!("call {}", prefix_name(call)));
+    }
+
+    fn visit_function_call_end(&mut self, call: &ast::FunctionCall) {
+        self.log.push(format!("call end {}", prefix_name(call)));
+    }
+}
+
+fn prefix_name(call: &ast::FunctionCall) -> String {
+    match call.prefix() {
+        ast::Prefix::Name(token) => token.to_string(),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_traversal_order() {
+    let code = parse("foo(bar())").unwrap();
+
+    let mut visitor = OrderLogger::default();
+    code.visit_with_order(&mut visitor, TraversalOrder::PreOrder);
+    assert_eq!(
+        visitor.log,
+        vec!["call foo", "call bar", "call end bar", "call end foo"]
+    );
+
+    let mut visitor = OrderLogger::default();
+    code.visit_with_order(&mut visitor, TraversalOrder::PostOrder);
+    assert_eq!(
+        visitor.log,
+        vec!["call bar", "call end bar", "call foo", "call end foo"]
+    );
+}
+
+#[test]
+fn test_try_visitor() {
+    #[derive(Default)]
+    struct CallLimiter {
+        calls: Vec<String>,
+    }
+
+    impl TryVisitor<'_> for CallLimiter {
+        type Error = String;
+
+        fn visit_function_call(&mut self, call: &ast::FunctionCall) -> Result<(), String> {
+            let name = prefix_name(call);
+
+            if name == "error" {
+                Err(format!("called {}", name))
+            } else {
+                self.calls.push(name);
+                Ok(())
+            }
+        }
+    }
+
+    let mut visitor = CallLimiter::default();
+    assert_eq!(visitor.visit_ast(&parse("a() b(c())").unwrap()), Ok(()));
+    assert_eq!(visitor.calls, vec!["a", "b", "c"]);
+
+    let mut visitor = CallLimiter::default();
+    assert_eq!(
+        visitor.visit_ast(&parse("a() b(error()) c()").unwrap()),
+        Err("called error".to_owned())
+    );
+    assert_eq!(visitor.calls, vec!["a", "b"]);
+}
+
+#[test]
+fn test_visit_trivia_in_order() {
+    #[derive(Default)]
+    struct CommentVisitor {
+        comments: Vec<String>,
+        tokens: Vec<String>,
+    }
+
+    impl Visitor<'_> for CommentVisitor {
+        fn visit_single_line_comment(&mut self, token: &tokenizer::TokenReference<'_>) {
+            self.comments.push(token.to_string());
+        }
+
+        fn visit_multi_line_comment(&mut self, token: &tokenizer::TokenReference<'_>) {
+            self.comments.push(token.to_string());
+        }
+
+        fn visit_token(&mut self, token: &tokenizer::TokenReference<'_>) {
+            self.tokens.push(token.to_string());
+        }
+    }
+
+    let source = r#"-- before the first statement
+local x = 1 -- trailing the line
+local t = { -- after a brace
+    --[[ before a field ]] a = 1, -- after a comma
+    b = --[[ before a value ]] 2,
+    -- before the closing brace
+}
+
+function f(--[[ in the parameters ]])
+    -- in the body
+end
+-- at the end of the file"#;
+
+    let mut visitor = CommentVisitor::default();
+    visitor.visit_ast(&parse(source).unwrap());
+
+    assert_eq!(
+        visitor.comments,
+        vec![
+            "-- before the first statement",
+            "-- trailing the line",
+            "-- after a brace",
+            "--[[ before a field ]]",
+            "-- after a comma",
+            "--[[ before a value ]]",
+            "-- before the closing brace",
+            "--[[ in the parameters ]]",
+            "-- in the body",
+            "-- at the end of the file",
+        ]
+    );
+
+    // The first pass visits every token in source order, so printing it gives back the code
+    assert!(visitor.tokens.concat().starts_with(source));
+}
@@ -0,0 +1,65 @@
+use full_moon::{ast::Stmt, node::Node, parse, print};
+use std::fs;
+
+fn pass_case_sources() -> Vec<String> {
+    let mut paths: Vec<_> = fs::read_dir("./tests/cases/pass")
+        .expect("couldn't read directory")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| fs::read_to_string(path.join("source.lua")).expect("couldn't read source.lua"))
+        .collect()
+}
+
+fn assert_write_to_matches_print(source: &str) {
+    let ast = parse(source).expect("couldn't parse");
+    let mut written = Vec::new();
+    ast.write_to(&mut written).expect("couldn't write");
+    assert_eq!(written, print(&ast).as_bytes());
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_write_to_pass_cases() {
+    for source in pass_case_sources() {
+        assert_write_to_matches_print(&source);
+
+        let ast = parse(&source).unwrap();
+        let printed = print(&ast);
+        for stmt in ast.nodes_of_type::<Stmt>() {
+            let (start, end) = stmt.range().unwrap();
+            let mut written = Vec::new();
+            stmt.write_to(&ast, &mut written).expect("couldn't write");
+            assert_eq!(written, &printed.as_bytes()[start.bytes()..end.bytes()]);
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "no-source-tests", ignore)]
+fn test_write_to_large_file() {
+    let sources = pass_case_sources();
+    let mut large = String::new();
+    for _ in 0..5 {
+        for source in &sources {
+            large.push_str("do\n");
+            large.push_str(source);
+            large.push_str("\nend\n");
+        }
+    }
+
+    assert_write_to_matches_print(&large);
+}
+
+#[test]
+fn test_write_to_tokens() {
+    let ast = parse("local x = [==[ long ]==] --[[ comment ]]").unwrap();
+    let stmt = ast.nodes().iter_stmts().next().unwrap();
+
+    let mut written = Vec::new();
+    stmt.write_to(&ast, &mut written).unwrap();
+    assert_eq!(written, b"local x = [==[ long ]==]");
+}